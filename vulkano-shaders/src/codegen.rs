@@ -152,7 +152,7 @@ pub fn compile(
     ty: ShaderKind,
     include_directories: &[impl AsRef<Path>],
     macro_defines: &[(impl AsRef<str>, impl AsRef<str>)],
-    vulkan_version: Option<EnvVersion>,
+    vulkan_version: Option<u32>,
     spirv_version: Option<SpirvVersion>,
 ) -> Result<(CompilationArtifact, Vec<String>), String> {
     let includes_tracker = RefCell::new(Vec::new());
@@ -161,7 +161,7 @@ pub fn compile(
 
     compile_options.set_target_env(
         TargetEnv::Vulkan,
-        vulkan_version.unwrap_or(EnvVersion::Vulkan1_0) as u32,
+        vulkan_version.unwrap_or(EnvVersion::Vulkan1_0 as u32),
     );
 
     if let Some(spirv_version) = spirv_version {
@@ -244,6 +244,13 @@ where
                 }
             });
         }
+        (1, 6) => {
+            cap_checks.push(quote! {
+                if device.api_version() < Version::V1_3 {
+                    panic!("Device API version 1.3 required");
+                }
+            });
+        }
         _ => return Err(Error::UnsupportedSpirvVersion),
     }
 
@@ -306,14 +313,24 @@ where
     }
 
     // writing one method for each entry point of this module
+    let entry_point_names: Vec<&str> = doc
+        .instructions
+        .iter()
+        .filter_map(|instruction| match instruction {
+            Instruction::EntryPoint { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
     let mut entry_points_inside_impl: Vec<TokenStream> = vec![];
     for instruction in doc.instructions.iter() {
-        if let &Instruction::EntryPoint { .. } = instruction {
+        if let &Instruction::EntryPoint { ref name, .. } = instruction {
+            let name_with_stage = entry_point_names.iter().filter(|&&n| n == name).count() > 1;
             let entry_point = entry_point::write_entry_point(
                 &doc,
                 instruction,
                 &types_meta,
                 exact_entrypoint_interface,
+                name_with_stage,
             );
             entry_points_inside_impl.push(entry_point);
         }
@@ -11,7 +11,7 @@ use crate::descriptor_sets::{write_descriptor_set_layout_descs, write_push_const
 use crate::parse::{Instruction, Spirv};
 use crate::{spirv_search, TypesMeta};
 use proc_macro2::{Span, TokenStream};
-use spirv_headers::{Decoration, ExecutionMode, ExecutionModel, StorageClass};
+use spirv_headers::{BuiltIn, Decoration, ExecutionMode, ExecutionModel, StorageClass};
 use syn::Ident;
 
 pub(super) fn write_entry_point(
@@ -19,6 +19,7 @@ pub(super) fn write_entry_point(
     instruction: &Instruction,
     types_meta: &TypesMeta,
     exact_entrypoint_interface: bool,
+    name_with_stage: bool,
 ) -> TokenStream {
     let (execution, id, ep_name, interface) = match instruction {
         &Instruction::EntryPoint {
@@ -93,6 +94,11 @@ pub(super) fn write_entry_point(
 
     let (ty, f_call) = {
         if let ExecutionModel::GLCompute = *execution {
+            let with_local_size = match local_size(doc, id) {
+                Some([x, y, z]) => quote! { .with_local_size([#x, #y, #z]) },
+                None => quote! {},
+            };
+
             (
                 quote! { ::vulkano::pipeline::shader::ComputeEntryPoint },
                 quote! { compute_entry_point(
@@ -100,7 +106,7 @@ pub(super) fn write_entry_point(
                     #descriptor_set_layout_descs,
                     #push_constant_ranges,
                     <#spec_consts_struct>::descriptors(),
-                )},
+                )#with_local_size},
             )
         } else {
             let entry_ty = match *execution {
@@ -187,7 +193,12 @@ pub(super) fn write_entry_point(
         }
     };
 
+    // SPIR-V allows entry points of different stages to have the same name.
     let mut method_name = ep_name.clone();
+    if name_with_stage {
+        method_name.push('_');
+        method_name.push_str(stage_name(execution));
+    }
     method_name.push_str("_entry_point");
     let method_ident = Ident::new(&method_name, Span::call_site());
 
@@ -210,6 +221,54 @@ pub(super) fn write_entry_point(
     entry_point
 }
 
+// Returns the `LocalSize` execution mode of an entry point, unless it is overridden by a
+// `WorkgroupSize` built-in, whose value can depend on specialization constants.
+fn local_size(doc: &Spirv, id: u32) -> Option<[u32; 3]> {
+    let has_workgroup_size = doc
+        .instructions
+        .iter()
+        .any(|instruction| match instruction {
+            &Instruction::Decorate {
+                decoration: Decoration::BuiltIn,
+                ref params,
+                ..
+            } => params.first() == Some(&(BuiltIn::WorkgroupSize as u32)),
+            _ => false,
+        });
+
+    if has_workgroup_size {
+        return None;
+    }
+
+    doc.instructions
+        .iter()
+        .find_map(|instruction| match instruction {
+            &Instruction::ExecutionMode {
+                target_id,
+                mode: ExecutionMode::LocalSize,
+                ref optional_literals,
+            } if target_id == id && optional_literals.len() == 3 => Some([
+                optional_literals[0],
+                optional_literals[1],
+                optional_literals[2],
+            ]),
+            _ => None,
+        })
+}
+
+// Same names as the values of the `ty` option of the macro.
+fn stage_name(execution: &ExecutionModel) -> &'static str {
+    match *execution {
+        ExecutionModel::Vertex => "vertex",
+        ExecutionModel::TessellationControl => "tess_ctrl",
+        ExecutionModel::TessellationEvaluation => "tess_eval",
+        ExecutionModel::Geometry => "geometry",
+        ExecutionModel::Fragment => "fragment",
+        ExecutionModel::GLCompute => "compute",
+        _ => unreachable!(),
+    }
+}
+
 struct Element {
     location: u32,
     name: String,
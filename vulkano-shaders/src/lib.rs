@@ -49,7 +49,10 @@
 //! struct.
 //! * Methods for each entry point of the shader module. These construct and
 //! return the various entry point structs that can be found in the
-//! [vulkano::pipeline::shader][pipeline::shader] module.
+//! [vulkano::pipeline::shader][pipeline::shader] module. The method of an entry
+//! point named `name` is `name_entry_point`. If several entry points of different
+//! stages have the same name, the stage is added to the name of their methods,
+//! such as `main_vertex_entry_point` and `main_fragment_entry_point`.
 //! * A Rust struct translated from each struct contained in the shader data.
 //! By default each structure has a `Clone` and a `Copy` implemenetations. This
 //! behavior could be customized through the `types_meta` macro option(see below
@@ -105,6 +108,8 @@
 //! ## `ty: "..."`
 //!
 //! This defines what shader type the given GLSL source will be compiled into.
+//! It is optional with `bytes`, as the SPIR-V code already contains the stage of
+//! each of its entry points.
 //! The type can be any of the following:
 //!
 //! * `vertex`
@@ -154,6 +159,7 @@
 //! [`set_target_env`](shaderc::CompileOptions::set_target_env) and
 //! [`set_target_spirv`](shaderc::CompileOptions::set_target_spirv) compile options.
 //! If neither option is specified, then SPIR-V 1.0 code targeting Vulkan 1.0 will be generated.
+//! The supported versions go up to Vulkan 1.2 and SPIR-V 1.6.
 //!
 //! The generated code must be supported by the device at runtime. If not, then an error will be
 //! returned when calling `Shader::load`.
@@ -295,11 +301,11 @@ struct MacroInput {
     exact_entrypoint_interface: bool,
     include_directories: Vec<String>,
     macro_defines: Vec<(String, String)>,
-    shader_kind: ShaderKind,
+    shader_kind: Option<ShaderKind>,
     source_kind: SourceKind,
     spirv_version: Option<SpirvVersion>,
     types_meta: TypesMeta,
-    vulkan_version: Option<EnvVersion>,
+    vulkan_version: Option<u32>,
 }
 
 impl Parse for MacroInput {
@@ -390,6 +396,7 @@ impl Parse for MacroInput {
                         "1.3" => SpirvVersion::V1_3,
                         "1.4" => SpirvVersion::V1_4,
                         "1.5" => SpirvVersion::V1_5,
+                        "1.6" => SpirvVersion::V1_6,
                         _ => panic!("Unknown SPIR-V version: {}", version.value()),
                     });
                 }
@@ -559,9 +566,12 @@ impl Parse for MacroInput {
                 "vulkan_version" => {
                     let version: LitStr = input.parse()?;
                     vulkan_version = Some(match version.value().as_ref() {
-                        "1.0" => EnvVersion::Vulkan1_0,
-                        "1.1" => EnvVersion::Vulkan1_1,
-                        "1.2" => EnvVersion::Vulkan1_2,
+                        "1.0" => EnvVersion::Vulkan1_0 as u32,
+                        "1.1" => EnvVersion::Vulkan1_1 as u32,
+                        "1.2" => EnvVersion::Vulkan1_2 as u32,
+                        // shaderc has no Vulkan 1.3 target environment yet, and silently compiles
+                        // for Vulkan 1.0 when given an unknown version.
+                        "1.3" => panic!("Vulkan 1.3 is not supported as a target environment yet"),
                         _ => panic!("Unknown Vulkan version: {}", version.value()),
                     });
                 }
@@ -573,16 +583,16 @@ impl Parse for MacroInput {
             }
         }
 
-        let shader_kind = match shader_kind {
-            Some(shader_kind) => shader_kind,
-            None => panic!("Please provide a shader type e.g. `ty: \"vertex\"`"),
-        };
-
         let source_kind = match source_kind {
             Some(source_kind) => source_kind,
             None => panic!("Please provide a source e.g. `path: \"foo.glsl\"` or `src: \"glsl source code here ...\"`")
         };
 
+        // Precompiled SPIR-V carries the stages of its entry points.
+        if shader_kind.is_none() && !matches!(source_kind, SourceKind::Bytes(_)) {
+            panic!("Please provide a shader type e.g. `ty: \"vertex\"`");
+        }
+
         let dump = dump.unwrap_or(false);
 
         Ok(Self {
@@ -669,7 +679,7 @@ pub fn shader(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
             path,
             &root_path,
             &source_code,
-            input.shader_kind,
+            input.shader_kind.unwrap(),
             &include_paths,
             &input.macro_defines,
             input.vulkan_version,
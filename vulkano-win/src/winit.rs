@@ -1,9 +1,5 @@
-use std::borrow::Borrow;
 use std::error;
 use std::fmt;
-#[cfg(target_os = "windows")]
-use std::ptr;
-use std::rc::Rc;
 use std::sync::Arc;
 
 use vulkano::instance::Instance;
@@ -15,17 +11,7 @@ use winit::event_loop::EventLoopWindowTarget;
 use winit::window::Window;
 use winit::window::WindowBuilder;
 
-#[cfg(target_os = "macos")]
-use cocoa::appkit::{NSView, NSWindow};
-#[cfg(target_os = "macos")]
-use cocoa::base::id as cocoa_id;
-#[cfg(target_os = "macos")]
-use metal::CoreAnimationLayer;
-#[cfg(target_os = "macos")]
-use objc::runtime::YES;
-
-#[cfg(target_os = "macos")]
-use std::mem;
+pub use vulkano::SafeBorrow;
 
 pub fn required_extensions() -> InstanceExtensions {
     let ideal = InstanceExtensions {
@@ -57,7 +43,7 @@ pub fn create_vk_surface<W>(
 where
     W: SafeBorrow<Window>,
 {
-    unsafe { winit_to_surface(instance, window) }
+    Surface::from_winit(instance, window)
 }
 
 pub trait VkSurfaceBuild<E> {
@@ -125,91 +111,3 @@ impl From<WindowCreationError> for CreationError {
         CreationError::WindowCreationError(err)
     }
 }
-
-#[cfg(target_os = "android")]
-unsafe fn winit_to_surface<W: SafeBorrow<Window>>(
-    instance: Arc<Instance>,
-    win: W,
-) -> Result<Arc<Surface<W>>, SurfaceCreationError> {
-    use winit::platform::android::WindowExtAndroid;
-
-    Surface::from_anativewindow(instance, win.borrow().native_window(), win)
-}
-
-#[cfg(all(unix, not(target_os = "android"), not(target_os = "macos")))]
-unsafe fn winit_to_surface<W: SafeBorrow<Window>>(
-    instance: Arc<Instance>,
-    win: W,
-) -> Result<Arc<Surface<W>>, SurfaceCreationError> {
-    use winit::platform::unix::WindowExtUnix;
-
-    match (
-        win.borrow().wayland_display(),
-        win.borrow().wayland_surface(),
-    ) {
-        (Some(display), Some(surface)) => Surface::from_wayland(instance, display, surface, win),
-        _ => {
-            // No wayland display found, check if we can use xlib.
-            // If not, we use xcb.
-            if instance.enabled_extensions().khr_xlib_surface {
-                Surface::from_xlib(
-                    instance,
-                    win.borrow().xlib_display().unwrap(),
-                    win.borrow().xlib_window().unwrap() as _,
-                    win,
-                )
-            } else {
-                Surface::from_xcb(
-                    instance,
-                    win.borrow().xcb_connection().unwrap(),
-                    win.borrow().xlib_window().unwrap() as _,
-                    win,
-                )
-            }
-        }
-    }
-}
-
-#[cfg(target_os = "windows")]
-unsafe fn winit_to_surface<W: SafeBorrow<Window>>(
-    instance: Arc<Instance>,
-    win: W,
-) -> Result<Arc<Surface<W>>, SurfaceCreationError> {
-    use winit::platform::windows::WindowExtWindows;
-
-    Surface::from_hwnd(instance, win.borrow().hinstance(), win.borrow().hwnd(), win)
-}
-
-#[cfg(target_os = "macos")]
-unsafe fn winit_to_surface<W: SafeBorrow<Window>>(
-    instance: Arc<Instance>,
-    win: W,
-) -> Result<Arc<Surface<W>>, SurfaceCreationError> {
-    use winit::platform::macos::WindowExtMacOS;
-
-    let wnd: cocoa_id = mem::transmute(win.borrow().ns_window());
-    let layer = CoreAnimationLayer::new();
-
-    layer.set_edge_antialiasing_mask(0);
-    layer.set_presents_with_transaction(false);
-    layer.remove_all_animations();
-
-    let view = wnd.contentView();
-
-    layer.set_contents_scale(view.backingScaleFactor());
-    view.setLayer(mem::transmute(layer.as_ref())); // Bombs here with out of memory
-    view.setWantsLayer(YES);
-
-    Surface::from_macos_moltenvk(instance, win.borrow().ns_view() as *const (), win)
-}
-
-/// An alternative to `Borrow<T>` with the requirement that all calls to
-/// `borrow` return the same object.
-pub unsafe trait SafeBorrow<T>: Borrow<T> {}
-
-unsafe impl<T> SafeBorrow<T> for T {}
-unsafe impl<'a, T> SafeBorrow<T> for &'a T {}
-unsafe impl<'a, T> SafeBorrow<T> for &'a mut T {}
-unsafe impl<T> SafeBorrow<T> for Rc<T> {}
-unsafe impl<T> SafeBorrow<T> for Arc<T> {}
-unsafe impl<T> SafeBorrow<T> for Box<T> {}
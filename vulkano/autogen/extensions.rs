@@ -151,6 +151,13 @@ fn write_device_extensions(members: &[ExtensionsMember]) -> TokenStream {
         }
     });
 
+    let disable_by_name_items = members.iter().map(|ExtensionsMember { name, .. }| {
+        let name_string = name.to_string();
+        quote! {
+            #name_string => std::mem::replace(&mut self.#name, false),
+        }
+    });
+
     let required_if_supported_extensions_items = members.iter().map(
         |ExtensionsMember {
              name,
@@ -178,6 +185,14 @@ fn write_device_extensions(members: &[ExtensionsMember]) -> TokenStream {
                 Ok(())
             }
 
+            /// Disables the extension with the given name, and returns true if it was enabled.
+            pub(super) fn disable_by_name(&mut self, name: &str) -> bool {
+                match name {
+                    #(#disable_by_name_items)*
+                    _ => false,
+                }
+            }
+
             pub(crate) fn required_if_supported_extensions() -> Self {
                 Self {
                     #(#required_if_supported_extensions_items)*
@@ -310,6 +325,7 @@ fn write_extensions_common(struct_name: Ident, members: &[ExtensionsMember]) ->
     quote! {
         /// List of extensions that are enabled or available.
         #[derive(Copy, Clone, PartialEq, Eq)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub struct #struct_name {
             #(#struct_items)*
 
@@ -317,6 +333,7 @@ fn write_extensions_common(struct_name: Ident, members: &[ExtensionsMember]) ->
             /// can only be created through Vulkano functions and the update
             /// syntax. This way, extensions can be added to Vulkano without
             /// breaking existing code.
+            #[cfg_attr(feature = "serde", serde(skip, default = "crate::extensions::Unbuildable::new"))]
             pub _unbuildable: crate::extensions::Unbuildable,
         }
 
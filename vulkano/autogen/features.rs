@@ -158,6 +158,13 @@ fn write_features(members: &[FeaturesMember]) -> TokenStream {
         }
     });
 
+    let disable_by_name_items = members.iter().map(|FeaturesMember { name, .. }| {
+        let name_string = name.to_string();
+        quote! {
+            #name_string => std::mem::replace(&mut self.#name, false),
+        }
+    });
+
     let all_items = members.iter().map(|FeaturesMember { name, .. }| {
         quote! {
             #name: true,
@@ -170,6 +177,12 @@ fn write_features(members: &[FeaturesMember]) -> TokenStream {
         }
     });
 
+    let union_items = members.iter().map(|FeaturesMember { name, .. }| {
+        quote! {
+            #name: self.#name || other.#name,
+        }
+    });
+
     let intersection_items = members.iter().map(|FeaturesMember { name, .. }| {
         quote! {
             #name: self.#name && other.#name,
@@ -250,6 +263,7 @@ fn write_features(members: &[FeaturesMember]) -> TokenStream {
         /// ```
         ///
         #[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub struct Features {
             #(#struct_items)*
         }
@@ -267,6 +281,14 @@ fn write_features(members: &[FeaturesMember]) -> TokenStream {
                 Ok(())
             }
 
+            /// Disables the feature with the given name, and returns true if it was enabled.
+            pub(super) fn disable_by_name(&mut self, name: &str) -> bool {
+                match name {
+                    #(#disable_by_name_items)*
+                    _ => false,
+                }
+            }
+
             /// Builds a `Features` object with all values to false.
             pub const fn none() -> Features {
                 Features {
@@ -292,6 +314,15 @@ fn write_features(members: &[FeaturesMember]) -> TokenStream {
                 #(#is_superset_of_items)&&*
             }
 
+            /// Builds a `Features` that is the union of `self` and another `Features` object.
+            ///
+            /// The result's field will be true if it is true in either `self` or `other`.
+            pub const fn union(&self, other: &Features) -> Features {
+                Features {
+                    #(#union_items)*
+                }
+            }
+
             /// Builds a `Features` that is the intersection of `self` and another `Features`
             /// object.
             ///
@@ -15,7 +15,14 @@ use vk_parse::{Extension, ExtensionChild, InterfaceItem};
 
 pub fn write(extensions: &IndexMap<&str, &Extension>) -> TokenStream {
     let entry_fns = write_fns(&[], "Entry");
-    let instance_fns = write_fns(&make_extension_fns("instance", &extensions), "Instance");
+    // Device extensions can add physical device commands, which must be loaded from the instance.
+    let instance_fns = write_fns(
+        &make_extension_fns("instance", extensions)
+            .into_iter()
+            .chain(make_extension_fns("device", extensions))
+            .collect::<Vec<_>>(),
+        "Instance",
+    );
     let device_fns = write_fns(&make_extension_fns("device", &extensions), "Device");
 
     quote! {
@@ -104,6 +104,7 @@ fn write_properties(members: &[PropertiesMember]) -> TokenStream {
         /// available extensions, not every property may be available. For that reason, some
         /// properties are wrapped in an `Option`.
         #[derive(Clone, Debug, Default)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub struct Properties {
             #(#struct_items)*
         }
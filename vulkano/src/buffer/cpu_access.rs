@@ -23,6 +23,7 @@ use crate::buffer::traits::BufferInner;
 use crate::buffer::traits::TypedBufferAccess;
 use crate::buffer::BufferUsage;
 use crate::device::physical::QueueFamily;
+use crate::device::DebugName;
 use crate::device::Device;
 use crate::device::DeviceOwned;
 use crate::device::Queue;
@@ -40,6 +41,7 @@ use crate::memory::DeviceMemoryAllocError;
 use crate::sync::AccessError;
 use crate::sync::Sharing;
 use crate::DeviceSize;
+use crate::OomError;
 use parking_lot::RwLock;
 use parking_lot::RwLockReadGuard;
 use parking_lot::RwLockWriteGuard;
@@ -99,6 +101,7 @@ enum CurrentGpuAccess {
 
 impl<T> CpuAccessibleBuffer<T> {
     /// Builds a new buffer with some data in it. Only allowed for sized data.
+    #[track_caller]
     pub fn from_data(
         device: Arc<Device>,
         usage: BufferUsage,
@@ -132,6 +135,7 @@ impl<T> CpuAccessibleBuffer<T> {
 
     /// Builds a new uninitialized buffer. Only allowed for sized data.
     #[inline]
+    #[track_caller]
     pub unsafe fn uninitialized(
         device: Arc<Device>,
         usage: BufferUsage,
@@ -150,6 +154,7 @@ impl<T> CpuAccessibleBuffer<T> {
 impl<T> CpuAccessibleBuffer<[T]> {
     /// Builds a new buffer that contains an array `T`. The initial data comes from an iterator
     /// that produces that list of Ts.
+    #[track_caller]
     pub fn from_iter<I>(
         device: Arc<Device>,
         usage: BufferUsage,
@@ -186,6 +191,7 @@ impl<T> CpuAccessibleBuffer<[T]> {
 
     /// Builds a new buffer. Can be used for arrays.
     #[inline]
+    #[track_caller]
     pub unsafe fn uninitialized_array(
         device: Arc<Device>,
         len: DeviceSize,
@@ -209,6 +215,7 @@ impl<T: ?Sized> CpuAccessibleBuffer<T> {
     ///
     /// You must ensure that the size that you pass is correct for `T`.
     ///
+    #[track_caller]
     pub unsafe fn raw<'a, I>(
         device: Arc<Device>,
         size: DeviceSize,
@@ -495,6 +502,13 @@ unsafe impl<T: ?Sized, A> DeviceOwned for CpuAccessibleBuffer<T, A> {
     }
 }
 
+impl<T: ?Sized, A> DebugName for CpuAccessibleBuffer<T, A> {
+    #[inline]
+    fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        self.inner.set_debug_name(name)
+    }
+}
+
 impl<T: ?Sized, A> PartialEq for CpuAccessibleBuffer<T, A>
 where
     T: 'static + Send + Sync,
@@ -20,6 +20,7 @@ use crate::buffer::traits::BufferInner;
 use crate::buffer::traits::TypedBufferAccess;
 use crate::buffer::BufferUsage;
 use crate::device::physical::QueueFamily;
+use crate::device::DebugName;
 use crate::device::Device;
 use crate::device::DeviceOwned;
 use crate::device::Queue;
@@ -35,6 +36,7 @@ use crate::memory::{DeviceMemoryAllocError, ExternalMemoryHandleType};
 use crate::sync::AccessError;
 use crate::sync::Sharing;
 use crate::DeviceSize;
+use crate::OomError;
 use smallvec::SmallVec;
 use std::fs::File;
 use std::hash::Hash;
@@ -81,6 +83,7 @@ impl<T> DeviceLocalBuffer<T> {
     /// Builds a new buffer. Only allowed for sized data.
     // TODO: unsafe because uninitialized data
     #[inline]
+    #[track_caller]
     pub fn new<'a, I>(
         device: Arc<Device>,
         usage: BufferUsage,
@@ -104,6 +107,7 @@ impl<T> DeviceLocalBuffer<[T]> {
     /// Builds a new buffer. Can be used for arrays.
     // TODO: unsafe because uninitialized data
     #[inline]
+    #[track_caller]
     pub fn array<'a, I>(
         device: Arc<Device>,
         len: DeviceSize,
@@ -131,6 +135,7 @@ impl<T: ?Sized> DeviceLocalBuffer<T> {
     ///
     /// You must ensure that the size that you pass is correct for `T`.
     ///
+    #[track_caller]
     pub unsafe fn raw<'a, I>(
         device: Arc<Device>,
         size: DeviceSize,
@@ -175,6 +180,7 @@ impl<T: ?Sized> DeviceLocalBuffer<T> {
 
     /// Same as `raw` but with exportable fd option for the allocated memory on Linux
     #[cfg(target_os = "linux")]
+    #[track_caller]
     pub unsafe fn raw_with_exportable_fd<'a, I>(
         device: Arc<Device>,
         size: DeviceSize,
@@ -220,6 +226,7 @@ impl<T: ?Sized> DeviceLocalBuffer<T> {
         }))
     }
 
+    #[track_caller]
     unsafe fn build_buffer(
         device: &Arc<Device>,
         size: DeviceSize,
@@ -278,6 +285,13 @@ unsafe impl<T: ?Sized, A> DeviceOwned for DeviceLocalBuffer<T, A> {
     }
 }
 
+impl<T: ?Sized, A> DebugName for DeviceLocalBuffer<T, A> {
+    #[inline]
+    fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        self.inner.set_debug_name(name)
+    }
+}
+
 unsafe impl<T: ?Sized, A> BufferAccess for DeviceLocalBuffer<T, A>
 where
     T: 'static + Send + Sync,
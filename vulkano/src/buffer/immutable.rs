@@ -31,6 +31,7 @@ use crate::command_buffer::CommandBufferUsage;
 use crate::command_buffer::PrimaryAutoCommandBuffer;
 use crate::command_buffer::PrimaryCommandBuffer;
 use crate::device::physical::QueueFamily;
+use crate::device::DebugName;
 use crate::device::Device;
 use crate::device::DeviceOwned;
 use crate::device::Queue;
@@ -47,6 +48,7 @@ use crate::sync::AccessError;
 use crate::sync::NowFuture;
 use crate::sync::Sharing;
 use crate::DeviceSize;
+use crate::OomError;
 use smallvec::SmallVec;
 use std::hash::Hash;
 use std::hash::Hasher;
@@ -90,6 +92,7 @@ impl<T: ?Sized> ImmutableBuffer<T> {
     /// the initial upload operation. In order to be allowed to use the `ImmutableBuffer`, you must
     /// either submit your operation after this future, or execute this future and wait for it to
     /// be finished before submitting your own operation.
+    #[track_caller]
     pub fn from_data(
         data: T,
         usage: BufferUsage,
@@ -113,6 +116,7 @@ impl<T: ?Sized> ImmutableBuffer<T> {
     /// the initial upload operation. In order to be allowed to use the `ImmutableBuffer`, you must
     /// either submit your operation after this future, or execute this future and wait for it to
     /// be finished before submitting your own operation.
+    #[track_caller]
     pub fn from_buffer<B>(
         source: B,
         usage: BufferUsage,
@@ -172,6 +176,7 @@ impl<T> ImmutableBuffer<T> {
     ///   data, otherwise the content is undefined.
     ///
     #[inline]
+    #[track_caller]
     pub unsafe fn uninitialized(
         device: Arc<Device>,
         usage: BufferUsage,
@@ -187,6 +192,7 @@ impl<T> ImmutableBuffer<T> {
 }
 
 impl<T> ImmutableBuffer<[T]> {
+    #[track_caller]
     pub fn from_iter<D>(
         data: D,
         usage: BufferUsage,
@@ -222,6 +228,7 @@ impl<T> ImmutableBuffer<[T]> {
     ///   data, otherwise the content is undefined.
     ///
     #[inline]
+    #[track_caller]
     pub unsafe fn uninitialized_array(
         device: Arc<Device>,
         len: DeviceSize,
@@ -259,6 +266,7 @@ impl<T: ?Sized> ImmutableBuffer<T> {
     ///   data.
     ///
     #[inline]
+    #[track_caller]
     pub unsafe fn raw<'a, I>(
         device: Arc<Device>,
         size: DeviceSize,
@@ -274,6 +282,7 @@ impl<T: ?Sized> ImmutableBuffer<T> {
 
     // Internal implementation of `raw`. This is separated from `raw` so that it doesn't need to be
     // inlined.
+    #[track_caller]
     unsafe fn raw_impl(
         device: Arc<Device>,
         size: DeviceSize,
@@ -403,6 +412,13 @@ unsafe impl<T: ?Sized, A> DeviceOwned for ImmutableBuffer<T, A> {
     }
 }
 
+impl<T: ?Sized, A> DebugName for ImmutableBuffer<T, A> {
+    #[inline]
+    fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        self.inner.set_debug_name(name)
+    }
+}
+
 impl<T: ?Sized, A> PartialEq for ImmutableBuffer<T, A> {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
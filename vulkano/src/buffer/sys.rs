@@ -30,6 +30,7 @@ use crate::device::DeviceOwned;
 use crate::memory::DeviceMemory;
 use crate::memory::DeviceMemoryAllocError;
 use crate::memory::MemoryRequirements;
+use crate::pnext::PNextChain;
 use crate::sync::Sharing;
 use crate::DeviceSize;
 use crate::Error;
@@ -43,6 +44,7 @@ use std::fmt;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::mem::MaybeUninit;
+use std::panic::Location;
 use std::ptr;
 use std::sync::Arc;
 
@@ -64,12 +66,42 @@ impl UnsafeBuffer {
     /// - Panics if `sparse.sparse` is false and `sparse.sparse_residency` or `sparse.sparse_aliased` is true.
     /// - Panics if `usage` is empty.
     ///
+    #[inline]
+    #[track_caller]
     pub unsafe fn new<'a, I>(
+        device: Arc<Device>,
+        size: DeviceSize,
+        usage: BufferUsage,
+        sharing: Sharing<I>,
+        sparse: Option<SparseLevel>,
+    ) -> Result<(UnsafeBuffer, MemoryRequirements), BufferCreationError>
+    where
+        I: Iterator<Item = u32>,
+    {
+        UnsafeBuffer::with_p_next_chain(device, size, usage, sharing, sparse, PNextChain::new())
+    }
+
+    /// Same as `new`, but also appends structures to the `pNext` chain of the
+    /// `VkBufferCreateInfo` structure. See the `pnext` module.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `sparse.sparse` is false and `sparse.sparse_residency` or `sparse.sparse_aliased` is true.
+    /// - Panics if `usage` is empty.
+    ///
+    /// # Safety
+    ///
+    /// In addition to the requirements of `new`, the structures of `p_next_chain` must be valid,
+    /// and the extensions that define them must be enabled on the device.
+    ///
+    #[track_caller]
+    pub unsafe fn with_p_next_chain<I>(
         device: Arc<Device>,
         size: DeviceSize,
         mut usage: BufferUsage,
         sharing: Sharing<I>,
         sparse: Option<SparseLevel>,
+        mut p_next_chain: PNextChain<ash::vk::BufferCreateInfo>,
     ) -> Result<(UnsafeBuffer, MemoryRequirements), BufferCreationError>
     where
         I: Iterator<Item = u32>,
@@ -130,6 +162,7 @@ impl UnsafeBuffer {
             };
 
             let infos = ash::vk::BufferCreateInfo {
+                p_next: p_next_chain.link(ptr::null()),
                 flags,
                 size,
                 usage: usage_bits,
@@ -248,10 +281,35 @@ impl UnsafeBuffer {
             size,
             usage,
         };
+        device.auto_debug_name(&obj, Location::caller());
 
         Ok((obj, mem_reqs))
     }
 
+    /// Builds an `UnsafeBuffer` from a buffer that was created outside of vulkano.
+    ///
+    /// The `UnsafeBuffer` takes ownership of the handle, and destroys it when it is dropped.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid buffer that was created from `device`.
+    /// - `size` and `usage` must match the size and usage that the buffer was created with.
+    /// - The buffer must not be destroyed by anything else than the returned `UnsafeBuffer`.
+    #[inline]
+    pub unsafe fn from_handle(
+        device: Arc<Device>,
+        handle: ash::vk::Buffer,
+        size: DeviceSize,
+        usage: BufferUsage,
+    ) -> UnsafeBuffer {
+        UnsafeBuffer {
+            buffer: handle,
+            device,
+            size,
+            usage,
+        }
+    }
+
     /// Binds device memory to this buffer.
     pub unsafe fn bind_memory(
         &self,
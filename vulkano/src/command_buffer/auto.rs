@@ -8,11 +8,16 @@
 // according to those terms.
 
 use crate::buffer::BufferAccess;
+use crate::buffer::BufferSlice;
 use crate::buffer::TypedBufferAccess;
 use crate::command_buffer::pool::standard::StandardCommandPoolAlloc;
 use crate::command_buffer::pool::standard::StandardCommandPoolBuilder;
 use crate::command_buffer::pool::CommandPool;
 use crate::command_buffer::pool::CommandPoolBuilderAlloc;
+use crate::command_buffer::pool::StandardCommandPool;
+use crate::command_buffer::registered::RegisteredDescriptorSet;
+use crate::command_buffer::registered::RegisteredIndexBuffer;
+use crate::command_buffer::registered::RegisteredResources;
 use crate::command_buffer::synced::SyncCommandBuffer;
 use crate::command_buffer::synced::SyncCommandBufferBuilder;
 use crate::command_buffer::synced::SyncCommandBufferBuilderError;
@@ -22,24 +27,31 @@ use crate::command_buffer::sys::UnsafeCommandBufferBuilderColorImageClear;
 use crate::command_buffer::sys::UnsafeCommandBufferBuilderImageBlit;
 use crate::command_buffer::sys::UnsafeCommandBufferBuilderImageCopy;
 use crate::command_buffer::validity::*;
+use crate::command_buffer::BarrierBuilder;
 use crate::command_buffer::CommandBufferExecError;
 use crate::command_buffer::CommandBufferInheritance;
 use crate::command_buffer::CommandBufferInheritanceRenderPass;
 use crate::command_buffer::CommandBufferLevel;
 use crate::command_buffer::CommandBufferUsage;
+use crate::command_buffer::DescriptorSetId;
 use crate::command_buffer::DispatchIndirectCommand;
 use crate::command_buffer::DrawIndexedIndirectCommand;
 use crate::command_buffer::DrawIndirectCommand;
 use crate::command_buffer::DynamicState;
+use crate::command_buffer::GraphicsPipelineId;
 use crate::command_buffer::ImageUninitializedSafe;
+use crate::command_buffer::IndexBufferId;
 use crate::command_buffer::PrimaryCommandBuffer;
 use crate::command_buffer::SecondaryCommandBuffer;
 use crate::command_buffer::StateCacher;
 use crate::command_buffer::StateCacherOutcome;
 use crate::command_buffer::SubpassContents;
+use crate::command_buffer::VertexBufferId;
+use crate::descriptor_set::DescriptorSet;
 use crate::descriptor_set::DescriptorSetWithOffsets;
 use crate::descriptor_set::DescriptorSetsCollection;
 use crate::device::physical::QueueFamily;
+use crate::device::DebugName;
 use crate::device::Device;
 use crate::device::DeviceOwned;
 use crate::device::Queue;
@@ -79,6 +91,8 @@ use crate::DeviceSize;
 use crate::VulkanObject;
 use crate::{OomError, SafeDeref};
 use fnv::FnvHashMap;
+use smallvec::SmallVec;
+use std::cmp;
 use std::convert::TryInto;
 use std::error;
 use std::ffi::CStr;
@@ -101,6 +115,9 @@ pub struct AutoCommandBufferBuilder<L, P = StandardCommandPoolBuilder> {
     pool_builder_alloc: P, // Safety: must be dropped after `inner`
     state_cacher: StateCacher,
 
+    // The resources that can be bound by identifier.
+    registered: RegisteredResources,
+
     // The queue family that this command buffer is being created for.
     queue_family_id: u32,
 
@@ -113,8 +130,12 @@ pub struct AutoCommandBufferBuilder<L, P = StandardCommandPoolBuilder> {
     // If we're inside a render pass, contains the render pass state.
     render_pass_state: Option<RenderPassState>,
 
-    // If any queries are active, this hashmap contains their state.
-    query_state: FnvHashMap<ash::vk::QueryType, QueryState>,
+    // If any queries are active, this hashmap contains their state, by type and index.
+    query_state: FnvHashMap<(ash::vk::QueryType, u32), QueryState>,
+
+    // True if the draw and dispatch commands skip the checks that only catch mistakes of the
+    // application. See `skip_validity_checks`.
+    skip_validity_checks: bool,
 
     _data: PhantomData<L>,
 }
@@ -153,6 +174,21 @@ impl AutoCommandBufferBuilder<PrimaryAutoCommandBuffer, StandardCommandPoolBuild
             CommandBufferLevel::primary(),
         )
     }
+
+    /// Same as `primary`, but allocates the command buffer from `pool` instead of the device's
+    /// standard command pool.
+    ///
+    /// The queue family of the command buffer is the one of the pool.
+    #[inline]
+    pub fn primary_with_pool(
+        pool: Arc<StandardCommandPool>,
+        usage: CommandBufferUsage,
+    ) -> Result<
+        AutoCommandBufferBuilder<PrimaryAutoCommandBuffer, StandardCommandPoolBuilder>,
+        OomError,
+    > {
+        AutoCommandBufferBuilder::with_pool_and_level(pool, usage, CommandBufferLevel::primary())
+    }
 }
 
 impl AutoCommandBufferBuilder<SecondaryAutoCommandBuffer, StandardCommandPoolBuilder> {
@@ -266,7 +302,7 @@ impl AutoCommandBufferBuilder<SecondaryAutoCommandBuffer, StandardCommandPoolBui
 }
 
 impl<L> AutoCommandBufferBuilder<L, StandardCommandPoolBuilder> {
-    // Actual constructor. Private.
+    #[inline]
     fn with_level<F>(
         device: Arc<Device>,
         queue_family: QueueFamily,
@@ -276,6 +312,20 @@ impl<L> AutoCommandBufferBuilder<L, StandardCommandPoolBuilder> {
     where
         F: FramebufferAbstract + Clone + Send + Sync + 'static,
     {
+        let pool = Device::standard_command_pool(&device, queue_family);
+        AutoCommandBufferBuilder::with_pool_and_level(pool, usage, level)
+    }
+
+    // Actual constructor. Private.
+    fn with_pool_and_level<F>(
+        pool: Arc<StandardCommandPool>,
+        usage: CommandBufferUsage,
+        level: CommandBufferLevel<F>,
+    ) -> Result<AutoCommandBufferBuilder<L, StandardCommandPoolBuilder>, OomError>
+    where
+        F: FramebufferAbstract + Clone + Send + Sync + 'static,
+    {
+        let queue_family = pool.queue_family();
         let (inheritance, render_pass_state) = match &level {
             CommandBufferLevel::Primary => (None, None),
             CommandBufferLevel::Secondary(inheritance) => {
@@ -313,20 +363,26 @@ impl<L> AutoCommandBufferBuilder<L, StandardCommandPoolBuilder> {
         };
 
         unsafe {
-            let pool = Device::standard_command_pool(&device, queue_family);
             let pool_builder_alloc = pool
                 .alloc(!matches!(level, CommandBufferLevel::Primary), 1)?
                 .next()
                 .expect("Requested one command buffer from the command pool, but got zero.");
-            let inner = SyncCommandBufferBuilder::new(pool_builder_alloc.inner(), level, usage)?;
+            let inner = SyncCommandBufferBuilder::with_arena_cache(
+                pool_builder_alloc.inner(),
+                level,
+                usage,
+                Some(pool.arena_cache().clone()),
+            )?;
 
             Ok(AutoCommandBufferBuilder {
                 inner,
                 pool_builder_alloc,
                 state_cacher: StateCacher::new(),
+                registered: RegisteredResources::new(),
                 queue_family_id: queue_family.id(),
                 render_pass_state,
                 query_state: FnvHashMap::default(),
+                skip_validity_checks: false,
                 inheritance,
                 usage,
                 _data: PhantomData,
@@ -477,11 +533,11 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         }
 
         // Render passes must be compatible.
-        if !pipeline
-            .subpass()
-            .render_pass()
-            .desc()
-            .is_compatible_with_desc(&render_pass_state.subpass.0.desc())
+        if !self.skip_validity_checks
+            && !pipeline
+                .subpass()
+                .render_pass()
+                .is_compatible_with(&render_pass_state.subpass.0)
         {
             return Err(AutoCommandBufferBuilderContextError::IncompatibleRenderPass);
         }
@@ -904,6 +960,77 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         }
     }
 
+    /// Adds a command that copies several regions of a buffer of bytes to an image, with a single
+    /// `vkCmdCopyBufferToImage`.
+    ///
+    /// The `buffer_offset` of the regions is in bytes. The data of each region must be tightly
+    /// packed, in other words `buffer_row_length` and `buffer_image_height` must be 0.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the `buffer_row_length` or the `buffer_image_height` of a region isn't 0.
+    /// - Panics if the `buffer_offset` of a region isn't a multiple of 4 and of the size of a
+    ///   texel block of the format of `destination`.
+    pub fn copy_buffer_to_image_regions<S, D, R>(
+        &mut self,
+        source: S,
+        destination: D,
+        regions: R,
+    ) -> Result<&mut Self, CopyBufferImageError>
+    where
+        S: TypedBufferAccess<Content = [u8]> + Send + Sync + 'static,
+        D: ImageAccess + Send + Sync + 'static,
+        R: IntoIterator<Item = UnsafeCommandBufferBuilderBufferImageCopy>,
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+
+            let regions: SmallVec<[_; 4]> = regions.into_iter().collect();
+            let block_size = destination.format().size().unwrap_or(1);
+
+            for region in &regions {
+                assert_eq!(region.buffer_row_length, 0);
+                assert_eq!(region.buffer_image_height, 0);
+                assert_eq!(region.buffer_offset % 4, 0);
+                assert_eq!(region.buffer_offset % block_size, 0);
+
+                if region.image_offset.iter().any(|&offset| offset < 0) {
+                    return Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange.into());
+                }
+
+                // Checks the region against the part of the buffer that starts at its offset.
+                let start = cmp::min(region.buffer_offset, source.len());
+                let data = BufferSlice::from_typed_buffer_access(&source)
+                    .slice(start..source.len())
+                    .unwrap();
+
+                check_copy_buffer_image(
+                    self.device(),
+                    &data,
+                    &destination,
+                    CheckCopyBufferImageTy::BufferToImage,
+                    [
+                        region.image_offset[0] as u32,
+                        region.image_offset[1] as u32,
+                        region.image_offset[2] as u32,
+                    ],
+                    region.image_extent,
+                    region.image_base_array_layer,
+                    region.image_layer_count,
+                    region.image_mip_level,
+                )?;
+            }
+
+            self.inner.copy_buffer_to_image(
+                source,
+                destination,
+                ImageLayout::TransferDstOptimal,
+                regions,
+            )?;
+            Ok(self)
+        }
+    }
+
     /// Adds a command that copies from an image to a buffer.
     // The data layout of the image on the gpu is opaque, as in, it is non of our business how the gpu stores the image.
     // This does not matter since the act of copying the image into a buffer converts it to linear form.
@@ -1048,6 +1175,77 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         Ok(self)
     }
 
+    /// Restricts the following commands to the physical devices of `device_mask`, for a device
+    /// created from a device group.
+    ///
+    /// Bit `i` of the mask stands for the physical device of device index `i`. Until this is
+    /// called, the commands are executed by all the physical devices of the device. This is the
+    /// building block of split-frame and alternate-frame rendering.
+    #[inline]
+    pub fn set_device_mask(&mut self, device_mask: u32) -> Result<&mut Self, CheckDeviceMaskError> {
+        check_device_mask(self.device(), device_mask)?;
+
+        unsafe {
+            self.inner.set_device_mask(device_mask);
+        }
+
+        Ok(self)
+    }
+
+    /// Makes the draw and dispatch commands that follow skip the checks that only catch mistakes
+    /// of the application, which are the checks of the descriptor sets, push constants, dynamic
+    /// state and vertex buffers against the pipeline, and of the compatibility of the pipeline
+    /// with the current render pass. `pipeline_barrier` also skips the check of the old layouts
+    /// of its image barriers. This saves CPU time once the application is known to pass these
+    /// checks. Passing `false` enables the checks again.
+    ///
+    /// The tracking of the image layouts and of the accesses to the resources isn't affected,
+    /// since the pipeline barriers are derived from it.
+    ///
+    /// # Safety
+    ///
+    /// - While the checks are skipped, the draw, dispatch and pipeline barrier commands must be
+    ///   called with parameters that would have passed them.
+    ///
+    #[inline]
+    pub unsafe fn skip_validity_checks(&mut self, skip: bool) -> &mut Self {
+        self.skip_validity_checks = skip;
+        self
+    }
+
+    /// Inserts a checkpoint labelled `label` into the command buffer.
+    ///
+    /// After the device is lost, the label is returned by
+    /// [`Queue::checkpoints`](crate::device::Queue::checkpoints) if this checkpoint is the last
+    /// one that the queue reached. Requires the `nv_device_diagnostic_checkpoints` extension.
+    ///
+    /// The device only keeps the last 4096 distinct labels, so prefer a fixed set of labels over
+    /// labels built at runtime. The checkpoints whose label has been dropped are reported without
+    /// a label.
+    #[inline]
+    pub fn set_checkpoint(&mut self, label: &str) -> Result<&mut Self, CheckCheckpointError> {
+        check_checkpoint(self.device(), self.queue_family())?;
+
+        unsafe {
+            self.inner.set_checkpoint(label.to_owned());
+        }
+
+        Ok(self)
+    }
+
+    /// Sets whether the builder records exactly the state changes of each command.
+    ///
+    /// By default, the draw and dispatch commands don't bind a pipeline, descriptor sets, vertex
+    /// buffers or an index buffer that are already bound with the same parameters, and only set
+    /// the dynamic state that differs from the current one. If `exact` is true, each of these
+    /// commands records all of its state instead, for example so that a capture tool shows the
+    /// state of each draw where the application set it.
+    #[inline]
+    pub fn set_exact_recording(&mut self, exact: bool) -> &mut Self {
+        self.state_cacher.set_enabled(!exact);
+        self
+    }
+
     /// Perform a single compute operation using a compute pipeline.
     #[inline]
     pub fn dispatch<S, Pc>(
@@ -1068,8 +1266,10 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
             }
 
             self.ensure_outside_render_pass()?;
-            check_push_constants_validity(pipeline.layout(), &push_constants)?;
-            check_descriptor_sets_validity(pipeline.layout(), &descriptor_sets)?;
+            if !self.skip_validity_checks {
+                check_push_constants_validity(pipeline.layout(), &push_constants)?;
+                check_descriptor_sets_validity(pipeline.layout(), &descriptor_sets)?;
+            }
             check_dispatch(pipeline.device(), group_counts)?;
 
             let pipeline_layout = pipeline.layout().clone();
@@ -1121,8 +1321,10 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
 
             self.ensure_outside_render_pass()?;
             check_indirect_buffer(self.device(), &indirect_buffer)?;
-            check_push_constants_validity(pipeline.layout(), &push_constants)?;
-            check_descriptor_sets_validity(pipeline.layout(), &descriptor_sets)?;
+            if !self.skip_validity_checks {
+                check_push_constants_validity(pipeline.layout(), &push_constants)?;
+                check_descriptor_sets_validity(pipeline.layout(), &descriptor_sets)?;
+            }
 
             let pipeline_layout = pipeline.layout().clone();
 
@@ -1172,55 +1374,25 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         let descriptor_sets = descriptor_sets.into_vec();
         let vertex_buffers = vertex_buffers.into_vec();
 
-        let (max_vertex_count, max_instance_count) =
-            pipeline.vertex_input().max_vertices_instances(
-                vertex_buffers
-                    .iter()
-                    .enumerate()
-                    .map(|(i, v)| (i as u32, v as _)),
-            );
-
-        if first_vertex + vertex_count > max_vertex_count {
-            return Err(CheckVertexBufferError::TooManyVertices {
-                vertex_count,
-                max_vertex_count,
-            }
-            .into());
-        }
-
-        if first_instance + instance_count > max_instance_count {
-            return Err(CheckVertexBufferError::TooManyInstances {
-                instance_count,
-                max_instance_count,
-            }
-            .into());
-        }
-
-        if let Some(multiview) = pipeline.subpass().render_pass().desc().multiview() {
-            let max_instance_index = pipeline
-                .device()
-                .physical_device()
-                .properties()
-                .max_multiview_instance_index
-                .unwrap_or(0);
-
-            if first_instance + instance_count > max_instance_index + 1 {
-                return Err(CheckVertexBufferError::TooManyInstances {
-                    instance_count,
-                    max_instance_count: max_instance_index + 1, // TODO: this can overflow
-                }
-                .into());
-            }
-        }
+        check_draw_ranges(
+            &pipeline,
+            vertex_buffers.iter().map(|v| v as _),
+            Some((first_vertex, vertex_count)),
+            None,
+            first_instance,
+            instance_count,
+        )?;
 
         unsafe {
             // TODO: must check that pipeline is compatible with render pass
 
             self.ensure_inside_render_pass_inline(&pipeline)?;
-            check_dynamic_state_validity(&pipeline, dynamic)?;
-            check_push_constants_validity(pipeline.layout(), &push_constants)?;
-            check_descriptor_sets_validity(pipeline.layout(), &descriptor_sets)?;
-            check_vertex_buffers(&pipeline, &vertex_buffers)?;
+            if !self.skip_validity_checks {
+                check_dynamic_state_validity(&pipeline, dynamic)?;
+                check_push_constants_validity(pipeline.layout(), &push_constants)?;
+                check_descriptor_sets_validity(pipeline.layout(), &descriptor_sets)?;
+                check_vertex_buffers(&pipeline, &vertex_buffers)?;
+            }
 
             let pipeline_layout = pipeline.layout().clone();
 
@@ -1292,10 +1464,12 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
 
             self.ensure_inside_render_pass_inline(&pipeline)?;
             check_indirect_buffer(self.device(), &indirect_buffer)?;
-            check_dynamic_state_validity(&pipeline, dynamic)?;
-            check_push_constants_validity(pipeline.layout(), &push_constants)?;
-            check_descriptor_sets_validity(pipeline.layout(), &descriptor_sets)?;
-            check_vertex_buffers(&pipeline, &vertex_buffers)?;
+            if !self.skip_validity_checks {
+                check_dynamic_state_validity(&pipeline, dynamic)?;
+                check_push_constants_validity(pipeline.layout(), &push_constants)?;
+                check_descriptor_sets_validity(pipeline.layout(), &descriptor_sets)?;
+                check_vertex_buffers(&pipeline, &vertex_buffers)?;
+            }
 
             let requested = indirect_buffer.len() as u32;
             let limit = self
@@ -1378,57 +1552,30 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         let descriptor_sets = descriptor_sets.into_vec();
         let vertex_buffers = vertex_buffers.into_vec();
 
-        let (max_vertex_count, max_instance_count) =
-            pipeline.vertex_input().max_vertices_instances(
-                vertex_buffers
-                    .iter()
-                    .enumerate()
-                    .map(|(i, v)| (i as u32, v as _)),
-            );
-        let max_index_count = index_buffer.len().try_into().unwrap_or(u32::MAX);
-
-        if first_index + index_count > max_index_count {
-            return Err(CheckVertexBufferError::TooManyIndices {
+        check_draw_ranges(
+            &pipeline,
+            vertex_buffers.iter().map(|v| v as _),
+            None,
+            Some((
+                first_index,
                 index_count,
-                max_index_count,
-            }
-            .into());
-        }
-
-        if first_instance + instance_count > max_instance_count {
-            return Err(CheckVertexBufferError::TooManyInstances {
-                instance_count,
-                max_instance_count,
-            }
-            .into());
-        }
-
-        if let Some(multiview) = pipeline.subpass().render_pass().desc().multiview() {
-            let max_instance_index = pipeline
-                .device()
-                .physical_device()
-                .properties()
-                .max_multiview_instance_index
-                .unwrap_or(0);
-
-            if first_instance + instance_count > max_instance_index + 1 {
-                return Err(CheckVertexBufferError::TooManyInstances {
-                    instance_count,
-                    max_instance_count: max_instance_index + 1, // TODO: this can overflow
-                }
-                .into());
-            }
-        }
+                index_buffer.len().try_into().unwrap_or(u32::MAX),
+            )),
+            first_instance,
+            instance_count,
+        )?;
 
         unsafe {
             // TODO: must check that pipeline is compatible with render pass
 
             self.ensure_inside_render_pass_inline(&pipeline)?;
             check_index_buffer(self.device(), &index_buffer)?;
-            check_dynamic_state_validity(&pipeline, dynamic)?;
-            check_push_constants_validity(pipeline.layout(), &push_constants)?;
-            check_descriptor_sets_validity(pipeline.layout(), &descriptor_sets)?;
-            check_vertex_buffers(&pipeline, &vertex_buffers)?;
+            if !self.skip_validity_checks {
+                check_dynamic_state_validity(&pipeline, dynamic)?;
+                check_push_constants_validity(pipeline.layout(), &push_constants)?;
+                check_descriptor_sets_validity(pipeline.layout(), &descriptor_sets)?;
+                check_vertex_buffers(&pipeline, &vertex_buffers)?;
+            }
 
             let pipeline_layout = pipeline.layout().clone();
 
@@ -1517,10 +1664,12 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
             self.ensure_inside_render_pass_inline(&pipeline)?;
             check_index_buffer(self.device(), &index_buffer)?;
             check_indirect_buffer(self.device(), &indirect_buffer)?;
-            check_dynamic_state_validity(&pipeline, dynamic)?;
-            check_push_constants_validity(pipeline.layout(), &push_constants)?;
-            check_descriptor_sets_validity(pipeline.layout(), &descriptor_sets)?;
-            check_vertex_buffers(&pipeline, &vertex_buffers)?;
+            if !self.skip_validity_checks {
+                check_dynamic_state_validity(&pipeline, dynamic)?;
+                check_push_constants_validity(pipeline.layout(), &push_constants)?;
+                check_descriptor_sets_validity(pipeline.layout(), &descriptor_sets)?;
+                check_vertex_buffers(&pipeline, &vertex_buffers)?;
+            }
 
             let requested = indirect_buffer.len() as u32;
             let limit = self
@@ -1577,76 +1726,578 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         }
     }
 
-    /// Adds a command that writes the content of a buffer.
+    /// Perform multiple draw operations using a graphics pipeline, using an index buffer, with a
+    /// number of draws that is read from a buffer when the command is executed.
     ///
-    /// This function is similar to the `memset` function in C. The `data` parameter is a number
-    /// that will be repeatedly written through the entire buffer.
+    /// The first `u32` of `count_buffer` is the number of [`DrawIndexedIndirectCommand`] structs
+    /// that are read from `indirect_buffer`. It is clamped to the length of `indirect_buffer`,
+    /// which must not exceed the
+    /// [`max_draw_indirect_count`](crate::device::Properties::max_draw_indirect_count) limit.
+    /// This allows the indirect commands and their count to be written by the GPU, for example
+    /// by a culling pass in a compute shader.
     ///
-    /// > **Note**: This function is technically safe because buffers can only contain integers or
-    /// > floating point numbers, which are always valid whatever their memory representation is.
-    /// > But unless your buffer actually contains only 32-bits integers, you are encouraged to use
-    /// > this function only for zeroing the content of a buffer by passing `0` for the data.
-    // TODO: not safe because of signalling NaNs
-    #[inline]
-    pub fn fill_buffer<B>(&mut self, buffer: B, data: u32) -> Result<&mut Self, FillBufferError>
-    where
-        B: BufferAccess + Send + Sync + 'static,
-    {
-        unsafe {
-            self.ensure_outside_render_pass()?;
-            check_fill_buffer(self.device(), &buffer)?;
-            self.inner.fill_buffer(buffer, data);
-            Ok(self)
-        }
-    }
-
-    /// Adds a command that writes data to a buffer.
+    /// On devices with an API version of 1.2, the
+    /// [`draw_indirect_count`](crate::device::Features::draw_indirect_count) feature must be
+    /// enabled. On devices with a lower API version, the `khr_draw_indirect_count` extension must
+    /// be enabled.
     ///
-    /// If `data` is larger than the buffer, only the part of `data` that fits is written. If the
-    /// buffer is larger than `data`, only the start of the buffer is written.
+    /// The other parameters are the same as `draw_indexed_indirect`.
     #[inline]
-    pub fn update_buffer<B, D, Dd>(
+    pub fn draw_indexed_indirect_count<V, Ib, I, Inb, Cb, S, Pc>(
         &mut self,
-        buffer: B,
-        data: Dd,
-    ) -> Result<&mut Self, UpdateBufferError>
+        pipeline: Arc<GraphicsPipeline>,
+        dynamic: &DynamicState,
+        vertex_buffers: V,
+        index_buffer: Ib,
+        indirect_buffer: Inb,
+        count_buffer: Cb,
+        descriptor_sets: S,
+        push_constants: Pc,
+    ) -> Result<&mut Self, DrawIndexedIndirectCountError>
     where
-        B: TypedBufferAccess<Content = D> + Send + Sync + 'static,
-        D: ?Sized,
-        Dd: SafeDeref<Target = D> + Send + Sync + 'static,
+        V: VertexBuffersCollection,
+        Ib: BufferAccess + TypedBufferAccess<Content = [I]> + Send + Sync + 'static,
+        I: Index + 'static,
+        Inb: BufferAccess
+            + TypedBufferAccess<Content = [DrawIndexedIndirectCommand]>
+            + Send
+            + Sync
+            + 'static,
+        Cb: BufferAccess + TypedBufferAccess<Content = u32> + Send + Sync + 'static,
+        S: DescriptorSetsCollection,
     {
+        let descriptor_sets = descriptor_sets.into_vec();
+        let vertex_buffers = vertex_buffers.into_vec();
+
         unsafe {
-            self.ensure_outside_render_pass()?;
-            check_update_buffer(self.device(), &buffer, data.deref())?;
+            // TODO: must check that pipeline is compatible with render pass
 
-            let size_of_data = mem::size_of_val(data.deref()) as DeviceSize;
-            if buffer.size() >= size_of_data {
-                self.inner.update_buffer(buffer, data);
-            } else {
-                unimplemented!() // TODO:
-                                 //self.inner.update_buffer(buffer.slice(0 .. size_of_data), data);
+            self.ensure_inside_render_pass_inline(&pipeline)?;
+            check_index_buffer(self.device(), &index_buffer)?;
+            check_indirect_buffer(self.device(), &indirect_buffer)?;
+            check_indirect_count_buffer(self.device(), &count_buffer)?;
+            if !self.skip_validity_checks {
+                check_dynamic_state_validity(&pipeline, dynamic)?;
+                check_push_constants_validity(pipeline.layout(), &push_constants)?;
+                check_descriptor_sets_validity(pipeline.layout(), &descriptor_sets)?;
+                check_vertex_buffers(&pipeline, &vertex_buffers)?;
+            }
+
+            let requested = indirect_buffer.len() as u32;
+            let limit = self
+                .device()
+                .physical_device()
+                .properties()
+                .max_draw_indirect_count;
+
+            if requested > limit {
+                return Err(
+                    CheckIndirectBufferError::MaxDrawIndirectCountLimitExceeded {
+                        limit,
+                        requested,
+                    }
+                    .into(),
+                );
+            }
+
+            let pipeline_layout = pipeline.layout().clone();
+
+            if let StateCacherOutcome::NeedChange =
+                self.state_cacher.bind_graphics_pipeline(&pipeline)
+            {
+                self.inner.bind_pipeline_graphics(pipeline);
             }
 
+            if let StateCacherOutcome::NeedChange =
+                self.state_cacher.bind_index_buffer(&index_buffer, I::ty())
+            {
+                self.inner.bind_index_buffer(index_buffer, I::ty())?;
+            }
+
+            let dynamic = self.state_cacher.dynamic_state(dynamic);
+
+            set_push_constants(&mut self.inner, &pipeline_layout, push_constants);
+            set_state(&mut self.inner, &dynamic);
+            bind_descriptor_sets(
+                &mut self.inner,
+                &mut self.state_cacher,
+                PipelineBindPoint::Graphics,
+                &pipeline_layout,
+                descriptor_sets,
+            )?;
+            bind_vertex_buffers(&mut self.inner, &mut self.state_cacher, vertex_buffers)?;
+
+            debug_assert!(self.queue_family().supports_graphics());
+
+            self.inner.draw_indexed_indirect_count(
+                indirect_buffer,
+                count_buffer,
+                requested,
+                mem::size_of::<DrawIndexedIndirectCommand>() as u32,
+            )?;
             Ok(self)
         }
     }
 
-    /// Adds a command that begins a query.
+    /// Registers a graphics pipeline with the builder, so that it can be bound by
+    /// `draw_registered` and `draw_indexed_registered`.
     ///
-    /// The query will be active until [`end_query`](Self::end_query) is called for the same query.
+    /// The builder keeps the pipeline alive, and only clones it again when it needs to bind it.
     ///
-    /// # Safety
-    /// The query must be unavailable, ensured by calling [`reset_query_pool`](Self::reset_query_pool).
-    pub unsafe fn begin_query(
-        &mut self,
-        query_pool: Arc<QueryPool>,
+    /// # Panic
+    ///
+    /// - Panics if the pipeline was not created with the same device as the builder.
+    ///
+    #[inline]
+    pub fn register_graphics_pipeline(
+        &mut self,
+        pipeline: Arc<GraphicsPipeline>,
+    ) -> GraphicsPipelineId {
+        assert_eq!(
+            pipeline.device().internal_object(),
+            self.device().internal_object()
+        );
+
+        self.registered.push_graphics_pipeline(pipeline)
+    }
+
+    /// Registers a descriptor set and its dynamic offsets with the builder, so that it can be
+    /// bound by `draw_registered` and `draw_indexed_registered`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the descriptor set was not created with the same device as the builder.
+    /// - Panics if the dynamic offsets are invalid, see `DescriptorSetWithOffsets::new`.
+    ///
+    pub fn register_descriptor_set<S, O>(
+        &mut self,
+        descriptor_set: S,
+        dynamic_offsets: O,
+    ) -> DescriptorSetId
+    where
+        S: DescriptorSet + Send + Sync + 'static,
+        O: IntoIterator<Item = u32>,
+    {
+        assert_eq!(
+            descriptor_set.device().internal_object(),
+            self.device().internal_object()
+        );
+
+        let set: Arc<dyn DescriptorSet + Send + Sync> = Arc::new(descriptor_set);
+        let with_offsets = DescriptorSetWithOffsets::new(set.clone(), dynamic_offsets);
+        self.registered
+            .push_descriptor_set(RegisteredDescriptorSet { set, with_offsets })
+    }
+
+    /// Registers a vertex buffer with the builder, so that it can be bound by `draw_registered`
+    /// and `draw_indexed_registered`.
+    ///
+    /// On error, `CheckVertexBufferError::BufferMissingUsage` refers to the registered buffer as
+    /// buffer 0.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the buffer was not created with the same device as the builder.
+    ///
+    pub fn register_vertex_buffer<B>(
+        &mut self,
+        buffer: B,
+    ) -> Result<VertexBufferId, CheckVertexBufferError>
+    where
+        B: BufferAccess + Send + Sync + 'static,
+    {
+        assert_eq!(
+            buffer.inner().buffer.device().internal_object(),
+            self.device().internal_object()
+        );
+
+        if !buffer.inner().buffer.usage().vertex_buffer {
+            return Err(CheckVertexBufferError::BufferMissingUsage { num_buffer: 0 });
+        }
+
+        Ok(self.registered.push_vertex_buffer(Arc::new(buffer)))
+    }
+
+    /// Registers an index buffer with the builder, so that it can be bound by
+    /// `draw_indexed_registered`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the buffer was not created with the same device as the builder.
+    ///
+    pub fn register_index_buffer<Ib, I>(
+        &mut self,
+        index_buffer: Ib,
+    ) -> Result<IndexBufferId, CheckIndexBufferError>
+    where
+        Ib: BufferAccess + TypedBufferAccess<Content = [I]> + Send + Sync + 'static,
+        I: Index + 'static,
+    {
+        check_index_buffer(self.device(), &index_buffer)?;
+
+        let len = index_buffer.len().try_into().unwrap_or(u32::MAX);
+        Ok(self.registered.push_index_buffer(RegisteredIndexBuffer {
+            buffer: Arc::new(index_buffer),
+            ty: I::ty(),
+            len,
+        }))
+    }
+
+    /// Same as `draw`, but with a pipeline, vertex buffers and descriptor sets that were
+    /// registered with the builder.
+    ///
+    /// The registered resources are borrowed by the builder rather than cloned, unless they
+    /// aren't already bound. Their usage and device were checked when they were registered.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the identifiers was returned by another builder.
+    ///
+    #[inline]
+    pub fn draw_registered<Pc>(
+        &mut self,
+        vertex_count: u32,
+        instance_count: u32,
+        first_vertex: u32,
+        first_instance: u32,
+        pipeline: GraphicsPipelineId,
+        dynamic: &DynamicState,
+        vertex_buffers: &[VertexBufferId],
+        descriptor_sets: &[DescriptorSetId],
+        push_constants: Pc,
+    ) -> Result<&mut Self, DrawError> {
+        let pipeline = self.registered.graphics_pipeline(pipeline);
+
+        check_draw_ranges(
+            pipeline,
+            vertex_buffers
+                .iter()
+                .map(|&id| &**self.registered.vertex_buffer(id) as _),
+            Some((first_vertex, vertex_count)),
+            None,
+            first_instance,
+            instance_count,
+        )?;
+
+        unsafe {
+            self.ensure_inside_render_pass_inline(pipeline)?;
+            if !self.skip_validity_checks {
+                check_dynamic_state_validity(pipeline, dynamic)?;
+                check_push_constants_validity(pipeline.layout(), &push_constants)?;
+                check_descriptor_set_layouts(pipeline.layout(), |set_num| {
+                    descriptor_sets
+                        .get(set_num)
+                        .map(|&id| &**self.registered.descriptor_set_layout(id))
+                })?;
+            }
+
+            if let StateCacherOutcome::NeedChange =
+                self.state_cacher.bind_graphics_pipeline(pipeline)
+            {
+                self.inner.bind_pipeline_graphics(pipeline.clone());
+            }
+
+            let dynamic = self.state_cacher.dynamic_state(dynamic);
+
+            set_push_constants(&mut self.inner, pipeline.layout(), push_constants);
+            set_state(&mut self.inner, &dynamic);
+            bind_registered_resources(
+                &mut self.inner,
+                &mut self.state_cacher,
+                &self.registered,
+                pipeline.layout(),
+                vertex_buffers,
+                descriptor_sets,
+            )?;
+
+            debug_assert!(self.queue_family().supports_graphics());
+
+            self.inner
+                .draw(vertex_count, instance_count, first_vertex, first_instance);
+            Ok(self)
+        }
+    }
+
+    /// Same as `draw_indexed`, but with a pipeline, buffers and descriptor sets that were
+    /// registered with the builder.
+    ///
+    /// The registered resources are borrowed by the builder rather than cloned, unless they
+    /// aren't already bound. Their usage and device were checked when they were registered.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the identifiers was returned by another builder.
+    ///
+    #[inline]
+    pub fn draw_indexed_registered<Pc>(
+        &mut self,
+        index_count: u32,
+        instance_count: u32,
+        first_index: u32,
+        vertex_offset: i32,
+        first_instance: u32,
+        pipeline: GraphicsPipelineId,
+        dynamic: &DynamicState,
+        vertex_buffers: &[VertexBufferId],
+        index_buffer: IndexBufferId,
+        descriptor_sets: &[DescriptorSetId],
+        push_constants: Pc,
+    ) -> Result<&mut Self, DrawIndexedError> {
+        let pipeline = self.registered.graphics_pipeline(pipeline);
+        let index_buffer = self.registered.index_buffer(index_buffer);
+
+        check_draw_ranges(
+            pipeline,
+            vertex_buffers
+                .iter()
+                .map(|&id| &**self.registered.vertex_buffer(id) as _),
+            None,
+            Some((first_index, index_count, index_buffer.len)),
+            first_instance,
+            instance_count,
+        )?;
+
+        unsafe {
+            self.ensure_inside_render_pass_inline(pipeline)?;
+            if !self.skip_validity_checks {
+                check_dynamic_state_validity(pipeline, dynamic)?;
+                check_push_constants_validity(pipeline.layout(), &push_constants)?;
+                check_descriptor_set_layouts(pipeline.layout(), |set_num| {
+                    descriptor_sets
+                        .get(set_num)
+                        .map(|&id| &**self.registered.descriptor_set_layout(id))
+                })?;
+            }
+
+            if let StateCacherOutcome::NeedChange =
+                self.state_cacher.bind_graphics_pipeline(pipeline)
+            {
+                self.inner.bind_pipeline_graphics(pipeline.clone());
+            }
+
+            if let StateCacherOutcome::NeedChange = self
+                .state_cacher
+                .bind_index_buffer(&*index_buffer.buffer, index_buffer.ty)
+            {
+                self.inner
+                    .bind_index_buffer(index_buffer.buffer.clone(), index_buffer.ty)?;
+            }
+
+            let dynamic = self.state_cacher.dynamic_state(dynamic);
+
+            set_push_constants(&mut self.inner, pipeline.layout(), push_constants);
+            set_state(&mut self.inner, &dynamic);
+            bind_registered_resources(
+                &mut self.inner,
+                &mut self.state_cacher,
+                &self.registered,
+                pipeline.layout(),
+                vertex_buffers,
+                descriptor_sets,
+            )?;
+
+            debug_assert!(self.queue_family().supports_graphics());
+
+            self.inner.draw_indexed(
+                index_count,
+                instance_count,
+                first_index,
+                vertex_offset,
+                first_instance,
+            );
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that writes the content of a buffer.
+    ///
+    /// This function is similar to the `memset` function in C. The `data` parameter is a number
+    /// that will be repeatedly written through the entire buffer.
+    ///
+    /// > **Note**: This function is technically safe because buffers can only contain integers or
+    /// > floating point numbers, which are always valid whatever their memory representation is.
+    /// > But unless your buffer actually contains only 32-bits integers, you are encouraged to use
+    /// > this function only for zeroing the content of a buffer by passing `0` for the data.
+    // TODO: not safe because of signalling NaNs
+    #[inline]
+    pub fn fill_buffer<B>(&mut self, buffer: B, data: u32) -> Result<&mut Self, FillBufferError>
+    where
+        B: BufferAccess + Send + Sync + 'static,
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            check_fill_buffer(self.device(), &buffer)?;
+            self.inner.fill_buffer(buffer, data);
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that writes data to a buffer.
+    ///
+    /// If `data` is larger than the buffer, only the part of `data` that fits is written. If the
+    /// buffer is larger than `data`, only the start of the buffer is written.
+    #[inline]
+    pub fn update_buffer<B, D, Dd>(
+        &mut self,
+        buffer: B,
+        data: Dd,
+    ) -> Result<&mut Self, UpdateBufferError>
+    where
+        B: TypedBufferAccess<Content = D> + Send + Sync + 'static,
+        D: ?Sized,
+        Dd: SafeDeref<Target = D> + Send + Sync + 'static,
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            check_update_buffer(self.device(), &buffer, data.deref())?;
+
+            let size_of_data = mem::size_of_val(data.deref()) as DeviceSize;
+            if buffer.size() >= size_of_data {
+                self.inner.update_buffer(buffer, data);
+            } else {
+                unimplemented!() // TODO:
+                                 //self.inner.update_buffer(buffer.slice(0 .. size_of_data), data);
+            }
+
+            Ok(self)
+        }
+    }
+
+    /// Adds a pipeline barrier inside the current subpass.
+    ///
+    /// This is needed to safely read, as an input attachment, data that was written to an
+    /// attachment earlier in the same subpass (a "feedback loop"). The current subpass must have
+    /// been declared with a self-dependency (a `SubpassDependencyDesc` whose source and
+    /// destination are both this subpass) that includes all of the given stages and accesses,
+    /// and whose `by_region` value is the same as `by_region`.
+    ///
+    /// Since the barrier is a global memory barrier, it applies to all the attachments of the
+    /// subpass.
+    #[inline]
+    pub fn subpass_barrier(
+        &mut self,
+        source_stages: PipelineStages,
+        source_access: AccessFlags,
+        destination_stages: PipelineStages,
+        destination_access: AccessFlags,
+        by_region: bool,
+    ) -> Result<&mut Self, SubpassBarrierError> {
+        let render_pass_state = self
+            .render_pass_state
+            .as_ref()
+            .ok_or(AutoCommandBufferBuilderContextError::ForbiddenOutsideRenderPass)?;
+
+        if render_pass_state.contents != SubpassContents::Inline {
+            return Err(AutoCommandBufferBuilderContextError::WrongSubpassType.into());
+        }
+
+        let (ref render_pass, subpass) = render_pass_state.subpass;
+        check_subpass_barrier(
+            render_pass.desc(),
+            subpass,
+            source_stages,
+            source_access,
+            destination_stages,
+            destination_access,
+            by_region,
+        )?;
+
+        unsafe {
+            self.inner.subpass_barrier(
+                source_stages,
+                source_access,
+                destination_stages,
+                destination_access,
+                by_region,
+            );
+        }
+
+        Ok(self)
+    }
+
+    /// Adds a pipeline barrier whose stages, accesses, subresource ranges and queue family
+    /// ownership transfers are described manually.
+    ///
+    /// The automatic synchronization trusts the barriers that cover a whole resource. If the next
+    /// command that uses one of these resources only accesses it within the destination scope of
+    /// its barrier, no other barrier is inserted. The barriers that only cover some of the
+    /// mipmap levels or array layers of an image, or a range of a buffer, aren't trusted, and the
+    /// next command that uses their resource waits for them. If the source stages of a barrier
+    /// don't include the previous use of its resource in the command buffer, the builder makes
+    /// the barrier wait for it.
+    ///
+    /// The `old_layout` of an image barrier must be either `Undefined`, which discards the content
+    /// of the image, or the current layout of the image. This isn't checked while
+    /// [`skip_validity_checks`](Self::skip_validity_checks) is enabled.
+    ///
+    /// An ownership transfer must release the resource from the queue family of the command
+    /// buffer, or acquire it for that family. The ownership of the resources isn't tracked: a
+    /// release must be followed by the matching acquire in a command buffer of the destination
+    /// family, which [`QueueHandoff`](crate::sync::QueueHandoff) takes care of, and the resource
+    /// must have been created with an exclusive sharing mode.
+    ///
+    /// See the documentation of [`BarrierBuilder`](crate::command_buffer::BarrierBuilder).
+    #[inline]
+    pub fn pipeline_barrier(
+        &mut self,
+        barrier: BarrierBuilder,
+    ) -> Result<&mut Self, PipelineBarrierError> {
+        self.ensure_outside_render_pass()?;
+        check_pipeline_barrier(self.device(), self.queue_family(), &barrier)?;
+        if !self.skip_validity_checks {
+            check_pipeline_barrier_layouts(&barrier, |image| self.inner.image_layout(image))?;
+        }
+
+        if barrier.is_empty() {
+            return Ok(self);
+        }
+
+        unsafe {
+            self.inner.pipeline_barrier(barrier);
+        }
+
+        Ok(self)
+    }
+
+    /// Adds a command that begins a query.
+    ///
+    /// The query will be active until [`end_query`](Self::end_query) is called for the same query.
+    ///
+    /// # Safety
+    /// The query must be unavailable, ensured by calling [`reset_query_pool`](Self::reset_query_pool).
+    pub unsafe fn begin_query(
+        &mut self,
+        query_pool: Arc<QueryPool>,
+        query: u32,
+        flags: QueryControlFlags,
+    ) -> Result<&mut Self, BeginQueryError> {
+        self.begin_query_indexed(query_pool, query, flags, 0)
+    }
+
+    /// Adds a command that begins a query for the vertex stream `index`.
+    ///
+    /// Only [`QueryType::TransformFeedbackStream`] queries can have an index other than 0. The
+    /// query will be active until [`end_query_indexed`](Self::end_query_indexed) is called for the
+    /// same query and index.
+    ///
+    /// For performance query pools, the profiling lock of the device must be held, see
+    /// [`Device::acquire_profiling_lock`](crate::device::Device::acquire_profiling_lock).
+    ///
+    /// # Safety
+    /// - The query must be unavailable, ensured by calling [`reset_query_pool`](Self::reset_query_pool).
+    /// - For performance query pools, the profiling lock must stay held until the command buffer
+    ///   has finished executing.
+    pub unsafe fn begin_query_indexed(
+        &mut self,
+        query_pool: Arc<QueryPool>,
         query: u32,
         flags: QueryControlFlags,
+        index: u32,
     ) -> Result<&mut Self, BeginQueryError> {
-        check_begin_query(self.device(), &query_pool, query, flags)?;
+        check_begin_query_indexed(self.device(), &query_pool, query, flags, index)?;
 
         match query_pool.ty() {
-            QueryType::Occlusion => {
+            QueryType::Occlusion | QueryType::TransformFeedbackStream => {
                 if !self.queue_family().supports_graphics() {
                     return Err(
                         AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into(),
@@ -1662,20 +2313,32 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
                     );
                 }
             }
+            QueryType::PerformanceQuery => {
+                if query_pool.performance_queue_family() != Some(self.queue_family()) {
+                    return Err(
+                        AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into(),
+                    );
+                }
+            }
             QueryType::Timestamp => unreachable!(),
         }
 
         let ty = query_pool.ty();
         let raw_ty = ty.into();
         let raw_query_pool = query_pool.internal_object();
-        if self.query_state.contains_key(&raw_ty) {
+        if self.query_state.contains_key(&(raw_ty, index)) {
             return Err(AutoCommandBufferBuilderContextError::QueryIsActive.into());
         }
 
         // TODO: validity checks
-        self.inner.begin_query(query_pool, query, flags);
+        if index == 0 {
+            self.inner.begin_query(query_pool, query, flags);
+        } else {
+            self.inner
+                .begin_query_indexed(query_pool, query, flags, index);
+        }
         self.query_state.insert(
-            raw_ty,
+            (raw_ty, index),
             QueryState {
                 query_pool: raw_query_pool,
                 query,
@@ -1689,24 +2352,43 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
     }
 
     /// Adds a command that ends an active query.
+    #[inline]
     pub fn end_query(
         &mut self,
         query_pool: Arc<QueryPool>,
         query: u32,
+    ) -> Result<&mut Self, EndQueryError> {
+        self.end_query_indexed(query_pool, query, 0)
+    }
+
+    /// Adds a command that ends an active query for the vertex stream `index`.
+    pub fn end_query_indexed(
+        &mut self,
+        query_pool: Arc<QueryPool>,
+        query: u32,
+        index: u32,
     ) -> Result<&mut Self, EndQueryError> {
         unsafe {
-            check_end_query(self.device(), &query_pool, query)?;
+            check_end_query_indexed(self.device(), &query_pool, query, index)?;
 
             let raw_ty = query_pool.ty().into();
             let raw_query_pool = query_pool.internal_object();
-            if !self.query_state.get(&raw_ty).map_or(false, |state| {
-                state.query_pool == raw_query_pool && state.query == query
-            }) {
+            if !self
+                .query_state
+                .get(&(raw_ty, index))
+                .map_or(false, |state| {
+                    state.query_pool == raw_query_pool && state.query == query
+                })
+            {
                 return Err(AutoCommandBufferBuilderContextError::QueryNotActive.into());
             }
 
-            self.inner.end_query(query_pool, query);
-            self.query_state.remove(&raw_ty);
+            if index == 0 {
+                self.inner.end_query(query_pool, query);
+            } else {
+                self.inner.end_query_indexed(query_pool, query, index);
+            }
+            self.query_state.remove(&(raw_ty, index));
         }
 
         Ok(self)
@@ -1750,6 +2432,10 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
     /// [`QueryResultFlags::with_availability`] is enabled.
     /// The provided buffer must be large enough to hold the data.
     ///
+    /// If [`QueryResultFlags::with_availability`] is enabled, the results can be split with
+    /// [`QueryType::split_available_results`](crate::query::QueryType::split_available_results)
+    /// once they have been read back.
+    ///
     /// See also [`get_results`](crate::query::QueriesRange::get_results).
     pub fn copy_query_pool_results<D, T>(
         &mut self,
@@ -1832,6 +2518,38 @@ where
         contents: SubpassContents,
         clear_values: I,
     ) -> Result<&mut Self, BeginRenderPassError>
+    where
+        F: FramebufferAbstract + Clone + Send + Sync + 'static,
+        I: IntoIterator<Item = ClearValue>,
+    {
+        let [width, height, _] = framebuffer.dimensions();
+        self.begin_render_pass_with_area(
+            framebuffer,
+            contents,
+            clear_values,
+            [0, 0],
+            [width, height],
+        )
+    }
+
+    /// Adds a command that enters a render pass, only affecting part of the framebuffer.
+    ///
+    /// This is the same as `begin_render_pass`, except that only the pixels inside the render
+    /// area starting at `render_area_offset` with the size `render_area_extent` are affected by
+    /// the render pass. Content outside of the render area is undefined after the render pass if
+    /// it is written to, so draw commands must be constrained with viewports or scissors.
+    ///
+    /// The render area must be inside the framebuffer. For optimal performance, it should also
+    /// be aligned to the render pass granularity, which can be checked with
+    /// `RenderPass::is_render_area_optimal`.
+    pub fn begin_render_pass_with_area<F, I>(
+        &mut self,
+        framebuffer: F,
+        contents: SubpassContents,
+        clear_values: I,
+        render_area_offset: [u32; 2],
+        render_area_extent: [u32; 2],
+    ) -> Result<&mut Self, BeginRenderPassError>
     where
         F: FramebufferAbstract + Clone + Send + Sync + 'static,
         I: IntoIterator<Item = ClearValue>,
@@ -1842,6 +2560,7 @@ where
             }
 
             self.ensure_outside_render_pass()?;
+            check_render_area(&framebuffer, render_area_offset, render_area_extent)?;
 
             let clear_values = framebuffer
                 .render_pass()
@@ -1920,8 +2639,13 @@ where
             }
 
             let framebuffer_object = FramebufferAbstract::inner(&framebuffer).internal_object();
-            self.inner
-                .begin_render_pass(framebuffer.clone(), contents, clear_values)?;
+            self.inner.begin_render_pass(
+                framebuffer.clone(),
+                contents,
+                clear_values,
+                render_area_offset,
+                render_area_extent,
+            )?;
             self.render_pass_state = Some(RenderPassState {
                 subpass: (framebuffer.render_pass().clone(), 0),
                 contents,
@@ -2096,8 +2820,7 @@ where
         if !render_pass
             .subpass
             .render_pass()
-            .desc()
-            .is_compatible_with_desc(render_pass_state.subpass.0.desc())
+            .is_compatible_with(&render_pass_state.subpass.0)
         {
             return Err(AutoCommandBufferBuilderContextError::IncompatibleRenderPass);
         }
@@ -2168,6 +2891,77 @@ unsafe impl<L, P> DeviceOwned for AutoCommandBufferBuilder<L, P> {
 }
 
 // Shortcut function to set the push constants.
+// Checks that a draw command doesn't use more vertices, indices or instances than the vertex
+// buffers, the index buffer and the multiview instance limit allow. `vertices` is the first
+// vertex and the number of vertices of a non-indexed draw, and `indices` the first index, the
+// number of indices and the number of indices in the index buffer of an indexed draw.
+fn check_draw_ranges<'a>(
+    pipeline: &GraphicsPipeline,
+    vertex_buffers: impl IntoIterator<Item = &'a dyn BufferAccess>,
+    vertices: Option<(u32, u32)>,
+    indices: Option<(u32, u32, u32)>,
+    first_instance: u32,
+    instance_count: u32,
+) -> Result<(), CheckVertexBufferError> {
+    let (max_vertex_count, max_instance_count) = pipeline.vertex_input().max_vertices_instances(
+        vertex_buffers
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| (i as u32, v)),
+    );
+
+    if let Some((first_vertex, vertex_count)) = vertices {
+        if first_vertex as u64 + vertex_count as u64 > max_vertex_count as u64 {
+            return Err(CheckVertexBufferError::TooManyVertices {
+                vertex_count,
+                max_vertex_count,
+            });
+        }
+    }
+
+    if let Some((first_index, index_count, max_index_count)) = indices {
+        if first_index as u64 + index_count as u64 > max_index_count as u64 {
+            return Err(CheckVertexBufferError::TooManyIndices {
+                index_count,
+                max_index_count,
+            });
+        }
+    }
+
+    let instance_end = first_instance as u64 + instance_count as u64;
+
+    if instance_end > max_instance_count as u64 {
+        return Err(CheckVertexBufferError::TooManyInstances {
+            instance_count,
+            max_instance_count,
+        });
+    }
+
+    if pipeline
+        .subpass()
+        .render_pass()
+        .desc()
+        .multiview()
+        .is_some()
+    {
+        let max_instance_index = pipeline
+            .device()
+            .physical_device()
+            .properties()
+            .max_multiview_instance_index
+            .unwrap_or(0);
+
+        if instance_end > max_instance_index as u64 + 1 {
+            return Err(CheckVertexBufferError::TooManyInstances {
+                instance_count,
+                max_instance_count: max_instance_index.saturating_add(1),
+            });
+        }
+    }
+
+    Ok(())
+}
+
 unsafe fn set_push_constants<Pc>(
     destination: &mut SyncCommandBufferBuilder,
     pipeline_layout: &Arc<PipelineLayout>,
@@ -2228,7 +3022,7 @@ unsafe fn set_state(destination: &mut SyncCommandBufferBuilder, dynamic: &Dynami
 unsafe fn bind_vertex_buffers(
     destination: &mut SyncCommandBufferBuilder,
     state_cacher: &mut StateCacher,
-    vertex_buffers: Vec<Box<dyn BufferAccess + Send + Sync>>,
+    vertex_buffers: SmallVec<[Box<dyn BufferAccess + Send + Sync>; 4]>,
 ) -> Result<(), SyncCommandBufferBuilderError> {
     let binding_range = {
         let mut compare = state_cacher.bind_vertex_buffers();
@@ -2261,7 +3055,7 @@ unsafe fn bind_descriptor_sets(
     state_cacher: &mut StateCacher,
     pipeline_bind_point: PipelineBindPoint,
     pipeline_layout: &Arc<PipelineLayout>,
-    descriptor_sets: Vec<DescriptorSetWithOffsets>,
+    descriptor_sets: SmallVec<[DescriptorSetWithOffsets; 12]>,
 ) -> Result<(), SyncCommandBufferBuilderError> {
     let first_binding = {
         let mut compare = state_cacher.bind_descriptor_sets(pipeline_bind_point);
@@ -2284,6 +3078,60 @@ unsafe fn bind_descriptor_sets(
     Ok(())
 }
 
+// Shortcut function to bind registered descriptor sets and vertex buffers to the graphics
+// pipeline. Only the sets and buffers that aren't already bound are cloned.
+unsafe fn bind_registered_resources(
+    destination: &mut SyncCommandBufferBuilder,
+    state_cacher: &mut StateCacher,
+    registered: &RegisteredResources,
+    pipeline_layout: &Arc<PipelineLayout>,
+    vertex_buffers: &[VertexBufferId],
+    descriptor_sets: &[DescriptorSetId],
+) -> Result<(), SyncCommandBufferBuilderError> {
+    let first_binding = {
+        let mut compare = state_cacher.bind_descriptor_sets(PipelineBindPoint::Graphics);
+        for &id in descriptor_sets {
+            compare.add(&registered.descriptor_set(id).with_offsets);
+        }
+        compare.compare()
+    };
+
+    if let Some(first_binding) = first_binding {
+        let mut sets_binder = destination.bind_descriptor_sets();
+        for &id in descriptor_sets.iter().skip(first_binding as usize) {
+            let descriptor_set = registered.descriptor_set(id);
+            let dynamic_offsets = descriptor_set.with_offsets.as_ref().1.iter().copied();
+            sets_binder.add(DescriptorSetWithOffsets::new(
+                descriptor_set.set.clone(),
+                dynamic_offsets,
+            ));
+        }
+        sets_binder.submit(
+            PipelineBindPoint::Graphics,
+            pipeline_layout.clone(),
+            first_binding,
+        )?;
+    }
+
+    let binding_range = {
+        let mut compare = state_cacher.bind_vertex_buffers();
+        for &id in vertex_buffers {
+            compare.add(&**registered.vertex_buffer(id));
+        }
+        compare.compare()
+    };
+
+    if let Some(binding_range) = binding_range {
+        let mut binder = destination.bind_vertex_buffers();
+        for &id in &vertex_buffers[binding_range.start as usize..binding_range.end as usize] {
+            binder.add(registered.vertex_buffer(id).clone());
+        }
+        binder.submit(binding_range.start)?;
+    }
+
+    Ok(())
+}
+
 pub struct PrimaryAutoCommandBuffer<P = StandardCommandPoolAlloc> {
     inner: SyncCommandBuffer,
     pool_alloc: P, // Safety: must be dropped after `inner`
@@ -2299,6 +3147,13 @@ unsafe impl<P> DeviceOwned for PrimaryAutoCommandBuffer<P> {
     }
 }
 
+impl<P> DebugName for PrimaryAutoCommandBuffer<P> {
+    #[inline]
+    fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        self.inner.as_ref().set_debug_name(name)
+    }
+}
+
 unsafe impl<P> PrimaryCommandBuffer for PrimaryAutoCommandBuffer<P> {
     #[inline]
     fn inner(&self) -> &UnsafeCommandBuffer {
@@ -2408,6 +3263,13 @@ unsafe impl<P> DeviceOwned for SecondaryAutoCommandBuffer<P> {
     }
 }
 
+impl<P> DebugName for SecondaryAutoCommandBuffer<P> {
+    #[inline]
+    fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        self.inner.as_ref().set_debug_name(name)
+    }
+}
+
 unsafe impl<P> SecondaryCommandBuffer for SecondaryAutoCommandBuffer<P> {
     #[inline]
     fn inner(&self) -> &UnsafeCommandBuffer {
@@ -2573,6 +3435,7 @@ err_gen!(BuildError {
 
 err_gen!(BeginRenderPassError {
     AutoCommandBufferBuilderContextError,
+    CheckRenderAreaError,
     SyncCommandBufferBuilderError,
 });
 
@@ -2678,6 +3541,17 @@ err_gen!(DrawIndexedIndirectError {
     SyncCommandBufferBuilderError,
 });
 
+err_gen!(DrawIndexedIndirectCountError {
+    AutoCommandBufferBuilderContextError,
+    CheckDynamicStateValidityError,
+    CheckPushConstantsValidityError,
+    CheckDescriptorSetsValidityError,
+    CheckVertexBufferError,
+    CheckIndexBufferError,
+    CheckIndirectBufferError,
+    SyncCommandBufferBuilderError,
+});
+
 err_gen!(ExecuteCommandsError {
     AutoCommandBufferBuilderContextError,
     SyncCommandBufferBuilderError,
@@ -2703,6 +3577,16 @@ err_gen!(ResetQueryPoolError {
     CheckResetQueryPoolError,
 });
 
+err_gen!(PipelineBarrierError {
+    AutoCommandBufferBuilderContextError,
+    CheckPipelineBarrierError,
+});
+
+err_gen!(SubpassBarrierError {
+    AutoCommandBufferBuilderContextError,
+    CheckSubpassBarrierError,
+});
+
 err_gen!(UpdateBufferError {
     AutoCommandBufferBuilderContextError,
     CheckUpdateBufferError,
@@ -2802,16 +3686,36 @@ mod tests {
     use crate::buffer::BufferUsage;
     use crate::buffer::CpuAccessibleBuffer;
     use crate::command_buffer::synced::SyncCommandBufferBuilderError;
+    use crate::command_buffer::validity::CheckIndirectBufferError;
+    use crate::command_buffer::validity::CheckPipelineBarrierError;
+    use crate::command_buffer::validity::CheckVertexBufferError;
     use crate::command_buffer::AutoCommandBufferBuilder;
+    use crate::command_buffer::BarrierBuilder;
+    use crate::command_buffer::BufferMemoryBarrier;
     use crate::command_buffer::CommandBufferExecError;
     use crate::command_buffer::CommandBufferUsage;
+    use crate::command_buffer::DrawError;
+    use crate::command_buffer::DrawIndexedIndirectCommand;
+    use crate::command_buffer::DrawIndexedIndirectCountError;
+    use crate::command_buffer::DynamicState;
     use crate::command_buffer::ExecuteCommandsError;
+    use crate::command_buffer::ImageMemoryBarrier;
+    use crate::command_buffer::PipelineBarrierError;
     use crate::command_buffer::PrimaryCommandBuffer;
+    use crate::debug_draw::DebugVertex;
     use crate::device::physical::PhysicalDevice;
     use crate::device::Device;
     use crate::device::DeviceExtensions;
     use crate::device::Features;
+    use crate::format::Format;
+    use crate::image::ImageDimensions;
+    use crate::image::ImageLayout;
+    use crate::image::StorageImage;
+    use crate::sync::AccessFlags;
     use crate::sync::GpuFuture;
+    use crate::sync::PipelineStages;
+    use crate::tests::begin_debug_draw_pass;
+    use crate::tests::debug_draw_pipeline;
     use std::sync::Arc;
 
     #[test]
@@ -2878,6 +3782,146 @@ mod tests {
         assert_eq!(*result, [0_u32, 1, 2, 3, 4]);
     }
 
+    #[test]
+    fn manual_pipeline_barrier() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let source = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::all(),
+            true,
+            [0_u32; 4].iter().copied(),
+        )
+        .unwrap();
+        let destination = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::all(),
+            true,
+            [0_u32; 4].iter().copied(),
+        )
+        .unwrap();
+
+        let mut cbb = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        let transfer = PipelineStages {
+            transfer: true,
+            ..PipelineStages::none()
+        };
+        let mut barrier = BarrierBuilder::new();
+        barrier.add_buffer(
+            source.clone(),
+            BufferMemoryBarrier {
+                source_stages: transfer,
+                source_access: AccessFlags {
+                    transfer_write: true,
+                    ..AccessFlags::none()
+                },
+                destination_stages: transfer,
+                destination_access: AccessFlags {
+                    transfer_read: true,
+                    ..AccessFlags::none()
+                },
+                ..Default::default()
+            },
+        );
+
+        cbb.fill_buffer(source.clone(), 7)
+            .unwrap()
+            .pipeline_barrier(barrier)
+            .unwrap()
+            .copy_buffer(source.clone(), destination.clone())
+            .unwrap();
+
+        let cb = cbb.build().unwrap();
+
+        let future = cb
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap();
+        future.wait(None).unwrap();
+
+        let result = destination.read().unwrap();
+
+        assert_eq!(*result, [7_u32; 4]);
+    }
+
+    #[test]
+    fn pipeline_barrier_old_layout() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = StorageImage::new(
+            device.clone(),
+            ImageDimensions::Dim2d {
+                width: 4,
+                height: 4,
+                array_layers: 1,
+            },
+            Format::R8G8B8A8Unorm,
+            Some(queue.family()),
+        )
+        .unwrap();
+
+        let barrier = |old_layout, new_layout| {
+            let transfer = PipelineStages {
+                transfer: true,
+                ..PipelineStages::none()
+            };
+            let mut barrier = BarrierBuilder::new();
+            barrier.add_image(
+                image.clone(),
+                ImageMemoryBarrier {
+                    source_stages: transfer,
+                    destination_stages: transfer,
+                    old_layout,
+                    new_layout,
+                    ..Default::default()
+                },
+            );
+            barrier
+        };
+
+        let mut cbb = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        cbb.pipeline_barrier(barrier(
+            ImageLayout::Undefined,
+            ImageLayout::TransferDstOptimal,
+        ))
+        .unwrap();
+        match cbb.pipeline_barrier(barrier(ImageLayout::General, ImageLayout::General)) {
+            Err(PipelineBarrierError::CheckPipelineBarrierError(
+                CheckPipelineBarrierError::OldLayoutMismatch,
+            )) => (),
+            _ => panic!(),
+        }
+        cbb.pipeline_barrier(barrier(
+            ImageLayout::TransferDstOptimal,
+            ImageLayout::General,
+        ))
+        .unwrap();
+
+        // The old layout isn't checked while the validity checks are skipped.
+        unsafe {
+            cbb.skip_validity_checks(true);
+        }
+        assert!(cbb
+            .pipeline_barrier(barrier(
+                ImageLayout::TransferSrcOptimal,
+                ImageLayout::General
+            ))
+            .is_ok());
+    }
+
     #[test]
     fn secondary_nonconcurrent_conflict() {
         let (device, queue) = gfx_dev_and_queue!();
@@ -2948,4 +3992,192 @@ mod tests {
             builder.execute_commands(secondary.clone()).unwrap();
         }
     }
+
+    #[test]
+    fn register_vertex_buffer_missing_usage() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        let buffer = CpuAccessibleBuffer::from_data(
+            device.clone(),
+            BufferUsage::transfer_source(),
+            false,
+            0u32,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            builder.register_vertex_buffer(buffer),
+            Err(CheckVertexBufferError::BufferMissingUsage { num_buffer: 0 })
+        ));
+    }
+
+    #[test]
+    fn draw_registered() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let vertices = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::vertex_buffer(),
+            false,
+            [DebugVertex::default(); 2].iter().cloned(),
+        )
+        .unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device,
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        let (pipeline, dynamic) = begin_debug_draw_pass(&mut builder);
+        let pipeline = builder.register_graphics_pipeline(pipeline);
+        let vertex_buffer = builder.register_vertex_buffer(vertices).unwrap();
+
+        assert!(matches!(
+            builder.draw_registered(
+                3,
+                1,
+                0,
+                0,
+                pipeline,
+                &dynamic,
+                &[vertex_buffer],
+                &[],
+                [[0.0f32; 4]; 4],
+            ),
+            Err(DrawError::CheckVertexBufferError(
+                CheckVertexBufferError::TooManyVertices { .. }
+            ))
+        ));
+        builder
+            .draw_registered(
+                2,
+                1,
+                0,
+                0,
+                pipeline,
+                &dynamic,
+                &[vertex_buffer],
+                &[],
+                [[0.0f32; 4]; 4],
+            )
+            .unwrap();
+        builder.end_render_pass().unwrap();
+        builder.build().unwrap();
+    }
+
+    #[test]
+    fn draw_indexed_indirect_count_not_enabled() {
+        // The feature and the extension aren't enabled by the macro.
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let vertices = CpuAccessibleBuffer::from_data(
+            device.clone(),
+            BufferUsage::vertex_buffer(),
+            false,
+            DebugVertex::default(),
+        )
+        .unwrap();
+        let indices = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::index_buffer(),
+            false,
+            [0u32, 0].iter().cloned(),
+        )
+        .unwrap();
+        let indirect = BufferUsage {
+            indirect_buffer: true,
+            ..BufferUsage::none()
+        };
+        let commands = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            indirect,
+            false,
+            [DrawIndexedIndirectCommand {
+                index_count: 2,
+                instance_count: 1,
+                first_index: 0,
+                vertex_offset: 0,
+                first_instance: 0,
+            }]
+            .iter()
+            .cloned(),
+        )
+        .unwrap();
+        let count = CpuAccessibleBuffer::from_data(device.clone(), indirect, false, 1u32).unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device,
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        let (pipeline, dynamic) = begin_debug_draw_pass(&mut builder);
+
+        assert!(matches!(
+            builder.draw_indexed_indirect_count(
+                pipeline,
+                &dynamic,
+                vec![vertices],
+                indices,
+                commands,
+                count,
+                (),
+                [[0.0f32; 4]; 4],
+            ),
+            Err(DrawIndexedIndirectCountError::CheckIndirectBufferError(
+                CheckIndirectBufferError::DrawIndirectCountNotEnabled
+            ))
+        ));
+    }
+
+    #[test]
+    fn draw_registered_foreign_identifier() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let (_, pipeline) = debug_draw_pipeline(device.clone());
+        let vertices = CpuAccessibleBuffer::from_data(
+            device.clone(),
+            BufferUsage::vertex_buffer(),
+            false,
+            DebugVertex::default(),
+        )
+        .unwrap();
+
+        let mut first = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        let mut second = AutoCommandBufferBuilder::primary(
+            device,
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        let pipeline = first.register_graphics_pipeline(pipeline);
+        let vertex_buffer = second.register_vertex_buffer(vertices).unwrap();
+
+        assert_should_panic!("another builder", {
+            let _ = second.draw_registered(
+                1,
+                1,
+                0,
+                0,
+                pipeline,
+                &DynamicState::none(),
+                &[vertex_buffer],
+                &[],
+                [[0.0f32; 4]; 4],
+            );
+        });
+    }
 }
@@ -0,0 +1,197 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::buffer::BufferAccess;
+use crate::image::ImageAccess;
+use crate::image::ImageLayout;
+use crate::sync::AccessFlags;
+use crate::sync::PipelineStages;
+use crate::DeviceSize;
+use std::fmt;
+use std::ops::Range;
+
+/// A set of memory barriers that are manually described, to be recorded with
+/// `AutoCommandBufferBuilder::pipeline_barrier`.
+///
+/// The automatic synchronization of `AutoCommandBufferBuilder` is conservative, and always
+/// synchronizes entire resources with the stages and accesses of the commands that use them.
+/// With a `BarrierBuilder`, you can instead describe the exact source and destination stages and
+/// accesses, subresource ranges, layout transitions and queue family ownership transfers.
+///
+/// The command buffer builder trusts these barriers: a command that comes after a barrier and that
+/// only uses a resource within the destination scope of that barrier doesn't get another
+/// automatic barrier. If the source stages of a barrier don't include the previous use of the
+/// resource that the builder knows about, the barrier is made to wait for that use as well.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use vulkano::buffer::BufferAccess;
+/// # let buffer: Arc<dyn BufferAccess + Send + Sync> = return;
+/// use vulkano::command_buffer::BarrierBuilder;
+/// use vulkano::command_buffer::BufferMemoryBarrier;
+/// use vulkano::sync::AccessFlags;
+/// use vulkano::sync::PipelineStages;
+///
+/// // Make the result of a compute shader visible to indirect draws.
+/// let mut barrier = BarrierBuilder::new();
+/// barrier.add_buffer(
+///     buffer.clone(),
+///     BufferMemoryBarrier {
+///         source_stages: PipelineStages {
+///             compute_shader: true,
+///             ..PipelineStages::none()
+///         },
+///         source_access: AccessFlags {
+///             shader_write: true,
+///             ..AccessFlags::none()
+///         },
+///         destination_stages: PipelineStages {
+///             draw_indirect: true,
+///             ..PipelineStages::none()
+///         },
+///         destination_access: AccessFlags {
+///             indirect_command_read: true,
+///             ..AccessFlags::none()
+///         },
+///         ..Default::default()
+///     },
+/// );
+/// ```
+#[derive(Default)]
+pub struct BarrierBuilder {
+    pub(crate) buffers: Vec<(Box<dyn BufferAccess + Send + Sync>, BufferMemoryBarrier)>,
+    pub(crate) images: Vec<(Box<dyn ImageAccess + Send + Sync>, ImageMemoryBarrier)>,
+}
+
+impl BarrierBuilder {
+    /// Builds a new empty `BarrierBuilder`.
+    #[inline]
+    pub fn new() -> BarrierBuilder {
+        BarrierBuilder::default()
+    }
+
+    /// Adds a barrier for a buffer.
+    #[inline]
+    pub fn add_buffer<B>(&mut self, buffer: B, barrier: BufferMemoryBarrier) -> &mut Self
+    where
+        B: BufferAccess + Send + Sync + 'static,
+    {
+        self.buffers.push((Box::new(buffer), barrier));
+        self
+    }
+
+    /// Adds a barrier for an image.
+    #[inline]
+    pub fn add_image<I>(&mut self, image: I, barrier: ImageMemoryBarrier) -> &mut Self
+    where
+        I: ImageAccess + Send + Sync + 'static,
+    {
+        self.images.push((Box::new(image), barrier));
+        self
+    }
+
+    /// Returns true if no barrier has been added.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty() && self.images.is_empty()
+    }
+}
+
+impl fmt::Debug for BarrierBuilder {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("BarrierBuilder")
+            .field(
+                "buffers",
+                &self.buffers.iter().map(|(_, b)| b).collect::<Vec<_>>(),
+            )
+            .field(
+                "images",
+                &self.images.iter().map(|(_, b)| b).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// Description of a memory barrier for a buffer.
+#[derive(Clone, Debug)]
+pub struct BufferMemoryBarrier {
+    /// The stages that must be finished before the barrier.
+    pub source_stages: PipelineStages,
+    /// The memory accesses of `source_stages` that must be made available.
+    pub source_access: AccessFlags,
+    /// The stages that must wait for the barrier.
+    pub destination_stages: PipelineStages,
+    /// The memory accesses of `destination_stages` that the writes must be made visible to.
+    pub destination_access: AccessFlags,
+    /// The range of the buffer in bytes, relative to the start of the buffer. `None` means the
+    /// whole buffer.
+    pub range: Option<Range<DeviceSize>>,
+    /// If `Some`, the ownership of the buffer is transferred from the first queue family index
+    /// to the second one. One of them must be the queue family of the command buffer.
+    pub queue_family_transfer: Option<(u32, u32)>,
+}
+
+impl Default for BufferMemoryBarrier {
+    #[inline]
+    fn default() -> BufferMemoryBarrier {
+        BufferMemoryBarrier {
+            source_stages: PipelineStages::none(),
+            source_access: AccessFlags::none(),
+            destination_stages: PipelineStages::none(),
+            destination_access: AccessFlags::none(),
+            range: None,
+            queue_family_transfer: None,
+        }
+    }
+}
+
+/// Description of a memory barrier for an image.
+#[derive(Clone, Debug)]
+pub struct ImageMemoryBarrier {
+    /// The stages that must be finished before the barrier.
+    pub source_stages: PipelineStages,
+    /// The memory accesses of `source_stages` that must be made available.
+    pub source_access: AccessFlags,
+    /// The stages that must wait for the barrier.
+    pub destination_stages: PipelineStages,
+    /// The memory accesses of `destination_stages` that the writes must be made visible to.
+    pub destination_access: AccessFlags,
+    /// The layout of the image before the barrier. `Undefined` discards the content of the image.
+    pub old_layout: ImageLayout,
+    /// The layout of the image after the barrier. If it is different from `old_layout`, the
+    /// barrier performs a layout transition and must cover the whole image.
+    pub new_layout: ImageLayout,
+    /// The mipmap levels of the image. `None` means all the levels.
+    pub mipmap_levels: Option<Range<u32>>,
+    /// The array layers of the image. `None` means all the layers.
+    pub array_layers: Option<Range<u32>>,
+    /// If `Some`, the ownership of the image is transferred from the first queue family index
+    /// to the second one. One of them must be the queue family of the command buffer.
+    pub queue_family_transfer: Option<(u32, u32)>,
+}
+
+impl Default for ImageMemoryBarrier {
+    #[inline]
+    fn default() -> ImageMemoryBarrier {
+        ImageMemoryBarrier {
+            source_stages: PipelineStages::none(),
+            source_access: AccessFlags::none(),
+            destination_stages: PipelineStages::none(),
+            destination_access: AccessFlags::none(),
+            old_layout: ImageLayout::General,
+            new_layout: ImageLayout::General,
+            mipmap_levels: None,
+            array_layers: None,
+            queue_family_transfer: None,
+        }
+    }
+}
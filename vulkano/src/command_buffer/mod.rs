@@ -93,16 +93,26 @@ pub use self::auto::DispatchError;
 pub use self::auto::DispatchIndirectError;
 pub use self::auto::DrawError;
 pub use self::auto::DrawIndexedError;
+pub use self::auto::DrawIndexedIndirectCountError;
 pub use self::auto::DrawIndexedIndirectError;
 pub use self::auto::DrawIndirectError;
 pub use self::auto::EndQueryError;
 pub use self::auto::ExecuteCommandsError;
 pub use self::auto::FillBufferError;
+pub use self::auto::PipelineBarrierError;
 pub use self::auto::PrimaryAutoCommandBuffer;
 pub use self::auto::ResetQueryPoolError;
 pub use self::auto::SecondaryAutoCommandBuffer;
+pub use self::auto::SubpassBarrierError;
 pub use self::auto::UpdateBufferError;
 pub use self::auto::WriteTimestampError;
+pub use self::barrier::BarrierBuilder;
+pub use self::barrier::BufferMemoryBarrier;
+pub use self::barrier::ImageMemoryBarrier;
+pub use self::registered::DescriptorSetId;
+pub use self::registered::GraphicsPipelineId;
+pub use self::registered::IndexBufferId;
+pub use self::registered::VertexBufferId;
 pub use self::state_cacher::StateCacher;
 pub use self::state_cacher::StateCacherOutcome;
 pub use self::traits::CommandBufferExecError;
@@ -117,7 +127,9 @@ use crate::render_pass::{Framebuffer, Subpass};
 use std::sync::Arc;
 
 mod auto;
+mod barrier;
 pub mod pool;
+mod registered;
 mod state_cacher;
 pub mod submit;
 pub mod synced;
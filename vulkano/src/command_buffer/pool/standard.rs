@@ -14,6 +14,7 @@ use std::mem::ManuallyDrop;
 use std::ptr;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::sync::RwLock;
 use std::sync::Weak;
 use std::thread;
 use std::vec::IntoIter as VecIntoIter;
@@ -23,6 +24,7 @@ use crate::command_buffer::pool::CommandPoolAlloc;
 use crate::command_buffer::pool::CommandPoolBuilderAlloc;
 use crate::command_buffer::pool::UnsafeCommandPool;
 use crate::command_buffer::pool::UnsafeCommandPoolAlloc;
+use crate::command_buffer::synced::CommandArenaCache;
 use crate::device::physical::QueueFamily;
 
 use crate::device::Device;
@@ -46,7 +48,10 @@ pub struct StandardCommandPool {
     queue_family: u32,
 
     // For each thread, we store thread-specific info.
-    per_thread: Mutex<FnvHashMap<thread::ThreadId, Weak<StandardCommandPoolPerThread>>>,
+    per_thread: RwLock<FnvHashMap<thread::ThreadId, Weak<StandardCommandPoolPerThread>>>,
+
+    // Memory for the commands of the command buffers, reused once they are destroyed.
+    arena_cache: Arc<CommandArenaCache>,
 }
 
 unsafe impl Send for StandardCommandPool {}
@@ -77,9 +82,16 @@ impl StandardCommandPool {
         StandardCommandPool {
             device: device,
             queue_family: queue_family.id(),
-            per_thread: Mutex::new(Default::default()),
+            per_thread: RwLock::new(Default::default()),
+            arena_cache: Arc::new(CommandArenaCache::default()),
         }
     }
+
+    // Returns the memory for the commands of the command buffers allocated from this pool.
+    #[inline]
+    pub(crate) fn arena_cache(&self) -> &Arc<CommandArenaCache> {
+        &self.arena_cache
+    }
 }
 
 unsafe impl CommandPool for Arc<StandardCommandPool> {
@@ -88,17 +100,27 @@ unsafe impl CommandPool for Arc<StandardCommandPool> {
     type Alloc = StandardCommandPoolAlloc;
 
     fn alloc(&self, secondary: bool, count: u32) -> Result<Self::Iter, OomError> {
-        // Find the correct `StandardCommandPoolPerThread` structure.
-        let mut hashmap = self.per_thread.lock().unwrap();
-        // TODO: meh for iterating everything every time
-        hashmap.retain(|_, w| w.upgrade().is_some());
-
         let this_thread = thread::current().id();
 
+        // Find the correct `StandardCommandPoolPerThread` structure. The threads that record
+        // command buffers only share a read lock, unless this thread doesn't have a pool yet.
+        let existing = self
+            .per_thread
+            .read()
+            .unwrap()
+            .get(&this_thread)
+            .and_then(Weak::upgrade);
+
         // Get an appropriate `Arc<StandardCommandPoolPerThread>`.
-        let per_thread = if let Some(entry) = hashmap.get(&this_thread).and_then(Weak::upgrade) {
+        let per_thread = if let Some(entry) = existing {
             entry
         } else {
+            let mut hashmap = self.per_thread.write().unwrap();
+
+            // Only remove the entries of the pools that were destroyed when adding a new one,
+            // instead of iterating over all the entries at each allocation.
+            hashmap.retain(|_, w| w.upgrade().is_some());
+
             let new_pool =
                 UnsafeCommandPool::new(self.device.clone(), self.queue_family(), false, true)?;
             let pt = Arc::new(StandardCommandPoolPerThread {
@@ -0,0 +1,183 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Resources that are registered with a command buffer builder, and bound by identifier.
+//!
+//! Binding resources with `draw` or `draw_indexed` clones and type-erases every pipeline,
+//! descriptor set and buffer at each call. Resources that are used by many draws can instead be
+//! registered once with `AutoCommandBufferBuilder::register_*`, which returns a lightweight
+//! identifier, and then be bound with `draw_registered` or `draw_indexed_registered`. The
+//! builder then only clones a resource when it actually needs to bind it, in other words when
+//! it isn't already bound.
+
+use crate::buffer::BufferAccess;
+use crate::descriptor_set::layout::DescriptorSetLayout;
+use crate::descriptor_set::DescriptorSet;
+use crate::descriptor_set::DescriptorSetWithOffsets;
+use crate::pipeline::input_assembly::IndexType;
+use crate::pipeline::GraphicsPipeline;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+// Identifier of the next registry, so that identifiers can't be used with another builder.
+static NEXT_REGISTRY_ID: AtomicU64 = AtomicU64::new(0);
+
+macro_rules! registered_id {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        ///
+        /// It's only valid for the builder that returned it.
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+        pub struct $name {
+            registry: u64,
+            index: u32,
+        }
+    };
+}
+
+registered_id!(
+    /// Identifier of a graphics pipeline that was registered with
+    /// `AutoCommandBufferBuilder::register_graphics_pipeline`.
+    GraphicsPipelineId
+);
+
+registered_id!(
+    /// Identifier of a descriptor set that was registered with
+    /// `AutoCommandBufferBuilder::register_descriptor_set`.
+    DescriptorSetId
+);
+
+registered_id!(
+    /// Identifier of a vertex buffer that was registered with
+    /// `AutoCommandBufferBuilder::register_vertex_buffer`.
+    VertexBufferId
+);
+
+registered_id!(
+    /// Identifier of an index buffer that was registered with
+    /// `AutoCommandBufferBuilder::register_index_buffer`.
+    IndexBufferId
+);
+
+pub(crate) struct RegisteredDescriptorSet {
+    // The set, kept in an `Arc` so that binding it doesn't require cloning the set itself.
+    pub(crate) set: Arc<dyn DescriptorSet + Send + Sync>,
+    // The same set with its dynamic offsets, which were checked at registration.
+    pub(crate) with_offsets: DescriptorSetWithOffsets,
+}
+
+pub(crate) struct RegisteredIndexBuffer {
+    pub(crate) buffer: Arc<dyn BufferAccess + Send + Sync>,
+    pub(crate) ty: IndexType,
+    pub(crate) len: u32,
+}
+
+// The resources registered with a builder.
+pub(crate) struct RegisteredResources {
+    id: u64,
+    graphics_pipelines: Vec<Arc<GraphicsPipeline>>,
+    descriptor_sets: Vec<RegisteredDescriptorSet>,
+    vertex_buffers: Vec<Arc<dyn BufferAccess + Send + Sync>>,
+    index_buffers: Vec<RegisteredIndexBuffer>,
+}
+
+macro_rules! registered_accessors {
+    ($push:ident, $get:ident, $field:ident, $id:ident, $ty:ty) => {
+        pub(crate) fn $push(&mut self, value: $ty) -> $id {
+            self.$field.push(value);
+            $id {
+                registry: self.id,
+                index: (self.$field.len() - 1) as u32,
+            }
+        }
+
+        // Panics if `id` was returned by another builder.
+        #[inline]
+        pub(crate) fn $get(&self, id: $id) -> &$ty {
+            assert_eq!(
+                id.registry, self.id,
+                "the identifier was registered with another builder"
+            );
+            &self.$field[id.index as usize]
+        }
+    };
+}
+
+impl RegisteredResources {
+    pub(crate) fn new() -> RegisteredResources {
+        RegisteredResources {
+            id: NEXT_REGISTRY_ID.fetch_add(1, Ordering::Relaxed),
+            graphics_pipelines: Vec::new(),
+            descriptor_sets: Vec::new(),
+            vertex_buffers: Vec::new(),
+            index_buffers: Vec::new(),
+        }
+    }
+
+    registered_accessors!(
+        push_graphics_pipeline,
+        graphics_pipeline,
+        graphics_pipelines,
+        GraphicsPipelineId,
+        Arc<GraphicsPipeline>
+    );
+    registered_accessors!(
+        push_descriptor_set,
+        descriptor_set,
+        descriptor_sets,
+        DescriptorSetId,
+        RegisteredDescriptorSet
+    );
+    registered_accessors!(
+        push_vertex_buffer,
+        vertex_buffer,
+        vertex_buffers,
+        VertexBufferId,
+        Arc<dyn BufferAccess + Send + Sync>
+    );
+    registered_accessors!(
+        push_index_buffer,
+        index_buffer,
+        index_buffers,
+        IndexBufferId,
+        RegisteredIndexBuffer
+    );
+
+    // Returns the layout of a registered descriptor set.
+    #[inline]
+    pub(crate) fn descriptor_set_layout(&self, id: DescriptorSetId) -> &Arc<DescriptorSetLayout> {
+        self.descriptor_set(id).with_offsets.as_ref().0.layout()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::command_buffer::registered::RegisteredResources;
+
+    #[test]
+    fn foreign_identifier() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let buffer =
+            CpuAccessibleBuffer::from_data(device, BufferUsage::all(), false, 0u32).unwrap();
+
+        let mut first = RegisteredResources::new();
+        let mut second = RegisteredResources::new();
+        let id = first.push_vertex_buffer(buffer.clone());
+        second.push_vertex_buffer(buffer);
+
+        first.vertex_buffer(id);
+        assert_should_panic!("another builder", {
+            second.vertex_buffer(id);
+        });
+    }
+}
@@ -25,12 +25,18 @@ use std::ops::Range;
 /// > **Important**: Executing a secondary command buffer invalidates the state of a command buffer
 /// > builder. When you do so, you need to call `invalidate()`.
 pub struct StateCacher {
+    // If false, the cache is invalidated before each operation.
+    enabled: bool,
     // The dynamic state to synchronize with `CmdSetState`.
     dynamic_state: DynamicState,
     // The compute pipeline currently bound. 0 if nothing bound.
     compute_pipeline: ash::vk::Pipeline,
     // The graphics pipeline currently bound. 0 if nothing bound.
     graphics_pipeline: ash::vk::Pipeline,
+    // The layouts of the pipelines currently bound. The descriptor sets that were bound with
+    // another layout may have been disturbed when the pipeline changed. 0 if nothing bound.
+    compute_pipeline_layout: ash::vk::PipelineLayout,
+    graphics_pipeline_layout: ash::vk::PipelineLayout,
     // The descriptor sets for the compute pipeline.
     compute_descriptor_sets: SmallVec<[(ash::vk::DescriptorSet, SmallVec<[u32; 32]>); 12]>,
     // The descriptor sets for the graphics pipeline.
@@ -62,9 +68,12 @@ impl StateCacher {
     #[inline]
     pub fn new() -> StateCacher {
         StateCacher {
+            enabled: true,
             dynamic_state: DynamicState::none(),
             compute_pipeline: ash::vk::Pipeline::null(),
             graphics_pipeline: ash::vk::Pipeline::null(),
+            compute_pipeline_layout: ash::vk::PipelineLayout::null(),
+            graphics_pipeline_layout: ash::vk::PipelineLayout::null(),
             compute_descriptor_sets: SmallVec::new(),
             graphics_descriptor_sets: SmallVec::new(),
             poisoned_descriptor_sets: false,
@@ -81,18 +90,45 @@ impl StateCacher {
         self.dynamic_state = DynamicState::none();
         self.compute_pipeline = ash::vk::Pipeline::null();
         self.graphics_pipeline = ash::vk::Pipeline::null();
+        self.compute_pipeline_layout = ash::vk::PipelineLayout::null();
+        self.graphics_pipeline_layout = ash::vk::PipelineLayout::null();
         self.compute_descriptor_sets = SmallVec::new();
         self.graphics_descriptor_sets = SmallVec::new();
         self.vertex_buffers = SmallVec::new();
         self.index_buffer = None;
     }
 
+    /// Enables or disables the cache, which is enabled by default.
+    ///
+    /// While the cache is disabled, every state change is reported as necessary, so that a
+    /// command buffer builder records exactly the state changes that it is asked for.
+    #[inline]
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.invalidate();
+    }
+
+    /// Returns true if the cache is enabled. See `set_enabled`.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // Forgets the cached state if the cache is disabled.
+    #[inline]
+    fn invalidate_if_disabled(&mut self) {
+        if !self.enabled {
+            self.invalidate();
+        }
+    }
+
     /// Compares the current state with `incoming`, and returns a new state that contains the
     /// states that differ and that need to be actually set in the command buffer builder.
     ///
     /// This function also updates the state cacher. The state cacher assumes that the state
     /// changes are going to be performed after this function returns.
     pub fn dynamic_state(&mut self, incoming: &DynamicState) -> DynamicState {
+        self.invalidate_if_disabled();
         let mut changed = DynamicState::none();
 
         macro_rules! cmp {
@@ -130,6 +166,8 @@ impl StateCacher {
         &mut self,
         pipeline_bind_point: PipelineBindPoint,
     ) -> StateCacherDescriptorSets {
+        self.invalidate_if_disabled();
+
         if self.poisoned_descriptor_sets {
             self.compute_descriptor_sets = SmallVec::new();
             self.graphics_descriptor_sets = SmallVec::new();
@@ -155,6 +193,14 @@ impl StateCacher {
     /// This function also updates the state cacher. The state cacher assumes that the state
     /// changes are going to be performed after this function returns.
     pub fn bind_graphics_pipeline(&mut self, pipeline: &GraphicsPipeline) -> StateCacherOutcome {
+        self.invalidate_if_disabled();
+
+        let layout = pipeline.layout().internal_object();
+        if layout != self.graphics_pipeline_layout {
+            self.graphics_pipeline_layout = layout;
+            self.graphics_descriptor_sets = SmallVec::new();
+        }
+
         let inner = pipeline.internal_object();
         if inner == self.graphics_pipeline {
             StateCacherOutcome::AlreadyOk
@@ -171,6 +217,14 @@ impl StateCacher {
     /// This function also updates the state cacher. The state cacher assumes that the state
     /// changes are going to be performed after this function returns.
     pub fn bind_compute_pipeline(&mut self, pipeline: &ComputePipeline) -> StateCacherOutcome {
+        self.invalidate_if_disabled();
+
+        let layout = pipeline.layout().internal_object();
+        if layout != self.compute_pipeline_layout {
+            self.compute_pipeline_layout = layout;
+            self.compute_descriptor_sets = SmallVec::new();
+        }
+
         let inner = pipeline.internal_object();
         if inner == self.compute_pipeline {
             StateCacherOutcome::AlreadyOk
@@ -191,6 +245,8 @@ impl StateCacher {
     /// changes are going to be performed after the `compare` function returns.
     #[inline]
     pub fn bind_vertex_buffers(&mut self) -> StateCacherVertexBuffers {
+        self.invalidate_if_disabled();
+
         if self.poisoned_vertex_buffers {
             self.vertex_buffers = SmallVec::new();
         }
@@ -216,6 +272,8 @@ impl StateCacher {
     where
         B: ?Sized + BufferAccess,
     {
+        self.invalidate_if_disabled();
+
         let value = {
             let inner = index_buffer.inner();
             (inner.buffer.internal_object(), inner.offset, ty)
@@ -475,4 +533,37 @@ mod tests {
             assert_eq!(bind_vb.compare(), Some(0..1));
         }
     }
+
+    #[test]
+    fn disabled_cache() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        const EMPTY: [i32; 0] = [];
+        let buf =
+            CpuAccessibleBuffer::from_data(device, BufferUsage::vertex_buffer(), false, EMPTY)
+                .unwrap();
+
+        let mut cacher = StateCacher::new();
+        cacher.set_enabled(false);
+
+        for _ in 0..3 {
+            let mut bind_vb = cacher.bind_vertex_buffers();
+            bind_vb.add(&buf);
+            assert_eq!(bind_vb.compare(), Some(0..1));
+        }
+
+        cacher.set_enabled(true);
+
+        {
+            let mut bind_vb = cacher.bind_vertex_buffers();
+            bind_vb.add(&buf);
+            assert_eq!(bind_vb.compare(), Some(0..1));
+        }
+
+        {
+            let mut bind_vb = cacher.bind_vertex_buffers();
+            bind_vb.add(&buf);
+            assert_eq!(bind_vb.compare(), None);
+        }
+    }
 }
@@ -10,6 +10,7 @@
 use crate::buffer::sys::UnsafeBuffer;
 use crate::check_errors;
 use crate::device::Queue;
+use crate::device::QueueGuard;
 use crate::image::sys::UnsafeImage;
 use crate::memory::DeviceMemory;
 use crate::sync::Fence;
@@ -17,7 +18,6 @@ use crate::sync::Semaphore;
 use crate::DeviceSize;
 use crate::Error;
 use crate::OomError;
-use crate::SynchronizedVulkanObject;
 use crate::VulkanObject;
 use smallvec::SmallVec;
 use std::error;
@@ -135,13 +135,40 @@ impl<'a> SubmitBindSparseBuilder<'a> {
         Ok(())
     }
 
+    /// Adds a semaphore that is going to be signaled once all the sparse binding operations of
+    /// the submission have been executed.
+    ///
+    /// The semaphore is signaled by the last batch, whose signal operations include the sparse
+    /// binding operations of all the previous batches. A batch is added if there is none.
+    ///
+    /// # Safety
+    ///
+    /// Same as `SubmitBindSparseBatchBuilder::add_signal_semaphore`.
+    ///
+    #[inline]
+    pub unsafe fn add_signal_semaphore(&mut self, semaphore: &'a Semaphore) {
+        if self.infos.is_empty() {
+            self.infos.push(SubmitBindSparseBatchBuilder::new());
+        }
+
+        self.infos
+            .last_mut()
+            .unwrap()
+            .add_signal_semaphore(semaphore);
+    }
+
     /// Submits the command. Calls `vkQueueBindSparse`.
+    #[inline]
     pub fn submit(self, queue: &Queue) -> Result<(), SubmitBindSparseError> {
+        self.submit_locked(&mut queue.lock())
+    }
+
+    /// Same as `submit`, but submits to a queue that is already locked.
+    pub fn submit_locked(self, queue: &mut QueueGuard) -> Result<(), SubmitBindSparseError> {
         unsafe {
-            debug_assert!(queue.family().supports_sparse_binding());
+            debug_assert!(queue.queue().family().supports_sparse_binding());
 
-            let fns = queue.device().fns();
-            let queue = queue.internal_object_guard();
+            let fns = queue.queue().device().fns();
 
             // We start by storing all the `VkSparseBufferMemoryBindInfo`s of the whole command
             // in the same collection.
@@ -240,7 +267,7 @@ impl<'a> SubmitBindSparseBuilder<'a> {
 
             // Finally executing the command.
             check_errors(fns.v1_0.queue_bind_sparse(
-                *queue,
+                queue.internal_object(),
                 bs_infos.len() as u32,
                 bs_infos.as_ptr(),
                 self.fence,
@@ -7,14 +7,20 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use smallvec::smallvec;
 use smallvec::SmallVec;
 use std::error;
+use std::ffi::c_void;
 use std::fmt;
 use std::marker::PhantomData;
 use std::ptr;
 
 use crate::device::DeviceOwned;
 use crate::device::Queue;
+use crate::device::QueueGuard;
+use crate::swapchain::display::DisplayPresentInfo;
+use crate::swapchain::display_timing::PresentTime;
+use crate::swapchain::DeviceGroupPresentMode;
 use crate::swapchain::PresentRegion;
 use crate::swapchain::Swapchain;
 use crate::sync::Semaphore;
@@ -22,7 +28,6 @@ use crate::sync::Semaphore;
 use crate::check_errors;
 use crate::Error;
 use crate::OomError;
-use crate::SynchronizedVulkanObject;
 use crate::VulkanObject;
 
 /// Prototype for a submission that presents a swapchain on the screen.
@@ -33,6 +38,11 @@ pub struct SubmitPresentBuilder<'a> {
     image_indices: SmallVec<[u32; 4]>,
     present_regions: SmallVec<[ash::vk::PresentRegionKHR; 4]>,
     rect_layers: SmallVec<[ash::vk::RectLayerKHR; 4]>,
+    display_present_info: Option<ash::vk::DisplayPresentInfoKHR>,
+    present_times: SmallVec<[ash::vk::PresentTimeGOOGLE; 4]>,
+    present_ids: SmallVec<[u64; 4]>,
+    device_masks: SmallVec<[u32; 4]>,
+    device_group_present_mode: Option<DeviceGroupPresentMode>,
     marker: PhantomData<&'a ()>,
 }
 
@@ -46,6 +56,11 @@ impl<'a> SubmitPresentBuilder<'a> {
             image_indices: SmallVec::new(),
             present_regions: SmallVec::new(),
             rect_layers: SmallVec::new(),
+            display_present_info: None,
+            present_times: SmallVec::new(),
+            present_ids: SmallVec::new(),
+            device_masks: SmallVec::new(),
+            device_group_present_mode: None,
             marker: PhantomData,
         }
     }
@@ -125,13 +140,97 @@ impl<'a> SubmitPresentBuilder<'a> {
         self.image_indices.push(image_num);
     }
 
+    /// Sets the regions of the images and of the displays to use when presenting to swapchains
+    /// created on display surfaces.
+    ///
+    /// # Safety
+    ///
+    /// - The `VK_KHR_display_swapchain` extension must be enabled on the device.
+    ///
+    #[inline]
+    pub unsafe fn set_display_present_info(&mut self, info: &DisplayPresentInfo) {
+        self.display_present_info = Some(info.into());
+    }
+
+    /// Sets the identifier and desired presentation time of the swapchain image that was added
+    /// last.
+    ///
+    /// # Safety
+    ///
+    /// - The `VK_GOOGLE_display_timing` extension must be enabled on the device.
+    ///
+    #[inline]
+    pub unsafe fn set_present_time(&mut self, present_time: PresentTime) {
+        debug_assert!(!self.swapchains.is_empty());
+        self.present_times
+            .resize(self.swapchains.len() - 1, PresentTime::default().into());
+        self.present_times.push(present_time.into());
+    }
+
+    /// Sets the present ID of the swapchain image that was added last.
+    ///
+    /// # Safety
+    ///
+    /// - The `present_id` feature must be enabled on the device.
+    /// - `present_id` must be greater than any present ID previously used with the same
+    ///   swapchain.
+    ///
+    #[inline]
+    pub unsafe fn set_present_id(&mut self, present_id: u64) {
+        debug_assert!(!self.swapchains.is_empty());
+        self.present_ids.resize(self.swapchains.len() - 1, 0);
+        self.present_ids.push(present_id);
+    }
+
+    /// Sets the physical devices of a device group that present the swapchain image that was
+    /// added last.
+    ///
+    /// # Safety
+    ///
+    /// - The device must support device groups.
+    /// - If a device mask is set, it must be set for all the swapchain images of the builder.
+    /// - `device_mask` must be valid for the device group present mode, see
+    ///   `set_device_group_present_mode`, and the physical devices must be able to present with
+    ///   this mode.
+    ///
+    #[inline]
+    pub unsafe fn set_device_mask(&mut self, device_mask: u32) {
+        debug_assert!(!self.swapchains.is_empty());
+        debug_assert_eq!(self.device_masks.len(), self.swapchains.len() - 1);
+        self.device_masks.push(device_mask);
+    }
+
+    /// Sets how the physical devices of a device group present the swapchain images. The default
+    /// is `Local`.
+    ///
+    /// # Safety
+    ///
+    /// - The device must support device groups.
+    /// - The swapchains must have been created with `mode` in their device group present modes.
+    ///
+    #[inline]
+    pub unsafe fn set_device_group_present_mode(&mut self, mode: DeviceGroupPresentMode) {
+        self.device_group_present_mode = Some(mode);
+    }
+
     /// Submits the command. Calls `vkQueuePresentKHR`.
     ///
     /// # Panic
     ///
     /// Panics if no swapchain image has been added to the builder.
     ///
-    pub fn submit(mut self, queue: &Queue) -> Result<(), SubmitPresentError> {
+    #[inline]
+    pub fn submit(self, queue: &Queue) -> Result<(), SubmitPresentError> {
+        self.submit_locked(&mut queue.lock())
+    }
+
+    /// Same as `submit`, but submits to a queue that is already locked.
+    ///
+    /// # Panic
+    ///
+    /// Panics if no swapchain image has been added to the builder.
+    ///
+    pub fn submit_locked(mut self, queue: &mut QueueGuard) -> Result<(), SubmitPresentError> {
         unsafe {
             debug_assert_eq!(self.swapchains.len(), self.image_indices.len());
             assert!(
@@ -139,9 +238,15 @@ impl<'a> SubmitPresentBuilder<'a> {
                 "Tried to submit a present command without any swapchain"
             );
 
-            let present_regions = {
+            let mut present_regions = {
                 if !self.present_regions.is_empty() {
-                    debug_assert!(queue.device().enabled_extensions().khr_incremental_present);
+                    debug_assert!(
+                        queue
+                            .queue()
+                            .device()
+                            .enabled_extensions()
+                            .khr_incremental_present
+                    );
                     debug_assert_eq!(self.swapchains.len(), self.present_regions.len());
                     let mut current_index = 0;
                     for present_region in &mut self.present_regions {
@@ -158,16 +263,85 @@ impl<'a> SubmitPresentBuilder<'a> {
                 }
             };
 
-            let mut results = vec![ash::vk::Result::SUCCESS; self.swapchains.len()];
+            let mut results: SmallVec<[_; 4]> =
+                smallvec![ash::vk::Result::SUCCESS; self.swapchains.len()];
+
+            let fns = queue.queue().device().fns();
+
+            // Swapchains without an explicit present time get the default one, which has no
+            // constraint.
+            let mut present_times = if !self.present_times.is_empty() {
+                self.present_times
+                    .resize(self.swapchains.len(), PresentTime::default().into());
+                Some(ash::vk::PresentTimesInfoGOOGLE {
+                    swapchain_count: self.present_times.len() as u32,
+                    p_times: self.present_times.as_ptr(),
+                    ..Default::default()
+                })
+            } else {
+                None
+            };
+
+            // A present ID of 0 means that the present has no ID.
+            let mut present_ids = if !self.present_ids.is_empty() {
+                self.present_ids.resize(self.swapchains.len(), 0);
+                Some(ash::vk::PresentIdKHR {
+                    swapchain_count: self.present_ids.len() as u32,
+                    p_present_ids: self.present_ids.as_ptr(),
+                    ..Default::default()
+                })
+            } else {
+                None
+            };
 
-            let fns = queue.device().fns();
-            let queue = queue.internal_object_guard();
+            let mut device_group_info =
+                if !self.device_masks.is_empty() || self.device_group_present_mode.is_some() {
+                    debug_assert!(
+                        self.device_masks.is_empty()
+                            || self.device_masks.len() == self.swapchains.len()
+                    );
+                    Some(ash::vk::DeviceGroupPresentInfoKHR {
+                        swapchain_count: self.device_masks.len() as u32,
+                        p_device_masks: self.device_masks.as_ptr(),
+                        mode: self
+                            .device_group_present_mode
+                            .unwrap_or(DeviceGroupPresentMode::Local)
+                            .into(),
+                        ..Default::default()
+                    })
+                } else {
+                    None
+                };
+
+            let mut p_next: *const c_void = ptr::null();
+
+            if let Some(device_group_info) = device_group_info.as_mut() {
+                device_group_info.p_next = p_next;
+                p_next = device_group_info as *const ash::vk::DeviceGroupPresentInfoKHR as *const _;
+            }
+
+            if let Some(present_regions) = present_regions.as_mut() {
+                present_regions.p_next = p_next;
+                p_next = present_regions as *const ash::vk::PresentRegionsKHR as *const _;
+            }
+
+            if let Some(display_present_info) = self.display_present_info.as_mut() {
+                display_present_info.p_next = p_next;
+                p_next = display_present_info as *const ash::vk::DisplayPresentInfoKHR as *const _;
+            }
+
+            if let Some(present_times) = present_times.as_mut() {
+                present_times.p_next = p_next;
+                p_next = present_times as *const ash::vk::PresentTimesInfoGOOGLE as *const _;
+            }
+
+            if let Some(present_ids) = present_ids.as_mut() {
+                present_ids.p_next = p_next;
+                p_next = present_ids as *const ash::vk::PresentIdKHR as *const _;
+            }
 
             let infos = ash::vk::PresentInfoKHR {
-                p_next: present_regions
-                    .as_ref()
-                    .map(|pr| pr as *const ash::vk::PresentRegionsKHR as *const _)
-                    .unwrap_or(ptr::null()),
+                p_next,
                 wait_semaphore_count: self.wait_semaphores.len() as u32,
                 p_wait_semaphores: self.wait_semaphores.as_ptr(),
                 swapchain_count: self.swapchains.len() as u32,
@@ -177,7 +351,10 @@ impl<'a> SubmitPresentBuilder<'a> {
                 ..Default::default()
             };
 
-            check_errors(fns.khr_swapchain.queue_present_khr(*queue, &infos))?;
+            check_errors(
+                fns.khr_swapchain
+                    .queue_present_khr(queue.internal_object(), &infos),
+            )?;
 
             for result in results {
                 check_errors(result)?;
@@ -10,26 +10,45 @@
 use crate::check_errors;
 use crate::command_buffer::sys::UnsafeCommandBuffer;
 use crate::device::Queue;
+use crate::device::QueueGuard;
+use crate::memory::DeviceMemory;
 use crate::sync::Fence;
 use crate::sync::PipelineStages;
 use crate::sync::Semaphore;
+use crate::sync::TimelineSemaphore;
 use crate::Error;
 use crate::OomError;
-use crate::SynchronizedVulkanObject;
 use crate::VulkanObject;
 use smallvec::SmallVec;
 use std::error;
+use std::ffi::c_void;
 use std::fmt;
 use std::marker::PhantomData;
+use std::ptr;
 
 /// Prototype for a submission that executes command buffers.
 // TODO: example here
 #[derive(Debug)]
 pub struct SubmitCommandBufferBuilder<'a> {
     wait_semaphores: SmallVec<[ash::vk::Semaphore; 16]>,
+    wait_values: SmallVec<[u64; 16]>,
     destination_stages: SmallVec<[ash::vk::PipelineStageFlags; 8]>,
     signal_semaphores: SmallVec<[ash::vk::Semaphore; 16]>,
+    signal_values: SmallVec<[u64; 16]>,
+    // True if one of the semaphores is a timeline semaphore.
+    has_timeline_semaphores: bool,
     command_buffers: SmallVec<[ash::vk::CommandBuffer; 4]>,
+    // The device group indices and masks that were set, without the trailing default values.
+    // A device mask of 0 stands for all the physical devices of the device.
+    wait_device_indices: SmallVec<[u32; 16]>,
+    command_buffer_device_masks: SmallVec<[u32; 4]>,
+    signal_device_indices: SmallVec<[u32; 16]>,
+    performance_counter_pass: Option<u32>,
+    keyed_mutex_acquires: SmallVec<[ash::vk::DeviceMemory; 2]>,
+    keyed_mutex_acquire_keys: SmallVec<[u64; 2]>,
+    keyed_mutex_acquire_timeouts: SmallVec<[u32; 2]>,
+    keyed_mutex_releases: SmallVec<[ash::vk::DeviceMemory; 2]>,
+    keyed_mutex_release_keys: SmallVec<[u64; 2]>,
     fence: ash::vk::Fence,
     marker: PhantomData<&'a ()>,
 }
@@ -40,9 +59,21 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     pub fn new() -> SubmitCommandBufferBuilder<'a> {
         SubmitCommandBufferBuilder {
             wait_semaphores: SmallVec::new(),
+            wait_values: SmallVec::new(),
             destination_stages: SmallVec::new(),
             signal_semaphores: SmallVec::new(),
+            signal_values: SmallVec::new(),
+            has_timeline_semaphores: false,
             command_buffers: SmallVec::new(),
+            wait_device_indices: SmallVec::new(),
+            command_buffer_device_masks: SmallVec::new(),
+            signal_device_indices: SmallVec::new(),
+            performance_counter_pass: None,
+            keyed_mutex_acquires: SmallVec::new(),
+            keyed_mutex_acquire_keys: SmallVec::new(),
+            keyed_mutex_acquire_timeouts: SmallVec::new(),
+            keyed_mutex_releases: SmallVec::new(),
+            keyed_mutex_release_keys: SmallVec::new(),
             fence: ash::vk::Fence::null(),
             marker: PhantomData,
         }
@@ -140,9 +171,43 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
         debug_assert!(!ash::vk::PipelineStageFlags::from(stages).is_empty());
         // TODO: debug assert that the device supports the stages
         self.wait_semaphores.push(semaphore.internal_object());
+        self.wait_values.push(0);
         self.destination_stages.push(stages.into());
     }
 
+    /// Adds a timeline semaphore to be waited upon before the command buffers are executed.
+    ///
+    /// The command buffers start executing once the value of the semaphore is greater than or
+    /// equal to `value`. Only the given `stages` of the command buffers added afterwards will wait
+    /// upon the semaphore.
+    ///
+    /// # Safety
+    ///
+    /// - The stages must be supported by the device.
+    ///
+    /// - If you submit this builder, the semaphore must be kept alive until you are guaranteed
+    ///   that the GPU has at least started executing the command buffers.
+    ///
+    /// - If you submit this builder, the semaphore must eventually reach `value`, either from
+    ///   another submission or from the CPU. In other words, you must not block the queue
+    ///   forever.
+    ///
+    /// - The fence, command buffers, and semaphores must all belong to the same device.
+    ///
+    #[inline]
+    pub unsafe fn add_wait_timeline_semaphore(
+        &mut self,
+        semaphore: &'a TimelineSemaphore,
+        value: u64,
+        stages: PipelineStages,
+    ) {
+        debug_assert!(!ash::vk::PipelineStageFlags::from(stages).is_empty());
+        self.wait_semaphores.push(semaphore.internal_object());
+        self.wait_values.push(value);
+        self.destination_stages.push(stages.into());
+        self.has_timeline_semaphores = true;
+    }
+
     /// Adds a command buffer that is executed as part of this command.
     ///
     /// The command buffers are submitted in the order in which they are added.
@@ -166,6 +231,141 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
         self.command_buffers.push(command_buffer.internal_object());
     }
 
+    /// Sets the physical devices of a device group that execute the command buffer that was
+    /// added last. By default, command buffers are executed by all the physical devices.
+    ///
+    /// # Safety
+    ///
+    /// - The device must support device groups, and `device_mask` must be a valid device mask,
+    ///   see [`check_device_mask`](crate::command_buffer::validity::check_device_mask).
+    /// - The device mask must be a subset of the device masks the command buffer was recorded
+    ///   with.
+    ///
+    #[inline]
+    pub unsafe fn set_command_buffer_device_mask(&mut self, device_mask: u32) {
+        debug_assert!(!self.command_buffers.is_empty());
+        debug_assert_ne!(device_mask, 0);
+        self.command_buffer_device_masks
+            .resize(self.command_buffers.len() - 1, 0);
+        self.command_buffer_device_masks.push(device_mask);
+    }
+
+    /// Sets the index of the physical device of a device group that waits on the semaphore that
+    /// was added last. By default, the physical device of index 0 waits on the semaphores.
+    ///
+    /// # Safety
+    ///
+    /// - The device must support device groups, and `device_index` must be smaller than the
+    ///   number of physical devices of the device.
+    ///
+    #[inline]
+    pub unsafe fn set_wait_semaphore_device_index(&mut self, device_index: u32) {
+        debug_assert!(!self.wait_semaphores.is_empty());
+        self.wait_device_indices
+            .resize(self.wait_semaphores.len() - 1, 0);
+        self.wait_device_indices.push(device_index);
+    }
+
+    /// Sets the index of the physical device of a device group that signals the semaphore that
+    /// was added last. By default, the physical device of index 0 signals the semaphores.
+    ///
+    /// # Safety
+    ///
+    /// - The device must support device groups, and `device_index` must be smaller than the
+    ///   number of physical devices of the device.
+    ///
+    #[inline]
+    pub unsafe fn set_signal_semaphore_device_index(&mut self, device_index: u32) {
+        debug_assert!(!self.signal_semaphores.is_empty());
+        self.signal_device_indices
+            .resize(self.signal_semaphores.len() - 1, 0);
+        self.signal_device_indices.push(device_index);
+    }
+
+    // Returns true if a device group index or mask was set.
+    #[inline]
+    fn has_device_group_info(&self) -> bool {
+        !self.wait_device_indices.is_empty()
+            || !self.command_buffer_device_masks.is_empty()
+            || !self.signal_device_indices.is_empty()
+    }
+
+    // Adds the default values of the device group indices and masks that weren't set. Device
+    // masks that weren't set stay 0.
+    #[inline]
+    fn pad_device_group_info(&mut self) {
+        self.wait_device_indices
+            .resize(self.wait_semaphores.len(), 0);
+        self.command_buffer_device_masks
+            .resize(self.command_buffers.len(), 0);
+        self.signal_device_indices
+            .resize(self.signal_semaphores.len(), 0);
+    }
+
+    /// Sets the pass of the performance query pools that the command buffers use.
+    ///
+    /// If the counters of a performance query pool can't all be read in a single execution, the
+    /// command buffers must be submitted once for each pass, from 0 to
+    /// [`QueryPool::num_passes`](crate::query::QueryPool::num_passes). The pass is 0 by default.
+    ///
+    /// # Safety
+    ///
+    /// - `index` must be smaller than the number of passes of the query pools.
+    ///
+    #[inline]
+    pub unsafe fn set_performance_counter_pass(&mut self, index: u32) {
+        self.performance_counter_pass = Some(index);
+    }
+
+    /// Adds the acquisition of the keyed mutex of memory that was imported from a Direct3D 11
+    /// texture, before the command buffers are executed.
+    ///
+    /// The submission waits until the mutex is released with `key` by Direct3D 11, or by
+    /// another submission, or until `timeout_ms` milliseconds have passed.
+    ///
+    /// # Safety
+    ///
+    /// - The `khr_win32_keyed_mutex` extension must be enabled on the device.
+    ///
+    /// - `memory` must have been imported from a `d3d11_texture` or `d3d11_texture_kmt` handle
+    ///   of a texture created with `D3D11_RESOURCE_MISC_SHARED_KEYEDMUTEX`.
+    ///
+    /// - If you submit this builder, the memory must be kept alive until you are guaranteed
+    ///   that the GPU has finished executing this submission.
+    ///
+    #[inline]
+    pub unsafe fn add_keyed_mutex_acquire(
+        &mut self,
+        memory: &'a DeviceMemory,
+        key: u64,
+        timeout_ms: u32,
+    ) {
+        self.keyed_mutex_acquires.push(memory.internal_object());
+        self.keyed_mutex_acquire_keys.push(key);
+        self.keyed_mutex_acquire_timeouts.push(timeout_ms);
+    }
+
+    /// Adds the release of the keyed mutex of memory that was imported from a Direct3D 11
+    /// texture with `key`, after the command buffers have been executed.
+    ///
+    /// # Safety
+    ///
+    /// - The `khr_win32_keyed_mutex` extension must be enabled on the device.
+    ///
+    /// - `memory` must have been imported from a `d3d11_texture` or `d3d11_texture_kmt` handle
+    ///   of a texture created with `D3D11_RESOURCE_MISC_SHARED_KEYEDMUTEX`.
+    ///
+    /// - The mutex must be acquired when queue execution reaches this submission.
+    ///
+    /// - If you submit this builder, the memory must be kept alive until you are guaranteed
+    ///   that the GPU has finished executing this submission.
+    ///
+    #[inline]
+    pub unsafe fn add_keyed_mutex_release(&mut self, memory: &'a DeviceMemory, key: u64) {
+        self.keyed_mutex_releases.push(memory.internal_object());
+        self.keyed_mutex_release_keys.push(key);
+    }
+
     /// Returns the number of semaphores to signal.
     ///
     /// In other words, this is the number of times `add_signal_semaphore` has been called.
@@ -189,6 +389,30 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     #[inline]
     pub unsafe fn add_signal_semaphore(&mut self, semaphore: &'a Semaphore) {
         self.signal_semaphores.push(semaphore.internal_object());
+        self.signal_values.push(0);
+    }
+
+    /// Adds a timeline semaphore whose value is set to `value` at the end of the submission.
+    ///
+    /// # Safety
+    ///
+    /// - If you submit this builder, the semaphore must be kept alive until you are guaranteed
+    ///   that the GPU has finished executing this submission.
+    ///
+    /// - `value` must be greater than the current value of the semaphore, and than the values of
+    ///   the signal operations that are submitted before this one.
+    ///
+    /// - The fence, command buffers, and semaphores must all belong to the same device.
+    ///
+    #[inline]
+    pub unsafe fn add_signal_timeline_semaphore(
+        &mut self,
+        semaphore: &'a TimelineSemaphore,
+        value: u64,
+    ) {
+        self.signal_semaphores.push(semaphore.internal_object());
+        self.signal_values.push(value);
+        self.has_timeline_semaphores = true;
     }
 
     /// Submits the command buffer to the given queue.
@@ -196,14 +420,95 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     /// > **Note**: This is an expensive operation, so you may want to merge as many builders as
     /// > possible together and avoid submitting them one by one.
     ///
+    #[inline]
     pub fn submit(self, queue: &Queue) -> Result<(), SubmitCommandBufferError> {
+        self.submit_locked(&mut queue.lock())
+    }
+
+    /// Same as `submit`, but submits to a queue that is already locked.
+    pub fn submit_locked(mut self, queue: &mut QueueGuard) -> Result<(), SubmitCommandBufferError> {
         unsafe {
-            let fns = queue.device().fns();
-            let queue = queue.internal_object_guard();
+            let fns = queue.queue().device().fns();
 
             debug_assert_eq!(self.wait_semaphores.len(), self.destination_stages.len());
+            debug_assert_eq!(self.wait_semaphores.len(), self.wait_values.len());
+            debug_assert_eq!(self.signal_semaphores.len(), self.signal_values.len());
+            debug_assert_eq!(
+                self.keyed_mutex_acquires.len(),
+                self.keyed_mutex_acquire_keys.len()
+            );
+            debug_assert_eq!(
+                self.keyed_mutex_releases.len(),
+                self.keyed_mutex_release_keys.len()
+            );
+
+            let mut p_next: *const c_void = ptr::null();
+
+            let device_group_info;
+            if self.has_device_group_info() {
+                self.pad_device_group_info();
+                let all_devices_mask = queue.queue().device().all_devices_mask();
+                for device_mask in self.command_buffer_device_masks.iter_mut() {
+                    if *device_mask == 0 {
+                        *device_mask = all_devices_mask;
+                    }
+                }
+
+                device_group_info = ash::vk::DeviceGroupSubmitInfo {
+                    p_next,
+                    wait_semaphore_count: self.wait_device_indices.len() as u32,
+                    p_wait_semaphore_device_indices: self.wait_device_indices.as_ptr(),
+                    command_buffer_count: self.command_buffer_device_masks.len() as u32,
+                    p_command_buffer_device_masks: self.command_buffer_device_masks.as_ptr(),
+                    signal_semaphore_count: self.signal_device_indices.len() as u32,
+                    p_signal_semaphore_device_indices: self.signal_device_indices.as_ptr(),
+                    ..Default::default()
+                };
+                p_next = &device_group_info as *const _ as *const _;
+            }
+
+            let performance_info;
+            if let Some(index) = self.performance_counter_pass {
+                performance_info = ash::vk::PerformanceQuerySubmitInfoKHR {
+                    p_next,
+                    counter_pass_index: index,
+                    ..Default::default()
+                };
+                p_next = &performance_info as *const _ as *const _;
+            }
+
+            // The values of binary semaphores are ignored.
+            let timeline_info;
+            if self.has_timeline_semaphores {
+                timeline_info = ash::vk::TimelineSemaphoreSubmitInfo {
+                    p_next,
+                    wait_semaphore_value_count: self.wait_values.len() as u32,
+                    p_wait_semaphore_values: self.wait_values.as_ptr(),
+                    signal_semaphore_value_count: self.signal_values.len() as u32,
+                    p_signal_semaphore_values: self.signal_values.as_ptr(),
+                    ..Default::default()
+                };
+                p_next = &timeline_info as *const _ as *const _;
+            }
+
+            let keyed_mutex_info;
+            if !self.keyed_mutex_acquires.is_empty() || !self.keyed_mutex_releases.is_empty() {
+                keyed_mutex_info = ash::vk::Win32KeyedMutexAcquireReleaseInfoKHR {
+                    p_next,
+                    acquire_count: self.keyed_mutex_acquires.len() as u32,
+                    p_acquire_syncs: self.keyed_mutex_acquires.as_ptr(),
+                    p_acquire_keys: self.keyed_mutex_acquire_keys.as_ptr(),
+                    p_acquire_timeouts: self.keyed_mutex_acquire_timeouts.as_ptr(),
+                    release_count: self.keyed_mutex_releases.len() as u32,
+                    p_release_syncs: self.keyed_mutex_releases.as_ptr(),
+                    p_release_keys: self.keyed_mutex_release_keys.as_ptr(),
+                    ..Default::default()
+                };
+                p_next = &keyed_mutex_info as *const _ as *const _;
+            }
 
             let batch = ash::vk::SubmitInfo {
+                p_next,
                 wait_semaphore_count: self.wait_semaphores.len() as u32,
                 p_wait_semaphores: self.wait_semaphores.as_ptr(),
                 p_wait_dst_stage_mask: self.destination_stages.as_ptr(),
@@ -214,7 +519,12 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
                 ..Default::default()
             };
 
-            check_errors(fns.v1_0.queue_submit(*queue, 1, &batch, self.fence))?;
+            #[cfg(feature = "tracy")]
+            let _zone = crate::tracy::zone("vkQueueSubmit");
+            check_errors(
+                fns.v1_0
+                    .queue_submit(queue.internal_object(), 1, &batch, self.fence),
+            )?;
             Ok(())
         }
     }
@@ -223,18 +533,49 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     ///
     /// # Panic
     ///
-    /// Panics if both builders have a fence already set.
+    /// - Panics if both builders have a fence already set.
+    /// - Panics if both builders have a different performance counter pass.
     // TODO: create multiple batches instead
-    pub fn merge(mut self, other: Self) -> Self {
+    pub fn merge(mut self, mut other: Self) -> Self {
         assert!(
             self.fence == ash::vk::Fence::null() || other.fence == ash::vk::Fence::null(),
             "Can't merge two queue submits that both have a fence"
         );
+        assert!(
+            self.performance_counter_pass.is_none()
+                || other.performance_counter_pass.is_none()
+                || self.performance_counter_pass == other.performance_counter_pass,
+            "Can't merge two queue submits that have a different performance counter pass"
+        );
+
+        if self.has_device_group_info() || other.has_device_group_info() {
+            self.pad_device_group_info();
+            other.pad_device_group_info();
+            self.wait_device_indices.extend(other.wait_device_indices);
+            self.command_buffer_device_masks
+                .extend(other.command_buffer_device_masks);
+            self.signal_device_indices
+                .extend(other.signal_device_indices);
+        }
 
         self.wait_semaphores.extend(other.wait_semaphores);
+        self.wait_values.extend(other.wait_values);
         self.destination_stages.extend(other.destination_stages); // TODO: meh? will be solved if we submit multiple batches
         self.signal_semaphores.extend(other.signal_semaphores);
+        self.signal_values.extend(other.signal_values);
+        self.has_timeline_semaphores |= other.has_timeline_semaphores;
         self.command_buffers.extend(other.command_buffers);
+        self.performance_counter_pass = self
+            .performance_counter_pass
+            .or(other.performance_counter_pass);
+        self.keyed_mutex_acquires.extend(other.keyed_mutex_acquires);
+        self.keyed_mutex_acquire_keys
+            .extend(other.keyed_mutex_acquire_keys);
+        self.keyed_mutex_acquire_timeouts
+            .extend(other.keyed_mutex_acquire_timeouts);
+        self.keyed_mutex_releases.extend(other.keyed_mutex_releases);
+        self.keyed_mutex_release_keys
+            .extend(other.keyed_mutex_release_keys);
 
         if self.fence == ash::vk::Fence::null() {
             self.fence = other.fence;
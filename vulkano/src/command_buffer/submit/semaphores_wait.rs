@@ -13,6 +13,7 @@ use crate::command_buffer::submit::SubmitCommandBufferBuilder;
 use crate::command_buffer::submit::SubmitPresentBuilder;
 use crate::sync::PipelineStages;
 use crate::sync::Semaphore;
+use crate::sync::TimelineSemaphore;
 
 /// Prototype for a submission that waits on semaphores.
 ///
@@ -21,6 +22,7 @@ use crate::sync::Semaphore;
 #[derive(Debug)]
 pub struct SubmitSemaphoresWaitBuilder<'a> {
     semaphores: SmallVec<[&'a Semaphore; 8]>,
+    timeline_semaphores: SmallVec<[(&'a TimelineSemaphore, u64); 4]>,
 }
 
 impl<'a> SubmitSemaphoresWaitBuilder<'a> {
@@ -29,6 +31,7 @@ impl<'a> SubmitSemaphoresWaitBuilder<'a> {
     pub fn new() -> SubmitSemaphoresWaitBuilder<'a> {
         SubmitSemaphoresWaitBuilder {
             semaphores: SmallVec::new(),
+            timeline_semaphores: SmallVec::new(),
         }
     }
 
@@ -40,36 +43,74 @@ impl<'a> SubmitSemaphoresWaitBuilder<'a> {
         self.semaphores.push(semaphore);
     }
 
+    /// Adds an operation that waits until the value of a timeline semaphore is greater than or
+    /// equal to `value`.
+    ///
+    /// The value must eventually be reached, either from a submission or from the CPU.
+    #[inline]
+    pub unsafe fn add_wait_timeline_semaphore(
+        &mut self,
+        semaphore: &'a TimelineSemaphore,
+        value: u64,
+    ) {
+        self.timeline_semaphores.push((semaphore, value));
+    }
+
     /// Merges this builder with another builder.
     #[inline]
     pub fn merge(&mut self, mut other: SubmitSemaphoresWaitBuilder<'a>) {
         self.semaphores.extend(other.semaphores.drain(..));
+        self.timeline_semaphores
+            .extend(other.timeline_semaphores.drain(..));
     }
-}
 
-impl<'a> Into<SubmitCommandBufferBuilder<'a>> for SubmitSemaphoresWaitBuilder<'a> {
+    /// Turns this builder into a command buffer submission builder, where only the given
+    /// `stages` of the command buffers wait upon the semaphores.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `stages` is empty.
+    ///
     #[inline]
-    fn into(mut self) -> SubmitCommandBufferBuilder<'a> {
+    pub fn into_command_buffer_builder(
+        mut self,
+        stages: PipelineStages,
+    ) -> SubmitCommandBufferBuilder<'a> {
+        assert!(!ash::vk::PipelineStageFlags::from(stages).is_empty());
+
         unsafe {
             let mut builder = SubmitCommandBufferBuilder::new();
             for sem in self.semaphores.drain(..) {
-                builder.add_wait_semaphore(
-                    sem,
-                    PipelineStages {
-                        // TODO: correct stages ; hard
-                        all_commands: true,
-                        ..PipelineStages::none()
-                    },
-                );
+                builder.add_wait_semaphore(sem, stages);
+            }
+            for (sem, value) in self.timeline_semaphores.drain(..) {
+                builder.add_wait_timeline_semaphore(sem, value, stages);
             }
             builder
         }
     }
 }
 
+impl<'a> Into<SubmitCommandBufferBuilder<'a>> for SubmitSemaphoresWaitBuilder<'a> {
+    #[inline]
+    fn into(self) -> SubmitCommandBufferBuilder<'a> {
+        self.into_command_buffer_builder(PipelineStages {
+            // TODO: correct stages ; hard
+            all_commands: true,
+            ..PipelineStages::none()
+        })
+    }
+}
+
 impl<'a> Into<SubmitPresentBuilder<'a>> for SubmitSemaphoresWaitBuilder<'a> {
     #[inline]
     fn into(mut self) -> SubmitPresentBuilder<'a> {
+        // VUID-vkQueuePresentKHR-pWaitSemaphores-03267
+        assert!(
+            self.timeline_semaphores.is_empty(),
+            "presenting can't wait on timeline semaphores; execute a command buffer first"
+        );
+
         unsafe {
             let mut builder = SubmitPresentBuilder::new();
             for sem in self.semaphores.drain(..) {
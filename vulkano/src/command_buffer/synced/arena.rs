@@ -0,0 +1,302 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Storage of the commands of a command buffer.
+//!
+//! Allocating each command on its own shows up when recording many commands, so the commands
+//! are instead written one after the other in chunks of memory, and dropped at once with their
+//! command buffer. The chunks are then given back to a `CommandArenaCache`, which the standard
+//! command pool shares between its command buffers so that the chunks are reused from one frame
+//! to the next.
+
+use super::Command;
+use std::alloc;
+use std::alloc::Layout;
+use std::fmt;
+use std::mem;
+use std::ops::Deref;
+use std::ptr;
+use std::ptr::NonNull;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+// Size and alignment of the chunks. Commands that don't fit get a chunk of their own.
+const CHUNK_SIZE: usize = 64 * 1024;
+const CHUNK_ALIGN: usize = 16;
+
+// Maximum number of chunks kept by a `CommandArenaCache`, so that the memory used by an unusually
+// large command buffer isn't kept forever.
+const MAX_CACHED_CHUNKS: usize = 64;
+
+// A chunk of memory of the arena.
+struct Chunk {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+// The chunks are only plain memory.
+unsafe impl Send for Chunk {}
+
+impl Chunk {
+    fn new(layout: Layout) -> Chunk {
+        debug_assert_ne!(layout.size(), 0);
+
+        let ptr = unsafe { alloc::alloc(layout) };
+        let ptr = match NonNull::new(ptr) {
+            Some(ptr) => ptr,
+            None => alloc::handle_alloc_error(layout),
+        };
+
+        Chunk { ptr, layout }
+    }
+
+    #[inline]
+    fn default_layout() -> Layout {
+        Layout::from_size_align(CHUNK_SIZE, CHUNK_ALIGN).unwrap()
+    }
+}
+
+impl Drop for Chunk {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.ptr.as_ptr(), self.layout) }
+    }
+}
+
+/// Chunks of memory that are shared by the arenas of the command buffers of a pool.
+#[derive(Default)]
+pub(crate) struct CommandArenaCache {
+    chunks: Mutex<Vec<Chunk>>,
+}
+
+impl fmt::Debug for CommandArenaCache {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<Command arena cache>")
+    }
+}
+
+/// Reference to a command stored in a `CommandArena`.
+///
+/// It is only valid for as long as the arena that contains the command. This holds as long as
+/// the references are only stored in the builder or the command buffer that owns the arena, or
+/// in the commands of the arena.
+#[derive(Copy, Clone)]
+pub(super) struct CommandRef(NonNull<dyn Command + Send + Sync>);
+
+// The commands are `Send` and `Sync`.
+unsafe impl Send for CommandRef {}
+unsafe impl Sync for CommandRef {}
+
+impl Deref for CommandRef {
+    type Target = dyn Command + Send + Sync;
+
+    #[inline]
+    fn deref(&self) -> &(dyn Command + Send + Sync + 'static) {
+        unsafe { self.0.as_ref() }
+    }
+}
+
+impl fmt::Debug for CommandRef {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt::Debug::fmt(&**self, fmt)
+    }
+}
+
+/// The list of the commands of a command buffer, stored in chunks of memory.
+///
+/// Dereferences to the list of the commands, in the order in which they were pushed.
+pub(super) struct CommandArena {
+    // The chunks that contain the commands. Only the last one has free space.
+    chunks: Vec<Chunk>,
+    // Offset of the free space in the last chunk.
+    offset: usize,
+    // The commands, in order.
+    commands: Vec<CommandRef>,
+    // Where to give the chunks back when the arena is dropped.
+    cache: Option<Arc<CommandArenaCache>>,
+}
+
+// The arena only contains `Send` and `Sync` commands.
+unsafe impl Send for CommandArena {}
+unsafe impl Sync for CommandArena {}
+
+impl CommandArena {
+    /// Builds an empty arena, whose chunks are taken from and given back to `cache` if any.
+    #[inline]
+    pub(super) fn new(cache: Option<Arc<CommandArenaCache>>) -> CommandArena {
+        CommandArena {
+            chunks: Vec::new(),
+            offset: 0,
+            commands: Vec::new(),
+            cache,
+        }
+    }
+
+    /// Moves a command into the arena, and returns a reference to it.
+    pub(super) fn push<C>(&mut self, command: C) -> CommandRef
+    where
+        C: Command + Send + Sync + 'static,
+    {
+        let ptr = self.alloc(Layout::new::<C>()) as *mut C;
+
+        let command_ref = unsafe {
+            ptr::write(ptr, command);
+            CommandRef(NonNull::new_unchecked(
+                ptr as *mut (dyn Command + Send + Sync),
+            ))
+        };
+
+        self.commands.push(command_ref);
+        command_ref
+    }
+
+    // Returns memory for a value with the given layout.
+    fn alloc(&mut self, layout: Layout) -> *mut u8 {
+        if let Some(chunk) = self.chunks.last() {
+            let start = (self.offset + layout.align() - 1) & !(layout.align() - 1);
+
+            if layout.align() <= chunk.layout.align()
+                && start + layout.size() <= chunk.layout.size()
+            {
+                self.offset = start + layout.size();
+                return unsafe { chunk.ptr.as_ptr().add(start) };
+            }
+        }
+
+        let chunk = self.new_chunk(layout);
+        let ptr = chunk.ptr.as_ptr();
+        self.chunks.push(chunk);
+        self.offset = layout.size();
+        ptr
+    }
+
+    // Returns a chunk that can contain a value with the given layout, from the cache if possible.
+    fn new_chunk(&mut self, layout: Layout) -> Chunk {
+        if layout.size() > CHUNK_SIZE || layout.align() > CHUNK_ALIGN {
+            let size = layout.size().max(CHUNK_SIZE);
+            let align = layout.align().max(CHUNK_ALIGN);
+            return Chunk::new(Layout::from_size_align(size, align).unwrap());
+        }
+
+        let cached = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.chunks.lock().unwrap().pop());
+        cached.unwrap_or_else(|| Chunk::new(Chunk::default_layout()))
+    }
+}
+
+impl Deref for CommandArena {
+    type Target = [CommandRef];
+
+    #[inline]
+    fn deref(&self) -> &[CommandRef] {
+        &self.commands
+    }
+}
+
+impl Drop for CommandArena {
+    fn drop(&mut self) {
+        unsafe {
+            for command in self.commands.drain(..) {
+                ptr::drop_in_place(command.0.as_ptr());
+            }
+        }
+
+        if let Some(cache) = self.cache.take() {
+            let mut cached = cache.chunks.lock().unwrap();
+            let chunks = mem::take(&mut self.chunks);
+
+            for chunk in chunks {
+                if cached.len() < MAX_CACHED_CHUNKS && chunk.layout == Chunk::default_layout() {
+                    cached.push(chunk);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for CommandArena {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt::Debug::fmt(&self.commands, fmt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CommandArena;
+    use super::CommandArenaCache;
+    use super::CHUNK_SIZE;
+    use crate::command_buffer::synced::Command;
+    use crate::command_buffer::sys::UnsafeCommandBufferBuilder;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    struct Cmd<T> {
+        _data: T,
+        dropped: Arc<AtomicUsize>,
+    }
+
+    impl<T> Command for Cmd<T> {
+        fn name(&self) -> &'static str {
+            "test"
+        }
+
+        unsafe fn send(&self, _: &mut UnsafeCommandBufferBuilder) {}
+    }
+
+    impl<T> Drop for Cmd<T> {
+        fn drop(&mut self) {
+            self.dropped.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn drops_and_recycles() {
+        let cache = Arc::new(CommandArenaCache::default());
+        let dropped = Arc::new(AtomicUsize::new(0));
+
+        {
+            let mut arena = CommandArena::new(Some(cache.clone()));
+            for _ in 0..10_000 {
+                arena.push(Cmd {
+                    _data: [0u64; 4],
+                    dropped: dropped.clone(),
+                });
+            }
+            // Too large for a regular chunk.
+            arena.push(Cmd {
+                _data: [0u8; CHUNK_SIZE * 2],
+                dropped: dropped.clone(),
+            });
+
+            assert_eq!(arena.len(), 10_001);
+            assert!(arena.iter().all(|command| command.name() == "test"));
+        }
+
+        assert_eq!(dropped.load(Ordering::SeqCst), 10_001);
+        let cached = cache.chunks.lock().unwrap().len();
+        assert!(cached > 1);
+
+        {
+            let mut arena = CommandArena::new(Some(cache.clone()));
+            arena.push(Cmd {
+                _data: (),
+                dropped: dropped.clone(),
+            });
+            assert_eq!(cache.chunks.lock().unwrap().len(), cached - 1);
+        }
+
+        assert_eq!(cache.chunks.lock().unwrap().len(), cached);
+    }
+}
@@ -10,6 +10,9 @@
 pub use self::commands::SyncCommandBufferBuilderBindDescriptorSets;
 pub use self::commands::SyncCommandBufferBuilderBindVertexBuffer;
 pub use self::commands::SyncCommandBufferBuilderExecuteCommands;
+use super::arena::CommandArena;
+use super::arena::CommandArenaCache;
+use super::arena::CommandRef;
 use super::Command;
 use super::ResourceFinalState;
 use super::ResourceKey;
@@ -26,6 +29,7 @@ use crate::command_buffer::ImageUninitializedSafe;
 use crate::descriptor_set::DescriptorSet;
 use crate::device::Device;
 use crate::device::DeviceOwned;
+use crate::image::ImageAccess;
 use crate::image::ImageLayout;
 use crate::pipeline::{ComputePipeline, GraphicsPipeline, PipelineBindPoint};
 use crate::render_pass::FramebufferAbstract;
@@ -38,6 +42,7 @@ use std::borrow::Cow;
 use std::collections::hash_map::Entry;
 use std::error;
 use std::fmt;
+use std::mem;
 use std::sync::Arc;
 
 #[path = "commands.rs"]
@@ -66,7 +71,7 @@ pub struct SyncCommandBufferBuilder {
     // submitted to the inner builder yet.
     // Each command owns the resources it uses (buffers, images, pipelines, descriptor sets etc.),
     // references to any of these must be indirect in the form of a command index + resource id.
-    commands: Vec<Arc<dyn Command + Send + Sync>>,
+    commands: CommandArena,
 
     // Prototype for the pipeline barrier that must be submitted before flushing the commands
     // in `commands`.
@@ -109,6 +114,29 @@ pub struct SyncCommandBufferBuilder {
 
     // True if we're a secondary command buffer.
     is_secondary: bool,
+
+    // True if a checkpoint is inserted before each command. See `Device::set_auto_checkpoints`.
+    auto_checkpoints: bool,
+
+    // List of the resources of the latest draw or dispatch command, kept so that the next one
+    // doesn't have to allocate a new list. Always empty between two commands.
+    command_resources: Vec<CommandResource>,
+}
+
+// Sends `command` to `inner`, preceded by a checkpoint labelled with the name of the command if
+// `auto_checkpoints` is true.
+#[inline]
+unsafe fn send_command(
+    inner: &mut UnsafeCommandBufferBuilder,
+    command: &dyn Command,
+    auto_checkpoints: bool,
+) {
+    if auto_checkpoints {
+        let marker = inner.device().checkpoint_marker(command.name());
+        inner.set_checkpoint(marker);
+    }
+
+    command.send(inner);
 }
 
 impl SyncCommandBufferBuilder {
@@ -118,11 +146,26 @@ impl SyncCommandBufferBuilder {
     /// # Safety
     ///
     /// See `UnsafeCommandBufferBuilder::new()`.
+    #[inline]
     pub unsafe fn new<F>(
         pool_alloc: &UnsafeCommandPoolAlloc,
         level: CommandBufferLevel<F>,
         usage: CommandBufferUsage,
     ) -> Result<SyncCommandBufferBuilder, OomError>
+    where
+        F: FramebufferAbstract,
+    {
+        SyncCommandBufferBuilder::with_arena_cache(pool_alloc, level, usage, None)
+    }
+
+    // Same as `new`, but the commands are stored in memory taken from `arena_cache`, and given
+    // back to it when the command buffer is dropped.
+    pub(crate) unsafe fn with_arena_cache<F>(
+        pool_alloc: &UnsafeCommandPoolAlloc,
+        level: CommandBufferLevel<F>,
+        usage: CommandBufferUsage,
+        arena_cache: Option<Arc<CommandArenaCache>>,
+    ) -> Result<SyncCommandBufferBuilder, OomError>
     where
         F: FramebufferAbstract,
     {
@@ -134,10 +177,11 @@ impl SyncCommandBufferBuilder {
         };
 
         let cmd = UnsafeCommandBufferBuilder::new(pool_alloc, level, usage)?;
-        Ok(SyncCommandBufferBuilder::from_unsafe_cmd(
+        Ok(SyncCommandBufferBuilder::from_unsafe_cmd_with_arena(
             cmd,
             is_secondary,
             inside_render_pass,
+            CommandArena::new(arena_cache),
         ))
     }
 
@@ -155,12 +199,27 @@ impl SyncCommandBufferBuilder {
         cmd: UnsafeCommandBufferBuilder,
         is_secondary: bool,
         inside_render_pass: bool,
+    ) -> SyncCommandBufferBuilder {
+        SyncCommandBufferBuilder::from_unsafe_cmd_with_arena(
+            cmd,
+            is_secondary,
+            inside_render_pass,
+            CommandArena::new(None),
+        )
+    }
+
+    unsafe fn from_unsafe_cmd_with_arena(
+        cmd: UnsafeCommandBufferBuilder,
+        is_secondary: bool,
+        inside_render_pass: bool,
+        commands: CommandArena,
     ) -> SyncCommandBufferBuilder {
         let latest_render_pass_enter = if inside_render_pass { Some(0) } else { None };
+        let auto_checkpoints = cmd.device().auto_checkpoints();
 
         SyncCommandBufferBuilder {
             inner: cmd,
-            commands: Vec::new(),
+            commands,
             pending_barrier: UnsafeCommandBufferBuilderPipelineBarrier::new(),
             barriers: Vec::new(),
             first_unflushed: 0,
@@ -171,9 +230,33 @@ impl SyncCommandBufferBuilder {
             bindings: Default::default(),
             is_poisoned: false,
             is_secondary,
+            auto_checkpoints,
+            command_resources: Vec::new(),
         }
     }
 
+    // Returns an empty list for the resources of a command, to be passed to
+    // `append_command_reusing`.
+    #[inline]
+    fn take_command_resources(&mut self) -> Vec<CommandResource> {
+        mem::take(&mut self.command_resources)
+    }
+
+    // Same as `append_command`, but keeps `resources` to be reused by the next command.
+    fn append_command_reusing<C>(
+        &mut self,
+        command: C,
+        mut resources: Vec<CommandResource>,
+    ) -> Result<(), SyncCommandBufferBuilderError>
+    where
+        C: Command + Send + Sync + 'static,
+    {
+        let result = self.append_command(command, &resources);
+        resources.clear();
+        self.command_resources = resources;
+        result
+    }
+
     // Adds a command to be processed by the builder.
     //
     // The `resources` argument should contain each buffer or image used by the command.
@@ -210,7 +293,7 @@ impl SyncCommandBufferBuilder {
 
         // Note that we don't submit the command to the inner command buffer yet.
         let (latest_command_id, end) = {
-            self.commands.push(Arc::new(command));
+            self.commands.push(command);
             let latest_command_id = self.commands.len() - 1;
             let end = self.latest_render_pass_enter.unwrap_or(latest_command_id);
             (latest_command_id, end)
@@ -244,6 +327,18 @@ impl SyncCommandBufferBuilder {
                 match self.resources.entry(resource_key) {
                     // Situation where this resource was used before in this command buffer.
                     Entry::Occupied(mut entry) => {
+                        // A manual pipeline barrier is only trusted for the next use of the
+                        // resource.
+                        let trusted = match entry.get_mut().trusted.take() {
+                            Some((stages, access)) => {
+                                stages_include(stages, memory.stages)
+                                    && ash::vk::AccessFlags::from(access)
+                                        .contains(memory.access.into())
+                                    && entry.get().current_layout == start_layout
+                            }
+                            None => false,
+                        };
+
                         // `collision_cmd_ids` contains the IDs of the commands that we are potentially
                         // colliding with.
                         let collision_cmd_ids = &entry.get().command_ids;
@@ -251,8 +346,16 @@ impl SyncCommandBufferBuilder {
 
                         let entry_key_resource_index = entry.get().resource_index;
 
-                        // Find out if we have a collision with the pending commands.
-                        if memory.exclusive
+                        if trusted {
+                            // The access is covered by the barrier, so no other barrier is needed.
+                            let entry = entry.into_mut();
+                            entry.command_ids.push(latest_command_id);
+                            entry.memory = memory;
+                            entry.exclusive_any |= memory.exclusive;
+                            if memory.exclusive || end_layout != ImageLayout::Undefined {
+                                entry.current_layout = end_layout;
+                            }
+                        } else if memory.exclusive
                             || entry.get().memory.exclusive
                             || entry.get().current_layout != start_layout
                         {
@@ -312,8 +415,12 @@ impl SyncCommandBufferBuilder {
                                                 command2_offset: latest_command_id,
                                             });
                                         }
-                                        for command in &mut self.commands[start..end] {
-                                            command.send(&mut self.inner);
+                                        for command in &self.commands[start..end] {
+                                            send_command(
+                                                &mut self.inner,
+                                                &**command,
+                                                self.auto_checkpoints,
+                                            );
                                         }
                                         self.first_unflushed = end;
                                     }
@@ -465,6 +572,7 @@ impl SyncCommandBufferBuilder {
                             initial_layout: actual_start_layout,
                             current_layout: end_layout, // TODO: what if we reach the end with Undefined? that's not correct?
                             image_uninitialized_safe,
+                            trusted: None,
                         });
                     }
                 }
@@ -525,8 +633,8 @@ impl SyncCommandBufferBuilder {
             self.inner.pipeline_barrier(&self.pending_barrier);
             let start = self.first_unflushed;
             self.barriers.push(start); // Track inserted barriers
-            for command in &mut self.commands[start..] {
-                command.send(&mut self.inner);
+            for command in &self.commands[start..] {
+                send_command(&mut self.inner, &**command, self.auto_checkpoints);
             }
         }
 
@@ -650,6 +758,13 @@ impl SyncCommandBufferBuilder {
             .get(&binding_num)
             .map(|cmd| cmd.bound_vertex_buffer(binding_num))
     }
+
+    /// Returns the current layout of `image`, or `None` if it hasn't been used yet.
+    pub(crate) fn image_layout(&self, image: &dyn ImageAccess) -> Option<ImageLayout> {
+        self.resources
+            .get(&ResourceKey::from(image))
+            .map(|state| state.current_layout)
+    }
 }
 
 unsafe impl DeviceOwned for SyncCommandBufferBuilder {
@@ -702,6 +817,17 @@ impl From<CommandBufferExecError> for SyncCommandBufferBuilderError {
     }
 }
 
+// A resource used by a command, as passed to `append_command`.
+type CommandResource = (
+    KeyTy,
+    Option<(
+        PipelineMemoryAccess,
+        ImageLayout,
+        ImageLayout,
+        ImageUninitializedSafe,
+    )>,
+);
+
 /// Type of resource whose state is to be tracked.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum KeyTy {
@@ -734,15 +860,25 @@ struct ResourceState {
 
     // Extra context of how the image will be used
     image_uninitialized_safe: ImageUninitializedSafe,
+
+    // If the last use of the resource was a manual pipeline barrier, contains its destination
+    // stages and accesses.
+    trusted: Option<(PipelineStages, AccessFlags)>,
+}
+
+// Returns true if `stages` includes all the stages of `other`.
+#[inline]
+fn stages_include(stages: PipelineStages, other: PipelineStages) -> bool {
+    stages.all_commands || ash::vk::PipelineStageFlags::from(stages).contains(other.into())
 }
 
 /// Holds the index of the most recent command that binds a particular resource, or `None` if
 /// nothing has been bound yet.
 #[derive(Debug, Default)]
 struct BindingState {
-    descriptor_sets: FnvHashMap<PipelineBindPoint, FnvHashMap<u32, Arc<dyn Command + Send + Sync>>>,
-    index_buffer: Option<Arc<dyn Command + Send + Sync>>,
-    pipeline_compute: Option<Arc<dyn Command + Send + Sync>>,
-    pipeline_graphics: Option<Arc<dyn Command + Send + Sync>>,
-    vertex_buffers: FnvHashMap<u32, Arc<dyn Command + Send + Sync>>,
+    descriptor_sets: FnvHashMap<PipelineBindPoint, FnvHashMap<u32, CommandRef>>,
+    index_buffer: Option<CommandRef>,
+    pipeline_compute: Option<CommandRef>,
+    pipeline_graphics: Option<CommandRef>,
+    vertex_buffers: FnvHashMap<u32, CommandRef>,
 }
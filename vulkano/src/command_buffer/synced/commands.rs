@@ -7,10 +7,17 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use super::send_command;
 use super::Command;
+use super::CommandRef;
+use super::ResourceKey;
+use super::ResourceLocation;
 use crate::buffer::BufferAccess;
 use crate::buffer::TypedBufferAccess;
+use crate::command_buffer::synced::builder::stages_include;
+use crate::command_buffer::synced::builder::CommandResource;
 use crate::command_buffer::synced::builder::KeyTy;
+use crate::command_buffer::synced::builder::ResourceState;
 use crate::command_buffer::synced::builder::SyncCommandBufferBuilder;
 use crate::command_buffer::synced::builder::SyncCommandBufferBuilderError;
 use crate::command_buffer::sys::UnsafeCommandBufferBuilder;
@@ -20,6 +27,8 @@ use crate::command_buffer::sys::UnsafeCommandBufferBuilderColorImageClear;
 use crate::command_buffer::sys::UnsafeCommandBufferBuilderExecuteCommands;
 use crate::command_buffer::sys::UnsafeCommandBufferBuilderImageBlit;
 use crate::command_buffer::sys::UnsafeCommandBufferBuilderImageCopy;
+use crate::command_buffer::sys::UnsafeCommandBufferBuilderPipelineBarrier;
+use crate::command_buffer::BarrierBuilder;
 use crate::command_buffer::CommandBufferExecError;
 use crate::command_buffer::ImageUninitializedSafe;
 use crate::command_buffer::SecondaryCommandBuffer;
@@ -27,6 +36,7 @@ use crate::command_buffer::SubpassContents;
 use crate::descriptor_set::layout::DescriptorDescTy;
 use crate::descriptor_set::DescriptorSet;
 use crate::descriptor_set::DescriptorSetWithOffsets;
+use crate::device::DeviceOwned;
 use crate::format::ClearValue;
 use crate::image::ImageAccess;
 use crate::image::ImageLayout;
@@ -57,6 +67,7 @@ use crate::SafeDeref;
 use crate::VulkanObject;
 use smallvec::SmallVec;
 use std::borrow::Cow;
+use std::collections::hash_map::Entry;
 use std::ffi::CStr;
 use std::mem;
 use std::ops::Range;
@@ -99,6 +110,48 @@ impl SyncCommandBufferBuilder {
         .unwrap();
     }
 
+    /// Calls `vkCmdBeginQueryIndexedEXT` on the builder.
+    #[inline]
+    pub unsafe fn begin_query_indexed(
+        &mut self,
+        query_pool: Arc<QueryPool>,
+        query: u32,
+        flags: QueryControlFlags,
+        index: u32,
+    ) {
+        struct Cmd {
+            query_pool: Arc<QueryPool>,
+            query: u32,
+            flags: QueryControlFlags,
+            index: u32,
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdBeginQueryIndexedEXT"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.begin_query_indexed(
+                    self.query_pool.query(self.query).unwrap(),
+                    self.flags,
+                    self.index,
+                );
+            }
+        }
+
+        self.append_command(
+            Cmd {
+                query_pool,
+                query,
+                flags,
+                index,
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
     /// Calls `vkBeginRenderPass` on the builder.
     // TODO: it shouldn't be possible to get an error if the framebuffer checked conflicts already
     // TODO: after begin_render_pass has been called, flushing should be forbidden and an error
@@ -109,6 +162,8 @@ impl SyncCommandBufferBuilder {
         framebuffer: F,
         subpass_contents: SubpassContents,
         clear_values: I,
+        render_area_offset: [u32; 2],
+        render_area_extent: [u32; 2],
     ) -> Result<(), SyncCommandBufferBuilderError>
     where
         F: FramebufferAbstract + Send + Sync + 'static,
@@ -118,6 +173,8 @@ impl SyncCommandBufferBuilder {
             framebuffer: F,
             subpass_contents: SubpassContents,
             clear_values: Mutex<Option<I>>,
+            render_area_offset: [u32; 2],
+            render_area_extent: [u32; 2],
         }
 
         impl<F, I> Command for Cmd<F, I>
@@ -134,6 +191,8 @@ impl SyncCommandBufferBuilder {
                     &self.framebuffer,
                     self.subpass_contents,
                     self.clear_values.lock().unwrap().take().unwrap(),
+                    self.render_area_offset,
+                    self.render_area_extent,
                 );
             }
 
@@ -188,6 +247,8 @@ impl SyncCommandBufferBuilder {
                 framebuffer,
                 subpass_contents,
                 clear_values: Mutex::new(Some(clear_values)),
+                render_area_offset,
+                render_area_extent,
             },
             &resources,
         )?;
@@ -1138,7 +1199,7 @@ impl SyncCommandBufferBuilder {
     pub unsafe fn dispatch(&mut self, group_counts: [u32; 3]) {
         struct Cmd {
             group_counts: [u32; 3],
-            descriptor_sets: SmallVec<[Arc<dyn Command + Send + Sync>; 12]>,
+            descriptor_sets: SmallVec<[CommandRef; 12]>,
         }
 
         impl Command for Cmd {
@@ -1213,6 +1274,7 @@ impl SyncCommandBufferBuilder {
             }
         }
 
+        let mut resources = self.take_command_resources();
         let pipeline = self
             .bindings
             .pipeline_compute
@@ -1220,19 +1282,18 @@ impl SyncCommandBufferBuilder {
             .unwrap()
             .bound_pipeline_compute();
 
-        let mut resources = Vec::new();
         let descriptor_sets = self.add_descriptor_set_resources(
             &mut resources,
             pipeline.layout(),
             PipelineBindPoint::Compute,
         );
 
-        self.append_command(
+        self.append_command_reusing(
             Cmd {
                 group_counts,
                 descriptor_sets,
             },
-            &resources,
+            resources,
         )
         .unwrap();
     }
@@ -1247,7 +1308,7 @@ impl SyncCommandBufferBuilder {
         B: BufferAccess + Send + Sync + 'static,
     {
         struct Cmd<B> {
-            descriptor_sets: SmallVec<[Arc<dyn Command + Send + Sync>; 12]>,
+            descriptor_sets: SmallVec<[CommandRef; 12]>,
             indirect_buffer: B,
         }
 
@@ -1332,6 +1393,7 @@ impl SyncCommandBufferBuilder {
             }
         }
 
+        let mut resources = self.take_command_resources();
         let pipeline = self
             .bindings
             .pipeline_compute
@@ -1339,7 +1401,6 @@ impl SyncCommandBufferBuilder {
             .unwrap()
             .bound_pipeline_compute();
 
-        let mut resources = Vec::new();
         let descriptor_sets = self.add_descriptor_set_resources(
             &mut resources,
             pipeline.layout(),
@@ -1347,12 +1408,12 @@ impl SyncCommandBufferBuilder {
         );
         self.add_indirect_buffer_resources(&mut resources);
 
-        self.append_command(
+        self.append_command_reusing(
             Cmd {
                 descriptor_sets,
                 indirect_buffer,
             },
-            &resources,
+            resources,
         )?;
 
         Ok(())
@@ -1368,8 +1429,8 @@ impl SyncCommandBufferBuilder {
         first_instance: u32,
     ) {
         struct Cmd {
-            descriptor_sets: SmallVec<[Arc<dyn Command + Send + Sync>; 12]>,
-            vertex_buffers: SmallVec<[(u32, Arc<dyn Command + Send + Sync>); 4]>,
+            descriptor_sets: SmallVec<[CommandRef; 12]>,
+            vertex_buffers: SmallVec<[(u32, CommandRef); 4]>,
             vertex_count: u32,
             instance_count: u32,
             first_vertex: u32,
@@ -1477,6 +1538,7 @@ impl SyncCommandBufferBuilder {
             }
         }
 
+        let mut resources = self.take_command_resources();
         let pipeline = self
             .bindings
             .pipeline_graphics
@@ -1484,7 +1546,6 @@ impl SyncCommandBufferBuilder {
             .unwrap()
             .bound_pipeline_graphics();
 
-        let mut resources = Vec::new();
         let descriptor_sets = self.add_descriptor_set_resources(
             &mut resources,
             pipeline.layout(),
@@ -1493,7 +1554,7 @@ impl SyncCommandBufferBuilder {
         let vertex_buffers =
             self.add_vertex_buffer_resources(&mut resources, pipeline.vertex_input());
 
-        self.append_command(
+        self.append_command_reusing(
             Cmd {
                 descriptor_sets,
                 vertex_buffers,
@@ -1502,7 +1563,7 @@ impl SyncCommandBufferBuilder {
                 first_vertex,
                 first_instance,
             },
-            &resources,
+            resources,
         )
         .unwrap();
     }
@@ -1518,9 +1579,9 @@ impl SyncCommandBufferBuilder {
         first_instance: u32,
     ) {
         struct Cmd {
-            descriptor_sets: SmallVec<[Arc<dyn Command + Send + Sync>; 12]>,
-            vertex_buffers: SmallVec<[(u32, Arc<dyn Command + Send + Sync>); 4]>,
-            index_buffer: Arc<dyn Command + Send + Sync>,
+            descriptor_sets: SmallVec<[CommandRef; 12]>,
+            vertex_buffers: SmallVec<[(u32, CommandRef); 4]>,
+            index_buffer: CommandRef,
             index_count: u32,
             instance_count: u32,
             first_index: u32,
@@ -1638,6 +1699,7 @@ impl SyncCommandBufferBuilder {
             }
         }
 
+        let mut resources = self.take_command_resources();
         let pipeline = self
             .bindings
             .pipeline_graphics
@@ -1645,7 +1707,6 @@ impl SyncCommandBufferBuilder {
             .unwrap()
             .bound_pipeline_graphics();
 
-        let mut resources = Vec::new();
         let descriptor_sets = self.add_descriptor_set_resources(
             &mut resources,
             pipeline.layout(),
@@ -1655,7 +1716,7 @@ impl SyncCommandBufferBuilder {
             self.add_vertex_buffer_resources(&mut resources, pipeline.vertex_input());
         let index_buffer = self.add_index_buffer_resources(&mut resources);
 
-        self.append_command(
+        self.append_command_reusing(
             Cmd {
                 descriptor_sets,
                 vertex_buffers,
@@ -1666,7 +1727,7 @@ impl SyncCommandBufferBuilder {
                 vertex_offset,
                 first_instance,
             },
-            &resources,
+            resources,
         )
         .unwrap();
     }
@@ -1683,8 +1744,8 @@ impl SyncCommandBufferBuilder {
         B: BufferAccess + Send + Sync + 'static,
     {
         struct Cmd<B> {
-            descriptor_sets: SmallVec<[Arc<dyn Command + Send + Sync>; 12]>,
-            vertex_buffers: SmallVec<[(u32, Arc<dyn Command + Send + Sync>); 4]>,
+            descriptor_sets: SmallVec<[CommandRef; 12]>,
+            vertex_buffers: SmallVec<[(u32, CommandRef); 4]>,
             indirect_buffer: B,
             draw_count: u32,
             stride: u32,
@@ -1797,6 +1858,7 @@ impl SyncCommandBufferBuilder {
             }
         }
 
+        let mut resources = self.take_command_resources();
         let pipeline = self
             .bindings
             .pipeline_graphics
@@ -1804,7 +1866,6 @@ impl SyncCommandBufferBuilder {
             .unwrap()
             .bound_pipeline_graphics();
 
-        let mut resources = Vec::new();
         let descriptor_sets = self.add_descriptor_set_resources(
             &mut resources,
             pipeline.layout(),
@@ -1814,7 +1875,7 @@ impl SyncCommandBufferBuilder {
             self.add_vertex_buffer_resources(&mut resources, pipeline.vertex_input());
         self.add_indirect_buffer_resources(&mut resources);
 
-        self.append_command(
+        self.append_command_reusing(
             Cmd {
                 descriptor_sets,
                 vertex_buffers,
@@ -1822,7 +1883,7 @@ impl SyncCommandBufferBuilder {
                 draw_count,
                 stride,
             },
-            &resources,
+            resources,
         )?;
 
         Ok(())
@@ -1840,9 +1901,9 @@ impl SyncCommandBufferBuilder {
         B: BufferAccess + Send + Sync + 'static,
     {
         struct Cmd<B> {
-            descriptor_sets: SmallVec<[Arc<dyn Command + Send + Sync>; 12]>,
-            vertex_buffers: SmallVec<[(u32, Arc<dyn Command + Send + Sync>); 4]>,
-            index_buffer: Arc<dyn Command + Send + Sync>,
+            descriptor_sets: SmallVec<[CommandRef; 12]>,
+            vertex_buffers: SmallVec<[(u32, CommandRef); 4]>,
+            index_buffer: CommandRef,
             indirect_buffer: B,
             draw_count: u32,
             stride: u32,
@@ -1959,6 +2020,7 @@ impl SyncCommandBufferBuilder {
             }
         }
 
+        let mut resources = self.take_command_resources();
         let pipeline = self
             .bindings
             .pipeline_graphics
@@ -1966,7 +2028,6 @@ impl SyncCommandBufferBuilder {
             .unwrap()
             .bound_pipeline_graphics();
 
-        let mut resources = Vec::new();
         let descriptor_sets = self.add_descriptor_set_resources(
             &mut resources,
             pipeline.layout(),
@@ -1977,7 +2038,7 @@ impl SyncCommandBufferBuilder {
         let index_buffer = self.add_index_buffer_resources(&mut resources);
         self.add_indirect_buffer_resources(&mut resources);
 
-        self.append_command(
+        self.append_command_reusing(
             Cmd {
                 descriptor_sets,
                 vertex_buffers,
@@ -1986,7 +2047,186 @@ impl SyncCommandBufferBuilder {
                 draw_count,
                 stride,
             },
-            &resources,
+            resources,
+        )?;
+
+        Ok(())
+    }
+
+    /// Calls `vkCmdDrawIndexedIndirectCount` on the builder.
+    #[inline]
+    pub unsafe fn draw_indexed_indirect_count<B, C>(
+        &mut self,
+        indirect_buffer: B,
+        count_buffer: C,
+        max_draw_count: u32,
+        stride: u32,
+    ) -> Result<(), SyncCommandBufferBuilderError>
+    where
+        B: BufferAccess + Send + Sync + 'static,
+        C: BufferAccess + Send + Sync + 'static,
+    {
+        struct Cmd<B, C> {
+            descriptor_sets: SmallVec<[CommandRef; 12]>,
+            vertex_buffers: SmallVec<[(u32, CommandRef); 4]>,
+            index_buffer: CommandRef,
+            indirect_buffer: B,
+            count_buffer: C,
+            max_draw_count: u32,
+            stride: u32,
+        }
+
+        impl<B, C> Command for Cmd<B, C>
+        where
+            B: BufferAccess + Send + Sync + 'static,
+            C: BufferAccess + Send + Sync + 'static,
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdDrawIndexedIndirectCount"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.draw_indexed_indirect_count(
+                    &self.indirect_buffer,
+                    &self.count_buffer,
+                    self.max_draw_count,
+                    self.stride,
+                );
+            }
+
+            fn buffer(&self, mut num: usize) -> &dyn BufferAccess {
+                for set in self
+                    .descriptor_sets
+                    .iter()
+                    .enumerate()
+                    .map(|(set_num, cmd)| cmd.bound_descriptor_set(set_num as u32).0)
+                {
+                    if let Some(buf) = set.buffer(num) {
+                        return buf.0;
+                    }
+                    num -= set.num_buffers();
+                }
+
+                for buffer in self
+                    .vertex_buffers
+                    .iter()
+                    .map(|(binding_num, cmd)| cmd.bound_vertex_buffer(*binding_num))
+                {
+                    if num == 0 {
+                        return buffer;
+                    }
+                    num -= 1;
+                }
+
+                if num == 0 {
+                    return self.index_buffer.bound_index_buffer();
+                } else if num == 1 {
+                    return &self.indirect_buffer;
+                } else if num == 2 {
+                    return &self.count_buffer;
+                }
+
+                panic!()
+            }
+
+            fn buffer_name(&self, mut num: usize) -> Cow<'static, str> {
+                for (set_num, set) in self
+                    .descriptor_sets
+                    .iter()
+                    .enumerate()
+                    .map(|(set_num, cmd)| (set_num, cmd.bound_descriptor_set(set_num as u32).0))
+                {
+                    if let Some(buf) = set.buffer(num) {
+                        return format!("Buffer bound to set {} descriptor {}", set_num, buf.1)
+                            .into();
+                    }
+                    num -= set.num_buffers();
+                }
+
+                for binding_num in self
+                    .vertex_buffers
+                    .iter()
+                    .map(|(binding_num, _)| *binding_num)
+                {
+                    if num == 0 {
+                        return format!("Vertex buffer binding {}", binding_num).into();
+                    }
+                    num -= 1;
+                }
+
+                if num == 0 {
+                    return "index buffer".into();
+                } else if num == 1 {
+                    return "indirect buffer".into();
+                } else if num == 2 {
+                    return "count buffer".into();
+                }
+
+                panic!()
+            }
+
+            fn image(&self, mut num: usize) -> &dyn ImageAccess {
+                for set in self
+                    .descriptor_sets
+                    .iter()
+                    .enumerate()
+                    .map(|(set_num, cmd)| cmd.bound_descriptor_set(set_num as u32).0)
+                {
+                    if let Some(img) = set.image(num) {
+                        return img.0.image();
+                    }
+                    num -= set.num_images();
+                }
+                panic!()
+            }
+
+            fn image_name(&self, mut num: usize) -> Cow<'static, str> {
+                for (set_num, set) in self
+                    .descriptor_sets
+                    .iter()
+                    .enumerate()
+                    .map(|(set_num, cmd)| (set_num, cmd.bound_descriptor_set(set_num as u32).0))
+                {
+                    if let Some(img) = set.image(num) {
+                        return format!("Image bound to set {} descriptor {}", set_num, img.1)
+                            .into();
+                    }
+                    num -= set.num_images();
+                }
+                panic!()
+            }
+        }
+
+        let mut resources = self.take_command_resources();
+        let pipeline = self
+            .bindings
+            .pipeline_graphics
+            .as_ref()
+            .unwrap()
+            .bound_pipeline_graphics();
+
+        let descriptor_sets = self.add_descriptor_set_resources(
+            &mut resources,
+            pipeline.layout(),
+            PipelineBindPoint::Graphics,
+        );
+        let vertex_buffers =
+            self.add_vertex_buffer_resources(&mut resources, pipeline.vertex_input());
+        let index_buffer = self.add_index_buffer_resources(&mut resources);
+        self.add_indirect_buffer_resources(&mut resources);
+        self.add_indirect_buffer_resources(&mut resources);
+
+        self.append_command_reusing(
+            Cmd {
+                descriptor_sets,
+                vertex_buffers,
+                index_buffer,
+                indirect_buffer,
+                count_buffer,
+                max_draw_count,
+                stride,
+            },
+            resources,
         )?;
 
         Ok(())
@@ -2013,6 +2253,36 @@ impl SyncCommandBufferBuilder {
         self.append_command(Cmd { query_pool, query }, &[]).unwrap();
     }
 
+    /// Calls `vkCmdEndQueryIndexedEXT` on the builder.
+    #[inline]
+    pub unsafe fn end_query_indexed(&mut self, query_pool: Arc<QueryPool>, query: u32, index: u32) {
+        struct Cmd {
+            query_pool: Arc<QueryPool>,
+            query: u32,
+            index: u32,
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdEndQueryIndexedEXT"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.end_query_indexed(self.query_pool.query(self.query).unwrap(), self.index);
+            }
+        }
+
+        self.append_command(
+            Cmd {
+                query_pool,
+                query,
+                index,
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
     /// Calls `vkCmdEndRenderPass` on the builder.
     #[inline]
     pub unsafe fn end_render_pass(&mut self) {
@@ -2121,6 +2391,324 @@ impl SyncCommandBufferBuilder {
         self.append_command(Cmd { subpass_contents }, &[]).unwrap();
     }
 
+    /// Calls `vkCmdPipelineBarrier` on the builder with a single memory barrier, while inside a
+    /// subpass.
+    ///
+    /// The barrier must be covered by a self-dependency of the current subpass.
+    #[inline]
+    pub unsafe fn subpass_barrier(
+        &mut self,
+        source_stages: PipelineStages,
+        source_access: AccessFlags,
+        destination_stages: PipelineStages,
+        destination_access: AccessFlags,
+        by_region: bool,
+    ) {
+        struct Cmd {
+            source_stages: PipelineStages,
+            source_access: AccessFlags,
+            destination_stages: PipelineStages,
+            destination_access: AccessFlags,
+            by_region: bool,
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdPipelineBarrier"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+                barrier.add_memory_barrier(
+                    self.source_stages,
+                    self.source_access,
+                    self.destination_stages,
+                    self.destination_access,
+                    self.by_region,
+                );
+                out.pipeline_barrier(&barrier);
+            }
+        }
+
+        self.append_command(
+            Cmd {
+                source_stages,
+                source_access,
+                destination_stages,
+                destination_access,
+                by_region,
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
+    /// Calls `vkCmdPipelineBarrier` on the builder, with barriers that are described manually.
+    ///
+    /// All the commands that were added before are flushed first. For each resource whose
+    /// barrier covers all of it, the destination scope of the barrier is trusted for the next
+    /// command that uses it: if that command's access is included in the scope, no other barrier
+    /// is inserted for it. Barriers that only cover a part of their resource are never trusted.
+    ///
+    /// If the source stages of a barrier don't include the previous use of its resource, an
+    /// additional barrier is inserted before it. The barriers must be valid, including their old
+    /// layouts, and must be added outside of a render pass.
+    #[inline]
+    pub unsafe fn pipeline_barrier(&mut self, barrier: BarrierBuilder) {
+        struct Cmd {
+            barrier: BarrierBuilder,
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdPipelineBarrier"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+
+                for (buffer, b) in &self.barrier.buffers {
+                    let range = b.range.clone().unwrap_or(0..buffer.size());
+                    barrier.add_buffer_memory_barrier(
+                        &**buffer,
+                        b.source_stages,
+                        b.source_access,
+                        b.destination_stages,
+                        b.destination_access,
+                        true,
+                        b.queue_family_transfer,
+                        range.start,
+                        range.end - range.start,
+                    );
+                }
+
+                for (image, b) in &self.barrier.images {
+                    barrier.add_image_memory_barrier(
+                        &**image,
+                        b.mipmap_levels
+                            .clone()
+                            .unwrap_or_else(|| image.current_miplevels_access()),
+                        b.array_layers
+                            .clone()
+                            .unwrap_or_else(|| image.current_layer_levels_access()),
+                        b.source_stages,
+                        b.source_access,
+                        b.destination_stages,
+                        b.destination_access,
+                        true,
+                        b.queue_family_transfer,
+                        b.old_layout,
+                        b.new_layout,
+                    );
+                }
+
+                out.pipeline_barrier(&barrier);
+            }
+
+            fn buffer(&self, num: usize) -> &dyn BufferAccess {
+                &*self.barrier.buffers[num].0
+            }
+
+            fn image(&self, num: usize) -> &dyn ImageAccess {
+                &*self.barrier.images[num].0
+            }
+
+            fn buffer_name(&self, num: usize) -> Cow<'static, str> {
+                format!("buffer {}", num).into()
+            }
+
+            fn image_name(&self, num: usize) -> Cow<'static, str> {
+                format!("image {}", num).into()
+            }
+        }
+
+        // TODO: see comment for the `is_poisoned` member in the struct
+        assert!(
+            !self.is_poisoned,
+            "The builder has been put in an inconsistent state by a previous error"
+        );
+        debug_assert!(self.latest_render_pass_enter.is_none());
+
+        // For each resource: its type, source and destination scopes, whether the barrier covers
+        // all of the resource, and layouts.
+        let resources: Vec<_> = barrier
+            .buffers
+            .iter()
+            .map(|(buffer, b)| {
+                // The resource is the whole underlying buffer, even if `buffer` is a slice.
+                let inner = buffer.inner();
+                let whole = inner.offset == 0
+                    && buffer.size() == inner.buffer.size()
+                    && match b.range {
+                        Some(ref range) => range.start == 0 && range.end >= buffer.size(),
+                        None => true,
+                    };
+
+                (
+                    KeyTy::Buffer,
+                    (b.source_stages, b.source_access),
+                    (b.destination_stages, b.destination_access),
+                    whole,
+                    b.queue_family_transfer.is_some(),
+                    ImageLayout::Undefined,
+                    ImageLayout::Undefined,
+                )
+            })
+            .chain(barrier.images.iter().map(|(image, b)| {
+                let whole = match b.mipmap_levels {
+                    Some(ref levels) => *levels == image.current_miplevels_access(),
+                    None => true,
+                } && match b.array_layers {
+                    Some(ref layers) => *layers == image.current_layer_levels_access(),
+                    None => true,
+                };
+
+                (
+                    KeyTy::Image,
+                    (b.source_stages, b.source_access),
+                    (b.destination_stages, b.destination_access),
+                    whole,
+                    b.queue_family_transfer.is_some() || b.old_layout != b.new_layout,
+                    b.old_layout,
+                    b.new_layout,
+                )
+            }))
+            .collect();
+
+        self.commands.push(Cmd { barrier });
+        let command_id = self.commands.len() - 1;
+
+        // If the source scope of a barrier doesn't include the previous use of its resource,
+        // make it wait for that use with an additional barrier.
+        let mut fixup_barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        let (mut num_buffers, mut num_images) = (0, 0);
+
+        for &(resource_ty, source, ..) in &resources {
+            let resource_key = match resource_ty {
+                KeyTy::Buffer => {
+                    num_buffers += 1;
+                    ResourceKey::from(self.commands[command_id].buffer(num_buffers - 1))
+                }
+                KeyTy::Image => {
+                    num_images += 1;
+                    ResourceKey::from(self.commands[command_id].image(num_images - 1))
+                }
+            };
+
+            if let Some(state) = self.resources.get(&resource_key) {
+                if !stages_include(source.0, state.memory.stages) {
+                    fixup_barrier.add_memory_barrier(
+                        state.memory.stages,
+                        state.memory.access,
+                        source.0,
+                        AccessFlags::none(),
+                        true,
+                    );
+                }
+            }
+        }
+
+        // The barrier must be recorded after all the previous commands, so flush them.
+        self.inner.pipeline_barrier(&self.pending_barrier);
+        self.pending_barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        let start = self.first_unflushed;
+        self.barriers.push(start); // Track inserted barriers
+        for command in &self.commands[start..command_id] {
+            send_command(&mut self.inner, &**command, self.auto_checkpoints);
+        }
+
+        self.inner.pipeline_barrier(&fixup_barrier);
+        send_command(
+            &mut self.inner,
+            &*self.commands[command_id],
+            self.auto_checkpoints,
+        );
+        self.first_unflushed = self.commands.len();
+
+        let mut last_cmd_buffer = 0;
+        let mut last_cmd_image = 0;
+
+        for (resource_ty, source, destination, whole, exclusive, old_layout, new_layout) in
+            resources
+        {
+            let (resource_key, resource_index) = match resource_ty {
+                KeyTy::Buffer => {
+                    let buffer = self.commands[command_id].buffer(last_cmd_buffer);
+                    (ResourceKey::from(buffer), last_cmd_buffer)
+                }
+                KeyTy::Image => {
+                    let image = self.commands[command_id].image(last_cmd_image);
+                    (ResourceKey::from(image), last_cmd_image)
+                }
+            };
+
+            // Any use of the resource that isn't trusted must wait for the barrier. The uses of the
+            // parts of the resource that the barrier doesn't cover must wait too.
+            let trusted = if whole { Some(destination) } else { None };
+            let memory = PipelineMemoryAccess {
+                stages: destination.0,
+                access: destination.1,
+                exclusive: true,
+            };
+
+            match self.resources.entry(resource_key) {
+                Entry::Occupied(entry) => {
+                    let entry = entry.into_mut();
+                    entry.command_ids.push(command_id);
+                    entry.memory = memory;
+                    entry.exclusive_any |= exclusive;
+                    entry.current_layout = new_layout;
+                    entry.trusted = trusted;
+                }
+                Entry::Vacant(entry) => {
+                    if !self.is_secondary && resource_ty == KeyTy::Image && exclusive {
+                        self.commands[command_id]
+                            .image(resource_index)
+                            .layout_initialized();
+                    }
+
+                    entry.insert(ResourceState {
+                        command_ids: vec![command_id],
+                        resource_index,
+                        memory,
+                        exclusive_any: exclusive,
+                        initial_layout: old_layout,
+                        current_layout: new_layout,
+                        image_uninitialized_safe: ImageUninitializedSafe::Unsafe,
+                        trusted,
+                    });
+                }
+            }
+
+            let location = ResourceLocation {
+                command_id,
+                resource_index,
+            };
+            let memory = PipelineMemoryAccess {
+                stages: source.0 | destination.0,
+                access: source.1 | destination.1,
+                exclusive: true,
+            };
+
+            match resource_ty {
+                KeyTy::Buffer => {
+                    self.buffers.push((location, memory));
+                    last_cmd_buffer += 1;
+                }
+                KeyTy::Image => {
+                    self.images.push((
+                        location,
+                        memory,
+                        old_layout,
+                        new_layout,
+                        ImageUninitializedSafe::Unsafe,
+                    ));
+                    last_cmd_image += 1;
+                }
+            }
+        }
+    }
+
     /// Calls `vkCmdPushConstants` on the builder.
     #[inline]
     pub unsafe fn push_constants<D>(
@@ -2321,6 +2909,51 @@ impl SyncCommandBufferBuilder {
         self.append_command(Cmd { event, stages }, &[]).unwrap();
     }
 
+    /// Calls `vkCmdSetDeviceMask` on the builder.
+    #[inline]
+    pub unsafe fn set_device_mask(&mut self, device_mask: u32) {
+        struct Cmd {
+            device_mask: u32,
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdSetDeviceMask"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.set_device_mask(self.device_mask);
+            }
+        }
+
+        self.append_command(Cmd { device_mask }, &[]).unwrap();
+    }
+
+    /// Calls `vkCmdSetCheckpointNV` on the builder, with a marker for `label`.
+    ///
+    /// # Safety
+    ///
+    /// - The `nv_device_diagnostic_checkpoints` extension must be enabled on the device.
+    #[inline]
+    pub unsafe fn set_checkpoint(&mut self, label: String) {
+        struct Cmd {
+            label: String,
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdSetCheckpointNV"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                let marker = out.device().checkpoint_marker(&self.label);
+                out.set_checkpoint(marker);
+            }
+        }
+
+        self.append_command(Cmd { label }, &[]).unwrap();
+    }
+
     /// Calls `vkCmdSetLineWidth` on the builder.
     #[inline]
     pub unsafe fn set_line_width(&mut self, line_width: f32) {
@@ -2602,22 +3235,13 @@ impl SyncCommandBufferBuilder {
 
     fn add_descriptor_set_resources(
         &self,
-        resources: &mut Vec<(
-            KeyTy,
-            Option<(
-                PipelineMemoryAccess,
-                ImageLayout,
-                ImageLayout,
-                ImageUninitializedSafe,
-            )>,
-        )>,
+        resources: &mut Vec<CommandResource>,
         pipeline_layout: &PipelineLayout,
         pipeline_bind_point: PipelineBindPoint,
-    ) -> SmallVec<[Arc<dyn Command + Send + Sync>; 12]> {
-        let descriptor_sets: SmallVec<[Arc<dyn Command + Send + Sync>; 12]> = (0..pipeline_layout
+    ) -> SmallVec<[CommandRef; 12]> {
+        let descriptor_sets: SmallVec<[CommandRef; 12]> = (0..pipeline_layout
             .descriptor_set_layouts()
-            .len()
-            as u32)
+            .len() as u32)
             .map(|set_num| self.bindings.descriptor_sets[&pipeline_bind_point][&set_num].clone())
             .collect();
 
@@ -2702,18 +3326,10 @@ impl SyncCommandBufferBuilder {
 
     fn add_vertex_buffer_resources(
         &self,
-        resources: &mut Vec<(
-            KeyTy,
-            Option<(
-                PipelineMemoryAccess,
-                ImageLayout,
-                ImageLayout,
-                ImageUninitializedSafe,
-            )>,
-        )>,
+        resources: &mut Vec<CommandResource>,
         vertex_input: &VertexInput,
-    ) -> SmallVec<[(u32, Arc<dyn Command + Send + Sync>); 4]> {
-        let vertex_buffers: SmallVec<[(u32, Arc<dyn Command + Send + Sync>); 4]> = vertex_input
+    ) -> SmallVec<[(u32, CommandRef); 4]> {
+        let vertex_buffers: SmallVec<[(u32, CommandRef); 4]> = vertex_input
             .bindings()
             .map(|(binding_num, _)| {
                 (
@@ -2748,18 +3364,7 @@ impl SyncCommandBufferBuilder {
         vertex_buffers
     }
 
-    fn add_index_buffer_resources(
-        &self,
-        resources: &mut Vec<(
-            KeyTy,
-            Option<(
-                PipelineMemoryAccess,
-                ImageLayout,
-                ImageLayout,
-                ImageUninitializedSafe,
-            )>,
-        )>,
-    ) -> Arc<dyn Command + Send + Sync> {
+    fn add_index_buffer_resources(&self, resources: &mut Vec<CommandResource>) -> CommandRef {
         let index_buffer = self.bindings.index_buffer.as_ref().unwrap().clone();
 
         resources.push((
@@ -2785,18 +3390,7 @@ impl SyncCommandBufferBuilder {
         index_buffer
     }
 
-    fn add_indirect_buffer_resources(
-        &self,
-        resources: &mut Vec<(
-            KeyTy,
-            Option<(
-                PipelineMemoryAccess,
-                ImageLayout,
-                ImageLayout,
-                ImageUninitializedSafe,
-            )>,
-        )>,
-    ) {
+    fn add_indirect_buffer_resources(&self, resources: &mut Vec<CommandResource>) {
         resources.push((
             KeyTy::Buffer,
             Some((
@@ -64,6 +64,8 @@
 //! queue. If not possible, the queue will be entirely flushed and the command added to a fresh new
 //! queue with a fresh new barrier prototype.
 
+use self::arena::CommandArena;
+pub(crate) use self::arena::CommandArenaCache;
 pub use self::builder::SyncCommandBufferBuilder;
 pub use self::builder::SyncCommandBufferBuilderBindDescriptorSets;
 pub use self::builder::SyncCommandBufferBuilderBindVertexBuffer;
@@ -92,6 +94,7 @@ use std::borrow::Cow;
 use std::ops::Range;
 use std::sync::Arc;
 
+mod arena;
 mod builder;
 
 /// Command buffer built from a `SyncCommandBufferBuilder` that provides utilities to handle
@@ -102,7 +105,7 @@ pub struct SyncCommandBuffer {
 
     // List of commands used by the command buffer. Used to hold the various resources that are
     // being used.
-    commands: Vec<Arc<dyn Command + Send + Sync>>,
+    commands: CommandArena,
 
     // Locations within commands that pipeline barriers were inserted. For debugging purposes.
     // TODO: present only in cfg(debug_assertions)?
@@ -49,6 +49,7 @@ use crate::sync::PipelineStage;
 use crate::sync::PipelineStages;
 use crate::DeviceSize;
 use crate::OomError;
+use crate::Version;
 use crate::VulkanObject;
 use ash::vk::Handle;
 use smallvec::SmallVec;
@@ -56,6 +57,7 @@ use std::ffi::CStr;
 use std::fmt;
 use std::mem;
 use std::ops::Range;
+use std::os::raw::c_void;
 use std::sync::Arc;
 
 /// Command buffer being built.
@@ -230,13 +232,42 @@ impl UnsafeCommandBufferBuilder {
             .cmd_begin_query(cmd, query.pool().internal_object(), query.index(), flags);
     }
 
+    /// Calls `vkCmdBeginQueryIndexedEXT` on the builder.
+    #[inline]
+    pub unsafe fn begin_query_indexed(
+        &mut self,
+        query: Query,
+        flags: QueryControlFlags,
+        index: u32,
+    ) {
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+        let flags = if flags.precise {
+            ash::vk::QueryControlFlags::PRECISE
+        } else {
+            ash::vk::QueryControlFlags::empty()
+        };
+        fns.ext_transform_feedback.cmd_begin_query_indexed_ext(
+            cmd,
+            query.pool().internal_object(),
+            query.index(),
+            flags,
+            index,
+        );
+    }
+
     /// Calls `vkCmdBeginRenderPass` on the builder.
+    ///
+    /// `render_area_offset` and `render_area_extent` describe the area of the framebuffer that
+    /// is affected by the render pass.
     #[inline]
     pub unsafe fn begin_render_pass<F, I>(
         &mut self,
         framebuffer: &F,
         subpass_contents: SubpassContents,
         clear_values: I,
+        render_area_offset: [u32; 2],
+        render_area_extent: [u32; 2],
     ) where
         F: ?Sized + FramebufferAbstract,
         I: IntoIterator<Item = ClearValue>,
@@ -281,23 +312,22 @@ impl UnsafeCommandBufferBuilder {
             })
             .collect();
 
-        // TODO: allow customizing
-        let rect = [
-            0..framebuffer.dimensions()[0],
-            0..framebuffer.dimensions()[1],
-        ];
+        debug_assert!((0..2).all(|i| {
+            render_area_offset[i] as u64 + render_area_extent[i] as u64
+                <= framebuffer.dimensions()[i] as u64
+        }));
 
         let begin = ash::vk::RenderPassBeginInfo {
             render_pass: raw_render_pass,
             framebuffer: raw_framebuffer,
             render_area: ash::vk::Rect2D {
                 offset: ash::vk::Offset2D {
-                    x: rect[0].start as i32,
-                    y: rect[1].start as i32,
+                    x: render_area_offset[0] as i32,
+                    y: render_area_offset[1] as i32,
                 },
                 extent: ash::vk::Extent2D {
-                    width: rect[0].end - rect[0].start,
-                    height: rect[1].end - rect[1].start,
+                    width: render_area_extent[0],
+                    height: render_area_extent[1],
                 },
             },
             clear_value_count: raw_clear_values.len() as u32,
@@ -1140,6 +1170,54 @@ impl UnsafeCommandBufferBuilder {
         );
     }
 
+    /// Calls `vkCmdDrawIndexedIndirectCount` on the builder.
+    #[inline]
+    pub unsafe fn draw_indexed_indirect_count<B, C>(
+        &mut self,
+        buffer: &B,
+        count_buffer: &C,
+        max_draw_count: u32,
+        stride: u32,
+    ) where
+        B: ?Sized + BufferAccess,
+        C: ?Sized + BufferAccess,
+    {
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+
+        let inner = buffer.inner();
+        debug_assert!(inner.offset < inner.buffer.size());
+        debug_assert!(inner.buffer.usage().indirect_buffer);
+
+        let count_inner = count_buffer.inner();
+        debug_assert!(count_inner.offset < count_inner.buffer.size());
+        debug_assert!(count_inner.buffer.usage().indirect_buffer);
+        debug_assert_eq!(count_inner.offset % 4, 0);
+
+        if self.device().api_version() >= Version::V1_2 {
+            fns.v1_2.cmd_draw_indexed_indirect_count(
+                cmd,
+                inner.buffer.internal_object(),
+                inner.offset,
+                count_inner.buffer.internal_object(),
+                count_inner.offset,
+                max_draw_count,
+                stride,
+            );
+        } else {
+            fns.khr_draw_indirect_count
+                .cmd_draw_indexed_indirect_count_khr(
+                    cmd,
+                    inner.buffer.internal_object(),
+                    inner.offset,
+                    count_inner.buffer.internal_object(),
+                    count_inner.offset,
+                    max_draw_count,
+                    stride,
+                );
+        }
+    }
+
     /// Calls `vkCmdEndQuery` on the builder.
     #[inline]
     pub unsafe fn end_query(&mut self, query: Query) {
@@ -1149,6 +1227,19 @@ impl UnsafeCommandBufferBuilder {
             .cmd_end_query(cmd, query.pool().internal_object(), query.index());
     }
 
+    /// Calls `vkCmdEndQueryIndexedEXT` on the builder.
+    #[inline]
+    pub unsafe fn end_query_indexed(&mut self, query: Query, index: u32) {
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+        fns.ext_transform_feedback.cmd_end_query_indexed_ext(
+            cmd,
+            query.pool().internal_object(),
+            query.index(),
+            index,
+        );
+    }
+
     /// Calls `vkCmdEndRenderPass` on the builder.
     #[inline]
     pub unsafe fn end_render_pass(&mut self) {
@@ -1336,6 +1427,42 @@ impl UnsafeCommandBufferBuilder {
             .cmd_set_event(cmd, event.internal_object(), stages.into());
     }
 
+    /// Calls `vkCmdSetCheckpointNV` on the builder.
+    ///
+    /// The marker is returned as is by `vkGetQueueCheckpointDataNV`.
+    ///
+    /// # Safety
+    ///
+    /// - The `nv_device_diagnostic_checkpoints` extension must be enabled on the device.
+    #[inline]
+    pub unsafe fn set_checkpoint(&mut self, marker: *const c_void) {
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+        debug_assert!(
+            self.device()
+                .enabled_extensions()
+                .nv_device_diagnostic_checkpoints
+        );
+        fns.nv_device_diagnostic_checkpoints
+            .cmd_set_checkpoint_nv(cmd, marker);
+    }
+
+    /// Calls `vkCmdSetDeviceMask` on the builder.
+    #[inline]
+    pub unsafe fn set_device_mask(&mut self, device_mask: u32) {
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+        debug_assert!(device_mask != 0);
+        debug_assert!(device_mask & !self.device().all_devices_mask() == 0);
+
+        if self.device().api_version() >= Version::V1_1 {
+            fns.v1_1.cmd_set_device_mask(cmd, device_mask);
+        } else {
+            fns.khr_device_group
+                .cmd_set_device_mask_khr(cmd, device_mask);
+        }
+    }
+
     /// Calls `vkCmdSetLineWidth` on the builder.
     #[inline]
     pub unsafe fn set_line_width(&mut self, line_width: f32) {
@@ -1400,11 +1527,7 @@ impl UnsafeCommandBufferBuilder {
                 || self.device().enabled_features().multi_viewport
         );
         debug_assert!({
-            let max = self
-                .device()
-                .physical_device()
-                .properties()
-                .max_viewports;
+            let max = self.device().physical_device().properties().max_viewports;
             first_scissor + scissors.len() as u32 <= max
         });
 
@@ -1435,11 +1558,7 @@ impl UnsafeCommandBufferBuilder {
                 || self.device().enabled_features().multi_viewport
         );
         debug_assert!({
-            let max = self
-                .device()
-                .physical_device()
-                .properties()
-                .max_viewports;
+            let max = self.device().physical_device().properties().max_viewports;
             first_viewport + viewports.len() as u32 <= max
         });
 
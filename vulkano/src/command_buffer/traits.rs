@@ -11,6 +11,7 @@ use crate::buffer::BufferAccess;
 use crate::command_buffer::submit::SubmitAnyBuilder;
 use crate::command_buffer::submit::SubmitCommandBufferBuilder;
 use crate::command_buffer::sys::UnsafeCommandBuffer;
+use crate::command_buffer::validity::check_device_mask;
 use crate::command_buffer::CommandBufferInheritance;
 use crate::command_buffer::ImageUninitializedSafe;
 use crate::device::Device;
@@ -138,6 +139,7 @@ pub unsafe trait PrimaryCommandBuffer: DeviceOwned {
             queue,
             submitted: Mutex::new(false),
             finished: AtomicBool::new(false),
+            device_mask: None,
         })
     }
 
@@ -321,6 +323,45 @@ where
     // Therefore we use a `Mutex<bool>` and not an `AtomicBool`.
     submitted: Mutex<bool>,
     finished: AtomicBool,
+    // The physical devices of a device group that execute the command buffer, or `None` for all
+    // of them.
+    device_mask: Option<u32>,
+}
+
+impl<F, Cb> CommandBufferExecFuture<F, Cb>
+where
+    F: GpuFuture,
+    Cb: PrimaryCommandBuffer,
+{
+    /// Executes the command buffer only on the physical devices of `device_mask` of a device
+    /// group, instead of all of them.
+    ///
+    /// This is used for alternate frame rendering, where each frame is rendered by a single
+    /// physical device.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device doesn't support device groups, or if `device_mask` contains
+    ///   physical devices that aren't part of the device, or if `device_mask` is 0.
+    /// - Panics if the command buffer has already been submitted.
+    ///
+    pub fn with_device_mask(mut self, device_mask: u32) -> Self {
+        if let Err(err) = check_device_mask(self.queue.device(), device_mask) {
+            panic!("invalid device mask: {}", err);
+        }
+        assert!(!*self.submitted.get_mut().unwrap());
+        self.device_mask = Some(device_mask);
+        self
+    }
+
+    // Adds the command buffer to `builder`, with its device mask.
+    #[inline]
+    unsafe fn add_to_builder<'a>(&'a self, builder: &mut SubmitCommandBufferBuilder<'a>) {
+        builder.add_command_buffer(self.command_buffer.inner());
+        if let Some(device_mask) = self.device_mask {
+            builder.set_command_buffer_device_mask(device_mask);
+        }
+    }
 }
 
 unsafe impl<F, Cb> GpuFuture for CommandBufferExecFuture<F, Cb>
@@ -337,17 +378,17 @@ where
         Ok(match self.previous.build_submission()? {
             SubmitAnyBuilder::Empty => {
                 let mut builder = SubmitCommandBufferBuilder::new();
-                builder.add_command_buffer(self.command_buffer.inner());
+                self.add_to_builder(&mut builder);
                 SubmitAnyBuilder::CommandBuffer(builder)
             }
             SubmitAnyBuilder::SemaphoresWait(sem) => {
                 let mut builder: SubmitCommandBufferBuilder = sem.into();
-                builder.add_command_buffer(self.command_buffer.inner());
+                self.add_to_builder(&mut builder);
                 SubmitAnyBuilder::CommandBuffer(builder)
             }
             SubmitAnyBuilder::CommandBuffer(mut builder) => {
                 // FIXME: add pipeline barrier
-                builder.add_command_buffer(self.command_buffer.inner());
+                self.add_to_builder(&mut builder);
                 SubmitAnyBuilder::CommandBuffer(builder)
             }
             SubmitAnyBuilder::QueuePresent(_) | SubmitAnyBuilder::BindSparse(_) => {
@@ -0,0 +1,77 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use crate::device::physical::QueueFamily;
+use crate::device::Device;
+
+/// Checks whether a checkpoint can be set in a command buffer of the given queue family.
+pub fn check_checkpoint(
+    device: &Device,
+    queue_family: QueueFamily,
+) -> Result<(), CheckCheckpointError> {
+    if !device.enabled_extensions().nv_device_diagnostic_checkpoints {
+        return Err(CheckCheckpointError::ExtensionNotEnabled);
+    }
+
+    if !(queue_family.supports_graphics()
+        || queue_family.supports_compute()
+        || queue_family.explicitly_supports_transfers())
+    {
+        return Err(CheckCheckpointError::NotSupportedByQueueFamily);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when setting a checkpoint.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckCheckpointError {
+    /// The `nv_device_diagnostic_checkpoints` extension wasn't enabled on the device.
+    ExtensionNotEnabled,
+    /// The queue family doesn't support graphics, compute or transfer operations.
+    NotSupportedByQueueFamily,
+}
+
+impl error::Error for CheckCheckpointError {}
+
+impl fmt::Display for CheckCheckpointError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                CheckCheckpointError::ExtensionNotEnabled => {
+                    "the `nv_device_diagnostic_checkpoints` extension wasn't enabled"
+                }
+                CheckCheckpointError::NotSupportedByQueueFamily => {
+                    "the queue family doesn't support graphics, compute or transfer operations"
+                }
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_buffer::validity;
+
+    #[test]
+    fn missing_extension() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        assert_eq!(
+            validity::check_checkpoint(&device, queue.family()),
+            Err(validity::CheckCheckpointError::ExtensionNotEnabled)
+        );
+    }
+}
@@ -126,7 +126,7 @@ where
 
 /// Computes the minimum required len in elements for buffer with image data in specified
 /// format of specified size.
-fn required_len_for_format<Px>(
+pub(crate) fn required_len_for_format<Px>(
     format: Format,
     image_size: [u32; 3],
     image_num_layers: u32,
@@ -11,6 +11,7 @@ use std::error;
 use std::fmt;
 
 use crate::descriptor_set::layout::DescriptorDescSupersetError;
+use crate::descriptor_set::layout::DescriptorSetLayout;
 use crate::descriptor_set::DescriptorSetWithOffsets;
 use crate::pipeline::layout::PipelineLayout;
 
@@ -19,6 +20,22 @@ pub fn check_descriptor_sets_validity(
     pipeline_layout: &PipelineLayout,
     descriptor_sets: &[DescriptorSetWithOffsets],
 ) -> Result<(), CheckDescriptorSetsValidityError> {
+    check_descriptor_set_layouts(pipeline_layout, |set_num| {
+        descriptor_sets
+            .get(set_num)
+            .map(|so| &**so.as_ref().0.layout())
+    })
+}
+
+// Same as `check_descriptor_sets_validity`, but takes a function that returns the layout of the
+// set bound to each set number.
+pub(crate) fn check_descriptor_set_layouts<'a, F>(
+    pipeline_layout: &PipelineLayout,
+    set_layout: F,
+) -> Result<(), CheckDescriptorSetsValidityError>
+where
+    F: Fn(usize) -> Option<&'a DescriptorSetLayout>,
+{
     // What's important is not that the pipeline layout and the descriptor sets *match*. Instead
     // what's important is that the descriptor sets are a superset of the pipeline layout. It's not
     // a problem if the descriptor sets provide more elements than expected.
@@ -27,9 +44,7 @@ pub fn check_descriptor_sets_validity(
         for (binding_num, pipeline_desc) in
             (0..set.num_bindings()).filter_map(|i| set.descriptor(i).map(|d| (i, d)))
         {
-            let set_desc = descriptor_sets
-                .get(set_num)
-                .and_then(|so| so.as_ref().0.layout().descriptor(binding_num));
+            let set_desc = set_layout(set_num).and_then(|layout| layout.descriptor(binding_num));
 
             let set_desc = match set_desc {
                 Some(s) => s,
@@ -0,0 +1,101 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use crate::device::Device;
+use crate::Version;
+
+/// Checks whether a device mask can be set on a command buffer of the device.
+pub fn check_device_mask(device: &Device, device_mask: u32) -> Result<(), CheckDeviceMaskError> {
+    if !(device.api_version() >= Version::V1_1 || device.enabled_extensions().khr_device_group) {
+        return Err(CheckDeviceMaskError::ExtensionNotEnabled);
+    }
+
+    if device_mask == 0 {
+        return Err(CheckDeviceMaskError::EmptyMask);
+    }
+
+    if device_mask & !device.all_devices_mask() != 0 {
+        return Err(CheckDeviceMaskError::DeviceIndexOutOfRange {
+            device_mask,
+            num_devices: device.physical_devices().len() as u32,
+        });
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when checking a device mask.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckDeviceMaskError {
+    /// The `khr_device_group` extension must be enabled on devices with an API version lower
+    /// than 1.1.
+    ExtensionNotEnabled,
+    /// The mask doesn't contain any physical device.
+    EmptyMask,
+    /// The mask contains a physical device that the device doesn't span.
+    DeviceIndexOutOfRange {
+        /// The requested mask.
+        device_mask: u32,
+        /// Number of physical devices of the device.
+        num_devices: u32,
+    },
+}
+
+impl error::Error for CheckDeviceMaskError {}
+
+impl fmt::Display for CheckDeviceMaskError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                CheckDeviceMaskError::ExtensionNotEnabled => {
+                    "the `khr_device_group` extension must be enabled"
+                }
+                CheckDeviceMaskError::EmptyMask => {
+                    "the mask doesn't contain any physical device"
+                }
+                CheckDeviceMaskError::DeviceIndexOutOfRange { .. } => {
+                    "the mask contains a physical device that the device doesn't span"
+                }
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_buffer::validity;
+
+    #[test]
+    fn out_of_range() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        if validity::check_device_mask(&device, device.all_devices_mask())
+            == Err(validity::CheckDeviceMaskError::ExtensionNotEnabled)
+        {
+            return;
+        }
+
+        assert_eq!(
+            validity::check_device_mask(&device, 0),
+            Err(validity::CheckDeviceMaskError::EmptyMask)
+        );
+
+        let num_devices = device.physical_devices().len() as u32;
+        match validity::check_device_mask(&device, 1 << num_devices) {
+            Err(validity::CheckDeviceMaskError::DeviceIndexOutOfRange { .. }) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -10,6 +10,7 @@
 use crate::buffer::BufferAccess;
 use crate::device::Device;
 use crate::device::DeviceOwned;
+use crate::Version;
 use crate::VulkanObject;
 use std::error;
 use std::fmt;
@@ -34,6 +35,31 @@ where
     Ok(())
 }
 
+/// Checks whether a buffer can be used as the count buffer of an indirect draw with a count.
+pub fn check_indirect_count_buffer<Cb>(
+    device: &Device,
+    count_buffer: &Cb,
+) -> Result<(), CheckIndirectBufferError>
+where
+    Cb: BufferAccess + Send + Sync + 'static,
+{
+    if device.api_version() >= Version::V1_2 {
+        if !device.enabled_features().draw_indirect_count {
+            return Err(CheckIndirectBufferError::DrawIndirectCountNotEnabled);
+        }
+    } else if !device.enabled_extensions().khr_draw_indirect_count {
+        return Err(CheckIndirectBufferError::DrawIndirectCountNotEnabled);
+    }
+
+    check_indirect_buffer(device, count_buffer)?;
+
+    if count_buffer.inner().offset % 4 != 0 {
+        return Err(CheckIndirectBufferError::CountBufferNotAligned);
+    }
+
+    Ok(())
+}
+
 /// Error that can happen when checking whether binding an indirect buffer is valid.
 #[derive(Debug, Copy, Clone)]
 pub enum CheckIndirectBufferError {
@@ -46,6 +72,11 @@ pub enum CheckIndirectBufferError {
         /// What was requested.
         requested: u32,
     },
+    /// The `draw_indirect_count` feature must be enabled on devices with an API version of 1.2,
+    /// and the `khr_draw_indirect_count` extension on devices with a lower API version.
+    DrawIndirectCountNotEnabled,
+    /// The offset of the count buffer must be a multiple of 4.
+    CountBufferNotAligned,
 }
 
 impl error::Error for CheckIndirectBufferError {}
@@ -66,6 +97,12 @@ impl fmt::Display for CheckIndirectBufferError {
                 } => {
                     "the maximum number of indirect draws has been exceeded"
                 }
+                CheckIndirectBufferError::DrawIndirectCountNotEnabled => {
+                    "the `draw_indirect_count` feature or the `khr_draw_indirect_count` extension must be enabled"
+                }
+                CheckIndirectBufferError::CountBufferNotAligned => {
+                    "the offset of the count buffer must be a multiple of 4"
+                }
             }
         )
     }
@@ -0,0 +1,406 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::command_buffer::BarrierBuilder;
+use crate::device::physical::QueueFamily;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::image::ImageAccess;
+use crate::image::ImageLayout;
+use crate::sync::AccessFlags;
+use crate::sync::PipelineStages;
+use crate::VulkanObject;
+use std::error;
+use std::fmt;
+
+/// Checks whether a manual pipeline barrier is valid, for a command buffer of `queue_family`.
+///
+/// # Panic
+///
+/// - Panics if one of the resources was not created with `device`.
+///
+pub fn check_pipeline_barrier(
+    device: &Device,
+    queue_family: QueueFamily,
+    barrier: &BarrierBuilder,
+) -> Result<(), CheckPipelineBarrierError> {
+    let num_queue_families = device.physical_device().queue_families().len() as u32;
+
+    for (buffer, b) in &barrier.buffers {
+        assert_eq!(
+            buffer.inner().buffer.device().internal_object(),
+            device.internal_object()
+        );
+
+        check_scope(
+            b.source_stages,
+            b.source_access,
+            b.destination_stages,
+            b.destination_access,
+        )?;
+        check_queue_family_transfer(b.queue_family_transfer, num_queue_families, queue_family)?;
+
+        if let Some(ref range) = b.range {
+            if range.start >= range.end || range.end > buffer.size() {
+                return Err(CheckPipelineBarrierError::BufferRangeOutOfBounds);
+            }
+        }
+    }
+
+    for (image, b) in &barrier.images {
+        assert_eq!(
+            image.inner().image.device().internal_object(),
+            device.internal_object()
+        );
+
+        check_scope(
+            b.source_stages,
+            b.source_access,
+            b.destination_stages,
+            b.destination_access,
+        )?;
+        check_queue_family_transfer(b.queue_family_transfer, num_queue_families, queue_family)?;
+
+        if b.new_layout == ImageLayout::Undefined || b.new_layout == ImageLayout::Preinitialized {
+            return Err(CheckPipelineBarrierError::InvalidNewLayout);
+        }
+
+        let all_levels = image.current_miplevels_access();
+        let all_layers = image.current_layer_levels_access();
+        let levels = b
+            .mipmap_levels
+            .clone()
+            .unwrap_or_else(|| all_levels.clone());
+        let layers = b.array_layers.clone().unwrap_or_else(|| all_layers.clone());
+
+        if levels.start >= levels.end
+            || levels.end > image.mipmap_levels()
+            || layers.start >= layers.end
+            || layers.end > image.dimensions().array_layers()
+        {
+            return Err(CheckPipelineBarrierError::ImageRangeOutOfBounds);
+        }
+
+        // The layout of an image is tracked for the image as a whole.
+        if b.old_layout != b.new_layout && (levels != all_levels || layers != all_layers) {
+            return Err(CheckPipelineBarrierError::PartialLayoutTransition);
+        }
+    }
+
+    Ok(())
+}
+
+fn check_scope(
+    source_stages: PipelineStages,
+    source_access: AccessFlags,
+    destination_stages: PipelineStages,
+    destination_access: AccessFlags,
+) -> Result<(), CheckPipelineBarrierError> {
+    if source_stages == PipelineStages::none() || destination_stages == PipelineStages::none() {
+        return Err(CheckPipelineBarrierError::EmptyStages);
+    }
+
+    if !source_access.is_compatible_with(&source_stages)
+        || !destination_access.is_compatible_with(&destination_stages)
+    {
+        return Err(CheckPipelineBarrierError::AccessNotCompatibleWithStages);
+    }
+
+    Ok(())
+}
+
+fn check_queue_family_transfer(
+    transfer: Option<(u32, u32)>,
+    num_queue_families: u32,
+    queue_family: QueueFamily,
+) -> Result<(), CheckPipelineBarrierError> {
+    if let Some((source, destination)) = transfer {
+        if source >= num_queue_families || destination >= num_queue_families {
+            return Err(CheckPipelineBarrierError::InvalidQueueFamily);
+        }
+
+        // VUID-vkCmdPipelineBarrier-srcQueueFamilyIndex-02817
+        if source != destination && source != queue_family.id() && destination != queue_family.id()
+        {
+            return Err(CheckPipelineBarrierError::UnrelatedQueueFamilyTransfer);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks whether the old layouts of the image barriers of a manual pipeline barrier are valid.
+///
+/// `current_layout` returns the layout of an image at the point where the barrier is recorded,
+/// or `None` if it isn't known, in which case any old layout is accepted.
+pub fn check_pipeline_barrier_layouts<F>(
+    barrier: &BarrierBuilder,
+    current_layout: F,
+) -> Result<(), CheckPipelineBarrierError>
+where
+    F: Fn(&dyn ImageAccess) -> Option<ImageLayout>,
+{
+    for (image, b) in &barrier.images {
+        if b.old_layout == ImageLayout::Undefined {
+            continue;
+        }
+
+        match current_layout(&**image) {
+            Some(layout) if layout != b.old_layout => {
+                return Err(CheckPipelineBarrierError::OldLayoutMismatch);
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to add a manual pipeline barrier.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckPipelineBarrierError {
+    /// The source or destination stages of a barrier are empty.
+    EmptyStages,
+    /// The source or destination accesses of a barrier are not compatible with its stages.
+    AccessNotCompatibleWithStages,
+    /// The range of a buffer barrier is empty or goes beyond the end of the buffer.
+    BufferRangeOutOfBounds,
+    /// The mipmap levels or array layers of an image barrier are empty or out of range.
+    ImageRangeOutOfBounds,
+    /// The new layout of an image barrier is `Undefined` or `Preinitialized`.
+    InvalidNewLayout,
+    /// An image barrier transitions the layout of only part of the image.
+    PartialLayoutTransition,
+    /// The queue family index of an ownership transfer doesn't exist.
+    InvalidQueueFamily,
+    /// An ownership transfer neither releases the resource from the queue family of the command
+    /// buffer nor acquires it for that queue family.
+    UnrelatedQueueFamilyTransfer,
+    /// The old layout of an image barrier is neither `Undefined` nor the current layout of the
+    /// image.
+    OldLayoutMismatch,
+}
+
+impl error::Error for CheckPipelineBarrierError {}
+
+impl fmt::Display for CheckPipelineBarrierError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                CheckPipelineBarrierError::EmptyStages => {
+                    "the source or destination stages of a barrier are empty"
+                }
+                CheckPipelineBarrierError::AccessNotCompatibleWithStages => {
+                    "the source or destination accesses of a barrier are not compatible with its \
+                     stages"
+                }
+                CheckPipelineBarrierError::BufferRangeOutOfBounds => {
+                    "the range of a buffer barrier is empty or goes beyond the end of the buffer"
+                }
+                CheckPipelineBarrierError::ImageRangeOutOfBounds => {
+                    "the mipmap levels or array layers of an image barrier are empty or out of \
+                     range"
+                }
+                CheckPipelineBarrierError::InvalidNewLayout => {
+                    "the new layout of an image barrier is `Undefined` or `Preinitialized`"
+                }
+                CheckPipelineBarrierError::PartialLayoutTransition => {
+                    "an image barrier transitions the layout of only part of the image"
+                }
+                CheckPipelineBarrierError::InvalidQueueFamily => {
+                    "the queue family index of an ownership transfer doesn't exist"
+                }
+                CheckPipelineBarrierError::UnrelatedQueueFamilyTransfer => {
+                    "an ownership transfer neither releases the resource from the queue family of \
+                     the command buffer nor acquires it for that queue family"
+                }
+                CheckPipelineBarrierError::OldLayoutMismatch => {
+                    "the old layout of an image barrier is neither `Undefined` nor the current \
+                     layout of the image"
+                }
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::command_buffer::BufferMemoryBarrier;
+    use crate::command_buffer::ImageMemoryBarrier;
+    use crate::format::Format;
+    use crate::image::ImageDimensions;
+    use crate::image::StorageImage;
+
+    #[test]
+    fn buffer_range_out_of_bounds() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let buffer =
+            CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                .unwrap();
+
+        let mut barrier = BarrierBuilder::new();
+        barrier.add_buffer(
+            buffer,
+            BufferMemoryBarrier {
+                source_stages: PipelineStages {
+                    transfer: true,
+                    ..PipelineStages::none()
+                },
+                destination_stages: PipelineStages {
+                    transfer: true,
+                    ..PipelineStages::none()
+                },
+                range: Some(0..8),
+                ..Default::default()
+            },
+        );
+
+        match check_pipeline_barrier(&device, queue.family(), &barrier) {
+            Err(CheckPipelineBarrierError::BufferRangeOutOfBounds) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn access_not_compatible() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let buffer =
+            CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                .unwrap();
+
+        let mut barrier = BarrierBuilder::new();
+        barrier.add_buffer(
+            buffer,
+            BufferMemoryBarrier {
+                source_stages: PipelineStages {
+                    transfer: true,
+                    ..PipelineStages::none()
+                },
+                source_access: AccessFlags {
+                    shader_write: true,
+                    ..AccessFlags::none()
+                },
+                destination_stages: PipelineStages {
+                    transfer: true,
+                    ..PipelineStages::none()
+                },
+                ..Default::default()
+            },
+        );
+
+        match check_pipeline_barrier(&device, queue.family(), &barrier) {
+            Err(CheckPipelineBarrierError::AccessNotCompatibleWithStages) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn unrelated_queue_family_transfer() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let buffer =
+            CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                .unwrap();
+
+        // The transfer needs two queue families other than the one of the command buffer.
+        let others: Vec<_> = device
+            .physical_device()
+            .queue_families()
+            .map(|q| q.id())
+            .filter(|&id| id != queue.family().id())
+            .take(2)
+            .collect();
+        if others.len() < 2 {
+            return;
+        }
+
+        let transfer = |families| {
+            let mut barrier = BarrierBuilder::new();
+            barrier.add_buffer(
+                buffer.clone(),
+                BufferMemoryBarrier {
+                    source_stages: PipelineStages {
+                        transfer: true,
+                        ..PipelineStages::none()
+                    },
+                    destination_stages: PipelineStages {
+                        transfer: true,
+                        ..PipelineStages::none()
+                    },
+                    queue_family_transfer: Some(families),
+                    ..Default::default()
+                },
+            );
+            barrier
+        };
+
+        match check_pipeline_barrier(&device, queue.family(), &transfer((others[0], others[1]))) {
+            Err(CheckPipelineBarrierError::UnrelatedQueueFamilyTransfer) => (),
+            _ => panic!(),
+        }
+        let release = transfer((queue.family().id(), others[0]));
+        assert!(check_pipeline_barrier(&device, queue.family(), &release).is_ok());
+        let acquire = transfer((others[0], queue.family().id()));
+        assert!(check_pipeline_barrier(&device, queue.family(), &acquire).is_ok());
+    }
+
+    #[test]
+    fn old_layout_mismatch() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let image = StorageImage::new(
+            device,
+            ImageDimensions::Dim2d {
+                width: 4,
+                height: 4,
+                array_layers: 1,
+            },
+            Format::R8G8B8A8Unorm,
+            Some(queue.family()),
+        )
+        .unwrap();
+
+        let barrier = |old_layout| {
+            let mut barrier = BarrierBuilder::new();
+            barrier.add_image(
+                image.clone(),
+                ImageMemoryBarrier {
+                    source_stages: PipelineStages {
+                        transfer: true,
+                        ..PipelineStages::none()
+                    },
+                    destination_stages: PipelineStages {
+                        transfer: true,
+                        ..PipelineStages::none()
+                    },
+                    old_layout,
+                    new_layout: ImageLayout::General,
+                    ..Default::default()
+                },
+            );
+            barrier
+        };
+        let general = |_: &dyn ImageAccess| Some(ImageLayout::General);
+
+        match check_pipeline_barrier_layouts(&barrier(ImageLayout::TransferDstOptimal), general) {
+            Err(CheckPipelineBarrierError::OldLayoutMismatch) => (),
+            _ => panic!(),
+        }
+        assert!(check_pipeline_barrier_layouts(&barrier(ImageLayout::General), general).is_ok());
+        assert!(check_pipeline_barrier_layouts(&barrier(ImageLayout::Undefined), general).is_ok());
+        assert!(
+            check_pipeline_barrier_layouts(&barrier(ImageLayout::TransferDstOptimal), |_| None)
+                .is_ok()
+        );
+    }
+}
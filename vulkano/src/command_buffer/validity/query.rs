@@ -49,17 +49,62 @@ pub fn check_begin_query(
                 return Err(CheckBeginQueryError::OcclusionQueryPreciseFeatureNotEnabled);
             }
         }
-        QueryType::PipelineStatistics(_) => {
+        QueryType::PipelineStatistics(_) | QueryType::TransformFeedbackStream => {
             if flags.precise {
                 return Err(CheckBeginQueryError::InvalidFlags);
             }
         }
+        QueryType::PerformanceQuery => {
+            if flags.precise {
+                return Err(CheckBeginQueryError::InvalidFlags);
+            }
+
+            if !device.is_profiling_lock_held() {
+                return Err(CheckBeginQueryError::ProfilingLockNotHeld);
+            }
+        }
         QueryType::Timestamp => return Err(CheckBeginQueryError::NotPermitted),
     }
 
     Ok(())
 }
 
+/// Checks whether a `begin_query_indexed` command is valid.
+///
+/// # Panic
+///
+/// - Panics if the query pool was not created with `device`.
+pub fn check_begin_query_indexed(
+    device: &Device,
+    query_pool: &QueryPool,
+    query: u32,
+    flags: QueryControlFlags,
+    index: u32,
+) -> Result<(), CheckBeginQueryError> {
+    check_begin_query(device, query_pool, query, flags)?;
+
+    if !is_valid_query_index(device, query_pool, index) {
+        return Err(CheckBeginQueryError::IndexOutOfRange);
+    }
+
+    Ok(())
+}
+
+// Only transform feedback stream queries can have an index other than 0, the vertex stream.
+fn is_valid_query_index(device: &Device, query_pool: &QueryPool, index: u32) -> bool {
+    match query_pool.ty() {
+        QueryType::TransformFeedbackStream => {
+            index
+                < device
+                    .physical_device()
+                    .properties()
+                    .max_transform_feedback_streams
+                    .unwrap_or(1)
+        }
+        _ => index == 0,
+    }
+}
+
 /// Error that can happen from `check_begin_query`.
 #[derive(Debug, Copy, Clone)]
 pub enum CheckBeginQueryError {
@@ -71,6 +116,11 @@ pub enum CheckBeginQueryError {
     OcclusionQueryPreciseFeatureNotEnabled,
     /// The provided query index is not valid for this pool.
     OutOfRange,
+    /// The query pool is a performance query pool, but the profiling lock of the device isn't
+    /// held.
+    ProfilingLockNotHeld,
+    /// The provided index is not valid for this type of query.
+    IndexOutOfRange,
 }
 
 impl error::Error for CheckBeginQueryError {}
@@ -94,6 +144,12 @@ impl fmt::Display for CheckBeginQueryError {
                 Self::OutOfRange => {
                     "the provided query index is not valid for this pool"
                 }
+                Self::ProfilingLockNotHeld => {
+                    "the query pool is a performance query pool, but the profiling lock of the device isn't held"
+                }
+                Self::IndexOutOfRange => {
+                    "the provided index is not valid for this type of query"
+                }
             }
         )
     }
@@ -121,11 +177,33 @@ pub fn check_end_query(
     Ok(())
 }
 
+/// Checks whether a `end_query_indexed` command is valid.
+///
+/// # Panic
+///
+/// - Panics if the query pool was not created with `device`.
+pub fn check_end_query_indexed(
+    device: &Device,
+    query_pool: &QueryPool,
+    query: u32,
+    index: u32,
+) -> Result<(), CheckEndQueryError> {
+    check_end_query(device, query_pool, query)?;
+
+    if !is_valid_query_index(device, query_pool, index) {
+        return Err(CheckEndQueryError::IndexOutOfRange);
+    }
+
+    Ok(())
+}
+
 /// Error that can happen from `check_end_query`.
 #[derive(Debug, Copy, Clone)]
 pub enum CheckEndQueryError {
     /// The provided query index is not valid for this pool.
     OutOfRange,
+    /// The provided index is not valid for this type of query.
+    IndexOutOfRange,
 }
 
 impl error::Error for CheckEndQueryError {}
@@ -140,6 +218,9 @@ impl fmt::Display for CheckEndQueryError {
                 Self::OutOfRange => {
                     "the provided query index is not valid for this pool"
                 }
+                Self::IndexOutOfRange => {
+                    "the provided index is not valid for this type of query"
+                }
             }
         )
     }
@@ -299,6 +380,8 @@ pub enum CheckCopyQueryPoolResultsError {
     DestinationMissingTransferUsage,
     /// The provided flags are not allowed for this type of query.
     InvalidFlags,
+    /// This operation is not permitted on this query type.
+    NotPermitted,
     /// The provided queries range is not valid for this pool.
     OutOfRange,
 }
@@ -315,6 +398,7 @@ impl From<GetResultsError> for CheckCopyQueryPoolResultsError {
                 actual_len,
             },
             GetResultsError::InvalidFlags => CheckCopyQueryPoolResultsError::InvalidFlags,
+            GetResultsError::InvalidQueryType => CheckCopyQueryPoolResultsError::NotPermitted,
             GetResultsError::DeviceLost | GetResultsError::OomError(_) => unreachable!(),
         }
     }
@@ -338,6 +422,9 @@ impl fmt::Display for CheckCopyQueryPoolResultsError {
                 Self::InvalidFlags => {
                     "the provided flags are not allowed for this type of query"
                 }
+                Self::NotPermitted => {
+                    "this operation is not permitted on this query type"
+                }
                 Self::OutOfRange => {
                     "the provided queries range is not valid for this pool"
                 }
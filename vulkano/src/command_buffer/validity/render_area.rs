@@ -0,0 +1,123 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::render_pass::FramebufferAbstract;
+use std::error;
+use std::fmt;
+
+/// Checks whether a render area is valid for beginning a render pass with the given framebuffer.
+///
+/// The render area must be contained inside the framebuffer. It doesn't need to be aligned to
+/// the granularity of the render pass, but performance may be worse if it isn't. Use
+/// `RenderPass::is_render_area_optimal` to check the alignment.
+pub fn check_render_area<F>(
+    framebuffer: &F,
+    offset: [u32; 2],
+    extent: [u32; 2],
+) -> Result<(), CheckRenderAreaError>
+where
+    F: ?Sized + FramebufferAbstract,
+{
+    let dimensions = framebuffer.dimensions();
+
+    for i in 0..2 {
+        if offset[i] as u64 + extent[i] as u64 > dimensions[i] as u64 {
+            return Err(CheckRenderAreaError::OutOfFramebufferBounds {
+                offset,
+                extent,
+                framebuffer_dimensions: [dimensions[0], dimensions[1]],
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to begin a render pass with a custom render area.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckRenderAreaError {
+    /// The render area is not contained inside the framebuffer.
+    OutOfFramebufferBounds {
+        offset: [u32; 2],
+        extent: [u32; 2],
+        framebuffer_dimensions: [u32; 2],
+    },
+}
+
+impl error::Error for CheckRenderAreaError {}
+
+impl fmt::Display for CheckRenderAreaError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                CheckRenderAreaError::OutOfFramebufferBounds { .. } => {
+                    "the render area is not contained inside the framebuffer"
+                }
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::Format;
+    use crate::image::view::ImageView;
+    use crate::image::AttachmentImage;
+    use crate::render_pass::Framebuffer;
+    use std::sync::Arc;
+
+    #[test]
+    fn bounds() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let render_pass = Arc::new(
+            crate::single_pass_renderpass!(device.clone(),
+                attachments: {
+                    color: {
+                        load: Clear,
+                        store: Store,
+                        format: Format::R8G8B8A8Unorm,
+                        samples: 1,
+                    }
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {}
+                }
+            )
+            .unwrap(),
+        );
+        let [gw, gh] = render_pass.granularity();
+        let dimensions = [gw * 4, gh * 4];
+        let view = ImageView::new(
+            AttachmentImage::new(device, dimensions, Format::R8G8B8A8Unorm).unwrap(),
+        )
+        .unwrap();
+        let framebuffer = Framebuffer::start(render_pass)
+            .add(view)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert!(check_render_area(&framebuffer, [0, 0], dimensions).is_ok());
+        assert!(check_render_area(&framebuffer, [gw, gh], [gw * 3, gh * 3]).is_ok());
+
+        match check_render_area(&framebuffer, [gw, 0], [u32::MAX, gh]) {
+            Err(CheckRenderAreaError::OutOfFramebufferBounds { .. }) => (),
+            _ => panic!(),
+        }
+
+        // Unaligned render areas are allowed, they are only slower.
+        assert!(check_render_area(&framebuffer, [1, 0], [gw, gh]).is_ok());
+    }
+}
@@ -0,0 +1,268 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::render_pass::RenderPassDesc;
+use crate::sync::AccessFlags;
+use crate::sync::PipelineStages;
+use std::error;
+use std::fmt;
+
+/// Checks whether a pipeline barrier can be recorded inside the given subpass.
+///
+/// The subpass must have a self-dependency whose stages and accesses are a superset of the
+/// barrier's, and whose `by_region` flag is the same as the barrier's.
+pub fn check_subpass_barrier(
+    render_pass_desc: &RenderPassDesc,
+    subpass: u32,
+    source_stages: PipelineStages,
+    source_access: AccessFlags,
+    destination_stages: PipelineStages,
+    destination_access: AccessFlags,
+    by_region: bool,
+) -> Result<(), CheckSubpassBarrierError> {
+    if source_stages.host || destination_stages.host {
+        return Err(CheckSubpassBarrierError::HostStage);
+    }
+
+    if !source_access.is_compatible_with(&source_stages)
+        || !destination_access.is_compatible_with(&destination_stages)
+    {
+        return Err(CheckSubpassBarrierError::AccessNotCompatibleWithStages);
+    }
+
+    if source_stages.has_framebuffer_space_stage()
+        && destination_stages.has_framebuffer_space_stage()
+        && !by_region
+    {
+        return Err(CheckSubpassBarrierError::NotByRegion);
+    }
+
+    let mut self_dependencies = render_pass_desc
+        .self_dependencies(subpass as usize)
+        .peekable();
+
+    if self_dependencies.peek().is_none() {
+        return Err(CheckSubpassBarrierError::NoSelfDependency);
+    }
+
+    let is_subset = |a: ash::vk::Flags, b: ash::vk::Flags| a & !b == 0;
+
+    let matches = self_dependencies.any(|dep| {
+        dep.by_region == by_region
+            && is_subset(
+                ash::vk::PipelineStageFlags::from(source_stages).as_raw(),
+                ash::vk::PipelineStageFlags::from(dep.source_stages).as_raw(),
+            )
+            && is_subset(
+                ash::vk::PipelineStageFlags::from(destination_stages).as_raw(),
+                ash::vk::PipelineStageFlags::from(dep.destination_stages).as_raw(),
+            )
+            && is_subset(
+                ash::vk::AccessFlags::from(source_access).as_raw(),
+                ash::vk::AccessFlags::from(dep.source_access).as_raw(),
+            )
+            && is_subset(
+                ash::vk::AccessFlags::from(destination_access).as_raw(),
+                ash::vk::AccessFlags::from(dep.destination_access).as_raw(),
+            )
+    });
+
+    if !matches {
+        return Err(CheckSubpassBarrierError::NotCoveredBySelfDependency);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to add a pipeline barrier inside a subpass.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckSubpassBarrierError {
+    /// The access flags are not compatible with the pipeline stages.
+    AccessNotCompatibleWithStages,
+    /// The host stage can't be used in a pipeline barrier inside a render pass.
+    HostStage,
+    /// The current subpass doesn't have a self-dependency.
+    NoSelfDependency,
+    /// Both the source and destination stages include framebuffer-space stages, but the barrier
+    /// is not `by_region`.
+    NotByRegion,
+    /// None of the self-dependencies of the current subpass is a superset of the barrier.
+    NotCoveredBySelfDependency,
+}
+
+impl error::Error for CheckSubpassBarrierError {}
+
+impl fmt::Display for CheckSubpassBarrierError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                CheckSubpassBarrierError::AccessNotCompatibleWithStages => {
+                    "the access flags are not compatible with the pipeline stages"
+                }
+                CheckSubpassBarrierError::HostStage => {
+                    "the host stage can't be used in a pipeline barrier inside a render pass"
+                }
+                CheckSubpassBarrierError::NoSelfDependency => {
+                    "the current subpass doesn't have a self-dependency"
+                }
+                CheckSubpassBarrierError::NotByRegion => {
+                    "both the source and destination stages include framebuffer-space stages, \
+                     but the barrier is not by region"
+                }
+                CheckSubpassBarrierError::NotCoveredBySelfDependency => {
+                    "none of the self-dependencies of the current subpass is a superset of the \
+                     barrier"
+                }
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::Format;
+    use crate::image::ImageLayout;
+    use crate::image::SampleCount;
+    use crate::render_pass::AttachmentDesc;
+    use crate::render_pass::LoadOp;
+    use crate::render_pass::StoreOp;
+    use crate::render_pass::SubpassDependencyDesc;
+    use crate::render_pass::SubpassDesc;
+
+    fn desc(dependencies: Vec<SubpassDependencyDesc>) -> RenderPassDesc {
+        RenderPassDesc::new(
+            vec![AttachmentDesc {
+                format: Format::R8G8B8A8Unorm,
+                samples: SampleCount::Sample1,
+                load: LoadOp::Load,
+                store: StoreOp::Store,
+                stencil_load: LoadOp::DontCare,
+                stencil_store: StoreOp::DontCare,
+                initial_layout: ImageLayout::General,
+                final_layout: ImageLayout::General,
+            }],
+            vec![SubpassDesc {
+                color_attachments: vec![(0, ImageLayout::General)],
+                depth_stencil: None,
+                input_attachments: vec![(0, ImageLayout::General)],
+                resolve_attachments: vec![],
+                preserve_attachments: vec![],
+            }],
+            dependencies,
+        )
+    }
+
+    fn feedback_dependency() -> SubpassDependencyDesc {
+        SubpassDependencyDesc {
+            source_subpass: 0,
+            destination_subpass: 0,
+            source_stages: PipelineStages {
+                color_attachment_output: true,
+                ..PipelineStages::none()
+            },
+            destination_stages: PipelineStages {
+                fragment_shader: true,
+                ..PipelineStages::none()
+            },
+            source_access: AccessFlags {
+                color_attachment_write: true,
+                ..AccessFlags::none()
+            },
+            destination_access: AccessFlags {
+                input_attachment_read: true,
+                ..AccessFlags::none()
+            },
+            by_region: true,
+        }
+    }
+
+    #[test]
+    fn no_self_dependency() {
+        let desc = desc(vec![]);
+        let dep = feedback_dependency();
+
+        assert_eq!(
+            check_subpass_barrier(
+                &desc,
+                0,
+                dep.source_stages,
+                dep.source_access,
+                dep.destination_stages,
+                dep.destination_access,
+                true,
+            ),
+            Err(CheckSubpassBarrierError::NoSelfDependency)
+        );
+    }
+
+    #[test]
+    fn covered() {
+        let desc = desc(vec![feedback_dependency()]);
+        let dep = feedback_dependency();
+
+        assert_eq!(
+            check_subpass_barrier(
+                &desc,
+                0,
+                dep.source_stages,
+                dep.source_access,
+                dep.destination_stages,
+                dep.destination_access,
+                true,
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn not_covered() {
+        let desc = desc(vec![feedback_dependency()]);
+        let dep = feedback_dependency();
+
+        assert_eq!(
+            check_subpass_barrier(
+                &desc,
+                0,
+                dep.source_stages,
+                dep.source_access,
+                dep.destination_stages,
+                AccessFlags {
+                    input_attachment_read: true,
+                    shader_read: true,
+                    ..AccessFlags::none()
+                },
+                true,
+            ),
+            Err(CheckSubpassBarrierError::NotCoveredBySelfDependency)
+        );
+    }
+
+    #[test]
+    fn not_by_region() {
+        let desc = desc(vec![feedback_dependency()]);
+        let dep = feedback_dependency();
+
+        assert_eq!(
+            check_subpass_barrier(
+                &desc,
+                0,
+                dep.source_stages,
+                dep.source_access,
+                dep.destination_stages,
+                dep.destination_access,
+                false,
+            ),
+            Err(CheckSubpassBarrierError::NotByRegion)
+        );
+    }
+}
@@ -0,0 +1,1114 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! GPU-driven culling of objects, and indirect draws of the objects that are visible.
+//!
+//! A [`GpuCulling`] holds the objects of a scene. Each object has a transformation matrix, a
+//! bounding sphere, and the range of the shared index buffer that draws it. At each frame,
+//! `cull` records a compute pass that tests the bounding sphere of every object against the
+//! planes of the view frustum, and appends a `DrawIndexedIndirectCommand` for each visible
+//! object to an indirect buffer, while incrementing a count. `draw` then records a
+//! `draw_indexed_indirect_count` command that draws the visible objects, without reading the
+//! result back to the CPU.
+//!
+//! The `first_instance` of each command is the index of the object, so that the vertex shader
+//! can read the transformation of the object with `gl_InstanceIndex` from the buffer returned by
+//! `transforms`.
+//!
+//! The bounding spheres are transformed to world space on the CPU, when the objects are
+//! inserted or modified. The buffers are uploaded by the next call to `cull`.
+//!
+//! # Custom culling shaders
+//!
+//! `with_shader` replaces the built-in frustum culling shader, for example by a shader that also
+//! tests the bounding spheres against a depth pyramid of the previous frame for occlusion
+//! culling. The shader must access the same descriptors in set 0 and the same push constants as
+//! the built-in shader, whose source is in this file. The other sets that it accesses are passed
+//! to `cull_with_sets`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use vulkano::buffer::TypedBufferAccess;
+//! # use vulkano::command_buffer::AutoCommandBufferBuilder;
+//! # use vulkano::command_buffer::DynamicState;
+//! # use vulkano::command_buffer::PrimaryAutoCommandBuffer;
+//! # use vulkano::descriptor_set::DescriptorSet;
+//! # use vulkano::device::Queue;
+//! # use vulkano::pipeline::GraphicsPipeline;
+//! # use vulkano::buffer::BufferAccess;
+//! # let queue: Arc<Queue> = return;
+//! # let mut builder: AutoCommandBufferBuilder<PrimaryAutoCommandBuffer> = return;
+//! # let pipeline: Arc<GraphicsPipeline> = return;
+//! # let dynamic: DynamicState = return;
+//! # let vertex_buffer: Arc<dyn BufferAccess + Send + Sync> = return;
+//! # let index_buffer: Arc<vulkano::buffer::ImmutableBuffer<[u32]>> = return;
+//! # let transforms_set: Arc<dyn DescriptorSet + Send + Sync> = return;
+//! # let view_proj: [[f32; 4]; 4] = return;
+//! use vulkano::culling::CulledObject;
+//! use vulkano::culling::GpuCulling;
+//!
+//! let mut culling = GpuCulling::new(queue.device().clone(), 1024, [queue.family()]).unwrap();
+//!
+//! let object = culling
+//!     .insert(CulledObject {
+//!         transform: [
+//!             [1.0, 0.0, 0.0, 0.0],
+//!             [0.0, 1.0, 0.0, 0.0],
+//!             [0.0, 0.0, 1.0, 0.0],
+//!             [0.0, 0.0, -5.0, 1.0],
+//!         ],
+//!         center: [0.0, 0.0, 0.0],
+//!         radius: 1.0,
+//!         index_count: 36,
+//!         first_index: 0,
+//!         vertex_offset: 0,
+//!     })
+//!     .unwrap();
+//!
+//! // Outside of a render pass.
+//! culling.cull(&mut builder, view_proj).unwrap();
+//!
+//! // Inside the render pass. `transforms_set` contains `culling.transforms()`.
+//! culling
+//!     .draw(
+//!         &mut builder,
+//!         pipeline,
+//!         &dynamic,
+//!         vec![vertex_buffer],
+//!         index_buffer,
+//!         transforms_set,
+//!         (),
+//!     )
+//!     .unwrap();
+//! ```
+
+use crate::buffer::BufferAccess;
+use crate::buffer::BufferUsage;
+use crate::buffer::CpuAccessibleBuffer;
+use crate::buffer::DeviceLocalBuffer;
+use crate::buffer::TypedBufferAccess;
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::CopyBufferError;
+use crate::command_buffer::DispatchError;
+use crate::command_buffer::DrawIndexedIndirectCommand;
+use crate::command_buffer::DrawIndexedIndirectCountError;
+use crate::command_buffer::DynamicState;
+use crate::command_buffer::FillBufferError;
+use crate::descriptor_set::layout::DescriptorBufferDesc;
+use crate::descriptor_set::layout::DescriptorDesc;
+use crate::descriptor_set::layout::DescriptorDescTy;
+use crate::descriptor_set::layout::DescriptorSetDesc;
+use crate::descriptor_set::DescriptorSet;
+use crate::descriptor_set::DescriptorSetsCollection;
+use crate::descriptor_set::PersistentDescriptorSet;
+use crate::descriptor_set::PersistentDescriptorSetBuildError;
+use crate::descriptor_set::PersistentDescriptorSetError;
+use crate::device::physical::QueueFamily;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::memory::DeviceMemoryAllocError;
+use crate::pipeline::input_assembly::Index;
+use crate::pipeline::layout::PipelineLayoutPcRange;
+use crate::pipeline::shader::EntryPointAbstract;
+use crate::pipeline::shader::ShaderModule;
+use crate::pipeline::shader::ShaderStages;
+use crate::pipeline::vertex::VertexBuffersCollection;
+use crate::pipeline::ComputePipeline;
+use crate::pipeline::ComputePipelineCreationError;
+use crate::pipeline::GraphicsPipeline;
+use crate::OomError;
+use std::error;
+use std::ffi::CStr;
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+
+/// Number of invocations in a workgroup of the built-in culling shader.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// An object that a `GpuCulling` culls and draws.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CulledObject {
+    /// Transformation from the space of the object to world space, as a column-major matrix.
+    pub transform: [[f32; 4]; 4],
+    /// Center of the bounding sphere, in the space of the object.
+    pub center: [f32; 3],
+    /// Radius of the bounding sphere, in the space of the object.
+    pub radius: f32,
+    /// Number of indices that draw the object.
+    pub index_count: u32,
+    /// Index of the first index that draws the object, in the index buffer.
+    pub first_index: u32,
+    /// Value that is added to the indices before indexing the vertex buffers.
+    pub vertex_offset: u32,
+}
+
+/// Push constants of the culling shader.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct CullingConstants {
+    planes: [[f32; 4]; 6],
+    num_objects: u32,
+}
+
+/// Culls objects on the GPU and draws the visible ones.
+///
+/// See [the module-level documentation](self) for more information.
+pub struct GpuCulling {
+    pipeline: Arc<ComputePipeline>,
+    descriptor_set: Arc<dyn DescriptorSet + Send + Sync>,
+    objects: Vec<Option<CulledObject>>,
+    free_slots: Vec<u32>,
+    dirty: bool,
+    bounds: Arc<DeviceLocalBuffer<[[f32; 4]]>>,
+    transforms: Arc<DeviceLocalBuffer<[[[f32; 4]; 4]]>>,
+    draws: Arc<DeviceLocalBuffer<[DrawIndexedIndirectCommand]>>,
+    visible_draws: Arc<DeviceLocalBuffer<[DrawIndexedIndirectCommand]>>,
+    draw_count: Arc<DeviceLocalBuffer<u32>>,
+}
+
+impl GpuCulling {
+    /// Builds a new `GpuCulling` that holds up to `capacity` objects, with the built-in frustum
+    /// culling shader.
+    ///
+    /// The [`draw_indirect_first_instance`](crate::device::Features::draw_indirect_first_instance)
+    /// feature must be enabled on the device, because the draw commands use the index of the
+    /// object as `first_instance`. Drawing more than one object also requires the
+    /// [`multi_draw_indirect`](crate::device::Features::multi_draw_indirect) feature, and
+    /// `draw` has the same requirements as `draw_indexed_indirect_count`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `capacity` is 0.
+    ///
+    pub fn new<'a, I>(
+        device: Arc<Device>,
+        capacity: u32,
+        queue_families: I,
+    ) -> Result<GpuCulling, GpuCullingCreationError>
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        let module = unsafe { ShaderModule::from_words(device.clone(), &CULLING_SHADER)? };
+
+        let shader = unsafe {
+            let storage_buffer = |readonly| {
+                Some(DescriptorDesc {
+                    ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                        dynamic: None,
+                        storage: true,
+                    }),
+                    array_count: 1,
+                    stages: ShaderStages {
+                        compute: true,
+                        ..ShaderStages::none()
+                    },
+                    readonly,
+                })
+            };
+
+            module.compute_entry_point(
+                CStr::from_bytes_with_nul_unchecked(b"main\0"),
+                [DescriptorSetDesc::new([
+                    storage_buffer(true),
+                    storage_buffer(true),
+                    storage_buffer(false),
+                    storage_buffer(false),
+                ])],
+                Some(PipelineLayoutPcRange {
+                    offset: 0,
+                    size: mem::size_of::<CullingConstants>(),
+                    stages: ShaderStages {
+                        compute: true,
+                        ..ShaderStages::none()
+                    },
+                }),
+                &[],
+            )
+        };
+
+        GpuCulling::with_shader(device, &shader, capacity, queue_families)
+    }
+
+    /// Same as `new`, but with a custom culling shader.
+    ///
+    /// See [the module-level documentation](self#custom-culling-shaders) for the interface of
+    /// the shader. The same features are required as by `new`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `capacity` is 0.
+    ///
+    pub fn with_shader<'a, Cs, I>(
+        device: Arc<Device>,
+        shader: &Cs,
+        capacity: u32,
+        queue_families: I,
+    ) -> Result<GpuCulling, GpuCullingCreationError>
+    where
+        Cs: EntryPointAbstract,
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        assert!(capacity != 0);
+
+        if !device.enabled_features().draw_indirect_first_instance {
+            return Err(GpuCullingCreationError::DrawIndirectFirstInstanceFeatureNotEnabled);
+        }
+
+        let pipeline = Arc::new(ComputePipeline::new(device.clone(), shader, &(), None)?);
+        let queue_families = queue_families.into_iter().collect::<Vec<_>>();
+
+        let storage = BufferUsage {
+            storage_buffer: true,
+            transfer_destination: true,
+            ..BufferUsage::none()
+        };
+        let indirect = BufferUsage {
+            indirect_buffer: true,
+            ..storage
+        };
+
+        let len = capacity as u64;
+        let bounds =
+            DeviceLocalBuffer::array(device.clone(), len, storage, queue_families.iter().cloned())?;
+        let transforms =
+            DeviceLocalBuffer::array(device.clone(), len, storage, queue_families.iter().cloned())?;
+        let draws =
+            DeviceLocalBuffer::array(device.clone(), len, storage, queue_families.iter().cloned())?;
+        let visible_draws = DeviceLocalBuffer::array(
+            device.clone(),
+            len,
+            indirect,
+            queue_families.iter().cloned(),
+        )?;
+        let draw_count = DeviceLocalBuffer::new(device, indirect, queue_families)?;
+
+        let layout = pipeline.layout().descriptor_set_layouts()[0].clone();
+        let descriptor_set = Arc::new(
+            PersistentDescriptorSet::start(layout)
+                .add_buffer(bounds.clone())?
+                .add_buffer(draws.clone())?
+                .add_buffer(visible_draws.clone())?
+                .add_buffer(draw_count.clone())?
+                .build()?,
+        );
+
+        Ok(GpuCulling {
+            pipeline,
+            descriptor_set,
+            objects: Vec::new(),
+            free_slots: Vec::new(),
+            dirty: false,
+            bounds,
+            transforms,
+            draws,
+            visible_draws,
+            draw_count,
+        })
+    }
+
+    /// Returns the maximum number of objects.
+    #[inline]
+    pub fn capacity(&self) -> u32 {
+        self.visible_draws.len() as u32
+    }
+
+    /// Returns the number of objects.
+    #[inline]
+    pub fn len(&self) -> u32 {
+        (self.objects.len() - self.free_slots.len()) as u32
+    }
+
+    /// Returns true if there is no object.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds an object, and returns its index. Returns `None` if the capacity is reached.
+    ///
+    /// The index of an object is the `first_instance` of its draw command, and stays the same
+    /// until the object is removed. The indices of removed objects are reused.
+    pub fn insert(&mut self, object: CulledObject) -> Option<u32> {
+        let index = match self.free_slots.pop() {
+            Some(index) => {
+                self.objects[index as usize] = Some(object);
+                index
+            }
+            None if self.objects.len() < self.capacity() as usize => {
+                self.objects.push(Some(object));
+                self.objects.len() as u32 - 1
+            }
+            None => return None,
+        };
+
+        self.dirty = true;
+        Some(index)
+    }
+
+    /// Returns the object with the given index.
+    #[inline]
+    pub fn get(&self, index: u32) -> Option<&CulledObject> {
+        self.objects.get(index as usize).and_then(Option::as_ref)
+    }
+
+    /// Replaces the object with the given index, for example to move it.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if there is no object with this index.
+    ///
+    #[inline]
+    pub fn set(&mut self, index: u32, object: CulledObject) {
+        let slot = self
+            .objects
+            .get_mut(index as usize)
+            .and_then(Option::as_mut);
+        *slot.expect("no object with this index") = object;
+        self.dirty = true;
+    }
+
+    /// Removes the object with the given index, and returns it.
+    pub fn remove(&mut self, index: u32) -> Option<CulledObject> {
+        let object = self.objects.get_mut(index as usize)?.take()?;
+        self.free_slots.push(index);
+
+        // Trailing empty slots don't need to be culled.
+        while let Some(None) = self.objects.last() {
+            self.objects.pop();
+        }
+        let len = self.objects.len() as u32;
+        self.free_slots.retain(|&index| index < len);
+
+        self.dirty = true;
+        Some(object)
+    }
+
+    /// Removes all the objects.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.objects.clear();
+        self.free_slots.clear();
+        self.dirty = true;
+    }
+
+    /// Returns the buffer of the transformation matrices of the objects, indexed by the indices
+    /// of the objects.
+    ///
+    /// The vertex shader reads the matrix of the object with `gl_InstanceIndex`.
+    #[inline]
+    pub fn transforms(&self) -> &Arc<DeviceLocalBuffer<[[[f32; 4]; 4]]>> {
+        &self.transforms
+    }
+
+    /// Returns the buffer of the bounding spheres of the objects in world space, indexed by the
+    /// indices of the objects.
+    ///
+    /// The center is in the first three components, and the radius in the last one.
+    #[inline]
+    pub fn bounds(&self) -> &Arc<DeviceLocalBuffer<[[f32; 4]]>> {
+        &self.bounds
+    }
+
+    /// Returns the buffer that the culling pass writes the draw commands of the visible objects
+    /// to.
+    #[inline]
+    pub fn indirect_buffer(&self) -> &Arc<DeviceLocalBuffer<[DrawIndexedIndirectCommand]>> {
+        &self.visible_draws
+    }
+
+    /// Returns the buffer that the culling pass writes the number of visible objects to.
+    #[inline]
+    pub fn count_buffer(&self) -> &Arc<DeviceLocalBuffer<u32>> {
+        &self.draw_count
+    }
+
+    /// Records the upload of the objects if they were modified, and the culling pass.
+    ///
+    /// `view_proj` is the column-major matrix that transforms world space to clip space. The
+    /// command must be recorded outside of a render pass.
+    #[inline]
+    pub fn cull<L, P>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<L, P>,
+        view_proj: [[f32; 4]; 4],
+    ) -> Result<(), CullError> {
+        self.cull_with_sets(builder, view_proj, Vec::new())
+    }
+
+    /// Same as `cull`, but binds `descriptor_sets` from set 1 onwards, for a custom culling
+    /// shader.
+    pub fn cull_with_sets<L, P>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<L, P>,
+        view_proj: [[f32; 4]; 4],
+        descriptor_sets: Vec<Arc<dyn DescriptorSet + Send + Sync>>,
+    ) -> Result<(), CullError> {
+        if self.dirty && !self.objects.is_empty() {
+            self.upload(builder)?;
+        }
+        self.dirty = false;
+
+        builder.fill_buffer(self.draw_count.clone(), 0)?;
+
+        let num_objects = self.objects.len() as u32;
+        if num_objects == 0 {
+            return Ok(());
+        }
+
+        let mut sets = vec![self.descriptor_set.clone()];
+        sets.extend(descriptor_sets);
+
+        builder.dispatch(
+            [(num_objects + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE, 1, 1],
+            self.pipeline.clone(),
+            sets,
+            CullingConstants {
+                planes: frustum_planes(view_proj),
+                num_objects,
+            },
+        )?;
+
+        Ok(())
+    }
+
+    // Records the copy of the objects to the device-local buffers.
+    fn upload<L, P>(&self, builder: &mut AutoCommandBufferBuilder<L, P>) -> Result<(), CullError> {
+        let device = self.draws.device().clone();
+        let staging = BufferUsage::transfer_source();
+
+        let bounds = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            staging,
+            false,
+            self.objects.iter().map(|object| match object {
+                Some(object) => world_bounds(object),
+                // The sphere of an empty slot is never visible.
+                None => [0.0, 0.0, 0.0, f32::NEG_INFINITY],
+            }),
+        )?;
+        let transforms = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            staging,
+            false,
+            self.objects.iter().map(|object| match object {
+                Some(object) => object.transform,
+                None => [[0.0; 4]; 4],
+            }),
+        )?;
+        let draws = CpuAccessibleBuffer::from_iter(
+            device,
+            staging,
+            false,
+            self.objects
+                .iter()
+                .enumerate()
+                .map(|(index, object)| DrawIndexedIndirectCommand {
+                    index_count: object.map_or(0, |object| object.index_count),
+                    instance_count: 1,
+                    first_index: object.map_or(0, |object| object.first_index),
+                    vertex_offset: object.map_or(0, |object| object.vertex_offset),
+                    first_instance: index as u32,
+                }),
+        )?;
+
+        builder
+            .copy_buffer(bounds, self.bounds.clone())?
+            .copy_buffer(transforms, self.transforms.clone())?
+            .copy_buffer(draws, self.draws.clone())?;
+        Ok(())
+    }
+
+    /// Records a draw of the objects that the last culling pass found visible, with
+    /// `draw_indexed_indirect_count`.
+    ///
+    /// The command must be recorded inside a render pass, after `cull`. The parameters are the
+    /// same as `draw_indexed_indirect_count`.
+    #[inline]
+    pub fn draw<L, P, V, Ib, I, S, Pc>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L, P>,
+        pipeline: Arc<GraphicsPipeline>,
+        dynamic: &DynamicState,
+        vertex_buffers: V,
+        index_buffer: Ib,
+        descriptor_sets: S,
+        push_constants: Pc,
+    ) -> Result<(), DrawIndexedIndirectCountError>
+    where
+        V: VertexBuffersCollection,
+        Ib: BufferAccess + TypedBufferAccess<Content = [I]> + Send + Sync + 'static,
+        I: Index + 'static,
+        S: DescriptorSetsCollection,
+    {
+        builder.draw_indexed_indirect_count(
+            pipeline,
+            dynamic,
+            vertex_buffers,
+            index_buffer,
+            self.visible_draws.clone(),
+            self.draw_count.clone(),
+            descriptor_sets,
+            push_constants,
+        )?;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for GpuCulling {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("GpuCulling")
+            .field("pipeline", &self.pipeline)
+            .field("capacity", &self.capacity())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Returns the planes of the view frustum of a column-major matrix that transforms world space
+/// to the clip space of Vulkan, whose depth range is from 0 to 1.
+///
+/// The planes are in the order left, right, bottom, top, near, far. Each plane is `[a, b, c, d]`
+/// with a normalized normal `[a, b, c]` that points to the inside of the frustum, so that
+/// `a * x + b * y + c * z + d` is the signed distance of a point to the plane.
+pub fn frustum_planes(view_proj: [[f32; 4]; 4]) -> [[f32; 4]; 6] {
+    let row = |i: usize| {
+        [
+            view_proj[0][i],
+            view_proj[1][i],
+            view_proj[2][i],
+            view_proj[3][i],
+        ]
+    };
+    let add = |a: [f32; 4], b: [f32; 4]| [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]];
+    let sub = |a: [f32; 4], b: [f32; 4]| [a[0] - b[0], a[1] - b[1], a[2] - b[2], a[3] - b[3]];
+
+    let planes = [
+        add(row(3), row(0)),
+        sub(row(3), row(0)),
+        add(row(3), row(1)),
+        sub(row(3), row(1)),
+        row(2),
+        sub(row(3), row(2)),
+    ];
+
+    let mut normalized = [[0.0; 4]; 6];
+    for (plane, out) in planes.iter().zip(normalized.iter_mut()) {
+        let len = (plane[0] * plane[0] + plane[1] * plane[1] + plane[2] * plane[2]).sqrt();
+        *out = [
+            plane[0] / len,
+            plane[1] / len,
+            plane[2] / len,
+            plane[3] / len,
+        ];
+    }
+    normalized
+}
+
+// Returns the bounding sphere of an object in world space. The radius is scaled by the largest
+// scale of the transformation.
+fn world_bounds(object: &CulledObject) -> [f32; 4] {
+    let m = &object.transform;
+    let c = object.center;
+
+    let mut bounds = [0.0; 4];
+    for (i, out) in bounds.iter_mut().take(3).enumerate() {
+        *out = m[0][i] * c[0] + m[1][i] * c[1] + m[2][i] * c[2] + m[3][i];
+    }
+
+    let scale = m
+        .iter()
+        .take(3)
+        .map(|column| column[0] * column[0] + column[1] * column[1] + column[2] * column[2])
+        .fold(0.0, f32::max)
+        .sqrt();
+    bounds[3] = object.radius * scale;
+    bounds
+}
+
+/// Error that can happen when creating a `GpuCulling`.
+#[derive(Debug, Clone)]
+pub enum GpuCullingCreationError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// Failed to create the compute pipeline.
+    PipelineCreationError(ComputePipelineCreationError),
+    /// Failed to allocate the buffers.
+    DeviceMemoryAllocError(DeviceMemoryAllocError),
+    /// The buffers don't match the descriptors of the shader.
+    DescriptorSetError(PersistentDescriptorSetError),
+    /// Failed to create the descriptor set.
+    DescriptorSetBuildError(PersistentDescriptorSetBuildError),
+    /// The `draw_indirect_first_instance` feature must be enabled.
+    DrawIndirectFirstInstanceFeatureNotEnabled,
+}
+
+impl error::Error for GpuCullingCreationError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            GpuCullingCreationError::OomError(ref err) => Some(err),
+            GpuCullingCreationError::PipelineCreationError(ref err) => Some(err),
+            GpuCullingCreationError::DeviceMemoryAllocError(ref err) => Some(err),
+            GpuCullingCreationError::DescriptorSetError(ref err) => Some(err),
+            GpuCullingCreationError::DescriptorSetBuildError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GpuCullingCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                GpuCullingCreationError::OomError(_) => "not enough memory available",
+                GpuCullingCreationError::PipelineCreationError(_) => {
+                    "failed to create the compute pipeline"
+                }
+                GpuCullingCreationError::DeviceMemoryAllocError(_) => {
+                    "failed to allocate the buffers"
+                }
+                GpuCullingCreationError::DescriptorSetError(_) => {
+                    "the buffers don't match the descriptors of the shader"
+                }
+                GpuCullingCreationError::DescriptorSetBuildError(_) => {
+                    "failed to create the descriptor set"
+                }
+                GpuCullingCreationError::DrawIndirectFirstInstanceFeatureNotEnabled => {
+                    "the `draw_indirect_first_instance` feature must be enabled"
+                }
+            }
+        )
+    }
+}
+
+impl From<OomError> for GpuCullingCreationError {
+    #[inline]
+    fn from(err: OomError) -> GpuCullingCreationError {
+        GpuCullingCreationError::OomError(err)
+    }
+}
+
+impl From<ComputePipelineCreationError> for GpuCullingCreationError {
+    #[inline]
+    fn from(err: ComputePipelineCreationError) -> GpuCullingCreationError {
+        GpuCullingCreationError::PipelineCreationError(err)
+    }
+}
+
+impl From<DeviceMemoryAllocError> for GpuCullingCreationError {
+    #[inline]
+    fn from(err: DeviceMemoryAllocError) -> GpuCullingCreationError {
+        GpuCullingCreationError::DeviceMemoryAllocError(err)
+    }
+}
+
+impl From<PersistentDescriptorSetError> for GpuCullingCreationError {
+    #[inline]
+    fn from(err: PersistentDescriptorSetError) -> GpuCullingCreationError {
+        GpuCullingCreationError::DescriptorSetError(err)
+    }
+}
+
+impl From<PersistentDescriptorSetBuildError> for GpuCullingCreationError {
+    #[inline]
+    fn from(err: PersistentDescriptorSetBuildError) -> GpuCullingCreationError {
+        GpuCullingCreationError::DescriptorSetBuildError(err)
+    }
+}
+
+/// Error that can happen when recording a culling pass.
+#[derive(Debug, Clone)]
+pub enum CullError {
+    /// Failed to allocate the staging buffers.
+    DeviceMemoryAllocError(DeviceMemoryAllocError),
+    /// The upload of the objects is invalid.
+    CopyBufferError(CopyBufferError),
+    /// The reset of the count is invalid.
+    FillBufferError(FillBufferError),
+    /// The dispatch command is invalid.
+    DispatchError(DispatchError),
+}
+
+impl error::Error for CullError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            CullError::DeviceMemoryAllocError(ref err) => Some(err),
+            CullError::CopyBufferError(ref err) => Some(err),
+            CullError::FillBufferError(ref err) => Some(err),
+            CullError::DispatchError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for CullError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                CullError::DeviceMemoryAllocError(_) => "failed to allocate the staging buffers",
+                CullError::CopyBufferError(_) => "the upload of the objects is invalid",
+                CullError::FillBufferError(_) => "the reset of the count is invalid",
+                CullError::DispatchError(_) => "the dispatch command is invalid",
+            }
+        )
+    }
+}
+
+impl From<DeviceMemoryAllocError> for CullError {
+    #[inline]
+    fn from(err: DeviceMemoryAllocError) -> CullError {
+        CullError::DeviceMemoryAllocError(err)
+    }
+}
+
+impl From<CopyBufferError> for CullError {
+    #[inline]
+    fn from(err: CopyBufferError) -> CullError {
+        CullError::CopyBufferError(err)
+    }
+}
+
+impl From<FillBufferError> for CullError {
+    #[inline]
+    fn from(err: FillBufferError) -> CullError {
+        CullError::FillBufferError(err)
+    }
+}
+
+impl From<DispatchError> for CullError {
+    #[inline]
+    fn from(err: DispatchError) -> CullError {
+        CullError::DispatchError(err)
+    }
+}
+
+// Built-in culling shader.
+static CULLING_SHADER: [u32; 606] = [
+    0x07230203, 0x00010000, 0x00000000, 0x00000062, 0x00000000, 0x00020011, 0x00000001, 0x0003000e,
+    0x00000000, 0x00000001, 0x0006000f, 0x00000005, 0x00000029, 0x6e69616d, 0x00000000, 0x0000001c,
+    0x00060010, 0x00000029, 0x00000011, 0x00000040, 0x00000001, 0x00000001, 0x00040005, 0x00000029,
+    0x6e69616d, 0x00000000, 0x00040005, 0x0000000c, 0x6e756f62, 0x00007364, 0x00040005, 0x00000011,
+    0x77617264, 0x00000073, 0x00060005, 0x00000012, 0x69736976, 0x5f656c62, 0x77617264, 0x00000073,
+    0x00050005, 0x00000015, 0x77617264, 0x756f635f, 0x0000746e, 0x00050005, 0x0000001a, 0x736e6f63,
+    0x746e6174, 0x00000073, 0x00040047, 0x00000009, 0x00000006, 0x00000010, 0x00030047, 0x0000000a,
+    0x00000003, 0x00050048, 0x0000000a, 0x00000000, 0x00000023, 0x00000000, 0x00040048, 0x0000000a,
+    0x00000000, 0x00000018, 0x00050048, 0x0000000d, 0x00000000, 0x00000023, 0x00000000, 0x00050048,
+    0x0000000d, 0x00000001, 0x00000023, 0x00000004, 0x00050048, 0x0000000d, 0x00000002, 0x00000023,
+    0x00000008, 0x00050048, 0x0000000d, 0x00000003, 0x00000023, 0x0000000c, 0x00050048, 0x0000000d,
+    0x00000004, 0x00000023, 0x00000010, 0x00040047, 0x0000000e, 0x00000006, 0x00000014, 0x00030047,
+    0x0000000f, 0x00000003, 0x00050048, 0x0000000f, 0x00000000, 0x00000023, 0x00000000, 0x00030047,
+    0x00000060, 0x00000003, 0x00050048, 0x00000060, 0x00000000, 0x00000023, 0x00000000, 0x00040048,
+    0x00000060, 0x00000000, 0x00000018, 0x00030047, 0x00000013, 0x00000003, 0x00050048, 0x00000013,
+    0x00000000, 0x00000023, 0x00000000, 0x00040047, 0x00000017, 0x00000006, 0x00000010, 0x00030047,
+    0x00000018, 0x00000002, 0x00050048, 0x00000018, 0x00000000, 0x00000023, 0x00000000, 0x00050048,
+    0x00000018, 0x00000001, 0x00000023, 0x00000060, 0x00040047, 0x0000001c, 0x0000000b, 0x0000001c,
+    0x00040047, 0x0000000c, 0x00000022, 0x00000000, 0x00040047, 0x0000000c, 0x00000021, 0x00000000,
+    0x00040047, 0x00000011, 0x00000022, 0x00000000, 0x00040047, 0x00000011, 0x00000021, 0x00000001,
+    0x00040047, 0x00000012, 0x00000022, 0x00000000, 0x00040047, 0x00000012, 0x00000021, 0x00000002,
+    0x00040047, 0x00000015, 0x00000022, 0x00000000, 0x00040047, 0x00000015, 0x00000021, 0x00000003,
+    0x00020013, 0x00000001, 0x00030021, 0x00000002, 0x00000001, 0x00040015, 0x00000003, 0x00000020,
+    0x00000000, 0x00040015, 0x00000004, 0x00000020, 0x00000001, 0x00030016, 0x00000005, 0x00000020,
+    0x00040017, 0x00000006, 0x00000005, 0x00000004, 0x00040017, 0x00000007, 0x00000003, 0x00000003,
+    0x00020014, 0x00000008, 0x0003001d, 0x00000009, 0x00000006, 0x0003001e, 0x0000000a, 0x00000009,
+    0x00040020, 0x0000000b, 0x00000002, 0x0000000a, 0x0004003b, 0x0000000b, 0x0000000c, 0x00000002,
+    0x0007001e, 0x0000000d, 0x00000003, 0x00000003, 0x00000003, 0x00000004, 0x00000003, 0x0003001d,
+    0x0000000e, 0x0000000d, 0x0003001e, 0x0000000f, 0x0000000e, 0x00040020, 0x00000010, 0x00000002,
+    0x0000000f, 0x0003001e, 0x00000060, 0x0000000e, 0x00040020, 0x00000061, 0x00000002, 0x00000060,
+    0x0004003b, 0x00000061, 0x00000011, 0x00000002, 0x0004003b, 0x00000010, 0x00000012, 0x00000002,
+    0x0003001e, 0x00000013, 0x00000003, 0x00040020, 0x00000014, 0x00000002, 0x00000013, 0x0004003b,
+    0x00000014, 0x00000015, 0x00000002, 0x0004002b, 0x00000003, 0x00000016, 0x00000006, 0x0004001c,
+    0x00000017, 0x00000006, 0x00000016, 0x0004001e, 0x00000018, 0x00000017, 0x00000003, 0x00040020,
+    0x00000019, 0x00000009, 0x00000018, 0x0004003b, 0x00000019, 0x0000001a, 0x00000009, 0x00040020,
+    0x0000001b, 0x00000001, 0x00000007, 0x0004003b, 0x0000001b, 0x0000001c, 0x00000001, 0x0004002b,
+    0x00000004, 0x0000001d, 0x00000000, 0x0004002b, 0x00000004, 0x0000001e, 0x00000001, 0x0004002b,
+    0x00000005, 0x0000001f, 0x3f800000, 0x0004002b, 0x00000003, 0x00000021, 0x00000001, 0x0004002b,
+    0x00000003, 0x00000022, 0x00000000, 0x00040020, 0x00000023, 0x00000001, 0x00000003, 0x00040020,
+    0x00000024, 0x00000009, 0x00000003, 0x00040020, 0x00000025, 0x00000009, 0x00000006, 0x00040020,
+    0x00000026, 0x00000002, 0x00000006, 0x00040020, 0x00000027, 0x00000002, 0x0000000d, 0x00040020,
+    0x00000028, 0x00000002, 0x00000003, 0x0004002b, 0x00000004, 0x0000002f, 0x00000002, 0x0004002b,
+    0x00000004, 0x00000030, 0x00000003, 0x0004002b, 0x00000004, 0x00000031, 0x00000004, 0x0004002b,
+    0x00000004, 0x00000032, 0x00000005, 0x00050036, 0x00000001, 0x00000029, 0x00000000, 0x00000002,
+    0x000200f8, 0x0000002a, 0x00050041, 0x00000023, 0x00000033, 0x0000001c, 0x0000001d, 0x0004003d,
+    0x00000003, 0x00000034, 0x00000033, 0x00050041, 0x00000024, 0x00000035, 0x0000001a, 0x0000001e,
+    0x0004003d, 0x00000003, 0x00000036, 0x00000035, 0x000500b0, 0x00000008, 0x00000037, 0x00000034,
+    0x00000036, 0x000300f7, 0x0000002e, 0x00000000, 0x000400fa, 0x00000037, 0x0000002b, 0x0000002e,
+    0x000200f8, 0x0000002b, 0x00060041, 0x00000026, 0x00000038, 0x0000000c, 0x0000001d, 0x00000034,
+    0x0004003d, 0x00000006, 0x00000039, 0x00000038, 0x00060052, 0x00000006, 0x0000003a, 0x0000001f,
+    0x00000039, 0x00000003, 0x00050051, 0x00000005, 0x0000003b, 0x00000039, 0x00000003, 0x0004007f,
+    0x00000005, 0x0000003c, 0x0000003b, 0x00060041, 0x00000025, 0x0000003d, 0x0000001a, 0x0000001d,
+    0x0000001d, 0x0004003d, 0x00000006, 0x0000003e, 0x0000003d, 0x00050094, 0x00000005, 0x0000003f,
+    0x0000003e, 0x0000003a, 0x000500be, 0x00000008, 0x00000040, 0x0000003f, 0x0000003c, 0x00060041,
+    0x00000025, 0x00000041, 0x0000001a, 0x0000001d, 0x0000001e, 0x0004003d, 0x00000006, 0x00000042,
+    0x00000041, 0x00050094, 0x00000005, 0x00000043, 0x00000042, 0x0000003a, 0x000500be, 0x00000008,
+    0x00000044, 0x00000043, 0x0000003c, 0x00060041, 0x00000025, 0x00000045, 0x0000001a, 0x0000001d,
+    0x0000002f, 0x0004003d, 0x00000006, 0x00000046, 0x00000045, 0x00050094, 0x00000005, 0x00000047,
+    0x00000046, 0x0000003a, 0x000500be, 0x00000008, 0x00000048, 0x00000047, 0x0000003c, 0x00060041,
+    0x00000025, 0x00000049, 0x0000001a, 0x0000001d, 0x00000030, 0x0004003d, 0x00000006, 0x0000004a,
+    0x00000049, 0x00050094, 0x00000005, 0x0000004b, 0x0000004a, 0x0000003a, 0x000500be, 0x00000008,
+    0x0000004c, 0x0000004b, 0x0000003c, 0x00060041, 0x00000025, 0x0000004d, 0x0000001a, 0x0000001d,
+    0x00000031, 0x0004003d, 0x00000006, 0x0000004e, 0x0000004d, 0x00050094, 0x00000005, 0x0000004f,
+    0x0000004e, 0x0000003a, 0x000500be, 0x00000008, 0x00000050, 0x0000004f, 0x0000003c, 0x00060041,
+    0x00000025, 0x00000051, 0x0000001a, 0x0000001d, 0x00000032, 0x0004003d, 0x00000006, 0x00000052,
+    0x00000051, 0x00050094, 0x00000005, 0x00000053, 0x00000052, 0x0000003a, 0x000500be, 0x00000008,
+    0x00000054, 0x00000053, 0x0000003c, 0x000500a7, 0x00000008, 0x00000055, 0x00000040, 0x00000044,
+    0x000500a7, 0x00000008, 0x00000056, 0x00000055, 0x00000048, 0x000500a7, 0x00000008, 0x00000057,
+    0x00000056, 0x0000004c, 0x000500a7, 0x00000008, 0x00000058, 0x00000057, 0x00000050, 0x000500a7,
+    0x00000008, 0x00000059, 0x00000058, 0x00000054, 0x000300f7, 0x0000002d, 0x00000000, 0x000400fa,
+    0x00000059, 0x0000002c, 0x0000002d, 0x000200f8, 0x0000002c, 0x00050041, 0x00000028, 0x0000005a,
+    0x00000015, 0x0000001d, 0x000700ea, 0x00000003, 0x0000005b, 0x0000005a, 0x00000021, 0x00000022,
+    0x00000021, 0x00060041, 0x00000027, 0x0000005c, 0x00000011, 0x0000001d, 0x00000034, 0x0004003d,
+    0x0000000d, 0x0000005d, 0x0000005c, 0x00060041, 0x00000027, 0x0000005e, 0x00000012, 0x0000001d,
+    0x0000005b, 0x0003003e, 0x0000005e, 0x0000005d, 0x000200f9, 0x0000002d, 0x000200f8, 0x0000002d,
+    0x000200f9, 0x0000002e, 0x000200f8, 0x0000002e, 0x000100fd, 0x00010038,
+];
+
+/*
+#version 450
+
+layout(local_size_x = 64) in;
+
+struct DrawCommand {
+    uint index_count;
+    uint instance_count;
+    uint first_index;
+    int vertex_offset;
+    uint first_instance;
+};
+
+// Bounding spheres in world space, with the radius in `w`.
+layout(set = 0, binding = 0, std430) readonly buffer Bounds {
+    vec4 bounds[];
+};
+
+// Draw commands of all the objects.
+layout(set = 0, binding = 1, std430) readonly buffer Draws {
+    DrawCommand draws[];
+};
+
+// Draw commands of the visible objects.
+layout(set = 0, binding = 2, std430) writeonly buffer VisibleDraws {
+    DrawCommand visible_draws[];
+};
+
+// Number of visible objects, reset to 0 before the dispatch.
+layout(set = 0, binding = 3, std430) buffer DrawCount {
+    uint draw_count;
+};
+
+layout(push_constant) uniform Constants {
+    // Left, right, bottom, top, near and far planes, with normals pointing inside.
+    vec4 planes[6];
+    uint num_objects;
+} constants;
+
+void main() {
+    uint index = gl_GlobalInvocationID.x;
+    if (index < constants.num_objects) {
+        vec4 sphere = bounds[index];
+        vec4 center = vec4(sphere.xyz, 1.0);
+        float radius = sphere.w;
+
+        bool visible = dot(constants.planes[0], center) >= -radius
+            && dot(constants.planes[1], center) >= -radius
+            && dot(constants.planes[2], center) >= -radius
+            && dot(constants.planes[3], center) >= -radius
+            && dot(constants.planes[4], center) >= -radius
+            && dot(constants.planes[5], center) >= -radius;
+
+        if (visible) {
+            uint slot = atomicAdd(draw_count, 1);
+            visible_draws[slot] = draws[index];
+        }
+    }
+}
+*/
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::command_buffer::AutoCommandBufferBuilder;
+    use crate::command_buffer::CommandBufferUsage;
+    use crate::command_buffer::PrimaryCommandBuffer;
+    use crate::culling::frustum_planes;
+    use crate::culling::world_bounds;
+    use crate::culling::CulledObject;
+    use crate::culling::GpuCulling;
+    use crate::culling::GpuCullingCreationError;
+    use crate::culling::CULLING_SHADER;
+    use crate::debug_draw::DebugVertex;
+    use crate::pipeline::shader::reflect::ShaderReflection;
+    use crate::pipeline::shader::ShaderStage;
+    use crate::sync::GpuFuture;
+    use crate::tests::begin_debug_draw_pass;
+
+    const IDENTITY: [[f32; 4]; 4] = [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+
+    fn object(transform: [[f32; 4]; 4]) -> CulledObject {
+        CulledObject {
+            transform,
+            center: [0.0, 0.0, 0.0],
+            radius: 1.0,
+            index_count: 6,
+            first_index: 0,
+            vertex_offset: 0,
+        }
+    }
+
+    #[test]
+    fn shader_reflection() {
+        let reflection = ShaderReflection::new(&CULLING_SHADER).unwrap();
+        let entry_point = reflection.entry_point("main").unwrap();
+        assert_eq!(entry_point.stage, ShaderStage::Compute);
+        assert_eq!(entry_point.local_size(), Some([64, 1, 1]));
+        assert_eq!(entry_point.push_constants.as_ref().unwrap().range.size, 100);
+
+        let readonly = entry_point
+            .descriptor_bindings
+            .iter()
+            .map(|binding| (binding.set, binding.binding, binding.desc.readonly))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            readonly,
+            [(0, 0, true), (0, 1, true), (0, 2, false), (0, 3, false)]
+        );
+    }
+
+    #[test]
+    fn planes() {
+        let planes = frustum_planes(IDENTITY);
+        let distance = |p: [f32; 4], x: [f32; 3]| p[0] * x[0] + p[1] * x[1] + p[2] * x[2] + p[3];
+
+        assert_eq!(planes[0], [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(planes[4], [0.0, 0.0, 1.0, 0.0]);
+        assert!(planes.iter().all(|&p| distance(p, [0.0, 0.0, 0.5]) > 0.0));
+        assert!(distance(planes[1], [2.0, 0.0, 0.5]) < 0.0);
+        assert!(distance(planes[5], [0.0, 0.0, 1.5]) < 0.0);
+    }
+
+    #[test]
+    fn bounds() {
+        let transform = [
+            [2.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 3.0, 0.0],
+            [1.0, 2.0, 3.0, 1.0],
+        ];
+        assert_eq!(world_bounds(&object(transform)), [1.0, 2.0, 3.0, 3.0]);
+    }
+
+    #[test]
+    fn insert_remove() {
+        let (device, queue) = gfx_dev_and_queue!(draw_indirect_first_instance);
+        let mut culling = GpuCulling::new(device, 2, [queue.family()]).unwrap();
+        assert_eq!(culling.capacity(), 2);
+
+        let a = culling.insert(object(IDENTITY)).unwrap();
+        let b = culling.insert(object(IDENTITY)).unwrap();
+        assert_eq!((a, b), (0, 1));
+        assert!(culling.insert(object(IDENTITY)).is_none());
+
+        assert!(culling.remove(a).is_some());
+        assert!(culling.get(a).is_none());
+        assert_eq!(culling.len(), 1);
+        assert_eq!(culling.insert(object(IDENTITY)), Some(a));
+
+        culling.remove(b);
+        culling.remove(a);
+        assert!(culling.is_empty());
+        assert_eq!(culling.insert(object(IDENTITY)), Some(0));
+    }
+
+    #[test]
+    fn first_instance_feature_not_enabled() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        match GpuCulling::new(device, 2, [queue.family()]) {
+            Err(GpuCullingCreationError::DrawIndirectFirstInstanceFeatureNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn cull_and_draw() {
+        let (device, queue) = gfx_dev_and_queue!(
+            draw_indirect_first_instance,
+            multi_draw_indirect,
+            draw_indirect_count
+        );
+
+        let mut culling = GpuCulling::new(device.clone(), 4, [queue.family()]).unwrap();
+        culling.insert(object(IDENTITY)).unwrap();
+        // Outside of the frustum.
+        let mut far = IDENTITY;
+        far[3] = [100.0, 0.0, 0.0, 1.0];
+        culling.insert(object(far)).unwrap();
+
+        let vertices = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::vertex_buffer(),
+            false,
+            [DebugVertex::default(); 2].iter().cloned(),
+        )
+        .unwrap();
+        let indices = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::index_buffer(),
+            false,
+            [0u32; 6].iter().cloned(),
+        )
+        .unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        culling.cull(&mut builder, IDENTITY).unwrap();
+        let (pipeline, dynamic) = begin_debug_draw_pass(&mut builder);
+        culling
+            .draw(
+                &mut builder,
+                pipeline,
+                &dynamic,
+                vec![vertices],
+                indices,
+                (),
+                [[0.0f32; 4]; 4],
+            )
+            .unwrap();
+        builder.end_render_pass().unwrap();
+
+        builder
+            .build()
+            .unwrap()
+            .execute(queue)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+    }
+}
@@ -0,0 +1,574 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Immediate-mode drawing of lines and shapes, for debugging.
+//!
+//! A [`DebugDraw`] collects lines, wireframe boxes, spheres and rectangles that any part of the
+//! application submits during the frame, for example to visualize the colliders of a physics
+//! engine or the paths of an AI. At the end of the frame, `draw` uploads them to a
+//! `CpuBufferPool` and records them in the render pass of the main target, with one draw call
+//! for the shapes in world space and one for the rectangles in screen space. The shapes are then
+//! discarded, so they must be submitted again at each frame.
+//!
+//! The shapes are drawn as lines over the content of the target, without depth test, and with
+//! alpha blending.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use vulkano::command_buffer::AutoCommandBufferBuilder;
+//! # use vulkano::command_buffer::PrimaryAutoCommandBuffer;
+//! # use vulkano::pipeline::viewport::Viewport;
+//! # use vulkano::render_pass::Subpass;
+//! # let subpass: Subpass = return;
+//! # let mut builder: AutoCommandBufferBuilder<PrimaryAutoCommandBuffer> = return;
+//! # let viewport: Viewport = return;
+//! # let view_proj: [[f32; 4]; 4] = return;
+//! use vulkano::debug_draw::DebugDraw;
+//!
+//! let debug_draw = Arc::new(DebugDraw::new(subpass).unwrap());
+//!
+//! // Anywhere during the frame.
+//! debug_draw.line([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 0.0, 1.0]);
+//! debug_draw.wire_box([-1.0, -1.0, -1.0], [1.0, 1.0, 1.0], [0.0, 1.0, 0.0, 1.0]);
+//! debug_draw.sphere([0.0, 2.0, 0.0], 0.5, [0.0, 0.0, 1.0, 1.0]);
+//! debug_draw.rect([10.0, 10.0], [200.0, 40.0], [1.0, 1.0, 1.0, 0.5]);
+//!
+//! // Inside the render pass of the main target.
+//! debug_draw.draw(&mut builder, view_proj, &viewport).unwrap();
+//! ```
+
+use crate::buffer::BufferUsage;
+use crate::buffer::CpuBufferPool;
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::DrawError;
+use crate::command_buffer::DynamicState;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::format::Format;
+use crate::memory::DeviceMemoryAllocError;
+use crate::pipeline::layout::PipelineLayoutPcRange;
+use crate::pipeline::shader::GraphicsShaderType;
+use crate::pipeline::shader::ShaderInterface;
+use crate::pipeline::shader::ShaderInterfaceEntry;
+use crate::pipeline::shader::ShaderModule;
+use crate::pipeline::shader::ShaderStages;
+use crate::pipeline::viewport::Viewport;
+use crate::pipeline::GraphicsPipeline;
+use crate::pipeline::GraphicsPipelineCreationError;
+use crate::render_pass::Subpass;
+use crate::OomError;
+use std::borrow::Cow;
+use std::error;
+use std::f32::consts::PI;
+use std::ffi::CStr;
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// Number of segments of each of the three circles that a sphere is drawn with.
+const SPHERE_SEGMENTS: usize = 32;
+
+/// Collects lines and shapes during the frame, and draws them.
+///
+/// The methods that add shapes take `&self`, so the same `DebugDraw` can be shared between the
+/// parts of the application, and between threads.
+///
+/// See [the module-level documentation](self) for more information.
+pub struct DebugDraw {
+    pipeline: Arc<GraphicsPipeline>,
+    vertex_buffers: CpuBufferPool<DebugVertex>,
+    batches: Mutex<Batches>,
+}
+
+#[derive(Default)]
+struct Batches {
+    // Pairs of vertices with positions in world space.
+    world: Vec<DebugVertex>,
+    // Pairs of vertices with positions in pixels.
+    screen: Vec<DebugVertex>,
+}
+
+/// Vertex of the lines drawn by a `DebugDraw`.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[repr(C)]
+pub struct DebugVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+crate::impl_vertex!(DebugVertex, position, color);
+
+impl DebugDraw {
+    /// Builds a new `DebugDraw` that draws in `subpass`, which is usually the subpass that
+    /// renders the main target.
+    ///
+    /// The first color attachment of the subpass is drawn to.
+    pub fn new(subpass: Subpass) -> Result<DebugDraw, DebugDrawError> {
+        let device = subpass.render_pass().device().clone();
+
+        let vs_module = unsafe { ShaderModule::from_words(device.clone(), &VERTEX_SHADER)? };
+        let fs_module = unsafe { ShaderModule::from_words(device.clone(), &FRAGMENT_SHADER)? };
+
+        let pipeline = unsafe {
+            let main = CStr::from_bytes_with_nul_unchecked(b"main\0");
+
+            let vs = vs_module.graphics_entry_point(
+                main,
+                [],
+                Some(PipelineLayoutPcRange {
+                    offset: 0,
+                    size: mem::size_of::<[[f32; 4]; 4]>(),
+                    stages: ShaderStages {
+                        vertex: true,
+                        ..ShaderStages::none()
+                    },
+                }),
+                &[],
+                ShaderInterface::new_unchecked(vec![
+                    interface_entry(0, Format::R32G32B32Sfloat, Some("position")),
+                    interface_entry(1, Format::R32G32B32A32Sfloat, Some("color")),
+                ]),
+                ShaderInterface::new_unchecked(vec![interface_entry(
+                    0,
+                    Format::R32G32B32A32Sfloat,
+                    None,
+                )]),
+                GraphicsShaderType::Vertex,
+            );
+            let fs = fs_module.graphics_entry_point(
+                main,
+                [],
+                None,
+                &[],
+                ShaderInterface::new_unchecked(vec![interface_entry(
+                    0,
+                    Format::R32G32B32A32Sfloat,
+                    None,
+                )]),
+                ShaderInterface::new_unchecked(vec![interface_entry(
+                    0,
+                    Format::R32G32B32A32Sfloat,
+                    None,
+                )]),
+                GraphicsShaderType::Fragment,
+            );
+
+            GraphicsPipeline::start()
+                .vertex_input_single_buffer::<DebugVertex>()
+                .vertex_shader(vs, ())
+                .line_list()
+                .viewports_dynamic_scissors_irrelevant(1)
+                .fragment_shader(fs, ())
+                .depth_stencil_disabled()
+                .blend_alpha_blending()
+                .render_pass(subpass)
+                .build(device.clone())?
+        };
+
+        Ok(DebugDraw {
+            pipeline: Arc::new(pipeline),
+            vertex_buffers: CpuBufferPool::new(device, BufferUsage::vertex_buffer()),
+            batches: Mutex::new(Batches::default()),
+        })
+    }
+
+    /// Adds a line between two points in world space.
+    #[inline]
+    pub fn line(&self, from: [f32; 3], to: [f32; 3], color: [f32; 4]) {
+        let mut batches = self.batches.lock().unwrap();
+        push_line(&mut batches.world, from, to, color);
+    }
+
+    /// Adds the edges of an axis-aligned box, given by its minimum and maximum corners in world
+    /// space.
+    pub fn wire_box(&self, min: [f32; 3], max: [f32; 3], color: [f32; 4]) {
+        let corner = |i: usize| {
+            [
+                if i & 1 == 0 { min[0] } else { max[0] },
+                if i & 2 == 0 { min[1] } else { max[1] },
+                if i & 4 == 0 { min[2] } else { max[2] },
+            ]
+        };
+
+        let mut batches = self.batches.lock().unwrap();
+        // Each edge joins two corners whose indices differ by one bit.
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    push_line(&mut batches.world, corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+
+    /// Adds a sphere, drawn as its three circles that are parallel to the axis planes.
+    pub fn sphere(&self, center: [f32; 3], radius: f32, color: [f32; 4]) {
+        let point = |axis: usize, segment: usize| {
+            let angle = segment as f32 * 2.0 * PI / SPHERE_SEGMENTS as f32;
+            let mut point = center;
+            point[(axis + 1) % 3] += radius * angle.cos();
+            point[(axis + 2) % 3] += radius * angle.sin();
+            point
+        };
+
+        let mut batches = self.batches.lock().unwrap();
+        for axis in 0..3 {
+            for segment in 0..SPHERE_SEGMENTS {
+                push_line(
+                    &mut batches.world,
+                    point(axis, segment),
+                    point(axis, segment + 1),
+                    color,
+                );
+            }
+        }
+    }
+
+    /// Adds the edges of a rectangle in screen space, given by its minimum and maximum corners in
+    /// pixels from the top-left hand corner of the viewport.
+    pub fn rect(&self, min: [f32; 2], max: [f32; 2], color: [f32; 4]) {
+        let corners = [
+            [min[0], min[1], 0.0],
+            [max[0], min[1], 0.0],
+            [max[0], max[1], 0.0],
+            [min[0], max[1], 0.0],
+        ];
+
+        let mut batches = self.batches.lock().unwrap();
+        for i in 0..4 {
+            push_line(&mut batches.screen, corners[i], corners[(i + 1) % 4], color);
+        }
+    }
+
+    /// Returns the number of lines that were added since the last call to `draw` or `clear`.
+    #[inline]
+    pub fn num_lines(&self) -> usize {
+        let batches = self.batches.lock().unwrap();
+        (batches.world.len() + batches.screen.len()) / 2
+    }
+
+    /// Discards the lines that were added since the last call to `draw`.
+    #[inline]
+    pub fn clear(&self) {
+        let mut batches = self.batches.lock().unwrap();
+        batches.world.clear();
+        batches.screen.clear();
+    }
+
+    /// Records the draw commands of the lines that were added since the last call, and discards
+    /// them.
+    ///
+    /// Must be called inside an instance of the render pass of the subpass that was given to
+    /// `new`, while that subpass is the current one. `view_proj` is the matrix, in column-major
+    /// order, that transforms world space to clip space, and `viewport` is the viewport of the
+    /// target.
+    pub fn draw<L, P>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L, P>,
+        view_proj: [[f32; 4]; 4],
+        viewport: &Viewport,
+    ) -> Result<(), DebugDrawError> {
+        let Batches { world, screen } = mem::take(&mut *self.batches.lock().unwrap());
+
+        let dynamic_state = DynamicState {
+            viewports: Some(vec![viewport.clone()]),
+            ..DynamicState::none()
+        };
+
+        // Maps pixels from the top-left hand corner of the viewport to clip space.
+        let [width, height] = viewport.dimensions;
+        let screen_proj = [
+            [2.0 / width, 0.0, 0.0, 0.0],
+            [0.0, 2.0 / height, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [-1.0, -1.0, 0.0, 1.0],
+        ];
+
+        for (vertices, transform) in [(world, view_proj), (screen, screen_proj)] {
+            if vertices.is_empty() {
+                continue;
+            }
+
+            let num_vertices = vertices.len() as u32;
+            let vertex_buffer = self.vertex_buffers.chunk(vertices)?;
+            builder.draw(
+                num_vertices,
+                1,
+                0,
+                0,
+                self.pipeline.clone(),
+                &dynamic_state,
+                vertex_buffer,
+                (),
+                transform,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the pipeline that the lines are drawn with.
+    #[inline]
+    pub fn pipeline(&self) -> &Arc<GraphicsPipeline> {
+        &self.pipeline
+    }
+}
+
+unsafe impl DeviceOwned for DebugDraw {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.pipeline.device()
+    }
+}
+
+impl fmt::Debug for DebugDraw {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("DebugDraw")
+            .field("pipeline", &self.pipeline)
+            .field("num_lines", &self.num_lines())
+            .finish()
+    }
+}
+
+#[inline]
+fn push_line(vertices: &mut Vec<DebugVertex>, from: [f32; 3], to: [f32; 3], color: [f32; 4]) {
+    vertices.push(DebugVertex {
+        position: from,
+        color,
+    });
+    vertices.push(DebugVertex {
+        position: to,
+        color,
+    });
+}
+
+fn interface_entry(
+    location: u32,
+    format: Format,
+    name: Option<&'static str>,
+) -> ShaderInterfaceEntry {
+    ShaderInterfaceEntry {
+        location: location..location + 1,
+        format,
+        name: name.map(Cow::Borrowed),
+    }
+}
+
+/*
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec4 color;
+
+layout(location = 0) out vec4 v_color;
+
+layout(push_constant) uniform PushConstants {
+    mat4 transform;
+} push_constants;
+
+void main() {
+    v_color = color;
+    gl_Position = push_constants.transform * vec4(position, 1.0);
+}
+*/
+static VERTEX_SHADER: [u32; 195] = [
+    0x07230203, 0x00010000, 0x00000000, 0x00000020, 0x00000000, 0x00020011, 0x00000001, 0x0003000e,
+    0x00000000, 0x00000001, 0x0009000f, 0x00000000, 0x00000015, 0x6e69616d, 0x00000000, 0x0000000a,
+    0x0000000b, 0x0000000c, 0x0000000d, 0x00050005, 0x0000000a, 0x69736f70, 0x6e6f6974, 0x00000000,
+    0x00040005, 0x0000000b, 0x6f6c6f63, 0x00000072, 0x00040047, 0x0000000a, 0x0000001e, 0x00000000,
+    0x00040047, 0x0000000b, 0x0000001e, 0x00000001, 0x00040047, 0x0000000c, 0x0000001e, 0x00000000,
+    0x00040047, 0x0000000d, 0x0000000b, 0x00000000, 0x00030047, 0x0000000e, 0x00000002, 0x00040048,
+    0x0000000e, 0x00000000, 0x00000005, 0x00050048, 0x0000000e, 0x00000000, 0x00000023, 0x00000000,
+    0x00050048, 0x0000000e, 0x00000000, 0x00000007, 0x00000010, 0x00020013, 0x00000001, 0x00030021,
+    0x00000002, 0x00000001, 0x00030016, 0x00000003, 0x00000020, 0x00040017, 0x00000004, 0x00000003,
+    0x00000003, 0x00040017, 0x00000005, 0x00000003, 0x00000004, 0x00040018, 0x00000006, 0x00000005,
+    0x00000004, 0x00040020, 0x00000007, 0x00000001, 0x00000004, 0x00040020, 0x00000008, 0x00000001,
+    0x00000005, 0x00040020, 0x00000009, 0x00000003, 0x00000005, 0x0004003b, 0x00000007, 0x0000000a,
+    0x00000001, 0x0004003b, 0x00000008, 0x0000000b, 0x00000001, 0x0004003b, 0x00000009, 0x0000000c,
+    0x00000003, 0x0004003b, 0x00000009, 0x0000000d, 0x00000003, 0x0003001e, 0x0000000e, 0x00000006,
+    0x00040020, 0x0000000f, 0x00000009, 0x0000000e, 0x0004003b, 0x0000000f, 0x00000010, 0x00000009,
+    0x00040015, 0x00000011, 0x00000020, 0x00000001, 0x0004002b, 0x00000011, 0x00000012, 0x00000000,
+    0x00040020, 0x00000013, 0x00000009, 0x00000006, 0x0004002b, 0x00000003, 0x00000014, 0x3f800000,
+    0x00050036, 0x00000001, 0x00000015, 0x00000000, 0x00000002, 0x000200f8, 0x00000016, 0x0004003d,
+    0x00000005, 0x00000017, 0x0000000b, 0x0003003e, 0x0000000c, 0x00000017, 0x00050041, 0x00000013,
+    0x00000018, 0x00000010, 0x00000012, 0x0004003d, 0x00000006, 0x00000019, 0x00000018, 0x0004003d,
+    0x00000004, 0x0000001a, 0x0000000a, 0x00050051, 0x00000003, 0x0000001b, 0x0000001a, 0x00000000,
+    0x00050051, 0x00000003, 0x0000001c, 0x0000001a, 0x00000001, 0x00050051, 0x00000003, 0x0000001d,
+    0x0000001a, 0x00000002, 0x00070050, 0x00000005, 0x0000001e, 0x0000001b, 0x0000001c, 0x0000001d,
+    0x00000014, 0x00050091, 0x00000005, 0x0000001f, 0x00000019, 0x0000001e, 0x0003003e, 0x0000000d,
+    0x0000001f, 0x000100fd, 0x00010038,
+];
+
+/*
+#version 450
+
+layout(location = 0) in vec4 v_color;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = v_color;
+}
+*/
+static FRAGMENT_SHADER: [u32; 72] = [
+    0x07230203, 0x00010000, 0x00000000, 0x0000000c, 0x00000000, 0x00020011, 0x00000001, 0x0003000e,
+    0x00000000, 0x00000001, 0x0007000f, 0x00000004, 0x00000007, 0x6e69616d, 0x00000000, 0x00000005,
+    0x00000006, 0x00030010, 0x00000007, 0x00000007, 0x00040047, 0x00000005, 0x0000001e, 0x00000000,
+    0x00040047, 0x00000006, 0x0000001e, 0x00000000, 0x00020013, 0x00000001, 0x00030021, 0x00000002,
+    0x00000001, 0x00030016, 0x00000003, 0x00000020, 0x00040017, 0x00000004, 0x00000003, 0x00000004,
+    0x00040020, 0x00000008, 0x00000001, 0x00000004, 0x00040020, 0x00000009, 0x00000003, 0x00000004,
+    0x0004003b, 0x00000008, 0x00000005, 0x00000001, 0x0004003b, 0x00000009, 0x00000006, 0x00000003,
+    0x00050036, 0x00000001, 0x00000007, 0x00000000, 0x00000002, 0x000200f8, 0x0000000a, 0x0004003d,
+    0x00000004, 0x0000000b, 0x00000005, 0x0003003e, 0x00000006, 0x0000000b, 0x000100fd, 0x00010038,
+];
+
+/// Error that can happen when creating or drawing with a `DebugDraw`.
+#[derive(Debug, Clone)]
+pub enum DebugDrawError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// Failed to create the pipeline.
+    PipelineCreationError(GraphicsPipelineCreationError),
+    /// Failed to allocate the vertex buffer.
+    DeviceMemoryAllocError(DeviceMemoryAllocError),
+    /// The draw command is invalid.
+    DrawError(DrawError),
+}
+
+impl error::Error for DebugDrawError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            DebugDrawError::OomError(ref err) => Some(err),
+            DebugDrawError::PipelineCreationError(ref err) => Some(err),
+            DebugDrawError::DeviceMemoryAllocError(ref err) => Some(err),
+            DebugDrawError::DrawError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for DebugDrawError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                DebugDrawError::OomError(_) => "not enough memory available",
+                DebugDrawError::PipelineCreationError(_) => "failed to create the pipeline",
+                DebugDrawError::DeviceMemoryAllocError(_) => {
+                    "failed to allocate the vertex buffer"
+                }
+                DebugDrawError::DrawError(_) => "the draw command is invalid",
+            }
+        )
+    }
+}
+
+impl From<OomError> for DebugDrawError {
+    #[inline]
+    fn from(err: OomError) -> DebugDrawError {
+        DebugDrawError::OomError(err)
+    }
+}
+
+impl From<GraphicsPipelineCreationError> for DebugDrawError {
+    #[inline]
+    fn from(err: GraphicsPipelineCreationError) -> DebugDrawError {
+        DebugDrawError::PipelineCreationError(err)
+    }
+}
+
+impl From<DeviceMemoryAllocError> for DebugDrawError {
+    #[inline]
+    fn from(err: DeviceMemoryAllocError) -> DebugDrawError {
+        DebugDrawError::DeviceMemoryAllocError(err)
+    }
+}
+
+impl From<DrawError> for DebugDrawError {
+    #[inline]
+    fn from(err: DrawError) -> DebugDrawError {
+        DebugDrawError::DrawError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debug_draw::DebugDraw;
+    use crate::debug_draw::FRAGMENT_SHADER;
+    use crate::debug_draw::SPHERE_SEGMENTS;
+    use crate::debug_draw::VERTEX_SHADER;
+    use crate::format::Format;
+    use crate::pipeline::shader::reflect::ShaderReflection;
+    use crate::pipeline::shader::ShaderStage;
+    use crate::render_pass::Subpass;
+    use std::sync::Arc;
+
+    #[test]
+    fn shaders_reflection() {
+        let vs = ShaderReflection::new(&VERTEX_SHADER).unwrap();
+        let vs = vs.entry_point("main").unwrap();
+        assert_eq!(vs.stage, ShaderStage::Vertex);
+        assert_eq!(vs.push_constants.as_ref().unwrap().range.size, 64);
+        let inputs = vs
+            .input_interface
+            .iter()
+            .map(|entry| (entry.location.clone(), entry.format, entry.name.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            inputs,
+            [
+                (0..1, Format::R32G32B32Sfloat, Some("position".into())),
+                (1..2, Format::R32G32B32A32Sfloat, Some("color".into())),
+            ]
+        );
+        assert_eq!(vs.output_interface.len(), 1);
+
+        let fs = ShaderReflection::new(&FRAGMENT_SHADER).unwrap();
+        let fs = fs.entry_point("main").unwrap();
+        assert_eq!(fs.stage, ShaderStage::Fragment);
+        assert_eq!(fs.input_interface.len(), 1);
+        assert_eq!(fs.output_interface[0].format, Format::R32G32B32A32Sfloat);
+    }
+
+    #[test]
+    fn shapes() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let render_pass = crate::single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Load,
+                    store: Store,
+                    format: Format::R8G8B8A8Unorm,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )
+        .unwrap();
+        let debug_draw = DebugDraw::new(Subpass::from(Arc::new(render_pass), 0).unwrap()).unwrap();
+
+        debug_draw.line([0.0; 3], [1.0; 3], [1.0; 4]);
+        debug_draw.wire_box([0.0; 3], [1.0; 3], [1.0; 4]);
+        debug_draw.sphere([0.0; 3], 1.0, [1.0; 4]);
+        debug_draw.rect([0.0; 2], [10.0; 2], [1.0; 4]);
+        assert_eq!(debug_draw.num_lines(), 1 + 12 + 3 * SPHERE_SEGMENTS + 4);
+
+        debug_draw.clear();
+        assert_eq!(debug_draw.num_lines(), 0);
+    }
+}
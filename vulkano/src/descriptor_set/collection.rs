@@ -8,16 +8,18 @@
 // according to those terms.
 
 use crate::descriptor_set::DescriptorSetWithOffsets;
+use smallvec::smallvec;
+use smallvec::SmallVec;
 
 /// A collection of descriptor set objects.
 pub unsafe trait DescriptorSetsCollection {
-    fn into_vec(self) -> Vec<DescriptorSetWithOffsets>;
+    fn into_vec(self) -> SmallVec<[DescriptorSetWithOffsets; 12]>;
 }
 
 unsafe impl DescriptorSetsCollection for () {
     #[inline]
-    fn into_vec(self) -> Vec<DescriptorSetWithOffsets> {
-        vec![]
+    fn into_vec(self) -> SmallVec<[DescriptorSetWithOffsets; 12]> {
+        SmallVec::new()
     }
 }
 
@@ -26,8 +28,8 @@ where
     T: Into<DescriptorSetWithOffsets>,
 {
     #[inline]
-    fn into_vec(self) -> Vec<DescriptorSetWithOffsets> {
-        vec![self.into()]
+    fn into_vec(self) -> SmallVec<[DescriptorSetWithOffsets; 12]> {
+        smallvec![self.into()]
     }
 }
 
@@ -36,7 +38,7 @@ where
     T: Into<DescriptorSetWithOffsets>,
 {
     #[inline]
-    fn into_vec(self) -> Vec<DescriptorSetWithOffsets> {
+    fn into_vec(self) -> SmallVec<[DescriptorSetWithOffsets; 12]> {
         self.into_iter().map(|x| x.into()).collect()
     }
 }
@@ -48,12 +50,12 @@ macro_rules! impl_collection {
                   $(, $others: Into<DescriptorSetWithOffsets>)*
         {
             #[inline]
-            fn into_vec(self) -> Vec<DescriptorSetWithOffsets> {
+            fn into_vec(self) -> SmallVec<[DescriptorSetWithOffsets; 12]> {
                 #![allow(non_snake_case)]
 
                 let ($first, $($others,)*) = self;
 
-                let mut list = Vec::new();
+                let mut list = SmallVec::new();
                 list.push($first.into());
                 $(
                     list.push($others.into());
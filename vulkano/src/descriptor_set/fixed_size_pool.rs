@@ -55,6 +55,7 @@ use crate::descriptor_set::pool::DescriptorPoolAllocError;
 use crate::descriptor_set::pool::UnsafeDescriptorPool;
 use crate::descriptor_set::DescriptorSet;
 use crate::descriptor_set::UnsafeDescriptorSet;
+use crate::device::DebugName;
 use crate::device::Device;
 use crate::device::DeviceOwned;
 use crate::image::view::ImageViewAbstract;
@@ -149,6 +150,13 @@ unsafe impl<R> DeviceOwned for FixedSizeDescriptorSet<R> {
     }
 }
 
+impl<R> DebugName for FixedSizeDescriptorSet<R> {
+    #[inline]
+    fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        self.inner.set_debug_name(name)
+    }
+}
+
 impl<R> PartialEq for FixedSizeDescriptorSet<R>
 where
     R: PersistentDescriptorSetResources,
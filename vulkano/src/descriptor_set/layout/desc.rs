@@ -52,7 +52,7 @@ use std::cmp;
 use std::error;
 use std::fmt;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct DescriptorSetDesc {
     descriptors: SmallVec<[Option<DescriptorDesc>; 32]>,
 }
@@ -41,15 +41,22 @@ use crate::descriptor_set::pool::DescriptorPoolAlloc;
 use crate::descriptor_set::sys::DescriptorWrite;
 use crate::descriptor_set::DescriptorSet;
 use crate::descriptor_set::UnsafeDescriptorSet;
+use crate::device::DebugName;
 use crate::device::Device;
 use crate::device::DeviceOwned;
 use crate::format::Format;
 use crate::image::view::ImageViewAbstract;
+use crate::image::ImageLayout;
 use crate::image::SampleCount;
+use crate::image::Texture;
+use crate::image::TextureView;
+use crate::render_pass::Subpass;
 use crate::sampler::Sampler;
 use crate::OomError;
 use crate::VulkanObject;
+use ash::vk::Handle;
 use std::error;
+use std::ffi::CString;
 use std::fmt;
 use std::hash::Hash;
 use std::hash::Hasher;
@@ -124,6 +131,28 @@ unsafe impl<R, P> DeviceOwned for PersistentDescriptorSet<R, P> {
     }
 }
 
+impl<R, P> DebugName for PersistentDescriptorSet<R, P>
+where
+    P: DescriptorPoolAlloc,
+{
+    fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        assert!(
+            self.device()
+                .instance()
+                .enabled_extensions()
+                .ext_debug_utils
+        );
+        let name = CString::new(name).unwrap();
+        unsafe {
+            self.device().set_object_name_raw(
+                ash::vk::ObjectType::DESCRIPTOR_SET,
+                self.inner.inner().internal_object().as_raw(),
+                &name,
+            )
+        }
+    }
+}
+
 impl<R, P> PartialEq for PersistentDescriptorSet<R, P>
 where
     P: DescriptorPoolAlloc,
@@ -171,6 +200,12 @@ pub struct PersistentDescriptorSetBuilder<R> {
     resources: R,
 }
 
+// Resources of a `PersistentDescriptorSetBuilder` after `add_texture`.
+type WithTexture<R> = (
+    (R, PersistentDescriptorSetImg<Arc<TextureView>>),
+    PersistentDescriptorSetSampler,
+);
+
 // TODO: lots of checks are still missing, see the docs of
 //       VkDescriptorImageInfo and VkWriteDescriptorSet
 
@@ -291,6 +326,57 @@ impl<R> PersistentDescriptorSetBuilder<R> {
         self.enter_array()?.add_buffer(buffer)?.leave_array()
     }
 
+    /// Binds buffers as the next descriptors, one buffer per descriptor, the same as calling
+    /// `add_buffer` for each of them.
+    ///
+    /// Contrary to `add_buffer`, the number of buffers doesn't need to be known at compile time.
+    ///
+    /// # Panic
+    ///
+    /// Panics if one of the buffers doesn't have the same device as the descriptor set layout.
+    ///
+    pub fn add_buffers<I, T>(
+        self,
+        buffers: I,
+    ) -> Result<
+        PersistentDescriptorSetBuilder<(R, PersistentDescriptorSetBufs<T>)>,
+        PersistentDescriptorSetError,
+    >
+    where
+        I: IntoIterator<Item = T>,
+        T: BufferAccess,
+    {
+        let PersistentDescriptorSetBuilder {
+            mut layout,
+            mut binding_id,
+            mut writes,
+            resources,
+        } = self;
+        let mut added = Vec::new();
+
+        for buffer in buffers {
+            let builder = PersistentDescriptorSetBuilder {
+                layout,
+                binding_id,
+                writes,
+                resources: (),
+            }
+            .add_buffer(buffer)?;
+
+            layout = builder.layout;
+            binding_id = builder.binding_id;
+            writes = builder.writes;
+            added.push(builder.resources.1);
+        }
+
+        Ok(PersistentDescriptorSetBuilder {
+            layout,
+            binding_id,
+            writes,
+            resources: (resources, PersistentDescriptorSetBufs { buffers: added }),
+        })
+    }
+
     /// Binds a buffer view as the next descriptor.
     ///
     /// An error is returned if the buffer isn't compatible with the descriptor.
@@ -334,6 +420,35 @@ impl<R> PersistentDescriptorSetBuilder<R> {
         self.enter_array()?.add_image(image_view)?.leave_array()
     }
 
+    /// Binds an image view as the next descriptor, which must be an input attachment.
+    ///
+    /// `input_attachment_index` is the `input_attachment_index` of the descriptor in the shader,
+    /// which corresponds to the index of the input attachment in `subpass`. An error is returned
+    /// if the image view isn't compatible with the descriptor, or with the attachment of the
+    /// render pass that is read by `subpass` at this index.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the image view doesn't have the same device as the descriptor set layout.
+    ///
+    #[inline]
+    pub fn add_input_attachment<T>(
+        self,
+        image_view: T,
+        subpass: &Subpass,
+        input_attachment_index: u32,
+    ) -> Result<
+        PersistentDescriptorSetBuilder<(R, PersistentDescriptorSetImg<T>)>,
+        PersistentDescriptorSetError,
+    >
+    where
+        T: ImageViewAbstract,
+    {
+        self.enter_array()?
+            .add_input_attachment(image_view, subpass, input_attachment_index)?
+            .leave_array()
+    }
+
     /// Binds an image view with a sampler as the next descriptor.
     ///
     /// An error is returned if the image view isn't compatible with the descriptor.
@@ -363,6 +478,16 @@ impl<R> PersistentDescriptorSetBuilder<R> {
             .leave_array()
     }
 
+    /// Binds the view and the sampler of a texture as the next descriptor, the same as
+    /// `add_sampled_image`.
+    #[inline]
+    pub fn add_texture(
+        self,
+        texture: &Texture,
+    ) -> Result<PersistentDescriptorSetBuilder<WithTexture<R>>, PersistentDescriptorSetError> {
+        self.add_sampled_image(texture.view().clone(), texture.sampler().clone())
+    }
+
     /// Binds a sampler as the next descriptor.
     ///
     /// An error is returned if the sampler isn't compatible with the descriptor.
@@ -729,6 +854,85 @@ impl<R> PersistentDescriptorSetBuilderArray<R> {
         })
     }
 
+    /// Binds an image view as the next element in the array, which must be an input attachment.
+    ///
+    /// `input_attachment_index` is the `input_attachment_index` of the descriptor in the shader,
+    /// which corresponds to the index of the input attachment in `subpass`. An error is returned
+    /// if the image view isn't compatible with the descriptor, or with the attachment of the
+    /// render pass that is read by `subpass` at this index.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the image view doesn't have the same device as the descriptor set layout.
+    ///
+    pub fn add_input_attachment<T>(
+        self,
+        image_view: T,
+        subpass: &Subpass,
+        input_attachment_index: u32,
+    ) -> Result<
+        PersistentDescriptorSetBuilderArray<(R, PersistentDescriptorSetImg<T>)>,
+        PersistentDescriptorSetError,
+    >
+    where
+        T: ImageViewAbstract,
+    {
+        match self.desc.ty {
+            DescriptorDescTy::InputAttachment { .. } => (),
+            ref ty => {
+                return Err(PersistentDescriptorSetError::WrongDescriptorTy { expected: ty.ty() });
+            }
+        }
+
+        let render_pass_desc = subpass.render_pass().desc();
+        let input_attachments =
+            &render_pass_desc.subpasses()[subpass.index() as usize].input_attachments;
+
+        let (attachment_num, layout) = match input_attachments.get(input_attachment_index as usize)
+        {
+            Some(&input) => input,
+            None => {
+                return Err(
+                    PersistentDescriptorSetError::InputAttachmentIndexOutOfRange {
+                        index: input_attachment_index,
+                        num_input_attachments: input_attachments.len() as u32,
+                    },
+                );
+            }
+        };
+
+        let attachment_format = render_pass_desc.attachments()[attachment_num].format;
+        if image_view.format() != attachment_format {
+            return Err(PersistentDescriptorSetError::ImageViewFormatMismatch {
+                expected: attachment_format,
+                obtained: image_view.format(),
+            });
+        }
+
+        // VUID-VkDescriptorImageInfo-imageView-01976
+        let aspects = image_view.format().aspects();
+        if aspects.depth && aspects.stencil {
+            return Err(PersistentDescriptorSetError::InputAttachmentDepthAndStencil);
+        }
+
+        // The image is expected to be in its input attachment layout when used in a descriptor,
+        // which must be the layout that the subpass puts the attachment in.
+        let image_layout = match image_view.image().descriptor_layouts() {
+            Some(layouts) => layouts.input_attachment,
+            None => return Err(PersistentDescriptorSetError::MissingDescriptorLayouts),
+        };
+        if image_layout != layout {
+            return Err(
+                PersistentDescriptorSetError::InputAttachmentLayoutMismatch {
+                    expected: layout,
+                    obtained: image_layout,
+                },
+            );
+        }
+
+        self.add_image(image_view)
+    }
+
     /// Binds an image view with a sampler as the next element in the array.
     ///
     /// An error is returned if the image view isn't compatible with the descriptor.
@@ -1016,6 +1220,44 @@ where
     }
 }
 
+/// Internal object related to the `PersistentDescriptorSet` system.
+pub struct PersistentDescriptorSetBufs<B> {
+    buffers: Vec<PersistentDescriptorSetBuf<B>>,
+}
+
+unsafe impl<R, B> PersistentDescriptorSetResources for (R, PersistentDescriptorSetBufs<B>)
+where
+    R: PersistentDescriptorSetResources,
+    B: BufferAccess,
+{
+    #[inline]
+    fn num_buffers(&self) -> usize {
+        self.0.num_buffers() + self.1.buffers.len()
+    }
+
+    #[inline]
+    fn buffer(&self, index: usize) -> Option<(&dyn BufferAccess, u32)> {
+        if let Some(buf) = self.0.buffer(index) {
+            Some(buf)
+        } else {
+            self.1
+                .buffers
+                .get(index - self.0.num_buffers())
+                .map(|buf| (&buf.buffer as &dyn BufferAccess, buf.descriptor_num))
+        }
+    }
+
+    #[inline]
+    fn num_images(&self) -> usize {
+        self.0.num_images()
+    }
+
+    #[inline]
+    fn image(&self, index: usize) -> Option<(&dyn ImageViewAbstract, u32)> {
+        self.0.image(index)
+    }
+}
+
 /// Internal object related to the `PersistentDescriptorSet` system.
 pub struct PersistentDescriptorSetBufView<V>
 where
@@ -1183,6 +1425,26 @@ pub enum PersistentDescriptorSetError {
     /// The image view isn't compatible with the sampler.
     IncompatibleImageViewSampler,
 
+    /// The image view of an input attachment has both a depth and a stencil aspect.
+    InputAttachmentDepthAndStencil,
+
+    /// The input attachment index doesn't exist in the subpass.
+    InputAttachmentIndexOutOfRange {
+        /// The input attachment index that was passed.
+        index: u32,
+        /// Number of input attachments of the subpass.
+        num_input_attachments: u32,
+    },
+
+    /// The layout the image is in when used as an input attachment doesn't match the layout of
+    /// the input attachment in the subpass.
+    InputAttachmentLayoutMismatch {
+        /// Layout of the input attachment in the subpass.
+        expected: ImageLayout,
+        /// Layout of the image when used as an input attachment.
+        obtained: ImageLayout,
+    },
+
     /// Didn't fill all the elements of an array before leaving.
     MissingArrayElements {
         /// Number of expected elements.
@@ -1194,6 +1456,9 @@ pub enum PersistentDescriptorSetError {
     /// The buffer is missing the correct usage.
     MissingBufferUsage(MissingBufferUsage),
 
+    /// The image of an image view doesn't specify the layouts it's in when used in descriptors.
+    MissingDescriptorLayouts,
+
     /// The image is missing the correct usage.
     MissingImageUsage(MissingImageUsage),
 
@@ -1240,12 +1505,25 @@ impl fmt::Display for PersistentDescriptorSetError {
                 PersistentDescriptorSetError::IncompatibleImageViewSampler => {
                     "the image view isn't compatible with the sampler"
                 }
+                PersistentDescriptorSetError::InputAttachmentDepthAndStencil => {
+                    "the image view of an input attachment has both a depth and a stencil aspect"
+                }
+                PersistentDescriptorSetError::InputAttachmentIndexOutOfRange { .. } => {
+                    "the input attachment index doesn't exist in the subpass"
+                }
+                PersistentDescriptorSetError::InputAttachmentLayoutMismatch { .. } => {
+                    "the layout of the image doesn't match the layout of the input attachment in \
+                     the subpass"
+                }
                 PersistentDescriptorSetError::MissingArrayElements { .. } => {
                     "didn't fill all the elements of an array before leaving"
                 }
                 PersistentDescriptorSetError::MissingBufferUsage { .. } => {
                     "the buffer is missing the correct usage"
                 }
+                PersistentDescriptorSetError::MissingDescriptorLayouts => {
+                    "the image of an image view doesn't specify its descriptor layouts"
+                }
                 PersistentDescriptorSetError::MissingImageUsage { .. } => {
                     "the image is missing the correct usage"
                 }
@@ -1302,3 +1580,269 @@ impl fmt::Display for PersistentDescriptorSetBuildError {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::BufferAccess;
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::descriptor_set::layout::DescriptorBufferDesc;
+    use crate::descriptor_set::layout::DescriptorDesc;
+    use crate::descriptor_set::layout::DescriptorDescTy;
+    use crate::descriptor_set::layout::DescriptorImageDescArray;
+    use crate::descriptor_set::layout::DescriptorSetDesc;
+    use crate::descriptor_set::layout::DescriptorSetLayout;
+    use crate::descriptor_set::PersistentDescriptorSet;
+    use crate::descriptor_set::PersistentDescriptorSetError;
+    use crate::device::Device;
+    use crate::format::Format;
+    use crate::image::view::ImageView;
+    use crate::image::AttachmentImage;
+    use crate::image::ImageCreateFlags;
+    use crate::image::ImageDimensions;
+    use crate::image::ImageLayout;
+    use crate::image::ImageUsage;
+    use crate::image::StorageImage;
+    use crate::pipeline::shader::ShaderStages;
+    use crate::render_pass::RenderPass;
+    use crate::render_pass::Subpass;
+    use std::iter;
+    use std::sync::Arc;
+
+    #[test]
+    fn add_buffers() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let storage_buffer = DescriptorDesc {
+            ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                dynamic: Some(false),
+                storage: true,
+            }),
+            array_count: 1,
+            stages: ShaderStages {
+                compute: true,
+                ..ShaderStages::none()
+            },
+            readonly: true,
+        };
+        let layout = Arc::new(
+            DescriptorSetLayout::new(
+                device.clone(),
+                DescriptorSetDesc::new([Some(storage_buffer.clone()), Some(storage_buffer)]),
+            )
+            .unwrap(),
+        );
+
+        let buffers: Vec<Arc<dyn BufferAccess + Send + Sync>> = (0..3)
+            .map(|_| {
+                CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                    .unwrap() as Arc<_>
+            })
+            .collect();
+
+        let set = PersistentDescriptorSet::start(layout.clone())
+            .add_buffers(buffers[..2].iter().cloned())
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(set.resources.1.buffers.len(), 2);
+
+        // The buffers can follow other descriptors, and be followed by other descriptors.
+        assert!(PersistentDescriptorSet::start(layout.clone())
+            .add_buffer(buffers[0].clone())
+            .unwrap()
+            .add_buffers(iter::empty::<Arc<dyn BufferAccess + Send + Sync>>())
+            .unwrap()
+            .add_buffers(iter::once(buffers[1].clone()))
+            .unwrap()
+            .build()
+            .is_ok());
+
+        // There are more buffers than descriptors.
+        match PersistentDescriptorSet::start(layout).add_buffers(buffers.iter().cloned()) {
+            Err(PersistentDescriptorSetError::EmptyExpected) => (),
+            _ => panic!(),
+        }
+    }
+
+    // Layout with a single input attachment descriptor.
+    fn input_attachment_layout(device: Arc<Device>) -> Arc<DescriptorSetLayout> {
+        let input_attachment = DescriptorDesc {
+            ty: DescriptorDescTy::InputAttachment {
+                multisampled: false,
+                array_layers: DescriptorImageDescArray::NonArrayed,
+            },
+            array_count: 1,
+            stages: ShaderStages {
+                fragment: true,
+                ..ShaderStages::none()
+            },
+            readonly: true,
+        };
+
+        Arc::new(
+            DescriptorSetLayout::new(device, DescriptorSetDesc::new([Some(input_attachment)]))
+                .unwrap(),
+        )
+    }
+
+    // Render pass whose second subpass reads the color and the depth-stencil attachments of the
+    // first one.
+    fn input_attachment_render_pass(device: Arc<Device>, depth_format: Format) -> Arc<RenderPass> {
+        Arc::new(
+            crate::ordered_passes_renderpass!(device,
+                attachments: {
+                    color: {
+                        load: Clear,
+                        store: DontCare,
+                        format: Format::R8G8B8A8Unorm,
+                        samples: 1,
+                    },
+                    depth: {
+                        load: Clear,
+                        store: DontCare,
+                        format: depth_format,
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [color],
+                        depth_stencil: {depth},
+                        input: []
+                    },
+                    {
+                        color: [],
+                        depth_stencil: {},
+                        input: [color, depth]
+                    }
+                ]
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn input_attachment_index_out_of_range() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let render_pass = input_attachment_render_pass(device.clone(), Format::D16Unorm);
+        let view = ImageView::new(
+            AttachmentImage::input_attachment(device.clone(), [64, 64], Format::R8G8B8A8Unorm)
+                .unwrap(),
+        )
+        .unwrap();
+
+        let subpass = Subpass::from(render_pass, 1).unwrap();
+        assert!(
+            PersistentDescriptorSet::start(input_attachment_layout(device.clone()))
+                .add_input_attachment(view.clone(), &subpass, 0)
+                .is_ok()
+        );
+
+        match PersistentDescriptorSet::start(input_attachment_layout(device))
+            .add_input_attachment(view, &subpass, 2)
+        {
+            Err(PersistentDescriptorSetError::InputAttachmentIndexOutOfRange {
+                index: 2,
+                num_input_attachments: 2,
+            }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn input_attachment_format_mismatch() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let render_pass = input_attachment_render_pass(device.clone(), Format::D16Unorm);
+        let view = ImageView::new(
+            AttachmentImage::input_attachment(device.clone(), [64, 64], Format::R8G8B8A8Srgb)
+                .unwrap(),
+        )
+        .unwrap();
+
+        match PersistentDescriptorSet::start(input_attachment_layout(device)).add_input_attachment(
+            view,
+            &Subpass::from(render_pass, 1).unwrap(),
+            0,
+        ) {
+            Err(PersistentDescriptorSetError::ImageViewFormatMismatch {
+                expected: Format::R8G8B8A8Unorm,
+                obtained: Format::R8G8B8A8Srgb,
+            }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn input_attachment_depth_and_stencil() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        // Either of these formats is guaranteed to be supported.
+        let depth_format = [Format::D24Unorm_S8Uint, Format::D32Sfloat_S8Uint]
+            .iter()
+            .copied()
+            .find(|format| {
+                format
+                    .properties(device.physical_device())
+                    .optimal_tiling_features
+                    .depth_stencil_attachment
+            })
+            .unwrap();
+
+        let render_pass = input_attachment_render_pass(device.clone(), depth_format);
+        let view = ImageView::new(
+            AttachmentImage::input_attachment(device.clone(), [64, 64], depth_format).unwrap(),
+        )
+        .unwrap();
+
+        match PersistentDescriptorSet::start(input_attachment_layout(device)).add_input_attachment(
+            view,
+            &Subpass::from(render_pass, 1).unwrap(),
+            1,
+        ) {
+            Err(PersistentDescriptorSetError::InputAttachmentDepthAndStencil) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn input_attachment_layout_mismatch() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let render_pass = input_attachment_render_pass(device.clone(), Format::D16Unorm);
+
+        // Storage images are in the general layout when used in descriptors, while the subpass
+        // reads its input attachments in the shader read-only layout.
+        let image = StorageImage::with_usage(
+            device.clone(),
+            ImageDimensions::Dim2d {
+                width: 64,
+                height: 64,
+                array_layers: 1,
+            },
+            Format::R8G8B8A8Unorm,
+            ImageUsage {
+                input_attachment: true,
+                ..ImageUsage::none()
+            },
+            ImageCreateFlags::none(),
+            Some(queue.family()),
+        )
+        .unwrap();
+        let view = ImageView::new(image).unwrap();
+
+        match PersistentDescriptorSet::start(input_attachment_layout(device)).add_input_attachment(
+            view,
+            &Subpass::from(render_pass, 1).unwrap(),
+            0,
+        ) {
+            Err(PersistentDescriptorSetError::InputAttachmentLayoutMismatch {
+                expected: ImageLayout::ShaderReadOnlyOptimal,
+                obtained: ImageLayout::General,
+            }) => (),
+            _ => panic!(),
+        }
+    }
+}
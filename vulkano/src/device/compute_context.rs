@@ -0,0 +1,325 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::CommandBufferExecError;
+use crate::command_buffer::CommandBufferExecFuture;
+use crate::command_buffer::CommandBufferUsage;
+use crate::command_buffer::PrimaryAutoCommandBuffer;
+use crate::command_buffer::PrimaryCommandBuffer;
+use crate::device::physical::PhysicalDevice;
+use crate::device::Device;
+use crate::device::DeviceCreationError;
+use crate::device::DeviceExtensions;
+use crate::device::Features;
+use crate::device::PhysicalDeviceSelector;
+use crate::device::Queue;
+use crate::instance::Instance;
+use crate::instance::InstanceCreationError;
+use crate::instance::InstanceExtensions;
+use crate::memory::pool::StdMemoryPool;
+use crate::pipeline::ComputeRunner;
+use crate::sync::FlushError;
+use crate::sync::GpuFuture;
+use crate::sync::NowFuture;
+use crate::OomError;
+use crate::Version;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+/// Everything needed to run computations on a GPU, without a window.
+///
+/// `ComputeContext::new` creates an instance without any surface extension, picks the most
+/// suitable physical device that supports compute operations, and creates a device with one
+/// compute queue. Discrete GPUs are preferred, and a queue family that doesn't support graphics
+/// operations is preferred. The memory is allocated from the standard memory pool of the device.
+///
+/// # Example
+///
+/// ```no_run
+/// use vulkano::buffer::BufferUsage;
+/// use vulkano::buffer::CpuAccessibleBuffer;
+/// use vulkano::device::ComputeContext;
+///
+/// let context = ComputeContext::new().unwrap();
+///
+/// let data = CpuAccessibleBuffer::from_iter(
+///     context.device().clone(),
+///     BufferUsage::all(),
+///     false,
+///     0..65536u32,
+/// )
+/// .unwrap();
+///
+/// let mut builder = context.command_buffer_builder().unwrap();
+/// builder.fill_buffer(data.clone(), 12).unwrap();
+/// context.submit_and_wait(builder.build().unwrap()).unwrap();
+///
+/// assert!(data.read().unwrap().iter().all(|&n| n == 12));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ComputeContext {
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    memory_pool: Arc<StdMemoryPool>,
+}
+
+impl ComputeContext {
+    /// Builds a new `ComputeContext` without any required feature or extension.
+    ///
+    /// The `khr_storage_buffer_storage_class` extension is enabled if it is supported, since
+    /// shaders that access storage buffers need it on Vulkan 1.0.
+    #[inline]
+    pub fn new() -> Result<ComputeContext, ComputeContextError> {
+        ComputeContext::with_requirements(&Features::none(), &DeviceExtensions::none())
+    }
+
+    /// Same as `new`, but only picks a physical device that supports the given features and
+    /// extensions, and enables them.
+    pub fn with_requirements(
+        features: &Features,
+        extensions: &DeviceExtensions,
+    ) -> Result<ComputeContext, ComputeContextError> {
+        let instance = Instance::new(None, Version::V1_2, &InstanceExtensions::none(), None)?;
+
+        let selected = PhysicalDeviceSelector::new()
+            .compute()
+            .features(features.clone())
+            .extensions(*extensions)
+            .select(&instance)
+            .ok_or(ComputeContextError::NoSuitablePhysicalDevice)?;
+        let queue_family = selected.compute_queue_family.unwrap();
+
+        let (device, mut queues, _) = Device::with_optional(
+            selected.physical_device,
+            features,
+            &Features::none(),
+            extensions,
+            &DeviceExtensions {
+                khr_storage_buffer_storage_class: true,
+                ..DeviceExtensions::none()
+            },
+            [(queue_family, 1.0)],
+        )?;
+        let queue = queues.next().unwrap();
+        let memory_pool = Device::standard_pool(&device);
+
+        Ok(ComputeContext {
+            device,
+            queue,
+            memory_pool,
+        })
+    }
+
+    /// Returns the instance.
+    #[inline]
+    pub fn instance(&self) -> &Arc<Instance> {
+        self.device.instance()
+    }
+
+    /// Returns the physical device that was picked.
+    #[inline]
+    pub fn physical_device(&self) -> PhysicalDevice<'_> {
+        self.device.physical_device()
+    }
+
+    /// Returns the device.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Returns the compute queue.
+    #[inline]
+    pub fn queue(&self) -> &Arc<Queue> {
+        &self.queue
+    }
+
+    /// Returns the memory pool that buffers and images are allocated from.
+    #[inline]
+    pub fn memory_pool(&self) -> &Arc<StdMemoryPool> {
+        &self.memory_pool
+    }
+
+    /// Starts recording a command buffer for the compute queue, to be submitted once.
+    #[inline]
+    pub fn command_buffer_builder(
+        &self,
+    ) -> Result<AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, OomError> {
+        AutoCommandBufferBuilder::primary(
+            self.device.clone(),
+            self.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+    }
+
+    /// Submits a command buffer to the compute queue. The returned future must be flushed for
+    /// the work to start.
+    #[inline]
+    pub fn submit<C>(
+        &self,
+        command_buffer: C,
+    ) -> Result<CommandBufferExecFuture<NowFuture, C>, CommandBufferExecError>
+    where
+        C: PrimaryCommandBuffer + 'static,
+    {
+        command_buffer.execute(self.queue.clone())
+    }
+
+    /// Submits a command buffer to the compute queue, and blocks until it is executed.
+    pub fn submit_and_wait<C>(&self, command_buffer: C) -> Result<(), ComputeSubmitError>
+    where
+        C: PrimaryCommandBuffer + 'static,
+    {
+        self.submit(command_buffer)?
+            .then_signal_fence_and_flush()?
+            .wait(None)?;
+        Ok(())
+    }
+
+    /// Builds a `ComputeRunner` that submits to the compute queue.
+    #[inline]
+    pub fn runner(&self) -> ComputeRunner {
+        ComputeRunner::new(self.queue.clone(), None)
+    }
+}
+
+/// Error that can happen when creating a `ComputeContext`.
+#[derive(Debug, Clone)]
+pub enum ComputeContextError {
+    /// Failed to create the instance.
+    InstanceCreationError(InstanceCreationError),
+    /// No physical device supports compute operations and the required features and extensions.
+    NoSuitablePhysicalDevice,
+    /// Failed to create the device.
+    DeviceCreationError(DeviceCreationError),
+}
+
+impl error::Error for ComputeContextError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ComputeContextError::InstanceCreationError(ref err) => Some(err),
+            ComputeContextError::DeviceCreationError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ComputeContextError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                ComputeContextError::InstanceCreationError(_) => "failed to create the instance",
+                ComputeContextError::NoSuitablePhysicalDevice => {
+                    "no physical device supports compute operations and the required features and extensions"
+                }
+                ComputeContextError::DeviceCreationError(_) => "failed to create the device",
+            }
+        )
+    }
+}
+
+impl From<InstanceCreationError> for ComputeContextError {
+    #[inline]
+    fn from(err: InstanceCreationError) -> ComputeContextError {
+        ComputeContextError::InstanceCreationError(err)
+    }
+}
+
+impl From<DeviceCreationError> for ComputeContextError {
+    #[inline]
+    fn from(err: DeviceCreationError) -> ComputeContextError {
+        ComputeContextError::DeviceCreationError(err)
+    }
+}
+
+/// Error that can happen when submitting a command buffer with
+/// `ComputeContext::submit_and_wait`.
+#[derive(Debug, Clone)]
+pub enum ComputeSubmitError {
+    /// Failed to submit the command buffer.
+    ExecError(CommandBufferExecError),
+    /// Failed to flush the submission or to wait for it.
+    FlushError(FlushError),
+}
+
+impl error::Error for ComputeSubmitError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ComputeSubmitError::ExecError(ref err) => Some(err),
+            ComputeSubmitError::FlushError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for ComputeSubmitError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                ComputeSubmitError::ExecError(_) => "failed to submit the command buffer",
+                ComputeSubmitError::FlushError(_) => {
+                    "failed to flush the submission or to wait for it"
+                }
+            }
+        )
+    }
+}
+
+impl From<CommandBufferExecError> for ComputeSubmitError {
+    #[inline]
+    fn from(err: CommandBufferExecError) -> ComputeSubmitError {
+        ComputeSubmitError::ExecError(err)
+    }
+}
+
+impl From<FlushError> for ComputeSubmitError {
+    #[inline]
+    fn from(err: FlushError) -> ComputeSubmitError {
+        ComputeSubmitError::FlushError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::device::ComputeContext;
+
+    #[test]
+    fn fill_buffer() {
+        let context = match ComputeContext::new() {
+            Ok(context) => context,
+            Err(_) => return,
+        };
+        assert!(context.queue().family().supports_compute());
+
+        let data = CpuAccessibleBuffer::from_iter(
+            context.device().clone(),
+            BufferUsage::all(),
+            false,
+            0..16u32,
+        )
+        .unwrap();
+
+        let mut builder = context.command_buffer_builder().unwrap();
+        builder.fill_buffer(data.clone(), 12).unwrap();
+        context.submit_and_wait(builder.build().unwrap()).unwrap();
+
+        assert!(data.read().unwrap().iter().all(|&n| n == 12));
+    }
+}
@@ -0,0 +1,192 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::sync::PipelineStage;
+use fnv::FnvHashMap;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
+use std::os::raw::c_void;
+
+/// Crash diagnostics that the driver collects for a device, with the
+/// `nv_device_diagnostics_config` extension. See `Device::with_diagnostics_config`.
+///
+/// The collected data is written in the GPU crash dumps of tools such as Nsight Aftermath.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct DiagnosticsConfig {
+    /// Keeps the debug information of the shaders, so that a crash can be traced back to the
+    /// source of the shader. The shaders must be compiled with debug information.
+    pub shader_debug_info: bool,
+    /// Tracks the resources that are in use, so a crash can be traced back to an invalid
+    /// resource.
+    pub resource_tracking: bool,
+    /// Has the driver insert checkpoints around the commands. These checkpoints are only
+    /// reported in the crash dumps, not by `Queue::checkpoints`.
+    pub automatic_checkpoints: bool,
+}
+
+impl DiagnosticsConfig {
+    /// Builds a `DiagnosticsConfig` with all the diagnostics disabled.
+    #[inline]
+    pub fn none() -> DiagnosticsConfig {
+        DiagnosticsConfig::default()
+    }
+
+    /// Builds a `DiagnosticsConfig` with all the diagnostics enabled.
+    #[inline]
+    pub fn all() -> DiagnosticsConfig {
+        DiagnosticsConfig {
+            shader_debug_info: true,
+            resource_tracking: true,
+            automatic_checkpoints: true,
+        }
+    }
+}
+
+impl From<DiagnosticsConfig> for ash::vk::DeviceDiagnosticsConfigFlagsNV {
+    #[inline]
+    fn from(val: DiagnosticsConfig) -> Self {
+        let mut result = ash::vk::DeviceDiagnosticsConfigFlagsNV::empty();
+        if val.shader_debug_info {
+            result |= ash::vk::DeviceDiagnosticsConfigFlagsNV::ENABLE_SHADER_DEBUG_INFO;
+        }
+        if val.resource_tracking {
+            result |= ash::vk::DeviceDiagnosticsConfigFlagsNV::ENABLE_RESOURCE_TRACKING;
+        }
+        if val.automatic_checkpoints {
+            result |= ash::vk::DeviceDiagnosticsConfigFlagsNV::ENABLE_AUTOMATIC_CHECKPOINTS;
+        }
+        result
+    }
+}
+
+/// A checkpoint of a command buffer reached by a queue, as returned by `Queue::checkpoints`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// The pipeline stage that the queue had reached for this checkpoint. `None` if the driver
+    /// returned a combination of stages.
+    pub stage: Option<PipelineStage>,
+    /// The label of the checkpoint, or `None` if it wasn't set by vulkano or has been dropped,
+    /// see `AutoCommandBufferBuilder::set_checkpoint`.
+    pub label: Option<String>,
+}
+
+impl fmt::Display for Checkpoint {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", self.label.as_deref().unwrap_or("<unknown>"))?;
+        match self.stage {
+            Some(stage) => write!(fmt, " ({:?})", stage),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Checkpoint {
+    pub(crate) fn from_data(data: &ash::vk::CheckpointDataNV, labels: &CheckpointLabels) -> Self {
+        Checkpoint {
+            stage: PipelineStage::try_from(data.stage).ok(),
+            label: labels.label(data.p_checkpoint_marker).map(str::to_owned),
+        }
+    }
+}
+
+// Maximum number of labels that a device keeps.
+const MAX_CHECKPOINT_LABELS: usize = 4096;
+
+// Labels of the checkpoints of a device.
+//
+// A checkpoint marker is an opaque pointer that the driver returns as is, so the markers are
+// ids of the labels in this list rather than pointers to them. This way they stay valid whatever
+// happened to the command buffers when the device was lost. Labels are deduplicated, as the
+// automatic checkpoints are labelled with the name of the command.
+//
+// Only the last `MAX_CHECKPOINT_LABELS` distinct labels are kept, so that labels built at
+// runtime don't grow the list without bound. The markers of the labels that were dropped are
+// never reused, and have no label.
+#[derive(Debug, Default)]
+pub(crate) struct CheckpointLabels {
+    labels: VecDeque<String>,
+    // Id of the first label of `labels`.
+    first_id: usize,
+    ids: FnvHashMap<String, usize>,
+}
+
+impl CheckpointLabels {
+    // Returns the marker of the checkpoints labelled `label`.
+    pub(crate) fn marker(&mut self, label: &str) -> *const c_void {
+        let id = match self.ids.get(label) {
+            Some(&id) => id,
+            None => {
+                if self.labels.len() == MAX_CHECKPOINT_LABELS {
+                    let oldest = self.labels.pop_front().unwrap();
+                    self.ids.remove(&oldest);
+                    self.first_id += 1;
+                }
+
+                let id = self.first_id + self.labels.len();
+                self.labels.push_back(label.to_owned());
+                self.ids.insert(label.to_owned(), id);
+                id
+            }
+        };
+
+        // Null markers are reserved for the checkpoints that vulkano doesn't know about.
+        (id + 1) as *const c_void
+    }
+
+    // Returns the label of the checkpoints with the given marker.
+    pub(crate) fn label(&self, marker: *const c_void) -> Option<&str> {
+        (marker as usize)
+            .checked_sub(1)
+            .and_then(|id| id.checked_sub(self.first_id))
+            .and_then(|index| self.labels.get(index))
+            .map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::device::diagnostics::CheckpointLabels;
+    use crate::device::diagnostics::MAX_CHECKPOINT_LABELS;
+    use std::ptr;
+
+    #[test]
+    fn checkpoint_labels() {
+        let mut labels = CheckpointLabels::default();
+        let draw = labels.marker("vkCmdDraw");
+        let dispatch = labels.marker("vkCmdDispatch");
+
+        assert_eq!(labels.marker("vkCmdDraw"), draw);
+        assert_ne!(draw, dispatch);
+        assert_eq!(labels.label(draw), Some("vkCmdDraw"));
+        assert_eq!(labels.label(dispatch), Some("vkCmdDispatch"));
+        assert_eq!(labels.label(ptr::null()), None);
+    }
+
+    #[test]
+    fn checkpoint_labels_bounded() {
+        let mut labels = CheckpointLabels::default();
+        let first = labels.marker("label 0");
+        for i in 1..=MAX_CHECKPOINT_LABELS {
+            labels.marker(&format!("label {}", i));
+        }
+
+        assert_eq!(labels.labels.len(), MAX_CHECKPOINT_LABELS);
+        assert_eq!(labels.ids.len(), MAX_CHECKPOINT_LABELS);
+        assert_eq!(labels.label(first), None);
+        assert_ne!(labels.marker("label 0"), first);
+
+        let last = labels.marker(&format!("label {}", MAX_CHECKPOINT_LABELS));
+        assert_eq!(
+            labels.label(last),
+            Some(format!("label {}", MAX_CHECKPOINT_LABELS).as_str())
+        );
+    }
+}
@@ -90,15 +90,21 @@
 //!
 //! TODO: write
 
+pub use self::compute_context::{ComputeContext, ComputeContextError, ComputeSubmitError};
+use self::diagnostics::CheckpointLabels;
+pub use self::diagnostics::{Checkpoint, DiagnosticsConfig};
 pub(crate) use self::features::FeaturesFfi;
 pub use self::features::{FeatureRestriction, FeatureRestrictionError, Features};
 pub use self::properties::Properties;
 pub(crate) use self::properties::PropertiesFfi;
+pub use self::queue_request::{QueueRequest, QueueRequestError, RequestedQueues};
+pub use self::selector::{PhysicalDeviceSelector, SelectedPhysicalDevice};
 pub use crate::autogen::DeviceExtensions;
 use crate::check_errors;
 use crate::command_buffer::pool::StandardCommandPool;
 use crate::descriptor_set::pool::StdDescriptorPool;
 use crate::device::physical::PhysicalDevice;
+use crate::device::physical::PhysicalDeviceGroup;
 use crate::device::physical::QueueFamily;
 pub use crate::extensions::{
     ExtensionRestriction, ExtensionRestrictionError, SupportedExtensionsError,
@@ -111,17 +117,26 @@ use crate::image::ImageTiling;
 use crate::image::ImageType;
 use crate::image::ImageUsage;
 use crate::instance::Instance;
+use crate::instance::InstanceExtensions;
 use crate::memory::pool::StdMemoryPool;
+use crate::pipeline::layout::PipelineLayout;
+use crate::pnext::PNextChain;
+use crate::sampler::Sampler;
+use crate::sampler::SamplerKey;
 use crate::Error;
 use crate::OomError;
+use crate::Success;
 use crate::SynchronizedVulkanObject;
 use crate::Version;
 use crate::VulkanObject;
 use ash::vk::Handle;
+use crossbeam_queue::SegQueue;
+use fnv::FnvHashMap;
 use fnv::FnvHasher;
 use smallvec::SmallVec;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::error;
 use std::ffi::CStr;
 use std::ffi::CString;
@@ -129,24 +144,37 @@ use std::fmt;
 use std::hash::BuildHasherDefault;
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::iter;
 use std::mem;
 use std::mem::MaybeUninit;
 use std::ops::Deref;
+use std::panic::Location;
+use std::path::Path;
 use std::ptr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::MutexGuard;
+use std::sync::RwLock;
 use std::sync::Weak;
+use std::time::Duration;
 
+pub(crate) mod compute_context;
+pub(crate) mod diagnostics;
 pub(crate) mod extensions;
 pub(crate) mod features;
 pub mod physical;
 pub(crate) mod properties;
+pub(crate) mod queue_request;
+pub(crate) mod selector;
 
 /// Represents a Vulkan context.
 pub struct Device {
     instance: Arc<Instance>,
     physical_device: usize,
+    // Indices of the physical devices of the device group, in the order of their device index.
+    physical_devices: SmallVec<[usize; 4]>,
     device: ash::vk::Device,
 
     // The highest version that is supported for this device.
@@ -157,14 +185,23 @@ pub struct Device {
     standard_pool: Mutex<Weak<StdMemoryPool>>,
     standard_descriptor_pool: Mutex<Weak<StdDescriptorPool>>,
     standard_command_pools:
-        Mutex<HashMap<u32, Weak<StandardCommandPool>, BuildHasherDefault<FnvHasher>>>,
+        RwLock<HashMap<u32, Weak<StandardCommandPool>, BuildHasherDefault<FnvHasher>>>,
     features: Features,
     extensions: DeviceExtensions,
     active_queue_families: SmallVec<[u32; 8]>,
     allocation_count: Mutex<u32>,
-    fence_pool: Mutex<Vec<ash::vk::Fence>>,
-    semaphore_pool: Mutex<Vec<ash::vk::Semaphore>>,
-    event_pool: Mutex<Vec<ash::vk::Event>>,
+    fence_pool: SegQueue<ash::vk::Fence>,
+    semaphore_pool: SegQueue<ash::vk::Semaphore>,
+    event_pool: SegQueue<ash::vk::Event>,
+    // True if the profiling lock was acquired with `acquire_profiling_lock` and not released.
+    profiling_lock: AtomicBool,
+    auto_debug_names: AtomicBool,
+    auto_checkpoints: AtomicBool,
+    checkpoint_labels: Mutex<CheckpointLabels>,
+    // The pipeline layouts that pipelines share, or `None` if the cache is disabled.
+    pipeline_layout_cache: Mutex<Option<Vec<Weak<PipelineLayout>>>>,
+    // The samplers that are shared, or `None` if the cache is disabled.
+    sampler_cache: Mutex<Option<FnvHashMap<SamplerKey, Weak<Sampler>>>>,
 }
 
 // The `StandardCommandPool` type doesn't implement Send/Sync, so we have to manually reimplement
@@ -193,6 +230,7 @@ impl Device {
     ///
     // TODO: return Arc<Queue> and handle synchronization in the Queue
     // TODO: should take the PhysicalDevice by value
+    #[inline]
     pub fn new<'a, I>(
         physical_device: PhysicalDevice,
         requested_features: &Features,
@@ -202,6 +240,233 @@ impl Device {
     where
         I: IntoIterator<Item = (QueueFamily<'a>, f32)>,
     {
+        Device::with_global_priorities(
+            physical_device,
+            requested_features,
+            requested_extensions,
+            queue_families,
+            iter::empty(),
+        )
+    }
+
+    /// Same as `new`, but additionally enables the optional features and extensions that are
+    /// supported by the physical device.
+    ///
+    /// The required features and extensions must be supported, like with `new`. The optional
+    /// ones that aren't supported are left out instead of returning an error, and the returned
+    /// `OptionalSupport` indicates which of them have been enabled.
+    ///
+    /// An optional feature or extension whose own requirements aren't met, for example an
+    /// extension that depends on an extension that isn't enabled, is left out as well. If an
+    /// optional extension depends on another extension, put the latter in the optional
+    /// extensions too.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the queue families doesn't belong to the given device.
+    ///
+    #[inline]
+    pub fn with_optional<'a, I>(
+        physical_device: PhysicalDevice,
+        required_features: &Features,
+        optional_features: &Features,
+        required_extensions: &DeviceExtensions,
+        optional_extensions: &DeviceExtensions,
+        queue_families: I,
+    ) -> Result<(Arc<Device>, QueuesIter, OptionalSupport), DeviceCreationError>
+    where
+        I: IntoIterator<Item = (QueueFamily<'a>, f32)>,
+    {
+        let optional_support = OptionalSupport::new(
+            (required_features, required_extensions),
+            (optional_features, optional_extensions),
+            (
+                physical_device.supported_features(),
+                physical_device.supported_extensions(),
+            ),
+            physical_device.api_version(),
+            physical_device.instance().enabled_extensions(),
+        );
+
+        let (device, queues) = Device::new(
+            physical_device,
+            &required_features.union(&optional_support.enabled_features),
+            &required_extensions.union(&optional_support.enabled_extensions),
+            queue_families,
+        )?;
+
+        Ok((device, queues, optional_support))
+    }
+
+    /// Same as `new`, but additionally requests a system-wide priority for the queues of some
+    /// queue families.
+    ///
+    /// Each element of `global_priorities` indicates a queue family and the global priority to
+    /// assign to all the queues created from it. Unlike the priorities passed in `queue_families`,
+    /// which only order the queues of this device relative to each other, the global priority
+    /// orders them relative to the queues of all the other devices and processes of the system.
+    /// Families that aren't listed use the default priority, `QueueGlobalPriority::Medium`.
+    ///
+    /// The `ext_global_priority` extension must be enabled if `global_priorities` isn't empty.
+    /// Raising the priority above `Medium` may require special privileges, in which case
+    /// `DeviceCreationError::NotPermitted` is returned.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the queue families doesn't belong to the given device.
+    /// - Panics if a queue family appears in `global_priorities` more than once, or doesn't
+    ///   appear in `queue_families`.
+    ///
+    #[inline]
+    pub fn with_global_priorities<'a, I, G>(
+        physical_device: PhysicalDevice,
+        requested_features: &Features,
+        requested_extensions: &DeviceExtensions,
+        queue_families: I,
+        global_priorities: G,
+    ) -> Result<(Arc<Device>, QueuesIter), DeviceCreationError>
+    where
+        I: IntoIterator<Item = (QueueFamily<'a>, f32)>,
+        G: IntoIterator<Item = (QueueFamily<'a>, QueueGlobalPriority)>,
+    {
+        Device::create(
+            &[physical_device],
+            requested_features,
+            requested_extensions,
+            queue_families,
+            global_priorities,
+            PNextChain::new(),
+        )
+    }
+
+    /// Same as `new`, but builds a device that spans all the physical devices of `group`.
+    ///
+    /// The queues, features and extensions are those of the first physical device of the group,
+    /// which the queue families must belong to. The commands of a command buffer are executed by
+    /// all the physical devices of the group, unless they are restricted with
+    /// [`set_device_mask`](crate::command_buffer::AutoCommandBufferBuilder::set_device_mask).
+    ///
+    /// The `khr_device_group` extension should be enabled if the API version of the device is
+    /// lower than 1.1, as most of the functions that are specific to device groups require it.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the queue families doesn't belong to the first physical device of
+    ///   `group`.
+    ///
+    #[inline]
+    pub fn with_device_group<'a, I>(
+        group: &PhysicalDeviceGroup<'a>,
+        requested_features: &Features,
+        requested_extensions: &DeviceExtensions,
+        queue_families: I,
+    ) -> Result<(Arc<Device>, QueuesIter), DeviceCreationError>
+    where
+        I: IntoIterator<Item = (QueueFamily<'a>, f32)>,
+    {
+        Device::create(
+            group.physical_devices(),
+            requested_features,
+            requested_extensions,
+            queue_families,
+            iter::empty(),
+            PNextChain::new(),
+        )
+    }
+
+    /// Same as `new`, but also enables crash diagnostics collected by the driver.
+    ///
+    /// The `nv_device_diagnostics_config` extension and the `diagnostics_config` feature must be
+    /// enabled. See also `set_auto_checkpoints` and `Queue::checkpoints`, which work without
+    /// this.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the queue families doesn't belong to the given device.
+    ///
+    #[inline]
+    pub fn with_diagnostics_config<'a, I>(
+        physical_device: PhysicalDevice,
+        requested_features: &Features,
+        requested_extensions: &DeviceExtensions,
+        queue_families: I,
+        diagnostics_config: DiagnosticsConfig,
+    ) -> Result<(Arc<Device>, QueuesIter), DeviceCreationError>
+    where
+        I: IntoIterator<Item = (QueueFamily<'a>, f32)>,
+    {
+        if !requested_extensions.nv_device_diagnostics_config
+            || !requested_features.diagnostics_config
+        {
+            return Err(DeviceCreationError::DiagnosticsConfigNotEnabled);
+        }
+
+        let mut info = ash::vk::DeviceDiagnosticsConfigCreateInfoNV {
+            flags: diagnostics_config.into(),
+            ..Default::default()
+        };
+        let mut p_next_chain = PNextChain::<ash::vk::DeviceCreateInfo>::new();
+        p_next_chain.push(&mut info);
+
+        Device::create(
+            &[physical_device],
+            requested_features,
+            requested_extensions,
+            queue_families,
+            iter::empty(),
+            p_next_chain,
+        )
+    }
+
+    /// Same as `new`, but also appends structures to the `pNext` chain of the
+    /// `VkDeviceCreateInfo` structure. See the `pnext` module.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the queue families doesn't belong to the given device.
+    ///
+    /// # Safety
+    ///
+    /// - The structures of `p_next_chain` must be valid, and the extensions that define them
+    ///   must be enabled in `requested_extensions`.
+    /// - `p_next_chain` must not contain a structure that vulkano already appends, in particular
+    ///   the structures of the features that vulkano knows about and
+    ///   `VkDeviceGroupDeviceCreateInfo`.
+    ///
+    #[inline]
+    pub unsafe fn with_p_next_chain<'a, I>(
+        physical_device: PhysicalDevice,
+        requested_features: &Features,
+        requested_extensions: &DeviceExtensions,
+        queue_families: I,
+        p_next_chain: PNextChain<ash::vk::DeviceCreateInfo>,
+    ) -> Result<(Arc<Device>, QueuesIter), DeviceCreationError>
+    where
+        I: IntoIterator<Item = (QueueFamily<'a>, f32)>,
+    {
+        Device::create(
+            &[physical_device],
+            requested_features,
+            requested_extensions,
+            queue_families,
+            iter::empty(),
+            p_next_chain,
+        )
+    }
+
+    fn create<'a, I, G>(
+        physical_devices: &[PhysicalDevice],
+        requested_features: &Features,
+        requested_extensions: &DeviceExtensions,
+        queue_families: I,
+        global_priorities: G,
+        mut p_next_chain: PNextChain<ash::vk::DeviceCreateInfo>,
+    ) -> Result<(Arc<Device>, QueuesIter), DeviceCreationError>
+    where
+        I: IntoIterator<Item = (QueueFamily<'a>, f32)>,
+        G: IntoIterator<Item = (QueueFamily<'a>, QueueGlobalPriority)>,
+    {
+        let physical_device = physical_devices[0];
         let instance = physical_device.instance();
         let fns_i = instance.fns();
         let api_version = physical_device.api_version();
@@ -273,18 +538,58 @@ impl Device {
                 output_queues.push((queue_family.id(), 0));
             }
 
+            // global priority of each entry of `queues`, if one was requested
+            let mut global_priority_infos: SmallVec<[Option<_>; 16]> =
+                queues.iter().map(|_| None).collect();
+
+            for (queue_family, global_priority) in global_priorities {
+                assert_eq!(
+                    queue_family.physical_device().internal_object(),
+                    physical_device.internal_object()
+                );
+
+                if !requested_extensions.ext_global_priority {
+                    return Err(DeviceCreationError::GlobalPriorityExtensionNotEnabled);
+                }
+
+                if let Some(supported) = queue_family.supported_global_priorities() {
+                    if !supported.contains(&global_priority) {
+                        return Err(DeviceCreationError::GlobalPriorityNotSupported);
+                    }
+                }
+
+                let index = queues
+                    .iter()
+                    .position(|q| q.0 == queue_family.id())
+                    .expect("Requested a global priority for a queue family without any queue");
+                assert!(
+                    global_priority_infos[index].is_none(),
+                    "Requested a global priority for the same queue family twice"
+                );
+                global_priority_infos[index] =
+                    Some(ash::vk::DeviceQueueGlobalPriorityCreateInfoEXT {
+                        global_priority: global_priority.into(),
+                        ..Default::default()
+                    });
+            }
+
             // turning `queues` into an array of `vkDeviceQueueCreateInfo` suitable for Vulkan
             let queues = queues
                 .iter()
-                .map(
-                    |&(queue_id, ref priorities)| ash::vk::DeviceQueueCreateInfo {
+                .zip(global_priority_infos.iter())
+                .map(|(&(queue_id, ref priorities), global_priority_info)| {
+                    ash::vk::DeviceQueueCreateInfo {
+                        p_next: match global_priority_info {
+                            Some(info) => info as *const _ as *const _,
+                            None => ptr::null(),
+                        },
                         flags: ash::vk::DeviceQueueCreateFlags::empty(),
                         queue_family_index: queue_id,
                         queue_count: priorities.len() as u32,
                         p_queue_priorities: priorities.as_ptr(),
                         ..Default::default()
-                    },
-                )
+                    }
+                })
                 .collect::<SmallVec<[_; 16]>>();
 
             let mut features_ffi = FeaturesFfi::default();
@@ -319,12 +624,30 @@ impl Device {
                 .enabled_extensions()
                 .khr_get_physical_device_properties2;
 
+            let mut p_next: *const std::ffi::c_void = if has_khr_get_physical_device_properties2 {
+                features_ffi.head_as_ref() as *const _ as _
+            } else {
+                ptr::null()
+            };
+
+            // A group of a single physical device is the same as no group at all.
+            let physical_device_handles = physical_devices
+                .iter()
+                .map(|physical_device| physical_device.internal_object())
+                .collect::<SmallVec<[_; 4]>>();
+            let device_group_info = ash::vk::DeviceGroupDeviceCreateInfo {
+                p_next,
+                physical_device_count: physical_device_handles.len() as u32,
+                p_physical_devices: physical_device_handles.as_ptr(),
+                ..Default::default()
+            };
+            if physical_device_handles.len() > 1 {
+                p_next = &device_group_info as *const _ as _;
+            }
+            p_next = p_next_chain.link(p_next);
+
             let infos = ash::vk::DeviceCreateInfo {
-                p_next: if has_khr_get_physical_device_properties2 {
-                    features_ffi.head_as_ref() as *const _ as _
-                } else {
-                    ptr::null()
-                },
+                p_next,
                 flags: ash::vk::DeviceCreateFlags::empty(),
                 queue_create_info_count: queues.len() as u32,
                 p_queue_create_infos: queues.as_ptr(),
@@ -351,8 +674,84 @@ impl Device {
             (output.assume_init(), output_queues)
         };
 
+        let features = Features {
+            // Always enabled ; see above
+            robust_buffer_access: true,
+            ..requested_features.clone()
+        };
+
+        Ok(unsafe {
+            Device::from_handle_inner(
+                physical_devices,
+                device,
+                &features,
+                requested_extensions,
+                queues,
+            )
+        })
+    }
+
+    /// Builds a `Device` from a Vulkan device that was created outside of vulkano, for example
+    /// by an OpenXR runtime or by a library that uses `ash`.
+    ///
+    /// Each element of `queues` indicates the family and the index of a queue that was created
+    /// with the device. The returned iterator produces these queues in the same order. The
+    /// `Device` takes ownership of the handle, and destroys it when it is dropped.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the queue families doesn't belong to the given device.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid device that was created from `physical_device`.
+    /// - `enabled_features` and `enabled_extensions` must match the features and extensions that
+    ///   were enabled when creating the device.
+    /// - The queues of `queues` must have been created with the device.
+    /// - The device must not be destroyed by anything else than the returned `Device`.
+    ///
+    pub unsafe fn from_handle<'a, I>(
+        physical_device: PhysicalDevice,
+        handle: ash::vk::Device,
+        enabled_features: &Features,
+        enabled_extensions: &DeviceExtensions,
+        queues: I,
+    ) -> (Arc<Device>, QueuesIter)
+    where
+        I: IntoIterator<Item = (QueueFamily<'a>, u32)>,
+    {
+        let queues = queues
+            .into_iter()
+            .map(|(queue_family, index)| {
+                assert_eq!(
+                    queue_family.physical_device().internal_object(),
+                    physical_device.internal_object()
+                );
+                (queue_family.id(), index)
+            })
+            .collect();
+
+        Device::from_handle_inner(
+            &[physical_device],
+            handle,
+            enabled_features,
+            enabled_extensions,
+            queues,
+        )
+    }
+
+    unsafe fn from_handle_inner(
+        physical_devices: &[PhysicalDevice],
+        device: ash::vk::Device,
+        features: &Features,
+        extensions: &DeviceExtensions,
+        queues: SmallVec<[(u32, u32); 8]>,
+    ) -> (Arc<Device>, QueuesIter) {
+        let physical_device = physical_devices[0];
+        let fns_i = physical_device.instance().fns();
+
         // loading the function pointers of the newly-created device
-        let fns = DeviceFunctions::load(|name| unsafe {
+        let fns = DeviceFunctions::load(|name| {
             mem::transmute(fns_i.v1_0.get_device_proc_addr(device, name.as_ptr()))
         });
 
@@ -369,23 +768,29 @@ impl Device {
         let device = Arc::new(Device {
             instance: physical_device.instance().clone(),
             physical_device: physical_device.index(),
+            physical_devices: physical_devices
+                .iter()
+                .map(|physical_device| physical_device.index())
+                .collect(),
             device: device,
-            api_version,
+            api_version: physical_device.api_version(),
             fns,
             standard_pool: Mutex::new(Weak::new()),
             standard_descriptor_pool: Mutex::new(Weak::new()),
-            standard_command_pools: Mutex::new(Default::default()),
-            features: Features {
-                // Always enabled ; see above
-                robust_buffer_access: true,
-                ..requested_features.clone()
-            },
-            extensions: requested_extensions.clone(),
+            standard_command_pools: RwLock::new(Default::default()),
+            features: features.clone(),
+            extensions: *extensions,
             active_queue_families,
             allocation_count: Mutex::new(0),
-            fence_pool: Mutex::new(Vec::new()),
-            semaphore_pool: Mutex::new(Vec::new()),
-            event_pool: Mutex::new(Vec::new()),
+            fence_pool: SegQueue::new(),
+            semaphore_pool: SegQueue::new(),
+            event_pool: SegQueue::new(),
+            profiling_lock: AtomicBool::new(false),
+            auto_debug_names: AtomicBool::new(false),
+            auto_checkpoints: AtomicBool::new(false),
+            checkpoint_labels: Mutex::new(CheckpointLabels::default()),
+            pipeline_layout_cache: Mutex::new(None),
+            sampler_cache: Mutex::new(None),
         });
 
         // Iterator for the produced queues.
@@ -395,7 +800,7 @@ impl Device {
             families_and_ids: queues,
         };
 
-        Ok((device, queues))
+        (device, queues)
     }
 
     /// Returns the Vulkan version supported by the device.
@@ -430,6 +835,75 @@ impl Device {
         Ok(())
     }
 
+    /// Acquires the profiling lock of the device, or waits at most `timeout` for another process
+    /// to release it.
+    ///
+    /// The profiling lock must be held while command buffers that use performance query pools
+    /// are recorded and executed. See [`QueryPool::performance`](crate::query::QueryPool::performance).
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the `khr_performance_query` extension isn't enabled on the device.
+    ///
+    pub fn acquire_profiling_lock(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<(), ProfilingLockError> {
+        assert!(self.enabled_extensions().khr_performance_query);
+
+        let timeout_ns = if let Some(timeout) = timeout {
+            timeout
+                .as_secs()
+                .saturating_mul(1_000_000_000)
+                .saturating_add(timeout.subsec_nanos() as u64)
+        } else {
+            u64::MAX
+        };
+
+        let info = ash::vk::AcquireProfilingLockInfoKHR {
+            flags: ash::vk::AcquireProfilingLockFlagsKHR::empty(),
+            timeout: timeout_ns,
+            ..Default::default()
+        };
+
+        unsafe {
+            match check_errors(
+                self.fns
+                    .khr_performance_query
+                    .acquire_profiling_lock_khr(self.device, &info),
+            ) {
+                Ok(Success::Success) => {
+                    self.profiling_lock.store(true, Ordering::Release);
+                    Ok(())
+                }
+                Ok(Success::Timeout) => Err(ProfilingLockError::Timeout),
+                r => panic!("unexpected result: {:?}", r),
+            }
+        }
+    }
+
+    /// Returns true if the profiling lock of the device was acquired with
+    /// `acquire_profiling_lock` and wasn't released since.
+    #[inline]
+    pub fn is_profiling_lock_held(&self) -> bool {
+        self.profiling_lock.load(Ordering::Acquire)
+    }
+
+    /// Releases the profiling lock of the device.
+    ///
+    /// # Safety
+    ///
+    /// - The profiling lock must have been acquired with `acquire_profiling_lock`.
+    /// - No command buffer that uses a performance query pool must be being recorded or executed.
+    ///
+    #[inline]
+    pub unsafe fn release_profiling_lock(&self) {
+        self.profiling_lock.store(false, Ordering::Release);
+        self.fns
+            .khr_performance_query
+            .release_profiling_lock_khr(self.device);
+    }
+
     /// Returns the instance used to create this device.
     #[inline]
     pub fn instance(&self) -> &Arc<Instance> {
@@ -442,6 +916,75 @@ impl Device {
         PhysicalDevice::from_index(&self.instance, self.physical_device).unwrap()
     }
 
+    /// Returns the physical devices of the device group that this device spans, in the order of
+    /// their device index. For a device that wasn't created from a device group, this only
+    /// contains the physical device returned by `physical_device`.
+    #[inline]
+    pub fn physical_devices(&self) -> impl ExactSizeIterator<Item = PhysicalDevice<'_>> {
+        let instance = &self.instance;
+        self.physical_devices
+            .iter()
+            .map(move |&index| PhysicalDevice::from_index(instance, index).unwrap())
+    }
+
+    /// Returns the device mask that contains all the physical devices of the device.
+    #[inline]
+    pub fn all_devices_mask(&self) -> u32 {
+        u32::MAX >> (32 - self.physical_devices.len())
+    }
+
+    /// Returns how the physical device of index `local_device_index` can access memory that is
+    /// allocated in the heap `heap_index` of the physical device of index `remote_device_index`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device was created with an API version lower than 1.1 and the
+    ///   `khr_device_group` extension isn't enabled.
+    /// - Panics if `heap_index` is out of range.
+    /// - Panics if one of the device indices is out of range, or if they are equal.
+    ///
+    pub fn peer_memory_features(
+        &self,
+        heap_index: u32,
+        local_device_index: u32,
+        remote_device_index: u32,
+    ) -> PeerMemoryFeatures {
+        assert!(self.api_version() >= Version::V1_1 || self.enabled_extensions().khr_device_group);
+        assert!(self
+            .physical_device()
+            .memory_heap_by_id(heap_index)
+            .is_some());
+        assert!((local_device_index as usize) < self.physical_devices.len());
+        assert!((remote_device_index as usize) < self.physical_devices.len());
+        assert_ne!(local_device_index, remote_device_index);
+
+        unsafe {
+            let fns = self.fns();
+            let mut output = MaybeUninit::uninit();
+
+            if self.api_version() >= Version::V1_1 {
+                fns.v1_1.get_device_group_peer_memory_features(
+                    self.device,
+                    heap_index,
+                    local_device_index,
+                    remote_device_index,
+                    output.as_mut_ptr(),
+                );
+            } else {
+                fns.khr_device_group
+                    .get_device_group_peer_memory_features_khr(
+                        self.device,
+                        heap_index,
+                        local_device_index,
+                        remote_device_index,
+                        output.as_mut_ptr(),
+                    );
+            }
+
+            output.assume_init().into()
+        }
+    }
+
     /// Returns an iterator to the list of queues families that this device uses.
     ///
     /// > **Note**: Will return `-> impl ExactSizeIterator<Item = QueueFamily>` in the future.
@@ -506,7 +1049,19 @@ impl Device {
     /// - Panics if the device and the queue family don't belong to the same physical device.
     ///
     pub fn standard_command_pool(me: &Arc<Self>, queue: QueueFamily) -> Arc<StandardCommandPool> {
-        let mut standard_command_pools = me.standard_command_pools.lock().unwrap();
+        // Every `AutoCommandBufferBuilder` created with the default pool goes through here, so the
+        // threads that record command buffers only share a read lock as long as the pool exists.
+        if let Some(pool) = me
+            .standard_command_pools
+            .read()
+            .unwrap()
+            .get(&queue.id())
+            .and_then(Weak::upgrade)
+        {
+            return pool;
+        }
+
+        let mut standard_command_pools = me.standard_command_pools.write().unwrap();
 
         match standard_command_pools.entry(queue.id()) {
             Entry::Occupied(mut entry) => {
@@ -537,15 +1092,15 @@ impl Device {
         &self.allocation_count
     }
 
-    pub(crate) fn fence_pool(&self) -> &Mutex<Vec<ash::vk::Fence>> {
+    pub(crate) fn fence_pool(&self) -> &SegQueue<ash::vk::Fence> {
         &self.fence_pool
     }
 
-    pub(crate) fn semaphore_pool(&self) -> &Mutex<Vec<ash::vk::Semaphore>> {
+    pub(crate) fn semaphore_pool(&self) -> &SegQueue<ash::vk::Semaphore> {
         &self.semaphore_pool
     }
 
-    pub(crate) fn event_pool(&self) -> &Mutex<Vec<ash::vk::Event>> {
+    pub(crate) fn event_pool(&self) -> &SegQueue<ash::vk::Event> {
         &self.event_pool
     }
 
@@ -589,6 +1144,148 @@ impl Device {
         Ok(())
     }
 
+    /// Sets whether the objects created from this device are automatically named after the
+    /// source location where the application created them, for example `src/main.rs:42:17`.
+    ///
+    /// This applies to buffers, images, image views, samplers, shader modules, pipelines,
+    /// semaphores, fences, events and query pools. Descriptor sets, command buffers and the
+    /// synchronization primitives taken with `from_pool` are reused by vulkano, so they are not
+    /// named, and neither are the objects that vulkano creates internally. Naming an object with
+    /// `DebugName::set_debug_name` replaces its automatic name.
+    ///
+    /// Does nothing if the `ext_debug_utils` extension isn't enabled on the instance. The default
+    /// is `false`.
+    #[inline]
+    pub fn set_auto_debug_names(&self, enabled: bool) {
+        self.auto_debug_names.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns true if the objects created from this device are automatically named.
+    ///
+    /// See `set_auto_debug_names`.
+    #[inline]
+    pub fn auto_debug_names(&self) -> bool {
+        self.auto_debug_names.load(Ordering::Relaxed)
+    }
+
+    // Names `object` after the source location that created it, if automatic names are enabled.
+    pub(crate) fn auto_debug_name<T: VulkanObject>(&self, object: &T, location: &Location) {
+        if !self.auto_debug_names() || !self.instance.enabled_extensions().ext_debug_utils {
+            return;
+        }
+
+        // The objects that vulkano creates internally, for example in pools, would be named after
+        // a location within vulkano, which doesn't help the application.
+        let vulkano_src = Path::new(file!()).parent().and_then(Path::parent);
+        if matches!(vulkano_src, Some(src) if Path::new(location.file()).starts_with(src)) {
+            return;
+        }
+
+        let name = CString::new(location.to_string()).unwrap();
+        unsafe {
+            // Naming the object is only a debugging aid, so errors are ignored.
+            let _ =
+                self.set_object_name_raw(T::Object::TYPE, object.internal_object().as_raw(), &name);
+        }
+    }
+
+    /// Sets whether the command buffers recorded for this device insert a checkpoint before each
+    /// command, labelled with the name of the command, for example `vkCmdDraw`.
+    ///
+    /// After the device is lost, `Queue::checkpoints` then returns the last command that each
+    /// queue reached. Checkpoints can also be inserted with
+    /// [`set_checkpoint`](crate::command_buffer::AutoCommandBufferBuilder::set_checkpoint).
+    ///
+    /// Only the command buffers whose recording starts afterwards are affected. Does nothing if
+    /// the `nv_device_diagnostic_checkpoints` extension isn't enabled on the device. The default
+    /// is `false`.
+    #[inline]
+    pub fn set_auto_checkpoints(&self, enabled: bool) {
+        self.auto_checkpoints.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns true if checkpoints are automatically inserted before each command.
+    ///
+    /// See `set_auto_checkpoints`.
+    #[inline]
+    pub fn auto_checkpoints(&self) -> bool {
+        self.auto_checkpoints.load(Ordering::Relaxed)
+            && self.extensions.nv_device_diagnostic_checkpoints
+    }
+
+    /// Sets whether the pipeline layouts that are created from the descriptions of the shaders
+    /// are shared between the pipelines of this device.
+    ///
+    /// When enabled, `PipelineLayout::from_descs`, and therefore the graphics and compute pipelines
+    /// that are built without an explicit layout, return the existing layout with the same
+    /// descriptor sets and push constant ranges if there is one. Pipelines that were created
+    /// independently then have the same layout, so the descriptor sets that are bound for one of
+    /// them stay bound for the others, and a descriptor set created with the layout of one of
+    /// them can be used with all of them.
+    ///
+    /// The layouts are only kept for as long as they are used. Disabling the cache doesn't affect
+    /// the existing pipelines. The default is `false`.
+    pub fn set_pipeline_layout_cache(&self, enabled: bool) {
+        let mut cache = self.pipeline_layout_cache.lock().unwrap();
+        *cache = if enabled {
+            Some(cache.take().unwrap_or_default())
+        } else {
+            None
+        };
+    }
+
+    /// Returns true if the pipeline layouts are shared between the pipelines.
+    ///
+    /// See `set_pipeline_layout_cache`.
+    #[inline]
+    pub fn pipeline_layout_cache(&self) -> bool {
+        self.pipeline_layout_cache.lock().unwrap().is_some()
+    }
+
+    // Returns the pipeline layouts that are shared between the pipelines, which contains `None`
+    // if the cache is disabled.
+    #[inline]
+    pub(crate) fn pipeline_layouts(&self) -> MutexGuard<'_, Option<Vec<Weak<PipelineLayout>>>> {
+        self.pipeline_layout_cache.lock().unwrap()
+    }
+
+    /// Sets whether the samplers of this device are shared.
+    ///
+    /// When enabled, creating a sampler returns the existing sampler with exactly the same
+    /// parameters if there is one, including the border color. This way the materials that use
+    /// the same sampling state don't each create their own sampler, and don't come close to the
+    /// `max_sampler_allocation_count` limit.
+    ///
+    /// The samplers are only kept for as long as they are used. Disabling the cache doesn't affect
+    /// the existing samplers. The default is `false`.
+    pub fn set_sampler_cache(&self, enabled: bool) {
+        let mut cache = self.sampler_cache.lock().unwrap();
+        *cache = if enabled {
+            Some(cache.take().unwrap_or_default())
+        } else {
+            None
+        };
+    }
+
+    /// Returns true if the samplers are shared.
+    ///
+    /// See `set_sampler_cache`.
+    #[inline]
+    pub fn sampler_cache(&self) -> bool {
+        self.sampler_cache.lock().unwrap().is_some()
+    }
+
+    // Returns the samplers that are shared, which contains `None` if the cache is disabled.
+    #[inline]
+    pub(crate) fn samplers(&self) -> MutexGuard<'_, Option<FnvHashMap<SamplerKey, Weak<Sampler>>>> {
+        self.sampler_cache.lock().unwrap()
+    }
+
+    // Returns the marker of the checkpoints labelled `label`.
+    pub(crate) fn checkpoint_marker(&self, label: &str) -> *const std::ffi::c_void {
+        self.checkpoint_labels.lock().unwrap().marker(label)
+    }
+
     /// Checks the given combination of image attributes/configuration for compatibility with the physical device.
     ///
     /// Returns a struct with additional capabilities available for this image configuration.
@@ -647,17 +1344,17 @@ impl Drop for Device {
     #[inline]
     fn drop(&mut self) {
         unsafe {
-            for &raw_fence in self.fence_pool.lock().unwrap().iter() {
+            while let Some(raw_fence) = self.fence_pool.pop() {
                 self.fns
                     .v1_0
                     .destroy_fence(self.device, raw_fence, ptr::null());
             }
-            for &raw_sem in self.semaphore_pool.lock().unwrap().iter() {
+            while let Some(raw_sem) = self.semaphore_pool.pop() {
                 self.fns
                     .v1_0
                     .destroy_semaphore(self.device, raw_sem, ptr::null());
             }
-            for &raw_event in self.event_pool.lock().unwrap().iter() {
+            while let Some(raw_event) = self.event_pool.pop() {
                 self.fns
                     .v1_0
                     .destroy_event(self.device, raw_event, ptr::null());
@@ -706,6 +1403,140 @@ where
     }
 }
 
+/// Objects that can be given a human-readable name for debugging purposes.
+///
+/// Debugging tools such as RenderDoc and the messages of the validation layers show this name
+/// instead of the raw handle of the object. This is implemented for all the objects that wrap a
+/// Vulkan handle owned by a device, and for the buffers, images, image views, descriptor sets and
+/// command buffers that are built on top of them.
+pub trait DebugName {
+    /// Assigns a human-readable name to the object.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the `ext_debug_utils` extension isn't enabled on the instance.
+    /// - Panics if `name` contains a null character.
+    ///
+    fn set_debug_name(&self, name: &str) -> Result<(), OomError>;
+}
+
+impl<T> DebugName for T
+where
+    T: VulkanObject + DeviceOwned,
+{
+    #[inline]
+    fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        assert!(
+            self.device()
+                .instance()
+                .enabled_extensions()
+                .ext_debug_utils
+        );
+        let name = CString::new(name).unwrap();
+        self.device().set_object_name(self, &name)
+    }
+}
+
+/// Optional features and extensions that have been enabled or left out by
+/// `Device::with_optional`.
+#[derive(Debug, Clone)]
+pub struct OptionalSupport {
+    /// The optional features that have been enabled.
+    pub enabled_features: Features,
+    /// The optional extensions that have been enabled.
+    pub enabled_extensions: DeviceExtensions,
+    /// The optional features that aren't supported by the physical device, or whose
+    /// requirements aren't met.
+    pub missing_features: Features,
+    /// The optional extensions that aren't supported by the physical device, or whose
+    /// requirements aren't met.
+    pub missing_extensions: DeviceExtensions,
+}
+
+impl OptionalSupport {
+    // Chooses the optional features and extensions to enable. Starting from all the supported
+    // ones, the optional features and extensions that fail the requirement checks of
+    // `Device::new` are left out one at a time, until the checks pass or only fail because of
+    // the required ones, in which case `Device::new` returns the error.
+    fn new(
+        (required_features, required_extensions): (&Features, &DeviceExtensions),
+        (optional_features, optional_extensions): (&Features, &DeviceExtensions),
+        (supported_features, supported_extensions): (&Features, &DeviceExtensions),
+        api_version: Version,
+        instance_extensions: &InstanceExtensions,
+    ) -> OptionalSupport {
+        // The optional features and extensions that are also required are always enabled, and
+        // are never left out.
+        let mut enabled_features = optional_features
+            .intersection(supported_features)
+            .difference(required_features);
+        let mut enabled_extensions = optional_extensions
+            .intersection(supported_extensions)
+            .difference(required_extensions);
+
+        loop {
+            let extensions = required_extensions.union(&enabled_extensions);
+
+            if let Err(err) = extensions.check_requirements(
+                supported_extensions,
+                api_version,
+                instance_extensions,
+            ) {
+                let left_out = enabled_extensions.disable_by_name(err.extension)
+                    || match err.restriction {
+                        ExtensionRestriction::ConflictsDeviceExtension(other) => {
+                            enabled_extensions.disable_by_name(other)
+                        }
+                        _ => false,
+                    };
+
+                if left_out {
+                    continue;
+                } else {
+                    break;
+                }
+            }
+
+            // `Device::new` always enables `robust_buffer_access`.
+            let mut features = required_features.union(&enabled_features);
+            features.robust_buffer_access = true;
+
+            if let Err(err) =
+                features.check_requirements(supported_features, api_version, &extensions)
+            {
+                let left_out = match err.restriction {
+                    FeatureRestriction::RequiredByExtension(extension) => {
+                        enabled_extensions.disable_by_name(extension)
+                    }
+                    FeatureRestriction::ConflictsFeature(other) => {
+                        enabled_features.disable_by_name(err.feature)
+                            || enabled_features.disable_by_name(other)
+                    }
+                    _ => enabled_features.disable_by_name(err.feature),
+                };
+
+                if left_out {
+                    continue;
+                }
+            }
+
+            break;
+        }
+
+        let enabled_features =
+            enabled_features.union(&optional_features.intersection(required_features));
+        let enabled_extensions =
+            enabled_extensions.union(&optional_extensions.intersection(required_extensions));
+
+        OptionalSupport {
+            missing_features: optional_features.difference(&enabled_features),
+            missing_extensions: optional_extensions.difference(&enabled_extensions),
+            enabled_features,
+            enabled_extensions,
+        }
+    }
+}
+
 /// Iterator that returns the queues produced when creating a device.
 pub struct QueuesIter {
     next_queue: usize,
@@ -783,6 +1614,16 @@ pub enum DeviceCreationError {
     ExtensionRestrictionNotMet(ExtensionRestrictionError),
     /// A restriction for a feature was not met.
     FeatureRestrictionNotMet(FeatureRestrictionError),
+    /// A global priority was requested but the `ext_global_priority` extension wasn't enabled.
+    GlobalPriorityExtensionNotEnabled,
+    /// One of the requested global priorities isn't supported by its queue family.
+    GlobalPriorityNotSupported,
+    /// The caller doesn't have the privileges required to use one of the requested global
+    /// priorities.
+    NotPermitted,
+    /// Diagnostics were requested but the `nv_device_diagnostics_config` extension or the
+    /// `diagnostics_config` feature wasn't enabled.
+    DiagnosticsConfigNotEnabled,
 }
 
 impl error::Error for DeviceCreationError {}
@@ -825,6 +1666,33 @@ impl fmt::Display for DeviceCreationError {
             }
             DeviceCreationError::ExtensionRestrictionNotMet(err) => err.fmt(fmt),
             DeviceCreationError::FeatureRestrictionNotMet(err) => err.fmt(fmt),
+            DeviceCreationError::GlobalPriorityExtensionNotEnabled => {
+                write!(
+                    fmt,
+                    "a global priority was requested but the `ext_global_priority` extension \
+                     wasn't enabled"
+                )
+            }
+            DeviceCreationError::GlobalPriorityNotSupported => {
+                write!(
+                    fmt,
+                    "one of the requested global priorities isn't supported by its queue family"
+                )
+            }
+            DeviceCreationError::NotPermitted => {
+                write!(
+                    fmt,
+                    "the caller doesn't have the privileges required to use one of the requested \
+                     global priorities"
+                )
+            }
+            DeviceCreationError::DiagnosticsConfigNotEnabled => {
+                write!(
+                    fmt,
+                    "diagnostics were requested but the `nv_device_diagnostics_config` extension \
+                     or the `diagnostics_config` feature wasn't enabled"
+                )
+            }
         }
     }
 }
@@ -840,6 +1708,7 @@ impl From<Error> for DeviceCreationError {
             Error::ExtensionNotPresent => DeviceCreationError::ExtensionNotPresent,
             Error::FeatureNotPresent => DeviceCreationError::FeatureNotPresent,
             Error::TooManyObjects => DeviceCreationError::TooManyObjects,
+            Error::NotPermitted => DeviceCreationError::NotPermitted,
             _ => panic!("Unexpected error value: {}", err as i32),
         }
     }
@@ -859,6 +1728,96 @@ impl From<FeatureRestrictionError> for DeviceCreationError {
     }
 }
 
+/// Error that can be returned by `Device::acquire_profiling_lock`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfilingLockError {
+    /// The lock is held by another process, which didn't release it before the timeout.
+    Timeout,
+}
+
+impl error::Error for ProfilingLockError {}
+
+impl fmt::Display for ProfilingLockError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                ProfilingLockError::Timeout => {
+                    "the lock is held by another process, which didn't release it before the \
+                     timeout"
+                }
+            }
+        )
+    }
+}
+
+/// System-wide priority of a queue, relative to the queues of the other devices and processes.
+///
+/// Requires the `ext_global_priority` extension. See `Device::with_global_priorities`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(i32)]
+pub enum QueueGlobalPriority {
+    /// Lower than the default priority. Useful for background tasks.
+    Low = ash::vk::QueueGlobalPriorityEXT::LOW.as_raw(),
+    /// The default priority of a queue.
+    Medium = ash::vk::QueueGlobalPriorityEXT::MEDIUM.as_raw(),
+    /// Higher than the default priority.
+    High = ash::vk::QueueGlobalPriorityEXT::HIGH.as_raw(),
+    /// The highest priority. Work submitted to such a queue can preempt the work of all the
+    /// other queues of the system.
+    Realtime = ash::vk::QueueGlobalPriorityEXT::REALTIME.as_raw(),
+}
+
+impl From<QueueGlobalPriority> for ash::vk::QueueGlobalPriorityEXT {
+    #[inline]
+    fn from(val: QueueGlobalPriority) -> Self {
+        Self::from_raw(val as i32)
+    }
+}
+
+impl TryFrom<ash::vk::QueueGlobalPriorityEXT> for QueueGlobalPriority {
+    type Error = ();
+
+    #[inline]
+    fn try_from(val: ash::vk::QueueGlobalPriorityEXT) -> Result<Self, Self::Error> {
+        match val {
+            ash::vk::QueueGlobalPriorityEXT::LOW => Ok(Self::Low),
+            ash::vk::QueueGlobalPriorityEXT::MEDIUM => Ok(Self::Medium),
+            ash::vk::QueueGlobalPriorityEXT::HIGH => Ok(Self::High),
+            ash::vk::QueueGlobalPriorityEXT::REALTIME => Ok(Self::Realtime),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How a physical device of a device group can access the memory of another physical device of
+/// the group. See `Device::peer_memory_features`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct PeerMemoryFeatures {
+    /// The memory can be the source of transfer commands.
+    pub copy_src: bool,
+    /// The memory can be the destination of transfer commands.
+    pub copy_dst: bool,
+    /// The memory can be read by any kind of access.
+    pub generic_src: bool,
+    /// The memory can be written by any kind of access.
+    pub generic_dst: bool,
+}
+
+impl From<ash::vk::PeerMemoryFeatureFlags> for PeerMemoryFeatures {
+    #[inline]
+    fn from(val: ash::vk::PeerMemoryFeatureFlags) -> Self {
+        Self {
+            copy_src: val.intersects(ash::vk::PeerMemoryFeatureFlags::COPY_SRC),
+            copy_dst: val.intersects(ash::vk::PeerMemoryFeatureFlags::COPY_DST),
+            generic_src: val.intersects(ash::vk::PeerMemoryFeatureFlags::GENERIC_SRC),
+            generic_dst: val.intersects(ash::vk::PeerMemoryFeatureFlags::GENERIC_DST),
+        }
+    }
+}
+
 /// Represents a queue where commands can be submitted.
 // TODO: should use internal synchronization?
 #[derive(Debug)]
@@ -904,11 +1863,61 @@ impl Queue {
     /// Just like `Device::wait()`, you shouldn't have to call this function in a typical program.
     #[inline]
     pub fn wait(&self) -> Result<(), OomError> {
-        unsafe {
+        self.lock().wait()
+    }
+
+    /// Returns the last checkpoints that the queue reached, usually after the device was lost.
+    ///
+    /// There is one checkpoint for each pipeline stage that the driver tracks. The checkpoints
+    /// are those of `set_auto_checkpoints` and `set_checkpoint`, in no particular order.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the `nv_device_diagnostic_checkpoints` extension isn't enabled on the device.
+    ///
+    pub fn checkpoints(&self) -> Vec<Checkpoint> {
+        assert!(
+            self.device
+                .enabled_extensions()
+                .nv_device_diagnostic_checkpoints
+        );
+
+        let data = unsafe {
             let fns = self.device.fns();
             let queue = self.queue.lock().unwrap();
-            check_errors(fns.v1_0.queue_wait_idle(*queue))?;
-            Ok(())
+
+            let mut num = 0;
+            fns.nv_device_diagnostic_checkpoints
+                .get_queue_checkpoint_data_nv(*queue, &mut num, ptr::null_mut());
+
+            let mut data = vec![ash::vk::CheckpointDataNV::default(); num as usize];
+            fns.nv_device_diagnostic_checkpoints
+                .get_queue_checkpoint_data_nv(*queue, &mut num, data.as_mut_ptr());
+            data.truncate(num as usize);
+            data
+        };
+
+        let labels = self.device.checkpoint_labels.lock().unwrap();
+        data.iter()
+            .map(|data| Checkpoint::from_data(data, &labels))
+            .collect()
+    }
+
+    /// Locks the queue for exclusive use by the current thread.
+    ///
+    /// Vulkan requires all the operations on a queue to be externally synchronized, so vulkano
+    /// locks the queue for the duration of each submission, present or wait. Locking it once
+    /// with this function and using the `submit_locked` functions of the submission builders
+    /// avoids locking it again for each operation, and guarantees that no other thread submits
+    /// work in between.
+    ///
+    /// Other threads that use the queue block until the `QueueGuard` is dropped. Trying to
+    /// flush a future on this queue from the same thread while holding the guard deadlocks.
+    #[inline]
+    pub fn lock(&self) -> QueueGuard<'_> {
+        QueueGuard {
+            queue: self,
+            handle: self.queue.lock().unwrap(),
         }
     }
 }
@@ -936,13 +1945,77 @@ unsafe impl SynchronizedVulkanObject for Queue {
     }
 }
 
+/// Exclusive access to a `Queue`, returned by `Queue::lock`.
+///
+/// The queue is unlocked when the guard is dropped.
+pub struct QueueGuard<'a> {
+    queue: &'a Queue,
+    handle: MutexGuard<'a, ash::vk::Queue>,
+}
+
+impl<'a> QueueGuard<'a> {
+    /// Returns the queue that is locked.
+    #[inline]
+    pub fn queue(&self) -> &'a Queue {
+        self.queue
+    }
+
+    /// Waits until all work on the queue has finished.
+    ///
+    /// Same as `Queue::wait`, but doesn't lock the queue again.
+    #[inline]
+    pub fn wait(&mut self) -> Result<(), OomError> {
+        unsafe {
+            let fns = self.queue.device.fns();
+            check_errors(fns.v1_0.queue_wait_idle(*self.handle))?;
+            Ok(())
+        }
+    }
+}
+
+unsafe impl<'a> VulkanObject for QueueGuard<'a> {
+    type Object = ash::vk::Queue;
+
+    #[inline]
+    fn internal_object(&self) -> ash::vk::Queue {
+        *self.handle
+    }
+}
+
+impl<'a> fmt::Debug for QueueGuard<'a> {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("QueueGuard")
+            .field("queue", &self.queue)
+            .finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::command_buffer::submit::SubmitCommandBufferBuilder;
+    use crate::descriptor_set::layout::DescriptorDesc;
+    use crate::descriptor_set::layout::DescriptorDescTy;
+    use crate::descriptor_set::layout::DescriptorSetDesc;
+    #[cfg(feature = "serde")]
+    use crate::device::physical::CapabilityReport;
     use crate::device::physical::PhysicalDevice;
     use crate::device::Device;
     use crate::device::DeviceCreationError;
     use crate::device::DeviceExtensions;
+    use crate::device::OptionalSupport;
+    use crate::device::QueueGlobalPriority;
     use crate::device::{FeatureRestriction, FeatureRestrictionError, Features};
+    use crate::instance::InstanceExtensions;
+    use crate::pipeline::layout::PipelineLayout;
+    use crate::pipeline::layout::PipelineLayoutPcRange;
+    use crate::pipeline::shader::ShaderStages;
+    use crate::sampler::BorderColor;
+    use crate::sampler::Filter;
+    use crate::sampler::MipmapMode;
+    use crate::sampler::Sampler;
+    use crate::sampler::SamplerAddressMode;
+    use crate::Version;
     use std::sync::Arc;
 
     #[test]
@@ -1003,6 +2076,118 @@ mod tests {
         };
     }
 
+    #[test]
+    fn optional_features() {
+        let instance = instance!();
+        let physical = match PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return,
+        };
+
+        let family = physical.queue_families().next().unwrap();
+
+        let optional_features = Features {
+            geometry_shader: true,
+            sampler_anisotropy: true,
+            shader_int64: true,
+            ..Features::none()
+        };
+
+        let (device, _, optional_support) = Device::with_optional(
+            physical,
+            &Features::none(),
+            &optional_features,
+            &DeviceExtensions::none(),
+            &DeviceExtensions::none(),
+            Some((family, 1.0)),
+        )
+        .unwrap();
+
+        assert!(device
+            .enabled_features()
+            .is_superset_of(&optional_support.enabled_features));
+        assert_eq!(
+            optional_support
+                .enabled_features
+                .union(&optional_support.missing_features),
+            optional_features
+        );
+    }
+
+    #[test]
+    fn optional_requirements_not_met() {
+        // `khr_swapchain_mutable_format` is supported but requires `khr_maintenance2`, which
+        // isn't, and `sparse_image_float32_atomic_add` requires a feature that isn't supported.
+        let supported_extensions = DeviceExtensions {
+            khr_swapchain: true,
+            khr_swapchain_mutable_format: true,
+            khr_image_format_list: true,
+            ..DeviceExtensions::none()
+        };
+        let supported_features = Features {
+            robust_buffer_access: true,
+            sparse_image_float32_atomic_add: true,
+            geometry_shader: true,
+            ..Features::none()
+        };
+        let optional_extensions = DeviceExtensions {
+            khr_swapchain_mutable_format: true,
+            khr_image_format_list: true,
+            ..DeviceExtensions::none()
+        };
+        let optional_features = Features {
+            sparse_image_float32_atomic_add: true,
+            geometry_shader: true,
+            ..Features::none()
+        };
+
+        let optional_support = OptionalSupport::new(
+            (
+                &Features::none(),
+                &DeviceExtensions {
+                    khr_swapchain: true,
+                    ..DeviceExtensions::none()
+                },
+            ),
+            (&optional_features, &optional_extensions),
+            (&supported_features, &supported_extensions),
+            Version::V1_0,
+            &InstanceExtensions {
+                khr_surface: true,
+                ..InstanceExtensions::none()
+            },
+        );
+
+        assert_eq!(
+            optional_support.enabled_extensions,
+            DeviceExtensions {
+                khr_image_format_list: true,
+                ..DeviceExtensions::none()
+            }
+        );
+        assert_eq!(
+            optional_support.missing_extensions,
+            DeviceExtensions {
+                khr_swapchain_mutable_format: true,
+                ..DeviceExtensions::none()
+            }
+        );
+        assert_eq!(
+            optional_support.enabled_features,
+            Features {
+                geometry_shader: true,
+                ..Features::none()
+            }
+        );
+        assert_eq!(
+            optional_support.missing_features,
+            Features {
+                sparse_image_float32_atomic_add: true,
+                ..Features::none()
+            }
+        );
+    }
+
     #[test]
     fn priority_out_of_range() {
         let instance = instance!();
@@ -1033,4 +2218,144 @@ mod tests {
             _ => panic!(),
         };
     }
+
+    #[test]
+    fn global_priority_extension_not_enabled() {
+        let instance = instance!();
+        let physical = match PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return,
+        };
+
+        let family = physical.queue_families().next().unwrap();
+
+        match Device::with_global_priorities(
+            physical,
+            &Features::none(),
+            &DeviceExtensions::none(),
+            Some((family, 1.0)),
+            Some((family, QueueGlobalPriority::High)),
+        ) {
+            Err(DeviceCreationError::GlobalPriorityExtensionNotEnabled) => (), // Success
+            _ => panic!(),
+        };
+    }
+
+    #[test]
+    fn queue_guard() {
+        let (_, queue) = gfx_dev_and_queue!();
+
+        let mut guard = queue.lock();
+        assert!(guard.queue().is_same(&queue));
+        SubmitCommandBufferBuilder::new()
+            .submit_locked(&mut guard)
+            .unwrap();
+        guard.wait().unwrap();
+        drop(guard);
+
+        queue.wait().unwrap();
+    }
+
+    #[test]
+    fn pipeline_layout_cache() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let set_desc = DescriptorSetDesc::new([Some(DescriptorDesc {
+            ty: DescriptorDescTy::Sampler,
+            array_count: 1,
+            stages: ShaderStages::all(),
+            readonly: true,
+        })]);
+        let range = PipelineLayoutPcRange {
+            offset: 0,
+            size: 16,
+            stages: ShaderStages::all(),
+        };
+        let layout = |range: Option<PipelineLayoutPcRange>| {
+            PipelineLayout::from_descs(device.clone(), [set_desc.clone()], range).unwrap()
+        };
+
+        assert!(!Arc::ptr_eq(&layout(None), &layout(None)));
+
+        device.set_pipeline_layout_cache(true);
+        let first = layout(None);
+        assert!(Arc::ptr_eq(&first, &layout(None)));
+        assert!(!Arc::ptr_eq(&first, &layout(Some(range))));
+        assert!(Arc::ptr_eq(&layout(Some(range)), &layout(Some(range))));
+
+        device.set_pipeline_layout_cache(false);
+        assert!(!Arc::ptr_eq(&first, &layout(None)));
+    }
+
+    #[test]
+    fn sampler_cache() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let sampler = |address_mode| {
+            Sampler::new(
+                device.clone(),
+                Filter::Linear,
+                Filter::Linear,
+                MipmapMode::Nearest,
+                address_mode,
+                address_mode,
+                address_mode,
+                0.0,
+                1.0,
+                0.0,
+                1.0,
+            )
+            .unwrap()
+        };
+        let white = SamplerAddressMode::ClampToBorder(BorderColor::FloatOpaqueWhite);
+        let black = SamplerAddressMode::ClampToBorder(BorderColor::FloatOpaqueBlack);
+
+        assert!(!Arc::ptr_eq(&sampler(white), &sampler(white)));
+
+        device.set_sampler_cache(true);
+        let first = sampler(white);
+        assert!(Arc::ptr_eq(&first, &sampler(white)));
+        assert!(!Arc::ptr_eq(&first, &sampler(black)));
+        assert!(!Arc::ptr_eq(
+            &first,
+            &Sampler::simple_repeat_linear(device.clone())
+        ));
+
+        device.set_sampler_cache(false);
+        assert!(!Arc::ptr_eq(&first, &sampler(white)));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_round_trip() {
+        let features = Features {
+            geometry_shader: true,
+            sampler_anisotropy: true,
+            ..Features::none()
+        };
+        let serialized = ron::to_string(&features).unwrap();
+        assert_eq!(ron::from_str::<Features>(&serialized).unwrap(), features);
+
+        let extensions = DeviceExtensions {
+            khr_swapchain: true,
+            ..DeviceExtensions::none()
+        };
+        let serialized = ron::to_string(&extensions).unwrap();
+        assert_eq!(
+            ron::from_str::<DeviceExtensions>(&serialized).unwrap(),
+            extensions
+        );
+
+        let instance = instance!();
+        let physical = match PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return,
+        };
+        let report = physical.capability_report();
+        let serialized = ron::to_string(&report).unwrap();
+        let deserialized: CapabilityReport = ron::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.features, report.features);
+        assert_eq!(deserialized.extensions, report.extensions);
+        assert_eq!(deserialized.queue_families, report.queue_families);
+    }
 }
@@ -8,10 +8,15 @@
 // according to those terms.
 
 use crate::check_errors;
-use crate::device::{DeviceExtensions, Features, FeaturesFfi, Properties, PropertiesFfi};
+use crate::device::{
+    DeviceExtensions, Features, FeaturesFfi, Properties, PropertiesFfi, QueueGlobalPriority,
+};
 use crate::instance::{Instance, InstanceCreationError};
+use crate::query::PerformanceCounter;
 use crate::sync::PipelineStage;
 use crate::DeviceSize;
+use crate::OomError;
+use crate::Success;
 use crate::Version;
 use crate::VulkanObject;
 use std::convert::TryFrom;
@@ -32,6 +37,9 @@ pub(crate) struct PhysicalDeviceInfo {
     properties: Properties,
     memory_properties: ash::vk::PhysicalDeviceMemoryProperties,
     queue_families: Vec<ash::vk::QueueFamilyProperties>,
+    // Global priorities supported by each queue family, if `ext_global_priority_query` is
+    // supported.
+    queue_family_global_priorities: Option<Vec<Vec<QueueGlobalPriority>>>,
 }
 
 pub(crate) fn init_physical_devices(
@@ -108,6 +116,7 @@ pub(crate) fn init_physical_devices(
                 properties: Default::default(),
                 memory_properties: Default::default(),
                 queue_families: Default::default(),
+                queue_family_global_priorities: None,
             })
         })
         .collect::<Result<_, _>>()?;
@@ -253,6 +262,16 @@ fn init_physical_devices_inner2(instance: &Instance, infos: &mut [PhysicalDevice
             }
 
             let mut families = vec![ash::vk::QueueFamilyProperties2::default(); num as usize];
+            let mut global_priorities = if info.supported_extensions.ext_global_priority_query {
+                vec![ash::vk::QueueFamilyGlobalPriorityPropertiesEXT::default(); num as usize]
+            } else {
+                Vec::new()
+            };
+
+            for (family, global_priorities) in families.iter_mut().zip(global_priorities.iter_mut())
+            {
+                family.p_next = global_priorities as *mut _ as *mut _;
+            }
 
             if instance.api_version() >= Version::V1_1 {
                 fns.v1_1.get_physical_device_queue_family_properties2(
@@ -269,6 +288,22 @@ fn init_physical_devices_inner2(instance: &Instance, infos: &mut [PhysicalDevice
                     );
             }
 
+            if info.supported_extensions.ext_global_priority_query {
+                info.queue_family_global_priorities = Some(
+                    global_priorities
+                        .iter()
+                        .map(|properties| {
+                            properties.priorities[..properties.priority_count as usize]
+                                .iter()
+                                .filter_map(|&priority| {
+                                    QueueGlobalPriority::try_from(priority).ok()
+                                })
+                                .collect()
+                        })
+                        .collect(),
+                );
+            }
+
             families
                 .into_iter()
                 .map(|family| family.queue_family_properties)
@@ -364,6 +399,20 @@ impl<'a> PhysicalDevice<'a> {
             })
     }
 
+    /// Returns the physical device of `instance` whose handle is `handle`, or `None` if there
+    /// isn't any.
+    ///
+    /// This is useful when another API chooses the physical device, for example
+    /// `xrGetVulkanGraphicsDeviceKHR` in OpenXR.
+    #[inline]
+    pub fn from_handle(
+        instance: &'a Arc<Instance>,
+        handle: ash::vk::PhysicalDevice,
+    ) -> Option<PhysicalDevice<'a>> {
+        PhysicalDevice::enumerate(instance)
+            .find(|physical_device| physical_device.internal_object() == handle)
+    }
+
     /// Returns the instance corresponding to this physical device.
     ///
     /// # Example
@@ -418,6 +467,24 @@ impl<'a> PhysicalDevice<'a> {
         &self.info.properties
     }
 
+    /// Returns the locally unique identifier of the physical device, if it is known.
+    ///
+    /// On Windows, this is the LUID of the adapter that DXGI, Direct3D 12 and other APIs report,
+    /// which lets you find the same adapter in several APIs. Returns `None` if the API version
+    /// is lower than 1.1 and `khr_external_memory_capabilities` isn't enabled on the instance,
+    /// or if the implementation doesn't provide a valid LUID, which is usually the case outside
+    /// of Windows.
+    #[inline]
+    pub fn luid(&self) -> Option<Luid> {
+        let properties = self.properties();
+
+        if properties.device_luid_valid != Some(true) {
+            return None;
+        }
+
+        properties.device_luid.map(Luid::from)
+    }
+
     /// Returns the features that are supported by this physical device.
     #[inline]
     pub fn supported_features(&self) -> &'a Features {
@@ -510,6 +577,64 @@ impl<'a> PhysicalDevice<'a> {
             None
         }
     }
+
+    /// Gathers the capabilities of this physical device into a single report, similar to the
+    /// output of `vulkaninfo`.
+    ///
+    /// With the `serde` feature, the report can be serialized, for example to JSON with
+    /// `serde_json`. This is useful to collect the capabilities of the devices of users, or to
+    /// reproduce an issue that only happens on a particular device.
+    ///
+    /// ```no_run
+    /// use vulkano::device::physical::PhysicalDevice;
+    /// use vulkano::instance::Instance;
+    /// use vulkano::instance::InstanceExtensions;
+    /// use vulkano::Version;
+    ///
+    /// let instance = Instance::new(None, Version::V1_1, &InstanceExtensions::none(), None).unwrap();
+    ///
+    /// for physical_device in PhysicalDevice::enumerate(&instance) {
+    ///     println!("{:#?}", physical_device.capability_report());
+    /// }
+    /// ```
+    pub fn capability_report(&self) -> CapabilityReport {
+        CapabilityReport {
+            properties: self.properties().clone(),
+            features: self.supported_features().clone(),
+            extensions: *self.supported_extensions(),
+            queue_families: self
+                .queue_families()
+                .map(|queue_family| QueueFamilyReport {
+                    queues_count: queue_family.queues_count() as u32,
+                    graphics: queue_family.supports_graphics(),
+                    compute: queue_family.supports_compute(),
+                    transfer: queue_family.explicitly_supports_transfers(),
+                    sparse_binding: queue_family.supports_sparse_binding(),
+                    timestamp_valid_bits: queue_family.timestamp_valid_bits(),
+                    min_image_transfer_granularity: queue_family.min_image_transfer_granularity(),
+                })
+                .collect(),
+            memory_types: self
+                .memory_types()
+                .map(|memory_type| MemoryTypeReport {
+                    heap: memory_type.heap().id(),
+                    device_local: memory_type.is_device_local(),
+                    host_visible: memory_type.is_host_visible(),
+                    host_coherent: memory_type.is_host_coherent(),
+                    host_cached: memory_type.is_host_cached(),
+                    lazily_allocated: memory_type.is_lazily_allocated(),
+                })
+                .collect(),
+            memory_heaps: self
+                .memory_heaps()
+                .map(|memory_heap| MemoryHeapReport {
+                    size: memory_heap.size(),
+                    device_local: memory_heap.is_device_local(),
+                    multi_instance: memory_heap.is_multi_instance(),
+                })
+                .collect(),
+        }
+    }
 }
 
 unsafe impl<'a> VulkanObject for PhysicalDevice<'a> {
@@ -521,8 +646,101 @@ unsafe impl<'a> VulkanObject for PhysicalDevice<'a> {
     }
 }
 
+/// Group of physical devices that can be used together by a single logical device.
+///
+/// The physical devices of a group are typically several GPUs of the same model that are linked
+/// together. A device created with [`Device::with_device_group`](crate::device::Device::with_device_group)
+/// spans all the physical devices of a group.
+#[derive(Clone, Debug)]
+pub struct PhysicalDeviceGroup<'a> {
+    physical_devices: Vec<PhysicalDevice<'a>>,
+    subset_allocation: bool,
+}
+
+impl<'a> PhysicalDeviceGroup<'a> {
+    /// Returns the groups of physical devices available.
+    ///
+    /// Each physical device belongs to exactly one group. If the instance doesn't support
+    /// Vulkan 1.1 and doesn't have the `khr_device_group_creation` extension enabled, each
+    /// physical device is returned in its own group.
+    pub fn enumerate(
+        instance: &'a Arc<Instance>,
+    ) -> Result<Vec<PhysicalDeviceGroup<'a>>, OomError> {
+        if !(instance.api_version() >= Version::V1_1
+            || instance.enabled_extensions().khr_device_group_creation)
+        {
+            return Ok(PhysicalDevice::enumerate(instance)
+                .map(|physical_device| PhysicalDeviceGroup {
+                    physical_devices: vec![physical_device],
+                    subset_allocation: false,
+                })
+                .collect());
+        }
+
+        let properties = unsafe {
+            let fns = instance.fns();
+            let enumerate = if instance.api_version() >= Version::V1_1 {
+                fns.v1_1.enumerate_physical_device_groups
+            } else {
+                fns.khr_device_group_creation
+                    .enumerate_physical_device_groups_khr
+            };
+
+            let mut num = 0;
+            check_errors(enumerate(
+                instance.internal_object(),
+                &mut num,
+                ptr::null_mut(),
+            ))?;
+
+            let mut properties =
+                vec![ash::vk::PhysicalDeviceGroupProperties::default(); num as usize];
+            check_errors(enumerate(
+                instance.internal_object(),
+                &mut num,
+                properties.as_mut_ptr(),
+            ))?;
+            properties.truncate(num as usize);
+            properties
+        };
+
+        Ok(properties
+            .iter()
+            .map(|properties| PhysicalDeviceGroup {
+                physical_devices: properties.physical_devices
+                    [..properties.physical_device_count as usize]
+                    .iter()
+                    .map(|&handle| {
+                        PhysicalDevice::enumerate(instance)
+                            .find(|physical_device| physical_device.internal_object() == handle)
+                            .unwrap()
+                    })
+                    .collect(),
+                subset_allocation: properties.subset_allocation != ash::vk::FALSE,
+            })
+            .collect())
+    }
+
+    /// Returns the physical devices of the group.
+    ///
+    /// The index of a physical device in this list is its *device index*, which is used in the
+    /// device masks of a logical device created from the group.
+    #[inline]
+    pub fn physical_devices(&self) -> &[PhysicalDevice<'a>] {
+        &self.physical_devices
+    }
+
+    /// Returns true if memory can be allocated on a subset of the physical devices of the group.
+    /// Otherwise, every allocation is replicated on all the physical devices.
+    #[inline]
+    pub fn subset_allocation(&self) -> bool {
+        self.subset_allocation
+    }
+}
+
 /// Type of a physical device.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(i32)]
 pub enum PhysicalDeviceType {
     /// The device is an integrated GPU.
@@ -754,6 +972,130 @@ impl<'a> QueueFamily<'a> {
         !(self.properties.queue_flags & ash::vk::QueueFlags::SPARSE_BINDING).is_empty()
     }
 
+    /// Returns `true` if queues of this family can execute video decode operations.
+    ///
+    /// Requires the provisional `khr_video_decode_queue` extension to be supported.
+    #[inline]
+    pub fn supports_video_decode(&self) -> bool {
+        !(self.properties.queue_flags & ash::vk::QueueFlags::VIDEO_DECODE_KHR).is_empty()
+    }
+
+    /// Returns `true` if queues of this family can execute video encode operations.
+    ///
+    /// Requires the provisional `khr_video_encode_queue` extension to be supported.
+    #[inline]
+    pub fn supports_video_encode(&self) -> bool {
+        !(self.properties.queue_flags & ash::vk::QueueFlags::VIDEO_ENCODE_KHR).is_empty()
+    }
+
+    /// Returns the global priorities that can be requested for the queues of this family.
+    ///
+    /// Returns `None` if the physical device doesn't support the `ext_global_priority_query`
+    /// extension, in which case the supported priorities can't be known in advance.
+    #[inline]
+    pub fn supported_global_priorities(&self) -> Option<&'a [QueueGlobalPriority]> {
+        self.physical_device
+            .info
+            .queue_family_global_priorities
+            .as_ref()
+            .map(|priorities| priorities[self.id as usize].as_slice())
+    }
+
+    /// Returns the performance counters that the queries of this family can read.
+    ///
+    /// The indices of the counters in the returned list are the ones to pass to
+    /// [`QueryPool::performance`](crate::query::QueryPool::performance).
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the physical device doesn't support the `khr_performance_query` extension.
+    ///
+    pub fn performance_counters(&self) -> Result<Vec<PerformanceCounter>, OomError> {
+        assert!(
+            self.physical_device
+                .supported_extensions()
+                .khr_performance_query
+        );
+
+        let fns = self.physical_device.instance.fns();
+
+        let (counters, descriptions) = unsafe {
+            loop {
+                let mut num = 0;
+                check_errors(
+                    fns.khr_performance_query
+                        .enumerate_physical_device_queue_family_performance_query_counters_khr(
+                            self.physical_device.internal_object(),
+                            self.id,
+                            &mut num,
+                            ptr::null_mut(),
+                            ptr::null_mut(),
+                        ),
+                )?;
+
+                let mut counters = vec![ash::vk::PerformanceCounterKHR::default(); num as usize];
+                let mut descriptions =
+                    vec![ash::vk::PerformanceCounterDescriptionKHR::default(); num as usize];
+                let result = check_errors(
+                    fns.khr_performance_query
+                        .enumerate_physical_device_queue_family_performance_query_counters_khr(
+                            self.physical_device.internal_object(),
+                            self.id,
+                            &mut num,
+                            counters.as_mut_ptr(),
+                            descriptions.as_mut_ptr(),
+                        ),
+                )?;
+
+                if !matches!(result, Success::Incomplete) {
+                    counters.truncate(num as usize);
+                    descriptions.truncate(num as usize);
+                    break (counters, descriptions);
+                }
+            }
+        };
+
+        Ok(counters
+            .iter()
+            .zip(descriptions.iter())
+            .map(|(counter, description)| PerformanceCounter::from_raw(counter, description))
+            .collect())
+    }
+
+    /// Returns the number of times that the command buffers of this family must be submitted to
+    /// read all the counters of `counter_indices`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the physical device doesn't support the `khr_performance_query` extension.
+    ///
+    pub fn performance_query_passes(&self, counter_indices: &[u32]) -> u32 {
+        assert!(
+            self.physical_device
+                .supported_extensions()
+                .khr_performance_query
+        );
+
+        let info = ash::vk::QueryPoolPerformanceCreateInfoKHR {
+            queue_family_index: self.id,
+            counter_index_count: counter_indices.len() as u32,
+            p_counter_indices: counter_indices.as_ptr(),
+            ..Default::default()
+        };
+
+        unsafe {
+            let fns = self.physical_device.instance.fns();
+            let mut num_passes = 0;
+            fns.khr_performance_query
+                .get_physical_device_queue_family_performance_query_passes_khr(
+                    self.physical_device.internal_object(),
+                    &info,
+                    &mut num_passes,
+                );
+            num_passes
+        }
+    }
+
     /// Returns `true` if the queues of this family support a particular pipeline stage.
     #[inline]
     pub fn supports_stage(&self, stage: PipelineStage) -> bool {
@@ -770,8 +1112,79 @@ impl<'a> PartialEq for QueueFamily<'a> {
 
 impl<'a> Eq for QueueFamily<'a> {}
 
+/// Capabilities of a physical device, returned by `PhysicalDevice::capability_report`.
+///
+/// The queue families, memory types and memory heaps are listed in the order of their id.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CapabilityReport {
+    /// The properties of the physical device, including its limits.
+    pub properties: Properties,
+    /// The features supported by the physical device.
+    pub features: Features,
+    /// The extensions supported by the physical device.
+    pub extensions: DeviceExtensions,
+    /// The queue families of the physical device.
+    pub queue_families: Vec<QueueFamilyReport>,
+    /// The memory types of the physical device.
+    pub memory_types: Vec<MemoryTypeReport>,
+    /// The memory heaps of the physical device.
+    pub memory_heaps: Vec<MemoryHeapReport>,
+}
+
+/// Capabilities of a queue family, as part of a `CapabilityReport`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QueueFamilyReport {
+    /// See `QueueFamily::queues_count`.
+    pub queues_count: u32,
+    /// See `QueueFamily::supports_graphics`.
+    pub graphics: bool,
+    /// See `QueueFamily::supports_compute`.
+    pub compute: bool,
+    /// See `QueueFamily::explicitly_supports_transfers`.
+    pub transfer: bool,
+    /// See `QueueFamily::supports_sparse_binding`.
+    pub sparse_binding: bool,
+    /// See `QueueFamily::timestamp_valid_bits`.
+    pub timestamp_valid_bits: Option<u32>,
+    /// See `QueueFamily::min_image_transfer_granularity`.
+    pub min_image_transfer_granularity: [u32; 3],
+}
+
+/// Capabilities of a memory type, as part of a `CapabilityReport`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryTypeReport {
+    /// The id of the heap that the memory type belongs to.
+    pub heap: u32,
+    /// See `MemoryType::is_device_local`.
+    pub device_local: bool,
+    /// See `MemoryType::is_host_visible`.
+    pub host_visible: bool,
+    /// See `MemoryType::is_host_coherent`.
+    pub host_coherent: bool,
+    /// See `MemoryType::is_host_cached`.
+    pub host_cached: bool,
+    /// See `MemoryType::is_lazily_allocated`.
+    pub lazily_allocated: bool,
+}
+
+/// Capabilities of a memory heap, as part of a `CapabilityReport`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryHeapReport {
+    /// See `MemoryHeap::size`.
+    pub size: DeviceSize,
+    /// See `MemoryHeap::is_device_local`.
+    pub device_local: bool,
+    /// See `MemoryHeap::is_multi_instance`.
+    pub multi_instance: bool,
+}
+
 /// The version of the Vulkan conformance test that a driver is conformant against.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConformanceVersion {
     pub major: u8,
     pub minor: u8,
@@ -793,7 +1206,11 @@ impl From<ash::vk::ConformanceVersion> for ConformanceVersion {
 
 impl fmt::Debug for ConformanceVersion {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter, "{}.{}.{}", self.major, self.minor, self.patch)
+        write!(
+            formatter,
+            "{}.{}.{}.{}",
+            self.major, self.minor, self.subminor, self.patch
+        )
     }
 }
 
@@ -803,8 +1220,48 @@ impl fmt::Display for ConformanceVersion {
     }
 }
 
+/// A locally unique identifier of an adapter, with the layout of the `LUID` structure of
+/// Windows.
+///
+/// Converting it to `u64` gives the value that the APIs that store the LUID in a 64-bits integer
+/// use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Luid {
+    pub low_part: u32,
+    pub high_part: i32,
+}
+
+impl From<[u8; 8]> for Luid {
+    #[inline]
+    fn from(val: [u8; 8]) -> Self {
+        Luid {
+            low_part: u32::from_ne_bytes([val[0], val[1], val[2], val[3]]),
+            high_part: i32::from_ne_bytes([val[4], val[5], val[6], val[7]]),
+        }
+    }
+}
+
+impl From<Luid> for [u8; 8] {
+    #[inline]
+    fn from(val: Luid) -> Self {
+        let low = val.low_part.to_ne_bytes();
+        let high = val.high_part.to_ne_bytes();
+        [
+            low[0], low[1], low[2], low[3], high[0], high[1], high[2], high[3],
+        ]
+    }
+}
+
+impl From<Luid> for u64 {
+    #[inline]
+    fn from(val: Luid) -> Self {
+        ((val.high_part as u32 as u64) << 32) | val.low_part as u64
+    }
+}
+
 /// An identifier for the driver of a physical device.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(i32)]
 pub enum DriverId {
     AMDProprietary = ash::vk::DriverId::AMD_PROPRIETARY.as_raw(),
@@ -821,6 +1278,9 @@ pub enum DriverId {
     BroadcomProprietary = ash::vk::DriverId::BROADCOM_PROPRIETARY.as_raw(),
     MesaLLVMpipe = ash::vk::DriverId::MESA_LLVMPIPE.as_raw(),
     MoltenVK = ash::vk::DriverId::MOLTENVK.as_raw(),
+    CoreAVIProprietary = ash::vk::DriverId::COREAVI_PROPRIETARY.as_raw(),
+    JuiceProprietary = ash::vk::DriverId::JUICE_PROPRIETARY.as_raw(),
+    VeriSiliconProprietary = ash::vk::DriverId::VERISILICON_PROPRIETARY.as_raw(),
 }
 
 impl TryFrom<ash::vk::DriverId> for DriverId {
@@ -843,21 +1303,36 @@ impl TryFrom<ash::vk::DriverId> for DriverId {
             ash::vk::DriverId::BROADCOM_PROPRIETARY => Ok(Self::BroadcomProprietary),
             ash::vk::DriverId::MESA_LLVMPIPE => Ok(Self::MesaLLVMpipe),
             ash::vk::DriverId::MOLTENVK => Ok(Self::MoltenVK),
+            ash::vk::DriverId::COREAVI_PROPRIETARY => Ok(Self::CoreAVIProprietary),
+            ash::vk::DriverId::JUICE_PROPRIETARY => Ok(Self::JuiceProprietary),
+            ash::vk::DriverId::VERISILICON_PROPRIETARY => Ok(Self::VeriSiliconProprietary),
             _ => Err(()),
         }
     }
 }
 
 /// Specifies which subgroup operations are supported.
-#[derive(Clone, Copy, Debug)]
+///
+/// This is the type of the `subgroup_supported_operations` property, which applies to the
+/// stages of the `subgroup_supported_stages` property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SubgroupFeatures {
+    /// `subgroupElect`, `subgroupBarrier` and the other basic operations.
     pub basic: bool,
+    /// `subgroupAll`, `subgroupAny` and `subgroupAllEqual`.
     pub vote: bool,
+    /// Reductions and scans such as `subgroupAdd` and `subgroupInclusiveMul`.
     pub arithmetic: bool,
+    /// `subgroupBroadcast`, `subgroupBallot` and the other ballot operations.
     pub ballot: bool,
+    /// `subgroupShuffle` and `subgroupShuffleXor`.
     pub shuffle: bool,
+    /// `subgroupShuffleUp` and `subgroupShuffleDown`.
     pub shuffle_relative: bool,
+    /// Clustered reductions such as `subgroupClusteredAdd`.
     pub clustered: bool,
+    /// `subgroupQuadBroadcast` and the other quad operations.
     pub quad: bool,
 }
 
@@ -879,6 +1354,7 @@ impl From<ash::vk::SubgroupFeatureFlags> for SubgroupFeatures {
 
 /// Specifies how the device clips single point primitives.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(i32)]
 pub enum PointClippingBehavior {
     /// Points are clipped if they lie outside any clip plane, both those bounding the view volume
@@ -903,6 +1379,7 @@ impl TryFrom<ash::vk::PointClippingBehavior> for PointClippingBehavior {
 
 /// Specifies whether, and how, shader float controls can be set independently.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(i32)]
 pub enum ShaderFloatControlsIndependence {
     Float32Only = ash::vk::ShaderFloatControlsIndependence::TYPE_32_ONLY.as_raw(),
@@ -926,6 +1403,7 @@ impl TryFrom<ash::vk::ShaderFloatControlsIndependence> for ShaderFloatControlsIn
 
 /// Specifies shader core properties.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ShaderCoreProperties {}
 
 impl From<ash::vk::ShaderCorePropertiesFlagsAMD> for ShaderCoreProperties {
@@ -0,0 +1,331 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::device::physical::PhysicalDevice;
+use crate::device::physical::QueueFamily;
+use crate::device::selector::PresentSupportFn;
+use crate::device::Device;
+use crate::device::DeviceCreationError;
+use crate::device::DeviceExtensions;
+use crate::device::Features;
+use crate::device::Queue;
+use crate::swapchain::Surface;
+use smallvec::SmallVec;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+/// Builder that chooses the queues to create on a device depending on what the physical device
+/// supports, and creates the device.
+///
+/// A graphics queue is always created. The other queues are optional and fall back as follows:
+///
+/// - With `surface`, a queue that can present to the surface is required. The graphics queue is
+///   used if its family can present, otherwise a queue is created from another family.
+/// - With `dedicated_transfer`, a queue is created from a family that only supports transfer
+///   operations, which usually corresponds to the DMA engines of discrete GPUs. If there is no
+///   such family, the graphics queue is used.
+/// - With `async_compute`, a queue is created from a family that supports compute but not
+///   graphics operations, which can run at the same time as the graphics work. If there is no
+///   such family, the graphics queue is used.
+///
+/// If a family doesn't have enough queues left, the queue that was already created from this
+/// family is shared. The graphics and present queues have a priority of 1.0, and the transfer
+/// and compute queues have a priority of 0.5.
+///
+/// # Example
+///
+/// ```no_run
+/// use vulkano::device::DeviceExtensions;
+/// use vulkano::device::Features;
+/// use vulkano::device::QueueRequest;
+/// use vulkano::device::physical::PhysicalDevice;
+/// use vulkano::instance::Instance;
+/// use vulkano::instance::InstanceExtensions;
+/// use vulkano::Version;
+///
+/// let instance = Instance::new(None, Version::V1_1, &InstanceExtensions::none(), None).unwrap();
+/// let physical_device = PhysicalDevice::enumerate(&instance).next().unwrap();
+///
+/// let (device, queues) = QueueRequest::new()
+///     .dedicated_transfer()
+///     .async_compute()
+///     .create_device(physical_device, &Features::none(), &DeviceExtensions::none())
+///     .unwrap();
+///
+/// println!("Compute queue family: {}", queues.compute.family().id());
+/// ```
+pub struct QueueRequest<'s> {
+    present: Option<Box<PresentSupportFn<'s>>>,
+    dedicated_transfer: bool,
+    async_compute: bool,
+}
+
+type QueueCreateList<'a> = SmallVec<[(QueueFamily<'a>, f32); 4]>;
+
+impl<'s> QueueRequest<'s> {
+    /// Builds a request for a graphics queue only.
+    #[inline]
+    pub fn new() -> QueueRequest<'s> {
+        QueueRequest {
+            present: None,
+            dedicated_transfer: false,
+            async_compute: false,
+        }
+    }
+
+    /// Requires a queue that can present to `surface`.
+    ///
+    /// Errors that happen while querying the support are treated as no support.
+    #[inline]
+    pub fn surface<W>(mut self, surface: &'s Surface<W>) -> Self {
+        self.present = Some(Box::new(move |queue_family| {
+            surface.is_supported(queue_family).unwrap_or(false)
+        }));
+        self
+    }
+
+    /// Requests a queue from a family dedicated to transfer operations, if there is one.
+    #[inline]
+    pub fn dedicated_transfer(mut self) -> Self {
+        self.dedicated_transfer = true;
+        self
+    }
+
+    /// Requests a queue from a family that supports compute but not graphics operations, if
+    /// there is one.
+    #[inline]
+    pub fn async_compute(mut self) -> Self {
+        self.async_compute = true;
+        self
+    }
+
+    /// Returns the queue family and the priority of each queue to create on `physical_device`,
+    /// in the order of the fields of `RequestedQueues`, and the index in this list of the queue
+    /// used for each field.
+    fn resolve<'a>(
+        &self,
+        physical_device: PhysicalDevice<'a>,
+    ) -> Result<(QueueCreateList<'a>, [usize; 4]), QueueRequestError> {
+        let supports_present = |queue_family: QueueFamily| match self.present {
+            Some(ref present) => present(queue_family),
+            None => false,
+        };
+
+        let mut queues: QueueCreateList<'a> = SmallVec::new();
+        let mut add_queue = |queue_family: QueueFamily<'a>, priority: f32| {
+            let existing = queues
+                .iter()
+                .filter(|(q, _)| q.id() == queue_family.id())
+                .count();
+
+            if existing < queue_family.queues_count() {
+                queues.push((queue_family, priority));
+                queues.len() - 1
+            } else {
+                queues
+                    .iter()
+                    .position(|(q, _)| q.id() == queue_family.id())
+                    .unwrap()
+            }
+        };
+
+        let graphics_queue_family = physical_device
+            .queue_families()
+            .find(|&q| q.supports_graphics() && supports_present(q))
+            .or_else(|| {
+                physical_device
+                    .queue_families()
+                    .find(|q| q.supports_graphics())
+            })
+            .ok_or(QueueRequestError::NoGraphicsQueueFamily)?;
+        let graphics = add_queue(graphics_queue_family, 1.0);
+
+        let present = if self.present.is_none() || supports_present(graphics_queue_family) {
+            graphics
+        } else {
+            let queue_family = physical_device
+                .queue_families()
+                .find(|&q| supports_present(q))
+                .ok_or(QueueRequestError::NoPresentQueueFamily)?;
+            add_queue(queue_family, 1.0)
+        };
+
+        let transfer = match physical_device.queue_families().find(|q| {
+            q.explicitly_supports_transfers() && !q.supports_graphics() && !q.supports_compute()
+        }) {
+            Some(queue_family) if self.dedicated_transfer => add_queue(queue_family, 0.5),
+            _ => graphics,
+        };
+
+        let compute = match physical_device
+            .queue_families()
+            .find(|q| q.supports_compute() && !q.supports_graphics())
+        {
+            Some(queue_family) if self.async_compute => add_queue(queue_family, 0.5),
+            _ => graphics,
+        };
+
+        Ok((queues, [graphics, present, transfer, compute]))
+    }
+
+    /// Creates a device from `physical_device` with the requested queues.
+    ///
+    /// The features and extensions are passed to `Device::new`.
+    pub fn create_device(
+        &self,
+        physical_device: PhysicalDevice,
+        requested_features: &Features,
+        requested_extensions: &DeviceExtensions,
+    ) -> Result<(Arc<Device>, RequestedQueues), QueueRequestError> {
+        let (queue_families, [graphics, present, transfer, compute]) =
+            self.resolve(physical_device)?;
+        let (device, queues) = Device::new(
+            physical_device,
+            requested_features,
+            requested_extensions,
+            queue_families,
+        )?;
+        let queues: SmallVec<[Arc<Queue>; 4]> = queues.collect();
+
+        let queues = RequestedQueues {
+            graphics: queues[graphics].clone(),
+            present: if self.present.is_some() {
+                Some(queues[present].clone())
+            } else {
+                None
+            },
+            transfer: queues[transfer].clone(),
+            compute: queues[compute].clone(),
+        };
+
+        Ok((device, queues))
+    }
+}
+
+impl<'s> Default for QueueRequest<'s> {
+    #[inline]
+    fn default() -> Self {
+        QueueRequest::new()
+    }
+}
+
+impl<'s> fmt::Debug for QueueRequest<'s> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("QueueRequest")
+            .field("present", &self.present.is_some())
+            .field("dedicated_transfer", &self.dedicated_transfer)
+            .field("async_compute", &self.async_compute)
+            .finish()
+    }
+}
+
+/// Queues created by `QueueRequest::create_device`.
+///
+/// Several fields contain the same queue when the physical device doesn't have separate queues
+/// for them. Use `Arc::ptr_eq` to find out if two of them are the same queue.
+#[derive(Debug, Clone)]
+pub struct RequestedQueues {
+    /// Queue that supports graphics operations.
+    pub graphics: Arc<Queue>,
+    /// Queue that can present to the surface, if one was given.
+    pub present: Option<Arc<Queue>>,
+    /// Queue to use for transfer operations.
+    pub transfer: Arc<Queue>,
+    /// Queue to use for compute operations.
+    pub compute: Arc<Queue>,
+}
+
+/// Error that can be returned by `QueueRequest::create_device`.
+#[derive(Copy, Clone, Debug)]
+pub enum QueueRequestError {
+    /// The physical device doesn't have any queue family that supports graphics operations.
+    NoGraphicsQueueFamily,
+    /// The physical device doesn't have any queue family that can present to the surface.
+    NoPresentQueueFamily,
+    /// Error while creating the device.
+    DeviceCreationError(DeviceCreationError),
+}
+
+impl error::Error for QueueRequestError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            QueueRequestError::DeviceCreationError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for QueueRequestError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                QueueRequestError::NoGraphicsQueueFamily => {
+                    "the physical device doesn't have any queue family that supports graphics operations"
+                }
+                QueueRequestError::NoPresentQueueFamily => {
+                    "the physical device doesn't have any queue family that can present to the surface"
+                }
+                QueueRequestError::DeviceCreationError(_) => "error while creating the device",
+            }
+        )
+    }
+}
+
+impl From<DeviceCreationError> for QueueRequestError {
+    #[inline]
+    fn from(err: DeviceCreationError) -> QueueRequestError {
+        QueueRequestError::DeviceCreationError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::device::physical::PhysicalDevice;
+    use crate::device::DeviceExtensions;
+    use crate::device::Features;
+    use crate::device::QueueRequest;
+    use std::sync::Arc;
+
+    #[test]
+    fn fallbacks() {
+        let instance = instance!();
+
+        let physical_device = match PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return,
+        };
+
+        let (_device, queues) = QueueRequest::new()
+            .dedicated_transfer()
+            .async_compute()
+            .create_device(
+                physical_device,
+                &Features::none(),
+                &DeviceExtensions::none(),
+            )
+            .unwrap();
+
+        assert!(queues.graphics.family().supports_graphics());
+        assert!(queues.present.is_none());
+        assert!(queues.compute.family().supports_compute());
+
+        if !Arc::ptr_eq(&queues.compute, &queues.graphics) {
+            assert!(!queues.compute.family().supports_graphics());
+        }
+        if !Arc::ptr_eq(&queues.transfer, &queues.graphics) {
+            assert!(!queues.transfer.family().supports_graphics());
+            assert!(!queues.transfer.family().supports_compute());
+        }
+    }
+}
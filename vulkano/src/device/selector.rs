@@ -0,0 +1,327 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::device::physical::PhysicalDevice;
+use crate::device::physical::PhysicalDeviceType;
+use crate::device::physical::QueueFamily;
+use crate::device::DeviceExtensions;
+use crate::device::Features;
+use crate::instance::Instance;
+use crate::swapchain::Surface;
+use crate::DeviceSize;
+use std::fmt;
+use std::sync::Arc;
+
+/// Builder that picks the most suitable physical device for an application, and the queue
+/// families to create the queues from.
+///
+/// The physical devices that don't meet all the requirements are rejected. The remaining ones
+/// are ordered by the position of their type in the list passed to `device_types`, then by the
+/// value returned by the `score` closure, then by the amount of device-local memory.
+///
+/// # Example
+///
+/// ```no_run
+/// use vulkano::device::DeviceExtensions;
+/// use vulkano::device::PhysicalDeviceSelector;
+/// use vulkano::instance::Instance;
+/// use vulkano::instance::InstanceExtensions;
+/// use vulkano::Version;
+///
+/// let instance = Instance::new(None, Version::V1_1, &InstanceExtensions::none(), None).unwrap();
+///
+/// let selected = PhysicalDeviceSelector::new()
+///     .graphics()
+///     .extensions(DeviceExtensions {
+///         khr_storage_buffer_storage_class: true,
+///         ..DeviceExtensions::none()
+///     })
+///     .min_device_local_memory(256 * 1024 * 1024)
+///     .select(&instance)
+///     .expect("no suitable physical device");
+///
+/// println!("Using {}", selected.physical_device.properties().device_name);
+/// ```
+pub struct PhysicalDeviceSelector<'s> {
+    graphics: bool,
+    compute: bool,
+    present: Option<Box<PresentSupportFn<'s>>>,
+    extensions: DeviceExtensions,
+    features: Features,
+    device_types: Vec<PhysicalDeviceType>,
+    min_device_local_memory: DeviceSize,
+    score: Option<Box<ScoreFn<'s>>>,
+}
+
+pub(crate) type PresentSupportFn<'s> = dyn Fn(QueueFamily) -> bool + 's;
+type ScoreFn<'s> = dyn Fn(PhysicalDevice) -> Option<u32> + 's;
+
+impl<'s> PhysicalDeviceSelector<'s> {
+    /// Builds a selector without any requirement.
+    ///
+    /// All the types of devices are accepted, and discrete GPUs are preferred over integrated
+    /// GPUs, then virtual GPUs, CPUs and other devices.
+    #[inline]
+    pub fn new() -> PhysicalDeviceSelector<'s> {
+        PhysicalDeviceSelector {
+            graphics: false,
+            compute: false,
+            present: None,
+            extensions: DeviceExtensions::none(),
+            features: Features::none(),
+            device_types: vec![
+                PhysicalDeviceType::DiscreteGpu,
+                PhysicalDeviceType::IntegratedGpu,
+                PhysicalDeviceType::VirtualGpu,
+                PhysicalDeviceType::Cpu,
+                PhysicalDeviceType::Other,
+            ],
+            min_device_local_memory: 0,
+            score: None,
+        }
+    }
+
+    /// Requires a queue family that supports graphics operations.
+    #[inline]
+    pub fn graphics(mut self) -> Self {
+        self.graphics = true;
+        self
+    }
+
+    /// Requires a queue family that supports compute operations.
+    #[inline]
+    pub fn compute(mut self) -> Self {
+        self.compute = true;
+        self
+    }
+
+    /// Requires a queue family that can present to `surface`.
+    ///
+    /// Errors that happen while querying the support are treated as no support.
+    #[inline]
+    pub fn surface<W>(mut self, surface: &'s Surface<W>) -> Self {
+        self.present = Some(Box::new(move |queue_family| {
+            surface.is_supported(queue_family).unwrap_or(false)
+        }));
+        self
+    }
+
+    /// Requires the physical device to support these extensions.
+    #[inline]
+    pub fn extensions(mut self, extensions: DeviceExtensions) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Requires the physical device to support these features.
+    #[inline]
+    pub fn features(mut self, features: Features) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Sets the types of physical devices that are accepted, from the most to the least
+    /// preferred.
+    #[inline]
+    pub fn device_types(mut self, device_types: &[PhysicalDeviceType]) -> Self {
+        self.device_types = device_types.to_vec();
+        self
+    }
+
+    /// Requires the physical device to have at least `size` bytes of device-local memory, summed
+    /// over all its device-local heaps.
+    #[inline]
+    pub fn min_device_local_memory(mut self, size: DeviceSize) -> Self {
+        self.min_device_local_memory = size;
+        self
+    }
+
+    /// Sets a closure that scores the physical devices that meet the other requirements. A
+    /// higher score is preferred, and returning `None` rejects the device.
+    #[inline]
+    pub fn score<F>(mut self, score: F) -> Self
+    where
+        F: Fn(PhysicalDevice) -> Option<u32> + 's,
+    {
+        self.score = Some(Box::new(score));
+        self
+    }
+
+    /// Returns the most suitable physical device of `instance`, or `None` if no physical device
+    /// meets the requirements.
+    pub fn select<'a>(&self, instance: &'a Arc<Instance>) -> Option<SelectedPhysicalDevice<'a>> {
+        PhysicalDevice::enumerate(instance)
+            .filter_map(|physical_device| {
+                let selected = self.check(physical_device)?;
+                let type_rank = self
+                    .device_types
+                    .iter()
+                    .position(|&ty| ty == physical_device.properties().device_type)?;
+                let score = match self.score {
+                    Some(ref score) => score(physical_device)?,
+                    None => 0,
+                };
+
+                Some((
+                    (
+                        std::cmp::Reverse(type_rank),
+                        score,
+                        device_local_memory(physical_device),
+                    ),
+                    selected,
+                ))
+            })
+            .max_by_key(|&(key, _)| key)
+            .map(|(_, selected)| selected)
+    }
+
+    // Checks the requirements that don't depend on the other physical devices, and chooses the
+    // queue families.
+    fn check<'a>(&self, physical_device: PhysicalDevice<'a>) -> Option<SelectedPhysicalDevice<'a>> {
+        if !physical_device
+            .supported_extensions()
+            .is_superset_of(&self.extensions)
+            || !physical_device
+                .supported_features()
+                .is_superset_of(&self.features)
+            || device_local_memory(physical_device) < self.min_device_local_memory
+        {
+            return None;
+        }
+
+        let supports_present = |queue_family: QueueFamily| match self.present {
+            Some(ref present) => present(queue_family),
+            None => false,
+        };
+
+        // Prefer a graphics family that can also present, to avoid sharing the swapchain images
+        // between queue families.
+        let graphics_queue_family = if self.graphics {
+            Some(
+                physical_device
+                    .queue_families()
+                    .find(|&q| q.supports_graphics() && supports_present(q))
+                    .or_else(|| {
+                        physical_device
+                            .queue_families()
+                            .find(|q| q.supports_graphics())
+                    })?,
+            )
+        } else {
+            None
+        };
+
+        let present_queue_family = if self.present.is_some() {
+            match graphics_queue_family {
+                Some(q) if supports_present(q) => Some(q),
+                _ => Some(
+                    physical_device
+                        .queue_families()
+                        .find(|&q| supports_present(q))?,
+                ),
+            }
+        } else {
+            None
+        };
+
+        // Prefer a family that doesn't support graphics, since it usually executes the compute
+        // operations asynchronously with the graphics ones.
+        let compute_queue_family = if self.compute {
+            Some(
+                physical_device
+                    .queue_families()
+                    .find(|q| q.supports_compute() && !q.supports_graphics())
+                    .or_else(|| {
+                        physical_device
+                            .queue_families()
+                            .find(|q| q.supports_compute())
+                    })?,
+            )
+        } else {
+            None
+        };
+
+        Some(SelectedPhysicalDevice {
+            physical_device,
+            graphics_queue_family,
+            compute_queue_family,
+            present_queue_family,
+        })
+    }
+}
+
+impl<'s> Default for PhysicalDeviceSelector<'s> {
+    #[inline]
+    fn default() -> Self {
+        PhysicalDeviceSelector::new()
+    }
+}
+
+impl<'s> fmt::Debug for PhysicalDeviceSelector<'s> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("PhysicalDeviceSelector")
+            .field("graphics", &self.graphics)
+            .field("compute", &self.compute)
+            .field("present", &self.present.is_some())
+            .field("extensions", &self.extensions)
+            .field("features", &self.features)
+            .field("device_types", &self.device_types)
+            .field("min_device_local_memory", &self.min_device_local_memory)
+            .field("score", &self.score.is_some())
+            .finish()
+    }
+}
+
+/// Physical device chosen by a `PhysicalDeviceSelector`, with the queue families that meet its
+/// requirements.
+#[derive(Debug, Copy, Clone)]
+pub struct SelectedPhysicalDevice<'a> {
+    /// The chosen physical device.
+    pub physical_device: PhysicalDevice<'a>,
+    /// A queue family that supports graphics operations, if they were required.
+    pub graphics_queue_family: Option<QueueFamily<'a>>,
+    /// A queue family that supports compute operations, if they were required. A family that
+    /// doesn't support graphics operations is preferred.
+    pub compute_queue_family: Option<QueueFamily<'a>>,
+    /// A queue family that can present to the surface, if one was given. The graphics queue
+    /// family is preferred.
+    pub present_queue_family: Option<QueueFamily<'a>>,
+}
+
+fn device_local_memory(physical_device: PhysicalDevice) -> DeviceSize {
+    physical_device
+        .memory_heaps()
+        .filter(|heap| heap.is_device_local())
+        .map(|heap| heap.size())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::device::PhysicalDeviceSelector;
+    use crate::DeviceSize;
+
+    #[test]
+    fn unsatisfiable_requirements() {
+        let instance = instance!();
+
+        assert!(PhysicalDeviceSelector::new()
+            .device_types(&[])
+            .select(&instance)
+            .is_none());
+        assert!(PhysicalDeviceSelector::new()
+            .min_device_local_memory(DeviceSize::MAX)
+            .select(&instance)
+            .is_none());
+        assert!(PhysicalDeviceSelector::new()
+            .score(|_| None)
+            .select(&instance)
+            .is_none());
+    }
+}
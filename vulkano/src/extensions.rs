@@ -147,3 +147,11 @@ impl fmt::Display for ExtensionRestriction {
 #[doc(hidden)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Unbuildable(pub(crate) ());
+
+#[cfg(feature = "serde")]
+impl Unbuildable {
+    #[inline]
+    pub(crate) const fn new() -> Unbuildable {
+        Unbuildable(())
+    }
+}
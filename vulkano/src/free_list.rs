@@ -0,0 +1,118 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::ops::Range;
+
+/// List of the free ranges of a one-dimensional space, such as the queries of a query pool or the
+/// texels of a row of an atlas.
+///
+/// The ranges are sorted and never adjacent, so a range that is freed is merged with the free
+/// ranges around it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct FreeList {
+    ranges: Vec<Range<u32>>,
+}
+
+impl FreeList {
+    /// Builds a new list where `range` is free, or nothing if it's empty.
+    #[inline]
+    pub fn new(range: Range<u32>) -> FreeList {
+        let mut ranges = Vec::new();
+        if !range.is_empty() {
+            ranges.push(range);
+        }
+
+        FreeList { ranges }
+    }
+
+    /// Returns the free ranges, sorted.
+    #[inline]
+    pub fn ranges(&self) -> &[Range<u32>] {
+        &self.ranges
+    }
+
+    /// Returns true if one of the free ranges has at least `len` elements.
+    #[inline]
+    pub fn can_take(&self, len: u32) -> bool {
+        self.ranges
+            .iter()
+            .any(|range| range.end - range.start >= len)
+    }
+
+    /// Takes `len` elements from the start of the first free range that is large enough.
+    pub fn take(&mut self, len: u32) -> Option<Range<u32>> {
+        let position = self
+            .ranges
+            .iter()
+            .position(|range| range.end - range.start >= len)?;
+
+        let start = self.ranges[position].start;
+        self.ranges[position].start += len;
+        if self.ranges[position].is_empty() {
+            self.ranges.remove(position);
+        }
+
+        Some(start..start + len)
+    }
+
+    /// Gives `range` back, merging it with the free ranges around it.
+    ///
+    /// `range` must not overlap a free range.
+    pub fn free(&mut self, range: Range<u32>) {
+        let ranges = &mut self.ranges;
+        let position = ranges
+            .iter()
+            .position(|other| other.start > range.start)
+            .unwrap_or(ranges.len());
+        debug_assert!(position == 0 || ranges[position - 1].end <= range.start);
+        debug_assert!(position == ranges.len() || range.end <= ranges[position].start);
+
+        let merges_previous = position > 0 && ranges[position - 1].end == range.start;
+        let merges_next = position < ranges.len() && ranges[position].start == range.end;
+
+        match (merges_previous, merges_next) {
+            (true, true) => {
+                ranges[position - 1].end = ranges[position].end;
+                ranges.remove(position);
+            }
+            (true, false) => ranges[position - 1].end = range.end,
+            (false, true) => ranges[position].start = range.start,
+            (false, false) => ranges.insert(position, range),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::free_list::FreeList;
+
+    #[test]
+    fn take_and_free() {
+        let mut free = FreeList::new(0..16);
+        let a = free.take(4).unwrap();
+        let b = free.take(4).unwrap();
+        let c = free.take(8).unwrap();
+        assert_eq!((a.clone(), b.clone(), c.clone()), (0..4, 4..8, 8..16));
+        assert!(free.ranges().is_empty());
+        assert!(!free.can_take(1));
+        assert_eq!(free.take(1), None);
+
+        free.free(c);
+        free.free(a);
+        assert_eq!(free.ranges(), [0..4, 8..16]);
+        assert!(free.can_take(8));
+        assert!(!free.can_take(9));
+        assert_eq!(free.take(6), Some(8..14));
+        free.free(8..14);
+        free.free(b);
+        assert_eq!(free.ranges(), [0..16]);
+
+        assert_eq!(FreeList::new(4..4), FreeList::default());
+    }
+}
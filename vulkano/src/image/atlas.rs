@@ -0,0 +1,567 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::device::physical::QueueFamily;
+use crate::device::Device;
+use crate::format::Format;
+use crate::free_list::FreeList;
+use crate::image::ImageCreateFlags;
+use crate::image::ImageCreationError;
+use crate::image::ImageDimensions;
+use crate::image::ImageUploader;
+use crate::image::ImageUsage;
+use crate::image::StorageImage;
+use crate::sync::FenceSignalFuture;
+use crate::sync::FrameTracker;
+use crate::sync::GpuFuture;
+use crate::sync::SignalPoint;
+use crate::sync::TimelineSemaphore;
+use crate::OomError;
+use std::sync::Arc;
+
+/// Divides a large image into rectangular regions, for example the glyphs of a font or the
+/// sprites of a game, and uploads their texels.
+///
+/// The regions are allocated by an [`AtlasAllocator`], and the texels are uploaded with an
+/// `ImageUploader`, so that all the regions that are inserted during a frame are copied with one
+/// command. The image can be sampled with the UV coordinates returned by
+/// `AtlasRegion::uv_rect`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use vulkano::command_buffer::AutoCommandBufferBuilder;
+/// # use vulkano::command_buffer::PrimaryAutoCommandBuffer;
+/// # use vulkano::device::Queue;
+/// # let queue: Arc<Queue> = return;
+/// # let mut builder: AutoCommandBufferBuilder<PrimaryAutoCommandBuffer> = return;
+/// # let glyph: Vec<u8> = return;
+/// use vulkano::format::Format;
+/// use vulkano::image::ImageUploader;
+/// use vulkano::image::TextureAtlas;
+/// use vulkano::sync;
+/// use vulkano::sync::GpuFuture;
+///
+/// let mut atlas = TextureAtlas::new(
+///     queue.device().clone(),
+///     [1024, 1024],
+///     Format::R8Unorm,
+///     Some(queue.family()),
+/// )
+/// .unwrap();
+/// let mut uploader = ImageUploader::new();
+///
+/// // A glyph of 12x16 texels.
+/// let region = atlas.insert(&mut uploader, [12, 16], &glyph).unwrap();
+/// let [u_min, v_min, u_max, v_max] = region.uv_rect();
+/// uploader.record(&mut builder).unwrap();
+///
+/// // Once the glyph isn't drawn anymore.
+/// atlas.free(region);
+///
+/// let future = Arc::new(
+///     sync::now(queue.device().clone())
+///         .then_execute(queue.clone(), builder.build().unwrap())
+///         .unwrap()
+///         .then_signal_fence_and_flush()
+///         .unwrap(),
+/// );
+/// atlas.end_frame_after_fence(&future);
+///
+/// // At the start of the next frames, makes the region available again once the fence is
+/// // signaled.
+/// atlas.cleanup_finished().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct TextureAtlas {
+    image: Arc<StorageImage>,
+    allocator: AtlasAllocator,
+}
+
+impl TextureAtlas {
+    /// Builds a new empty atlas, with an image of the given dimensions that can be sampled and
+    /// written by transfer commands.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the dimensions is 0.
+    ///
+    pub fn new<'a, I>(
+        device: Arc<Device>,
+        dimensions: [u32; 2],
+        format: Format,
+        queue_families: I,
+    ) -> Result<TextureAtlas, ImageCreationError>
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        let allocator = AtlasAllocator::new(dimensions);
+        let image = StorageImage::with_usage(
+            device,
+            ImageDimensions::Dim2d {
+                width: dimensions[0],
+                height: dimensions[1],
+                array_layers: 1,
+            },
+            format,
+            ImageUsage {
+                transfer_destination: true,
+                sampled: true,
+                ..ImageUsage::none()
+            },
+            ImageCreateFlags::none(),
+            queue_families,
+        )?;
+
+        Ok(TextureAtlas { image, allocator })
+    }
+
+    /// Allocates a region of the given dimensions, and adds the upload of `data`, which contains
+    /// its tightly packed texels, to `uploader`. Returns `None` if the atlas is full.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the dimensions is 0.
+    ///
+    pub fn insert(
+        &mut self,
+        uploader: &mut ImageUploader,
+        dimensions: [u32; 2],
+        data: &[u8],
+    ) -> Option<AtlasRegion> {
+        let region = self.allocator.allocate(dimensions)?;
+        self.upload(uploader, &region, data);
+        Some(region)
+    }
+
+    /// Adds the upload of new texels for a region that was already inserted to `uploader`.
+    pub fn upload(&self, uploader: &mut ImageUploader, region: &AtlasRegion, data: &[u8]) {
+        uploader.upload(
+            self.image.clone(),
+            data,
+            [region.offset[0], region.offset[1], 0],
+            [region.dimensions[0], region.dimensions[1], 1],
+            0..1,
+            0,
+        );
+    }
+
+    /// Frees a region at the end of the current frame. See `AtlasAllocator::free`.
+    #[inline]
+    pub fn free(&mut self, region: AtlasRegion) {
+        self.allocator.free(region);
+    }
+
+    /// Ends the current frame, and returns its identifier. See `AtlasAllocator::end_frame`.
+    #[inline]
+    pub fn end_frame(&mut self) -> AtlasFrame {
+        self.allocator.end_frame()
+    }
+
+    /// Ends the current frame, and frees its regions once `fence` is signaled. See
+    /// `AtlasAllocator::end_frame_after_fence`.
+    #[inline]
+    pub fn end_frame_after_fence<F>(&mut self, fence: &Arc<FenceSignalFuture<F>>) -> AtlasFrame
+    where
+        F: GpuFuture + Send + 'static,
+    {
+        self.allocator.end_frame_after_fence(fence)
+    }
+
+    /// Ends the current frame, and frees its regions once `semaphore` reaches `value`. See
+    /// `AtlasAllocator::end_frame_after_timeline`.
+    #[inline]
+    pub fn end_frame_after_timeline(
+        &mut self,
+        semaphore: &Arc<TimelineSemaphore>,
+        value: u64,
+    ) -> AtlasFrame {
+        self.allocator.end_frame_after_timeline(semaphore, value)
+    }
+
+    /// Makes the regions of the frames whose fence or timeline value has been signaled available
+    /// again. See `AtlasAllocator::cleanup_finished`.
+    #[inline]
+    pub fn cleanup_finished(&mut self) -> Result<(), OomError> {
+        self.allocator.cleanup_finished()
+    }
+
+    /// Makes the regions freed during `frame` available again. See
+    /// `AtlasAllocator::frame_completed`.
+    #[inline]
+    pub fn frame_completed(&mut self, frame: AtlasFrame) {
+        self.allocator.frame_completed(frame);
+    }
+
+    /// Returns the image of the atlas.
+    #[inline]
+    pub fn image(&self) -> &Arc<StorageImage> {
+        &self.image
+    }
+
+    /// Returns the allocator of the regions.
+    #[inline]
+    pub fn allocator(&self) -> &AtlasAllocator {
+        &self.allocator
+    }
+}
+
+/// Allocates rectangular regions in a two-dimensional area, such as an image.
+///
+/// The regions are packed into shelves, which are horizontal strips that span the width of the
+/// area. A region goes in the lowest shelf that is tall enough and has enough free space, and a
+/// new shelf as tall as the region is opened otherwise. This packs regions of similar heights,
+/// such as glyphs, with little waste.
+///
+/// The GPU may still be using a region that is freed, so the freed regions are only allocated
+/// again once the frame during which they were freed is finished. The frames are tracked the
+/// same way as by a `QueryPoolAllocator`: a frame that is ended with `end_frame_after_fence` is
+/// completed by `cleanup_finished` once its fence is signaled, and a frame that is ended with
+/// `end_frame` is completed by `frame_completed`.
+#[derive(Debug)]
+pub struct AtlasAllocator {
+    dimensions: [u32; 2],
+    // Sorted by vertical position.
+    shelves: Vec<Shelf>,
+    // The regions freed during each frame.
+    frames: FrameTracker<AtlasRegion>,
+}
+
+#[derive(Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    // The free horizontal ranges of the shelf.
+    free: FreeList,
+}
+
+impl AtlasAllocator {
+    /// Builds a new allocator for an area of the given dimensions, with no region allocated.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the dimensions is 0.
+    ///
+    pub fn new(dimensions: [u32; 2]) -> AtlasAllocator {
+        assert!(dimensions[0] != 0 && dimensions[1] != 0);
+
+        AtlasAllocator {
+            dimensions,
+            shelves: Vec::new(),
+            frames: FrameTracker::new(),
+        }
+    }
+
+    /// Returns the dimensions of the area.
+    #[inline]
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.dimensions
+    }
+
+    /// Allocates a region of the given dimensions. Returns `None` if there is no space left for
+    /// it.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the dimensions is 0.
+    ///
+    pub fn allocate(&mut self, dimensions: [u32; 2]) -> Option<AtlasRegion> {
+        assert!(dimensions[0] != 0 && dimensions[1] != 0);
+        let [width, height] = dimensions;
+
+        let best = self
+            .shelves
+            .iter()
+            .enumerate()
+            .filter(|(_, shelf)| shelf.height >= height && shelf.free.can_take(width))
+            .min_by_key(|(_, shelf)| shelf.height)
+            .map(|(index, _)| index);
+
+        let index = match best {
+            Some(index) => index,
+            None => {
+                let top = self
+                    .shelves
+                    .last()
+                    .map_or(0, |shelf| shelf.y + shelf.height);
+                if width > self.dimensions[0] || height > self.dimensions[1] - top {
+                    return None;
+                }
+
+                self.shelves.push(Shelf {
+                    y: top,
+                    height,
+                    free: FreeList::new(width..self.dimensions[0]),
+                });
+
+                return Some(AtlasRegion {
+                    offset: [0, top],
+                    dimensions,
+                    atlas_dimensions: self.dimensions,
+                });
+            }
+        };
+
+        let shelf = &mut self.shelves[index];
+        let x = shelf.free.take(width).unwrap().start;
+
+        Some(AtlasRegion {
+            offset: [x, shelf.y],
+            dimensions,
+            atlas_dimensions: self.dimensions,
+        })
+    }
+
+    /// Frees a region at the end of the current frame. It can be allocated again once the frame
+    /// is completed.
+    ///
+    /// `region` must have been allocated by this allocator, and not freed yet.
+    #[inline]
+    pub fn free(&mut self, region: AtlasRegion) {
+        self.frames.push(region);
+    }
+
+    /// Ends the current frame, and returns its identifier. The next regions that are freed
+    /// belong to the next frame.
+    ///
+    /// The regions freed during the frame are only made available again by `frame_completed`.
+    #[inline]
+    pub fn end_frame(&mut self) -> AtlasFrame {
+        AtlasFrame(self.frames.end_frame(None))
+    }
+
+    /// Ends the current frame like `end_frame`. Its regions are made available again by
+    /// `cleanup_finished` once `fence` is signaled.
+    ///
+    /// `fence` must be signaled after the command buffers that use the regions freed during the
+    /// frame, for example because it is the fence of the last submission of the frame.
+    pub fn end_frame_after_fence<F>(&mut self, fence: &Arc<FenceSignalFuture<F>>) -> AtlasFrame
+    where
+        F: GpuFuture + Send + 'static,
+    {
+        AtlasFrame(
+            self.frames
+                .end_frame(Some(SignalPoint::Fence(fence.clone()))),
+        )
+    }
+
+    /// Ends the current frame like `end_frame`. Its regions are made available again by
+    /// `cleanup_finished` once `semaphore` reaches `value`.
+    pub fn end_frame_after_timeline(
+        &mut self,
+        semaphore: &Arc<TimelineSemaphore>,
+        value: u64,
+    ) -> AtlasFrame {
+        AtlasFrame(
+            self.frames
+                .end_frame(Some(SignalPoint::Timeline(semaphore.clone(), value))),
+        )
+    }
+
+    /// Makes the regions of the frames whose fence or timeline value has been signaled available
+    /// again.
+    ///
+    /// The frames that were ended with `end_frame` are left alone. This function never blocks.
+    pub fn cleanup_finished(&mut self) -> Result<(), OomError> {
+        for region in self.frames.take_finished()? {
+            self.release(region);
+        }
+        Ok(())
+    }
+
+    /// Makes the regions that were freed during `frame` available again. This must be called
+    /// once the GPU has finished the frame, for example once its fence is signaled.
+    ///
+    /// Does nothing if `frame` was already completed or wasn't returned by this allocator.
+    ///
+    /// Contrary to `QueryPoolAllocator::frame_completed`, this function is safe: the allocator
+    /// only hands out coordinates, and the accesses of the command buffers to the image are
+    /// synchronized by vulkano. Completing a frame too early only lets new texels be uploaded
+    /// over a region that is still drawn, or makes the upload fail because the image is in use.
+    pub fn frame_completed(&mut self, frame: AtlasFrame) {
+        if let Some(regions) = self.frames.take_frame(frame.0) {
+            for region in regions {
+                self.release(region);
+            }
+        }
+    }
+
+    /// Frees all the regions immediately, including the ones that are waiting for their frame
+    /// to complete.
+    pub fn clear(&mut self) {
+        self.shelves.clear();
+        self.frames.clear();
+    }
+
+    /// Returns the number of frames that ended but weren't completed yet.
+    #[inline]
+    pub fn num_frames_in_flight(&self) -> usize {
+        self.frames.num_frames_in_flight()
+    }
+
+    /// Returns the height from the top of the area that the shelves occupy. The rest of the area
+    /// is free.
+    #[inline]
+    pub fn used_height(&self) -> u32 {
+        self.shelves
+            .last()
+            .map_or(0, |shelf| shelf.y + shelf.height)
+    }
+
+    // Gives the space of `region` back to its shelf, and removes the empty shelves at the bottom
+    // so that their height can be used by shelves of another height.
+    fn release(&mut self, region: AtlasRegion) {
+        let shelf = self
+            .shelves
+            .iter_mut()
+            .find(|shelf| shelf.y == region.offset[1])
+            .expect("the region wasn't allocated by this allocator");
+        shelf
+            .free
+            .free(region.offset[0]..region.offset[0] + region.dimensions[0]);
+
+        let width = self.dimensions[0];
+        while let Some(shelf) = self.shelves.last() {
+            if shelf.free != FreeList::new(0..width) {
+                break;
+            }
+            self.shelves.pop();
+        }
+    }
+}
+
+/// Identifier of a frame of an `AtlasAllocator`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AtlasFrame(u64);
+
+/// Region allocated by an `AtlasAllocator`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AtlasRegion {
+    offset: [u32; 2],
+    dimensions: [u32; 2],
+    atlas_dimensions: [u32; 2],
+}
+
+impl AtlasRegion {
+    /// Returns the position in texels of the top-left hand corner of the region.
+    #[inline]
+    pub fn offset(&self) -> [u32; 2] {
+        self.offset
+    }
+
+    /// Returns the dimensions in texels of the region.
+    #[inline]
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.dimensions
+    }
+
+    /// Returns the normalized texture coordinates of the region, as
+    /// `[u_min, v_min, u_max, v_max]`.
+    #[inline]
+    pub fn uv_rect(&self) -> [f32; 4] {
+        let [width, height] = self.atlas_dimensions;
+        [
+            self.offset[0] as f32 / width as f32,
+            self.offset[1] as f32 / height as f32,
+            (self.offset[0] + self.dimensions[0]) as f32 / width as f32,
+            (self.offset[1] + self.dimensions[1]) as f32 / height as f32,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::Format;
+    use crate::image::AtlasAllocator;
+    use crate::image::ImageUploader;
+    use crate::image::TextureAtlas;
+    use crate::sync::TimelineSemaphore;
+    use std::sync::Arc;
+
+    #[test]
+    fn shelves() {
+        let mut allocator = AtlasAllocator::new([64, 64]);
+
+        let a = allocator.allocate([32, 16]).unwrap();
+        let b = allocator.allocate([32, 8]).unwrap();
+        let c = allocator.allocate([16, 8]).unwrap();
+        assert_eq!(a.offset(), [0, 0]);
+        assert_eq!(b.offset(), [32, 0]);
+        // The first shelf is full, so a shelf as tall as `c` is opened.
+        assert_eq!(c.offset(), [0, 16]);
+        assert_eq!(allocator.used_height(), 24);
+        assert_eq!(c.uv_rect(), [0.0, 0.25, 0.25, 0.375]);
+
+        assert!(allocator.allocate([65, 1]).is_none());
+        assert!(allocator.allocate([1, 41]).is_none());
+    }
+
+    #[test]
+    fn deferred_free() {
+        let mut allocator = AtlasAllocator::new([64, 64]);
+
+        let a = allocator.allocate([64, 32]).unwrap();
+        let b = allocator.allocate([64, 32]).unwrap();
+        assert!(allocator.allocate([1, 1]).is_none());
+
+        allocator.free(b);
+        let frame = allocator.end_frame();
+        // Still in use by the frame.
+        assert!(allocator.allocate([1, 1]).is_none());
+        assert_eq!(allocator.num_frames_in_flight(), 1);
+
+        allocator.frame_completed(frame);
+        allocator.frame_completed(frame);
+        assert_eq!(allocator.num_frames_in_flight(), 0);
+        // The empty shelf at the bottom was removed, so its height can be used by any shelf.
+        assert_eq!(allocator.used_height(), 32);
+        assert_eq!(allocator.allocate([16, 16]).unwrap().offset(), [0, 32]);
+
+        allocator.free(a);
+        allocator.clear();
+        assert_eq!(allocator.used_height(), 0);
+    }
+
+    #[test]
+    fn deferred_free_after_timeline() {
+        let (device, _) = gfx_dev_and_queue!(timeline_semaphore);
+
+        let semaphore = Arc::new(TimelineSemaphore::new(device, 0).unwrap());
+        let mut allocator = AtlasAllocator::new([64, 64]);
+
+        let a = allocator.allocate([64, 64]).unwrap();
+        allocator.free(a);
+        allocator.end_frame_after_timeline(&semaphore, 1);
+
+        allocator.cleanup_finished().unwrap();
+        assert!(allocator.allocate([1, 1]).is_none());
+
+        semaphore.signal(1).unwrap();
+        allocator.cleanup_finished().unwrap();
+        assert_eq!(allocator.num_frames_in_flight(), 0);
+        assert_eq!(allocator.allocate([64, 64]), Some(a));
+    }
+
+    #[test]
+    fn insert() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let mut atlas =
+            TextureAtlas::new(device, [256, 256], Format::R8Unorm, Some(queue.family())).unwrap();
+        let mut uploader = ImageUploader::new();
+
+        let region = atlas
+            .insert(&mut uploader, [12, 16], &[0; 12 * 16])
+            .unwrap();
+        assert_eq!(region.dimensions(), [12, 16]);
+        assert_eq!(uploader.num_images(), 1);
+        assert_eq!(uploader.staging_size(), 12 * 16);
+    }
+}
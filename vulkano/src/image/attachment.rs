@@ -7,6 +7,7 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use crate::device::DebugName;
 use crate::device::Device;
 use crate::format::ClearValue;
 use crate::format::Format;
@@ -33,6 +34,7 @@ use crate::memory::pool::StdMemoryPoolAlloc;
 use crate::memory::DedicatedAlloc;
 use crate::sync::AccessError;
 use crate::sync::Sharing;
+use crate::OomError;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::iter::Empty;
@@ -99,6 +101,7 @@ impl AttachmentImage {
     /// Returns an error if the dimensions are too large or if the backend doesn't support this
     /// format as a framebuffer attachment.
     #[inline]
+    #[track_caller]
     pub fn new(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -118,6 +121,7 @@ impl AttachmentImage {
     ///
     /// > **Note**: This function is just a convenient shortcut for `with_usage`.
     #[inline]
+    #[track_caller]
     pub fn input_attachment(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -143,6 +147,7 @@ impl AttachmentImage {
     /// > **Note**: You can also use this function and pass `1` for the number of samples if you
     /// > want a regular image.
     #[inline]
+    #[track_caller]
     pub fn multisampled(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -156,6 +161,7 @@ impl AttachmentImage {
     ///
     /// > **Note**: This function is just a convenient shortcut for `multisampled_with_usage`.
     #[inline]
+    #[track_caller]
     pub fn multisampled_input_attachment(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -176,6 +182,7 @@ impl AttachmentImage {
     /// on the format of the usage. Therefore the `usage` parameter allows you specify usages in
     /// addition to these two.
     #[inline]
+    #[track_caller]
     pub fn with_usage(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -190,6 +197,7 @@ impl AttachmentImage {
     /// > **Note**: You can also use this function and pass `1` for the number of samples if you
     /// > want a regular image.
     #[inline]
+    #[track_caller]
     pub fn multisampled_with_usage(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -205,6 +213,7 @@ impl AttachmentImage {
     /// > **Note**: You can also use this function and pass `1` for the number of layers if you
     /// > want a regular image.
     #[inline]
+    #[track_caller]
     pub fn multisampled_with_usage_with_layers(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -220,6 +229,7 @@ impl AttachmentImage {
     ///
     /// > **Note**: This function is just a convenient shortcut for `with_usage`.
     #[inline]
+    #[track_caller]
     pub fn sampled(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -244,6 +254,7 @@ impl AttachmentImage {
     ///
     /// > **Note**: This function is just a convenient shortcut for `with_usage`.
     #[inline]
+    #[track_caller]
     pub fn sampled_input_attachment(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -272,6 +283,7 @@ impl AttachmentImage {
     ///
     /// > **Note**: This function is just a convenient shortcut for `multisampled_with_usage`.
     #[inline]
+    #[track_caller]
     pub fn sampled_multisampled(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -291,6 +303,7 @@ impl AttachmentImage {
     ///
     /// > **Note**: This function is just a convenient shortcut for `multisampled_with_usage`.
     #[inline]
+    #[track_caller]
     pub fn sampled_multisampled_input_attachment(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -313,6 +326,7 @@ impl AttachmentImage {
     ///
     /// > **Note**: This function is just a convenient shortcut for `with_usage`.
     #[inline]
+    #[track_caller]
     pub fn transient(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -337,6 +351,7 @@ impl AttachmentImage {
     ///
     /// > **Note**: This function is just a convenient shortcut for `with_usage`.
     #[inline]
+    #[track_caller]
     pub fn transient_input_attachment(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -365,6 +380,7 @@ impl AttachmentImage {
     ///
     /// > **Note**: This function is just a convenient shortcut for `multisampled_with_usage`.
     #[inline]
+    #[track_caller]
     pub fn transient_multisampled(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -384,6 +400,7 @@ impl AttachmentImage {
     ///
     /// > **Note**: This function is just a convenient shortcut for `multisampled_with_usage`.
     #[inline]
+    #[track_caller]
     pub fn transient_multisampled_input_attachment(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -400,6 +417,7 @@ impl AttachmentImage {
     }
 
     // All constructors dispatch to this one.
+    #[track_caller]
     fn new_impl(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -488,6 +506,13 @@ impl<A> AttachmentImage<A> {
     }
 }
 
+impl<A> DebugName for AttachmentImage<A> {
+    #[inline]
+    fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        self.image.set_debug_name(name)
+    }
+}
+
 unsafe impl<A> ImageAccess for AttachmentImage<A> {
     #[inline]
     fn inner(&self) -> ImageInner {
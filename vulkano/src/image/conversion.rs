@@ -0,0 +1,352 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Conversions of pixels on the CPU, before they are uploaded to an image.
+//!
+//! Image decoders don't always return the pixels in the layout of the format of the image. For
+//! example, formats with three 8-bit channels such as `R8G8B8_SRGB` are rarely supported for
+//! sampled images, so RGB pixels have to be expanded to RGBA first. Since these conversions can
+//! take longer than the upload itself, the functions of this module use SSSE3 or F16C on x86
+//! when the processor supports them, and NEON on AArch64. The sRGB conversions use lookup tables
+//! instead.
+
+use half::f16;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    // The linear value of each 8-bit sRGB value.
+    static ref SRGB_TO_LINEAR: [f32; 256] = {
+        let mut table = [0.0; 256];
+        for (value, linear) in table.iter_mut().enumerate() {
+            *linear = srgb_decode(value as f32 / 255.0);
+        }
+        table
+    };
+
+    // The linear values halfway between two consecutive 8-bit sRGB values. The 8-bit sRGB value
+    // of a linear value is the number of thresholds that are below it.
+    static ref LINEAR_THRESHOLDS: [f32; 255] = {
+        let mut table = [0.0; 255];
+        for (value, threshold) in table.iter_mut().enumerate() {
+            *threshold = srgb_decode((value as f32 + 0.5) / 255.0);
+        }
+        table
+    };
+}
+
+#[inline]
+fn srgb_decode(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Expands RGB pixels with 8-bit channels to RGBA, with the given alpha.
+///
+/// # Panic
+///
+/// - Panics if the length of `src` isn't a multiple of 3.
+/// - Panics if `dst` doesn't have 4 bytes for each pixel of `src`.
+pub fn rgb_to_rgba(src: &[u8], dst: &mut [u8], alpha: u8) {
+    assert_eq!(src.len() % 3, 0);
+    assert_eq!(dst.len(), src.len() / 3 * 4);
+
+    let pixels = simd::rgb_to_rgba(src, dst, alpha);
+
+    for (src, dst) in src[pixels * 3..]
+        .chunks_exact(3)
+        .zip(dst[pixels * 4..].chunks_exact_mut(4))
+    {
+        dst[..3].copy_from_slice(src);
+        dst[3] = alpha;
+    }
+}
+
+/// Swaps the red and blue channels of pixels with four 8-bit channels, which converts BGRA
+/// pixels to RGBA and the other way around.
+///
+/// # Panic
+///
+/// - Panics if the length of `pixels` isn't a multiple of 4.
+pub fn swap_red_blue(pixels: &mut [u8]) {
+    assert_eq!(pixels.len() % 4, 0);
+
+    let done = simd::swap_red_blue(pixels);
+
+    for pixel in pixels[done * 4..].chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+}
+
+/// Converts 32-bit floats to 16-bit floats, for formats such as `R16G16B16A16_SFLOAT`. Values
+/// are rounded to the nearest 16-bit float.
+///
+/// # Panic
+///
+/// - Panics if `src` and `dst` don't have the same length.
+pub fn f32_to_f16(src: &[f32], dst: &mut [f16]) {
+    assert_eq!(src.len(), dst.len());
+
+    let done = simd::f32_to_f16(src, dst);
+
+    for (src, dst) in src[done..].iter().zip(&mut dst[done..]) {
+        *dst = f16::from_f32(*src);
+    }
+}
+
+/// Decodes 8-bit sRGB values to linear values between 0.0 and 1.0.
+///
+/// The alpha channel isn't encoded in the sRGB formats, so only the color channels should be
+/// passed to this function.
+///
+/// # Panic
+///
+/// - Panics if `src` and `dst` don't have the same length.
+pub fn srgb_to_linear(src: &[u8], dst: &mut [f32]) {
+    assert_eq!(src.len(), dst.len());
+
+    let table = &*SRGB_TO_LINEAR;
+    for (src, dst) in src.iter().zip(dst) {
+        *dst = table[*src as usize];
+    }
+}
+
+/// Encodes linear values between 0.0 and 1.0 to the nearest 8-bit sRGB values. Values outside
+/// of this range are clamped.
+///
+/// The alpha channel isn't encoded in the sRGB formats, so only the color channels should be
+/// passed to this function.
+///
+/// # Panic
+///
+/// - Panics if `src` and `dst` don't have the same length.
+pub fn linear_to_srgb(src: &[f32], dst: &mut [u8]) {
+    assert_eq!(src.len(), dst.len());
+
+    let thresholds = &*LINEAR_THRESHOLDS;
+    for (src, dst) in src.iter().zip(dst) {
+        // Binary search of the number of thresholds that are below the value.
+        let mut index = 0;
+        let mut step = 128;
+        while step != 0 {
+            if index + step <= thresholds.len() && thresholds[index + step - 1] < *src {
+                index += step;
+            }
+            step /= 2;
+        }
+        *dst = index as u8;
+    }
+}
+
+// The functions of this module convert as many values as they can, and return how many they
+// converted. The caller converts the remaining values.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod simd {
+    use half::f16;
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    pub(super) fn rgb_to_rgba(src: &[u8], dst: &mut [u8], alpha: u8) -> usize {
+        if is_x86_feature_detected!("ssse3") {
+            unsafe { rgb_to_rgba_ssse3(src, dst, alpha) }
+        } else {
+            0
+        }
+    }
+
+    #[target_feature(enable = "ssse3")]
+    unsafe fn rgb_to_rgba_ssse3(src: &[u8], dst: &mut [u8], alpha: u8) -> usize {
+        let shuffle = _mm_setr_epi8(0, 1, 2, -1, 3, 4, 5, -1, 6, 7, 8, -1, 9, 10, 11, -1);
+        let alpha = _mm_set1_epi32((alpha as i32) << 24);
+
+        // Each iteration loads 16 bytes, but only converts the 4 pixels of the first 12.
+        let mut pixels = 0;
+        while pixels * 3 + 16 <= src.len() {
+            let rgb = _mm_loadu_si128(src.as_ptr().add(pixels * 3) as *const __m128i);
+            let rgba = _mm_or_si128(_mm_shuffle_epi8(rgb, shuffle), alpha);
+            _mm_storeu_si128(dst.as_mut_ptr().add(pixels * 4) as *mut __m128i, rgba);
+            pixels += 4;
+        }
+        pixels
+    }
+
+    pub(super) fn swap_red_blue(pixels: &mut [u8]) -> usize {
+        if is_x86_feature_detected!("ssse3") {
+            unsafe { swap_red_blue_ssse3(pixels) }
+        } else {
+            0
+        }
+    }
+
+    #[target_feature(enable = "ssse3")]
+    unsafe fn swap_red_blue_ssse3(pixels: &mut [u8]) -> usize {
+        let shuffle = _mm_setr_epi8(2, 1, 0, 3, 6, 5, 4, 7, 10, 9, 8, 11, 14, 13, 12, 15);
+
+        let mut done = 0;
+        while (done + 4) * 4 <= pixels.len() {
+            let ptr = pixels.as_mut_ptr().add(done * 4) as *mut __m128i;
+            _mm_storeu_si128(ptr, _mm_shuffle_epi8(_mm_loadu_si128(ptr), shuffle));
+            done += 4;
+        }
+        done
+    }
+
+    pub(super) fn f32_to_f16(src: &[f32], dst: &mut [f16]) -> usize {
+        if is_x86_feature_detected!("f16c") {
+            unsafe { f32_to_f16_f16c(src, dst) }
+        } else {
+            0
+        }
+    }
+
+    #[target_feature(enable = "f16c")]
+    unsafe fn f32_to_f16_f16c(src: &[f32], dst: &mut [f16]) -> usize {
+        let mut done = 0;
+        while done + 4 <= src.len() {
+            let values = _mm_loadu_ps(src.as_ptr().add(done));
+            let halves = _mm_cvtps_ph::<_MM_FROUND_TO_NEAREST_INT>(values);
+            _mm_storel_epi64(dst.as_mut_ptr().add(done) as *mut __m128i, halves);
+            done += 4;
+        }
+        done
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod simd {
+    use half::f16;
+    use std::arch::aarch64::*;
+
+    pub(super) fn rgb_to_rgba(src: &[u8], dst: &mut [u8], alpha: u8) -> usize {
+        let mut pixels = 0;
+        unsafe {
+            let alpha = vdupq_n_u8(alpha);
+            while (pixels + 16) * 3 <= src.len() {
+                let rgb = vld3q_u8(src.as_ptr().add(pixels * 3));
+                let rgba = uint8x16x4_t(rgb.0, rgb.1, rgb.2, alpha);
+                vst4q_u8(dst.as_mut_ptr().add(pixels * 4), rgba);
+                pixels += 16;
+            }
+        }
+        pixels
+    }
+
+    pub(super) fn swap_red_blue(pixels: &mut [u8]) -> usize {
+        let mut done = 0;
+        unsafe {
+            while (done + 16) * 4 <= pixels.len() {
+                let ptr = pixels.as_mut_ptr().add(done * 4);
+                let bgra = vld4q_u8(ptr);
+                vst4q_u8(ptr, uint8x16x4_t(bgra.2, bgra.1, bgra.0, bgra.3));
+                done += 16;
+            }
+        }
+        done
+    }
+
+    // The conversion instructions of NEON use 16-bit float types that aren't stable in Rust yet.
+    pub(super) fn f32_to_f16(_: &[f32], _: &mut [f16]) -> usize {
+        0
+    }
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+mod simd {
+    use half::f16;
+
+    pub(super) fn rgb_to_rgba(_: &[u8], _: &mut [u8], _: u8) -> usize {
+        0
+    }
+
+    pub(super) fn swap_red_blue(_: &mut [u8]) -> usize {
+        0
+    }
+
+    pub(super) fn f32_to_f16(_: &[f32], _: &mut [f16]) -> usize {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::image::conversion;
+    use half::f16;
+
+    // Lengths that leave a remainder after the SIMD loops.
+    const PIXELS: usize = 67;
+
+    #[test]
+    fn rgb_to_rgba() {
+        let src: Vec<u8> = (0..PIXELS * 3).map(|i| i as u8).collect();
+
+        for pixels in 0..=PIXELS {
+            let mut dst = vec![0; pixels * 4];
+            conversion::rgb_to_rgba(&src[..pixels * 3], &mut dst, 200);
+
+            for (src, dst) in src.chunks_exact(3).zip(dst.chunks_exact(4)) {
+                assert_eq!(dst, [src[0], src[1], src[2], 200]);
+            }
+        }
+    }
+
+    #[test]
+    fn swap_red_blue() {
+        let bgra: Vec<u8> = (0..PIXELS * 4).map(|i| i as u8).collect();
+
+        for pixels in 0..=PIXELS {
+            let mut rgba = bgra[..pixels * 4].to_vec();
+            conversion::swap_red_blue(&mut rgba);
+
+            for (bgra, rgba) in bgra.chunks_exact(4).zip(rgba.chunks_exact(4)) {
+                assert_eq!(rgba, [bgra[2], bgra[1], bgra[0], bgra[3]]);
+            }
+        }
+    }
+
+    #[test]
+    fn f32_to_f16() {
+        let src: Vec<f32> = (0..PIXELS)
+            .map(|i| (i as f32 - 20.0) * 1.37)
+            .chain([0.1, 65504.0, 1.0e6, -1.0e-8, f32::INFINITY])
+            .collect();
+
+        for len in 0..=src.len() {
+            let mut dst = vec![f16::ZERO; len];
+            conversion::f32_to_f16(&src[..len], &mut dst);
+
+            for (src, dst) in src.iter().zip(&dst) {
+                assert_eq!(dst.to_bits(), f16::from_f32(*src).to_bits());
+            }
+        }
+    }
+
+    #[test]
+    fn srgb_round_trip() {
+        let srgb: Vec<u8> = (0..=255).collect();
+        let mut linear = vec![0.0; 256];
+        conversion::srgb_to_linear(&srgb, &mut linear);
+
+        assert_eq!(linear[0], 0.0);
+        assert!((linear[255] - 1.0).abs() < 1.0e-6);
+        assert!((linear[188] - 0.5).abs() < 0.005);
+        assert!(linear.windows(2).all(|w| w[0] < w[1]));
+
+        let mut encoded = vec![0; 256];
+        conversion::linear_to_srgb(&linear, &mut encoded);
+        assert_eq!(encoded, srgb);
+
+        let mut clamped = [0; 2];
+        conversion::linear_to_srgb(&[-1.0, 2.0], &mut clamped);
+        assert_eq!(clamped, [0, 255]);
+    }
+}
@@ -0,0 +1,723 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::check_errors;
+use crate::command_buffer::submit::SubmitCommandBufferBuilder;
+use crate::command_buffer::submit::SubmitCommandBufferError;
+use crate::device::DebugName;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::device::Queue;
+use crate::format::ClearValue;
+use crate::format::Format;
+use crate::image::sys::ImageCreationError;
+use crate::image::sys::UnsafeImage;
+use crate::image::traits::ImageAccess;
+use crate::image::traits::ImageClearValue;
+use crate::image::traits::ImageContent;
+use crate::image::ImageCreateFlags;
+use crate::image::ImageDescriptorLayouts;
+use crate::image::ImageDimensions;
+use crate::image::ImageInner;
+use crate::image::ImageLayout;
+use crate::image::ImageUsage;
+use crate::image::SampleCount;
+use crate::memory::DedicatedAlloc;
+use crate::memory::DeviceMemory;
+use crate::memory::DeviceMemoryAllocError;
+use crate::memory::DeviceMemoryBuilder;
+use crate::memory::ExternalMemoryHandleType;
+use crate::pnext::PNextChain;
+use crate::sync::wait_semaphore;
+use crate::sync::AccessError;
+use crate::sync::Semaphore;
+use crate::sync::SemaphoreError;
+use crate::sync::SemaphoreWaitFuture;
+use crate::sync::Sharing;
+use crate::DeviceSize;
+use crate::OomError;
+use crate::VulkanObject;
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::iter::Empty;
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// Two-dimensional image whose memory is a dma-buf imported from a Linux driver or another API,
+/// for example a frame captured by a V4L2 camera or decoded by GStreamer.
+///
+/// The image has linear tiling, and its content is preserved when it's imported. It's always in
+/// the `General` layout once it has been used.
+///
+/// The row pitch of a linear image is chosen by the Vulkan implementation, so the import fails
+/// if the dma-buf has a different one. Allocate the buffers of the producer with the row pitch
+/// returned by `DmaBufImportError::IncompatibleRowPitch` in this case, if the producer allows
+/// it.
+#[derive(Debug)]
+pub struct DmaBufImage {
+    // Inner implementation.
+    image: UnsafeImage,
+
+    // Imported memory bound to the image.
+    memory: Arc<DeviceMemory>,
+
+    // If true, then the image is in the `General` layout. If false, then it is still
+    // `Preinitialized`.
+    initialized: AtomicBool,
+
+    // Number of times this image is locked on the GPU side.
+    gpu_lock: AtomicUsize,
+}
+
+impl DmaBufImage {
+    /// Imports a dma-buf as an image with a single mipmap level and array layer.
+    ///
+    /// The `khr_external_memory_fd` and `ext_external_memory_dma_buf` extensions must be
+    /// enabled on the device. The image takes the ownership of `fd` if the import succeeds.
+    ///
+    /// # Safety
+    ///
+    /// - `fd` must be a dma-buf that contains a single plane of `format` pixels, starting at
+    ///   offset 0, with rows of `row_pitch` bytes.
+    /// - The accesses of the producer of the dma-buf must be synchronized with those of
+    ///   vulkano, for example with the sync files of a `DmaBufRing`.
+    ///
+    pub unsafe fn import(
+        device: Arc<Device>,
+        fd: File,
+        dimensions: [u32; 2],
+        format: Format,
+        usage: ImageUsage,
+        row_pitch: DeviceSize,
+    ) -> Result<Arc<DmaBufImage>, DmaBufImportError> {
+        if !device.enabled_extensions().khr_external_memory_fd {
+            return Err(DmaBufImportError::MissingExtension(
+                "khr_external_memory_fd",
+            ));
+        }
+        if !device.enabled_extensions().ext_external_memory_dma_buf {
+            return Err(DmaBufImportError::MissingExtension(
+                "ext_external_memory_dma_buf",
+            ));
+        }
+
+        let mut external_memory_info = ash::vk::ExternalMemoryImageCreateInfo {
+            handle_types: ash::vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT,
+            ..Default::default()
+        };
+        let mut p_next_chain = PNextChain::<ash::vk::ImageCreateInfo>::new();
+        p_next_chain.push(&mut external_memory_info);
+
+        let (image, mem_reqs) = UnsafeImage::with_p_next_chain(
+            device.clone(),
+            usage,
+            format,
+            ImageCreateFlags::none(),
+            ImageDimensions::Dim2d {
+                width: dimensions[0],
+                height: dimensions[1],
+                array_layers: 1,
+            },
+            SampleCount::Sample1,
+            1,
+            Sharing::Exclusive::<Empty<_>>,
+            true,
+            true,
+            p_next_chain,
+        )?;
+
+        let layout = image.color_linear_layout(0);
+        if layout.row_pitch != row_pitch {
+            return Err(DmaBufImportError::IncompatibleRowPitch {
+                requested: row_pitch,
+                required: layout.row_pitch,
+            });
+        }
+
+        let memory_type_bits = {
+            let fns = device.fns();
+            let mut fd_properties = ash::vk::MemoryFdPropertiesKHR::default();
+            let result = fns.khr_external_memory_fd.get_memory_fd_properties_khr(
+                device.internal_object(),
+                ash::vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT,
+                fd.as_raw_fd(),
+                &mut fd_properties,
+            );
+            if result == ash::vk::Result::ERROR_INVALID_EXTERNAL_HANDLE {
+                return Err(DmaBufImportError::InvalidHandle);
+            }
+            check_errors(result).map_err(OomError::from)?;
+            fd_properties.memory_type_bits & mem_reqs.memory_type_bits
+        };
+
+        if memory_type_bits == 0 {
+            return Err(DmaBufImportError::NoCompatibleMemoryType);
+        }
+
+        let memory = DeviceMemoryBuilder::new(
+            device.clone(),
+            memory_type_bits.trailing_zeros(),
+            mem_reqs.size,
+        )
+        .dedicated_info(DedicatedAlloc::Image(&image))
+        .import_info(
+            fd,
+            ExternalMemoryHandleType {
+                dma_buf: true,
+                ..ExternalMemoryHandleType::none()
+            },
+        )
+        .build()?;
+        image.bind_memory(&memory, 0)?;
+
+        Ok(Arc::new(DmaBufImage {
+            image,
+            memory,
+            initialized: AtomicBool::new(false),
+            gpu_lock: AtomicUsize::new(0),
+        }))
+    }
+
+    /// Returns the dimensions of the image.
+    #[inline]
+    pub fn dimensions(&self) -> [u32; 2] {
+        let dims = self.image.dimensions();
+        [dims.width(), dims.height()]
+    }
+
+    /// Returns the memory that was imported from the dma-buf.
+    #[inline]
+    pub fn memory(&self) -> &Arc<DeviceMemory> {
+        &self.memory
+    }
+}
+
+impl DebugName for DmaBufImage {
+    #[inline]
+    fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        self.image.set_debug_name(name)
+    }
+}
+
+unsafe impl ImageAccess for DmaBufImage {
+    #[inline]
+    fn inner(&self) -> ImageInner {
+        ImageInner {
+            image: &self.image,
+            first_layer: 0,
+            num_layers: 1,
+            first_mipmap_level: 0,
+            num_mipmap_levels: 1,
+        }
+    }
+
+    #[inline]
+    fn initial_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    #[inline]
+    fn final_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    #[inline]
+    fn descriptor_layouts(&self) -> Option<ImageDescriptorLayouts> {
+        Some(ImageDescriptorLayouts {
+            storage_image: ImageLayout::General,
+            combined_image_sampler: ImageLayout::General,
+            sampled_image: ImageLayout::General,
+            input_attachment: ImageLayout::General,
+        })
+    }
+
+    #[inline]
+    fn conflict_key(&self) -> u64 {
+        self.image.key()
+    }
+
+    #[inline]
+    fn try_gpu_lock(
+        &self,
+        _: bool,
+        uninitialized_safe: bool,
+        expected_layout: ImageLayout,
+    ) -> Result<(), AccessError> {
+        if expected_layout != ImageLayout::General && expected_layout != ImageLayout::Undefined {
+            return Err(AccessError::UnexpectedImageLayout {
+                requested: expected_layout,
+                allowed: ImageLayout::General,
+            });
+        }
+
+        if !uninitialized_safe
+            && expected_layout != ImageLayout::Undefined
+            && !self.initialized.load(Ordering::SeqCst)
+        {
+            return Err(AccessError::ImageNotInitialized {
+                requested: expected_layout,
+            });
+        }
+
+        if self
+            .gpu_lock
+            .compare_exchange(0, 1, Ordering::SeqCst, Ordering::SeqCst)
+            .unwrap_or_else(|e| e)
+            == 0
+        {
+            Ok(())
+        } else {
+            Err(AccessError::AlreadyInUse)
+        }
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self) {
+        let val = self.gpu_lock.fetch_add(1, Ordering::SeqCst);
+        debug_assert!(val >= 1);
+    }
+
+    #[inline]
+    unsafe fn unlock(&self, new_layout: Option<ImageLayout>) {
+        if let Some(new_layout) = new_layout {
+            debug_assert_eq!(new_layout, ImageLayout::General);
+            self.initialized.store(true, Ordering::SeqCst);
+        }
+
+        let prev_val = self.gpu_lock.fetch_sub(1, Ordering::SeqCst);
+        debug_assert!(prev_val >= 1);
+    }
+
+    #[inline]
+    unsafe fn layout_initialized(&self) {
+        self.initialized.store(true, Ordering::SeqCst);
+    }
+
+    #[inline]
+    fn is_layout_initialized(&self) -> bool {
+        self.initialized.load(Ordering::SeqCst)
+    }
+
+    #[inline]
+    fn current_miplevels_access(&self) -> std::ops::Range<u32> {
+        0..1
+    }
+
+    #[inline]
+    fn current_layer_levels_access(&self) -> std::ops::Range<u32> {
+        0..1
+    }
+}
+
+unsafe impl ImageClearValue<ClearValue> for DmaBufImage {
+    #[inline]
+    fn decode(&self, value: ClearValue) -> Option<ClearValue> {
+        Some(self.image.format().decode_clear_value(value))
+    }
+}
+
+unsafe impl<P> ImageContent<P> for DmaBufImage {
+    #[inline]
+    fn matches_format(&self) -> bool {
+        true // FIXME:
+    }
+}
+
+impl PartialEq for DmaBufImage {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        ImageAccess::inner(self) == ImageAccess::inner(other)
+    }
+}
+
+impl Eq for DmaBufImage {}
+
+impl Hash for DmaBufImage {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        ImageAccess::inner(self).hash(state);
+    }
+}
+
+/// Fixed set of dma-buf frames that are exchanged with a producer, such as the buffers of a
+/// V4L2 capture queue exported with `VIDIOC_EXPBUF`, or the buffer pool of a GStreamer element.
+///
+/// Each frame is identified by its index, and cycles between the producer and vulkano:
+///
+/// - When the producer hands a frame over, call `acquire` with the sync file that is signaled
+///   once the producer has finished writing it, if there is one. The returned future must be
+///   waited on by the first submission that uses the image of the frame.
+/// - After submitting the last operation that uses the image, call `release` on the same queue.
+///   It returns a sync file that is signaled once these operations are finished, which the
+///   producer must wait for before writing the frame again.
+///
+/// A V4L2 capture queue doesn't use sync files: frames can be used as soon as they are
+/// dequeued, and the sync file returned by `release` must be waited on with `poll` before the
+/// frame is queued again.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::fs::File;
+/// # use std::sync::Arc;
+/// # use vulkano::device::Device;
+/// # use vulkano::device::Queue;
+/// # use vulkano::format::Format;
+/// # use vulkano::image::ImageUsage;
+/// use vulkano::image::DmaBufRing;
+///
+/// # let device: Arc<Device> = return;
+/// # let queue: Arc<Queue> = return;
+/// # let buffers: Vec<File> = return;
+/// let usage = ImageUsage {
+///     sampled: true,
+///     ..ImageUsage::none()
+/// };
+/// let ring = unsafe {
+///     DmaBufRing::new(device.clone(), buffers, [1280, 720], Format::B8G8R8A8Unorm, usage, 5120)
+/// }
+/// .unwrap();
+///
+/// // When frame 0 has been dequeued from the producer:
+/// let acquire = unsafe { ring.acquire(0, None) }.unwrap();
+///
+/// // ... record a command buffer that samples `ring.image(0)` and submit it, after `acquire` if
+/// // it is `Some` ...
+///
+/// let release_fd = ring.release(0, &queue).unwrap();
+/// // ... wait for `release_fd`, then give frame 0 back to the producer ...
+/// ```
+#[derive(Debug)]
+pub struct DmaBufRing {
+    device: Arc<Device>,
+    frames: Vec<DmaBufFrame>,
+}
+
+#[derive(Debug)]
+struct DmaBufFrame {
+    image: Arc<DmaBufImage>,
+    // Semaphore into which the sync files of the producer are imported.
+    acquire_semaphore: Arc<Semaphore>,
+    // Semaphore from which the sync files of `release` are exported.
+    release_semaphore: Semaphore,
+}
+
+impl DmaBufRing {
+    /// Imports the dma-bufs of a producer as the frames of the ring, in the order of their
+    /// indices. All of them share the same dimensions, format and row pitch.
+    ///
+    /// The `khr_external_semaphore_fd` extension must be enabled on the device, in addition to
+    /// the extensions required by `DmaBufImage::import`.
+    ///
+    /// # Safety
+    ///
+    /// The same as `DmaBufImage::import`, for each of the dma-bufs.
+    ///
+    pub unsafe fn new<I>(
+        device: Arc<Device>,
+        fds: I,
+        dimensions: [u32; 2],
+        format: Format,
+        usage: ImageUsage,
+        row_pitch: DeviceSize,
+    ) -> Result<DmaBufRing, DmaBufImportError>
+    where
+        I: IntoIterator<Item = File>,
+    {
+        let frames = fds
+            .into_iter()
+            .map(|fd| {
+                Ok(DmaBufFrame {
+                    image: DmaBufImage::import(
+                        device.clone(),
+                        fd,
+                        dimensions,
+                        format,
+                        usage,
+                        row_pitch,
+                    )?,
+                    acquire_semaphore: Arc::new(Semaphore::alloc(device.clone())?),
+                    release_semaphore: Semaphore::alloc_with_exportable_sync_fd(device.clone())?,
+                })
+            })
+            .collect::<Result<_, DmaBufImportError>>()?;
+
+        Ok(DmaBufRing { device, frames })
+    }
+
+    /// Returns the number of frames.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns true if the ring doesn't have any frame.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns the image of a frame.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `index` is out of range.
+    #[inline]
+    pub fn image(&self, index: usize) -> &Arc<DmaBufImage> {
+        &self.frames[index].image
+    }
+
+    /// Acquires a frame from the producer.
+    ///
+    /// If `fence` is a sync file, the returned future represents the moment when it's
+    /// signaled. Returns `None` if there isn't any sync file, in which case the frame can be
+    /// used immediately.
+    ///
+    /// # Safety
+    ///
+    /// - `fence` must be a sync file.
+    /// - The future returned by the previous acquisition of this frame must have been waited
+    ///   on by a submission.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `index` is out of range.
+    pub unsafe fn acquire(
+        &self,
+        index: usize,
+        fence: Option<File>,
+    ) -> Result<Option<SemaphoreWaitFuture>, SemaphoreError> {
+        let frame = &self.frames[index];
+
+        match fence {
+            Some(fence) => {
+                frame.acquire_semaphore.import_sync_fd(fence)?;
+                Ok(Some(wait_semaphore(frame.acquire_semaphore.clone())))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Releases a frame to the producer, and returns a sync file that is signaled once all the
+    /// operations submitted to `queue` so far are finished.
+    ///
+    /// The operations that use the image of the frame must have been submitted to `queue`. The
+    /// caller owns the returned file.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `index` is out of range.
+    pub fn release(&self, index: usize, queue: &Queue) -> Result<File, DmaBufReleaseError> {
+        let frame = &self.frames[index];
+
+        unsafe {
+            let mut builder = SubmitCommandBufferBuilder::new();
+            builder.add_signal_semaphore(&frame.release_semaphore);
+            builder.submit(queue)?;
+        }
+
+        // The signal operation of the semaphore has just been submitted.
+        Ok(unsafe { frame.release_semaphore.export_sync_fd()? })
+    }
+}
+
+unsafe impl DeviceOwned for DmaBufRing {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+/// Error that can happen when importing a dma-buf.
+#[derive(Clone, Debug)]
+pub enum DmaBufImportError {
+    /// An extension is missing.
+    MissingExtension(&'static str),
+    /// The file descriptor isn't a valid dma-buf.
+    InvalidHandle,
+    /// The row pitch of the dma-buf is different from the one of a linear image.
+    IncompatibleRowPitch {
+        /// The row pitch of the dma-buf.
+        requested: DeviceSize,
+        /// The row pitch required by the implementation.
+        required: DeviceSize,
+    },
+    /// None of the memory types that the dma-buf can be imported into can be bound to the image.
+    NoCompatibleMemoryType,
+    /// Error while creating the image.
+    ImageCreationError(ImageCreationError),
+    /// Error while importing the memory.
+    DeviceMemoryAllocError(DeviceMemoryAllocError),
+    /// Error while creating the semaphores of a `DmaBufRing`.
+    SemaphoreError(SemaphoreError),
+    /// Not enough memory.
+    OomError(OomError),
+}
+
+impl error::Error for DmaBufImportError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            DmaBufImportError::ImageCreationError(ref err) => Some(err),
+            DmaBufImportError::DeviceMemoryAllocError(ref err) => Some(err),
+            DmaBufImportError::SemaphoreError(ref err) => Some(err),
+            DmaBufImportError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DmaBufImportError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            DmaBufImportError::MissingExtension(ext) => {
+                write!(fmt, "the extension {} must be enabled", ext)
+            }
+            DmaBufImportError::InvalidHandle => {
+                write!(fmt, "the file descriptor isn't a valid dma-buf")
+            }
+            DmaBufImportError::IncompatibleRowPitch {
+                requested,
+                required,
+            } => write!(
+                fmt,
+                "the row pitch of the dma-buf ({}) is different from the one required by the \
+                 implementation ({})",
+                requested, required
+            ),
+            DmaBufImportError::NoCompatibleMemoryType => write!(
+                fmt,
+                "none of the memory types of the dma-buf can be bound to the image"
+            ),
+            DmaBufImportError::ImageCreationError(_) => {
+                write!(fmt, "error while creating the image")
+            }
+            DmaBufImportError::DeviceMemoryAllocError(_) => {
+                write!(fmt, "error while importing the memory")
+            }
+            DmaBufImportError::SemaphoreError(_) => {
+                write!(fmt, "error while creating the semaphores")
+            }
+            DmaBufImportError::OomError(_) => write!(fmt, "not enough memory"),
+        }
+    }
+}
+
+impl From<ImageCreationError> for DmaBufImportError {
+    #[inline]
+    fn from(err: ImageCreationError) -> DmaBufImportError {
+        DmaBufImportError::ImageCreationError(err)
+    }
+}
+
+impl From<DeviceMemoryAllocError> for DmaBufImportError {
+    #[inline]
+    fn from(err: DeviceMemoryAllocError) -> DmaBufImportError {
+        DmaBufImportError::DeviceMemoryAllocError(err)
+    }
+}
+
+impl From<SemaphoreError> for DmaBufImportError {
+    #[inline]
+    fn from(err: SemaphoreError) -> DmaBufImportError {
+        DmaBufImportError::SemaphoreError(err)
+    }
+}
+
+impl From<OomError> for DmaBufImportError {
+    #[inline]
+    fn from(err: OomError) -> DmaBufImportError {
+        DmaBufImportError::OomError(err)
+    }
+}
+
+/// Error that can happen when releasing a frame of a `DmaBufRing`.
+#[derive(Clone, Debug)]
+pub enum DmaBufReleaseError {
+    /// Error while submitting the signal operation.
+    SubmitCommandBufferError(SubmitCommandBufferError),
+    /// Error while exporting the sync file.
+    SemaphoreError(SemaphoreError),
+}
+
+impl error::Error for DmaBufReleaseError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            DmaBufReleaseError::SubmitCommandBufferError(ref err) => Some(err),
+            DmaBufReleaseError::SemaphoreError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for DmaBufReleaseError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                DmaBufReleaseError::SubmitCommandBufferError(_) => {
+                    "error while submitting the signal operation"
+                }
+                DmaBufReleaseError::SemaphoreError(_) => "error while exporting the sync file",
+            }
+        )
+    }
+}
+
+impl From<SubmitCommandBufferError> for DmaBufReleaseError {
+    #[inline]
+    fn from(err: SubmitCommandBufferError) -> DmaBufReleaseError {
+        DmaBufReleaseError::SubmitCommandBufferError(err)
+    }
+}
+
+impl From<SemaphoreError> for DmaBufReleaseError {
+    #[inline]
+    fn from(err: SemaphoreError) -> DmaBufReleaseError {
+        DmaBufReleaseError::SemaphoreError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::Format;
+    use crate::image::DmaBufImage;
+    use crate::image::DmaBufImportError;
+    use crate::image::ImageUsage;
+    use std::fs::File;
+
+    #[test]
+    fn missing_extension() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let fd = match File::open("/dev/null") {
+            Ok(fd) => fd,
+            Err(_) => return,
+        };
+        let usage = ImageUsage {
+            sampled: true,
+            ..ImageUsage::none()
+        };
+
+        match unsafe { DmaBufImage::import(device, fd, [16, 16], Format::R8G8B8A8Unorm, usage, 64) }
+        {
+            Err(DmaBufImportError::MissingExtension(_)) => (),
+            _ => panic!(),
+        }
+    }
+}
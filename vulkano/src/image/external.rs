@@ -0,0 +1,347 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::device::DebugName;
+use crate::device::Device;
+use crate::format::ClearValue;
+use crate::format::Format;
+use crate::image::sys::UnsafeImage;
+use crate::image::traits::ImageAccess;
+use crate::image::traits::ImageClearValue;
+use crate::image::traits::ImageContent;
+use crate::image::ImageCreateFlags;
+use crate::image::ImageDescriptorLayouts;
+use crate::image::ImageDimensions;
+use crate::image::ImageInner;
+use crate::image::ImageLayout;
+use crate::image::ImageUsage;
+use crate::image::SampleCount;
+use crate::sync::AccessError;
+use crate::OomError;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// Image that was created by another API or library, for example a swapchain image of an
+/// OpenXR runtime.
+///
+/// Vulkano doesn't destroy the image nor manage its memory. The image is expected to be in a
+/// specific layout, given at construction, every time it's handed over between vulkano and the
+/// other API. Vulkano transitions the image from this layout at the start of each command buffer
+/// that uses it, and back to it at the end.
+///
+/// # OpenXR
+///
+/// With `XR_KHR_vulkan_enable2`, the instance and the device are created by the runtime with
+/// `xrCreateVulkanInstanceKHR` and `xrCreateVulkanDeviceKHR`, and wrapped with
+/// `Instance::from_handle` and `Device::from_handle`. The physical device chosen by the runtime
+/// with `xrGetVulkanGraphicsDevice2KHR` is found with `PhysicalDevice::from_handle`. The API
+/// version of the instance must be within the range returned by
+/// `xrGetVulkanGraphicsRequirements2KHR`.
+///
+/// The images returned by `xrEnumerateSwapchainImages` are then wrapped with `from_handle`,
+/// using the parameters that the swapchain was created with. The runtime expects color images to
+/// be in the `ColorAttachmentOptimal` layout and depth images to be in the
+/// `DepthStencilAttachmentOptimal` layout when they are released with
+/// `xrReleaseSwapchainImage`. Each image must only be used between `xrWaitSwapchainImage` and
+/// `xrReleaseSwapchainImage`, and the command buffers that use it must have finished executing
+/// before releasing it.
+#[derive(Debug)]
+pub struct ExternalImage {
+    // Inner implementation.
+    image: UnsafeImage,
+
+    // Layout that the image must be in when it's handed over to the other API.
+    layout: ImageLayout,
+
+    // If true, then the image is in `layout`. If false, then it is still `Undefined`.
+    initialized: AtomicBool,
+
+    // Number of times this image is locked on the GPU side.
+    gpu_lock: AtomicUsize,
+}
+
+impl ExternalImage {
+    /// Wraps an image created by another API.
+    ///
+    /// The image is considered to be in the `Undefined` layout until it's used for the first
+    /// time.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid image of `device`, created with the given dimensions, format
+    ///   and usage, with a single mipmap level and a single sample, and bound to memory.
+    /// - The image must stay alive for as long as the `ExternalImage` exists.
+    /// - Once it has been used by vulkano, the image must be in `layout` every time it's used
+    ///   by vulkano, and the accesses of the other API must be synchronized with those of
+    ///   vulkano.
+    ///
+    pub unsafe fn from_handle(
+        device: Arc<Device>,
+        handle: ash::vk::Image,
+        dimensions: ImageDimensions,
+        format: Format,
+        usage: ImageUsage,
+        layout: ImageLayout,
+    ) -> Arc<ExternalImage> {
+        let image = UnsafeImage::from_raw(
+            device,
+            handle,
+            usage,
+            format,
+            ImageCreateFlags::none(),
+            dimensions,
+            SampleCount::Sample1,
+            1,
+        );
+
+        Arc::new(ExternalImage {
+            image,
+            layout,
+            initialized: AtomicBool::new(false),
+            gpu_lock: AtomicUsize::new(0),
+        })
+    }
+
+    /// Returns the dimensions of the image.
+    #[inline]
+    pub fn dimensions(&self) -> ImageDimensions {
+        self.image.dimensions()
+    }
+
+    /// Returns the layout that the image is in when it's handed over to the other API.
+    #[inline]
+    pub fn layout(&self) -> ImageLayout {
+        self.layout
+    }
+}
+
+impl DebugName for ExternalImage {
+    #[inline]
+    fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        self.image.set_debug_name(name)
+    }
+}
+
+unsafe impl ImageAccess for ExternalImage {
+    #[inline]
+    fn inner(&self) -> ImageInner {
+        ImageInner {
+            image: &self.image,
+            first_layer: 0,
+            num_layers: self.image.dimensions().array_layers() as usize,
+            first_mipmap_level: 0,
+            num_mipmap_levels: 1,
+        }
+    }
+
+    #[inline]
+    fn initial_layout_requirement(&self) -> ImageLayout {
+        self.layout
+    }
+
+    #[inline]
+    fn final_layout_requirement(&self) -> ImageLayout {
+        self.layout
+    }
+
+    #[inline]
+    fn descriptor_layouts(&self) -> Option<ImageDescriptorLayouts> {
+        Some(ImageDescriptorLayouts {
+            storage_image: ImageLayout::General,
+            combined_image_sampler: ImageLayout::ShaderReadOnlyOptimal,
+            sampled_image: ImageLayout::ShaderReadOnlyOptimal,
+            input_attachment: ImageLayout::ShaderReadOnlyOptimal,
+        })
+    }
+
+    #[inline]
+    fn conflict_key(&self) -> u64 {
+        self.image.key()
+    }
+
+    #[inline]
+    fn try_gpu_lock(
+        &self,
+        _: bool,
+        uninitialized_safe: bool,
+        expected_layout: ImageLayout,
+    ) -> Result<(), AccessError> {
+        if expected_layout != self.layout && expected_layout != ImageLayout::Undefined {
+            if self.initialized.load(Ordering::SeqCst) {
+                return Err(AccessError::UnexpectedImageLayout {
+                    requested: expected_layout,
+                    allowed: self.layout,
+                });
+            } else {
+                return Err(AccessError::UnexpectedImageLayout {
+                    requested: expected_layout,
+                    allowed: ImageLayout::Undefined,
+                });
+            }
+        }
+
+        if !uninitialized_safe
+            && expected_layout != ImageLayout::Undefined
+            && !self.initialized.load(Ordering::SeqCst)
+        {
+            return Err(AccessError::ImageNotInitialized {
+                requested: expected_layout,
+            });
+        }
+
+        if self
+            .gpu_lock
+            .compare_exchange(0, 1, Ordering::SeqCst, Ordering::SeqCst)
+            .unwrap_or_else(|e| e)
+            == 0
+        {
+            Ok(())
+        } else {
+            Err(AccessError::AlreadyInUse)
+        }
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self) {
+        let val = self.gpu_lock.fetch_add(1, Ordering::SeqCst);
+        debug_assert!(val >= 1);
+    }
+
+    #[inline]
+    unsafe fn unlock(&self, new_layout: Option<ImageLayout>) {
+        if let Some(new_layout) = new_layout {
+            debug_assert_eq!(new_layout, self.layout);
+            self.initialized.store(true, Ordering::SeqCst);
+        }
+
+        let prev_val = self.gpu_lock.fetch_sub(1, Ordering::SeqCst);
+        debug_assert!(prev_val >= 1);
+    }
+
+    #[inline]
+    unsafe fn layout_initialized(&self) {
+        self.initialized.store(true, Ordering::SeqCst);
+    }
+
+    #[inline]
+    fn is_layout_initialized(&self) -> bool {
+        self.initialized.load(Ordering::SeqCst)
+    }
+
+    #[inline]
+    fn current_miplevels_access(&self) -> std::ops::Range<u32> {
+        0..1
+    }
+
+    #[inline]
+    fn current_layer_levels_access(&self) -> std::ops::Range<u32> {
+        0..self.image.dimensions().array_layers()
+    }
+}
+
+unsafe impl ImageClearValue<ClearValue> for ExternalImage {
+    #[inline]
+    fn decode(&self, value: ClearValue) -> Option<ClearValue> {
+        Some(self.image.format().decode_clear_value(value))
+    }
+}
+
+unsafe impl<P> ImageContent<P> for ExternalImage {
+    #[inline]
+    fn matches_format(&self) -> bool {
+        true // FIXME:
+    }
+}
+
+impl PartialEq for ExternalImage {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        ImageAccess::inner(self) == ImageAccess::inner(other)
+    }
+}
+
+impl Eq for ExternalImage {}
+
+impl Hash for ExternalImage {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        ImageAccess::inner(self).hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::Format;
+    use crate::image::sys::UnsafeImage;
+    use crate::image::ExternalImage;
+    use crate::image::ImageAccess;
+    use crate::image::ImageCreateFlags;
+    use crate::image::ImageDimensions;
+    use crate::image::ImageLayout;
+    use crate::image::ImageUsage;
+    use crate::image::SampleCount;
+    use crate::sync::Sharing;
+    use crate::VulkanObject;
+    use std::iter::Empty;
+
+    #[test]
+    fn layout_requirements() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = ImageUsage {
+            color_attachment: true,
+            ..ImageUsage::none()
+        };
+        let dimensions = ImageDimensions::Dim2d {
+            width: 32,
+            height: 32,
+            array_layers: 1,
+        };
+        let (raw_image, _) = unsafe {
+            UnsafeImage::new(
+                device.clone(),
+                usage,
+                Format::R8G8B8A8Unorm,
+                ImageCreateFlags::none(),
+                dimensions,
+                SampleCount::Sample1,
+                1,
+                Sharing::Exclusive::<Empty<_>>,
+                false,
+                false,
+            )
+        }
+        .unwrap();
+
+        let image = unsafe {
+            ExternalImage::from_handle(
+                device,
+                raw_image.internal_object(),
+                dimensions,
+                Format::R8G8B8A8Unorm,
+                usage,
+                ImageLayout::ColorAttachmentOptimal,
+            )
+        };
+
+        assert_eq!(
+            image.initial_layout_requirement(),
+            ImageLayout::ColorAttachmentOptimal
+        );
+        assert_eq!(
+            image.final_layout_requirement(),
+            ImageLayout::ColorAttachmentOptimal
+        );
+        assert!(!image.is_layout_initialized());
+    }
+}
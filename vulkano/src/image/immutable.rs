@@ -17,10 +17,12 @@ use crate::command_buffer::CommandBufferUsage;
 use crate::command_buffer::PrimaryAutoCommandBuffer;
 use crate::command_buffer::PrimaryCommandBuffer;
 use crate::device::physical::QueueFamily;
+use crate::device::DebugName;
 use crate::device::Device;
 use crate::device::Queue;
 use crate::format::Format;
 use crate::format::Pixel;
+use crate::image::conversion;
 use crate::image::sys::ImageCreationError;
 use crate::image::sys::UnsafeImage;
 use crate::image::traits::ImageAccess;
@@ -45,6 +47,8 @@ use crate::sampler::Filter;
 use crate::sync::AccessError;
 use crate::sync::NowFuture;
 use crate::sync::Sharing;
+use crate::DeviceSize;
+use crate::OomError;
 use smallvec::SmallVec;
 use std::hash::Hash;
 use std::hash::Hasher;
@@ -178,6 +182,7 @@ fn generate_mipmaps<L, Img>(
 impl ImmutableImage {
     #[deprecated(note = "use ImmutableImage::uninitialized instead")]
     #[inline]
+    #[track_caller]
     pub fn new<'a, I>(
         device: Arc<Device>,
         dimensions: ImageDimensions,
@@ -199,6 +204,7 @@ impl ImmutableImage {
 
     #[deprecated(note = "use ImmutableImage::uninitialized instead")]
     #[inline]
+    #[track_caller]
     pub fn with_mipmaps<'a, I, M>(
         device: Arc<Device>,
         dimensions: ImageDimensions,
@@ -236,6 +242,7 @@ impl ImmutableImage {
     /// Builds an uninitialized immutable image.
     ///
     /// Returns two things: the image, and a special access that should be used for the initial upload to the image.
+    #[track_caller]
     pub fn uninitialized<'a, I, M>(
         device: Arc<Device>,
         dimensions: ImageDimensions,
@@ -316,6 +323,7 @@ impl ImmutableImage {
 
     /// Construct an ImmutableImage from the contents of `iter`.
     #[inline]
+    #[track_caller]
     pub fn from_iter<Px, I>(
         iter: I,
         dimensions: ImageDimensions,
@@ -342,7 +350,46 @@ impl ImmutableImage {
         ImmutableImage::from_buffer(source, dimensions, mipmaps, format, queue)
     }
 
+    /// Construct an ImmutableImage from RGB pixels with 8-bit channels, which are expanded to
+    /// opaque RGBA pixels while they are written to the staging buffer.
+    ///
+    /// `format` must have four 8-bit channels in this order, for example `R8G8B8A8_SRGB`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `format` doesn't have 4 bytes per texel.
+    /// - Panics if `data` doesn't have 3 bytes for each texel of `dimensions`.
+    #[track_caller]
+    pub fn from_rgb8(
+        data: &[u8],
+        dimensions: ImageDimensions,
+        mipmaps: MipmapsCount,
+        format: Format,
+        queue: Arc<Queue>,
+    ) -> Result<
+        (
+            Arc<Self>,
+            CommandBufferExecFuture<NowFuture, PrimaryAutoCommandBuffer>,
+        ),
+        ImageCreationError,
+    > {
+        assert_eq!(format.size(), Some(4));
+        assert_eq!(data.len(), dimensions.num_texels() as usize * 3);
+
+        let source = unsafe {
+            CpuAccessibleBuffer::<[u8]>::uninitialized_array(
+                queue.device().clone(),
+                (data.len() / 3 * 4) as DeviceSize,
+                BufferUsage::transfer_source(),
+                false,
+            )?
+        };
+        conversion::rgb_to_rgba(data, &mut source.write().unwrap(), 0xff);
+        ImmutableImage::from_buffer(source, dimensions, mipmaps, format, queue)
+    }
+
     /// Construct an ImmutableImage containing a copy of the data in `source`.
+    #[track_caller]
     pub fn from_buffer<B, Px>(
         source: B,
         dimensions: ImageDimensions,
@@ -442,6 +489,13 @@ impl<A> ImmutableImage<A> {
     }
 }
 
+impl<A> DebugName for ImmutableImage<A> {
+    #[inline]
+    fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        self.image.set_debug_name(name)
+    }
+}
+
 unsafe impl<A> ImageAccess for ImmutableImage<A> {
     #[inline]
     fn inner(&self) -> ImageInner {
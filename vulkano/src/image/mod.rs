@@ -48,32 +48,53 @@
 
 pub use self::aspect::ImageAspect;
 pub use self::aspect::ImageAspects;
+pub use self::atlas::AtlasAllocator;
+pub use self::atlas::AtlasFrame;
+pub use self::atlas::AtlasRegion;
+pub use self::atlas::TextureAtlas;
 pub use self::attachment::AttachmentImage;
+#[cfg(target_os = "linux")]
+pub use self::dma_buf::{DmaBufImage, DmaBufImportError, DmaBufReleaseError, DmaBufRing};
+pub use self::external::ExternalImage;
 pub use self::immutable::ImmutableImage;
 pub use self::layout::ImageDescriptorLayouts;
 pub use self::layout::ImageLayout;
 pub use self::storage::StorageImage;
 pub use self::swapchain::SwapchainImage;
 pub use self::sys::ImageCreationError;
+pub use self::texture::Texture;
+pub use self::texture::TextureCreationError;
+pub use self::texture::TextureUploadFuture;
+pub use self::texture::TextureView;
 pub use self::traits::ImageAccess;
 pub use self::traits::ImageInner;
+pub use self::upload::ImageUploadError;
+pub use self::upload::ImageUploader;
 pub use self::usage::ImageUsage;
 pub use self::view::ImageViewAbstract;
 use std::cmp;
 use std::convert::TryFrom;
 
 mod aspect;
+mod atlas;
 pub mod attachment; // TODO: make private
+pub mod conversion;
+#[cfg(target_os = "linux")]
+mod dma_buf;
+mod external;
 pub mod immutable; // TODO: make private
 mod layout;
 mod storage;
 pub mod swapchain; // TODO: make private
 pub mod sys;
+mod texture;
 pub mod traits;
+mod upload;
 mod usage;
 pub mod view;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u32)]
 pub enum SampleCount {
     Sample1 = ash::vk::SampleCountFlags::TYPE_1.as_raw(),
@@ -130,6 +151,7 @@ impl TryFrom<u32> for SampleCount {
 
 /// Specifies how many sample counts supported for an image used for storage operations.
 #[derive(Debug, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SampleCounts {
     // specify an image with one sample per pixel
     pub sample1: bool,
@@ -8,6 +8,7 @@
 // according to those terms.
 
 use crate::device::physical::QueueFamily;
+use crate::device::DebugName;
 use crate::device::Device;
 use crate::format::ClearValue;
 use crate::format::Format;
@@ -32,9 +33,15 @@ use crate::memory::pool::MemoryPoolAlloc;
 use crate::memory::pool::PotentialDedicatedAllocation;
 use crate::memory::pool::StdMemoryPool;
 use crate::memory::DedicatedAlloc;
+use crate::memory::DeviceMemoryAllocError;
+use crate::memory::ExternalMemoryHandleType;
+use crate::pnext::PNextChain;
 use crate::sync::AccessError;
 use crate::sync::Sharing;
+use crate::DeviceSize;
+use crate::OomError;
 use smallvec::SmallVec;
+use std::fs::File;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::sync::atomic::AtomicUsize;
@@ -70,6 +77,7 @@ where
 impl StorageImage {
     /// Creates a new image with the given dimensions and format.
     #[inline]
+    #[track_caller]
     pub fn new<'a, I>(
         device: Arc<Device>,
         dimensions: ImageDimensions,
@@ -103,6 +111,7 @@ impl StorageImage {
     }
 
     /// Same as `new`, but allows specifying the usage.
+    #[track_caller]
     pub fn with_usage<'a, I>(
         device: Arc<Device>,
         dimensions: ImageDimensions,
@@ -168,6 +177,117 @@ impl StorageImage {
             gpu_lock: AtomicUsize::new(0),
         }))
     }
+
+    /// Same as `with_usage`, but the memory of the image can be exported as a posix file
+    /// descriptor with `export_posix_fd`, for example to share the image with OpenGL.
+    ///
+    /// The `khr_external_memory` and `khr_external_memory_fd` extensions must be enabled on the
+    /// device. Only works on Linux.
+    ///
+    /// # OpenGL interop
+    ///
+    /// With the `GL_EXT_memory_object_fd` extension, the memory is imported with
+    /// `glImportMemoryFdEXT`, passing `memory_size` and `GL_HANDLE_TYPE_OPAQUE_FD_EXT`. OpenGL
+    /// takes ownership of the file descriptor, so it must not be closed afterwards. The texture
+    /// is then created with `glTexStorageMem2DEXT` at `memory_offset`, with the same dimensions,
+    /// format and number of mipmaps as the image, and with optimal tiling.
+    ///
+    /// The accesses of both APIs must be synchronized with semaphores created with
+    /// `Semaphore::alloc_with_exportable_fd` and imported with `GL_EXT_semaphore_fd`. The layout
+    /// passed to `glWaitSemaphoreEXT` and `glSignalSemaphoreEXT` must correspond to the layout
+    /// that the image is in on the Vulkan side when the semaphore is signaled or waited on.
+    ///
+    /// The image must be kept alive for as long as OpenGL uses the memory.
+    #[cfg(target_os = "linux")]
+    #[track_caller]
+    pub fn new_with_exportable_fd<'a, I>(
+        device: Arc<Device>,
+        dimensions: ImageDimensions,
+        format: Format,
+        usage: ImageUsage,
+        flags: ImageCreateFlags,
+        queue_families: I,
+    ) -> Result<Arc<StorageImage>, ImageCreationError>
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        assert!(device.enabled_extensions().khr_external_memory_fd);
+        assert!(device.enabled_extensions().khr_external_memory);
+
+        let queue_families = queue_families
+            .into_iter()
+            .map(|f| f.id())
+            .collect::<SmallVec<[u32; 4]>>();
+
+        let mut external_memory_info = ash::vk::ExternalMemoryImageCreateInfo {
+            handle_types: ExternalMemoryHandleType::posix().into(),
+            ..Default::default()
+        };
+
+        let (image, mem_reqs) = unsafe {
+            let sharing = if queue_families.len() >= 2 {
+                Sharing::Concurrent(queue_families.iter().cloned())
+            } else {
+                Sharing::Exclusive
+            };
+
+            let mut p_next_chain = PNextChain::<ash::vk::ImageCreateInfo>::new();
+            p_next_chain.push(&mut external_memory_info);
+
+            UnsafeImage::with_p_next_chain(
+                device.clone(),
+                usage,
+                format,
+                flags,
+                dimensions,
+                SampleCount::Sample1,
+                1,
+                sharing,
+                false,
+                false,
+                p_next_chain,
+            )?
+        };
+
+        let memory = MemoryPool::alloc_from_requirements_with_exportable_fd(
+            &Device::standard_pool(&device),
+            &mem_reqs,
+            AllocLayout::Optimal,
+            MappingRequirement::DoNotMap,
+            DedicatedAlloc::Image(&image),
+            |t| {
+                if t.is_device_local() {
+                    AllocFromRequirementsFilter::Preferred
+                } else {
+                    AllocFromRequirementsFilter::Allowed
+                }
+            },
+        )?;
+        debug_assert!((memory.offset() % mem_reqs.alignment) == 0);
+        unsafe {
+            image.bind_memory(memory.memory(), memory.offset())?;
+        }
+
+        Ok(Arc::new(StorageImage {
+            image,
+            memory,
+            dimensions,
+            format,
+            queue_families,
+            gpu_lock: AtomicUsize::new(0),
+        }))
+    }
+
+    /// Exports posix file descriptor for the allocated memory.
+    ///
+    /// The image must have been created with `new_with_exportable_fd`. Only works on Linux.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn export_posix_fd(&self) -> Result<File, DeviceMemoryAllocError> {
+        self.memory
+            .memory()
+            .export_fd(ExternalMemoryHandleType::posix())
+    }
 }
 
 impl<A> StorageImage<A>
@@ -179,6 +299,30 @@ where
     pub fn dimensions(&self) -> ImageDimensions {
         self.dimensions
     }
+
+    /// Returns the size of the memory object that the image is bound to.
+    ///
+    /// The memory object can be larger than the image if it's shared with other resources.
+    #[inline]
+    pub fn memory_size(&self) -> DeviceSize {
+        self.memory.memory().size()
+    }
+
+    /// Returns the offset of the image in the memory object that it is bound to.
+    #[inline]
+    pub fn memory_offset(&self) -> DeviceSize {
+        self.memory.offset()
+    }
+}
+
+impl<A> DebugName for StorageImage<A>
+where
+    A: MemoryPool,
+{
+    #[inline]
+    fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        self.image.set_debug_name(name)
+    }
 }
 
 unsafe impl<A> ImageAccess for StorageImage<A>
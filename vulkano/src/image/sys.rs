@@ -15,6 +15,7 @@
 
 use crate::check_errors;
 use crate::device::Device;
+use crate::device::DeviceOwned;
 use crate::format::Format;
 use crate::format::FormatFeatures;
 use crate::format::FormatTy;
@@ -27,6 +28,7 @@ use crate::image::SampleCount;
 use crate::memory::DeviceMemory;
 use crate::memory::DeviceMemoryAllocError;
 use crate::memory::MemoryRequirements;
+use crate::pnext::PNextChain;
 use crate::sync::Sharing;
 use crate::DeviceSize;
 use crate::Error;
@@ -41,6 +43,7 @@ use std::hash::Hash;
 use std::hash::Hasher;
 use std::mem::MaybeUninit;
 use std::ops::Range;
+use std::panic::Location;
 use std::ptr;
 use std::sync::Arc;
 
@@ -86,6 +89,7 @@ impl UnsafeImage {
     /// - Panics if the number of samples is 0.
     ///
     #[inline]
+    #[track_caller]
     pub unsafe fn new<'a, Mi, I>(
         device: Arc<Device>,
         usage: ImageUsage,
@@ -98,6 +102,54 @@ impl UnsafeImage {
         linear_tiling: bool,
         preinitialized_layout: bool,
     ) -> Result<(UnsafeImage, MemoryRequirements), ImageCreationError>
+    where
+        Mi: Into<MipmapsCount>,
+        I: Iterator<Item = u32>,
+    {
+        UnsafeImage::with_p_next_chain(
+            device,
+            usage,
+            format,
+            flags,
+            dimensions,
+            num_samples,
+            mipmaps,
+            sharing,
+            linear_tiling,
+            preinitialized_layout,
+            PNextChain::new(),
+        )
+    }
+
+    /// Same as `new`, but also appends structures to the `pNext` chain of the
+    /// `VkImageCreateInfo` structure. See the `pnext` module.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the dimensions is 0.
+    /// - Panics if the number of mipmaps is 0.
+    /// - Panics if the number of samples is 0.
+    ///
+    /// # Safety
+    ///
+    /// The structures of `p_next_chain` must be valid, and the extensions that define them must
+    /// be enabled on the device. Vulkano doesn't take them into account when it checks the
+    /// capabilities of the physical device for the image.
+    ///
+    #[track_caller]
+    pub unsafe fn with_p_next_chain<Mi, I>(
+        device: Arc<Device>,
+        usage: ImageUsage,
+        format: Format,
+        flags: ImageCreateFlags,
+        dimensions: ImageDimensions,
+        num_samples: SampleCount,
+        mipmaps: Mi,
+        sharing: Sharing<I>,
+        linear_tiling: bool,
+        preinitialized_layout: bool,
+        p_next_chain: PNextChain<ash::vk::ImageCreateInfo>,
+    ) -> Result<(UnsafeImage, MemoryRequirements), ImageCreationError>
     where
         Mi: Into<MipmapsCount>,
         I: Iterator<Item = u32>,
@@ -107,7 +159,7 @@ impl UnsafeImage {
             Sharing::Concurrent(ids) => (ash::vk::SharingMode::CONCURRENT, ids.collect()),
         };
 
-        UnsafeImage::new_impl(
+        let (image, mem_reqs) = UnsafeImage::new_impl(
             device,
             usage,
             format,
@@ -118,7 +170,11 @@ impl UnsafeImage {
             sharing,
             linear_tiling,
             preinitialized_layout,
-        )
+            p_next_chain,
+        )?;
+        image.device.auto_debug_name(&image, Location::caller());
+
+        Ok((image, mem_reqs))
     }
 
     // Non-templated version to avoid inlining and improve compile times.
@@ -133,6 +189,7 @@ impl UnsafeImage {
         (sh_mode, sh_indices): (ash::vk::SharingMode, SmallVec<[u32; 8]>),
         linear_tiling: bool,
         preinitialized_layout: bool,
+        mut p_next_chain: PNextChain<ash::vk::ImageCreateInfo>,
     ) -> Result<(UnsafeImage, MemoryRequirements), ImageCreationError> {
         // TODO: doesn't check that the proper features are enabled
 
@@ -537,6 +594,7 @@ impl UnsafeImage {
         // Everything now ok. Creating the image.
         let image = {
             let infos = ash::vk::ImageCreateInfo {
+                p_next: p_next_chain.link(ptr::null()),
                 flags: flags.into(),
                 image_type: ty,
                 format: format.into(),
@@ -889,6 +947,13 @@ impl UnsafeImage {
     }
 }
 
+unsafe impl DeviceOwned for UnsafeImage {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
 unsafe impl VulkanObject for UnsafeImage {
     type Object = ash::vk::Image;
 
@@ -0,0 +1,292 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::command_buffer::CommandBufferExecFuture;
+use crate::command_buffer::PrimaryAutoCommandBuffer;
+use crate::device::Queue;
+use crate::format::Format;
+use crate::format::Pixel;
+use crate::image::view::ImageView;
+use crate::image::view::ImageViewCreationError;
+use crate::image::ImageAccess;
+use crate::image::ImageCreationError;
+use crate::image::ImageDimensions;
+use crate::image::ImmutableImage;
+use crate::image::MipmapsCount;
+use crate::sampler::Filter;
+use crate::sampler::MipmapMode;
+use crate::sampler::Sampler;
+use crate::sampler::SamplerAddressMode;
+use crate::sampler::SamplerCreationError;
+use crate::sync::NowFuture;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+/// An image that shaders sample, with its view and its sampler.
+///
+/// The constructors upload the texels with a staging buffer and generate the mipmaps, the same
+/// as `ImmutableImage::from_iter`, then create a view of the whole image and a sampler with
+/// linear filtering and the repeat mode. The future that they return must be executed before the
+/// texture is used. The sampler can be replaced with `with_sampler`.
+///
+/// The texture can then be bound with `PersistentDescriptorSetBuilder::add_texture`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use vulkano::descriptor_set::layout::DescriptorSetLayout;
+/// # use vulkano::device::Queue;
+/// # let queue: Arc<Queue> = return;
+/// # let layout: Arc<DescriptorSetLayout> = return;
+/// # let texels: Vec<u8> = return;
+/// use vulkano::descriptor_set::PersistentDescriptorSet;
+/// use vulkano::format::Format;
+/// use vulkano::image::ImageDimensions;
+/// use vulkano::image::Texture;
+/// use vulkano::sync::GpuFuture;
+///
+/// let dimensions = ImageDimensions::Dim2d {
+///     width: 256,
+///     height: 256,
+///     array_layers: 1,
+/// };
+/// let (texture, upload) = Texture::from_iter(
+///     texels.into_iter(),
+///     dimensions,
+///     Format::R8G8B8A8Srgb,
+///     queue.clone(),
+/// )
+/// .unwrap();
+///
+/// let set = PersistentDescriptorSet::start(layout)
+///     .add_texture(&texture)
+///     .unwrap()
+///     .build()
+///     .unwrap();
+///
+/// upload.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct Texture {
+    image: Arc<ImmutableImage>,
+    view: Arc<TextureView>,
+    sampler: Arc<Sampler>,
+}
+
+impl fmt::Debug for Texture {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("Texture")
+            .field("image", &self.image)
+            .field("sampler", &self.sampler)
+            .finish()
+    }
+}
+
+/// View of the image of a `Texture`.
+pub type TextureView = ImageView<Arc<ImmutableImage>>;
+
+/// Future of the upload of a `Texture`.
+pub type TextureUploadFuture = CommandBufferExecFuture<NowFuture, PrimaryAutoCommandBuffer>;
+
+impl Texture {
+    /// Builds a texture from the texels of its first mipmap level, and generates the other
+    /// levels.
+    #[track_caller]
+    pub fn from_iter<Px, I>(
+        iter: I,
+        dimensions: ImageDimensions,
+        format: Format,
+        queue: Arc<Queue>,
+    ) -> Result<(Texture, TextureUploadFuture), TextureCreationError>
+    where
+        Px: Pixel + Send + Sync + Clone + 'static,
+        I: ExactSizeIterator<Item = Px>,
+    {
+        let (image, future) =
+            ImmutableImage::from_iter(iter, dimensions, MipmapsCount::Log2, format, queue)?;
+        Ok((Texture::from_image(image)?, future))
+    }
+
+    /// Builds a texture from RGB texels with 8-bit channels, which are expanded to opaque RGBA
+    /// texels, and generates the mipmaps.
+    ///
+    /// # Panic
+    ///
+    /// - Panics for the same reasons as `ImmutableImage::from_rgb8`.
+    #[track_caller]
+    pub fn from_rgb8(
+        data: &[u8],
+        dimensions: ImageDimensions,
+        format: Format,
+        queue: Arc<Queue>,
+    ) -> Result<(Texture, TextureUploadFuture), TextureCreationError> {
+        let (image, future) =
+            ImmutableImage::from_rgb8(data, dimensions, MipmapsCount::Log2, format, queue)?;
+        Ok((Texture::from_image(image)?, future))
+    }
+
+    /// Builds a texture from an image that was already created, for example by an image loader
+    /// that uploads every mipmap level.
+    #[track_caller]
+    pub fn from_image(image: Arc<ImmutableImage>) -> Result<Texture, TextureCreationError> {
+        let device = image.inner().image.device().clone();
+        let view = ImageView::new(image.clone())?;
+        let sampler = Sampler::new(
+            device,
+            Filter::Linear,
+            Filter::Linear,
+            MipmapMode::Linear,
+            SamplerAddressMode::Repeat,
+            SamplerAddressMode::Repeat,
+            SamplerAddressMode::Repeat,
+            0.0,
+            1.0,
+            0.0,
+            image.mipmap_levels() as f32,
+        )?;
+
+        Ok(Texture {
+            image,
+            view,
+            sampler,
+        })
+    }
+
+    /// Replaces the sampler of the texture.
+    #[inline]
+    pub fn with_sampler(self, sampler: Arc<Sampler>) -> Texture {
+        Texture { sampler, ..self }
+    }
+
+    /// Returns the image of the texture.
+    #[inline]
+    pub fn image(&self) -> &Arc<ImmutableImage> {
+        &self.image
+    }
+
+    /// Returns the view of the whole image.
+    #[inline]
+    pub fn view(&self) -> &Arc<TextureView> {
+        &self.view
+    }
+
+    /// Returns the sampler of the texture.
+    #[inline]
+    pub fn sampler(&self) -> &Arc<Sampler> {
+        &self.sampler
+    }
+
+    /// Returns the dimensions of the image.
+    #[inline]
+    pub fn dimensions(&self) -> ImageDimensions {
+        self.image.dimensions()
+    }
+
+    /// Returns the number of mipmap levels of the image.
+    #[inline]
+    pub fn mipmap_levels(&self) -> u32 {
+        self.image.mipmap_levels()
+    }
+}
+
+/// Error that can happen when creating a `Texture`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextureCreationError {
+    /// Failed to create the image.
+    ImageCreationError(ImageCreationError),
+    /// Failed to create the view of the image.
+    ImageViewCreationError(ImageViewCreationError),
+    /// Failed to create the sampler.
+    SamplerCreationError(SamplerCreationError),
+}
+
+impl error::Error for TextureCreationError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            TextureCreationError::ImageCreationError(ref err) => Some(err),
+            TextureCreationError::ImageViewCreationError(ref err) => Some(err),
+            TextureCreationError::SamplerCreationError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for TextureCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                TextureCreationError::ImageCreationError(_) => "failed to create the image",
+                TextureCreationError::ImageViewCreationError(_) => {
+                    "failed to create the view of the image"
+                }
+                TextureCreationError::SamplerCreationError(_) => "failed to create the sampler",
+            }
+        )
+    }
+}
+
+impl From<ImageCreationError> for TextureCreationError {
+    #[inline]
+    fn from(err: ImageCreationError) -> TextureCreationError {
+        TextureCreationError::ImageCreationError(err)
+    }
+}
+
+impl From<ImageViewCreationError> for TextureCreationError {
+    #[inline]
+    fn from(err: ImageViewCreationError) -> TextureCreationError {
+        TextureCreationError::ImageViewCreationError(err)
+    }
+}
+
+impl From<SamplerCreationError> for TextureCreationError {
+    #[inline]
+    fn from(err: SamplerCreationError) -> TextureCreationError {
+        TextureCreationError::SamplerCreationError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::Format;
+    use crate::image::ImageDimensions;
+    use crate::image::Texture;
+    use crate::sampler::Sampler;
+    use std::sync::Arc;
+
+    #[test]
+    fn from_rgb8() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let dimensions = ImageDimensions::Dim2d {
+            width: 16,
+            height: 16,
+            array_layers: 1,
+        };
+        let (texture, _) = Texture::from_rgb8(
+            &[0x80; 16 * 16 * 3],
+            dimensions,
+            Format::R8G8B8A8Unorm,
+            queue,
+        )
+        .unwrap();
+        assert_eq!(texture.dimensions(), dimensions);
+        assert_eq!(texture.mipmap_levels(), 5);
+
+        let sampler = Sampler::simple_repeat_linear_no_mipmap(device);
+        let texture = texture.with_sampler(sampler.clone());
+        assert!(Arc::ptr_eq(texture.sampler(), &sampler));
+    }
+}
@@ -0,0 +1,401 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::buffer::BufferUsage;
+use crate::buffer::CpuAccessibleBuffer;
+use crate::command_buffer::sys::UnsafeCommandBufferBuilderBufferImageCopy;
+use crate::command_buffer::validity::required_len_for_format;
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::CopyBufferImageError;
+use crate::image::ImageAccess;
+use crate::image::ImageAspect;
+use crate::memory::DeviceMemoryAllocError;
+use crate::DeviceSize;
+use smallvec::smallvec;
+use smallvec::SmallVec;
+use std::error;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Accumulates uploads to regions of images, and records them with as few commands as possible.
+///
+/// Streaming textures usually means uploading many small regions every frame, for example the
+/// tiles of a virtual texture or the mipmaps of the textures that were just loaded. Recording
+/// each of them with `copy_buffer_to_image_dimensions` takes a staging buffer and a copy command
+/// per region. The uploader instead writes the data of all the regions to a single staging buffer
+/// when it's recorded, and records one `vkCmdCopyBufferToImage` per image, with a region for each
+/// upload to that image.
+///
+/// All the uploads are recorded in the same command buffer, so they are part of a single
+/// submission instead of one per region. `num_uploads` and `num_images` give the number of
+/// regions and of copy commands that `record` adds.
+///
+/// The uploader can be reused for the next frame after it's recorded, and keeps its memory.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use vulkano::command_buffer::AutoCommandBufferBuilder;
+/// # use vulkano::command_buffer::PrimaryAutoCommandBuffer;
+/// # use vulkano::image::StorageImage;
+/// use vulkano::image::ImageUploader;
+///
+/// # let mut builder: AutoCommandBufferBuilder<PrimaryAutoCommandBuffer> = return;
+/// # let texture: Arc<StorageImage> = return;
+/// # let tiles: Vec<([u32; 3], Vec<u8>)> = return;
+/// let mut uploader = ImageUploader::new();
+///
+/// // Each tile is 64x64 texels.
+/// for (offset, tile) in &tiles {
+///     uploader.upload(texture.clone(), tile, *offset, [64, 64, 1], 0..1, 0);
+/// }
+///
+/// // One staging buffer and one copy command for all the tiles.
+/// uploader.record(&mut builder).unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct ImageUploader {
+    // The data of all the uploads, as it will be written to the staging buffer.
+    data: Vec<u8>,
+    // The images with the regions to copy to each of them, in the order of the first upload.
+    images: Vec<PendingImage>,
+    // The number of uploads since the uploader was last recorded.
+    num_uploads: usize,
+}
+
+struct PendingImage {
+    image: Arc<dyn ImageAccess + Send + Sync>,
+    regions: SmallVec<[UnsafeCommandBufferBuilderBufferImageCopy; 4]>,
+}
+
+impl fmt::Debug for PendingImage {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("PendingImage")
+            .field("image", &self.image.inner().image)
+            .field("regions", &self.regions)
+            .finish()
+    }
+}
+
+impl ImageUploader {
+    /// Builds a new empty `ImageUploader`.
+    #[inline]
+    pub fn new() -> ImageUploader {
+        ImageUploader::default()
+    }
+
+    /// Adds an upload of tightly packed texels to a region of an image.
+    ///
+    /// `data` is copied immediately. The region is checked when the uploader is recorded.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the format of `image` isn't a color format.
+    /// - Panics if the length of `data` isn't the size of the texels of `size` for each array
+    ///   layer of `array_layers`.
+    pub fn upload<I>(
+        &mut self,
+        image: Arc<I>,
+        data: &[u8],
+        offset: [u32; 3],
+        size: [u32; 3],
+        array_layers: Range<u32>,
+        mip_level: u32,
+    ) where
+        I: ImageAccess + Send + Sync + 'static,
+    {
+        assert!(image.has_color());
+        assert_eq!(
+            data.len() as DeviceSize,
+            required_len_for_format::<u8>(image.format(), size, array_layers.len() as u32),
+            "the length of the data doesn't match the size of the region"
+        );
+
+        // The offsets in the buffer must be a multiple of 4 and of the size of a texel block.
+        let block_size = image.format().size().unwrap_or(1) as usize;
+        let alignment = block_size * 4 / gcd(block_size, 4);
+        let buffer_offset = match self.data.len() % alignment {
+            0 => self.data.len(),
+            rest => self.data.len() + alignment - rest,
+        };
+        self.data.resize(buffer_offset, 0);
+        self.data.extend_from_slice(data);
+
+        let region = UnsafeCommandBufferBuilderBufferImageCopy {
+            buffer_offset: buffer_offset as DeviceSize,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_aspect: ImageAspect::Color,
+            image_mip_level: mip_level,
+            image_base_array_layer: array_layers.start,
+            image_layer_count: array_layers.end - array_layers.start,
+            image_offset: [offset[0] as i32, offset[1] as i32, offset[2] as i32],
+            image_extent: size,
+        };
+
+        self.num_uploads += 1;
+        let image = image as Arc<dyn ImageAccess + Send + Sync>;
+        match self
+            .images
+            .iter_mut()
+            .find(|pending| Arc::ptr_eq(&pending.image, &image))
+        {
+            Some(pending) => pending.regions.push(region),
+            None => self.images.push(PendingImage {
+                image,
+                regions: smallvec![region],
+            }),
+        }
+    }
+
+    /// Returns the number of uploads that were added since the uploader was last recorded, which
+    /// is the number of regions that `record` copies.
+    #[inline]
+    pub fn num_uploads(&self) -> usize {
+        self.num_uploads
+    }
+
+    /// Returns the number of images that uploads were added to since the uploader was last
+    /// recorded, which is the number of copy commands that `record` adds.
+    #[inline]
+    pub fn num_images(&self) -> usize {
+        self.images.len()
+    }
+
+    /// Returns the number of bytes that `record` writes to the staging buffer.
+    #[inline]
+    pub fn staging_size(&self) -> DeviceSize {
+        self.data.len() as DeviceSize
+    }
+
+    /// Writes the data of the uploads to a new staging buffer, and adds a copy command to
+    /// `builder` for each image. Does nothing if there is no upload.
+    ///
+    /// The uploader is then empty, and can be reused. If an error is returned, the uploads that
+    /// weren't recorded are discarded.
+    pub fn record<L, P>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<L, P>,
+    ) -> Result<(), ImageUploadError> {
+        if self.images.is_empty() {
+            return Ok(());
+        }
+
+        let result = self.record_inner(builder);
+        self.data.clear();
+        self.images.clear();
+        self.num_uploads = 0;
+        result
+    }
+
+    fn record_inner<L, P>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<L, P>,
+    ) -> Result<(), ImageUploadError> {
+        let device = self.images[0].image.inner().image.device().clone();
+
+        let staging = unsafe {
+            let staging = CpuAccessibleBuffer::<[u8]>::uninitialized_array(
+                device,
+                self.data.len() as DeviceSize,
+                BufferUsage::transfer_source(),
+                false,
+            )?;
+            staging.write().unwrap().copy_from_slice(&self.data);
+            staging
+        };
+
+        for pending in self.images.drain(..) {
+            builder.copy_buffer_to_image_regions(
+                staging.clone(),
+                pending.image,
+                pending.regions,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[inline]
+fn gcd(mut a: usize, mut b: usize) -> usize {
+    while b != 0 {
+        let rest = a % b;
+        a = b;
+        b = rest;
+    }
+    a
+}
+
+/// Error that can happen when recording an `ImageUploader`.
+#[derive(Debug, Clone)]
+pub enum ImageUploadError {
+    /// Failed to allocate the staging buffer.
+    AllocError(DeviceMemoryAllocError),
+    /// The copy to one of the images is invalid.
+    CopyError(CopyBufferImageError),
+}
+
+impl error::Error for ImageUploadError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ImageUploadError::AllocError(ref err) => Some(err),
+            ImageUploadError::CopyError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for ImageUploadError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                ImageUploadError::AllocError(_) => "failed to allocate the staging buffer",
+                ImageUploadError::CopyError(_) => "the copy to one of the images is invalid",
+            }
+        )
+    }
+}
+
+impl From<DeviceMemoryAllocError> for ImageUploadError {
+    #[inline]
+    fn from(err: DeviceMemoryAllocError) -> ImageUploadError {
+        ImageUploadError::AllocError(err)
+    }
+}
+
+impl From<CopyBufferImageError> for ImageUploadError {
+    #[inline]
+    fn from(err: CopyBufferImageError) -> ImageUploadError {
+        ImageUploadError::CopyError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_buffer::AutoCommandBufferBuilder;
+    use crate::command_buffer::CommandBufferUsage;
+    use crate::format::Format;
+    use crate::image::ImageDimensions;
+    use crate::image::ImageUploader;
+    use crate::image::StorageImage;
+
+    #[test]
+    fn coalesces_regions() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let dimensions = ImageDimensions::Dim2d {
+            width: 32,
+            height: 32,
+            array_layers: 1,
+        };
+        let first = StorageImage::new(
+            device.clone(),
+            dimensions,
+            Format::R8G8B8A8Unorm,
+            Some(queue.family()),
+        )
+        .unwrap();
+        let second = StorageImage::new(
+            device.clone(),
+            dimensions,
+            Format::R8Unorm,
+            Some(queue.family()),
+        )
+        .unwrap();
+
+        let mut uploader = ImageUploader::new();
+        for x in 0..4 {
+            uploader.upload(
+                first.clone(),
+                &[0; 8 * 8 * 4],
+                [x * 8, 0, 0],
+                [8, 8, 1],
+                0..1,
+                0,
+            );
+        }
+        uploader.upload(second.clone(), &[0; 3], [0, 0, 0], [3, 1, 1], 0..1, 0);
+        uploader.upload(second.clone(), &[0; 3], [0, 1, 0], [3, 1, 1], 0..1, 0);
+        uploader.upload(first, &[0; 4], [0, 8, 0], [1, 1, 1], 0..1, 0);
+
+        // Seven regions are copied by two commands.
+        assert_eq!(uploader.num_uploads(), 7);
+        assert_eq!(uploader.num_images(), 2);
+        // The uploads to the second image are aligned to 4 bytes.
+        assert_eq!(uploader.staging_size(), 4 * 256 + 4 + 3 + 1 + 4);
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        uploader.record(&mut builder).unwrap();
+        assert_eq!(uploader.num_uploads(), 0);
+        assert_eq!(uploader.num_images(), 0);
+        assert_eq!(uploader.staging_size(), 0);
+        builder.build().unwrap();
+    }
+
+    #[test]
+    fn data_length_mismatch() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = StorageImage::new(
+            device,
+            ImageDimensions::Dim2d {
+                width: 32,
+                height: 32,
+                array_layers: 2,
+            },
+            Format::R8G8B8A8Unorm,
+            Some(queue.family()),
+        )
+        .unwrap();
+
+        let mut uploader = ImageUploader::new();
+        uploader.upload(
+            image.clone(),
+            &[0; 8 * 8 * 4 * 2],
+            [0, 0, 0],
+            [8, 8, 1],
+            0..2,
+            0,
+        );
+
+        assert_should_panic!("the length of the data", {
+            uploader.upload(
+                image.clone(),
+                &[0; 8 * 8 * 4],
+                [0, 0, 0],
+                [8, 8, 1],
+                0..2,
+                0,
+            );
+        });
+        assert_should_panic!("the length of the data", {
+            uploader.upload(
+                image.clone(),
+                &[0; 8 * 8 * 4 + 1],
+                [0, 0, 0],
+                [8, 8, 1],
+                0..1,
+                0,
+            );
+        });
+        assert_eq!(uploader.num_uploads(), 1);
+    }
+}
@@ -14,7 +14,9 @@
 //! to be used in a shader descriptor or as a framebuffer attachment.
 
 use crate::check_errors;
+use crate::device::DebugName;
 use crate::device::Device;
+use crate::device::DeviceOwned;
 use crate::format::Format;
 use crate::format::FormatTy;
 use crate::image::sys::UnsafeImage;
@@ -31,6 +33,7 @@ use std::hash::Hash;
 use std::hash::Hasher;
 use std::mem::MaybeUninit;
 use std::ops::Range;
+use std::panic::Location;
 use std::ptr;
 use std::sync::Arc;
 
@@ -54,6 +57,7 @@ where
 {
     /// Creates a default `ImageView`. Equivalent to `ImageView::start(image).build()`.
     #[inline]
+    #[track_caller]
     pub fn new(image: I) -> Result<Arc<ImageView<I>>, ImageViewCreationError> {
         Self::start(image).build()
     }
@@ -141,6 +145,7 @@ where
     }
 
     /// Builds the `ImageView`.
+    #[track_caller]
     pub fn build(self) -> Result<Arc<ImageView<I>>, ImageViewCreationError> {
         let dimensions = self.image.dimensions();
         let format = self.image.format();
@@ -280,6 +285,16 @@ impl From<OomError> for ImageViewCreationError {
     }
 }
 
+impl<I> DebugName for ImageView<I>
+where
+    I: ImageAccess,
+{
+    #[inline]
+    fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        self.inner.set_debug_name(name)
+    }
+}
+
 /// A low-level wrapper around a `vkImageView`.
 pub struct UnsafeImageView {
     view: ash::vk::ImageView,
@@ -299,6 +314,7 @@ impl UnsafeImageView {
     /// # Panics
     /// Panics if the image is a YcbCr image, since the Vulkano API is not yet flexible enough to
     /// specify the aspect of image.
+    #[track_caller]
     pub unsafe fn new(
         image: &UnsafeImage,
         ty: ImageViewType,
@@ -347,10 +363,19 @@ impl UnsafeImageView {
             output.assume_init()
         };
 
-        Ok(UnsafeImageView {
+        let view = UnsafeImageView {
             view,
             device: image.device().clone(),
-        })
+        };
+        view.device.auto_debug_name(&view, Location::caller());
+        Ok(view)
+    }
+}
+
+unsafe impl DeviceOwned for UnsafeImageView {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
     }
 }
 
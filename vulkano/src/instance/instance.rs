@@ -16,6 +16,8 @@ use crate::instance::loader::FunctionPointers;
 use crate::instance::loader::Loader;
 use crate::instance::loader::LoadingError;
 use crate::instance::InstanceExtensions;
+use crate::instance::ValidationFeatures;
+use crate::pnext::PNextChain;
 use crate::Error;
 use crate::OomError;
 use crate::Version;
@@ -237,6 +239,8 @@ impl Instance {
             max_api_version,
             extensions,
             layers,
+            None,
+            PNextChain::new(),
             OwnedOrRef::Ref(loader::auto_loader()?),
         )
     }
@@ -262,19 +266,99 @@ impl Instance {
             max_api_version,
             extensions,
             layers,
+            None,
+            PNextChain::new(),
             OwnedOrRef::Owned(loader),
         )
     }
 
+    /// Same as `new`, but also enables or disables features of the validation layers.
+    ///
+    /// The `ext_validation_features` extension must be enabled in `extensions`.
+    pub fn with_validation_features<'a, L>(
+        app_infos: Option<&ApplicationInfo>,
+        max_api_version: Version,
+        extensions: &InstanceExtensions,
+        layers: L,
+        validation_features: &ValidationFeatures,
+    ) -> Result<Arc<Instance>, InstanceCreationError>
+    where
+        L: IntoIterator<Item = &'a str>,
+    {
+        let layers = layers
+            .into_iter()
+            .map(|layer| CString::new(layer).unwrap())
+            .collect::<SmallVec<[_; 16]>>();
+
+        Instance::new_inner(
+            app_infos,
+            max_api_version,
+            extensions,
+            layers,
+            Some(validation_features),
+            PNextChain::new(),
+            OwnedOrRef::Ref(loader::auto_loader()?),
+        )
+    }
+
+    /// Same as `new`, but also appends structures to the `pNext` chain of the
+    /// `VkInstanceCreateInfo` structure. See the `pnext` module.
+    ///
+    /// # Safety
+    ///
+    /// - The structures of `p_next_chain` must be valid, and the extensions that define them
+    ///   must be enabled in `extensions`.
+    /// - `p_next_chain` must not contain the same structure twice.
+    /// - `p_next_chain` must not contain a `VkValidationFeaturesEXT` structure. Use
+    ///   `with_validation_features` instead, which checks the features and appends this structure
+    ///   itself.
+    pub unsafe fn with_p_next_chain<'a, L>(
+        app_infos: Option<&ApplicationInfo>,
+        max_api_version: Version,
+        extensions: &InstanceExtensions,
+        layers: L,
+        p_next_chain: PNextChain<ash::vk::InstanceCreateInfo>,
+    ) -> Result<Arc<Instance>, InstanceCreationError>
+    where
+        L: IntoIterator<Item = &'a str>,
+    {
+        let layers = layers
+            .into_iter()
+            .map(|layer| CString::new(layer).unwrap())
+            .collect::<SmallVec<[_; 16]>>();
+
+        Instance::new_inner(
+            app_infos,
+            max_api_version,
+            extensions,
+            layers,
+            None,
+            p_next_chain,
+            OwnedOrRef::Ref(loader::auto_loader()?),
+        )
+    }
+
     fn new_inner(
         app_infos: Option<&ApplicationInfo>,
         max_api_version: Version,
         extensions: &InstanceExtensions,
         layers: SmallVec<[CString; 16]>,
+        validation_features: Option<&ValidationFeatures>,
+        mut p_next_chain: PNextChain<ash::vk::InstanceCreateInfo>,
         function_pointers: OwnedOrRef<FunctionPointers<Box<dyn Loader + Send + Sync>>>,
     ) -> Result<Arc<Instance>, InstanceCreationError> {
         let api_version = std::cmp::min(max_api_version, function_pointers.api_version()?);
 
+        if let Some(validation_features) = validation_features {
+            if !extensions.ext_validation_features {
+                return Err(InstanceCreationError::ValidationFeaturesExtensionNotEnabled);
+            }
+
+            if !validation_features.is_valid() {
+                return Err(InstanceCreationError::IncompatibleValidationFeatures);
+            }
+        }
+
         // Check if the extensions are correct
         extensions.check_requirements(
             &InstanceExtensions::supported_by_core_with_loader(&function_pointers)?,
@@ -350,10 +434,31 @@ impl Instance {
             .map(|extension| extension.as_ptr())
             .collect::<SmallVec<[_; 32]>>();
 
+        let enabled_validation_features = validation_features
+            .map(|features| features.enabled_list())
+            .unwrap_or_default();
+        let disabled_validation_features = validation_features
+            .map(|features| features.disabled_list())
+            .unwrap_or_default();
+        let validation_features_info =
+            validation_features.map(|_| ash::vk::ValidationFeaturesEXT {
+                enabled_validation_feature_count: enabled_validation_features.len() as u32,
+                p_enabled_validation_features: enabled_validation_features.as_ptr(),
+                disabled_validation_feature_count: disabled_validation_features.len() as u32,
+                p_disabled_validation_features: disabled_validation_features.as_ptr(),
+                ..Default::default()
+            });
+
         // Creating the Vulkan instance.
         let instance = unsafe {
             let mut output = MaybeUninit::uninit();
             let infos = ash::vk::InstanceCreateInfo {
+                p_next: p_next_chain.link(
+                    validation_features_info
+                        .as_ref()
+                        .map(|info| info as *const _ as *const _)
+                        .unwrap_or(ptr::null()),
+                ),
                 flags: ash::vk::InstanceCreateFlags::empty(),
                 p_application_info: if let Some(app) = app_infos.as_ref() {
                     app as *const _
@@ -375,6 +480,64 @@ impl Instance {
             output.assume_init()
         };
 
+        Instance::from_handle_inner(
+            instance,
+            api_version,
+            max_api_version,
+            extensions,
+            layers,
+            function_pointers,
+        )
+    }
+
+    /// Builds an `Instance` from a Vulkan instance that was created outside of vulkano, for
+    /// example by an OpenXR runtime or by a library that uses `ash`.
+    ///
+    /// The function pointers are loaded with the default loader, and the physical devices are
+    /// enumerated as with `new`. The `Instance` takes ownership of the handle, and destroys it
+    /// when it is dropped.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid instance that was created with the same Vulkan library as the
+    ///   default loader.
+    /// - `max_api_version`, `extensions` and `layers` must match the API version, extensions
+    ///   and layers that were enabled when creating the instance.
+    /// - The instance must not be destroyed by anything else than the returned `Instance`.
+    pub unsafe fn from_handle<'a, L>(
+        handle: ash::vk::Instance,
+        max_api_version: Version,
+        extensions: &InstanceExtensions,
+        layers: L,
+    ) -> Result<Arc<Instance>, InstanceCreationError>
+    where
+        L: IntoIterator<Item = &'a str>,
+    {
+        let layers = layers
+            .into_iter()
+            .map(|layer| CString::new(layer).unwrap())
+            .collect::<SmallVec<[_; 16]>>();
+        let function_pointers = OwnedOrRef::Ref(loader::auto_loader()?);
+        let api_version = std::cmp::min(max_api_version, function_pointers.api_version()?);
+
+        Instance::from_handle_inner(
+            handle,
+            api_version,
+            max_api_version,
+            extensions,
+            layers,
+            function_pointers,
+        )
+    }
+
+    fn from_handle_inner(
+        instance: ash::vk::Instance,
+        api_version: Version,
+        max_api_version: Version,
+        extensions: &InstanceExtensions,
+        layers: SmallVec<[CString; 16]>,
+        function_pointers: OwnedOrRef<FunctionPointers<Box<dyn Loader + Send + Sync>>>,
+    ) -> Result<Arc<Instance>, InstanceCreationError> {
         // Loading the function pointers of the newly-created instance.
         let fns = {
             InstanceFunctions::load(|name| {
@@ -598,6 +761,11 @@ pub enum InstanceCreationError {
     IncompatibleDriver,
     /// A restriction for an extension was not met.
     ExtensionRestrictionNotMet(ExtensionRestrictionError),
+    /// Validation features were requested, but the `ext_validation_features` extension isn't
+    /// enabled.
+    ValidationFeaturesExtensionNotEnabled,
+    /// The requested validation features can't be enabled together.
+    IncompatibleValidationFeatures,
 }
 
 impl error::Error for InstanceCreationError {
@@ -624,6 +792,14 @@ impl fmt::Display for InstanceCreationError {
             InstanceCreationError::ExtensionNotPresent => write!(fmt, "extension not present"),
             InstanceCreationError::IncompatibleDriver => write!(fmt, "incompatible driver"),
             InstanceCreationError::ExtensionRestrictionNotMet(err) => err.fmt(fmt),
+            InstanceCreationError::ValidationFeaturesExtensionNotEnabled => write!(
+                fmt,
+                "the `ext_validation_features` extension must be enabled to use validation features"
+            ),
+            InstanceCreationError::IncompatibleValidationFeatures => write!(
+                fmt,
+                "the requested validation features can't be enabled together"
+            ),
         }
     }
 }
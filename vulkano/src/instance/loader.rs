@@ -111,6 +111,44 @@ unsafe impl Loader for DynamicLibraryLoader {
     }
 }
 
+/// Implementation of `Loader` that calls a `vkGetInstanceProcAddr` function that was obtained
+/// by other means, for example from an OpenXR runtime or from a library that links to Vulkan
+/// statically.
+pub struct EntryPointLoader {
+    get_proc_addr:
+        extern "system" fn(instance: ash::vk::Instance, name: *const c_char) -> *const c_void,
+}
+
+impl EntryPointLoader {
+    /// Builds a loader that calls `get_instance_proc_addr`.
+    ///
+    /// # Safety
+    ///
+    /// - `get_instance_proc_addr` must be the `vkGetInstanceProcAddr` function of a valid Vulkan
+    ///   implementation, and must stay valid for as long as the loader is alive.
+    ///
+    #[inline]
+    pub unsafe fn new(
+        get_instance_proc_addr: ash::vk::PFN_vkGetInstanceProcAddr,
+    ) -> EntryPointLoader {
+        let get_proc_addr: extern "system" fn(ash::vk::Instance, *const c_char) -> *const c_void =
+            mem::transmute(get_instance_proc_addr);
+
+        EntryPointLoader { get_proc_addr }
+    }
+}
+
+unsafe impl Loader for EntryPointLoader {
+    #[inline]
+    fn get_instance_proc_addr(
+        &self,
+        instance: ash::vk::Instance,
+        name: *const c_char,
+    ) -> *const c_void {
+        (self.get_proc_addr)(instance, name)
+    }
+}
+
 /// Wraps around a loader and contains function pointers.
 pub struct FunctionPointers<L> {
     loader: L,
@@ -183,7 +221,8 @@ impl<L> FunctionPointers<L> {
 /// compiling.
 ///
 /// If you use this macro, you must linked to a library that provides the `vkGetInstanceProcAddr`
-/// symbol.
+/// symbol, for example a static build of MoltenVK. The returned loader can be passed to
+/// `FunctionPointers::new` and `Instance::with_loader`.
 ///
 /// This is provided as a macro and not as a regular function, because the macro contains an
 /// `extern {}` block.
@@ -191,20 +230,22 @@ impl<L> FunctionPointers<L> {
 #[macro_export]
 macro_rules! statically_linked_vulkan_loader {
     () => {{
-        extern "C" {
+        type RawInstance = <$crate::instance::Instance as $crate::VulkanObject>::Object;
+
+        extern "system" {
             fn vkGetInstanceProcAddr(
-                instance: ash::vk::Instance,
-                pName: *const c_char,
-            ) -> ash::vk::PFN_vkVoidFunction;
+                instance: RawInstance,
+                pName: *const std::os::raw::c_char,
+            ) -> *const std::os::raw::c_void;
         }
 
         struct StaticallyLinkedVulkanLoader;
-        unsafe impl Loader for StaticallyLinkedVulkanLoader {
+        unsafe impl $crate::instance::loader::Loader for StaticallyLinkedVulkanLoader {
             fn get_instance_proc_addr(
                 &self,
-                instance: ash::vk::Instance,
-                name: *const c_char,
-            ) -> extern "system" fn() -> () {
+                instance: RawInstance,
+                name: *const std::os::raw::c_char,
+            ) -> *const std::os::raw::c_void {
                 unsafe { vkGetInstanceProcAddr(instance, name) }
             }
         }
@@ -222,7 +263,7 @@ pub fn auto_loader(
 ) -> Result<&'static FunctionPointers<Box<dyn Loader + Send + Sync>>, LoadingError> {
     #[cfg(target_os = "ios")]
     #[allow(non_snake_case)]
-    fn def_loader_impl() -> Result<Box<Loader + Send + Sync>, LoadingError> {
+    fn def_loader_impl() -> Result<Box<dyn Loader + Send + Sync>, LoadingError> {
         let loader = statically_linked_vulkan_loader!();
         Ok(Box::new(loader))
     }
@@ -302,7 +343,12 @@ impl fmt::Display for LoadingError {
 #[cfg(test)]
 mod tests {
     use crate::instance::loader::DynamicLibraryLoader;
+    use crate::instance::loader::EntryPointLoader;
+    use crate::instance::loader::FunctionPointers;
     use crate::instance::loader::LoadingError;
+    use crate::Version;
+    use std::ffi::CStr;
+    use std::os::raw::c_char;
 
     #[test]
     fn dl_open_error() {
@@ -313,4 +359,31 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn entry_point_loader() {
+        unsafe extern "system" fn get_instance_proc_addr(
+            _instance: ash::vk::Instance,
+            name: *const c_char,
+        ) -> ash::vk::PFN_vkVoidFunction {
+            unsafe extern "system" fn enumerate_instance_version(
+                api_version: *mut u32,
+            ) -> ash::vk::Result {
+                *api_version = ash::vk::make_api_version(0, 1, 2, 0);
+                ash::vk::Result::SUCCESS
+            }
+
+            if CStr::from_ptr(name).to_bytes() == b"vkEnumerateInstanceVersion" {
+                Some(std::mem::transmute(
+                    enumerate_instance_version as unsafe extern "system" fn(*mut u32) -> _,
+                ))
+            } else {
+                None
+            }
+        }
+
+        let loader = unsafe { EntryPointLoader::new(get_instance_proc_addr) };
+        let function_pointers = FunctionPointers::new(loader);
+        assert_eq!(function_pointers.api_version().unwrap(), Version::V1_2);
+    }
 }
@@ -60,6 +60,8 @@ pub use self::layers::LayerProperties;
 pub use self::layers::LayersIterator;
 pub use self::layers::LayersListError;
 pub use self::loader::LoadingError;
+pub use self::validation::DisabledValidationChecks;
+pub use self::validation::ValidationFeatures;
 pub use crate::extensions::{
     ExtensionRestriction, ExtensionRestrictionError, SupportedExtensionsError,
 };
@@ -70,3 +72,4 @@ pub(crate) mod extensions;
 mod instance;
 mod layers;
 pub mod loader;
+mod validation;
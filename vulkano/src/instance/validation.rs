@@ -0,0 +1,146 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use smallvec::SmallVec;
+
+/// Features of the validation layers to enable or disable when creating an instance, with
+/// `Instance::with_validation_features`.
+///
+/// This replaces the configuration of the layers with vkconfig or with environment variables.
+/// The `ext_validation_features` extension must be enabled on the instance, and the validation
+/// layers must be enabled to have any effect.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ValidationFeatures {
+    /// Validates the shaders on the GPU, for example out of bounds descriptor accesses.
+    /// Incompatible with `debug_printf`.
+    pub gpu_assisted: bool,
+    /// Reserves a descriptor set binding slot for the use of GPU-assisted validation. Requires
+    /// `gpu_assisted`.
+    pub gpu_assisted_reserve_binding_slot: bool,
+    /// Reports the uses of the API that are valid but potentially inefficient.
+    pub best_practices: bool,
+    /// Forwards the output of the `debugPrintfEXT` calls of the shaders to the debug callbacks.
+    /// Incompatible with `gpu_assisted`.
+    pub debug_printf: bool,
+    /// Reports the hazards caused by missing or wrong synchronization between commands.
+    pub synchronization_validation: bool,
+    /// Checks of the validation layers to disable.
+    pub disabled_checks: DisabledValidationChecks,
+}
+
+impl ValidationFeatures {
+    /// Returns true if the features can be enabled together.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        !(self.gpu_assisted && self.debug_printf)
+            && (self.gpu_assisted || !self.gpu_assisted_reserve_binding_slot)
+    }
+
+    pub(crate) fn enabled_list(&self) -> SmallVec<[ash::vk::ValidationFeatureEnableEXT; 5]> {
+        let mut list = SmallVec::new();
+        if self.gpu_assisted {
+            list.push(ash::vk::ValidationFeatureEnableEXT::GPU_ASSISTED);
+        }
+        if self.gpu_assisted_reserve_binding_slot {
+            list.push(ash::vk::ValidationFeatureEnableEXT::GPU_ASSISTED_RESERVE_BINDING_SLOT);
+        }
+        if self.best_practices {
+            list.push(ash::vk::ValidationFeatureEnableEXT::BEST_PRACTICES);
+        }
+        if self.debug_printf {
+            list.push(ash::vk::ValidationFeatureEnableEXT::DEBUG_PRINTF);
+        }
+        if self.synchronization_validation {
+            list.push(ash::vk::ValidationFeatureEnableEXT::SYNCHRONIZATION_VALIDATION);
+        }
+        list
+    }
+
+    pub(crate) fn disabled_list(&self) -> SmallVec<[ash::vk::ValidationFeatureDisableEXT; 8]> {
+        let checks = &self.disabled_checks;
+        let mut list = SmallVec::new();
+        if checks.all {
+            list.push(ash::vk::ValidationFeatureDisableEXT::ALL);
+        }
+        if checks.shaders {
+            list.push(ash::vk::ValidationFeatureDisableEXT::SHADERS);
+        }
+        if checks.thread_safety {
+            list.push(ash::vk::ValidationFeatureDisableEXT::THREAD_SAFETY);
+        }
+        if checks.api_parameters {
+            list.push(ash::vk::ValidationFeatureDisableEXT::API_PARAMETERS);
+        }
+        if checks.object_lifetimes {
+            list.push(ash::vk::ValidationFeatureDisableEXT::OBJECT_LIFETIMES);
+        }
+        if checks.core_checks {
+            list.push(ash::vk::ValidationFeatureDisableEXT::CORE_CHECKS);
+        }
+        if checks.unique_handles {
+            list.push(ash::vk::ValidationFeatureDisableEXT::UNIQUE_HANDLES);
+        }
+        if checks.shader_validation_cache {
+            list.push(ash::vk::ValidationFeatureDisableEXT::SHADER_VALIDATION_CACHE);
+        }
+        list
+    }
+}
+
+/// Checks of the validation layers that can be disabled.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct DisabledValidationChecks {
+    /// Disables all the checks.
+    pub all: bool,
+    /// Disables the validation of the shaders.
+    pub shaders: bool,
+    /// Disables the checks that objects are not used concurrently from several threads.
+    pub thread_safety: bool,
+    /// Disables the validation of the parameters of the functions.
+    pub api_parameters: bool,
+    /// Disables the tracking of the lifetimes of the objects.
+    pub object_lifetimes: bool,
+    /// Disables the core checks, which validate the state of the objects and of the commands.
+    pub core_checks: bool,
+    /// Disables the wrapping of the handles, which makes the handles returned by the layers
+    /// unique.
+    pub unique_handles: bool,
+    /// Disables the cache of the shader validation results.
+    pub shader_validation_cache: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::instance::ValidationFeatures;
+
+    #[test]
+    fn incompatible_features() {
+        let features = ValidationFeatures {
+            gpu_assisted: true,
+            debug_printf: true,
+            ..Default::default()
+        };
+        assert!(!features.is_valid());
+
+        let features = ValidationFeatures {
+            gpu_assisted_reserve_binding_slot: true,
+            ..Default::default()
+        };
+        assert!(!features.is_valid());
+
+        let features = ValidationFeatures {
+            debug_printf: true,
+            best_practices: true,
+            synchronization_validation: true,
+            ..Default::default()
+        };
+        assert!(features.is_valid());
+        assert_eq!(features.enabled_list().len(), 3);
+    }
+}
@@ -64,9 +64,12 @@
 
 pub use ash::vk::Handle;
 pub use half;
+pub use smallvec;
+use std::borrow::Borrow;
 use std::error;
 use std::fmt;
 use std::ops::Deref;
+use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::MutexGuard;
 pub use version::Version;
@@ -77,21 +80,30 @@ mod tests;
 mod extensions;
 pub mod buffer;
 pub mod command_buffer;
+pub mod culling;
+pub mod debug_draw;
 pub mod descriptor_set;
 pub mod device;
 pub mod format;
+mod free_list;
 mod version;
 #[macro_use]
 pub mod render_pass;
+#[cfg(feature = "renderdoc")]
+pub mod renderdoc;
 mod fns;
 pub mod image;
 pub mod instance;
 pub mod memory;
 pub mod pipeline;
+pub mod pnext;
 pub mod query;
+pub mod render_graph;
 pub mod sampler;
 pub mod swapchain;
 pub mod sync;
+#[cfg(feature = "tracy")]
+pub mod tracy;
 
 mod autogen {
     // Generated by build.rs
@@ -108,7 +120,26 @@ unsafe impl<'a, T: ?Sized> SafeDeref for &'a T {}
 unsafe impl<T: ?Sized> SafeDeref for Arc<T> {}
 unsafe impl<T: ?Sized> SafeDeref for Box<T> {}
 
+/// An alternative to `Borrow<T>` with the requirement that all calls to
+/// `borrow` return the same object.
+///
+/// # Safety
+///
+/// Every call to `borrow` must return the same object, which must stay alive as long as `self`.
+pub unsafe trait SafeBorrow<T>: Borrow<T> {}
+unsafe impl<T> SafeBorrow<T> for T {}
+unsafe impl<T> SafeBorrow<T> for &T {}
+unsafe impl<T> SafeBorrow<T> for &mut T {}
+unsafe impl<T> SafeBorrow<T> for Rc<T> {}
+unsafe impl<T> SafeBorrow<T> for Arc<T> {}
+unsafe impl<T> SafeBorrow<T> for Box<T> {}
+
 /// Gives access to the internal identifier of an object.
+///
+/// The handle can be passed to functions loaded with `ash` or by other libraries. The vulkano
+/// object keeps the ownership of the handle, which must not be destroyed or used after the object
+/// is dropped. Several objects can be built from a handle instead, with their `from_handle`
+/// function.
 pub unsafe trait VulkanObject {
     /// The type of the object.
     type Object: ash::vk::Handle;
@@ -202,6 +233,7 @@ pub(crate) enum Error {
     ValidationFailed = ash::vk::Result::ERROR_VALIDATION_FAILED_EXT.as_raw(),
     OutOfPoolMemory = ash::vk::Result::ERROR_OUT_OF_POOL_MEMORY_KHR.as_raw(),
     FullscreenExclusiveLost = ash::vk::Result::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT.as_raw(),
+    NotPermitted = ash::vk::Result::ERROR_NOT_PERMITTED_EXT.as_raw(),
 }
 
 /// Checks whether the result returned correctly.
@@ -234,6 +266,7 @@ fn check_errors(result: ash::vk::Result) -> Result<Success, Error> {
         ash::vk::Result::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT => {
             Err(Error::FullscreenExclusiveLost)
         }
+        ash::vk::Result::ERROR_NOT_PERMITTED_EXT => Err(Error::NotPermitted),
         ash::vk::Result::ERROR_INVALID_SHADER_NV => panic!(
             "Vulkan function returned \
                                                VK_ERROR_INVALID_SHADER_NV"
@@ -35,29 +35,16 @@ use std::ptr;
 use std::sync::Arc;
 use std::sync::Mutex;
 
-#[repr(C)]
-pub struct BaseOutStructure {
-    pub s_type: i32,
-    pub p_next: *mut BaseOutStructure,
-}
-
-pub(crate) unsafe fn ptr_chain_iter<T>(ptr: &mut T) -> impl Iterator<Item = *mut BaseOutStructure> {
-    let ptr: *mut BaseOutStructure = ptr as *mut T as _;
-    (0..).scan(ptr, |p_ptr, _| {
-        if p_ptr.is_null() {
-            return None;
-        }
-        let n_ptr = (**p_ptr).p_next as *mut BaseOutStructure;
-        let old = *p_ptr;
-        *p_ptr = n_ptr;
-        Some(old)
-    })
-}
-
-pub unsafe trait ExtendsMemoryAllocateInfo {}
-unsafe impl ExtendsMemoryAllocateInfo for ash::vk::MemoryDedicatedAllocateInfoKHR {}
-unsafe impl ExtendsMemoryAllocateInfo for ash::vk::ExportMemoryAllocateInfo {}
-unsafe impl ExtendsMemoryAllocateInfo for ash::vk::ImportMemoryFdInfoKHR {}
+// Handle types that are defined by `VK_KHR_external_memory_win32`.
+const WIN32_HANDLE_TYPES: ash::vk::ExternalMemoryHandleTypeFlags =
+    ash::vk::ExternalMemoryHandleTypeFlags::from_raw(
+        ash::vk::ExternalMemoryHandleTypeFlags::OPAQUE_WIN32.as_raw()
+            | ash::vk::ExternalMemoryHandleTypeFlags::OPAQUE_WIN32_KMT.as_raw()
+            | ash::vk::ExternalMemoryHandleTypeFlags::D3D11_TEXTURE.as_raw()
+            | ash::vk::ExternalMemoryHandleTypeFlags::D3D11_TEXTURE_KMT.as_raw()
+            | ash::vk::ExternalMemoryHandleTypeFlags::D3D12_HEAP.as_raw()
+            | ash::vk::ExternalMemoryHandleTypeFlags::D3D12_RESOURCE.as_raw(),
+    );
 
 /// Represents memory that has been allocated.
 ///
@@ -102,6 +89,7 @@ pub struct DeviceMemoryBuilder<'a> {
     dedicated_info: Option<ash::vk::MemoryDedicatedAllocateInfoKHR>,
     export_info: Option<ash::vk::ExportMemoryAllocateInfo>,
     import_info: Option<ash::vk::ImportMemoryFdInfoKHR>,
+    import_win32_info: Option<ash::vk::ImportMemoryWin32HandleInfoKHR>,
     marker: PhantomData<&'a ()>,
 }
 
@@ -125,6 +113,7 @@ impl<'a> DeviceMemoryBuilder<'a> {
             dedicated_info: None,
             export_info: None,
             import_info: None,
+            import_win32_info: None,
             marker: PhantomData,
         }
     }
@@ -145,7 +134,7 @@ impl<'a> DeviceMemoryBuilder<'a> {
             return self;
         }
 
-        let mut dedicated_info = match dedicated {
+        let dedicated_info = match dedicated {
             DedicatedAlloc::Buffer(buffer) => ash::vk::MemoryDedicatedAllocateInfoKHR {
                 image: ash::vk::Image::null(),
                 buffer: buffer.internal_object(),
@@ -159,7 +148,6 @@ impl<'a> DeviceMemoryBuilder<'a> {
             DedicatedAlloc::None => return self,
         };
 
-        self = self.push_next(&mut dedicated_info);
         self.dedicated_info = Some(dedicated_info);
         self
     }
@@ -175,12 +163,11 @@ impl<'a> DeviceMemoryBuilder<'a> {
     ) -> DeviceMemoryBuilder<'a> {
         assert!(self.export_info.is_none());
 
-        let mut export_info = ash::vk::ExportMemoryAllocateInfo {
+        let export_info = ash::vk::ExportMemoryAllocateInfo {
             handle_types: handle_types.into(),
             ..Default::default()
         };
 
-        self = self.push_next(&mut export_info);
         self.export_info = Some(export_info);
         self
     }
@@ -198,51 +185,58 @@ impl<'a> DeviceMemoryBuilder<'a> {
     ) -> DeviceMemoryBuilder<'a> {
         assert!(self.import_info.is_none());
 
-        let mut import_info = ash::vk::ImportMemoryFdInfoKHR {
+        let import_info = ash::vk::ImportMemoryFdInfoKHR {
             handle_type: handle_types.into(),
             fd: fd.into_raw_fd(),
             ..Default::default()
         };
 
-        self = self.push_next(&mut import_info);
         self.import_info = Some(import_info);
         self
     }
 
-    // Private function copied shamelessly from Ash.
-    // https://github.com/MaikKlein/ash/blob/4ba8637d018fec6d6e3a90d7fa47d11c085f6b4a/generator/src/lib.rs
-    #[allow(unused_assignments)]
-    fn push_next<T: ExtendsMemoryAllocateInfo>(self, next: &mut T) -> DeviceMemoryBuilder<'a> {
-        unsafe {
-            // `next` here can contain a pointer chain. This means that we must correctly
-            // attach he head to the root and the tail to the rest of the chain
-            // For example:
-            //
-            // next = A -> B
-            // Before: `Root -> C -> D -> E`
-            // After: `Root -> A -> B -> C -> D -> E`
-
-            // Convert next to our ptr structure
-            let next_ptr = next as *mut T as *mut BaseOutStructure;
-            // Previous head (can be null)
-            let mut prev_head = self.allocate.p_next as *mut BaseOutStructure;
-            // Retrieve end of next chain
-            let last_next = ptr_chain_iter(next).last().unwrap();
-            // Set end of next chain's next to be previous head only if previous head's next'
-            if !prev_head.is_null() {
-                (*last_next).p_next = (*prev_head).p_next;
-            }
-            // Set next ptr to be first one
-            prev_head = next_ptr;
-        }
+    /// Sets an optional field for importing a Windows handle in the `DeviceMemoryBuilder`.
+    ///
+    /// This imports the memory of a resource of another API, for example a Direct3D 11 texture
+    /// shared with `IDXGIResource1::CreateSharedHandle`, or a Direct3D 12 heap or resource
+    /// shared with `ID3D12Device::CreateSharedHandle`. The size and the memory type must be
+    /// compatible with the requirements of the buffer or image that the memory is bound to.
+    /// Direct3D 11 textures and Direct3D 12 resources must be imported as dedicated allocations
+    /// with `dedicated_info`.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid handle of type `handle_type`.
+    /// - If `handle_type` is an NT handle type, the caller keeps the ownership of the handle.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if an import info has already been set.
+    /// - Panics if `handle_type` doesn't contain exactly one Windows handle type.
+    pub unsafe fn import_win32_info(
+        mut self,
+        handle: ash::vk::HANDLE,
+        handle_type: ExternalMemoryHandleType,
+    ) -> DeviceMemoryBuilder<'a> {
+        assert!(self.import_info.is_none() && self.import_win32_info.is_none());
 
+        let bits = ash::vk::ExternalMemoryHandleTypeFlags::from(handle_type);
+        assert!(bits.as_raw().is_power_of_two() && WIN32_HANDLE_TYPES.contains(bits));
+
+        let import_win32_info = ash::vk::ImportMemoryWin32HandleInfoKHR {
+            handle_type: bits,
+            handle,
+            ..Default::default()
+        };
+
+        self.import_win32_info = Some(import_win32_info);
         self
     }
 
     /// Creates a `DeviceMemory` object on success, consuming the `DeviceMemoryBuilder`.  An error
     /// is returned if the requested allocation is too large or if the total number of allocations
     /// would exceed per-device limits.
-    pub fn build(self) -> Result<Arc<DeviceMemory>, DeviceMemoryAllocError> {
+    pub fn build(mut self) -> Result<Arc<DeviceMemory>, DeviceMemoryAllocError> {
         if self.allocate.allocation_size == 0 {
             return Err(DeviceMemoryAllocError::InvalidSize)?;
         }
@@ -281,18 +275,30 @@ impl<'a> DeviceMemoryBuilder<'a> {
 
         let mut export_handle_bits = ash::vk::ExternalMemoryHandleTypeFlags::empty();
 
-        if self.export_info.is_some() || self.import_info.is_some() {
+        if self.export_info.is_some()
+            || self.import_info.is_some()
+            || self.import_win32_info.is_some()
+        {
             // TODO: check exportFromImportedHandleTypes
             export_handle_bits = match self.export_info {
                 Some(export_info) => export_info.handle_types,
                 None => ash::vk::ExternalMemoryHandleTypeFlags::empty(),
             };
 
-            let import_handle_bits = match self.import_info {
-                Some(import_info) => import_info.handle_type,
-                None => ash::vk::ExternalMemoryHandleTypeFlags::empty(),
+            let import_handle_bits = match (self.import_info, self.import_win32_info) {
+                (Some(import_info), _) => import_info.handle_type,
+                (_, Some(import_win32_info)) => import_win32_info.handle_type,
+                (None, None) => ash::vk::ExternalMemoryHandleTypeFlags::empty(),
             };
 
+            if (export_handle_bits | import_handle_bits).intersects(WIN32_HANDLE_TYPES)
+                && !self.device.enabled_extensions().khr_external_memory_win32
+            {
+                return Err(DeviceMemoryAllocError::MissingExtension(
+                    "khr_external_memory_win32",
+                ));
+            }
+
             if !(export_handle_bits & ash::vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT)
                 .is_empty()
             {
@@ -332,6 +338,26 @@ impl<'a> DeviceMemoryBuilder<'a> {
             }
         }
 
+        // The structures are linked only now, since the builder moves them around before.
+        let mut p_next: *const c_void = ptr::null();
+        if let Some(ref mut info) = self.dedicated_info {
+            info.p_next = p_next;
+            p_next = info as *const _ as *const _;
+        }
+        if let Some(ref mut info) = self.export_info {
+            info.p_next = p_next;
+            p_next = info as *const _ as *const _;
+        }
+        if let Some(ref mut info) = self.import_info {
+            info.p_next = p_next;
+            p_next = info as *const _ as *const _;
+        }
+        if let Some(ref mut info) = self.import_win32_info {
+            info.p_next = p_next;
+            p_next = info as *const _ as *const _;
+        }
+        self.allocate.p_next = p_next;
+
         let memory = unsafe {
             let physical_device = self.device.physical_device();
             let mut allocation_count = self
@@ -617,6 +643,57 @@ impl DeviceMemory {
         let file = unsafe { File::from_raw_fd(fd) };
         Ok(file)
     }
+
+    /// Exports the device memory into a Windows handle, for example to open it in Direct3D 12
+    /// with `ID3D12Device::OpenSharedHandle`.
+    ///
+    /// An NT handle (`opaque_win32`) is owned by the caller, who must close it with
+    /// `CloseHandle`, and can only be exported once. A global share handle (`opaque_win32_kmt`)
+    /// is owned by the memory and stays valid until it's freed.
+    #[inline]
+    pub fn export_win32_handle(
+        &self,
+        handle_type: ExternalMemoryHandleType,
+    ) -> Result<ash::vk::HANDLE, DeviceMemoryAllocError> {
+        let fns = self.device.fns();
+
+        if !self.device.enabled_extensions().khr_external_memory_win32 {
+            return Err(DeviceMemoryAllocError::MissingExtension(
+                "khr_external_memory_win32",
+            ));
+        }
+
+        // VUID-VkMemoryGetWin32HandleInfoKHR-handleType-00664: "handleType must be defined as an
+        // NT handle or a global share handle".
+        let bits = ash::vk::ExternalMemoryHandleTypeFlags::from(handle_type);
+        if !bits.as_raw().is_power_of_two() || !WIN32_HANDLE_TYPES.contains(bits) {
+            return Err(DeviceMemoryAllocError::SpecViolation(664))?;
+        }
+
+        // VUID-VkMemoryGetWin32HandleInfoKHR-handleType-00662: "handleType must have been
+        // included in VkExportMemoryAllocateInfo::handleTypes when memory was created".
+        if (bits & ash::vk::ExternalMemoryHandleTypeFlags::from(self.handle_types)).is_empty() {
+            return Err(DeviceMemoryAllocError::SpecViolation(662))?;
+        }
+
+        let handle = unsafe {
+            let info = ash::vk::MemoryGetWin32HandleInfoKHR {
+                memory: self.memory,
+                handle_type: bits,
+                ..Default::default()
+            };
+
+            let mut output = MaybeUninit::uninit();
+            check_errors(fns.khr_external_memory_win32.get_memory_win32_handle_khr(
+                self.device.internal_object(),
+                &info,
+                output.as_mut_ptr(),
+            ))?;
+            output.assume_init()
+        };
+
+        Ok(handle)
+    }
 }
 
 unsafe impl DeviceOwned for DeviceMemory {
@@ -1086,6 +1163,7 @@ impl From<OomError> for DeviceMemoryAllocError {
 mod tests {
     use crate::memory::DeviceMemory;
     use crate::memory::DeviceMemoryAllocError;
+    use crate::memory::ExternalMemoryHandleType;
     use crate::OomError;
 
     #[test]
@@ -1104,6 +1182,21 @@ mod tests {
         });
     }
 
+    #[test]
+    fn export_win32_missing_extension() {
+        let (device, _) = gfx_dev_and_queue!();
+        let mem_ty = device.physical_device().memory_types().next().unwrap();
+        let memory = DeviceMemory::alloc(device.clone(), mem_ty, 256).unwrap();
+
+        match memory.export_win32_handle(ExternalMemoryHandleType {
+            opaque_win32: true,
+            ..ExternalMemoryHandleType::none()
+        }) {
+            Err(DeviceMemoryAllocError::MissingExtension("khr_external_memory_win32")) => (),
+            _ => panic!(),
+        }
+    }
+
     #[test]
     #[cfg(target_pointer_width = "64")]
     fn oom_single() {
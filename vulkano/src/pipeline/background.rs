@@ -0,0 +1,379 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Building pipelines on worker threads, and using a fallback pipeline in the meantime.
+//!
+//! Building a pipeline can take long enough for the driver to compile the shaders that doing it
+//! while recording a frame causes a visible hitch. A [`PipelineCompiler`] instead builds the
+//! pipelines on its worker threads. Each request returns a [`BackgroundPipeline`] handle right
+//! away, which returns a fallback pipeline given with the request, for example one that draws
+//! the objects untextured, until the real pipeline is built. The handle then returns the real
+//! pipeline.
+//!
+//! The fallback is used in place of the real pipeline, so it must be compatible with the way the
+//! pipeline is used: same pipeline layout, same vertex input and same render pass.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use vulkano::device::Device;
+//! # use vulkano::debug_draw::DebugVertex as Vertex;
+//! # use vulkano::pipeline::shader::GraphicsEntryPoint;
+//! # use vulkano::pipeline::GraphicsPipeline;
+//! # use vulkano::pipeline::GraphicsPipelineCreationError;
+//! # use vulkano::render_pass::Subpass;
+//! # let device: Arc<Device> = return;
+//! # let untextured: Arc<GraphicsPipeline> = return;
+//! # let subpass: Subpass = return;
+//! # let vs: GraphicsEntryPoint<'static> = return;
+//! # let fs: GraphicsEntryPoint<'static> = return;
+//! use vulkano::pipeline::background::PipelineCompiler;
+//! use vulkano::pipeline::cache::PipelineCache;
+//!
+//! fn build(
+//!     device: Arc<Device>,
+//!     cache: &Arc<PipelineCache>,
+//!     subpass: Subpass,
+//!     vs: GraphicsEntryPoint,
+//!     fs: GraphicsEntryPoint,
+//! ) -> Result<GraphicsPipeline, GraphicsPipelineCreationError> {
+//!     GraphicsPipeline::start()
+//!         .vertex_input_single_buffer::<Vertex>()
+//!         .vertex_shader(vs, ())
+//!         .triangle_list()
+//!         .viewports_dynamic_scissors_irrelevant(1)
+//!         .fragment_shader(fs, ())
+//!         .render_pass(subpass)
+//!         // Lets the driver reuse the shaders that other pipelines already compiled.
+//!         .build_with_cache(cache.clone())
+//!         .build(device)
+//! }
+//!
+//! let cache = PipelineCache::empty(device.clone()).unwrap();
+//! let compiler = PipelineCompiler::new(cache, 2);
+//!
+//! let pipeline = compiler.compile(untextured, move |cache| {
+//!     Ok(Arc::new(build(device, cache, subpass, vs, fs)?))
+//! });
+//!
+//! loop {
+//!     // Returns `untextured` until the pipeline is built.
+//!     let current_pipeline = pipeline.get();
+//!     // ...
+//! #   break;
+//! }
+//! ```
+
+use crate::pipeline::cache::PipelineCache;
+use crate::pipeline::hot_reload::PipelineBuildError;
+use std::any::Any;
+use std::collections::VecDeque;
+use std::fmt;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::thread;
+
+/// Builds pipelines on worker threads.
+///
+/// See [the module-level documentation](self) for more information.
+pub struct PipelineCompiler {
+    shared: Arc<Shared>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+struct Shared {
+    cache: Arc<PipelineCache>,
+    state: Mutex<State>,
+    // Notified when a job is queued or when the workers must stop.
+    job_condvar: Condvar,
+    // Notified when the last job is finished.
+    idle_condvar: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    jobs: VecDeque<Job>,
+    // Number of jobs that are queued or being run.
+    pending: usize,
+    errors: Vec<PipelineBuildError>,
+    stop: bool,
+}
+
+type Job = Box<dyn FnOnce(&Arc<PipelineCache>) -> Result<(), PipelineBuildError> + Send>;
+
+impl PipelineCompiler {
+    /// Builds a new `PipelineCompiler` with `num_threads` worker threads. Pipelines are built
+    /// with `cache`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `num_threads` is 0.
+    ///
+    pub fn new(cache: Arc<PipelineCache>, num_threads: usize) -> PipelineCompiler {
+        assert_ne!(num_threads, 0);
+
+        let shared = Arc::new(Shared {
+            cache,
+            state: Mutex::new(State::default()),
+            job_condvar: Condvar::new(),
+            idle_condvar: Condvar::new(),
+        });
+
+        let threads = (0..num_threads)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || shared.run())
+            })
+            .collect();
+
+        PipelineCompiler { shared, threads }
+    }
+
+    /// Queues a pipeline to be built by `build` on one of the worker threads, and returns a
+    /// handle that returns `fallback` until then.
+    ///
+    /// `build` is given the pipeline cache of the compiler. If it returns an error, the handle
+    /// keeps returning `fallback`, and the error is returned by `take_errors`. The same happens if
+    /// `build` panics, in which case the error contains the message of the panic.
+    pub fn compile<P, F>(&self, fallback: Arc<P>, build: F) -> BackgroundPipeline<P>
+    where
+        P: Send + Sync + 'static,
+        F: FnOnce(&Arc<PipelineCache>) -> Result<Arc<P>, PipelineBuildError> + Send + 'static,
+    {
+        let handle = BackgroundPipeline {
+            slot: Arc::new(Slot {
+                fallback,
+                compiled: Mutex::new(None),
+            }),
+        };
+
+        let slot = handle.slot.clone();
+        let job: Job = Box::new(move |cache| {
+            let pipeline = build(cache)?;
+            *slot.compiled.lock().unwrap() = Some(pipeline);
+            Ok(())
+        });
+
+        let mut state = self.shared.state.lock().unwrap();
+        state.jobs.push_back(job);
+        state.pending += 1;
+        self.shared.job_condvar.notify_one();
+        drop(state);
+
+        handle
+    }
+
+    /// Returns the number of pipelines that are queued or being built.
+    #[inline]
+    pub fn num_pending(&self) -> usize {
+        self.shared.state.lock().unwrap().pending
+    }
+
+    /// Blocks until all the pipelines that were queued are built, for example at the end of a
+    /// loading screen.
+    pub fn wait_idle(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+        while state.pending != 0 {
+            state = self.shared.idle_condvar.wait(state).unwrap();
+        }
+    }
+
+    /// Returns the errors that the closures that build pipelines returned since the last call.
+    #[inline]
+    pub fn take_errors(&self) -> Vec<PipelineBuildError> {
+        std::mem::take(&mut self.shared.state.lock().unwrap().errors)
+    }
+}
+
+impl Shared {
+    // The loop of a worker thread.
+    fn run(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.stop {
+                return;
+            }
+
+            let job = match state.jobs.pop_front() {
+                Some(job) => job,
+                None => {
+                    state = self.job_condvar.wait(state).unwrap();
+                    continue;
+                }
+            };
+
+            drop(state);
+            // A panicking closure must not kill the worker, otherwise the job would stay pending
+            // forever and `wait_idle` would never return.
+            let result = match panic::catch_unwind(AssertUnwindSafe(|| job(&self.cache))) {
+                Ok(result) => result,
+                Err(payload) => Err(panic_message(payload).into()),
+            };
+            state = self.state.lock().unwrap();
+
+            if let Err(err) = result {
+                state.errors.push(err);
+            }
+            state.pending -= 1;
+            if state.pending == 0 {
+                self.idle_condvar.notify_all();
+            }
+        }
+    }
+}
+
+// Turns the payload of a panic into the message of the error that is returned in its place.
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        format!("the pipeline build panicked: {}", message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        format!("the pipeline build panicked: {}", message)
+    } else {
+        "the pipeline build panicked".to_owned()
+    }
+}
+
+impl Drop for PipelineCompiler {
+    // Waits for the pipelines that are being built. The pipelines that are still queued are not
+    // built, and their handles keep returning their fallback.
+    fn drop(&mut self) {
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            state.stop = true;
+            state.pending -= state.jobs.len();
+            state.jobs.clear();
+        }
+        self.shared.job_condvar.notify_all();
+
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl fmt::Debug for PipelineCompiler {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("PipelineCompiler")
+            .field("pending", &self.num_pending())
+            .field("worker_threads", &self.threads.len())
+            .finish()
+    }
+}
+
+/// Handle to a pipeline that is built by a `PipelineCompiler`.
+///
+/// Cloning the handle is cheap, and the clones always return the same pipeline.
+pub struct BackgroundPipeline<P> {
+    slot: Arc<Slot<P>>,
+}
+
+struct Slot<P> {
+    fallback: Arc<P>,
+    compiled: Mutex<Option<Arc<P>>>,
+}
+
+impl<P> BackgroundPipeline<P> {
+    /// Returns the pipeline if it is built, and the fallback otherwise.
+    #[inline]
+    pub fn get(&self) -> Arc<P> {
+        match *self.slot.compiled.lock().unwrap() {
+            Some(ref pipeline) => pipeline.clone(),
+            None => self.slot.fallback.clone(),
+        }
+    }
+
+    /// Returns true if the pipeline is built, in other words if `get` doesn't return the
+    /// fallback.
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.slot.compiled.lock().unwrap().is_some()
+    }
+}
+
+impl<P> Clone for BackgroundPipeline<P> {
+    #[inline]
+    fn clone(&self) -> BackgroundPipeline<P> {
+        BackgroundPipeline {
+            slot: self.slot.clone(),
+        }
+    }
+}
+
+impl<P> fmt::Debug for BackgroundPipeline<P>
+where
+    P: fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("BackgroundPipeline")
+            .field("current", &self.get())
+            .field("ready", &self.is_ready())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pipeline::background::PipelineCompiler;
+    use crate::pipeline::cache::PipelineCache;
+    use std::sync::mpsc;
+    use std::sync::Arc;
+
+    #[test]
+    fn fallback_then_compiled() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let cache = PipelineCache::empty(device).unwrap();
+        let compiler = PipelineCompiler::new(cache, 1);
+
+        // The pipeline is only built once the test allows it.
+        let (sender, receiver) = mpsc::channel::<()>();
+        let pipeline = compiler.compile(Arc::new(0), move |_| {
+            receiver.recv().unwrap();
+            Ok(Arc::new(1))
+        });
+        let failing = compiler.compile(Arc::new(2), |_| Err("failed".into()));
+
+        assert_eq!(*pipeline.get(), 0);
+        assert!(!pipeline.is_ready());
+        assert_eq!(compiler.num_pending(), 2);
+
+        sender.send(()).unwrap();
+        compiler.wait_idle();
+        assert_eq!(compiler.num_pending(), 0);
+        assert_eq!(*pipeline.get(), 1);
+        assert!(pipeline.clone().is_ready());
+        assert_eq!(*failing.get(), 2);
+        assert!(!failing.is_ready());
+        assert_eq!(compiler.take_errors().len(), 1);
+    }
+
+    #[test]
+    fn panicking_build() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let cache = PipelineCache::empty(device).unwrap();
+        let compiler = PipelineCompiler::new(cache, 1);
+
+        let panicking = compiler.compile(Arc::new(0), |_| panic!("broken shader"));
+        let pipeline = compiler.compile(Arc::new(1), |_| Ok(Arc::new(2)));
+
+        compiler.wait_idle();
+        assert_eq!(compiler.num_pending(), 0);
+        assert_eq!(*panicking.get(), 0);
+        assert_eq!(*pipeline.get(), 2);
+
+        let errors = compiler.take_errors();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("broken shader"));
+    }
+}
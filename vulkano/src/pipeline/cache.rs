@@ -23,6 +23,7 @@
 
 use crate::check_errors;
 use crate::device::Device;
+use crate::device::DeviceOwned;
 use crate::OomError;
 use crate::VulkanObject;
 use std::mem::MaybeUninit;
@@ -223,6 +224,13 @@ impl PipelineCache {
     }
 }
 
+unsafe impl DeviceOwned for PipelineCache {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
 unsafe impl VulkanObject for PipelineCache {
     type Object = ash::vk::PipelineCache;
 
@@ -8,15 +8,17 @@
 // according to those terms.
 
 use crate::check_errors;
-use crate::descriptor_set::layout::DescriptorSetLayout;
 use crate::device::Device;
 use crate::device::DeviceOwned;
 use crate::pipeline::cache::PipelineCache;
 use crate::pipeline::layout::PipelineLayout;
 use crate::pipeline::layout::PipelineLayoutCreationError;
 use crate::pipeline::layout::PipelineLayoutSupersetError;
+use crate::pipeline::shader::check_specialization_constants;
 use crate::pipeline::shader::EntryPointAbstract;
 use crate::pipeline::shader::SpecializationConstants;
+use crate::pipeline::shader::SpecializationConstantsError;
+use crate::pipeline::shader::SubgroupSizeControl;
 use crate::Error;
 use crate::OomError;
 use crate::VulkanObject;
@@ -25,6 +27,7 @@ use std::fmt;
 use std::marker::PhantomData;
 use std::mem;
 use std::mem::MaybeUninit;
+use std::panic::Location;
 use std::ptr;
 use std::sync::Arc;
 
@@ -51,6 +54,8 @@ struct Inner {
 
 impl ComputePipeline {
     /// Builds a new `ComputePipeline`.
+    #[inline]
+    #[track_caller]
     pub fn new<Cs, Css>(
         device: Arc<Device>,
         shader: &Cs,
@@ -61,27 +66,50 @@ impl ComputePipeline {
         Cs: EntryPointAbstract,
         Css: SpecializationConstants,
     {
+        ComputePipeline::with_subgroup_size_control(
+            device,
+            shader,
+            spec_constants,
+            SubgroupSizeControl::default(),
+            cache,
+        )
+    }
+
+    /// Same as `new`, but also controls the size of the subgroups that the shader is run with.
+    ///
+    /// An error will be returned if `subgroup_size_control` requires a feature that isn't
+    /// enabled, or a subgroup size that the device doesn't support for compute shaders.
+    #[track_caller]
+    pub fn with_subgroup_size_control<Cs, Css>(
+        device: Arc<Device>,
+        shader: &Cs,
+        spec_constants: &Css,
+        subgroup_size_control: SubgroupSizeControl,
+        cache: Option<Arc<PipelineCache>>,
+    ) -> Result<ComputePipeline, ComputePipelineCreationError>
+    where
+        Cs: EntryPointAbstract,
+        Css: SpecializationConstants,
+    {
+        check_specialization_constants(
+            shader.spec_constants(),
+            Css::descriptors(),
+            mem::size_of_val(spec_constants),
+        )?;
+        check_subgroup_size_control(&device, &subgroup_size_control, shader.local_size())?;
+
         unsafe {
-            let descriptor_set_layouts = shader
-                .descriptor_set_layout_descs()
-                .iter()
-                .map(|desc| {
-                    Ok(Arc::new(DescriptorSetLayout::new(
-                        device.clone(),
-                        desc.clone(),
-                    )?))
-                })
-                .collect::<Result<Vec<_>, OomError>>()?;
-            let pipeline_layout = Arc::new(PipelineLayout::new(
+            let pipeline_layout = PipelineLayout::from_descs(
                 device.clone(),
-                descriptor_set_layouts,
+                shader.descriptor_set_layout_descs().iter().cloned(),
                 shader.push_constant_range().iter().cloned(),
-            )?);
-            ComputePipeline::with_unchecked_pipeline_layout(
+            )?;
+            ComputePipeline::create(
                 device,
                 shader,
                 spec_constants,
                 pipeline_layout,
+                &subgroup_size_control,
                 cache,
             )
         }
@@ -91,6 +119,7 @@ impl ComputePipeline {
     ///
     /// An error will be returned if the pipeline layout isn't a superset of what the shader
     /// uses.
+    #[track_caller]
     pub fn with_pipeline_layout<Cs, Css>(
         device: Arc<Device>,
         shader: &Cs,
@@ -102,9 +131,11 @@ impl ComputePipeline {
         Cs: EntryPointAbstract,
         Css: SpecializationConstants,
     {
-        if Css::descriptors() != shader.spec_constants() {
-            return Err(ComputePipelineCreationError::IncompatibleSpecializationConstants);
-        }
+        check_specialization_constants(
+            shader.spec_constants(),
+            Css::descriptors(),
+            mem::size_of_val(spec_constants),
+        )?;
 
         unsafe {
             pipeline_layout.ensure_superset_of(
@@ -123,6 +154,7 @@ impl ComputePipeline {
 
     /// Same as `with_pipeline_layout`, but doesn't check whether the pipeline layout is a
     /// superset of what the shader expects.
+    #[track_caller]
     pub unsafe fn with_unchecked_pipeline_layout<Cs, Css>(
         device: Arc<Device>,
         shader: &Cs,
@@ -130,6 +162,29 @@ impl ComputePipeline {
         pipeline_layout: Arc<PipelineLayout>,
         cache: Option<Arc<PipelineCache>>,
     ) -> Result<ComputePipeline, ComputePipelineCreationError>
+    where
+        Cs: EntryPointAbstract,
+        Css: SpecializationConstants,
+    {
+        ComputePipeline::create(
+            device,
+            shader,
+            spec_constants,
+            pipeline_layout,
+            &SubgroupSizeControl::default(),
+            cache,
+        )
+    }
+
+    #[track_caller]
+    unsafe fn create<Cs, Css>(
+        device: Arc<Device>,
+        shader: &Cs,
+        spec_constants: &Css,
+        pipeline_layout: Arc<PipelineLayout>,
+        subgroup_size_control: &SubgroupSizeControl,
+        cache: Option<Arc<PipelineCache>>,
+    ) -> Result<ComputePipeline, ComputePipelineCreationError>
     where
         Cs: EntryPointAbstract,
         Css: SpecializationConstants,
@@ -145,8 +200,24 @@ impl ComputePipeline {
                 p_data: spec_constants as *const Css as *const _,
             };
 
+            let required_subgroup_size =
+                subgroup_size_control
+                    .required_subgroup_size
+                    .map(|required_subgroup_size| {
+                        ash::vk::PipelineShaderStageRequiredSubgroupSizeCreateInfoEXT {
+                            required_subgroup_size,
+                            ..Default::default()
+                        }
+                    });
+
             let stage = ash::vk::PipelineShaderStageCreateInfo {
-                flags: ash::vk::PipelineShaderStageCreateFlags::empty(),
+                p_next: match required_subgroup_size {
+                    Some(ref required_subgroup_size) => {
+                        required_subgroup_size as *const _ as *const _
+                    }
+                    None => ptr::null(),
+                },
+                flags: subgroup_size_control.flags(),
                 stage: ash::vk::ShaderStageFlags::COMPUTE,
                 module: shader.module().internal_object(),
                 p_name: shader.name().as_ptr(),
@@ -173,6 +244,8 @@ impl ComputePipeline {
             };
 
             let mut output = MaybeUninit::uninit();
+            #[cfg(feature = "tracy")]
+            let _zone = crate::tracy::zone("vkCreateComputePipelines");
             check_errors(fns.v1_0.create_compute_pipelines(
                 device.internal_object(),
                 cache_handle,
@@ -184,13 +257,18 @@ impl ComputePipeline {
             output.assume_init()
         };
 
-        Ok(ComputePipeline {
+        let pipeline = ComputePipeline {
             inner: Inner {
                 device: device.clone(),
                 pipeline: pipeline,
             },
             pipeline_layout: pipeline_layout,
-        })
+        };
+        pipeline
+            .inner
+            .device
+            .auto_debug_name(&pipeline, Location::caller());
+        Ok(pipeline)
     }
 
     /// Returns the `Device` this compute pipeline was created with.
@@ -213,6 +291,77 @@ impl fmt::Debug for ComputePipeline {
     }
 }
 
+// Checks that `control` can be used for the compute shader of a pipeline on `device`.
+fn check_subgroup_size_control(
+    device: &Device,
+    control: &SubgroupSizeControl,
+    local_size: Option<[u32; 3]>,
+) -> Result<(), ComputePipelineCreationError> {
+    let features = device.enabled_features();
+    let properties = device.physical_device().properties();
+
+    if (control.required_subgroup_size.is_some() || control.allow_varying_subgroup_size)
+        && !features.subgroup_size_control
+    {
+        return Err(ComputePipelineCreationError::SubgroupSizeControlFeatureNotEnabled);
+    }
+
+    if control.require_full_subgroups && !features.compute_full_subgroups {
+        return Err(ComputePipelineCreationError::ComputeFullSubgroupsFeatureNotEnabled);
+    }
+
+    if let Some(required_subgroup_size) = control.required_subgroup_size {
+        if control.allow_varying_subgroup_size {
+            return Err(ComputePipelineCreationError::RequiredAndVaryingSubgroupSize);
+        }
+
+        let stages_supported = matches!(
+            properties.required_subgroup_size_stages,
+            Some(stages) if stages.compute
+        );
+        let min = properties.min_subgroup_size.unwrap_or(0);
+        let max = properties.max_subgroup_size.unwrap_or(0);
+
+        if !stages_supported
+            || !required_subgroup_size.is_power_of_two()
+            || required_subgroup_size < min
+            || required_subgroup_size > max
+        {
+            return Err(
+                ComputePipelineCreationError::RequiredSubgroupSizeNotSupported {
+                    required: required_subgroup_size,
+                    min,
+                    max,
+                },
+            );
+        }
+    }
+
+    if control.require_full_subgroups {
+        let subgroup_size = if let Some(required_subgroup_size) = control.required_subgroup_size {
+            required_subgroup_size
+        } else if control.allow_varying_subgroup_size {
+            properties.max_subgroup_size.unwrap_or(0)
+        } else {
+            properties.subgroup_size.unwrap_or(0)
+        };
+
+        match local_size {
+            Some([x, _, _]) if subgroup_size != 0 && x % subgroup_size == 0 => (),
+            _ => {
+                return Err(
+                    ComputePipelineCreationError::LocalSizeNotMultipleOfSubgroupSize {
+                        local_size_x: local_size.map(|[x, _, _]| x),
+                        subgroup_size,
+                    },
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Opaque object that represents the inside of the compute pipeline. Can be made into a trait
 /// object.
 #[derive(Debug, Copy, Clone)]
@@ -264,7 +413,31 @@ pub enum ComputePipelineCreationError {
     /// The pipeline layout is not compatible with what the shader expects.
     IncompatiblePipelineLayout(PipelineLayoutSupersetError),
     /// The provided specialization constants are not compatible with what the shader expects.
-    IncompatibleSpecializationConstants,
+    IncompatibleSpecializationConstants(SpecializationConstantsError),
+    /// The `subgroup_size_control` feature must be enabled in order to control the subgroup size.
+    SubgroupSizeControlFeatureNotEnabled,
+    /// The `compute_full_subgroups` feature must be enabled in order to require full subgroups.
+    ComputeFullSubgroupsFeatureNotEnabled,
+    /// A required subgroup size and a varying subgroup size were both requested.
+    RequiredAndVaryingSubgroupSize,
+    /// The required subgroup size is not a power of two, is outside of the range supported by the
+    /// device, or the device doesn't support requiring a subgroup size for compute shaders.
+    RequiredSubgroupSizeNotSupported {
+        /// The required subgroup size.
+        required: u32,
+        /// The `min_subgroup_size` property of the device.
+        min: u32,
+        /// The `max_subgroup_size` property of the device.
+        max: u32,
+    },
+    /// Full subgroups were required, but the local size of the shader along the X dimension isn't
+    /// known, or isn't a multiple of the subgroup size.
+    LocalSizeNotMultipleOfSubgroupSize {
+        /// The local size along the X dimension, or `None` if it isn't known.
+        local_size_x: Option<u32>,
+        /// The subgroup size that the local size must be a multiple of.
+        subgroup_size: u32,
+    },
 }
 
 impl error::Error for ComputePipelineCreationError {
@@ -274,7 +447,8 @@ impl error::Error for ComputePipelineCreationError {
             ComputePipelineCreationError::OomError(ref err) => Some(err),
             ComputePipelineCreationError::PipelineLayoutCreationError(ref err) => Some(err),
             ComputePipelineCreationError::IncompatiblePipelineLayout(ref err) => Some(err),
-            ComputePipelineCreationError::IncompatibleSpecializationConstants => None,
+            ComputePipelineCreationError::IncompatibleSpecializationConstants(ref err) => Some(err),
+            _ => None,
         }
     }
 }
@@ -293,14 +467,37 @@ impl fmt::Display for ComputePipelineCreationError {
                 ComputePipelineCreationError::IncompatiblePipelineLayout(_) => {
                     "the pipeline layout is not compatible with what the shader expects"
                 }
-                ComputePipelineCreationError::IncompatibleSpecializationConstants => {
+                ComputePipelineCreationError::IncompatibleSpecializationConstants(_) => {
                     "the provided specialization constants are not compatible with what the shader expects"
                 }
+                ComputePipelineCreationError::SubgroupSizeControlFeatureNotEnabled => {
+                    "the `subgroup_size_control` feature must be enabled in order to control the subgroup size"
+                }
+                ComputePipelineCreationError::ComputeFullSubgroupsFeatureNotEnabled => {
+                    "the `compute_full_subgroups` feature must be enabled in order to require full subgroups"
+                }
+                ComputePipelineCreationError::RequiredAndVaryingSubgroupSize => {
+                    "a required subgroup size and a varying subgroup size were both requested"
+                }
+                ComputePipelineCreationError::RequiredSubgroupSizeNotSupported { .. } => {
+                    "the required subgroup size is not supported by the device for compute shaders"
+                }
+                ComputePipelineCreationError::LocalSizeNotMultipleOfSubgroupSize { .. } => {
+                    "full subgroups were required, but the local size of the shader along the X \
+                     dimension isn't known or isn't a multiple of the subgroup size"
+                }
             }
         )
     }
 }
 
+impl From<SpecializationConstantsError> for ComputePipelineCreationError {
+    #[inline]
+    fn from(err: SpecializationConstantsError) -> ComputePipelineCreationError {
+        ComputePipelineCreationError::IncompatibleSpecializationConstants(err)
+    }
+}
+
 impl From<OomError> for ComputePipelineCreationError {
     #[inline]
     fn from(err: OomError) -> ComputePipelineCreationError {
@@ -348,16 +545,82 @@ mod tests {
     use crate::descriptor_set::layout::DescriptorDescTy;
     use crate::descriptor_set::layout::DescriptorSetDesc;
     use crate::descriptor_set::PersistentDescriptorSet;
+    use crate::pipeline::compute_pipeline::check_subgroup_size_control;
     use crate::pipeline::shader::ShaderModule;
     use crate::pipeline::shader::ShaderStages;
     use crate::pipeline::shader::SpecializationConstants;
     use crate::pipeline::shader::SpecializationMapEntry;
+    use crate::pipeline::shader::SubgroupSizeControl;
     use crate::pipeline::ComputePipeline;
+    use crate::pipeline::ComputePipelineCreationError;
     use crate::sync::now;
     use crate::sync::GpuFuture;
     use std::ffi::CStr;
     use std::sync::Arc;
 
+    #[test]
+    fn subgroup_size_control_feature_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let control = SubgroupSizeControl {
+            required_subgroup_size: Some(32),
+            ..Default::default()
+        };
+        match check_subgroup_size_control(&device, &control, None) {
+            Err(ComputePipelineCreationError::SubgroupSizeControlFeatureNotEnabled) => (),
+            _ => panic!(),
+        }
+
+        let control = SubgroupSizeControl {
+            require_full_subgroups: true,
+            ..Default::default()
+        };
+        match check_subgroup_size_control(&device, &control, None) {
+            Err(ComputePipelineCreationError::ComputeFullSubgroupsFeatureNotEnabled) => (),
+            _ => panic!(),
+        }
+
+        assert!(
+            check_subgroup_size_control(&device, &SubgroupSizeControl::default(), None).is_ok()
+        );
+    }
+
+    #[test]
+    fn full_subgroups_local_size() {
+        let (device, _) = gfx_dev_and_queue!(subgroup_size_control, compute_full_subgroups);
+
+        let min = device
+            .physical_device()
+            .properties()
+            .min_subgroup_size
+            .unwrap();
+        let control = SubgroupSizeControl {
+            required_subgroup_size: Some(min),
+            require_full_subgroups: true,
+            ..Default::default()
+        };
+
+        match check_subgroup_size_control(&device, &control, None) {
+            Err(ComputePipelineCreationError::LocalSizeNotMultipleOfSubgroupSize {
+                local_size_x: None,
+                ..
+            }) => (),
+            _ => panic!(),
+        }
+
+        if min > 1 {
+            match check_subgroup_size_control(&device, &control, Some([min + 1, 1, 1])) {
+                Err(ComputePipelineCreationError::LocalSizeNotMultipleOfSubgroupSize {
+                    local_size_x: Some(x),
+                    subgroup_size,
+                }) if x == min + 1 && subgroup_size == min => (),
+                _ => panic!(),
+            }
+        }
+
+        assert!(check_subgroup_size_control(&device, &control, Some([min * 2, 1, 1])).is_ok());
+    }
+
     // TODO: test for basic creation
     // TODO: test for pipeline layout error
 
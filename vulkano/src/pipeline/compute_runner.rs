@@ -0,0 +1,499 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::buffer::BufferAccess;
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::BuildError;
+use crate::command_buffer::CommandBufferExecError;
+use crate::command_buffer::CommandBufferExecFuture;
+use crate::command_buffer::CommandBufferUsage;
+use crate::command_buffer::DispatchError;
+use crate::command_buffer::PrimaryAutoCommandBuffer;
+use crate::command_buffer::PrimaryCommandBuffer;
+use crate::descriptor_set::DescriptorSet;
+use crate::descriptor_set::PersistentDescriptorSet;
+use crate::descriptor_set::PersistentDescriptorSetBuildError;
+use crate::descriptor_set::PersistentDescriptorSetError;
+use crate::device::Device;
+use crate::device::Queue;
+use crate::pipeline::cache::PipelineCache;
+use crate::pipeline::shader::EntryPointAbstract;
+use crate::pipeline::shader::ShaderModule;
+use crate::pipeline::ComputePipeline;
+use crate::pipeline::ComputePipelineCreationError;
+use crate::sync::NowFuture;
+use crate::DeviceSize;
+use crate::OomError;
+use crate::VulkanObject;
+use fnv::FnvHashMap;
+use std::collections::VecDeque;
+use std::error;
+use std::ffi::CString;
+use std::fmt;
+use std::ptr;
+use std::sync::Arc;
+
+/// Runs compute shaders with a single call, for applications that only use the GPU for
+/// computations.
+///
+/// `run` creates the compute pipeline of the shader and the descriptor set of the buffers,
+/// records a command buffer that dispatches the shader, and submits it. The pipelines and the
+/// descriptor sets are cached, so running the same shader again with the same buffers only
+/// records and submits a command buffer.
+///
+/// The buffers are bound to the descriptors of the descriptor set 0 of the shader, in order. The
+/// cached pipelines keep their shader module alive, and the cached descriptor sets keep their
+/// buffers alive. Only the descriptor sets of the last 32 distinct lists of buffers are kept, and
+/// `clear_cache` drops all of them.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use vulkano::buffer::CpuAccessibleBuffer;
+/// # use vulkano::device::Queue;
+/// # use vulkano::pipeline::shader::ComputeEntryPoint;
+/// # use vulkano::pipeline::shader::ShaderModule;
+/// # let queue: Arc<Queue> = return;
+/// # let module: Arc<ShaderModule> = return;
+/// # let shader: ComputeEntryPoint = return;
+/// # let data: Arc<CpuAccessibleBuffer<[u32]>> = return;
+/// use vulkano::pipeline::ComputeRunner;
+/// use vulkano::sync::GpuFuture;
+///
+/// let mut runner = ComputeRunner::new(queue, None);
+///
+/// // Multiplies each of the 65536 values of `data` by 12, with 64 invocations per group.
+/// runner
+///     .run(&module, &shader, 12u32, &[data.clone()], [1024, 1, 1])
+///     .unwrap()
+///     .then_signal_fence_and_flush()
+///     .unwrap()
+///     .wait(None)
+///     .unwrap();
+///
+/// let content = data.read().unwrap();
+/// ```
+pub struct ComputeRunner {
+    queue: Arc<Queue>,
+    cache: Option<Arc<PipelineCache>>,
+    // The pipelines, by shader module and name of the entry point. The module is kept alive so
+    // that its handle can't be reused by another module while the pipeline is cached.
+    pipelines:
+        FnvHashMap<(ash::vk::ShaderModule, CString), (Arc<ShaderModule>, Arc<ComputePipeline>)>,
+    // The descriptor sets, by pipeline and buffers, from the least recently used. The buffers are
+    // kept alive by the descriptor sets, so their handles can't be reused either.
+    descriptor_sets: VecDeque<(DescriptorSetKey, Arc<dyn DescriptorSet + Send + Sync>)>,
+}
+
+// Maximum number of descriptor sets that a `ComputeRunner` caches.
+const MAX_CACHED_DESCRIPTOR_SETS: usize = 32;
+
+type DescriptorSetKey = (
+    ash::vk::Pipeline,
+    Vec<(ash::vk::Buffer, DeviceSize, DeviceSize)>,
+);
+
+impl ComputeRunner {
+    /// Builds a new `ComputeRunner` that submits to `queue`. The pipelines are created with
+    /// `cache` if it is `Some`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the family of `queue` doesn't support compute operations.
+    ///
+    pub fn new(queue: Arc<Queue>, cache: Option<Arc<PipelineCache>>) -> ComputeRunner {
+        assert!(queue.family().supports_compute());
+
+        ComputeRunner {
+            queue,
+            cache,
+            pipelines: FnvHashMap::default(),
+            descriptor_sets: VecDeque::new(),
+        }
+    }
+
+    /// Returns the device of the runner.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        self.queue.device()
+    }
+
+    /// Dispatches `group_counts` work groups of `shader`, which is an entry point of `module`,
+    /// with the given push constants and with `buffers` bound to the descriptors of the
+    /// descriptor set 0, and submits the command buffer.
+    ///
+    /// Pass `()` as `push_constants` if the shader doesn't use push constants. The specialization
+    /// constants of the shader keep their default values. The returned future must be flushed for
+    /// the work to start.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `shader` isn't an entry point of `module`.
+    ///
+    pub fn run<Cs, Pc>(
+        &mut self,
+        module: &Arc<ShaderModule>,
+        shader: &Cs,
+        push_constants: Pc,
+        buffers: &[Arc<dyn BufferAccess + Send + Sync>],
+        group_counts: [u32; 3],
+    ) -> Result<CommandBufferExecFuture<NowFuture, PrimaryAutoCommandBuffer>, ComputeRunError>
+    where
+        Cs: EntryPointAbstract,
+    {
+        assert!(ptr::eq(shader.module(), &**module));
+
+        let pipeline = self.pipeline(module, shader)?;
+        let descriptor_sets = match self.descriptor_set(&pipeline, buffers)? {
+            Some(descriptor_set) => vec![descriptor_set],
+            None => Vec::new(),
+        };
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.device().clone(),
+            self.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        builder.dispatch(group_counts, pipeline, descriptor_sets, push_constants)?;
+        let command_buffer = builder.build()?;
+
+        Ok(command_buffer.execute(self.queue.clone())?)
+    }
+
+    /// Drops the cached pipelines and descriptor sets, and the buffers of the descriptor sets.
+    #[inline]
+    pub fn clear_cache(&mut self) {
+        self.pipelines.clear();
+        self.descriptor_sets.clear();
+    }
+
+    // Returns the pipeline of `shader`, creating it if needed.
+    fn pipeline<Cs>(
+        &mut self,
+        module: &Arc<ShaderModule>,
+        shader: &Cs,
+    ) -> Result<Arc<ComputePipeline>, ComputeRunError>
+    where
+        Cs: EntryPointAbstract,
+    {
+        let key = (module.internal_object(), shader.name().to_owned());
+        if let Some((_, pipeline)) = self.pipelines.get(&key) {
+            return Ok(pipeline.clone());
+        }
+
+        if shader.descriptor_set_layout_descs().len() > 1 {
+            return Err(ComputeRunError::TooManyDescriptorSets);
+        }
+
+        let pipeline = Arc::new(ComputePipeline::new(
+            self.device().clone(),
+            shader,
+            &(),
+            self.cache.clone(),
+        )?);
+        self.pipelines
+            .insert(key, (module.clone(), pipeline.clone()));
+        Ok(pipeline)
+    }
+
+    // Returns the descriptor set of `pipeline` with `buffers`, creating it if needed, or `None`
+    // if the pipeline doesn't have a descriptor set.
+    fn descriptor_set(
+        &mut self,
+        pipeline: &Arc<ComputePipeline>,
+        buffers: &[Arc<dyn BufferAccess + Send + Sync>],
+    ) -> Result<Option<Arc<dyn DescriptorSet + Send + Sync>>, ComputeRunError> {
+        let layout = match pipeline.layout().descriptor_set_layouts().first() {
+            Some(layout) => layout.clone(),
+            None if buffers.is_empty() => return Ok(None),
+            None => return Err(PersistentDescriptorSetError::EmptyExpected.into()),
+        };
+
+        let key = (
+            pipeline.internal_object(),
+            buffers
+                .iter()
+                .map(|buffer| {
+                    let inner = buffer.inner();
+                    (inner.buffer.internal_object(), inner.offset, buffer.size())
+                })
+                .collect(),
+        );
+        if let Some(position) = self.descriptor_sets.iter().position(|(k, _)| *k == key) {
+            // Moves the descriptor set to the back, so that it is evicted last.
+            let entry = self.descriptor_sets.remove(position).unwrap();
+            let descriptor_set = entry.1.clone();
+            self.descriptor_sets.push_back(entry);
+            return Ok(Some(descriptor_set));
+        }
+
+        let descriptor_set = Arc::new(
+            PersistentDescriptorSet::start(layout)
+                .add_buffers(buffers.iter().cloned())?
+                .build()?,
+        ) as Arc<dyn DescriptorSet + Send + Sync>;
+        if self.descriptor_sets.len() == MAX_CACHED_DESCRIPTOR_SETS {
+            self.descriptor_sets.pop_front();
+        }
+        self.descriptor_sets
+            .push_back((key, descriptor_set.clone()));
+        Ok(Some(descriptor_set))
+    }
+}
+
+impl fmt::Debug for ComputeRunner {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("ComputeRunner")
+            .field("queue", &self.queue)
+            .field("pipelines", &self.pipelines.len())
+            .field("descriptor_sets", &self.descriptor_sets.len())
+            .finish()
+    }
+}
+
+/// Error that can happen when running a compute shader with a `ComputeRunner`.
+#[derive(Debug, Clone)]
+pub enum ComputeRunError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The shader uses more than one descriptor set.
+    TooManyDescriptorSets,
+    /// Failed to create the compute pipeline.
+    PipelineCreationError(ComputePipelineCreationError),
+    /// The buffers don't match the descriptors of the shader.
+    DescriptorSetError(PersistentDescriptorSetError),
+    /// Failed to create the descriptor set.
+    DescriptorSetBuildError(PersistentDescriptorSetBuildError),
+    /// The dispatch command is invalid.
+    DispatchError(DispatchError),
+    /// Failed to build the command buffer.
+    BuildError(BuildError),
+    /// Failed to submit the command buffer.
+    ExecError(CommandBufferExecError),
+}
+
+impl error::Error for ComputeRunError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ComputeRunError::OomError(ref err) => Some(err),
+            ComputeRunError::PipelineCreationError(ref err) => Some(err),
+            ComputeRunError::DescriptorSetError(ref err) => Some(err),
+            ComputeRunError::DescriptorSetBuildError(ref err) => Some(err),
+            ComputeRunError::DispatchError(ref err) => Some(err),
+            ComputeRunError::BuildError(ref err) => Some(err),
+            ComputeRunError::ExecError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ComputeRunError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                ComputeRunError::OomError(_) => "not enough memory available",
+                ComputeRunError::TooManyDescriptorSets => {
+                    "the shader uses more than one descriptor set"
+                }
+                ComputeRunError::PipelineCreationError(_) => {
+                    "failed to create the compute pipeline"
+                }
+                ComputeRunError::DescriptorSetError(_) => {
+                    "the buffers don't match the descriptors of the shader"
+                }
+                ComputeRunError::DescriptorSetBuildError(_) => {
+                    "failed to create the descriptor set"
+                }
+                ComputeRunError::DispatchError(_) => "the dispatch command is invalid",
+                ComputeRunError::BuildError(_) => "failed to build the command buffer",
+                ComputeRunError::ExecError(_) => "failed to submit the command buffer",
+            }
+        )
+    }
+}
+
+impl From<OomError> for ComputeRunError {
+    #[inline]
+    fn from(err: OomError) -> ComputeRunError {
+        ComputeRunError::OomError(err)
+    }
+}
+
+impl From<ComputePipelineCreationError> for ComputeRunError {
+    #[inline]
+    fn from(err: ComputePipelineCreationError) -> ComputeRunError {
+        ComputeRunError::PipelineCreationError(err)
+    }
+}
+
+impl From<PersistentDescriptorSetError> for ComputeRunError {
+    #[inline]
+    fn from(err: PersistentDescriptorSetError) -> ComputeRunError {
+        ComputeRunError::DescriptorSetError(err)
+    }
+}
+
+impl From<PersistentDescriptorSetBuildError> for ComputeRunError {
+    #[inline]
+    fn from(err: PersistentDescriptorSetBuildError) -> ComputeRunError {
+        ComputeRunError::DescriptorSetBuildError(err)
+    }
+}
+
+impl From<DispatchError> for ComputeRunError {
+    #[inline]
+    fn from(err: DispatchError) -> ComputeRunError {
+        ComputeRunError::DispatchError(err)
+    }
+}
+
+impl From<BuildError> for ComputeRunError {
+    #[inline]
+    fn from(err: BuildError) -> ComputeRunError {
+        ComputeRunError::BuildError(err)
+    }
+}
+
+impl From<CommandBufferExecError> for ComputeRunError {
+    #[inline]
+    fn from(err: CommandBufferExecError) -> ComputeRunError {
+        ComputeRunError::ExecError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::BufferAccess;
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::descriptor_set::layout::DescriptorBufferDesc;
+    use crate::descriptor_set::layout::DescriptorDesc;
+    use crate::descriptor_set::layout::DescriptorDescTy;
+    use crate::descriptor_set::layout::DescriptorSetDesc;
+    use crate::pipeline::compute_runner::MAX_CACHED_DESCRIPTOR_SETS;
+    use crate::pipeline::shader::ShaderModule;
+    use crate::pipeline::shader::ShaderStages;
+    use crate::pipeline::ComputeRunner;
+    use crate::sync::GpuFuture;
+    use crate::tests::empty_compute_spirv;
+    use std::ffi::CStr;
+    use std::sync::Arc;
+
+    const NAME: &[u8] = b"main\0";
+
+    #[test]
+    fn caches_pipelines() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let module =
+            unsafe { ShaderModule::from_words(device, &empty_compute_spirv(b"main", false)) }
+                .unwrap();
+        let shader = unsafe {
+            module.compute_entry_point(CStr::from_bytes_with_nul(NAME).unwrap(), [], None, &[])
+        };
+
+        let mut runner = ComputeRunner::new(queue, None);
+        for _ in 0..2 {
+            runner
+                .run(&module, &shader, (), &[], [1, 1, 1])
+                .unwrap()
+                .then_signal_fence_and_flush()
+                .unwrap()
+                .wait(None)
+                .unwrap();
+        }
+        assert_eq!(runner.pipelines.len(), 1);
+        assert!(runner.descriptor_sets.is_empty());
+
+        // The cached pipeline keeps its module alive.
+        assert_eq!(Arc::strong_count(&module), 2);
+        runner.clear_cache();
+        assert_eq!(Arc::strong_count(&module), 1);
+    }
+
+    #[test]
+    fn bounded_descriptor_sets() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let module = unsafe {
+            ShaderModule::from_words(device.clone(), &empty_compute_spirv(b"main", false))
+        }
+        .unwrap();
+        // The shader doesn't use the buffer, but the layout of the pipeline contains it.
+        let shader = unsafe {
+            module.compute_entry_point(
+                CStr::from_bytes_with_nul(NAME).unwrap(),
+                [DescriptorSetDesc::new([Some(DescriptorDesc {
+                    ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                        dynamic: Some(false),
+                        storage: true,
+                    }),
+                    array_count: 1,
+                    stages: ShaderStages {
+                        compute: true,
+                        ..ShaderStages::none()
+                    },
+                    readonly: true,
+                })])],
+                None,
+                &[],
+            )
+        };
+
+        let mut runner = ComputeRunner::new(queue, None);
+        let mut run = |buffer: &Arc<dyn BufferAccess + Send + Sync>| {
+            runner
+                .run(&module, &shader, (), &[buffer.clone()], [1, 1, 1])
+                .unwrap()
+                .then_signal_fence_and_flush()
+                .unwrap()
+                .wait(None)
+                .unwrap();
+        };
+
+        let buffers: Vec<Arc<dyn BufferAccess + Send + Sync>> = (0..MAX_CACHED_DESCRIPTOR_SETS + 1)
+            .map(|_| {
+                CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                    .unwrap() as Arc<_>
+            })
+            .collect();
+        for buffer in &buffers {
+            run(buffer);
+        }
+        // Running again with the last buffer reuses its descriptor set.
+        run(buffers.last().unwrap());
+
+        assert_eq!(runner.descriptor_sets.len(), MAX_CACHED_DESCRIPTOR_SETS);
+        // The descriptor set of the first buffer was evicted, and doesn't keep it alive anymore.
+        assert_eq!(Arc::strong_count(&buffers[0]), 1);
+        assert_eq!(Arc::strong_count(&buffers[1]), 2);
+    }
+
+    #[test]
+    fn shader_of_another_module() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let spirv = empty_compute_spirv(b"main", false);
+        let module = unsafe { ShaderModule::from_words(device.clone(), &spirv) }.unwrap();
+        let other = unsafe { ShaderModule::from_words(device, &spirv) }.unwrap();
+        let shader = unsafe {
+            other.compute_entry_point(CStr::from_bytes_with_nul(NAME).unwrap(), [], None, &[])
+        };
+
+        let mut runner = ComputeRunner::new(queue, None);
+        assert_should_panic!({
+            let _ = runner.run(&module, &shader, (), &[], [1, 1, 1]);
+        });
+    }
+}
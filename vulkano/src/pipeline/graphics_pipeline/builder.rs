@@ -13,12 +13,13 @@
 
 use crate::check_errors;
 use crate::descriptor_set::layout::DescriptorSetDesc;
-use crate::descriptor_set::layout::DescriptorSetLayout;
 use crate::device::Device;
+use crate::device::Features;
 use crate::image::SampleCount;
 use crate::pipeline::blend::AttachmentBlend;
 use crate::pipeline::blend::AttachmentsBlend;
 use crate::pipeline::blend::Blend;
+use crate::pipeline::blend::BlendFactor;
 use crate::pipeline::blend::LogicOp;
 use crate::pipeline::cache::PipelineCache;
 use crate::pipeline::depth_stencil::Compare;
@@ -29,12 +30,14 @@ use crate::pipeline::graphics_pipeline::GraphicsPipelineCreationError;
 use crate::pipeline::graphics_pipeline::Inner as GraphicsPipelineInner;
 use crate::pipeline::input_assembly::PrimitiveTopology;
 use crate::pipeline::layout::PipelineLayout;
+use crate::pipeline::layout::PipelineLayoutCreationError;
 use crate::pipeline::layout::PipelineLayoutPcRange;
 use crate::pipeline::raster::CullMode;
 use crate::pipeline::raster::DepthBiasControl;
 use crate::pipeline::raster::FrontFace;
 use crate::pipeline::raster::PolygonMode;
 use crate::pipeline::raster::Rasterization;
+use crate::pipeline::shader::check_specialization_constants;
 use crate::pipeline::shader::EntryPointAbstract;
 use crate::pipeline::shader::GraphicsEntryPoint;
 use crate::pipeline::shader::GraphicsShaderType;
@@ -47,12 +50,12 @@ use crate::pipeline::viewport::Scissor;
 use crate::pipeline::viewport::Viewport;
 use crate::pipeline::viewport::ViewportsState;
 use crate::render_pass::Subpass;
-use crate::OomError;
 use crate::VulkanObject;
 use smallvec::SmallVec;
 use std::collections::hash_map::{Entry, HashMap};
 use std::mem;
 use std::mem::MaybeUninit;
+use std::panic::Location;
 use std::ptr;
 use std::sync::Arc;
 use std::u32;
@@ -135,6 +138,7 @@ where
     Fss: SpecializationConstants,
 {
     /// Builds the graphics pipeline, using an inferred a pipeline layout.
+    #[track_caller]
     pub fn build(
         self,
         device: Arc<Device>,
@@ -146,6 +150,7 @@ where
     ///
     /// Configures the inferred layout for each descriptor `(set, binding)` in `dynamic_buffers` to accept dynamic
     /// buffers.
+    #[track_caller]
     pub fn with_auto_layout(
         self,
         device: Arc<Device>,
@@ -192,31 +197,34 @@ where
                     match range_map.entry((range.offset, range.size)) {
                         Entry::Vacant(entry) => {
                             entry.insert(range.stages);
-                        },
+                        }
                         Entry::Occupied(mut entry) => {
                             *entry.get_mut() = *entry.get() | range.stages;
-                        },
+                        }
                     }
                 }
             }
             let push_constant_ranges: Vec<_> = range_map
                 .iter()
-                .map(|((offset, size), stages)| {
-                    PipelineLayoutPcRange { offset: *offset, size: *size, stages: *stages }
+                .map(|((offset, size), stages)| PipelineLayoutPcRange {
+                    offset: *offset,
+                    size: *size,
+                    stages: *stages,
                 })
                 .collect();
 
             (descriptor_set_layout_descs, push_constant_ranges)
         };
 
-        let descriptor_set_layouts = descriptor_set_layout_descs
-            .into_iter()
-            .map(|desc| Ok(Arc::new(DescriptorSetLayout::new(device.clone(), desc)?)))
-            .collect::<Result<Vec<_>, OomError>>()?;
-        let pipeline_layout = Arc::new(
-            PipelineLayout::new(device.clone(), descriptor_set_layouts, push_constant_ranges)
-                .unwrap(),
-        );
+        let pipeline_layout = match PipelineLayout::from_descs(
+            device.clone(),
+            descriptor_set_layout_descs,
+            push_constant_ranges,
+        ) {
+            Ok(layout) => layout,
+            Err(PipelineLayoutCreationError::OomError(err)) => return Err(err.into()),
+            Err(err) => panic!("{}", err),
+        };
         self.with_pipeline_layout(device, pipeline_layout)
     }
 
@@ -225,6 +233,7 @@ where
     /// Does the same as `build`, except that `build` automatically builds the pipeline layout
     /// object corresponding to the union of your shaders while this function allows you to specify
     /// the pipeline layout.
+    #[track_caller]
     pub fn with_pipeline_layout(
         mut self,
         device: Arc<Device>,
@@ -297,9 +306,11 @@ where
         let vertex_shader_specialization = {
             let shader = self.vertex_shader.as_ref().unwrap();
             let spec_descriptors = Vss::descriptors();
-            if spec_descriptors != shader.0.spec_constants() {
-                return Err(GraphicsPipelineCreationError::IncompatibleSpecializationConstants);
-            }
+            check_specialization_constants(
+                shader.0.spec_constants(),
+                spec_descriptors,
+                mem::size_of_val(&shader.1),
+            )?;
 
             let constants = &shader.1;
             ash::vk::SpecializationInfo {
@@ -314,9 +325,11 @@ where
             let tcs_spec = {
                 let shader = &tess.tessellation_control_shader;
                 let spec_descriptors = Tcss::descriptors();
-                if spec_descriptors != shader.0.spec_constants() {
-                    return Err(GraphicsPipelineCreationError::IncompatibleSpecializationConstants);
-                }
+                check_specialization_constants(
+                    shader.0.spec_constants(),
+                    spec_descriptors,
+                    mem::size_of_val(&shader.1),
+                )?;
 
                 let constants = &shader.1;
                 ash::vk::SpecializationInfo {
@@ -329,9 +342,11 @@ where
             let tes_spec = {
                 let shader = &tess.tessellation_evaluation_shader;
                 let spec_descriptors = Tess::descriptors();
-                if spec_descriptors != shader.0.spec_constants() {
-                    return Err(GraphicsPipelineCreationError::IncompatibleSpecializationConstants);
-                }
+                check_specialization_constants(
+                    shader.0.spec_constants(),
+                    spec_descriptors,
+                    mem::size_of_val(&shader.1),
+                )?;
 
                 let constants = &shader.1;
                 ash::vk::SpecializationInfo {
@@ -348,9 +363,11 @@ where
 
         let geometry_shader_specialization = if let Some(ref shader) = self.geometry_shader {
             let spec_descriptors = Gss::descriptors();
-            if spec_descriptors != shader.0.spec_constants() {
-                return Err(GraphicsPipelineCreationError::IncompatibleSpecializationConstants);
-            }
+            check_specialization_constants(
+                shader.0.spec_constants(),
+                spec_descriptors,
+                mem::size_of_val(&shader.1),
+            )?;
 
             let constants = &shader.1;
             Some(ash::vk::SpecializationInfo {
@@ -365,9 +382,11 @@ where
 
         let fragment_shader_specialization = if let Some(ref shader) = self.fragment_shader {
             let spec_descriptors = Fss::descriptors();
-            if spec_descriptors != shader.0.spec_constants() {
-                return Err(GraphicsPipelineCreationError::IncompatibleSpecializationConstants);
-            }
+            check_specialization_constants(
+                shader.0.spec_constants(),
+                spec_descriptors,
+                mem::size_of_val(&shader.1),
+            )?;
 
             let constants = &shader.1;
             Some(ash::vk::SpecializationInfo {
@@ -606,6 +625,17 @@ where
             (binding_descriptions, binding_divisor_descriptions)
         };
 
+        if device.enabled_extensions().khr_portability_subset {
+            self.check_portability_subset(
+                device.enabled_features(),
+                device
+                    .physical_device()
+                    .properties()
+                    .min_vertex_input_binding_stride_alignment,
+                &binding_descriptions,
+            )?;
+        }
+
         let attribute_descriptions = {
             let mut attribute_descriptions = SmallVec::<[_; 8]>::new();
 
@@ -795,26 +825,10 @@ where
                 return Err(GraphicsPipelineCreationError::MaxViewportDimensionsExceeded);
             }
 
-            if vp.x
-                < device
-                    .physical_device()
-                    .properties()
-                    .viewport_bounds_range[0]
-                || vp.x + vp.width
-                    > device
-                        .physical_device()
-                        .properties()
-                        .viewport_bounds_range[1]
-                || vp.y
-                    < device
-                        .physical_device()
-                        .properties()
-                        .viewport_bounds_range[0]
-                || vp.y + vp.height
-                    > device
-                        .physical_device()
-                        .properties()
-                        .viewport_bounds_range[1]
+            if vp.x < device.physical_device().properties().viewport_bounds_range[0]
+                || vp.x + vp.width > device.physical_device().properties().viewport_bounds_range[1]
+                || vp.y < device.physical_device().properties().viewport_bounds_range[0]
+                || vp.y + vp.height > device.physical_device().properties().viewport_bounds_range[1]
             {
                 return Err(GraphicsPipelineCreationError::ViewportBoundsExceeded);
             }
@@ -1188,6 +1202,8 @@ where
             };
 
             let mut output = MaybeUninit::uninit();
+            #[cfg(feature = "tracy")]
+            let _zone = crate::tracy::zone("vkCreateGraphicsPipelines");
             check_errors(fns.v1_0.create_graphics_pipelines(
                 device.internal_object(),
                 cache_handle,
@@ -1206,7 +1222,7 @@ where
             panic!("vkCreateGraphicsPipelines provided a NULL handle");
         }
 
-        Ok(GraphicsPipeline {
+        let pipeline = GraphicsPipeline {
             inner: GraphicsPipelineInner {
                 device: device.clone(),
                 pipeline,
@@ -1226,7 +1242,81 @@ where
             dynamic_blend_constants: self.blend.blend_constants.is_none(),
 
             num_viewports: self.viewport.as_ref().unwrap().num_viewports(),
-        })
+        };
+        pipeline
+            .inner
+            .device
+            .auto_debug_name(&pipeline, Location::caller());
+        Ok(pipeline)
+    }
+
+    // Checks the parts of the state that a portability subset implementation only supports if
+    // the corresponding feature is enabled, or if they match its properties.
+    fn check_portability_subset(
+        &self,
+        features: &Features,
+        min_vertex_input_binding_stride_alignment: Option<u32>,
+        binding_descriptions: &[ash::vk::VertexInputBindingDescription],
+    ) -> Result<(), GraphicsPipelineCreationError> {
+        if let Some(alignment) = min_vertex_input_binding_stride_alignment {
+            for binding_desc in binding_descriptions {
+                if binding_desc.stride % alignment != 0 {
+                    return Err(
+                        GraphicsPipelineCreationError::VertexInputBindingStrideNotAligned {
+                            binding: binding_desc.binding,
+                            alignment,
+                            obtained: binding_desc.stride,
+                        },
+                    );
+                }
+            }
+        }
+
+        if self.input_assembly_topology == PrimitiveTopology::TriangleFan && !features.triangle_fans
+        {
+            return Err(GraphicsPipelineCreationError::TriangleFansFeatureNotEnabled);
+        }
+
+        if self.raster.polygon_mode == PolygonMode::Point && !features.point_polygons {
+            return Err(GraphicsPipelineCreationError::PointPolygonsFeatureNotEnabled);
+        }
+
+        if self.depth_stencil.stencil_front.reference != self.depth_stencil.stencil_back.reference
+            && matches!(self.raster.cull_mode, CullMode::None)
+            && !features.separate_stencil_mask_ref
+        {
+            return Err(GraphicsPipelineCreationError::SeparateStencilMaskRefFeatureNotEnabled);
+        }
+
+        if !features.constant_alpha_color_blend_factors {
+            let blends = match self.blend.attachments {
+                AttachmentsBlend::Collective(ref blend) => std::slice::from_ref(blend),
+                AttachmentsBlend::Individual(ref blend) => blend.as_slice(),
+            };
+
+            let uses_constant_alpha = |blend: &AttachmentBlend| {
+                blend.enabled
+                    && [
+                        blend.color_source,
+                        blend.color_destination,
+                        blend.alpha_source,
+                        blend.alpha_destination,
+                    ]
+                    .iter()
+                    .any(|&factor| {
+                        factor == BlendFactor::ConstantAlpha
+                            || factor == BlendFactor::OneMinusConstantAlpha
+                    })
+            };
+
+            if blends.iter().any(uses_constant_alpha) {
+                return Err(
+                    GraphicsPipelineCreationError::ConstantAlphaColorBlendFactorsFeatureNotEnabled,
+                );
+            }
+        }
+
+        Ok(())
     }
 
     // TODO: add build_with_cache method
@@ -1948,3 +2038,114 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::device::Features;
+    use crate::pipeline::blend::AttachmentBlend;
+    use crate::pipeline::blend::BlendFactor;
+    use crate::pipeline::depth_stencil::DepthStencil;
+    use crate::pipeline::graphics_pipeline::builder::GraphicsPipelineBuilder;
+    use crate::pipeline::GraphicsPipelineCreationError;
+
+    #[test]
+    fn portability_subset_features() {
+        let none = Features::none();
+        let builder = GraphicsPipelineBuilder::new;
+        assert!(builder().check_portability_subset(&none, None, &[]).is_ok());
+
+        let fan = builder().triangle_fan();
+        match fan.check_portability_subset(&none, None, &[]) {
+            Err(GraphicsPipelineCreationError::TriangleFansFeatureNotEnabled) => (),
+            _ => panic!(),
+        }
+        let features = Features {
+            triangle_fans: true,
+            ..Features::none()
+        };
+        assert!(fan.check_portability_subset(&features, None, &[]).is_ok());
+
+        let point = builder().polygon_mode_point();
+        match point.check_portability_subset(&none, None, &[]) {
+            Err(GraphicsPipelineCreationError::PointPolygonsFeatureNotEnabled) => (),
+            _ => panic!(),
+        }
+        let features = Features {
+            point_polygons: true,
+            ..Features::none()
+        };
+        assert!(point.check_portability_subset(&features, None, &[]).is_ok());
+
+        let mut depth_stencil = DepthStencil::disabled();
+        depth_stencil.stencil_front.reference = Some(0);
+        depth_stencil.stencil_back.reference = Some(1);
+        let stencil = builder().depth_stencil(depth_stencil.clone());
+        match stencil.check_portability_subset(&none, None, &[]) {
+            Err(GraphicsPipelineCreationError::SeparateStencilMaskRefFeatureNotEnabled) => (),
+            _ => panic!(),
+        }
+        // Only one face is rasterized if culling is enabled.
+        let culled = builder().depth_stencil(depth_stencil).cull_mode_back();
+        assert!(culled.check_portability_subset(&none, None, &[]).is_ok());
+        let features = Features {
+            separate_stencil_mask_ref: true,
+            ..Features::none()
+        };
+        assert!(stencil
+            .check_portability_subset(&features, None, &[])
+            .is_ok());
+
+        let blend = AttachmentBlend {
+            color_source: BlendFactor::OneMinusConstantAlpha,
+            ..AttachmentBlend::alpha_blending()
+        };
+        let constant_alpha =
+            builder().blend_individual([AttachmentBlend::pass_through(), blend.clone()]);
+        match constant_alpha.check_portability_subset(&none, None, &[]) {
+            Err(GraphicsPipelineCreationError::ConstantAlphaColorBlendFactorsFeatureNotEnabled) => {
+                ()
+            }
+            _ => panic!(),
+        }
+        // The blend factors are ignored if blending is disabled.
+        let disabled = builder().blend_collective(AttachmentBlend {
+            enabled: false,
+            ..blend
+        });
+        assert!(disabled.check_portability_subset(&none, None, &[]).is_ok());
+        let features = Features {
+            constant_alpha_color_blend_factors: true,
+            ..Features::none()
+        };
+        assert!(constant_alpha
+            .check_portability_subset(&features, None, &[])
+            .is_ok());
+    }
+
+    #[test]
+    fn portability_subset_binding_stride() {
+        let binding = |binding, stride| ash::vk::VertexInputBindingDescription {
+            binding,
+            stride,
+            input_rate: ash::vk::VertexInputRate::VERTEX,
+        };
+        let bindings = [binding(0, 8), binding(1, 12)];
+        let builder = GraphicsPipelineBuilder::new();
+        let none = Features::none();
+
+        assert!(builder
+            .check_portability_subset(&none, None, &bindings)
+            .is_ok());
+        assert!(builder
+            .check_portability_subset(&none, Some(4), &bindings)
+            .is_ok());
+        match builder.check_portability_subset(&none, Some(8), &bindings) {
+            Err(GraphicsPipelineCreationError::VertexInputBindingStrideNotAligned {
+                binding: 1,
+                alignment: 8,
+                obtained: 12,
+            }) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -10,6 +10,7 @@
 use crate::pipeline::input_assembly::PrimitiveTopology;
 use crate::pipeline::layout::PipelineLayoutSupersetError;
 use crate::pipeline::shader::ShaderInterfaceMismatchError;
+use crate::pipeline::shader::SpecializationConstantsError;
 use crate::pipeline::vertex::IncompatibleVertexDefinitionError;
 use crate::Error;
 use crate::OomError;
@@ -27,7 +28,7 @@ pub enum GraphicsPipelineCreationError {
     IncompatiblePipelineLayout(PipelineLayoutSupersetError),
 
     /// The provided specialization constants are not compatible with what the shader expects.
-    IncompatibleSpecializationConstants,
+    IncompatibleSpecializationConstants(SpecializationConstantsError),
 
     /// The output interface of one shader and the input interface of the next shader does not match.
     ShaderStagesMismatch(ShaderInterfaceMismatchError),
@@ -187,6 +188,35 @@ pub enum GraphicsPipelineCreationError {
 
     /// The device doesn't support using the `multiview´ feature with tessellation shaders.
     MultiviewTessellationShaderNotSupported,
+
+    /// The device is a portability subset implementation, and the `triangle_fans` feature must
+    /// be enabled in order to use the `TriangleFan` primitive topology.
+    TriangleFansFeatureNotEnabled,
+
+    /// The device is a portability subset implementation, and the `point_polygons` feature must
+    /// be enabled in order to use the `Point` polygon mode.
+    PointPolygonsFeatureNotEnabled,
+
+    /// The device is a portability subset implementation, and the
+    /// `constant_alpha_color_blend_factors` feature must be enabled in order to use the
+    /// `ConstantAlpha` and `OneMinusConstantAlpha` blend factors.
+    ConstantAlphaColorBlendFactorsFeatureNotEnabled,
+
+    /// The device is a portability subset implementation, and the `separate_stencil_mask_ref`
+    /// feature must be enabled in order to use different stencil references for the front and
+    /// back faces without culling.
+    SeparateStencilMaskRefFeatureNotEnabled,
+
+    /// The device is a portability subset implementation, and the stride of a vertex binding
+    /// isn't a multiple of the `min_vertex_input_binding_stride_alignment` property.
+    VertexInputBindingStrideNotAligned {
+        /// Index of the faulty binding.
+        binding: u32,
+        /// Required alignment.
+        alignment: u32,
+        /// Value that was passed.
+        obtained: u32,
+    },
 }
 
 impl error::Error for GraphicsPipelineCreationError {
@@ -195,6 +225,9 @@ impl error::Error for GraphicsPipelineCreationError {
         match *self {
             GraphicsPipelineCreationError::OomError(ref err) => Some(err),
             GraphicsPipelineCreationError::IncompatiblePipelineLayout(ref err) => Some(err),
+            GraphicsPipelineCreationError::IncompatibleSpecializationConstants(ref err) => {
+                Some(err)
+            }
             GraphicsPipelineCreationError::ShaderStagesMismatch(ref err) => Some(err),
             GraphicsPipelineCreationError::IncompatibleVertexDefinition(ref err) => Some(err),
             _ => None,
@@ -217,7 +250,7 @@ impl fmt::Display for GraphicsPipelineCreationError {
                 GraphicsPipelineCreationError::IncompatiblePipelineLayout(_) => {
                     "the pipeline layout is not compatible with what the shaders expect"
                 }
-                GraphicsPipelineCreationError::IncompatibleSpecializationConstants => {
+                GraphicsPipelineCreationError::IncompatibleSpecializationConstants(_) => {
                     "the provided specialization constants are not compatible with what the shader expects"
                 }
                 GraphicsPipelineCreationError::FragmentShaderRenderPassIncompatible => {
@@ -338,6 +371,28 @@ impl fmt::Display for GraphicsPipelineCreationError {
                 GraphicsPipelineCreationError::MultiviewTessellationShaderNotSupported => {
                     "the device doesn't support using the `multiview´ feature with tessellation shaders"
                 }
+                GraphicsPipelineCreationError::TriangleFansFeatureNotEnabled => {
+                    "the `triangle_fans` feature must be enabled on portability subset devices in \
+                    order to use the `TriangleFan` primitive topology"
+                }
+                GraphicsPipelineCreationError::PointPolygonsFeatureNotEnabled => {
+                    "the `point_polygons` feature must be enabled on portability subset devices in \
+                    order to use the `Point` polygon mode"
+                }
+                GraphicsPipelineCreationError::ConstantAlphaColorBlendFactorsFeatureNotEnabled => {
+                    "the `constant_alpha_color_blend_factors` feature must be enabled on \
+                    portability subset devices in order to use the constant alpha blend factors"
+                }
+                GraphicsPipelineCreationError::SeparateStencilMaskRefFeatureNotEnabled => {
+                    "the `separate_stencil_mask_ref` feature must be enabled on portability subset \
+                    devices in order to use different stencil references for the front and back \
+                    faces without culling"
+                }
+                GraphicsPipelineCreationError::VertexInputBindingStrideNotAligned { .. } => {
+                    "the stride of a vertex binding isn't a multiple of the \
+                    `min_vertex_input_binding_stride_alignment` property of the portability \
+                    subset device"
+                }
             }
         )
     }
@@ -357,6 +412,13 @@ impl From<PipelineLayoutSupersetError> for GraphicsPipelineCreationError {
     }
 }
 
+impl From<SpecializationConstantsError> for GraphicsPipelineCreationError {
+    #[inline]
+    fn from(err: SpecializationConstantsError) -> GraphicsPipelineCreationError {
+        GraphicsPipelineCreationError::IncompatibleSpecializationConstants(err)
+    }
+}
+
 impl From<IncompatibleVertexDefinitionError> for GraphicsPipelineCreationError {
     #[inline]
     fn from(err: IncompatibleVertexDefinitionError) -> GraphicsPipelineCreationError {
@@ -0,0 +1,775 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Reloading shaders and rebuilding pipelines while the application is running.
+//!
+//! A [`HotReloader`] watches shader files. When one of them changes, the shader module is
+//! recreated and the pipelines that use it are rebuilt with the pipeline cache of the reloader.
+//! The new pipelines are not used right away: they are put in their [`HotPipeline`] handle only
+//! when [`HotReloader::swap_pending`] is called, which the application does at a point where
+//! switching pipelines is safe, usually between two frames.
+//!
+//! Pipelines that were swapped out are kept alive by the command buffers that still use them, so
+//! swapping never destroys a pipeline that the GPU is executing.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use vulkano::device::Device;
+//! # use vulkano::pipeline::ComputePipeline;
+//! # let device: Arc<Device> = return;
+//! # fn build(module: &vulkano::pipeline::shader::ShaderModule) -> ComputePipeline { unimplemented!() }
+//! use std::time::Duration;
+//! use vulkano::pipeline::cache::PipelineCache;
+//! use vulkano::pipeline::hot_reload::HotReloader;
+//!
+//! let cache = PipelineCache::empty(device.clone()).unwrap();
+//! let reloader = HotReloader::new(device.clone(), cache, Some(Duration::from_millis(250)));
+//!
+//! let shader = unsafe { reloader.watch_spirv("shaders/blur.spv").unwrap() };
+//! let pipeline = reloader
+//!     .add_pipeline(&[shader], |modules, _cache| Ok(Arc::new(build(&modules[0]))))
+//!     .unwrap();
+//!
+//! loop {
+//!     // Between two frames.
+//!     reloader.swap_pending();
+//!     for error in reloader.take_errors() {
+//!         println!("{}", error);
+//!     }
+//!
+//!     let current_pipeline = pipeline.get();
+//!     // ...
+//! #   break;
+//! }
+//! ```
+
+use crate::device::Device;
+use crate::pipeline::cache::PipelineCache;
+#[cfg(feature = "shaderc")]
+use crate::pipeline::shader::compile_glsl;
+use crate::pipeline::shader::reflect::ShaderReflection;
+use crate::pipeline::shader::reflect::SpirvParseError;
+#[cfg(feature = "shaderc")]
+use crate::pipeline::shader::GlslCompileError;
+#[cfg(feature = "shaderc")]
+use crate::pipeline::shader::GlslCompileOptions;
+use crate::pipeline::shader::ShaderModule;
+#[cfg(feature = "shaderc")]
+use crate::pipeline::shader::ShaderStage;
+use crate::OomError;
+use std::collections::HashSet;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::SystemTime;
+
+/// Error type of the closures that build pipelines for a `HotReloader`.
+pub type PipelineBuildError = Box<dyn error::Error + Send + Sync>;
+
+/// Watches shader files, and rebuilds the pipelines that use them when they change.
+///
+/// See [the module-level documentation](self) for more information.
+pub struct HotReloader {
+    shared: Arc<Shared>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+struct Shared {
+    device: Arc<Device>,
+    cache: Arc<PipelineCache>,
+    state: Mutex<State>,
+    // Only one poll can run at a time, and the state is only locked briefly during a poll.
+    poll_lock: Mutex<()>,
+    // Set to true to stop the worker thread.
+    stop: Mutex<bool>,
+    stop_condvar: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    shaders: Vec<WatchedShader>,
+    pipelines: Vec<Arc<dyn Reloadable>>,
+    errors: Vec<HotReloadError>,
+}
+
+struct WatchedShader {
+    path: PathBuf,
+    source: Arc<ShaderSource>,
+    version: Option<FileVersion>,
+    module: Arc<ShaderModule>,
+}
+
+enum ShaderSource {
+    Spirv,
+    #[cfg(feature = "shaderc")]
+    Glsl {
+        stage: ShaderStage,
+        options: GlslCompileOptions,
+    },
+}
+
+// Modification time and length of a file, to detect changes.
+type FileVersion = (SystemTime, u64);
+
+impl HotReloader {
+    /// Builds a new `HotReloader` without shaders. Pipelines are rebuilt with `cache`.
+    ///
+    /// If `poll_interval` is `Some`, a worker thread checks the shader files for changes at this
+    /// interval, and rebuilds the affected pipelines. If it is `None`, this is only done when
+    /// `poll` is called.
+    pub fn new(
+        device: Arc<Device>,
+        cache: Arc<PipelineCache>,
+        poll_interval: Option<Duration>,
+    ) -> HotReloader {
+        let shared = Arc::new(Shared {
+            device,
+            cache,
+            state: Mutex::new(State::default()),
+            poll_lock: Mutex::new(()),
+            stop: Mutex::new(false),
+            stop_condvar: Condvar::new(),
+        });
+
+        let thread = poll_interval.map(|poll_interval| {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                let mut stop = shared.stop.lock().unwrap();
+                while !*stop {
+                    stop = shared
+                        .stop_condvar
+                        .wait_timeout(stop, poll_interval)
+                        .unwrap()
+                        .0;
+                    if *stop {
+                        break;
+                    }
+
+                    drop(stop);
+                    shared.poll();
+                    stop = shared.stop.lock().unwrap();
+                }
+            })
+        });
+
+        HotReloader { shared, thread }
+    }
+
+    /// Starts watching a file that contains SPIR-V code, and loads it.
+    ///
+    /// # Safety
+    ///
+    /// - The SPIR-V code of the file and of its later versions must be valid, the same as for
+    ///   [`ShaderModule::from_words`]. Code that can't be parsed, such as a file that is still
+    ///   being written, is rejected, but the code is not validated further.
+    ///
+    pub unsafe fn watch_spirv<P>(&self, path: P) -> Result<ShaderId, HotReloadError>
+    where
+        P: AsRef<Path>,
+    {
+        self.watch(path.as_ref(), ShaderSource::Spirv)
+    }
+
+    /// Starts watching a file that contains GLSL code, and compiles it with `compile_glsl`.
+    ///
+    /// # Safety
+    ///
+    /// - The SPIR-V code produced by the compiler is not validated, the same as for
+    ///   [`compile_glsl`].
+    ///
+    #[cfg(feature = "shaderc")]
+    pub unsafe fn watch_glsl<P>(
+        &self,
+        path: P,
+        stage: ShaderStage,
+        options: GlslCompileOptions,
+    ) -> Result<ShaderId, HotReloadError>
+    where
+        P: AsRef<Path>,
+    {
+        self.watch(path.as_ref(), ShaderSource::Glsl { stage, options })
+    }
+
+    unsafe fn watch(&self, path: &Path, source: ShaderSource) -> Result<ShaderId, HotReloadError> {
+        let version = file_version(path)?;
+        let module = self.shared.load(path, &source)?;
+
+        let mut state = self.shared.state.lock().unwrap();
+        state.shaders.push(WatchedShader {
+            path: path.to_owned(),
+            source: Arc::new(source),
+            version: Some(version),
+            module,
+        });
+
+        Ok(ShaderId(state.shaders.len() - 1))
+    }
+
+    /// Returns the current shader module of a watched shader.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `shader` wasn't returned by this reloader.
+    ///
+    #[inline]
+    pub fn shader_module(&self, shader: ShaderId) -> Arc<ShaderModule> {
+        self.shared.state.lock().unwrap().shaders[shader.0]
+            .module
+            .clone()
+    }
+
+    /// Builds a pipeline that uses the given shaders, and rebuilds it when one of them changes.
+    ///
+    /// `build` is given the current shader modules, in the same order as `shaders`, and the
+    /// pipeline cache of the reloader. It is called right away, and then from the thread that
+    /// reloads the shaders.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of `shaders` wasn't returned by this reloader.
+    ///
+    pub fn add_pipeline<P, F>(
+        &self,
+        shaders: &[ShaderId],
+        build: F,
+    ) -> Result<HotPipeline<P>, HotReloadError>
+    where
+        P: Send + Sync + 'static,
+        F: Fn(&[Arc<ShaderModule>], &Arc<PipelineCache>) -> Result<Arc<P>, PipelineBuildError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let modules = self.shared.modules(shaders);
+        let pipeline = build(&modules, &self.shared.cache).map_err(HotReloadError::Pipeline)?;
+
+        let handle = HotPipeline {
+            slot: Arc::new(Slot {
+                current: Mutex::new(pipeline),
+                pending: Mutex::new(None),
+            }),
+        };
+
+        let entry = PipelineEntry {
+            shaders: shaders.to_vec(),
+            build,
+            slot: handle.slot.clone(),
+        };
+        self.shared
+            .state
+            .lock()
+            .unwrap()
+            .pipelines
+            .push(Arc::new(entry));
+
+        Ok(handle)
+    }
+
+    /// Checks the shader files for changes right away, reloads the ones that changed and
+    /// rebuilds the pipelines that use them.
+    ///
+    /// The rebuilt pipelines are only put in their handles by `swap_pending`. The errors are
+    /// returned by `take_errors`.
+    #[inline]
+    pub fn poll(&self) {
+        self.shared.poll();
+    }
+
+    /// Puts the pipelines that were rebuilt since the last call in their handles, and returns
+    /// their number.
+    ///
+    /// This should be called at a point where the application can switch to new pipelines,
+    /// usually between two frames.
+    pub fn swap_pending(&self) -> usize {
+        let pipelines = self.shared.state.lock().unwrap().pipelines.clone();
+        pipelines
+            .iter()
+            .filter(|pipeline| pipeline.swap_pending())
+            .count()
+    }
+
+    /// Returns the errors that happened while reloading shaders and rebuilding pipelines since
+    /// the last call.
+    ///
+    /// When a shader fails to reload or a pipeline fails to be rebuilt, the previous version is
+    /// kept.
+    #[inline]
+    pub fn take_errors(&self) -> Vec<HotReloadError> {
+        std::mem::take(&mut self.shared.state.lock().unwrap().errors)
+    }
+}
+
+impl Shared {
+    fn poll(&self) {
+        let _poll_lock = self.poll_lock.lock().unwrap();
+
+        let shaders = {
+            let state = self.state.lock().unwrap();
+            state
+                .shaders
+                .iter()
+                .map(|shader| (shader.path.clone(), shader.source.clone(), shader.version))
+                .collect::<Vec<_>>()
+        };
+
+        let mut reloaded = HashSet::new();
+        let mut errors = Vec::new();
+
+        for (index, (path, source, version)) in shaders.into_iter().enumerate() {
+            let new_version = match file_version(&path) {
+                Ok(new_version) => new_version,
+                Err(err) => {
+                    // The file may be in the middle of being written. Report the error only once.
+                    if version.is_some() {
+                        errors.push(err);
+                        self.state.lock().unwrap().shaders[index].version = None;
+                    }
+                    continue;
+                }
+            };
+            if version == Some(new_version) {
+                continue;
+            }
+
+            // The version is updated even if loading fails, so that a broken file isn't reported
+            // again until it changes.
+            let module = unsafe { self.load(&path, &source) };
+            let mut state = self.state.lock().unwrap();
+            state.shaders[index].version = Some(new_version);
+            match module {
+                Ok(module) => {
+                    state.shaders[index].module = module;
+                    reloaded.insert(ShaderId(index));
+                }
+                Err(err) => errors.push(err),
+            }
+        }
+
+        if !reloaded.is_empty() {
+            let pipelines = self.state.lock().unwrap().pipelines.clone();
+            for pipeline in pipelines {
+                if !pipeline.shaders().iter().any(|id| reloaded.contains(id)) {
+                    continue;
+                }
+
+                let modules = self.modules(pipeline.shaders());
+                if let Err(err) = pipeline.rebuild(&modules, &self.cache) {
+                    errors.push(HotReloadError::Pipeline(err));
+                }
+            }
+        }
+
+        self.state.lock().unwrap().errors.extend(errors);
+    }
+
+    fn modules(&self, shaders: &[ShaderId]) -> Vec<Arc<ShaderModule>> {
+        let state = self.state.lock().unwrap();
+        shaders
+            .iter()
+            .map(|shader| state.shaders[shader.0].module.clone())
+            .collect()
+    }
+
+    unsafe fn load(
+        &self,
+        path: &Path,
+        source: &ShaderSource,
+    ) -> Result<Arc<ShaderModule>, HotReloadError> {
+        match *source {
+            ShaderSource::Spirv => {
+                let bytes = fs::read(path).map_err(|error| HotReloadError::Io {
+                    path: path.to_owned(),
+                    error,
+                })?;
+                let words = spirv_words(&bytes).ok_or_else(|| HotReloadError::InvalidSpirv {
+                    path: path.to_owned(),
+                })?;
+
+                // The file can be in the middle of being written, and malformed code must never
+                // reach the driver, so the previous module is kept unless the code parses.
+                ShaderReflection::new(&words).map_err(|error| HotReloadError::MalformedSpirv {
+                    path: path.to_owned(),
+                    error,
+                })?;
+
+                Ok(ShaderModule::from_words(self.device.clone(), &words)?)
+            }
+            #[cfg(feature = "shaderc")]
+            ShaderSource::Glsl { stage, ref options } => {
+                let code = fs::read_to_string(path).map_err(|error| HotReloadError::Io {
+                    path: path.to_owned(),
+                    error,
+                })?;
+                let shader =
+                    compile_glsl(self.device.clone(), &code, stage, options).map_err(|error| {
+                        HotReloadError::Compilation {
+                            path: path.to_owned(),
+                            error,
+                        }
+                    })?;
+
+                Ok(shader.module().clone())
+            }
+        }
+    }
+}
+
+impl Drop for HotReloader {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            *self.shared.stop.lock().unwrap() = true;
+            self.shared.stop_condvar.notify_all();
+            let _ = thread.join();
+        }
+    }
+}
+
+impl fmt::Debug for HotReloader {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let state = self.shared.state.lock().unwrap();
+        fmt.debug_struct("HotReloader")
+            .field(
+                "shaders",
+                &state
+                    .shaders
+                    .iter()
+                    .map(|shader| &shader.path)
+                    .collect::<Vec<_>>(),
+            )
+            .field("pipelines", &state.pipelines.len())
+            .field("worker_thread", &self.thread.is_some())
+            .finish()
+    }
+}
+
+fn file_version(path: &Path) -> Result<FileVersion, HotReloadError> {
+    fs::metadata(path)
+        .and_then(|metadata| Ok((metadata.modified()?, metadata.len())))
+        .map_err(|error| HotReloadError::Io {
+            path: path.to_owned(),
+            error,
+        })
+}
+
+// Converts the content of a SPIR-V file to words, in the endianness given by the magic number.
+fn spirv_words(bytes: &[u8]) -> Option<Vec<u32>> {
+    const MAGIC_NUMBER: u32 = 0x07230203;
+
+    let chunks = bytes.chunks_exact(4);
+    if bytes.is_empty() || !chunks.remainder().is_empty() {
+        return None;
+    }
+
+    let little_endian = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let big_endian = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let from_bytes = if little_endian == MAGIC_NUMBER {
+        u32::from_le_bytes
+    } else if big_endian == MAGIC_NUMBER {
+        u32::from_be_bytes
+    } else {
+        return None;
+    };
+
+    Some(
+        chunks
+            .map(|c| from_bytes([c[0], c[1], c[2], c[3]]))
+            .collect(),
+    )
+}
+
+/// Identifier of a shader watched by a `HotReloader`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ShaderId(usize);
+
+/// Handle to a pipeline that is rebuilt by a `HotReloader`.
+///
+/// Cloning the handle is cheap, and the clones always return the same pipeline.
+pub struct HotPipeline<P> {
+    slot: Arc<Slot<P>>,
+}
+
+struct Slot<P> {
+    current: Mutex<Arc<P>>,
+    pending: Mutex<Option<Arc<P>>>,
+}
+
+impl<P> HotPipeline<P> {
+    /// Returns the current version of the pipeline.
+    ///
+    /// The value only changes when `HotReloader::swap_pending` is called.
+    #[inline]
+    pub fn get(&self) -> Arc<P> {
+        self.slot.current.lock().unwrap().clone()
+    }
+}
+
+impl<P> Clone for HotPipeline<P> {
+    #[inline]
+    fn clone(&self) -> HotPipeline<P> {
+        HotPipeline {
+            slot: self.slot.clone(),
+        }
+    }
+}
+
+impl<P> fmt::Debug for HotPipeline<P>
+where
+    P: fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("HotPipeline")
+            .field("current", &self.get())
+            .finish()
+    }
+}
+
+// A pipeline of a `HotReloader`, with the type of the pipeline erased.
+trait Reloadable: Send + Sync {
+    fn shaders(&self) -> &[ShaderId];
+    fn rebuild(
+        &self,
+        modules: &[Arc<ShaderModule>],
+        cache: &Arc<PipelineCache>,
+    ) -> Result<(), PipelineBuildError>;
+    fn swap_pending(&self) -> bool;
+}
+
+struct PipelineEntry<P, F> {
+    shaders: Vec<ShaderId>,
+    build: F,
+    slot: Arc<Slot<P>>,
+}
+
+impl<P, F> Reloadable for PipelineEntry<P, F>
+where
+    P: Send + Sync,
+    F: Fn(&[Arc<ShaderModule>], &Arc<PipelineCache>) -> Result<Arc<P>, PipelineBuildError>
+        + Send
+        + Sync,
+{
+    #[inline]
+    fn shaders(&self) -> &[ShaderId] {
+        &self.shaders
+    }
+
+    fn rebuild(
+        &self,
+        modules: &[Arc<ShaderModule>],
+        cache: &Arc<PipelineCache>,
+    ) -> Result<(), PipelineBuildError> {
+        let pipeline = (self.build)(modules, cache)?;
+        *self.slot.pending.lock().unwrap() = Some(pipeline);
+        Ok(())
+    }
+
+    fn swap_pending(&self) -> bool {
+        match self.slot.pending.lock().unwrap().take() {
+            Some(pipeline) => {
+                *self.slot.current.lock().unwrap() = pipeline;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Error that can happen when reloading a shader or rebuilding a pipeline.
+#[derive(Debug)]
+pub enum HotReloadError {
+    /// A shader file couldn't be read.
+    Io {
+        /// Path of the file.
+        path: PathBuf,
+        /// The error of the file system.
+        error: io::Error,
+    },
+    /// A file doesn't contain SPIR-V code.
+    InvalidSpirv {
+        /// Path of the file.
+        path: PathBuf,
+    },
+    /// A file contains SPIR-V code that couldn't be parsed, for example because it is truncated.
+    MalformedSpirv {
+        /// Path of the file.
+        path: PathBuf,
+        /// The error of the parser.
+        error: SpirvParseError,
+    },
+    /// A GLSL file failed to compile.
+    #[cfg(feature = "shaderc")]
+    Compilation {
+        /// Path of the file.
+        path: PathBuf,
+        /// The error of the compiler.
+        error: GlslCompileError,
+    },
+    /// Not enough memory to create a shader module.
+    OomError(OomError),
+    /// The closure that builds a pipeline returned an error.
+    Pipeline(PipelineBuildError),
+}
+
+impl error::Error for HotReloadError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            HotReloadError::Io { ref error, .. } => Some(error),
+            HotReloadError::MalformedSpirv { ref error, .. } => Some(error),
+            #[cfg(feature = "shaderc")]
+            HotReloadError::Compilation { ref error, .. } => Some(error),
+            HotReloadError::OomError(ref err) => Some(err),
+            HotReloadError::Pipeline(ref err) => Some(&**err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for HotReloadError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            HotReloadError::Io { ref path, .. } => {
+                write!(fmt, "the shader file {} couldn't be read", path.display())
+            }
+            HotReloadError::InvalidSpirv { ref path } => {
+                write!(
+                    fmt,
+                    "the file {} doesn't contain SPIR-V code",
+                    path.display()
+                )
+            }
+            HotReloadError::MalformedSpirv { ref path, .. } => {
+                write!(
+                    fmt,
+                    "the file {} contains malformed SPIR-V code",
+                    path.display()
+                )
+            }
+            #[cfg(feature = "shaderc")]
+            HotReloadError::Compilation { ref path, .. } => {
+                write!(fmt, "the shader file {} failed to compile", path.display())
+            }
+            HotReloadError::OomError(_) => write!(fmt, "not enough memory available"),
+            HotReloadError::Pipeline(_) => write!(fmt, "a pipeline failed to be rebuilt"),
+        }
+    }
+}
+
+impl From<OomError> for HotReloadError {
+    #[inline]
+    fn from(err: OomError) -> HotReloadError {
+        HotReloadError::OomError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pipeline::cache::PipelineCache;
+    use crate::pipeline::hot_reload::spirv_words;
+    use crate::pipeline::hot_reload::HotReloadError;
+    use crate::pipeline::hot_reload::HotReloader;
+    use crate::pipeline::shader::reflect::ShaderReflection;
+    use crate::tests::empty_compute_spirv;
+    use std::fs;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    fn spirv(with_source: bool) -> Vec<u8> {
+        empty_compute_spirv(b"main", with_source)
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn spirv_endianness() {
+        let bytes = spirv(false);
+        let words = spirv_words(&bytes).unwrap();
+        assert_eq!(words[0], 0x07230203);
+
+        let swapped = bytes
+            .chunks(4)
+            .flat_map(|c| [c[3], c[2], c[1], c[0]])
+            .collect::<Vec<_>>();
+        assert_eq!(spirv_words(&swapped), Some(words));
+
+        assert_eq!(spirv_words(&bytes[..6]), None);
+        assert_eq!(spirv_words(&[0; 8]), None);
+    }
+
+    #[test]
+    fn truncated_spirv() {
+        let bytes = spirv(false);
+        let words = spirv_words(&bytes[..bytes.len() - 12]).unwrap();
+        assert!(ShaderReflection::new(&words).is_err());
+        assert!(ShaderReflection::new(&spirv_words(&bytes).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn reload_and_swap() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let path =
+            std::env::temp_dir().join(format!("vulkano-hot-reload-{}.spv", std::process::id()));
+        fs::write(&path, spirv(false)).unwrap();
+
+        let cache = PipelineCache::empty(device.clone()).unwrap();
+        let reloader = HotReloader::new(device, cache, None);
+        let shader = unsafe { reloader.watch_spirv(&path).unwrap() };
+
+        let builds = Arc::new(AtomicUsize::new(0));
+        let pipeline = {
+            let builds = builds.clone();
+            reloader
+                .add_pipeline(&[shader], move |_, _| {
+                    Ok(Arc::new(builds.fetch_add(1, Ordering::SeqCst)))
+                })
+                .unwrap()
+        };
+        assert_eq!(*pipeline.get(), 0);
+
+        reloader.poll();
+        assert_eq!(reloader.swap_pending(), 0);
+
+        fs::write(&path, spirv(true)).unwrap();
+        reloader.poll();
+        assert_eq!(*pipeline.get(), 0);
+        assert_eq!(reloader.swap_pending(), 1);
+        assert_eq!(*pipeline.get(), 1);
+        assert!(reloader.take_errors().is_empty());
+
+        fs::write(&path, [0; 8]).unwrap();
+        reloader.poll();
+        assert_eq!(reloader.swap_pending(), 0);
+        assert_eq!(reloader.take_errors().len(), 1);
+
+        // A file that is still being written keeps the previous module.
+        let bytes = spirv(false);
+        fs::write(&path, &bytes[..bytes.len() - 12]).unwrap();
+        reloader.poll();
+        assert_eq!(reloader.swap_pending(), 0);
+        match reloader.take_errors().as_slice() {
+            [HotReloadError::MalformedSpirv { .. }] => (),
+            _ => panic!(),
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -150,6 +150,79 @@ impl PipelineLayout {
             push_constant_ranges,
         })
     }
+
+    /// Creates a new `PipelineLayout` along with its descriptor set layouts, from the
+    /// descriptions of the descriptor sets.
+    ///
+    /// If the pipeline layout cache of the device is enabled, returns the existing layout with
+    /// the same descriptor sets and push constant ranges if there is one. See
+    /// `Device::set_pipeline_layout_cache`.
+    pub fn from_descs<D, P>(
+        device: Arc<Device>,
+        descriptor_set_descs: D,
+        push_constant_ranges: P,
+    ) -> Result<Arc<PipelineLayout>, PipelineLayoutCreationError>
+    where
+        D: IntoIterator<Item = DescriptorSetDesc>,
+        P: IntoIterator<Item = PipelineLayoutPcRange>,
+    {
+        let descriptor_set_descs: SmallVec<[DescriptorSetDesc; 16]> =
+            descriptor_set_descs.into_iter().collect();
+        let push_constant_ranges: SmallVec<[PipelineLayoutPcRange; 8]> =
+            push_constant_ranges.into_iter().collect();
+
+        // The lock is kept while the layout is created, so that two threads don't create the
+        // same layout.
+        let mut cache = device.pipeline_layouts();
+
+        if let Some(cache) = cache.as_mut() {
+            cache.retain(|layout| layout.strong_count() != 0);
+
+            let existing = cache
+                .iter()
+                .filter_map(|layout| layout.upgrade())
+                .find(|layout| layout.matches(&descriptor_set_descs, &push_constant_ranges));
+            if let Some(layout) = existing {
+                return Ok(layout);
+            }
+        }
+
+        let descriptor_set_layouts = descriptor_set_descs
+            .into_iter()
+            .map(|desc| Ok(Arc::new(DescriptorSetLayout::new(device.clone(), desc)?)))
+            .collect::<Result<SmallVec<[_; 16]>, OomError>>()?;
+        let layout = Arc::new(PipelineLayout::new(
+            device.clone(),
+            descriptor_set_layouts,
+            push_constant_ranges,
+        )?);
+
+        if let Some(cache) = cache.as_mut() {
+            cache.push(Arc::downgrade(&layout));
+        }
+
+        Ok(layout)
+    }
+
+    // Returns true if `self` has exactly the given descriptor sets and push constant ranges.
+    fn matches(
+        &self,
+        descriptor_set_descs: &[DescriptorSetDesc],
+        push_constant_ranges: &[PipelineLayoutPcRange],
+    ) -> bool {
+        // The order of the push constant ranges doesn't matter, and the ranges of a layout all
+        // have different stages.
+        self.descriptor_set_layouts.len() == descriptor_set_descs.len()
+            && self
+                .descriptor_set_layouts
+                .iter()
+                .zip(descriptor_set_descs)
+                .all(|(layout, desc)| layout.desc() == desc)
+            && self.push_constant_ranges.len() == push_constant_ranges.len()
+            && push_constant_ranges
+                .iter()
+                .all(|range| self.push_constant_ranges.contains(range))
+    }
 }
 
 impl PipelineLayout {
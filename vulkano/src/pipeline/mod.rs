@@ -78,16 +78,21 @@
 pub use self::compute_pipeline::ComputePipeline;
 pub use self::compute_pipeline::ComputePipelineCreationError;
 pub use self::compute_pipeline::ComputePipelineSys;
+pub use self::compute_runner::ComputeRunError;
+pub use self::compute_runner::ComputeRunner;
 pub use self::graphics_pipeline::GraphicsPipeline;
 pub use self::graphics_pipeline::GraphicsPipelineBuilder;
 pub use self::graphics_pipeline::GraphicsPipelineCreationError;
 pub use self::graphics_pipeline::GraphicsPipelineSys;
 
+pub mod background;
 pub mod blend;
 pub mod cache;
 mod compute_pipeline;
+mod compute_runner;
 pub mod depth_stencil;
 mod graphics_pipeline;
+pub mod hot_reload;
 pub mod input_assembly;
 pub mod layout;
 pub mod multisample;
@@ -0,0 +1,380 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Compilation of GLSL code at runtime, with `shaderc`.
+
+use crate::device::Device;
+use crate::pipeline::shader::reflect::ShaderReflection;
+use crate::pipeline::shader::reflect::SpirvParseError;
+use crate::pipeline::shader::ShaderModule;
+use crate::pipeline::shader::ShaderStage;
+use crate::OomError;
+use crate::Version;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+/// Compiles GLSL source code into a shader module.
+///
+/// This is the runtime counterpart of the `shader!` macro of the `vulkano-shaders` crate, for
+/// tools and for reloading shaders while the application is running. `stage` is the stage that
+/// the source code is written for, and is used instead of a `#pragma shader_stage` directive.
+///
+/// Unless `options.target_version` says otherwise, the code is compiled for the API version of
+/// `device`.
+///
+/// # Safety
+///
+/// - The SPIR-V code produced by the compiler is not validated against the features and limits
+///   of the device, the same as for [`ShaderModule::from_words`].
+///
+pub unsafe fn compile_glsl(
+    device: Arc<Device>,
+    source: &str,
+    stage: ShaderStage,
+    options: &GlslCompileOptions,
+) -> Result<CompiledShader, GlslCompileError> {
+    let mut compiler = shaderc::Compiler::new().ok_or(GlslCompileError::CompilerUnavailable)?;
+    let mut compile_options =
+        shaderc::CompileOptions::new().ok_or(GlslCompileError::CompilerUnavailable)?;
+
+    let target_version = options.target_version.unwrap_or(device.api_version());
+    compile_options.set_target_env(shaderc::TargetEnv::Vulkan, env_version(target_version));
+
+    for (name, value) in &options.macros {
+        compile_options.add_macro_definition(name, value.as_deref());
+    }
+
+    if options.optimize {
+        compile_options.set_optimization_level(shaderc::OptimizationLevel::Performance);
+    }
+
+    if options.debug_info {
+        compile_options.set_generate_debug_info();
+    }
+
+    if let Some(include_callback) = &options.include_callback {
+        set_include_callback(&mut compile_options, include_callback);
+    }
+
+    let artifact = compiler
+        .compile_into_spirv(
+            source,
+            shader_kind(stage),
+            &options.file_name,
+            &options.entry_point,
+            Some(&compile_options),
+        )
+        .map_err(|err| GlslCompileError::Compilation(err.to_string()))?;
+
+    CompiledShader::from_artifact(device, &artifact)
+}
+
+pub(super) fn set_include_callback<'a>(
+    compile_options: &mut shaderc::CompileOptions<'a>,
+    include_callback: &'a IncludeCallback,
+) {
+    compile_options.set_include_callback(
+        move |requested, ty, requester, depth| -> shaderc::IncludeCallbackResult {
+            let ty = match ty {
+                shaderc::IncludeType::Relative => IncludeType::Relative,
+                shaderc::IncludeType::Standard => IncludeType::Standard,
+            };
+            let resolved = include_callback(requested, ty, requester, depth)?;
+
+            Ok(shaderc::ResolvedInclude {
+                resolved_name: resolved.resolved_name,
+                content: resolved.content,
+            })
+        },
+    );
+}
+
+// Vulkan versions are given to shaderc in the same encoding as `VK_MAKE_VERSION`.
+pub(super) fn env_version(version: Version) -> u32 {
+    if version >= Version::V1_2 {
+        shaderc::EnvVersion::Vulkan1_2 as u32
+    } else if version >= Version::V1_1 {
+        shaderc::EnvVersion::Vulkan1_1 as u32
+    } else {
+        shaderc::EnvVersion::Vulkan1_0 as u32
+    }
+}
+
+pub(super) fn shader_kind(stage: ShaderStage) -> shaderc::ShaderKind {
+    match stage {
+        ShaderStage::Vertex => shaderc::ShaderKind::Vertex,
+        ShaderStage::TessellationControl => shaderc::ShaderKind::TessControl,
+        ShaderStage::TessellationEvaluation => shaderc::ShaderKind::TessEvaluation,
+        ShaderStage::Geometry => shaderc::ShaderKind::Geometry,
+        ShaderStage::Fragment => shaderc::ShaderKind::Fragment,
+        ShaderStage::Compute => shaderc::ShaderKind::Compute,
+    }
+}
+
+/// Callback that resolves `#include` directives.
+///
+/// The parameters are the requested file name, the type of the include, the name of the file
+/// that contains the directive and the depth of the include. An `Err` contains the message to
+/// report in the compilation error.
+pub type IncludeCallback =
+    dyn Fn(&str, IncludeType, &str, usize) -> Result<ResolvedInclude, String> + Send + Sync;
+
+/// Options for [`compile_glsl`].
+pub struct GlslCompileOptions {
+    /// Name of the source file, used in error messages and given to the include callback.
+    ///
+    /// The default value is `shader.glsl`.
+    pub file_name: String,
+
+    /// Name of the entry point of the source code.
+    ///
+    /// The default value is `main`.
+    pub entry_point: String,
+
+    /// Vulkan version to compile for. If `None`, the API version of the device is used.
+    ///
+    /// The default value is `None`.
+    pub target_version: Option<Version>,
+
+    /// Macros to define, with their optional value.
+    ///
+    /// The default value is empty.
+    pub macros: Vec<(String, Option<String>)>,
+
+    /// Whether to optimize the code for performance.
+    ///
+    /// The default value is `false`.
+    pub optimize: bool,
+
+    /// Whether to include debug information in the code.
+    ///
+    /// The default value is `false`.
+    pub debug_info: bool,
+
+    /// Callback that resolves `#include` directives. If `None`, the code can't include files.
+    ///
+    /// The default value is `None`.
+    pub include_callback: Option<Box<IncludeCallback>>,
+}
+
+impl Default for GlslCompileOptions {
+    #[inline]
+    fn default() -> GlslCompileOptions {
+        GlslCompileOptions {
+            file_name: "shader.glsl".to_owned(),
+            entry_point: "main".to_owned(),
+            target_version: None,
+            macros: Vec::new(),
+            optimize: false,
+            debug_info: false,
+            include_callback: None,
+        }
+    }
+}
+
+impl fmt::Debug for GlslCompileOptions {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("GlslCompileOptions")
+            .field("file_name", &self.file_name)
+            .field("entry_point", &self.entry_point)
+            .field("target_version", &self.target_version)
+            .field("macros", &self.macros)
+            .field("optimize", &self.optimize)
+            .field("debug_info", &self.debug_info)
+            .field("include_callback", &self.include_callback.is_some())
+            .finish()
+    }
+}
+
+/// Type of an `#include` directive.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IncludeType {
+    /// `#include "file"`, relative to the file that contains the directive.
+    Relative,
+    /// `#include <file>`, in the standard include directories.
+    Standard,
+}
+
+/// Result of resolving an `#include` directive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedInclude {
+    /// Name of the resolved file, used in error messages and for nested includes. Must not be
+    /// empty.
+    pub resolved_name: String,
+    /// Content of the resolved file.
+    pub content: String,
+}
+
+/// Shader module compiled by [`compile_glsl`] or
+/// [`compile_hlsl`](crate::pipeline::shader::compile_hlsl).
+#[derive(Debug)]
+pub struct CompiledShader {
+    module: Arc<ShaderModule>,
+    spirv: Vec<u32>,
+    reflection: ShaderReflection,
+    warnings: String,
+}
+
+impl CompiledShader {
+    pub(super) unsafe fn from_artifact(
+        device: Arc<Device>,
+        artifact: &shaderc::CompilationArtifact,
+    ) -> Result<CompiledShader, GlslCompileError> {
+        let spirv = artifact.as_binary().to_vec();
+        let reflection = ShaderReflection::new(&spirv)?;
+        let module = ShaderModule::from_words(device, &spirv)?;
+
+        Ok(CompiledShader {
+            module,
+            spirv,
+            reflection,
+            warnings: artifact.get_warning_messages(),
+        })
+    }
+
+    /// Returns the shader module.
+    #[inline]
+    pub fn module(&self) -> &Arc<ShaderModule> {
+        &self.module
+    }
+
+    /// Returns the SPIR-V code that the module was built from.
+    #[inline]
+    pub fn spirv(&self) -> &[u32] {
+        &self.spirv
+    }
+
+    /// Returns the information extracted from the SPIR-V code.
+    #[inline]
+    pub fn reflection(&self) -> &ShaderReflection {
+        &self.reflection
+    }
+
+    /// Returns the warnings of the compiler, or an empty string if there were none.
+    #[inline]
+    pub fn warnings(&self) -> &str {
+        &self.warnings
+    }
+}
+
+/// Error that can happen when compiling GLSL or HLSL code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GlslCompileError {
+    /// The shaderc compiler couldn't be initialized.
+    CompilerUnavailable,
+    /// The source code failed to compile. Contains the messages of the compiler.
+    Compilation(String),
+    /// The compiled code couldn't be parsed for reflection.
+    Reflection(SpirvParseError),
+    /// Not enough memory to create the shader module.
+    OomError(OomError),
+}
+
+impl error::Error for GlslCompileError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            GlslCompileError::Reflection(ref err) => Some(err),
+            GlslCompileError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GlslCompileError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            GlslCompileError::CompilerUnavailable => {
+                write!(fmt, "the shaderc compiler couldn't be initialized")
+            }
+            GlslCompileError::Compilation(ref messages) => {
+                write!(fmt, "the source code failed to compile: {}", messages)
+            }
+            GlslCompileError::Reflection(_) => {
+                write!(fmt, "the compiled code couldn't be parsed for reflection")
+            }
+            GlslCompileError::OomError(_) => write!(fmt, "not enough memory available"),
+        }
+    }
+}
+
+impl From<SpirvParseError> for GlslCompileError {
+    #[inline]
+    fn from(err: SpirvParseError) -> GlslCompileError {
+        GlslCompileError::Reflection(err)
+    }
+}
+
+impl From<OomError> for GlslCompileError {
+    #[inline]
+    fn from(err: OomError) -> GlslCompileError {
+        GlslCompileError::OomError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pipeline::shader::compile_glsl;
+    use crate::pipeline::shader::GlslCompileError;
+    use crate::pipeline::shader::GlslCompileOptions;
+    use crate::pipeline::shader::ResolvedInclude;
+    use crate::pipeline::shader::ShaderStage;
+
+    #[test]
+    fn compile_with_include() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let options = GlslCompileOptions {
+            include_callback: Some(Box::new(|requested, _, _, _| {
+                assert_eq!(requested, "common.glsl");
+                Ok(ResolvedInclude {
+                    resolved_name: "common.glsl".to_owned(),
+                    content: "#define LOCAL_SIZE 64".to_owned(),
+                })
+            })),
+            ..Default::default()
+        };
+        let source = "
+            #version 450
+            #extension GL_GOOGLE_include_directive : require
+            #include \"common.glsl\"
+            layout(local_size_x = LOCAL_SIZE) in;
+            void main() {}
+        ";
+
+        let shader = unsafe { compile_glsl(device, source, ShaderStage::Compute, &options) };
+        let shader = shader.unwrap();
+        assert_eq!(
+            shader.reflection().entry_point("main").unwrap().stage,
+            ShaderStage::Compute
+        );
+    }
+
+    #[test]
+    fn compilation_error() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let source = "#version 450\nvoid main() { undefined(); }";
+        let result = unsafe {
+            compile_glsl(
+                device,
+                source,
+                ShaderStage::Fragment,
+                &GlslCompileOptions::default(),
+            )
+        };
+
+        match result {
+            Err(GlslCompileError::Compilation(_)) => (),
+            _ => panic!(),
+        }
+    }
+}
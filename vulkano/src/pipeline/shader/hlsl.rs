@@ -0,0 +1,270 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Compilation of HLSL code at runtime, with the HLSL front-end of `shaderc`.
+
+use crate::device::Device;
+use crate::pipeline::shader::glsl::env_version;
+use crate::pipeline::shader::glsl::set_include_callback;
+use crate::pipeline::shader::glsl::shader_kind;
+use crate::pipeline::shader::CompiledShader;
+use crate::pipeline::shader::GlslCompileError;
+use crate::pipeline::shader::IncludeCallback;
+use crate::pipeline::shader::ShaderStage;
+use crate::Version;
+use std::fmt;
+use std::sync::Arc;
+
+/// Compiles HLSL source code into a shader module.
+///
+/// `stage` is the stage that the entry point `options.entry_point` is written for. The
+/// descriptors are bound with the `register` annotations of the code, moved by
+/// `options.binding_shifts` and overridden by `options.register_bindings`, in the same way as the
+/// `-fvk-*-shift` and `-fvk-bind-register` options of DXC.
+///
+/// Unless `options.target_version` says otherwise, the code is compiled for the API version of
+/// `device`.
+///
+/// # Safety
+///
+/// - The SPIR-V code produced by the compiler is not validated against the features and limits
+///   of the device, the same as for [`ShaderModule::from_words`](super::ShaderModule::from_words).
+///
+pub unsafe fn compile_hlsl(
+    device: Arc<Device>,
+    source: &str,
+    stage: ShaderStage,
+    options: &HlslCompileOptions,
+) -> Result<CompiledShader, GlslCompileError> {
+    let mut compiler = shaderc::Compiler::new().ok_or(GlslCompileError::CompilerUnavailable)?;
+    let mut compile_options =
+        shaderc::CompileOptions::new().ok_or(GlslCompileError::CompilerUnavailable)?;
+
+    compile_options.set_source_language(shaderc::SourceLanguage::HLSL);
+    compile_options.set_hlsl_io_mapping(true);
+    compile_options.set_auto_bind_uniforms(options.auto_bind_resources);
+
+    let target_version = options.target_version.unwrap_or(device.api_version());
+    compile_options.set_target_env(shaderc::TargetEnv::Vulkan, env_version(target_version));
+
+    let shifts = &options.binding_shifts;
+    compile_options.set_binding_base(shaderc::ResourceKind::Buffer, shifts.constant_buffer);
+    compile_options.set_binding_base(shaderc::ResourceKind::Texture, shifts.texture);
+    compile_options.set_binding_base(shaderc::ResourceKind::Sampler, shifts.sampler);
+    compile_options.set_binding_base(
+        shaderc::ResourceKind::UnorderedAccessView,
+        shifts.unordered_access_view,
+    );
+
+    for register_binding in &options.register_bindings {
+        compile_options.set_hlsl_register_set_and_binding(
+            &register_binding.register,
+            &register_binding.set.to_string(),
+            &register_binding.binding.to_string(),
+        );
+    }
+
+    for (name, value) in &options.macros {
+        compile_options.add_macro_definition(name, value.as_deref());
+    }
+
+    if options.optimize {
+        compile_options.set_optimization_level(shaderc::OptimizationLevel::Performance);
+    }
+
+    if options.debug_info {
+        compile_options.set_generate_debug_info();
+    }
+
+    if let Some(include_callback) = &options.include_callback {
+        set_include_callback(&mut compile_options, include_callback);
+    }
+
+    let artifact = compiler
+        .compile_into_spirv(
+            source,
+            shader_kind(stage),
+            &options.file_name,
+            &options.entry_point,
+            Some(&compile_options),
+        )
+        .map_err(|err| GlslCompileError::Compilation(err.to_string()))?;
+
+    CompiledShader::from_artifact(device, &artifact)
+}
+
+/// Options for [`compile_hlsl`].
+pub struct HlslCompileOptions {
+    /// Name of the source file, used in error messages and given to the include callback.
+    ///
+    /// The default value is `shader.hlsl`.
+    pub file_name: String,
+
+    /// Name of the entry point to compile. It is also the name of the entry point of the
+    /// compiled shader module.
+    ///
+    /// The default value is `main`.
+    pub entry_point: String,
+
+    /// Vulkan version to compile for, the equivalent of the `-fspv-target-env` option of DXC. If
+    /// `None`, the API version of the device is used.
+    ///
+    /// The default value is `None`.
+    pub target_version: Option<Version>,
+
+    /// Values added to the binding numbers of the `register` annotations, for each type of
+    /// register.
+    ///
+    /// The default value doesn't shift any binding.
+    pub binding_shifts: HlslBindingShifts,
+
+    /// Registers that are bound to an explicit descriptor set and binding, the equivalent of the
+    /// `-fvk-bind-register` option of DXC. These take precedence over `binding_shifts`.
+    ///
+    /// The default value is empty.
+    pub register_bindings: Vec<HlslRegisterBinding>,
+
+    /// Whether to give a binding to the resources that don't have a `register` annotation. If
+    /// `false`, these resources have no binding and the compilation of the code fails if they are
+    /// used.
+    ///
+    /// The default value is `false`.
+    pub auto_bind_resources: bool,
+
+    /// Macros to define, with their optional value.
+    ///
+    /// The default value is empty.
+    pub macros: Vec<(String, Option<String>)>,
+
+    /// Whether to optimize the code for performance.
+    ///
+    /// The default value is `false`.
+    pub optimize: bool,
+
+    /// Whether to include debug information in the code.
+    ///
+    /// The default value is `false`.
+    pub debug_info: bool,
+
+    /// Callback that resolves `#include` directives. If `None`, the code can't include files.
+    ///
+    /// The default value is `None`.
+    pub include_callback: Option<Box<IncludeCallback>>,
+}
+
+impl Default for HlslCompileOptions {
+    #[inline]
+    fn default() -> HlslCompileOptions {
+        HlslCompileOptions {
+            file_name: "shader.hlsl".to_owned(),
+            entry_point: "main".to_owned(),
+            target_version: None,
+            binding_shifts: HlslBindingShifts::default(),
+            register_bindings: Vec::new(),
+            auto_bind_resources: false,
+            macros: Vec::new(),
+            optimize: false,
+            debug_info: false,
+            include_callback: None,
+        }
+    }
+}
+
+impl fmt::Debug for HlslCompileOptions {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("HlslCompileOptions")
+            .field("file_name", &self.file_name)
+            .field("entry_point", &self.entry_point)
+            .field("target_version", &self.target_version)
+            .field("binding_shifts", &self.binding_shifts)
+            .field("register_bindings", &self.register_bindings)
+            .field("auto_bind_resources", &self.auto_bind_resources)
+            .field("macros", &self.macros)
+            .field("optimize", &self.optimize)
+            .field("debug_info", &self.debug_info)
+            .field("include_callback", &self.include_callback.is_some())
+            .finish()
+    }
+}
+
+/// Values added to the binding numbers of HLSL registers, for each type of register.
+///
+/// The HLSL registers of different types can have the same number, while Vulkan descriptors of
+/// the same set can't. Shifting the binding numbers of each type to a separate range, for example
+/// `t0` to binding 0 and `s0` to binding 16, avoids conflicts.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct HlslBindingShifts {
+    /// Shift of the `b` registers, for constant buffers. The equivalent of `-fvk-b-shift`.
+    pub constant_buffer: u32,
+    /// Shift of the `t` registers, for textures and other shader resource views. The equivalent
+    /// of `-fvk-t-shift`.
+    pub texture: u32,
+    /// Shift of the `s` registers, for samplers. The equivalent of `-fvk-s-shift`.
+    pub sampler: u32,
+    /// Shift of the `u` registers, for unordered access views. The equivalent of `-fvk-u-shift`.
+    pub unordered_access_view: u32,
+}
+
+/// HLSL register bound to an explicit descriptor set and binding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HlslRegisterBinding {
+    /// Name of the register, for example `t0` or `u3`.
+    pub register: String,
+    /// Descriptor set of the register.
+    pub set: u32,
+    /// Binding of the register within its descriptor set.
+    pub binding: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pipeline::shader::compile_hlsl;
+    use crate::pipeline::shader::HlslBindingShifts;
+    use crate::pipeline::shader::HlslCompileOptions;
+    use crate::pipeline::shader::HlslRegisterBinding;
+    use crate::pipeline::shader::ShaderStage;
+
+    #[test]
+    fn binding_shifts() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let options = HlslCompileOptions {
+            entry_point: "cs_main".to_owned(),
+            binding_shifts: HlslBindingShifts {
+                texture: 8,
+                ..Default::default()
+            },
+            register_bindings: vec![HlslRegisterBinding {
+                register: "u0".to_owned(),
+                set: 1,
+                binding: 2,
+            }],
+            ..Default::default()
+        };
+        let source = "
+            StructuredBuffer<uint> input : register(t0);
+            RWStructuredBuffer<uint> output : register(u0);
+            [numthreads(64, 1, 1)]
+            void cs_main(uint3 id : SV_DispatchThreadID) { output[id.x] = input[id.x]; }
+        ";
+
+        let shader = unsafe { compile_hlsl(device, source, ShaderStage::Compute, &options) };
+        let shader = shader.unwrap();
+        let entry_point = shader.reflection().entry_point("cs_main").unwrap();
+        assert_eq!(entry_point.stage, ShaderStage::Compute);
+
+        let mut bindings = entry_point
+            .descriptor_bindings
+            .iter()
+            .map(|binding| (binding.set, binding.binding))
+            .collect::<Vec<_>>();
+        bindings.sort_unstable();
+        assert_eq!(bindings, [(0, 8), (1, 2)]);
+    }
+}
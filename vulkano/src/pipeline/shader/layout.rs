@@ -0,0 +1,376 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Description of the layout of Rust data that is given to a shader through a buffer or push
+//! constants, to check it against the layout declared by the shader.
+//!
+//! Small types are where Rust and shader layouts most easily diverge: an `f16vec3` member of a
+//! block is often aligned to 8 bytes, while `[f16; 3]` is aligned to 2 bytes in Rust. Describing
+//! the Rust data with a [`BlockLayout`] and checking it against the members obtained from
+//! [`ShaderReflection`](crate::pipeline::shader::reflect::ShaderReflection) catches these
+//! mismatches when the pipeline is created instead of as corrupted data on the GPU.
+
+use crate::pipeline::shader::reflect::BlockMemberReflection;
+use crate::pipeline::shader::reflect::ScalarType;
+use std::error;
+use std::fmt;
+use std::mem;
+
+/// Rust type that can be the content of a member of a block.
+///
+/// This is implemented for the 8-, 16-, 32- and 64-bit integers, for `half::f16`, `f32` and `f64`,
+/// and for arrays of them, which correspond to vectors, matrices and arrays in shaders.
+///
+/// # Safety
+///
+/// - `SCALAR_TYPE` must have the same size and representation as `Self`, or as the elements of
+///   `Self` for an array.
+///
+pub unsafe trait BlockMemberType: Copy + 'static {
+    /// Type of the scalars that the type is made of.
+    const SCALAR_TYPE: ScalarType;
+}
+
+macro_rules! block_member_types {
+    ($($ty:ty => $scalar_type:expr,)+) => {
+        $(
+            unsafe impl BlockMemberType for $ty {
+                const SCALAR_TYPE: ScalarType = $scalar_type;
+            }
+        )+
+    };
+}
+
+block_member_types! {
+    u8 => ScalarType::Int { width: 8, signed: false },
+    i8 => ScalarType::Int { width: 8, signed: true },
+    u16 => ScalarType::Int { width: 16, signed: false },
+    i16 => ScalarType::Int { width: 16, signed: true },
+    u32 => ScalarType::Int { width: 32, signed: false },
+    i32 => ScalarType::Int { width: 32, signed: true },
+    u64 => ScalarType::Int { width: 64, signed: false },
+    i64 => ScalarType::Int { width: 64, signed: true },
+    half::f16 => ScalarType::Float { width: 16 },
+    f32 => ScalarType::Float { width: 32 },
+    f64 => ScalarType::Float { width: 64 },
+}
+
+unsafe impl<T, const N: usize> BlockMemberType for [T; N]
+where
+    T: BlockMemberType,
+{
+    const SCALAR_TYPE: ScalarType = T::SCALAR_TYPE;
+}
+
+/// Layout of Rust data that is the content of a block.
+///
+/// # Example
+///
+/// ```
+/// use half::f16;
+/// use vulkano::pipeline::shader::BlockLayout;
+///
+/// #[repr(C)]
+/// struct PushConstants {
+///     scale: f16,
+///     offset: [f16; 2],
+///     mask: u8,
+/// }
+///
+/// let layout = BlockLayout::new()
+///     .with_member::<f16>()
+///     .with_member::<[f16; 2]>()
+///     .with_member::<u8>();
+/// assert_eq!(layout.size(), std::mem::size_of::<PushConstants>());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockLayout {
+    members: Vec<BlockLayoutMember>,
+    size: usize,
+    align: usize,
+}
+
+impl BlockLayout {
+    /// Builds a new layout without members.
+    #[inline]
+    pub fn new() -> BlockLayout {
+        BlockLayout {
+            members: Vec::new(),
+            size: 0,
+            align: 1,
+        }
+    }
+
+    /// Adds a member of type `T` after the previous members, at the offset it would have in a
+    /// `#[repr(C)]` struct.
+    #[inline]
+    pub fn with_member<T>(self) -> BlockLayout
+    where
+        T: BlockMemberType,
+    {
+        let offset = align_up(self.end(), mem::align_of::<T>());
+        self.with_member_at::<T>(offset)
+    }
+
+    /// Adds a member of type `T` at `offset` bytes from the start of the data.
+    ///
+    /// This is meant for data whose members are explicitly padded.
+    pub fn with_member_at<T>(mut self, offset: usize) -> BlockLayout
+    where
+        T: BlockMemberType,
+    {
+        self.members.push(BlockLayoutMember {
+            offset,
+            size: mem::size_of::<T>(),
+            scalar_type: T::SCALAR_TYPE,
+        });
+        self.align = self.align.max(mem::align_of::<T>());
+        self.size = align_up(self.end(), self.align);
+        self
+    }
+
+    /// Returns the members of the layout, in the order they were added.
+    #[inline]
+    pub fn members(&self) -> &[BlockLayoutMember] {
+        &self.members
+    }
+
+    /// Returns the size in bytes of the data, including the padding at the end that a
+    /// `#[repr(C)]` struct would have.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Checks that the layout matches the members of a block declared by a shader.
+    ///
+    /// The members must be in the same order, at the same offset, with the same size and the
+    /// same scalar type. For a runtime array, which is always the last member of a block, the
+    /// Rust member describes one element and its size is not checked.
+    pub fn check(&self, members: &[BlockMemberReflection]) -> Result<(), BlockLayoutError> {
+        if self.members.len() != members.len() {
+            return Err(BlockLayoutError::MemberCountMismatch {
+                expected: members.len(),
+                obtained: self.members.len(),
+            });
+        }
+
+        for (index, (member, expected)) in self.members.iter().zip(members).enumerate() {
+            if expected.scalar_type != Some(member.scalar_type) {
+                return Err(BlockLayoutError::TypeMismatch {
+                    member: index,
+                    expected: expected.scalar_type,
+                    obtained: member.scalar_type,
+                });
+            }
+
+            if expected.offset as usize != member.offset {
+                return Err(BlockLayoutError::OffsetMismatch {
+                    member: index,
+                    expected: expected.offset,
+                    obtained: member.offset,
+                });
+            }
+
+            if expected.size != 0 && expected.size as usize != member.size {
+                return Err(BlockLayoutError::SizeMismatch {
+                    member: index,
+                    expected: expected.size,
+                    obtained: member.size,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn end(&self) -> usize {
+        self.members
+            .iter()
+            .map(|member| member.offset + member.size)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Default for BlockLayout {
+    #[inline]
+    fn default() -> BlockLayout {
+        BlockLayout::new()
+    }
+}
+
+#[inline]
+fn align_up(value: usize, align: usize) -> usize {
+    match value % align {
+        0 => value,
+        rem => value + align - rem,
+    }
+}
+
+/// Member of a `BlockLayout`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockLayoutMember {
+    /// Offset in bytes of the member from the start of the data.
+    pub offset: usize,
+    /// Size in bytes of the member.
+    pub size: usize,
+    /// Type of the scalars that the member is made of.
+    pub scalar_type: ScalarType,
+}
+
+/// Error that can happen when checking a `BlockLayout` against the layout declared by a shader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockLayoutError {
+    /// The number of members is different.
+    MemberCountMismatch {
+        /// Number of members declared by the shader.
+        expected: usize,
+        /// Number of members of the layout.
+        obtained: usize,
+    },
+
+    /// The scalar type of a member is different.
+    TypeMismatch {
+        /// Index of the member.
+        member: usize,
+        /// Scalar type declared by the shader, or `None` if the member is a boolean or a struct.
+        expected: Option<ScalarType>,
+        /// Scalar type of the layout.
+        obtained: ScalarType,
+    },
+
+    /// The offset of a member is different.
+    OffsetMismatch {
+        /// Index of the member.
+        member: usize,
+        /// Offset declared by the shader.
+        expected: u32,
+        /// Offset of the layout.
+        obtained: usize,
+    },
+
+    /// The size of a member is different.
+    SizeMismatch {
+        /// Index of the member.
+        member: usize,
+        /// Size declared by the shader.
+        expected: u32,
+        /// Size of the layout.
+        obtained: usize,
+    },
+}
+
+impl error::Error for BlockLayoutError {}
+
+impl fmt::Display for BlockLayoutError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            BlockLayoutError::MemberCountMismatch { expected, obtained } => write!(
+                fmt,
+                "the shader declares {} members, but the layout has {}",
+                expected, obtained
+            ),
+            BlockLayoutError::TypeMismatch {
+                member,
+                expected,
+                obtained,
+            } => write!(
+                fmt,
+                "member {} has a scalar type of {:?} in the shader, but {:?} in the layout",
+                member, expected, obtained
+            ),
+            BlockLayoutError::OffsetMismatch {
+                member,
+                expected,
+                obtained,
+            } => write!(
+                fmt,
+                "member {} is at offset {} in the shader, but {} in the layout",
+                member, expected, obtained
+            ),
+            BlockLayoutError::SizeMismatch {
+                member,
+                expected,
+                obtained,
+            } => write!(
+                fmt,
+                "member {} has a size of {} bytes in the shader, but {} in the layout",
+                member, expected, obtained
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pipeline::shader::reflect::BlockMemberReflection;
+    use crate::pipeline::shader::reflect::ScalarType;
+    use crate::pipeline::shader::BlockLayout;
+    use crate::pipeline::shader::BlockLayoutError;
+    use half::f16;
+
+    fn member(offset: u32, size: u32, scalar_type: ScalarType) -> BlockMemberReflection {
+        BlockMemberReflection {
+            name: None,
+            offset,
+            size,
+            scalar_type: Some(scalar_type),
+        }
+    }
+
+    #[test]
+    fn small_types() {
+        // layout(std430) buffer { float16_t a; f16vec3 b; uint8_t c; }
+        let half = ScalarType::Float { width: 16 };
+        let byte = ScalarType::Int {
+            width: 8,
+            signed: false,
+        };
+        let members = [member(0, 2, half), member(8, 6, half), member(14, 1, byte)];
+
+        let layout = BlockLayout::new()
+            .with_member::<f16>()
+            .with_member::<[f16; 3]>()
+            .with_member::<u8>();
+        assert_eq!(
+            layout.check(&members),
+            Err(BlockLayoutError::OffsetMismatch {
+                member: 1,
+                expected: 8,
+                obtained: 2,
+            })
+        );
+
+        let layout = BlockLayout::new()
+            .with_member::<f16>()
+            .with_member_at::<[f16; 3]>(8)
+            .with_member::<u8>();
+        assert!(layout.check(&members).is_ok());
+        assert_eq!(layout.size(), 16);
+
+        let layout = BlockLayout::new()
+            .with_member::<f16>()
+            .with_member_at::<[u16; 3]>(8)
+            .with_member::<u8>();
+        assert_eq!(
+            layout.check(&members),
+            Err(BlockLayoutError::TypeMismatch {
+                member: 1,
+                expected: Some(half),
+                obtained: ScalarType::Int {
+                    width: 16,
+                    signed: false,
+                },
+            })
+        );
+    }
+}
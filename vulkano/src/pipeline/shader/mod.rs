@@ -13,13 +13,16 @@
 //! code and can contain one or more entry points. Note that for the moment the official
 //! GLSL-to-SPIR-V compiler does not support multiple entry points.
 //!
-//! The vulkano library does not provide any functionality that checks and introspects the SPIR-V
-//! code, therefore the whole shader-related API is unsafe. You are encouraged to use the
-//! `vulkano-shaders` crate that will generate Rust code that wraps around vulkano's shaders API.
+//! The vulkano library does not check the SPIR-V code, therefore the whole shader-related API is
+//! unsafe. You are encouraged to use the `vulkano-shaders` crate that will generate Rust code that
+//! wraps around vulkano's shaders API. The [`reflect`] module can introspect SPIR-V code at
+//! runtime, and with the `shaderc` feature enabled, [`compile_glsl`] and [`compile_hlsl`] compile
+//! GLSL and HLSL at runtime for tools and hot-reloading.
 
 use crate::check_errors;
 use crate::descriptor_set::layout::DescriptorSetDesc;
 use crate::device::Device;
+use crate::device::DeviceOwned;
 use crate::format::Format;
 use crate::pipeline::input_assembly::PrimitiveTopology;
 use crate::pipeline::layout::PipelineLayoutPcRange;
@@ -35,9 +38,48 @@ use std::mem;
 use std::mem::MaybeUninit;
 use std::ops::BitOr;
 use std::ops::Range;
+use std::panic::Location;
 use std::ptr;
 use std::sync::Arc;
 
+#[cfg(feature = "shaderc")]
+pub use self::glsl::compile_glsl;
+#[cfg(feature = "shaderc")]
+pub use self::glsl::CompiledShader;
+#[cfg(feature = "shaderc")]
+pub use self::glsl::GlslCompileError;
+#[cfg(feature = "shaderc")]
+pub use self::glsl::GlslCompileOptions;
+#[cfg(feature = "shaderc")]
+pub use self::glsl::IncludeCallback;
+#[cfg(feature = "shaderc")]
+pub use self::glsl::IncludeType;
+#[cfg(feature = "shaderc")]
+pub use self::glsl::ResolvedInclude;
+#[cfg(feature = "shaderc")]
+pub use self::hlsl::compile_hlsl;
+#[cfg(feature = "shaderc")]
+pub use self::hlsl::HlslBindingShifts;
+#[cfg(feature = "shaderc")]
+pub use self::hlsl::HlslCompileOptions;
+#[cfg(feature = "shaderc")]
+pub use self::hlsl::HlslRegisterBinding;
+pub use self::layout::BlockLayout;
+pub use self::layout::BlockLayoutError;
+pub use self::layout::BlockLayoutMember;
+pub use self::layout::BlockMemberType;
+pub use self::module_cache::CachedShader;
+pub use self::module_cache::ShaderModuleCache;
+pub use self::module_cache::ShaderModuleCacheError;
+
+#[cfg(feature = "shaderc")]
+mod glsl;
+#[cfg(feature = "shaderc")]
+mod hlsl;
+mod layout;
+mod module_cache;
+pub mod reflect;
+
 /// Contains SPIR-V code with one or more entry points.
 ///
 /// Note that it is advised to wrap around a `ShaderModule` with a struct that is different for
@@ -59,6 +101,7 @@ impl ShaderModule {
     /// - The SPIR-V code may require some features that are not enabled. This isn't checked by
     ///   this function either.
     ///
+    #[track_caller]
     pub unsafe fn new(device: Arc<Device>, spirv: &[u8]) -> Result<Arc<ShaderModule>, OomError> {
         debug_assert!((spirv.len() % 4) == 0);
         Self::from_ptr(device, spirv.as_ptr() as *const _, spirv.len())
@@ -72,6 +115,7 @@ impl ShaderModule {
     /// - The SPIR-V code may require some features that are not enabled. This isn't checked by
     ///   this function either.
     ///
+    #[track_caller]
     pub unsafe fn from_words(
         device: Arc<Device>,
         spirv: &[u32],
@@ -87,6 +131,7 @@ impl ShaderModule {
     /// - The SPIR-V code may require some features that are not enabled. This isn't checked by
     ///   this function either.
     ///
+    #[track_caller]
     unsafe fn from_ptr(
         device: Arc<Device>,
         spirv: *const u32,
@@ -111,10 +156,12 @@ impl ShaderModule {
             output.assume_init()
         };
 
-        Ok(Arc::new(ShaderModule {
+        let module = Arc::new(ShaderModule {
             module: module,
             device: device,
-        }))
+        });
+        module.device.auto_debug_name(&*module, Location::caller());
+        Ok(module)
     }
 
     /// Gets access to an entry point contained in this module.
@@ -182,10 +229,18 @@ impl ShaderModule {
             descriptor_set_layout_descs: descriptor_set_layout_descs.into_iter().collect(),
             push_constant_range,
             spec_constants,
+            local_size: None,
         }
     }
 }
 
+unsafe impl DeviceOwned for ShaderModule {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
 unsafe impl VulkanObject for ShaderModule {
     type Object = ash::vk::ShaderModule;
 
@@ -221,6 +276,12 @@ pub unsafe trait EntryPointAbstract {
 
     /// Returns the layout of the specialization constants.
     fn spec_constants(&self) -> &[SpecializationMapEntry];
+
+    /// Returns the local size of the workgroups of a compute entry point, if it is known.
+    #[inline]
+    fn local_size(&self) -> Option<[u32; 3]> {
+        None
+    }
 }
 
 /// Represents a shader entry point in a shader module.
@@ -347,6 +408,25 @@ pub struct ComputeEntryPoint<'a> {
     descriptor_set_layout_descs: SmallVec<[DescriptorSetDesc; 16]>,
     push_constant_range: Option<PipelineLayoutPcRange>,
     spec_constants: &'static [SpecializationMapEntry],
+    local_size: Option<[u32; 3]>,
+}
+
+impl<'a> ComputeEntryPoint<'a> {
+    /// Sets the local size of the workgroups of the entry point.
+    ///
+    /// The local size is needed to check the `require_full_subgroups` member of
+    /// [`SubgroupSizeControl`]. The `shader!` macro provides it, unless the workgroup size
+    /// depends on specialization constants.
+    ///
+    /// # Safety
+    ///
+    /// - `local_size` must be the local size of the entry point.
+    ///
+    #[inline]
+    pub unsafe fn with_local_size(mut self, local_size: [u32; 3]) -> ComputeEntryPoint<'a> {
+        self.local_size = Some(local_size);
+        self
+    }
 }
 
 unsafe impl<'a> EntryPointAbstract for ComputeEntryPoint<'a> {
@@ -374,6 +454,11 @@ unsafe impl<'a> EntryPointAbstract for ComputeEntryPoint<'a> {
     fn spec_constants(&self) -> &[SpecializationMapEntry] {
         self.spec_constants
     }
+
+    #[inline]
+    fn local_size(&self) -> Option<[u32; 3]> {
+        self.local_size
+    }
 }
 
 /// Type that contains the definition of an interface between two shader stages, or between
@@ -612,9 +697,217 @@ pub struct SpecializationMapEntry {
     pub size: usize,
 }
 
+/// Checks whether specialization data can be used for an entry point.
+///
+/// `provided` describes the layout of the data, which is `data_size` bytes long, and `expected`
+/// contains the specialization constants of the entry point. Their offsets are ignored. The
+/// entries don't need to be in the same order, but each constant of the entry point must be
+/// provided exactly once with the same size.
+pub fn check_specialization_constants(
+    expected: &[SpecializationMapEntry],
+    provided: &[SpecializationMapEntry],
+    data_size: usize,
+) -> Result<(), SpecializationConstantsError> {
+    for (index, entry) in provided.iter().enumerate() {
+        let constant_id = entry.constant_id;
+
+        if provided[..index]
+            .iter()
+            .any(|other| other.constant_id == constant_id)
+        {
+            return Err(SpecializationConstantsError::DuplicateConstant { constant_id });
+        }
+
+        if entry.offset as usize + entry.size > data_size {
+            return Err(SpecializationConstantsError::OutOfBounds { constant_id });
+        }
+
+        match expected
+            .iter()
+            .find(|expected| expected.constant_id == constant_id)
+        {
+            Some(expected) if expected.size != entry.size => {
+                return Err(SpecializationConstantsError::SizeMismatch {
+                    constant_id,
+                    expected: expected.size,
+                    obtained: entry.size,
+                });
+            }
+            Some(_) => (),
+            None => {
+                return Err(SpecializationConstantsError::UnexpectedConstant { constant_id });
+            }
+        }
+    }
+
+    if let Some(missing) = expected.iter().find(|expected| {
+        !provided
+            .iter()
+            .any(|entry| entry.constant_id == expected.constant_id)
+    }) {
+        return Err(SpecializationConstantsError::MissingConstant {
+            constant_id: missing.constant_id,
+        });
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when specialization data doesn't match the specialization constants of
+/// an entry point.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpecializationConstantsError {
+    /// A specialization constant of the entry point isn't provided.
+    MissingConstant {
+        /// Constant id of the missing constant.
+        constant_id: u32,
+    },
+
+    /// A constant is provided that the entry point doesn't have.
+    UnexpectedConstant {
+        /// Constant id of the unexpected constant.
+        constant_id: u32,
+    },
+
+    /// A constant is provided with a size that doesn't match its type in the entry point.
+    SizeMismatch {
+        /// Constant id of the constant.
+        constant_id: u32,
+        /// Size of the constant in the entry point.
+        expected: usize,
+        /// Size of the provided constant.
+        obtained: usize,
+    },
+
+    /// A constant is provided more than once.
+    DuplicateConstant {
+        /// Constant id of the constant.
+        constant_id: u32,
+    },
+
+    /// A constant is located outside of the specialization data.
+    OutOfBounds {
+        /// Constant id of the constant.
+        constant_id: u32,
+    },
+}
+
+impl error::Error for SpecializationConstantsError {}
+
+impl fmt::Display for SpecializationConstantsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            SpecializationConstantsError::MissingConstant { constant_id } => write!(
+                fmt,
+                "the specialization constant {} of the shader is not provided",
+                constant_id
+            ),
+            SpecializationConstantsError::UnexpectedConstant { constant_id } => write!(
+                fmt,
+                "the specialization constant {} is provided but the shader doesn't have it",
+                constant_id
+            ),
+            SpecializationConstantsError::SizeMismatch {
+                constant_id,
+                expected,
+                obtained,
+            } => write!(
+                fmt,
+                "the specialization constant {} is {} bytes long but the shader expects {} bytes",
+                constant_id, obtained, expected
+            ),
+            SpecializationConstantsError::DuplicateConstant { constant_id } => write!(
+                fmt,
+                "the specialization constant {} is provided more than once",
+                constant_id
+            ),
+            SpecializationConstantsError::OutOfBounds { constant_id } => write!(
+                fmt,
+                "the specialization constant {} is outside of the specialization data",
+                constant_id
+            ),
+        }
+    }
+}
+
+/// Controls the size of the subgroups that a shader stage is run with.
+///
+/// Everything except the default value requires the `ext_subgroup_size_control` extension.
+/// Without it, the subgroup size is chosen by the implementation and can vary between pipelines,
+/// but it is never larger than the `subgroup_size` property of the physical device.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct SubgroupSizeControl {
+    /// If `Some`, the stage is run with exactly this subgroup size, and the `SubgroupSize`
+    /// built-in has this value.
+    ///
+    /// Requires the `subgroup_size_control` feature. The value must be a power of two between
+    /// the `min_subgroup_size` and `max_subgroup_size` properties, and the stage must be part of
+    /// the `required_subgroup_size_stages` property.
+    pub required_subgroup_size: Option<u32>,
+
+    /// If `true`, the subgroup size can vary between invocations of the stage, and the
+    /// `SubgroupSize` built-in can have any value allowed by the device. Can't be used together
+    /// with `required_subgroup_size`.
+    ///
+    /// Requires the `subgroup_size_control` feature.
+    pub allow_varying_subgroup_size: bool,
+
+    /// If `true`, all the invocations of each subgroup are active. Only for compute shaders.
+    ///
+    /// Requires the `compute_full_subgroups` feature. The local size along the X dimension must
+    /// be a multiple of the subgroup size: `required_subgroup_size` if it is provided, or
+    /// otherwise the `max_subgroup_size` property if `allow_varying_subgroup_size` is `true` and
+    /// the `subgroup_size` property if it isn't. The local size of the entry point must be known,
+    /// see [`ComputeEntryPoint::with_local_size`].
+    pub require_full_subgroups: bool,
+}
+
+impl SubgroupSizeControl {
+    #[inline]
+    pub(crate) fn flags(&self) -> ash::vk::PipelineShaderStageCreateFlags {
+        let mut flags = ash::vk::PipelineShaderStageCreateFlags::empty();
+        if self.allow_varying_subgroup_size {
+            flags |= ash::vk::PipelineShaderStageCreateFlags::ALLOW_VARYING_SUBGROUP_SIZE_EXT;
+        }
+        if self.require_full_subgroups {
+            flags |= ash::vk::PipelineShaderStageCreateFlags::REQUIRE_FULL_SUBGROUPS_EXT;
+        }
+        flags
+    }
+}
+
+/// A single shader stage.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+    Vertex,
+    TessellationControl,
+    TessellationEvaluation,
+    Geometry,
+    Fragment,
+    Compute,
+}
+
+impl From<ShaderStage> for ShaderStages {
+    #[inline]
+    fn from(val: ShaderStage) -> Self {
+        let mut result = ShaderStages::none();
+        match val {
+            ShaderStage::Vertex => result.vertex = true,
+            ShaderStage::TessellationControl => result.tessellation_control = true,
+            ShaderStage::TessellationEvaluation => result.tessellation_evaluation = true,
+            ShaderStage::Geometry => result.geometry = true,
+            ShaderStage::Fragment => result.fragment = true,
+            ShaderStage::Compute => result.compute = true,
+        }
+        result
+    }
+}
+
 /// Describes a set of shader stages.
 // TODO: add example with BitOr
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ShaderStages {
     pub vertex: bool,
     pub tessellation_control: bool,
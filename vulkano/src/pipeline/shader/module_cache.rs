@@ -0,0 +1,367 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::pipeline::shader::reflect::ShaderReflection;
+use crate::pipeline::shader::reflect::SpirvParseError;
+use crate::pipeline::shader::ShaderModule;
+use crate::OomError;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
+
+/// Shares shader modules between the users of identical SPIR-V code.
+///
+/// Loading the same SPIR-V code through the cache returns the same `ShaderModule` and the same
+/// reflection data, as long as a previous result is still alive. This avoids creating and
+/// parsing the module again when many materials use the same shaders.
+///
+/// The cache doesn't keep the modules alive by itself: a module is destroyed once all the values
+/// returned for it are dropped, and the next load of its code creates it again. The reflection
+/// data is kept as long as the entry of its module, which is removed when a module is added to
+/// the cache after the module was destroyed.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use vulkano::device::Device;
+/// # let device: Arc<Device> = return;
+/// # let spirv: Vec<u32> = return;
+/// use vulkano::pipeline::shader::ShaderModuleCache;
+///
+/// let cache = ShaderModuleCache::new(device.clone());
+///
+/// let first = unsafe { cache.load(&spirv).unwrap() };
+/// let second = unsafe { cache.load(&spirv).unwrap() };
+/// assert!(Arc::ptr_eq(first.module(), second.module()));
+/// ```
+pub struct ShaderModuleCache {
+    device: Arc<Device>,
+    // Entries indexed by the hash of their code. The code is kept to rule out hash collisions.
+    entries: Mutex<HashMap<u64, Vec<Entry>>>,
+}
+
+struct Entry {
+    spirv: Box<[u32]>,
+    // The entry is alive as long as the module is.
+    module: Weak<ShaderModule>,
+    reflection: Arc<ShaderReflection>,
+}
+
+impl ShaderModuleCache {
+    /// Builds a new empty cache.
+    #[inline]
+    pub fn new(device: Arc<Device>) -> ShaderModuleCache {
+        ShaderModuleCache {
+            device,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the shader module and the reflection data of SPIR-V code given as 32-bit words,
+    /// creating them if the cache doesn't contain them.
+    ///
+    /// # Safety
+    ///
+    /// - The SPIR-V code is not validated, the same as for [`ShaderModule::from_words`].
+    ///
+    pub unsafe fn load(&self, spirv: &[u32]) -> Result<CachedShader, ShaderModuleCacheError> {
+        let hash = hash(spirv);
+        let mut entries = self.entries.lock().unwrap();
+
+        if let Some(cached) = entries.get(&hash).and_then(|bucket| find(bucket, spirv)) {
+            return Ok(cached);
+        }
+
+        let reflection = Arc::new(ShaderReflection::new(spirv)?);
+        let module = ShaderModule::from_words(self.device.clone(), spirv)?;
+        insert(&mut entries, hash, spirv, &module, &reflection);
+
+        Ok(CachedShader { module, reflection })
+    }
+
+    /// Same as `load`, but uses `reflection` instead of parsing the code when the cache doesn't
+    /// contain it.
+    ///
+    /// This allows loading code that [`ShaderReflection::new`] rejects, such as code using
+    /// capabilities that are supported by the device but not yet by vulkano. `reflection` is
+    /// usually built with [`ShaderReflection::from_entry_points`].
+    ///
+    /// If the cache already contains the code, the cached module and reflection data are returned
+    /// and `reflection` is dropped.
+    ///
+    /// # Safety
+    ///
+    /// - The SPIR-V code is not validated, the same as for [`ShaderModule::from_words`].
+    /// - `reflection` must correctly describe the entry points of the code.
+    ///
+    pub unsafe fn load_unchecked(
+        &self,
+        spirv: &[u32],
+        reflection: ShaderReflection,
+    ) -> Result<CachedShader, OomError> {
+        let hash = hash(spirv);
+        let mut entries = self.entries.lock().unwrap();
+
+        if let Some(cached) = entries.get(&hash).and_then(|bucket| find(bucket, spirv)) {
+            return Ok(cached);
+        }
+
+        let reflection = Arc::new(reflection);
+        let module = ShaderModule::from_words(self.device.clone(), spirv)?;
+        insert(&mut entries, hash, spirv, &module, &reflection);
+
+        Ok(CachedShader { module, reflection })
+    }
+
+    /// Same as `load`, but with SPIR-V code given as bytes.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the length of `spirv` isn't a multiple of 4.
+    ///
+    /// # Safety
+    ///
+    /// - The SPIR-V code is not validated, the same as for [`ShaderModule::new`].
+    ///
+    pub unsafe fn load_bytes(&self, spirv: &[u8]) -> Result<CachedShader, ShaderModuleCacheError> {
+        assert_eq!(spirv.len() % 4, 0);
+        let words = spirv
+            .chunks(4)
+            .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+            .collect::<Vec<_>>();
+        self.load(&words)
+    }
+
+    /// Returns the number of shader modules of the cache that are still alive.
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .unwrap()
+            .values()
+            .flatten()
+            .filter(|entry| entry.module.strong_count() != 0)
+            .count()
+    }
+
+    /// Returns true if the cache doesn't contain any shader module that is still alive.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn hash(spirv: &[u32]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    spirv.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn find(bucket: &[Entry], spirv: &[u32]) -> Option<CachedShader> {
+    let entry = bucket.iter().find(|entry| *entry.spirv == *spirv)?;
+    let module = entry.module.upgrade()?;
+    Some(CachedShader {
+        module,
+        reflection: entry.reflection.clone(),
+    })
+}
+
+// Adds an entry, and removes the entries whose module was destroyed, so that the cache doesn't
+// grow with code that is never loaded again.
+fn insert(
+    entries: &mut HashMap<u64, Vec<Entry>>,
+    hash: u64,
+    spirv: &[u32],
+    module: &Arc<ShaderModule>,
+    reflection: &Arc<ShaderReflection>,
+) {
+    entries.retain(|_, bucket| {
+        bucket.retain(|entry| entry.module.strong_count() != 0 && *entry.spirv != *spirv);
+        !bucket.is_empty()
+    });
+    entries.entry(hash).or_default().push(Entry {
+        spirv: spirv.into(),
+        module: Arc::downgrade(module),
+        reflection: reflection.clone(),
+    });
+}
+
+unsafe impl DeviceOwned for ShaderModuleCache {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+impl fmt::Debug for ShaderModuleCache {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("ShaderModuleCache")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Shader module returned by a `ShaderModuleCache`, with its reflection data.
+#[derive(Debug, Clone)]
+pub struct CachedShader {
+    module: Arc<ShaderModule>,
+    reflection: Arc<ShaderReflection>,
+}
+
+impl CachedShader {
+    /// Returns the shader module.
+    #[inline]
+    pub fn module(&self) -> &Arc<ShaderModule> {
+        &self.module
+    }
+
+    /// Returns the information extracted from the SPIR-V code.
+    #[inline]
+    pub fn reflection(&self) -> &Arc<ShaderReflection> {
+        &self.reflection
+    }
+}
+
+/// Error that can happen when loading a shader module through a `ShaderModuleCache`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShaderModuleCacheError {
+    /// The code couldn't be parsed for reflection.
+    Reflection(SpirvParseError),
+    /// Not enough memory to create the shader module.
+    OomError(OomError),
+}
+
+impl error::Error for ShaderModuleCacheError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ShaderModuleCacheError::Reflection(ref err) => Some(err),
+            ShaderModuleCacheError::OomError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for ShaderModuleCacheError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            ShaderModuleCacheError::Reflection(_) => {
+                write!(fmt, "the code couldn't be parsed for reflection")
+            }
+            ShaderModuleCacheError::OomError(_) => write!(fmt, "not enough memory available"),
+        }
+    }
+}
+
+impl From<SpirvParseError> for ShaderModuleCacheError {
+    #[inline]
+    fn from(err: SpirvParseError) -> ShaderModuleCacheError {
+        ShaderModuleCacheError::Reflection(err)
+    }
+}
+
+impl From<OomError> for ShaderModuleCacheError {
+    #[inline]
+    fn from(err: OomError) -> ShaderModuleCacheError {
+        ShaderModuleCacheError::OomError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pipeline::shader::reflect::EntryPointReflection;
+    use crate::pipeline::shader::reflect::ExecutionMode;
+    use crate::pipeline::shader::reflect::ShaderReflection;
+    use crate::pipeline::shader::ShaderModuleCache;
+    use crate::pipeline::shader::ShaderStage;
+    use crate::tests::empty_compute_spirv;
+    use std::sync::Arc;
+
+    fn spirv(name: &[u8; 4]) -> Vec<u32> {
+        empty_compute_spirv(name, false)
+    }
+
+    #[test]
+    fn deduplication() {
+        let (device, _) = gfx_dev_and_queue!();
+        let cache = ShaderModuleCache::new(device);
+
+        let first = unsafe { cache.load(&spirv(b"main")).unwrap() };
+        let second = unsafe { cache.load(&spirv(b"main")).unwrap() };
+        assert!(Arc::ptr_eq(first.module(), second.module()));
+        assert!(Arc::ptr_eq(first.reflection(), second.reflection()));
+
+        let other = unsafe { cache.load(&spirv(b"comp")).unwrap() };
+        assert!(!Arc::ptr_eq(first.module(), other.module()));
+        assert!(other.reflection().entry_point("comp").is_some());
+        assert_eq!(cache.len(), 2);
+
+        drop((first, second));
+        assert_eq!(cache.len(), 1);
+
+        // Loading new code purges the entry of the destroyed module.
+        let _third = unsafe { cache.load(&spirv(b"abcd")).unwrap() };
+        assert_eq!(cache.entries.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn load_unchecked() {
+        let (device, _) = gfx_dev_and_queue!();
+        let cache = ShaderModuleCache::new(device);
+
+        let entry_point = EntryPointReflection {
+            name: "main".to_owned(),
+            stage: ShaderStage::Compute,
+            execution_modes: vec![ExecutionMode::LocalSize([64, 1, 1])],
+            descriptor_bindings: Vec::new(),
+            push_constants: None,
+            input_interface: Vec::new(),
+            output_interface: Vec::new(),
+        };
+        let reflection = ShaderReflection::from_entry_points(vec![entry_point], Vec::new(), None);
+
+        let first = unsafe {
+            cache
+                .load_unchecked(&spirv(b"main"), reflection.clone())
+                .unwrap()
+        };
+        let main = first.reflection().entry_point("main").unwrap();
+        assert_eq!(main.local_size(), Some([64, 1, 1]));
+
+        let second = unsafe { cache.load(&spirv(b"main")).unwrap() };
+        assert!(Arc::ptr_eq(first.module(), second.module()));
+        assert!(Arc::ptr_eq(first.reflection(), second.reflection()));
+    }
+
+    #[test]
+    fn module_keeps_entry_alive() {
+        let (device, _) = gfx_dev_and_queue!();
+        let cache = ShaderModuleCache::new(device);
+
+        // Only the module is kept, not the reflection data that was returned with it.
+        let module = unsafe { cache.load(&spirv(b"main")).unwrap() }
+            .module()
+            .clone();
+        assert_eq!(cache.len(), 1);
+
+        let second = unsafe { cache.load(&spirv(b"main")).unwrap() };
+        assert!(Arc::ptr_eq(&module, second.module()));
+        assert!(second.reflection().entry_point("main").is_some());
+        assert_eq!(cache.entries.lock().unwrap().values().flatten().count(), 1);
+    }
+}
@@ -0,0 +1,2098 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Introspection of SPIR-V code at runtime.
+//!
+//! This gives access to the information that the `vulkano-shaders` crate extracts at compile
+//! time, for shaders that are only known at runtime. [`ShaderReflection::new`] parses SPIR-V
+//! code, and returns for each entry point:
+//!
+//! - Its stage and execution modes.
+//! - The descriptors it accesses, with their type, array size and whether they are read or
+//!   written.
+//! - Its push constant block and the offsets of its members.
+//! - Its input and output interfaces, such as the vertex inputs of a vertex shader or the color
+//!   outputs of a fragment shader.
+//!
+//! The specialization constants of the module and the constants that specialize the workgroup
+//! size of compute shaders are also available.
+//!
+//! The results use the same types as the rest of the library where possible, so that they can be
+//! passed directly to the pipeline and descriptor set layout APIs.
+//!
+//! # Example
+//!
+//! ```
+//! use vulkano::pipeline::shader::reflect::ShaderReflection;
+//!
+//! # let spirv: Vec<u32> = return;
+//! let reflection = ShaderReflection::new(&spirv).unwrap();
+//!
+//! for entry_point in reflection.entry_points() {
+//!     println!("{} ({:?})", entry_point.name, entry_point.stage);
+//!
+//!     for binding in &entry_point.descriptor_bindings {
+//!         println!(
+//!             "  set {} binding {}: {:?}",
+//!             binding.set,
+//!             binding.binding,
+//!             binding.desc.ty.ty()
+//!         );
+//!     }
+//! }
+//! ```
+
+use crate::descriptor_set::layout::DescriptorBufferDesc;
+use crate::descriptor_set::layout::DescriptorDesc;
+use crate::descriptor_set::layout::DescriptorDescTy;
+use crate::descriptor_set::layout::DescriptorImageDesc;
+use crate::descriptor_set::layout::DescriptorImageDescArray;
+use crate::descriptor_set::layout::DescriptorImageDescDimensions;
+use crate::descriptor_set::layout::DescriptorSetDesc;
+use crate::device::DeviceExtensions;
+use crate::device::Features;
+use crate::format::Format;
+use crate::pipeline::layout::PipelineLayoutPcRange;
+use crate::pipeline::shader::check_specialization_constants;
+use crate::pipeline::shader::GeometryShaderExecutionMode;
+use crate::pipeline::shader::ShaderInterfaceEntry;
+use crate::pipeline::shader::ShaderStage;
+use crate::pipeline::shader::SpecializationConstantsError;
+use crate::pipeline::shader::SpecializationMapEntry;
+use crate::Version;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::error;
+use std::fmt;
+
+const MAGIC_NUMBER: u32 = 0x07230203;
+
+mod op {
+    pub const NAME: u16 = 5;
+    pub const MEMBER_NAME: u16 = 6;
+    pub const EXT_INST: u16 = 12;
+    pub const ENTRY_POINT: u16 = 15;
+    pub const EXECUTION_MODE: u16 = 16;
+    pub const CAPABILITY: u16 = 17;
+    pub const TYPE_BOOL: u16 = 20;
+    pub const TYPE_INT: u16 = 21;
+    pub const TYPE_FLOAT: u16 = 22;
+    pub const TYPE_VECTOR: u16 = 23;
+    pub const TYPE_MATRIX: u16 = 24;
+    pub const TYPE_IMAGE: u16 = 25;
+    pub const TYPE_SAMPLER: u16 = 26;
+    pub const TYPE_SAMPLED_IMAGE: u16 = 27;
+    pub const TYPE_ARRAY: u16 = 28;
+    pub const TYPE_RUNTIME_ARRAY: u16 = 29;
+    pub const TYPE_STRUCT: u16 = 30;
+    pub const TYPE_POINTER: u16 = 32;
+    pub const CONSTANT: u16 = 43;
+    pub const CONSTANT_COMPOSITE: u16 = 44;
+    pub const SPEC_CONSTANT_TRUE: u16 = 48;
+    pub const SPEC_CONSTANT_FALSE: u16 = 49;
+    pub const SPEC_CONSTANT: u16 = 50;
+    pub const SPEC_CONSTANT_COMPOSITE: u16 = 51;
+    pub const FUNCTION: u16 = 54;
+    pub const FUNCTION_END: u16 = 56;
+    pub const FUNCTION_CALL: u16 = 57;
+    pub const VARIABLE: u16 = 59;
+    pub const IMAGE_TEXEL_POINTER: u16 = 60;
+    pub const LOAD: u16 = 61;
+    pub const STORE: u16 = 62;
+    pub const COPY_MEMORY: u16 = 63;
+    pub const COPY_MEMORY_SIZED: u16 = 64;
+    pub const ACCESS_CHAIN: u16 = 65;
+    pub const IN_BOUNDS_ACCESS_CHAIN: u16 = 66;
+    pub const PTR_ACCESS_CHAIN: u16 = 67;
+    pub const ARRAY_LENGTH: u16 = 68;
+    pub const IN_BOUNDS_PTR_ACCESS_CHAIN: u16 = 70;
+    pub const DECORATE: u16 = 71;
+    pub const MEMBER_DECORATE: u16 = 72;
+    pub const COPY_OBJECT: u16 = 83;
+    pub const ATOMIC_LOAD: u16 = 227;
+    pub const ATOMIC_STORE: u16 = 228;
+    pub const ATOMIC_XOR: u16 = 242;
+    pub const ATOMIC_FLAG_TEST_AND_SET: u16 = 318;
+    pub const ATOMIC_FLAG_CLEAR: u16 = 319;
+    pub const EXECUTION_MODE_ID: u16 = 331;
+}
+
+mod decoration {
+    pub const SPEC_ID: u32 = 1;
+    pub const BLOCK: u32 = 2;
+    pub const BUFFER_BLOCK: u32 = 3;
+    pub const ROW_MAJOR: u32 = 4;
+    pub const ARRAY_STRIDE: u32 = 6;
+    pub const MATRIX_STRIDE: u32 = 7;
+    pub const BUILT_IN: u32 = 11;
+    pub const NON_WRITABLE: u32 = 24;
+    pub const NON_READABLE: u32 = 25;
+    pub const LOCATION: u32 = 30;
+    pub const BINDING: u32 = 33;
+    pub const DESCRIPTOR_SET: u32 = 34;
+    pub const OFFSET: u32 = 35;
+}
+
+const BUILT_IN_WORKGROUP_SIZE: u32 = 25;
+
+mod capability {
+    pub const FLOAT16: u32 = 9;
+    pub const FLOAT64: u32 = 10;
+    pub const INT64: u32 = 11;
+    pub const INT16: u32 = 22;
+    pub const INT8: u32 = 39;
+    pub const STORAGE_BUFFER_16_BIT_ACCESS: u32 = 4433;
+    pub const UNIFORM_AND_STORAGE_BUFFER_16_BIT_ACCESS: u32 = 4434;
+    pub const STORAGE_PUSH_CONSTANT_16: u32 = 4435;
+    pub const STORAGE_INPUT_OUTPUT_16: u32 = 4436;
+    pub const STORAGE_BUFFER_8_BIT_ACCESS: u32 = 4448;
+    pub const UNIFORM_AND_STORAGE_BUFFER_8_BIT_ACCESS: u32 = 4449;
+    pub const STORAGE_PUSH_CONSTANT_8: u32 = 4450;
+}
+
+mod storage_class {
+    pub const UNIFORM_CONSTANT: u32 = 0;
+    pub const INPUT: u32 = 1;
+    pub const UNIFORM: u32 = 2;
+    pub const OUTPUT: u32 = 3;
+    pub const PUSH_CONSTANT: u32 = 9;
+    pub const STORAGE_BUFFER: u32 = 12;
+}
+
+/// Information about the content of SPIR-V code.
+#[derive(Clone, Debug)]
+pub struct ShaderReflection {
+    capabilities: Vec<u32>,
+    entry_points: Vec<EntryPointReflection>,
+    specialization_constants: Vec<SpecializationConstantReflection>,
+    workgroup_size: Option<WorkgroupSizeReflection>,
+}
+
+impl ShaderReflection {
+    /// Parses `spirv` and extracts its information.
+    pub fn new(spirv: &[u32]) -> Result<ShaderReflection, SpirvParseError> {
+        let module = Module::parse(spirv)?;
+        let entry_points = module
+            .entry_points
+            .iter()
+            .map(|entry_point| module.reflect_entry_point(entry_point))
+            .collect::<Result<_, _>>()?;
+
+        let specialization_constants = module.reflect_specialization_constants()?;
+        let workgroup_size = module.reflect_workgroup_size()?;
+
+        Ok(ShaderReflection {
+            capabilities: module.capabilities,
+            entry_points,
+            specialization_constants,
+            workgroup_size,
+        })
+    }
+
+    /// Builds the information from a description supplied by the caller, without parsing any
+    /// SPIR-V code.
+    ///
+    /// This is for code that `new` can't parse, such as code using types or capabilities that
+    /// vulkano doesn't know about yet. Nothing checks that the description matches the code of
+    /// the shader module that it is used with. The description doesn't declare any capability, so
+    /// `required_features` and `required_extensions` return nothing.
+    #[inline]
+    pub fn from_entry_points(
+        entry_points: Vec<EntryPointReflection>,
+        specialization_constants: Vec<SpecializationConstantReflection>,
+        workgroup_size: Option<WorkgroupSizeReflection>,
+    ) -> ShaderReflection {
+        ShaderReflection {
+            capabilities: Vec::new(),
+            entry_points,
+            specialization_constants,
+            workgroup_size,
+        }
+    }
+
+    /// Returns the device features that are required by the capabilities declared by the module.
+    ///
+    /// Only the capabilities for 8-, 16- and 64-bit types in shader code and in storage are taken
+    /// into account.
+    pub fn required_features(&self) -> Features {
+        let has = |capability| self.capabilities.contains(&capability);
+
+        Features {
+            shader_float16: has(capability::FLOAT16),
+            shader_float64: has(capability::FLOAT64),
+            shader_int8: has(capability::INT8),
+            shader_int16: has(capability::INT16),
+            shader_int64: has(capability::INT64),
+            storage_buffer16_bit_access: has(capability::STORAGE_BUFFER_16_BIT_ACCESS),
+            uniform_and_storage_buffer16_bit_access: has(
+                capability::UNIFORM_AND_STORAGE_BUFFER_16_BIT_ACCESS,
+            ),
+            storage_push_constant16: has(capability::STORAGE_PUSH_CONSTANT_16),
+            storage_input_output16: has(capability::STORAGE_INPUT_OUTPUT_16),
+            storage_buffer8_bit_access: has(capability::STORAGE_BUFFER_8_BIT_ACCESS),
+            uniform_and_storage_buffer8_bit_access: has(
+                capability::UNIFORM_AND_STORAGE_BUFFER_8_BIT_ACCESS,
+            ),
+            storage_push_constant8: has(capability::STORAGE_PUSH_CONSTANT_8),
+            ..Features::none()
+        }
+    }
+
+    /// Returns the device extensions that must be enabled for the features returned by
+    /// `required_features`, on a device of the given API version.
+    pub fn required_extensions(&self, api_version: Version) -> DeviceExtensions {
+        let features = self.required_features();
+        let storage_16_bit = features.storage_buffer16_bit_access
+            || features.uniform_and_storage_buffer16_bit_access
+            || features.storage_push_constant16
+            || features.storage_input_output16;
+        let storage_8_bit = features.storage_buffer8_bit_access
+            || features.uniform_and_storage_buffer8_bit_access
+            || features.storage_push_constant8;
+
+        DeviceExtensions {
+            khr_16bit_storage: storage_16_bit && api_version < Version::V1_1,
+            khr_8bit_storage: storage_8_bit && api_version < Version::V1_2,
+            khr_storage_buffer_storage_class: (storage_16_bit || storage_8_bit)
+                && api_version < Version::V1_1,
+            khr_shader_float16_int8: (features.shader_float16 || features.shader_int8)
+                && api_version < Version::V1_2,
+            ..DeviceExtensions::none()
+        }
+    }
+
+    /// Returns the entry points of the module.
+    ///
+    /// Entry points whose execution model can't be used by vulkano, such as OpenCL kernels, are
+    /// not included.
+    #[inline]
+    pub fn entry_points(&self) -> &[EntryPointReflection] {
+        &self.entry_points
+    }
+
+    /// Returns the entry point with the given name, if any.
+    ///
+    /// If several entry points have the same name for different stages, the first one is
+    /// returned.
+    #[inline]
+    pub fn entry_point(&self, name: &str) -> Option<&EntryPointReflection> {
+        self.entry_points.iter().find(|entry| entry.name == name)
+    }
+
+    /// Returns the specialization constants of the module, ordered by constant id.
+    #[inline]
+    pub fn specialization_constants(&self) -> &[SpecializationConstantReflection] {
+        &self.specialization_constants
+    }
+
+    /// Returns the specialization constant with the given constant id, if any.
+    #[inline]
+    pub fn specialization_constant(
+        &self,
+        constant_id: u32,
+    ) -> Option<&SpecializationConstantReflection> {
+        self.specialization_constants
+            .iter()
+            .find(|constant| constant.constant_id == constant_id)
+    }
+
+    /// Returns the layout of specialization data that contains all the specialization constants
+    /// of the module, ordered by constant id and aligned to their size.
+    ///
+    /// This is the layout expected by entry points built from the reflection data.
+    pub fn specialization_map_entries(&self) -> Vec<SpecializationMapEntry> {
+        let mut offset = 0;
+
+        self.specialization_constants
+            .iter()
+            .map(|constant| {
+                let size = constant.size();
+                if offset % size != 0 {
+                    offset += size - offset % size;
+                }
+                let entry = SpecializationMapEntry {
+                    constant_id: constant.constant_id,
+                    offset: offset as u32,
+                    size,
+                };
+                offset += size;
+                entry
+            })
+            .collect()
+    }
+
+    /// Checks that specialization data described by `provided` and of `data_size` bytes matches
+    /// the specialization constants of the module.
+    ///
+    /// See [`check_specialization_constants`].
+    #[inline]
+    pub fn check_specialization_constants(
+        &self,
+        provided: &[SpecializationMapEntry],
+        data_size: usize,
+    ) -> Result<(), SpecializationConstantsError> {
+        check_specialization_constants(&self.specialization_map_entries(), provided, data_size)
+    }
+
+    /// Returns the workgroup size of the compute shaders of the module, if it is declared with
+    /// the `WorkgroupSize` built-in.
+    ///
+    /// In GLSL, this is the case when `local_size_x_id` and similar are used. The workgroup size
+    /// takes precedence over the `LocalSize` execution mode of the entry points.
+    #[inline]
+    pub fn workgroup_size(&self) -> Option<&WorkgroupSizeReflection> {
+        self.workgroup_size.as_ref()
+    }
+}
+
+/// Information about a specialization constant.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpecializationConstantReflection {
+    /// Constant id of the specialization constant, given by its `SpecId` decoration.
+    pub constant_id: u32,
+    /// Name of the constant, if the code contains debug names.
+    pub name: Option<String>,
+    /// Type of the constant.
+    pub ty: SpecializationConstantType,
+    /// Value of the constant if it isn't specialized.
+    pub default_value: SpecializationConstantValue,
+}
+
+impl SpecializationConstantReflection {
+    /// Returns the size in bytes of the constant in specialization data. Booleans take 4 bytes.
+    #[inline]
+    pub fn size(&self) -> usize {
+        match self.ty {
+            SpecializationConstantType::Bool => 4,
+            SpecializationConstantType::Int { width, .. }
+            | SpecializationConstantType::Float { width } => width as usize / 8,
+        }
+    }
+}
+
+/// Type of a specialization constant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpecializationConstantType {
+    Bool,
+    Int { width: u32, signed: bool },
+    Float { width: u32 },
+}
+
+/// Value of a specialization constant.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpecializationConstantValue {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+}
+
+/// Workgroup size of compute shaders, declared with the `WorkgroupSize` built-in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorkgroupSizeReflection {
+    /// Size of the workgroup in each dimension if it isn't specialized.
+    pub default_value: [u32; 3],
+    /// For each dimension, the constant id of the specialization constant that gives its size,
+    /// or `None` if the size can't be specialized.
+    pub constant_ids: [Option<u32>; 3],
+}
+
+impl WorkgroupSizeReflection {
+    /// Returns true if at least one dimension can be specialized.
+    #[inline]
+    pub fn is_specializable(&self) -> bool {
+        self.constant_ids.iter().any(Option::is_some)
+    }
+}
+
+/// Information about an entry point of SPIR-V code.
+#[derive(Clone, Debug)]
+pub struct EntryPointReflection {
+    /// Name of the entry point.
+    pub name: String,
+    /// Stage that the entry point is for.
+    pub stage: ShaderStage,
+    /// Execution modes declared for the entry point.
+    pub execution_modes: Vec<ExecutionMode>,
+    /// Descriptors accessed by the entry point, ordered by set and binding.
+    pub descriptor_bindings: Vec<DescriptorBindingReflection>,
+    /// Push constant block accessed by the entry point, if any.
+    pub push_constants: Option<PushConstantBlockReflection>,
+    /// Input variables of the entry point, ordered by location. Built-in variables are not
+    /// included.
+    ///
+    /// For tessellation and geometry shaders, the per-vertex array of the inputs is not counted
+    /// in the locations, the same as in `vulkano-shaders`.
+    pub input_interface: Vec<ShaderInterfaceEntry>,
+    /// Output variables of the entry point, ordered by location. Built-in variables are not
+    /// included.
+    pub output_interface: Vec<ShaderInterfaceEntry>,
+}
+
+impl EntryPointReflection {
+    /// Returns the layouts of the descriptor sets accessed by the entry point, indexed by set
+    /// number.
+    ///
+    /// The descriptors that are declared as runtime arrays have an `array_count` of 0, which must
+    /// be replaced before creating a layout.
+    pub fn descriptor_set_descs(&self) -> Vec<DescriptorSetDesc> {
+        let num_sets = self
+            .descriptor_bindings
+            .iter()
+            .map(|binding| binding.set + 1)
+            .max()
+            .unwrap_or(0);
+
+        (0..num_sets)
+            .map(|set| {
+                let bindings = self
+                    .descriptor_bindings
+                    .iter()
+                    .filter(|binding| binding.set == set)
+                    .collect::<Vec<_>>();
+                let num_bindings = bindings
+                    .iter()
+                    .map(|binding| binding.binding + 1)
+                    .max()
+                    .unwrap_or(0);
+
+                DescriptorSetDesc::new((0..num_bindings).map(|num| {
+                    bindings
+                        .iter()
+                        .find(|binding| binding.binding == num)
+                        .map(|binding| binding.desc.clone())
+                }))
+            })
+            .collect()
+    }
+
+    /// Returns the type of input primitives of a geometry shader, or `None` if the entry point
+    /// isn't a geometry shader or doesn't declare it.
+    pub fn geometry_execution_mode(&self) -> Option<GeometryShaderExecutionMode> {
+        if self.stage != ShaderStage::Geometry {
+            return None;
+        }
+
+        self.execution_modes.iter().find_map(|mode| match *mode {
+            ExecutionMode::InputPoints => Some(GeometryShaderExecutionMode::Points),
+            ExecutionMode::InputLines => Some(GeometryShaderExecutionMode::Lines),
+            ExecutionMode::InputLinesAdjacency => {
+                Some(GeometryShaderExecutionMode::LinesWithAdjacency)
+            }
+            ExecutionMode::Triangles => Some(GeometryShaderExecutionMode::Triangles),
+            ExecutionMode::InputTrianglesAdjacency => {
+                Some(GeometryShaderExecutionMode::TrianglesWithAdjacency)
+            }
+            _ => None,
+        })
+    }
+
+    /// Returns the local workgroup size of a compute shader, if it is declared with an execution
+    /// mode.
+    pub fn local_size(&self) -> Option<[u32; 3]> {
+        self.execution_modes.iter().find_map(|mode| match *mode {
+            ExecutionMode::LocalSize(size) => Some(size),
+            _ => None,
+        })
+    }
+}
+
+/// An execution mode of an entry point.
+///
+/// `LocalSizeId` and `LocalSizeHintId` are reported as `LocalSize` and `LocalSizeHint`, with the
+/// default values of the constants.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExecutionMode {
+    Invocations(u32),
+    SpacingEqual,
+    SpacingFractionalEven,
+    SpacingFractionalOdd,
+    VertexOrderCw,
+    VertexOrderCcw,
+    PixelCenterInteger,
+    OriginUpperLeft,
+    OriginLowerLeft,
+    EarlyFragmentTests,
+    PointMode,
+    Xfb,
+    DepthReplacing,
+    DepthGreater,
+    DepthLess,
+    DepthUnchanged,
+    LocalSize([u32; 3]),
+    LocalSizeHint([u32; 3]),
+    InputPoints,
+    InputLines,
+    InputLinesAdjacency,
+    Triangles,
+    InputTrianglesAdjacency,
+    Quads,
+    Isolines,
+    OutputVertices(u32),
+    OutputPoints,
+    OutputLineStrip,
+    OutputTriangleStrip,
+    /// Another execution mode, with its number and operands as found in the SPIR-V code.
+    Other(u32, Vec<u32>),
+}
+
+impl ExecutionMode {
+    fn from_operands(mode: u32, operands: &[u32]) -> ExecutionMode {
+        let size = || [operands[0], operands[1], operands[2]];
+
+        match (mode, operands.len()) {
+            (0, 1) => ExecutionMode::Invocations(operands[0]),
+            (1, _) => ExecutionMode::SpacingEqual,
+            (2, _) => ExecutionMode::SpacingFractionalEven,
+            (3, _) => ExecutionMode::SpacingFractionalOdd,
+            (4, _) => ExecutionMode::VertexOrderCw,
+            (5, _) => ExecutionMode::VertexOrderCcw,
+            (6, _) => ExecutionMode::PixelCenterInteger,
+            (7, _) => ExecutionMode::OriginUpperLeft,
+            (8, _) => ExecutionMode::OriginLowerLeft,
+            (9, _) => ExecutionMode::EarlyFragmentTests,
+            (10, _) => ExecutionMode::PointMode,
+            (11, _) => ExecutionMode::Xfb,
+            (12, _) => ExecutionMode::DepthReplacing,
+            (14, _) => ExecutionMode::DepthGreater,
+            (15, _) => ExecutionMode::DepthLess,
+            (16, _) => ExecutionMode::DepthUnchanged,
+            (17, 3) => ExecutionMode::LocalSize(size()),
+            (18, 3) => ExecutionMode::LocalSizeHint(size()),
+            (19, _) => ExecutionMode::InputPoints,
+            (20, _) => ExecutionMode::InputLines,
+            (21, _) => ExecutionMode::InputLinesAdjacency,
+            (22, _) => ExecutionMode::Triangles,
+            (23, _) => ExecutionMode::InputTrianglesAdjacency,
+            (24, _) => ExecutionMode::Quads,
+            (25, _) => ExecutionMode::Isolines,
+            (26, 1) => ExecutionMode::OutputVertices(operands[0]),
+            (27, _) => ExecutionMode::OutputPoints,
+            (28, _) => ExecutionMode::OutputLineStrip,
+            (29, _) => ExecutionMode::OutputTriangleStrip,
+            _ => ExecutionMode::Other(mode, operands.to_vec()),
+        }
+    }
+}
+
+/// Information about a descriptor accessed by an entry point.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DescriptorBindingReflection {
+    /// Descriptor set number of the descriptor.
+    pub set: u32,
+    /// Binding number of the descriptor within its set.
+    pub binding: u32,
+    /// Name of the variable, if the code contains debug names.
+    pub name: Option<String>,
+    /// Description of the descriptor.
+    ///
+    /// `desc.stages` contains the stage of the entry point, and `desc.readonly` is true if the
+    /// shader never writes to the descriptor. `desc.array_count` is 0 if the descriptor is a
+    /// runtime array, whose size is chosen when creating the layout.
+    pub desc: DescriptorDesc,
+    /// True if the shader never reads from the descriptor.
+    pub writeonly: bool,
+    /// For uniform and storage buffers, members of the block, in the order of their declaration.
+    /// Empty for other descriptors.
+    pub members: Vec<BlockMemberReflection>,
+}
+
+/// Information about the push constant block accessed by an entry point.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PushConstantBlockReflection {
+    /// Name of the variable, if the code contains debug names.
+    pub name: Option<String>,
+    /// Range covered by the members of the block, with the stage of the entry point.
+    pub range: PipelineLayoutPcRange,
+    /// Members of the block, in the order of their declaration.
+    pub members: Vec<BlockMemberReflection>,
+}
+
+/// Member of a push constant block or of a buffer block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockMemberReflection {
+    /// Name of the member, if the code contains debug names.
+    pub name: Option<String>,
+    /// Offset in bytes of the member from the start of the block.
+    pub offset: u32,
+    /// Size in bytes of the member, or 0 if it is a runtime array.
+    pub size: u32,
+    /// Type of the member if it is a scalar, or of its components or elements if it is a vector,
+    /// a matrix or an array of them. `None` for booleans and structs.
+    pub scalar_type: Option<ScalarType>,
+}
+
+/// Numerical scalar type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ScalarType {
+    /// Integer of `width` bits.
+    Int { width: u32, signed: bool },
+    /// Floating-point number of `width` bits.
+    Float { width: u32 },
+}
+
+impl ScalarType {
+    /// Returns the size in bytes of the type.
+    #[inline]
+    pub fn size(&self) -> u32 {
+        match *self {
+            ScalarType::Int { width, .. } | ScalarType::Float { width } => width / 8,
+        }
+    }
+}
+
+/// Error that can happen when parsing SPIR-V code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpirvParseError {
+    /// The code is too short to contain the SPIR-V header.
+    MissingHeader,
+    /// The code doesn't start with the SPIR-V magic number.
+    WrongHeader,
+    /// An instruction is longer than the rest of the code, or misses operands.
+    IncompleteInstruction,
+    /// A string literal isn't null-terminated or isn't valid UTF-8.
+    InvalidString,
+    /// An instruction refers to an id that isn't defined, or that isn't of the expected kind.
+    UnknownId(u32),
+    /// A descriptor has a type that isn't supported.
+    UnsupportedDescriptorType { set: u32, binding: u32 },
+    /// A variable of an interface or a member of a block has a type that isn't supported.
+    UnsupportedType(u32),
+}
+
+impl error::Error for SpirvParseError {}
+
+impl fmt::Display for SpirvParseError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                SpirvParseError::MissingHeader => {
+                    "the code is too short to contain the SPIR-V header"
+                }
+                SpirvParseError::WrongHeader => {
+                    "the code doesn't start with the SPIR-V magic number"
+                }
+                SpirvParseError::IncompleteInstruction => {
+                    "an instruction is longer than the rest of the code, or misses operands"
+                }
+                SpirvParseError::InvalidString => {
+                    "a string literal isn't null-terminated or isn't valid UTF-8"
+                }
+                SpirvParseError::UnknownId(_) => {
+                    "an instruction refers to an id that isn't defined"
+                }
+                SpirvParseError::UnsupportedDescriptorType { .. } => {
+                    "a descriptor has a type that isn't supported"
+                }
+                SpirvParseError::UnsupportedType(_) => {
+                    "a variable of an interface or a member of a block has a type that isn't \
+                     supported"
+                }
+            }
+        )
+    }
+}
+
+struct RawEntryPoint {
+    stage: ShaderStage,
+    function: u32,
+    name: String,
+    interface: Vec<u32>,
+}
+
+enum Type {
+    Bool,
+    Int {
+        width: u32,
+        signed: bool,
+    },
+    Float {
+        width: u32,
+    },
+    Vector {
+        component: u32,
+        count: u32,
+    },
+    Matrix {
+        column: u32,
+        count: u32,
+    },
+    Image {
+        dim: u32,
+        arrayed: bool,
+        multisampled: bool,
+        sampled: u32,
+        format: u32,
+    },
+    Sampler,
+    SampledImage {
+        image: u32,
+    },
+    Array {
+        element: u32,
+        length: u32,
+    },
+    RuntimeArray {
+        element: u32,
+    },
+    Struct {
+        members: Vec<u32>,
+    },
+    Pointer {
+        pointee: u32,
+    },
+}
+
+// A decoration and its parameters.
+type Decoration = (u32, Vec<u32>);
+
+// The instructions of a module that are needed for reflection, indexed by id.
+#[derive(Default)]
+struct Module {
+    entry_points: Vec<RawEntryPoint>,
+    execution_modes: HashMap<u32, Vec<ExecutionMode>>,
+    capabilities: Vec<u32>,
+    names: HashMap<u32, String>,
+    member_names: HashMap<(u32, u32), String>,
+    decorations: HashMap<u32, Vec<Decoration>>,
+    member_decorations: HashMap<(u32, u32), Vec<Decoration>>,
+    types: HashMap<u32, Type>,
+    constants: HashMap<u32, u64>,
+    // Specialization constants, with their type and their default value.
+    spec_constants: Vec<(u32, u32, u64)>,
+    composites: HashMap<u32, Vec<u32>>,
+    // Global variables, with their pointer type and storage class.
+    variables: HashMap<u32, (u32, u32)>,
+    // Ids that are used as pointers by each function, and the functions that it calls.
+    function_uses: HashMap<u32, (HashSet<u32>, HashSet<u32>)>,
+}
+
+impl Module {
+    fn parse(spirv: &[u32]) -> Result<Module, SpirvParseError> {
+        let instructions = parse_instructions(spirv)?;
+        let mut module = Module::default();
+        let mut current_function = None;
+        let mut execution_mode_ids = Vec::new();
+
+        for instruction in &instructions {
+            let operands = instruction.operands;
+            let min_operands = match instruction.opcode {
+                op::CAPABILITY | op::TYPE_BOOL | op::TYPE_SAMPLER | op::TYPE_STRUCT => 1,
+                op::ATOMIC_FLAG_CLEAR => 1,
+                op::NAME | op::EXECUTION_MODE | op::EXECUTION_MODE_ID | op::DECORATE => 2,
+                op::TYPE_FLOAT | op::TYPE_SAMPLED_IMAGE | op::TYPE_RUNTIME_ARRAY => 2,
+                op::FUNCTION | op::STORE | op::COPY_MEMORY | op::COPY_MEMORY_SIZED => 2,
+                op::MEMBER_NAME | op::ENTRY_POINT | op::MEMBER_DECORATE => 3,
+                op::TYPE_INT | op::TYPE_VECTOR | op::TYPE_MATRIX | op::TYPE_ARRAY => 3,
+                op::TYPE_POINTER | op::CONSTANT | op::SPEC_CONSTANT | op::VARIABLE => 3,
+                op::SPEC_CONSTANT_TRUE | op::SPEC_CONSTANT_FALSE => 2,
+                op::CONSTANT_COMPOSITE | op::SPEC_CONSTANT_COMPOSITE => 2,
+                op::FUNCTION_CALL
+                | op::IMAGE_TEXEL_POINTER
+                | op::LOAD
+                | op::ACCESS_CHAIN
+                | op::IN_BOUNDS_ACCESS_CHAIN
+                | op::PTR_ACCESS_CHAIN
+                | op::IN_BOUNDS_PTR_ACCESS_CHAIN
+                | op::ARRAY_LENGTH
+                | op::COPY_OBJECT
+                | op::ATOMIC_LOAD..=op::ATOMIC_XOR
+                | op::ATOMIC_FLAG_TEST_AND_SET => 3,
+                op::TYPE_IMAGE => 8,
+                _ => 0,
+            };
+            if operands.len() < min_operands {
+                return Err(SpirvParseError::IncompleteInstruction);
+            }
+
+            match instruction.opcode {
+                op::CAPABILITY => {
+                    module.capabilities.push(operands[0]);
+                }
+                op::NAME => {
+                    let (name, _) = parse_string(&operands[1..])?;
+                    module.names.insert(operands[0], name);
+                }
+                op::MEMBER_NAME => {
+                    let (name, _) = parse_string(&operands[2..])?;
+                    module.member_names.insert((operands[0], operands[1]), name);
+                }
+                op::ENTRY_POINT => {
+                    // Entry points of other execution models can't be used by vulkano.
+                    let stage = match execution_model_stage(operands[0]) {
+                        Some(stage) => stage,
+                        None => continue,
+                    };
+                    let (name, len) = parse_string(&operands[2..])?;
+
+                    module.entry_points.push(RawEntryPoint {
+                        stage,
+                        function: operands[1],
+                        name,
+                        interface: operands[2 + len..].to_vec(),
+                    });
+                }
+                op::EXECUTION_MODE => {
+                    module
+                        .execution_modes
+                        .entry(operands[0])
+                        .or_default()
+                        .push(ExecutionMode::from_operands(operands[1], &operands[2..]));
+                }
+                op::EXECUTION_MODE_ID => {
+                    // The constants are defined after the execution modes.
+                    execution_mode_ids.push((operands[0], operands[1], operands[2..].to_vec()));
+                }
+                op::DECORATE => {
+                    check_decoration_params(operands[1], &operands[2..])?;
+                    module
+                        .decorations
+                        .entry(operands[0])
+                        .or_default()
+                        .push((operands[1], operands[2..].to_vec()));
+                }
+                op::MEMBER_DECORATE => {
+                    check_decoration_params(operands[2], &operands[3..])?;
+                    module
+                        .member_decorations
+                        .entry((operands[0], operands[1]))
+                        .or_default()
+                        .push((operands[2], operands[3..].to_vec()));
+                }
+                op::TYPE_BOOL => {
+                    module.types.insert(operands[0], Type::Bool);
+                }
+                op::TYPE_INT => {
+                    let ty = Type::Int {
+                        width: operands[1],
+                        signed: operands[2] != 0,
+                    };
+                    module.types.insert(operands[0], ty);
+                }
+                op::TYPE_FLOAT => {
+                    let ty = Type::Float { width: operands[1] };
+                    module.types.insert(operands[0], ty);
+                }
+                op::TYPE_VECTOR => {
+                    let ty = Type::Vector {
+                        component: operands[1],
+                        count: operands[2],
+                    };
+                    module.types.insert(operands[0], ty);
+                }
+                op::TYPE_MATRIX => {
+                    let ty = Type::Matrix {
+                        column: operands[1],
+                        count: operands[2],
+                    };
+                    module.types.insert(operands[0], ty);
+                }
+                op::TYPE_IMAGE => {
+                    let ty = Type::Image {
+                        dim: operands[2],
+                        arrayed: operands[4] != 0,
+                        multisampled: operands[5] != 0,
+                        sampled: operands[6],
+                        format: operands[7],
+                    };
+                    module.types.insert(operands[0], ty);
+                }
+                op::TYPE_SAMPLER => {
+                    module.types.insert(operands[0], Type::Sampler);
+                }
+                op::TYPE_SAMPLED_IMAGE => {
+                    let ty = Type::SampledImage { image: operands[1] };
+                    module.types.insert(operands[0], ty);
+                }
+                op::TYPE_ARRAY => {
+                    let ty = Type::Array {
+                        element: operands[1],
+                        length: operands[2],
+                    };
+                    module.types.insert(operands[0], ty);
+                }
+                op::TYPE_RUNTIME_ARRAY => {
+                    let ty = Type::RuntimeArray {
+                        element: operands[1],
+                    };
+                    module.types.insert(operands[0], ty);
+                }
+                op::TYPE_STRUCT => {
+                    let ty = Type::Struct {
+                        members: operands[1..].to_vec(),
+                    };
+                    module.types.insert(operands[0], ty);
+                }
+                op::TYPE_POINTER => {
+                    let ty = Type::Pointer {
+                        pointee: operands[2],
+                    };
+                    module.types.insert(operands[0], ty);
+                }
+                op::CONSTANT | op::SPEC_CONSTANT => {
+                    let value = operands[2..]
+                        .iter()
+                        .rev()
+                        .fold(0, |value, &word| (value << 32) | word as u64);
+                    module.constants.insert(operands[1], value);
+
+                    if instruction.opcode == op::SPEC_CONSTANT {
+                        module
+                            .spec_constants
+                            .push((operands[1], operands[0], value));
+                    }
+                }
+                op::SPEC_CONSTANT_TRUE | op::SPEC_CONSTANT_FALSE => {
+                    let value = (instruction.opcode == op::SPEC_CONSTANT_TRUE) as u64;
+                    module.constants.insert(operands[1], value);
+                    module
+                        .spec_constants
+                        .push((operands[1], operands[0], value));
+                }
+                op::CONSTANT_COMPOSITE | op::SPEC_CONSTANT_COMPOSITE => {
+                    module
+                        .composites
+                        .insert(operands[1], operands[2..].to_vec());
+                }
+                op::VARIABLE if current_function.is_none() => {
+                    module
+                        .variables
+                        .insert(operands[1], (operands[0], operands[2]));
+                }
+                op::FUNCTION => {
+                    current_function = Some(operands[1]);
+                    module.function_uses.entry(operands[1]).or_default();
+                }
+                op::FUNCTION_END => {
+                    current_function = None;
+                }
+                opcode => {
+                    let function = match current_function {
+                        Some(function) => function,
+                        None => continue,
+                    };
+                    let (pointers, calls) = module.function_uses.get_mut(&function).unwrap();
+
+                    // Only the instructions that take pointers can access a variable.
+                    match opcode {
+                        op::FUNCTION_CALL => {
+                            calls.insert(operands[2]);
+                            pointers.extend(&operands[3..]);
+                        }
+                        op::STORE | op::COPY_MEMORY | op::COPY_MEMORY_SIZED => {
+                            pointers.insert(operands[0]);
+                            pointers.insert(operands[1]);
+                        }
+                        op::ATOMIC_STORE | op::ATOMIC_FLAG_CLEAR => {
+                            pointers.insert(operands[0]);
+                        }
+                        op::IMAGE_TEXEL_POINTER
+                        | op::LOAD
+                        | op::ACCESS_CHAIN
+                        | op::IN_BOUNDS_ACCESS_CHAIN
+                        | op::PTR_ACCESS_CHAIN
+                        | op::IN_BOUNDS_PTR_ACCESS_CHAIN
+                        | op::ARRAY_LENGTH
+                        | op::COPY_OBJECT
+                        | op::ATOMIC_LOAD..=op::ATOMIC_XOR
+                        | op::ATOMIC_FLAG_TEST_AND_SET => {
+                            pointers.insert(operands[2]);
+                        }
+                        // We don't know which operands of extended instructions are pointers.
+                        op::EXT_INST => {
+                            pointers.extend(operands.iter().skip(4));
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+
+        for (entry_point, mode, ids) in execution_mode_ids {
+            let operands = ids
+                .iter()
+                .map(|&id| module.constant(id).map(|value| value as u32))
+                .collect::<Result<Vec<_>, _>>()?;
+            // LocalSizeId and LocalSizeHintId have the same operands as the modes without ids.
+            let mode = match mode {
+                38 => 17,
+                39 => 18,
+                mode => mode,
+            };
+            module
+                .execution_modes
+                .entry(entry_point)
+                .or_default()
+                .push(ExecutionMode::from_operands(mode, &operands));
+        }
+
+        Ok(module)
+    }
+
+    fn reflect_specialization_constants(
+        &self,
+    ) -> Result<Vec<SpecializationConstantReflection>, SpirvParseError> {
+        let mut constants = Vec::new();
+
+        for &(id, ty, value) in &self.spec_constants {
+            // Constants without a `SpecId` can't be specialized.
+            let constant_id = match self.decoration(id, decoration::SPEC_ID) {
+                Some(params) => params[0],
+                None => continue,
+            };
+
+            let (ty, default_value) = match *self.ty(ty)? {
+                Type::Bool => (
+                    SpecializationConstantType::Bool,
+                    SpecializationConstantValue::Bool(value != 0),
+                ),
+                Type::Int { width, signed } if (1..=64).contains(&width) => {
+                    let default_value = if signed {
+                        // Sign-extends the value from its width.
+                        let shift = 64 - width;
+                        SpecializationConstantValue::Int((value << shift) as i64 >> shift)
+                    } else {
+                        SpecializationConstantValue::UInt(value)
+                    };
+                    (
+                        SpecializationConstantType::Int { width, signed },
+                        default_value,
+                    )
+                }
+                Type::Float { width } => {
+                    let default_value = match width {
+                        16 => half::f16::from_bits(value as u16).to_f64(),
+                        32 => f32::from_bits(value as u32) as f64,
+                        64 => f64::from_bits(value),
+                        _ => return Err(SpirvParseError::UnsupportedType(ty)),
+                    };
+                    (
+                        SpecializationConstantType::Float { width },
+                        SpecializationConstantValue::Float(default_value),
+                    )
+                }
+                _ => return Err(SpirvParseError::UnsupportedType(ty)),
+            };
+
+            constants.push(SpecializationConstantReflection {
+                constant_id,
+                name: self.names.get(&id).cloned(),
+                ty,
+                default_value,
+            });
+        }
+
+        constants.sort_by_key(|constant| constant.constant_id);
+        Ok(constants)
+    }
+
+    fn reflect_workgroup_size(&self) -> Result<Option<WorkgroupSizeReflection>, SpirvParseError> {
+        let id = self.composites.keys().cloned().find(|&id| {
+            matches!(
+                self.decoration(id, decoration::BUILT_IN),
+                Some(params) if params[0] == BUILT_IN_WORKGROUP_SIZE
+            )
+        });
+        let components = match id {
+            Some(id) => &self.composites[&id],
+            None => return Ok(None),
+        };
+        if components.len() != 3 {
+            return Err(SpirvParseError::UnsupportedType(id.unwrap()));
+        }
+
+        let mut workgroup_size = WorkgroupSizeReflection {
+            default_value: [0; 3],
+            constant_ids: [None; 3],
+        };
+        for (index, &component) in components.iter().enumerate() {
+            workgroup_size.default_value[index] = self.constant(component)? as u32;
+            workgroup_size.constant_ids[index] = self
+                .decoration(component, decoration::SPEC_ID)
+                .map(|params| params[0]);
+        }
+
+        Ok(Some(workgroup_size))
+    }
+
+    fn reflect_entry_point(
+        &self,
+        entry_point: &RawEntryPoint,
+    ) -> Result<EntryPointReflection, SpirvParseError> {
+        // For SPIR-V 1.4+, the interface of the entry point contains all the global variables
+        // that it uses, but before that it only contains the inputs and outputs, so the functions
+        // must be inspected too.
+        let mut used_variables = entry_point
+            .interface
+            .iter()
+            .cloned()
+            .collect::<HashSet<_>>();
+        let mut inspected_functions = HashSet::new();
+        let mut functions = vec![entry_point.function];
+        while let Some(function) = functions.pop() {
+            if !inspected_functions.insert(function) {
+                continue;
+            }
+
+            if let Some((pointers, calls)) = self.function_uses.get(&function) {
+                used_variables.extend(pointers);
+                functions.extend(calls);
+            }
+        }
+
+        let mut used_variables = used_variables
+            .into_iter()
+            .filter(|id| self.variables.contains_key(id))
+            .collect::<Vec<_>>();
+        used_variables.sort_unstable();
+
+        let mut descriptor_bindings = Vec::new();
+        let mut push_constants = None;
+        let mut input_interface = Vec::new();
+        let mut output_interface = Vec::new();
+
+        for &variable in &used_variables {
+            let (pointer_ty, storage) = self.variables[&variable];
+            let ty = match self.types.get(&pointer_ty) {
+                Some(&Type::Pointer { pointee }) => pointee,
+                _ => return Err(SpirvParseError::UnknownId(pointer_ty)),
+            };
+
+            match storage {
+                storage_class::UNIFORM_CONSTANT
+                | storage_class::UNIFORM
+                | storage_class::STORAGE_BUFFER => {
+                    let set = self.decoration(variable, decoration::DESCRIPTOR_SET);
+                    let binding = self.decoration(variable, decoration::BINDING);
+                    let (set, binding) = match (set, binding) {
+                        (Some(set), Some(binding)) => (set[0], binding[0]),
+                        _ => continue,
+                    };
+
+                    let (desc_ty, mut readonly, mut writeonly, array_count) = self
+                        .descriptor_infos(ty, storage, false)?
+                        .ok_or(SpirvParseError::UnsupportedDescriptorType { set, binding })?;
+                    readonly |= self.has_decoration(variable, decoration::NON_WRITABLE);
+                    writeonly |= self.has_decoration(variable, decoration::NON_READABLE);
+                    let members = match desc_ty {
+                        DescriptorDescTy::Buffer(_) => self.block_members(self.block_type(ty)?)?,
+                        _ => Vec::new(),
+                    };
+
+                    descriptor_bindings.push(DescriptorBindingReflection {
+                        set,
+                        binding,
+                        name: self.names.get(&variable).cloned(),
+                        desc: DescriptorDesc {
+                            ty: desc_ty,
+                            array_count,
+                            stages: entry_point.stage.into(),
+                            readonly,
+                        },
+                        writeonly,
+                        members,
+                    });
+                }
+                storage_class::PUSH_CONSTANT => {
+                    let members = self.block_members(ty)?;
+
+                    let start = members.iter().map(|m| m.offset).min().unwrap_or(0);
+                    let end = members.iter().map(|m| m.offset + m.size).max().unwrap_or(0);
+
+                    push_constants = Some(PushConstantBlockReflection {
+                        name: self.names.get(&variable).cloned(),
+                        range: PipelineLayoutPcRange {
+                            offset: start as usize,
+                            size: (end - start) as usize,
+                            stages: entry_point.stage.into(),
+                        },
+                        members,
+                    });
+                }
+                storage_class::INPUT | storage_class::OUTPUT => {
+                    if self.is_builtin(ty) || self.has_decoration(variable, decoration::BUILT_IN) {
+                        continue;
+                    }
+
+                    let location = match self.decoration(variable, decoration::LOCATION) {
+                        Some(params) => params[0],
+                        None => continue,
+                    };
+
+                    // These interfaces have one element per vertex.
+                    let per_vertex = matches!(
+                        (entry_point.stage, storage),
+                        (ShaderStage::TessellationControl, _)
+                            | (ShaderStage::TessellationEvaluation, storage_class::INPUT)
+                            | (ShaderStage::Geometry, storage_class::INPUT)
+                    );
+                    let ty = match *self.ty(ty)? {
+                        Type::Array { element, .. } if per_vertex => element,
+                        Type::RuntimeArray { element } if per_vertex => element,
+                        _ => ty,
+                    };
+                    let (format, num_locations) = self
+                        .interface_format(ty)?
+                        .ok_or(SpirvParseError::UnsupportedType(ty))?;
+
+                    let entry = ShaderInterfaceEntry {
+                        location: location..location + num_locations,
+                        format,
+                        name: self.names.get(&variable).cloned().map(Cow::Owned),
+                    };
+
+                    if storage == storage_class::INPUT {
+                        input_interface.push(entry);
+                    } else {
+                        output_interface.push(entry);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        descriptor_bindings.sort_by_key(|binding| (binding.set, binding.binding));
+        input_interface.sort_by_key(|entry| entry.location.start);
+        output_interface.sort_by_key(|entry| entry.location.start);
+
+        Ok(EntryPointReflection {
+            name: entry_point.name.clone(),
+            stage: entry_point.stage,
+            execution_modes: self
+                .execution_modes
+                .get(&entry_point.function)
+                .cloned()
+                .unwrap_or_default(),
+            descriptor_bindings,
+            push_constants,
+            input_interface,
+            output_interface,
+        })
+    }
+
+    // Returns the type of a descriptor, whether it is read-only and write-only, and the number
+    // of array elements, or `None` if the type isn't supported.
+    //
+    // See also section 14.5.2 of the Vulkan specs: Descriptor Set Interface.
+    fn descriptor_infos(
+        &self,
+        ty: u32,
+        storage: u32,
+        combined_image_sampler: bool,
+    ) -> Result<Option<(DescriptorDescTy, bool, bool, u32)>, SpirvParseError> {
+        let infos = match *self.ty(ty)? {
+            Type::Struct { ref members } => {
+                let block = self.has_decoration(ty, decoration::BLOCK);
+                let buffer_block = self.has_decoration(ty, decoration::BUFFER_BLOCK);
+                let storage = buffer_block || (block && storage == storage_class::STORAGE_BUFFER);
+
+                let all_members = |decoration| {
+                    (0..members.len() as u32)
+                        .all(|index| self.member_decoration(ty, index, decoration).is_some())
+                };
+
+                // Uniforms are never writable.
+                let readonly = !storage || all_members(decoration::NON_WRITABLE);
+                let writeonly = storage && all_members(decoration::NON_READABLE);
+                let ty = DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                    dynamic: None,
+                    storage,
+                });
+
+                Some((ty, readonly, writeonly, 1))
+            }
+            Type::Image {
+                dim,
+                arrayed,
+                multisampled,
+                sampled,
+                format,
+            } => {
+                let format = image_format(format);
+                let array_layers = if arrayed {
+                    DescriptorImageDescArray::Arrayed { max_layers: None }
+                } else {
+                    DescriptorImageDescArray::NonArrayed
+                };
+                // Sampled is 1 for images used with a sampler, and 2 for storage images.
+                let storage = sampled == 2;
+
+                match dim {
+                    // SubpassData
+                    6 => {
+                        let ty = DescriptorDescTy::InputAttachment {
+                            multisampled,
+                            array_layers,
+                        };
+                        Some((ty, true, false, 1))
+                    }
+                    // Buffer
+                    5 => {
+                        let ty = DescriptorDescTy::TexelBuffer { storage, format };
+                        Some((ty, !storage, false, 1))
+                    }
+                    0..=3 => {
+                        let dimensions = match dim {
+                            0 => DescriptorImageDescDimensions::OneDimensional,
+                            1 => DescriptorImageDescDimensions::TwoDimensional,
+                            2 => DescriptorImageDescDimensions::ThreeDimensional,
+                            _ => DescriptorImageDescDimensions::Cube,
+                        };
+                        let desc = DescriptorImageDesc {
+                            sampled: !storage,
+                            dimensions,
+                            format,
+                            multisampled,
+                            array_layers,
+                        };
+
+                        if combined_image_sampler {
+                            let ty = DescriptorDescTy::CombinedImageSampler(desc);
+                            Some((ty, true, false, 1))
+                        } else {
+                            Some((DescriptorDescTy::Image(desc), !storage, false, 1))
+                        }
+                    }
+                    _ => None,
+                }
+            }
+            Type::SampledImage { image } => self
+                .descriptor_infos(image, storage, true)?
+                .filter(|infos| matches!(infos.0, DescriptorDescTy::CombinedImageSampler(_))),
+            Type::Sampler => Some((DescriptorDescTy::Sampler, true, false, 1)),
+            Type::Array { element, length } => {
+                let length = self.constant(length)? as u32;
+                self.descriptor_infos(element, storage, false)?
+                    .filter(|infos| infos.3 == 1)
+                    .map(|(ty, readonly, writeonly, _)| (ty, readonly, writeonly, length))
+            }
+            Type::RuntimeArray { element } => self
+                .descriptor_infos(element, storage, false)?
+                .filter(|infos| infos.3 == 1)
+                .map(|(ty, readonly, writeonly, _)| (ty, readonly, writeonly, 0)),
+            _ => None,
+        };
+
+        Ok(infos)
+    }
+
+    // Returns the struct type of a buffer block, or of the elements of an array of blocks.
+    fn block_type(&self, ty: u32) -> Result<u32, SpirvParseError> {
+        match *self.ty(ty)? {
+            Type::Array { element, .. } | Type::RuntimeArray { element } => {
+                self.block_type(element)
+            }
+            _ => Ok(ty),
+        }
+    }
+
+    // Returns the members of a block.
+    fn block_members(&self, ty: u32) -> Result<Vec<BlockMemberReflection>, SpirvParseError> {
+        let members = match *self.ty(ty)? {
+            Type::Struct { ref members } => members,
+            _ => return Err(SpirvParseError::UnsupportedType(ty)),
+        };
+
+        members
+            .iter()
+            .enumerate()
+            .map(|(index, &member_ty)| {
+                let index = index as u32;
+                let offset = self
+                    .member_decoration(ty, index, decoration::OFFSET)
+                    .ok_or(SpirvParseError::UnsupportedType(ty))?[0];
+                let size = match *self.ty(member_ty)? {
+                    Type::RuntimeArray { .. } => 0,
+                    _ => self
+                        .member_size(ty, index, member_ty)?
+                        .ok_or(SpirvParseError::UnsupportedType(member_ty))?,
+                };
+
+                Ok(BlockMemberReflection {
+                    name: self.member_names.get(&(ty, index)).cloned(),
+                    offset,
+                    size,
+                    scalar_type: self.scalar_type(member_ty)?,
+                })
+            })
+            .collect()
+    }
+
+    // Returns the type of a scalar, or of the components or elements of a composite type.
+    fn scalar_type(&self, ty: u32) -> Result<Option<ScalarType>, SpirvParseError> {
+        let scalar_type = match *self.ty(ty)? {
+            Type::Int { width, signed } => Some(ScalarType::Int { width, signed }),
+            Type::Float { width } => Some(ScalarType::Float { width }),
+            Type::Vector { component, .. } => self.scalar_type(component)?,
+            Type::Matrix { column, .. } => self.scalar_type(column)?,
+            Type::Array { element, .. } | Type::RuntimeArray { element } => {
+                self.scalar_type(element)?
+            }
+            _ => None,
+        };
+
+        Ok(scalar_type)
+    }
+
+    // Returns the size of a member of a struct, taking its layout decorations into account.
+    fn member_size(
+        &self,
+        struct_ty: u32,
+        index: u32,
+        ty: u32,
+    ) -> Result<Option<u32>, SpirvParseError> {
+        if let Type::Matrix { column, count } = *self.ty(ty)? {
+            let stride = self.member_decoration(struct_ty, index, decoration::MATRIX_STRIDE);
+            if let Some(stride) = stride {
+                let row_major = self
+                    .member_decoration(struct_ty, index, decoration::ROW_MAJOR)
+                    .is_some();
+                let count = if row_major {
+                    match *self.ty(column)? {
+                        Type::Vector { count, .. } => count,
+                        _ => return Err(SpirvParseError::UnsupportedType(column)),
+                    }
+                } else {
+                    count
+                };
+
+                return Ok(Some(stride[0] * count));
+            }
+        }
+
+        self.size(ty)
+    }
+
+    // Returns the size of a type, or `None` if it doesn't have a size.
+    fn size(&self, ty: u32) -> Result<Option<u32>, SpirvParseError> {
+        let size = match *self.ty(ty)? {
+            Type::Int { width, .. } | Type::Float { width } => Some(width / 8),
+            Type::Vector { component, count } => self.size(component)?.map(|size| size * count),
+            Type::Matrix { column, count } => self.size(column)?.map(|size| size * count),
+            Type::Array { element, length } => {
+                let length = self.constant(length)? as u32;
+                match self.decoration(ty, decoration::ARRAY_STRIDE) {
+                    Some(stride) => Some(stride[0] * length),
+                    None => self.size(element)?.map(|size| size * length),
+                }
+            }
+            Type::Struct { ref members } => {
+                let mut end = 0;
+                for (index, &member_ty) in members.iter().enumerate() {
+                    let index = index as u32;
+                    let offset = match self.member_decoration(ty, index, decoration::OFFSET) {
+                        Some(offset) => offset[0],
+                        None => return Ok(None),
+                    };
+                    match self.member_size(ty, index, member_ty)? {
+                        Some(size) => end = end.max(offset + size),
+                        None => return Ok(None),
+                    }
+                }
+                Some(end)
+            }
+            _ => None,
+        };
+
+        Ok(size)
+    }
+
+    // Returns the format of each location of an interface variable, and the number of locations
+    // that it covers.
+    fn interface_format(&self, ty: u32) -> Result<Option<(Format, u32)>, SpirvParseError> {
+        let infos = match *self.ty(ty)? {
+            Type::Int { .. } | Type::Float { .. } => {
+                self.scalar_format(ty, 1)?.map(|format| (format, 1))
+            }
+            Type::Vector { component, count } => {
+                // 64-bit vectors with more than two components take two locations.
+                let num_locations = match *self.ty(component)? {
+                    Type::Int { width: 64, .. } | Type::Float { width: 64 } if count > 2 => 2,
+                    _ => 1,
+                };
+                self.scalar_format(component, count)?
+                    .map(|format| (format, num_locations))
+            }
+            Type::Matrix { column, count } => self
+                .interface_format(column)?
+                .map(|(format, num_locations)| (format, num_locations * count)),
+            Type::Array { element, length } => {
+                let length = self.constant(length)? as u32;
+                self.interface_format(element)?
+                    .map(|(format, num_locations)| (format, num_locations * length))
+            }
+            _ => None,
+        };
+
+        Ok(infos)
+    }
+
+    // Returns the format of a vector of `count` scalars of type `ty`.
+    fn scalar_format(&self, ty: u32, count: u32) -> Result<Option<Format>, SpirvParseError> {
+        let format = match *self.ty(ty)? {
+            Type::Int { width, signed } => match (width, signed, count) {
+                (8, true, 1) => Some(Format::R8Sint),
+                (8, true, 2) => Some(Format::R8G8Sint),
+                (8, true, 3) => Some(Format::R8G8B8Sint),
+                (8, true, 4) => Some(Format::R8G8B8A8Sint),
+                (8, false, 1) => Some(Format::R8Uint),
+                (8, false, 2) => Some(Format::R8G8Uint),
+                (8, false, 3) => Some(Format::R8G8B8Uint),
+                (8, false, 4) => Some(Format::R8G8B8A8Uint),
+                (16, true, 1) => Some(Format::R16Sint),
+                (16, true, 2) => Some(Format::R16G16Sint),
+                (16, true, 3) => Some(Format::R16G16B16Sint),
+                (16, true, 4) => Some(Format::R16G16B16A16Sint),
+                (16, false, 1) => Some(Format::R16Uint),
+                (16, false, 2) => Some(Format::R16G16Uint),
+                (16, false, 3) => Some(Format::R16G16B16Uint),
+                (16, false, 4) => Some(Format::R16G16B16A16Uint),
+                (32, true, 1) => Some(Format::R32Sint),
+                (32, true, 2) => Some(Format::R32G32Sint),
+                (32, true, 3) => Some(Format::R32G32B32Sint),
+                (32, true, 4) => Some(Format::R32G32B32A32Sint),
+                (32, false, 1) => Some(Format::R32Uint),
+                (32, false, 2) => Some(Format::R32G32Uint),
+                (32, false, 3) => Some(Format::R32G32B32Uint),
+                (32, false, 4) => Some(Format::R32G32B32A32Uint),
+                (64, true, 1) => Some(Format::R64Sint),
+                (64, true, 2) => Some(Format::R64G64Sint),
+                (64, true, 3) => Some(Format::R64G64B64Sint),
+                (64, true, 4) => Some(Format::R64G64B64A64Sint),
+                (64, false, 1) => Some(Format::R64Uint),
+                (64, false, 2) => Some(Format::R64G64Uint),
+                (64, false, 3) => Some(Format::R64G64B64Uint),
+                (64, false, 4) => Some(Format::R64G64B64A64Uint),
+                _ => None,
+            },
+            Type::Float { width } => match (width, count) {
+                (16, 1) => Some(Format::R16Sfloat),
+                (16, 2) => Some(Format::R16G16Sfloat),
+                (16, 3) => Some(Format::R16G16B16Sfloat),
+                (16, 4) => Some(Format::R16G16B16A16Sfloat),
+                (32, 1) => Some(Format::R32Sfloat),
+                (32, 2) => Some(Format::R32G32Sfloat),
+                (32, 3) => Some(Format::R32G32B32Sfloat),
+                (32, 4) => Some(Format::R32G32B32A32Sfloat),
+                (64, 1) => Some(Format::R64Sfloat),
+                (64, 2) => Some(Format::R64G64Sfloat),
+                (64, 3) => Some(Format::R64G64B64Sfloat),
+                (64, 4) => Some(Format::R64G64B64A64Sfloat),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        Ok(format)
+    }
+
+    // Returns true if the type is a block of built-in variables, such as `gl_PerVertex`.
+    fn is_builtin(&self, ty: u32) -> bool {
+        match self.types.get(&ty) {
+            Some(&Type::Array { element, .. }) | Some(&Type::RuntimeArray { element }) => {
+                self.is_builtin(element)
+            }
+            Some(Type::Struct { members }) => (0..members.len() as u32).any(|index| {
+                self.member_decoration(ty, index, decoration::BUILT_IN)
+                    .is_some()
+            }),
+            _ => false,
+        }
+    }
+
+    fn ty(&self, id: u32) -> Result<&Type, SpirvParseError> {
+        self.types.get(&id).ok_or(SpirvParseError::UnknownId(id))
+    }
+
+    fn constant(&self, id: u32) -> Result<u64, SpirvParseError> {
+        self.constants
+            .get(&id)
+            .cloned()
+            .ok_or(SpirvParseError::UnknownId(id))
+    }
+
+    fn decoration(&self, id: u32, decoration: u32) -> Option<&[u32]> {
+        self.decorations
+            .get(&id)?
+            .iter()
+            .find(|(d, _)| *d == decoration)
+            .map(|(_, params)| params.as_slice())
+    }
+
+    fn has_decoration(&self, id: u32, decoration: u32) -> bool {
+        self.decoration(id, decoration).is_some()
+    }
+
+    fn member_decoration(&self, id: u32, member: u32, decoration: u32) -> Option<&[u32]> {
+        self.member_decorations
+            .get(&(id, member))?
+            .iter()
+            .find(|(d, _)| *d == decoration)
+            .map(|(_, params)| params.as_slice())
+    }
+}
+
+struct Instruction<'a> {
+    opcode: u16,
+    operands: &'a [u32],
+}
+
+fn parse_instructions(spirv: &[u32]) -> Result<Vec<Instruction<'_>>, SpirvParseError> {
+    if spirv.len() < 5 {
+        return Err(SpirvParseError::MissingHeader);
+    }
+
+    if spirv[0] != MAGIC_NUMBER {
+        return Err(SpirvParseError::WrongHeader);
+    }
+
+    let mut instructions = Vec::new();
+    let mut rest = &spirv[5..];
+
+    while !rest.is_empty() {
+        let word_count = (rest[0] >> 16) as usize;
+        if word_count == 0 || word_count > rest.len() {
+            return Err(SpirvParseError::IncompleteInstruction);
+        }
+
+        instructions.push(Instruction {
+            opcode: rest[0] as u16,
+            operands: &rest[1..word_count],
+        });
+        rest = &rest[word_count..];
+    }
+
+    Ok(instructions)
+}
+
+// Parses a null-terminated string literal, and returns it with the number of words it used.
+fn parse_string(words: &[u32]) -> Result<(String, usize), SpirvParseError> {
+    let bytes = words
+        .iter()
+        .flat_map(|word| word.to_le_bytes().to_vec())
+        .collect::<Vec<_>>();
+    let len = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .ok_or(SpirvParseError::InvalidString)?;
+    let string =
+        String::from_utf8(bytes[..len].to_vec()).map_err(|_| SpirvParseError::InvalidString)?;
+
+    Ok((string, len / 4 + 1))
+}
+
+// Checks that the decorations whose parameters are read have them.
+fn check_decoration_params(decoration: u32, params: &[u32]) -> Result<(), SpirvParseError> {
+    let num_params = match decoration {
+        decoration::SPEC_ID
+        | decoration::ARRAY_STRIDE
+        | decoration::MATRIX_STRIDE
+        | decoration::BUILT_IN
+        | decoration::LOCATION
+        | decoration::BINDING
+        | decoration::DESCRIPTOR_SET
+        | decoration::OFFSET => 1,
+        _ => 0,
+    };
+
+    if params.len() < num_params {
+        return Err(SpirvParseError::IncompleteInstruction);
+    }
+
+    Ok(())
+}
+
+fn execution_model_stage(execution_model: u32) -> Option<ShaderStage> {
+    match execution_model {
+        0 => Some(ShaderStage::Vertex),
+        1 => Some(ShaderStage::TessellationControl),
+        2 => Some(ShaderStage::TessellationEvaluation),
+        3 => Some(ShaderStage::Geometry),
+        4 => Some(ShaderStage::Fragment),
+        5 => Some(ShaderStage::Compute),
+        _ => None,
+    }
+}
+
+// Converts an image format of SPIR-V, or returns `None` if it is `Unknown`.
+fn image_format(format: u32) -> Option<Format> {
+    match format {
+        1 => Some(Format::R32G32B32A32Sfloat),
+        2 => Some(Format::R16G16B16A16Sfloat),
+        3 => Some(Format::R32Sfloat),
+        4 => Some(Format::R8G8B8A8Unorm),
+        5 => Some(Format::R8G8B8A8Snorm),
+        6 => Some(Format::R32G32Sfloat),
+        7 => Some(Format::R16G16Sfloat),
+        8 => Some(Format::B10G11R11UfloatPack32),
+        9 => Some(Format::R16Sfloat),
+        10 => Some(Format::R16G16B16A16Unorm),
+        11 => Some(Format::A2B10G10R10UnormPack32),
+        12 => Some(Format::R16G16Unorm),
+        13 => Some(Format::R8G8Unorm),
+        14 => Some(Format::R16Unorm),
+        15 => Some(Format::R8Unorm),
+        16 => Some(Format::R16G16B16A16Snorm),
+        17 => Some(Format::R16G16Snorm),
+        18 => Some(Format::R8G8Snorm),
+        19 => Some(Format::R16Snorm),
+        20 => Some(Format::R8Snorm),
+        21 => Some(Format::R32G32B32A32Sint),
+        22 => Some(Format::R16G16B16A16Sint),
+        23 => Some(Format::R8G8B8A8Sint),
+        24 => Some(Format::R32Sint),
+        25 => Some(Format::R32G32Sint),
+        26 => Some(Format::R16G16Sint),
+        27 => Some(Format::R8G8Sint),
+        28 => Some(Format::R16Sint),
+        29 => Some(Format::R8Sint),
+        30 => Some(Format::R32G32B32A32Uint),
+        31 => Some(Format::R16G16B16A16Uint),
+        32 => Some(Format::R8G8B8A8Uint),
+        33 => Some(Format::R32Uint),
+        34 => Some(Format::A2B10G10R10UintPack32),
+        35 => Some(Format::R32G32Uint),
+        36 => Some(Format::R16G16Uint),
+        37 => Some(Format::R8G8Uint),
+        38 => Some(Format::R16Uint),
+        39 => Some(Format::R8Uint),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::descriptor_set::layout::DescriptorBufferDesc;
+    use crate::descriptor_set::layout::DescriptorDescTy;
+    use crate::device::DeviceExtensions;
+    use crate::format::Format;
+    use crate::pipeline::shader::reflect::BlockMemberReflection;
+    use crate::pipeline::shader::reflect::ExecutionMode;
+    use crate::pipeline::shader::reflect::ScalarType;
+    use crate::pipeline::shader::reflect::ShaderReflection;
+    use crate::pipeline::shader::reflect::SpecializationConstantType;
+    use crate::pipeline::shader::reflect::SpecializationConstantValue;
+    use crate::pipeline::shader::reflect::SpirvParseError;
+    use crate::pipeline::shader::ShaderStage;
+    use crate::pipeline::shader::ShaderStages;
+    use crate::pipeline::shader::SpecializationConstantsError;
+    use crate::pipeline::shader::SpecializationMapEntry;
+    use crate::Version;
+
+    fn string(s: &str) -> Vec<u32> {
+        let mut bytes = s.as_bytes().to_vec();
+        bytes.resize((bytes.len() / 4 + 1) * 4, 0);
+        bytes
+            .chunks(4)
+            .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect()
+    }
+
+    fn inst(opcode: u32, operands: &[u32]) -> Vec<u32> {
+        let mut words = vec![((operands.len() as u32 + 1) << 16) | opcode];
+        words.extend_from_slice(operands);
+        words
+    }
+
+    fn module(instructions: &[Vec<u32>]) -> Vec<u32> {
+        let mut words = vec![0x07230203, 0x00010000, 0, 100, 0];
+        for instruction in instructions {
+            words.extend_from_slice(instruction);
+        }
+        words
+    }
+
+    #[test]
+    fn entry_points() {
+        let spirv = module(&[
+            // OpEntryPoint Fragment %1 "main"
+            inst(15, &[&[4, 1][..], &string("main")].concat()),
+            // OpEntryPoint GLCompute %2 "comp" %3
+            inst(15, &[&[5, 2][..], &string("comp"), &[3]].concat()),
+            // OpExecutionMode %2 LocalSize 8 4 1
+            inst(16, &[2, 17, 8, 4, 1]),
+            // OpExecutionMode %1 OriginUpperLeft
+            inst(16, &[1, 7]),
+        ]);
+
+        let reflection = ShaderReflection::new(&spirv).unwrap();
+        let entry_points = reflection.entry_points();
+        assert_eq!(entry_points.len(), 2);
+        assert_eq!(entry_points[0].name, "main");
+        assert_eq!(entry_points[0].stage, ShaderStage::Fragment);
+        assert_eq!(
+            entry_points[0].execution_modes,
+            [ExecutionMode::OriginUpperLeft]
+        );
+
+        let compute = reflection.entry_point("comp").unwrap();
+        assert_eq!(compute.stage, ShaderStage::Compute);
+        assert_eq!(compute.local_size(), Some([8, 4, 1]));
+    }
+
+    #[test]
+    fn interfaces_descriptors_and_push_constants() {
+        let spirv = module(&[
+            // OpEntryPoint Vertex %1 "main" %5 %7
+            inst(15, &[&[0, 1][..], &string("main"), &[5, 7]].concat()),
+            inst(5, &[&[5][..], &string("position")].concat()),
+            inst(6, &[&[12, 1][..], &string("color")].concat()),
+            // %5: Location 0, %7: Location 2
+            inst(71, &[5, 30, 0]),
+            inst(71, &[7, 30, 2]),
+            // %9: Block, member 0 at Offset 0 and NonWritable
+            inst(71, &[9, 2]),
+            inst(72, &[9, 0, 35, 0]),
+            inst(72, &[9, 0, 24]),
+            // %11: DescriptorSet 1, Binding 3
+            inst(71, &[11, 34, 1]),
+            inst(71, &[11, 33, 3]),
+            // %12: Block, members at Offset 0 and 16
+            inst(71, &[12, 2]),
+            inst(72, &[12, 0, 35, 0]),
+            inst(72, &[12, 1, 35, 16]),
+            // %2 = float, %3 = vec4, %5 = Input vec4, %7 = Output vec4
+            inst(22, &[2, 32]),
+            inst(23, &[3, 2, 4]),
+            inst(32, &[4, 1, 3]),
+            inst(59, &[4, 5, 1]),
+            inst(32, &[6, 3, 3]),
+            inst(59, &[6, 7, 3]),
+            // %8 = uint, %11 = StorageBuffer { uint }
+            inst(21, &[8, 32, 0]),
+            inst(30, &[9, 8]),
+            inst(32, &[10, 12, 9]),
+            inst(59, &[10, 11, 12]),
+            // %14 = PushConstant { uint, vec4 }
+            inst(30, &[12, 8, 3]),
+            inst(32, &[13, 9, 12]),
+            inst(59, &[13, 14, 9]),
+            // The function accesses %11 and %14.
+            inst(54, &[15, 1, 0, 16]),
+            inst(61, &[8, 17, 11]),
+            inst(65, &[16, 18, 14, 19]),
+            inst(56, &[]),
+        ]);
+
+        let reflection = ShaderReflection::new(&spirv).unwrap();
+        let entry_point = reflection.entry_point("main").unwrap();
+
+        assert_eq!(entry_point.input_interface.len(), 1);
+        assert_eq!(entry_point.input_interface[0].location, 0..1);
+        assert_eq!(
+            entry_point.input_interface[0].format,
+            Format::R32G32B32A32Sfloat
+        );
+        assert_eq!(
+            entry_point.input_interface[0].name.as_deref(),
+            Some("position")
+        );
+        assert_eq!(entry_point.output_interface.len(), 1);
+        assert_eq!(entry_point.output_interface[0].location, 2..3);
+
+        assert_eq!(entry_point.descriptor_bindings.len(), 1);
+        let binding = &entry_point.descriptor_bindings[0];
+        assert_eq!((binding.set, binding.binding), (1, 3));
+        assert_eq!(
+            binding.desc.ty,
+            DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                dynamic: None,
+                storage: true,
+            })
+        );
+        assert_eq!(binding.desc.array_count, 1);
+        assert_eq!(binding.desc.stages, ShaderStages::from(ShaderStage::Vertex));
+        assert!(binding.desc.readonly);
+        assert!(!binding.writeonly);
+        assert_eq!(binding.members.len(), 1);
+        assert_eq!(binding.members[0].size, 4);
+
+        let set_descs = entry_point.descriptor_set_descs();
+        assert_eq!(set_descs.len(), 2);
+        assert!(set_descs[0].bindings().is_empty());
+        assert!(set_descs[1].descriptor(3).is_some());
+
+        let push_constants = entry_point.push_constants.as_ref().unwrap();
+        assert_eq!(push_constants.range.offset, 0);
+        assert_eq!(push_constants.range.size, 32);
+        assert_eq!(
+            push_constants.members,
+            [
+                BlockMemberReflection {
+                    name: None,
+                    offset: 0,
+                    size: 4,
+                    scalar_type: Some(ScalarType::Int {
+                        width: 32,
+                        signed: false,
+                    }),
+                },
+                BlockMemberReflection {
+                    name: Some("color".to_owned()),
+                    offset: 16,
+                    size: 16,
+                    scalar_type: Some(ScalarType::Float { width: 32 }),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn specialization_constants() {
+        let spirv = module(&[
+            inst(5, &[&[3][..], &string("enabled")].concat()),
+            // %3: SpecId 2, %4: SpecId 0, %6: BuiltIn WorkgroupSize
+            inst(71, &[3, 1, 2]),
+            inst(71, &[4, 1, 0]),
+            inst(71, &[6, 11, 25]),
+            // %1 = bool, %2 = int, %3 = true, %4 = -2, %5 = 4
+            inst(20, &[1]),
+            inst(21, &[2, 32, 1]),
+            inst(48, &[1, 3]),
+            inst(50, &[2, 4, (-2i32) as u32]),
+            inst(43, &[2, 5, 4]),
+            // %6 = ivec3(%4, %5, %5)
+            inst(23, &[7, 2, 3]),
+            inst(51, &[7, 6, 4, 5, 5]),
+        ]);
+
+        let reflection = ShaderReflection::new(&spirv).unwrap();
+        let constants = reflection.specialization_constants();
+        assert_eq!(constants.len(), 2);
+        assert_eq!(constants[0].constant_id, 0);
+        assert_eq!(
+            constants[0].ty,
+            SpecializationConstantType::Int {
+                width: 32,
+                signed: true,
+            }
+        );
+        assert_eq!(
+            constants[0].default_value,
+            SpecializationConstantValue::Int(-2)
+        );
+        let enabled = reflection.specialization_constant(2).unwrap();
+        assert_eq!(enabled.name.as_deref(), Some("enabled"));
+        assert_eq!(
+            enabled.default_value,
+            SpecializationConstantValue::Bool(true)
+        );
+
+        let workgroup_size = reflection.workgroup_size().unwrap();
+        assert_eq!(workgroup_size.default_value, [(-2i32) as u32, 4, 4]);
+        assert_eq!(workgroup_size.constant_ids, [Some(0), None, None]);
+        assert!(workgroup_size.is_specializable());
+
+        let entries = reflection.specialization_map_entries();
+        assert!(reflection
+            .check_specialization_constants(&entries, 8)
+            .is_ok());
+        assert_eq!(
+            reflection.check_specialization_constants(&entries[..1], 8),
+            Err(SpecializationConstantsError::MissingConstant { constant_id: 2 })
+        );
+        let mistyped = [
+            entries[0],
+            SpecializationMapEntry {
+                constant_id: 2,
+                offset: 4,
+                size: 1,
+            },
+        ];
+        assert_eq!(
+            reflection.check_specialization_constants(&mistyped, 8),
+            Err(SpecializationConstantsError::SizeMismatch {
+                constant_id: 2,
+                expected: 4,
+                obtained: 1,
+            })
+        );
+        assert_eq!(
+            reflection.check_specialization_constants(&entries, 4),
+            Err(SpecializationConstantsError::OutOfBounds { constant_id: 2 })
+        );
+    }
+
+    #[test]
+    fn small_types() {
+        let spirv = module(&[
+            // OpCapability Shader, Float16, Int8, StoragePushConstant8, StoragePushConstant16
+            inst(17, &[1]),
+            inst(17, &[9]),
+            inst(17, &[39]),
+            inst(17, &[4450]),
+            inst(17, &[4435]),
+            // OpEntryPoint GLCompute %1 "main"
+            inst(15, &[&[5, 1][..], &string("main")].concat()),
+            // %5: Block, members at Offset 0 and 8
+            inst(71, &[5, 2]),
+            inst(72, &[5, 0, 35, 0]),
+            inst(72, &[5, 1, 35, 8]),
+            // %2 = half, %3 = f16vec3, %4 = uint8_t, %7 = PushConstant { half, f16vec3[2] }
+            inst(22, &[2, 16]),
+            inst(23, &[3, 2, 3]),
+            inst(21, &[4, 8, 0]),
+            inst(43, &[4, 8, 2]),
+            inst(28, &[9, 3, 8]),
+            inst(71, &[9, 6, 8]),
+            inst(30, &[5, 2, 9]),
+            inst(32, &[6, 9, 5]),
+            inst(59, &[6, 7, 9]),
+            // The function accesses %7.
+            inst(54, &[10, 1, 0, 11]),
+            inst(65, &[12, 13, 7, 14]),
+            inst(56, &[]),
+        ]);
+
+        let reflection = ShaderReflection::new(&spirv).unwrap();
+        let push_constants = reflection
+            .entry_point("main")
+            .unwrap()
+            .push_constants
+            .as_ref()
+            .unwrap();
+        assert_eq!(push_constants.members[0].size, 2);
+        assert_eq!(push_constants.members[1].offset, 8);
+        assert_eq!(push_constants.members[1].size, 16);
+        assert_eq!(
+            push_constants.members[1].scalar_type,
+            Some(ScalarType::Float { width: 16 })
+        );
+
+        let features = reflection.required_features();
+        assert!(features.shader_float16);
+        assert!(features.shader_int8);
+        assert!(features.storage_push_constant8);
+        assert!(features.storage_push_constant16);
+        assert!(!features.storage_buffer8_bit_access);
+
+        let extensions = reflection.required_extensions(Version::V1_1);
+        assert!(extensions.khr_8bit_storage);
+        assert!(extensions.khr_shader_float16_int8);
+        assert!(!extensions.khr_16bit_storage);
+        assert_eq!(
+            reflection.required_extensions(Version::V1_2),
+            DeviceExtensions::none()
+        );
+    }
+
+    #[test]
+    fn incomplete_instruction() {
+        let spirv = [0x07230203, 0x00010000, 0, 10, 0, (5 << 16) | 15, 4];
+
+        match ShaderReflection::new(&spirv) {
+            Err(SpirvParseError::IncompleteInstruction) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -8,18 +8,19 @@
 // according to those terms.
 
 use crate::buffer::BufferAccess;
+use smallvec::smallvec;
+use smallvec::SmallVec;
 
 /// A collection of vertex buffers.
 pub unsafe trait VertexBuffersCollection {
     /// Converts `self` into a list of buffers.
-    // TODO: better than a Vec
-    fn into_vec(self) -> Vec<Box<dyn BufferAccess + Send + Sync>>;
+    fn into_vec(self) -> SmallVec<[Box<dyn BufferAccess + Send + Sync>; 4]>;
 }
 
 unsafe impl VertexBuffersCollection for () {
     #[inline]
-    fn into_vec(self) -> Vec<Box<dyn BufferAccess + Send + Sync>> {
-        vec![]
+    fn into_vec(self) -> SmallVec<[Box<dyn BufferAccess + Send + Sync>; 4]> {
+        SmallVec::new()
     }
 }
 
@@ -28,8 +29,8 @@ where
     T: BufferAccess + Send + Sync + 'static,
 {
     #[inline]
-    fn into_vec(self) -> Vec<Box<dyn BufferAccess + Send + Sync>> {
-        vec![Box::new(self) as Box<_>]
+    fn into_vec(self) -> SmallVec<[Box<dyn BufferAccess + Send + Sync>; 4]> {
+        smallvec![Box::new(self) as Box<_>]
     }
 }
 
@@ -38,7 +39,7 @@ where
     T: BufferAccess + Send + Sync + 'static,
 {
     #[inline]
-    fn into_vec(self) -> Vec<Box<dyn BufferAccess + Send + Sync>> {
+    fn into_vec(self) -> SmallVec<[Box<dyn BufferAccess + Send + Sync>; 4]> {
         self.into_iter()
             .map(|source| Box::new(source) as Box<_>)
             .collect()
@@ -52,12 +53,12 @@ macro_rules! impl_collection {
                   $(, $others: BufferAccess + Send + Sync + 'static)*
         {
             #[inline]
-            fn into_vec(self) -> Vec<Box<dyn BufferAccess + Send + Sync>> {
+            fn into_vec(self) -> SmallVec<[Box<dyn BufferAccess + Send + Sync>; 4]> {
                 #![allow(non_snake_case)]
 
                 let ($first, $($others,)*) = self;
 
-                let mut list = Vec::new();
+                let mut list = SmallVec::new();
                 list.push(Box::new($first) as Box<_>);
                 $(
                     list.push(Box::new($others) as Box<_>);
@@ -0,0 +1,165 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Extending the creation of objects with structures that vulkano doesn't wrap.
+//!
+//! Vulkan extensions often add new parameters to the creation of objects in the form of
+//! structures that are chained to the `pNext` field of the creation structure. A `PNextChain`
+//! lets you build these structures with `ash` and append them to the structures filled by
+//! vulkano, so that you can use an extension before it is supported by vulkano.
+//!
+//! The chain borrows the structures for as long as it exists, which guarantees that they outlive
+//! the creation of the object. Vulkan doesn't keep the pointers after the creation, so you can
+//! read the structures that are filled by the implementation once the chain has been consumed.
+//!
+//! # Example
+//!
+//! ```
+//! use vulkano::pnext::PNextChain;
+//!
+//! let mut overallocation = ash::vk::DeviceMemoryOverallocationCreateInfoAMD {
+//!     overallocation_behavior: ash::vk::MemoryOverallocationBehaviorAMD::DISALLOWED,
+//!     ..Default::default()
+//! };
+//!
+//! let mut chain = PNextChain::<ash::vk::DeviceCreateInfo>::new();
+//! chain.push(&mut overallocation);
+//! assert_eq!(chain.len(), 1);
+//! ```
+//!
+//! The chain is then passed to a function such as `Device::with_p_next_chain`. These functions
+//! are unsafe, because vulkano doesn't validate the content of the structures. In particular the
+//! extensions that define them must be enabled, and the chain must not contain a structure that
+//! vulkano already appends for the parameters that it wraps.
+
+use smallvec::SmallVec;
+use std::fmt;
+use std::marker::PhantomData;
+use std::os::raw::c_void;
+
+/// Structures to append to the `pNext` chain of the creation structure `T`.
+///
+/// `T` is the `ash` creation structure, for example `ash::vk::DeviceCreateInfo`. Only the
+/// structures that are allowed to extend `T` can be pushed.
+pub struct PNextChain<'a, T> {
+    structures: SmallVec<[*mut ash::vk::BaseOutStructure; 4]>,
+    marker: PhantomData<(&'a mut (), T)>,
+}
+
+impl<'a, T> PNextChain<'a, T> {
+    /// Builds an empty chain.
+    #[inline]
+    pub fn new() -> Self {
+        PNextChain {
+            structures: SmallVec::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of structures in the chain.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.structures.len()
+    }
+
+    /// Returns true if the chain doesn't contain any structure.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.structures.is_empty()
+    }
+
+    /// Links the structures of the chain in front of `p_next`, and returns the new head of the
+    /// chain.
+    ///
+    /// The `p_next` field of every structure of the chain is overwritten.
+    pub(crate) unsafe fn link(&mut self, mut p_next: *const c_void) -> *const c_void {
+        for &structure in self.structures.iter().rev() {
+            (*structure).p_next = p_next as *mut _;
+            p_next = structure as *const _;
+        }
+
+        p_next
+    }
+}
+
+impl<'a, T> Default for PNextChain<'a, T> {
+    #[inline]
+    fn default() -> Self {
+        PNextChain::new()
+    }
+}
+
+impl<'a, T> fmt::Debug for PNextChain<'a, T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_list()
+            .entries(
+                self.structures
+                    .iter()
+                    .map(|&structure| unsafe { (*structure).s_type }),
+            )
+            .finish()
+    }
+}
+
+macro_rules! push_impl {
+    ($($create_info:ident => $extends:ident,)+) => {
+        $(
+            impl<'a> PNextChain<'a, ash::vk::$create_info> {
+                /// Appends a structure to the chain.
+                ///
+                /// Its `p_next` field is overwritten when the chain is used. To append several
+                /// structures, push each of them instead of chaining them yourself.
+                #[inline]
+                pub fn push<S>(&mut self, structure: &'a mut S) -> &mut Self
+                where
+                    S: ash::vk::$extends,
+                {
+                    self.structures
+                        .push(structure as *mut S as *mut ash::vk::BaseOutStructure);
+                    self
+                }
+            }
+        )+
+    };
+}
+
+push_impl! {
+    BufferCreateInfo => ExtendsBufferCreateInfo,
+    DeviceCreateInfo => ExtendsDeviceCreateInfo,
+    ImageCreateInfo => ExtendsImageCreateInfo,
+    InstanceCreateInfo => ExtendsInstanceCreateInfo,
+    SurfaceCapabilities2KHR => ExtendsSurfaceCapabilities2KHR,
+    SwapchainCreateInfoKHR => ExtendsSwapchainCreateInfoKHR,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pnext::PNextChain;
+    use std::ptr;
+
+    #[test]
+    fn link_order() {
+        let mut first = ash::vk::ExternalMemoryBufferCreateInfo::default();
+        let mut second = ash::vk::BufferOpaqueCaptureAddressCreateInfo::default();
+        let tail = ash::vk::BufferDeviceAddressCreateInfoEXT::default();
+
+        let mut chain = PNextChain::<ash::vk::BufferCreateInfo>::new();
+        assert!(chain.is_empty());
+        chain.push(&mut first).push(&mut second);
+        assert_eq!(chain.len(), 2);
+
+        let tail_ptr = &tail as *const _ as *const _;
+        let head = unsafe { chain.link(tail_ptr) };
+        drop(chain);
+
+        assert!(ptr::eq(head, &first as *const _ as *const _));
+        assert!(ptr::eq(first.p_next, &second as *const _ as *const _));
+        assert!(ptr::eq(second.p_next, tail_ptr));
+    }
+}
@@ -0,0 +1,453 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::ResetQueryPoolError;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::free_list::FreeList;
+use crate::query::QueriesRange;
+use crate::query::QueryPool;
+use crate::query::QueryPoolCreationError;
+use crate::query::QueryType;
+use crate::sync::FenceSignalFuture;
+use crate::sync::FrameTracker;
+use crate::sync::GpuFuture;
+use crate::sync::SignalPoint;
+use crate::sync::TimelineSemaphore;
+use crate::OomError;
+use crate::VulkanObject;
+use std::cmp;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Hands out ranges of queries from a few large query pools, and recycles them once the frame
+/// that used them is finished.
+///
+/// Creating and destroying query pools every frame, for example for the timestamps of the passes
+/// of the frame, is costly. The allocator instead keeps its query pools, and divides them into the
+/// ranges that are requested with `allocate`. The ranges are given to the current frame, and are
+/// given back once the GPU has finished the frame. A new pool is only created when the existing
+/// ones are full.
+///
+/// A frame is usually ended with `end_frame_after_fence`, together with the fence of the last
+/// submission that uses its queries. `cleanup_finished` then gives back the queries of the frames
+/// whose fence is signaled. Alternatively, a frame ended with `end_frame` is given back with the
+/// unsafe `frame_completed`, for applications that track the completion of their frames
+/// themselves.
+///
+/// The queries must be reset before they are used. `record_resets` records the resets of the
+/// ranges that were allocated since it was last called, with as few commands as possible.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use vulkano::command_buffer::AutoCommandBufferBuilder;
+/// # use vulkano::command_buffer::PrimaryAutoCommandBuffer;
+/// # use vulkano::device::Device;
+/// # use vulkano::device::Queue;
+/// # let device: Arc<Device> = return;
+/// # let queue: Arc<Queue> = return;
+/// # let mut builder: AutoCommandBufferBuilder<PrimaryAutoCommandBuffer> = return;
+/// use vulkano::query::QueryPoolAllocator;
+/// use vulkano::query::QueryType;
+/// use vulkano::sync;
+/// use vulkano::sync::GpuFuture;
+///
+/// let mut allocator = QueryPoolAllocator::new(device.clone(), QueryType::Timestamp, 256);
+///
+/// // At the start of a frame.
+/// allocator.cleanup_finished().unwrap();
+/// let timestamps = allocator.allocate(2).unwrap();
+/// unsafe {
+///     allocator.record_resets(&mut builder).unwrap();
+/// }
+/// // ... Write the timestamps with `timestamps.pool()` and `timestamps.index(0)`.
+///
+/// let future = Arc::new(
+///     sync::now(device.clone())
+///         .then_execute(queue.clone(), builder.build().unwrap())
+///         .unwrap()
+///         .then_signal_fence_and_flush()
+///         .unwrap(),
+/// );
+/// allocator.end_frame_after_fence(&future);
+/// ```
+#[derive(Debug)]
+pub struct QueryPoolAllocator {
+    device: Arc<Device>,
+    ty: QueryType,
+    pool_size: u32,
+    pools: Vec<PoolState>,
+    // The allocated ranges of queries, with the index of their pool, by frame.
+    frames: FrameTracker<(usize, Range<u32>)>,
+    // The ranges that `record_resets` must reset.
+    to_reset: Vec<(usize, Range<u32>)>,
+}
+
+#[derive(Debug)]
+struct PoolState {
+    pool: Arc<QueryPool>,
+    free: FreeList,
+}
+
+impl QueryPoolAllocator {
+    /// Builds a new `QueryPoolAllocator` whose pools have `pool_size` queries of type `ty`. No
+    /// pool is created until the first allocation.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `pool_size` is 0.
+    /// - Panics if `ty` is `QueryType::PerformanceQuery`.
+    ///
+    pub fn new(device: Arc<Device>, ty: QueryType, pool_size: u32) -> QueryPoolAllocator {
+        assert_ne!(pool_size, 0);
+        assert!(!matches!(ty, QueryType::PerformanceQuery));
+
+        QueryPoolAllocator {
+            device,
+            ty,
+            pool_size,
+            pools: Vec::new(),
+            frames: FrameTracker::new(),
+            to_reset: Vec::new(),
+        }
+    }
+
+    /// Returns the type of the queries of the allocator.
+    #[inline]
+    pub fn ty(&self) -> QueryType {
+        self.ty
+    }
+
+    /// Allocates `num_queries` consecutive queries for the current frame.
+    ///
+    /// A new pool is created if none of the pools has enough free queries. It has `pool_size`
+    /// queries, or `num_queries` if it's larger.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `num_queries` is 0.
+    ///
+    pub fn allocate(
+        &mut self,
+        num_queries: u32,
+    ) -> Result<QueryAllocation, QueryPoolCreationError> {
+        assert_ne!(num_queries, 0);
+
+        let found = self
+            .pools
+            .iter_mut()
+            .enumerate()
+            .find_map(|(index, pool)| Some((index, pool.free.take(num_queries)?)));
+
+        let (index, range) = match found {
+            Some(found) => found,
+            None => {
+                let size = cmp::max(self.pool_size, num_queries);
+                let pool = Arc::new(QueryPool::new(self.device.clone(), self.ty, size)?);
+                let free = FreeList::new(num_queries..size);
+                self.pools.push(PoolState { pool, free });
+                (self.pools.len() - 1, 0..num_queries)
+            }
+        };
+
+        self.frames.push((index, range.clone()));
+        self.to_reset.push((index, range.clone()));
+
+        Ok(QueryAllocation {
+            pool: self.pools[index].pool.clone(),
+            range,
+        })
+    }
+
+    /// Adds commands to `builder` that reset the queries that were allocated since the last
+    /// call. Does nothing if there is no query to reset.
+    ///
+    /// This must be called outside of a render pass.
+    ///
+    /// # Safety
+    ///
+    /// - The command buffer of `builder` must be executed before the command buffers that use the
+    ///   queries that were allocated since the last call.
+    /// - The `QueryAllocation`s of the frames that were given back must not be used anymore,
+    ///   since their queries may be among the ones that are reset.
+    ///
+    pub unsafe fn record_resets<L, P>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<L, P>,
+    ) -> Result<(), ResetQueryPoolError> {
+        // Adjacent ranges in the same pool are reset with one command.
+        self.to_reset
+            .sort_unstable_by_key(|&(index, ref range)| (index, range.start));
+
+        let mut ranges = self.to_reset.drain(..).peekable();
+        while let Some((index, mut range)) = ranges.next() {
+            while let Some(&(next_index, ref next)) = ranges.peek() {
+                if next_index != index || next.start != range.end {
+                    break;
+                }
+                range.end = next.end;
+                ranges.next();
+            }
+
+            // The ranges that are handed out aren't used by the frames in flight, and the
+            // caller guarantees that the frames that were given back don't use them anymore.
+            builder.reset_query_pool(self.pools[index].pool.clone(), range)?;
+        }
+
+        Ok(())
+    }
+
+    /// Ends the current frame, and returns its identifier. The next allocations belong to the
+    /// next frame.
+    ///
+    /// The queries of the frame are only given back by `frame_completed`.
+    #[inline]
+    pub fn end_frame(&mut self) -> QueryFrame {
+        self.push_frame(None)
+    }
+
+    /// Ends the current frame like `end_frame`. Its queries are given back by `cleanup_finished`
+    /// once `fence` is signaled.
+    ///
+    /// `fence` must be signaled after the command buffers that use the queries of the frame, for
+    /// example because it is the fence of the last submission of the frame.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `fence` doesn't belong to the same device.
+    ///
+    pub fn end_frame_after_fence<F>(&mut self, fence: &Arc<FenceSignalFuture<F>>) -> QueryFrame
+    where
+        F: GpuFuture + Send + 'static,
+    {
+        assert_eq!(
+            fence.device().internal_object(),
+            self.device.internal_object()
+        );
+
+        self.push_frame(Some(SignalPoint::Fence(fence.clone())))
+    }
+
+    /// Ends the current frame like `end_frame`. Its queries are given back by `cleanup_finished`
+    /// once `semaphore` reaches `value`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `semaphore` doesn't belong to the same device.
+    ///
+    pub fn end_frame_after_timeline(
+        &mut self,
+        semaphore: &Arc<TimelineSemaphore>,
+        value: u64,
+    ) -> QueryFrame {
+        assert_eq!(
+            semaphore.device().internal_object(),
+            self.device.internal_object()
+        );
+
+        self.push_frame(Some(SignalPoint::Timeline(semaphore.clone(), value)))
+    }
+
+    fn push_frame(&mut self, point: Option<SignalPoint>) -> QueryFrame {
+        QueryFrame(self.frames.end_frame(point))
+    }
+
+    /// Gives back the queries of the frames whose fence or timeline value has been signaled.
+    ///
+    /// The frames that were ended with `end_frame` are left alone. This function never blocks.
+    pub fn cleanup_finished(&mut self) -> Result<(), OomError> {
+        let ranges = self.frames.take_finished()?;
+        self.release(ranges);
+        Ok(())
+    }
+
+    /// Gives the queries of `frame` back to the allocator, so that they can be allocated again.
+    ///
+    /// Does nothing if `frame` was already completed or wasn't returned by this allocator.
+    ///
+    /// Contrary to `AtlasAllocator::frame_completed`, this function is unsafe: the queries that
+    /// are given back are reset by the next call to `record_resets`, and resetting a query that
+    /// is still used by a command buffer is undefined behavior.
+    ///
+    /// # Safety
+    ///
+    /// - The command buffers that use the queries of `frame` must have finished executing.
+    /// - The `QueryAllocation`s of `frame` must not be used anymore.
+    ///
+    pub unsafe fn frame_completed(&mut self, frame: QueryFrame) {
+        if let Some(ranges) = self.frames.take_frame(frame.0) {
+            self.release(ranges);
+        }
+    }
+
+    fn release(&mut self, ranges: Vec<(usize, Range<u32>)>) {
+        for (index, range) in ranges {
+            self.pools[index].free.free(range);
+        }
+    }
+
+    /// Returns the number of frames that ended but weren't completed yet.
+    #[inline]
+    pub fn num_frames_in_flight(&self) -> usize {
+        self.frames.num_frames_in_flight()
+    }
+
+    /// Returns the number of queries that are allocated to the current frame and to the frames in
+    /// flight.
+    pub fn num_allocated(&self) -> u32 {
+        self.frames
+            .items()
+            .map(|(_, range)| range.end - range.start)
+            .sum()
+    }
+
+    /// Returns the query pools that the allocator created.
+    #[inline]
+    pub fn pools(&self) -> impl ExactSizeIterator<Item = &Arc<QueryPool>> {
+        self.pools.iter().map(|pool| &pool.pool)
+    }
+}
+
+/// Identifier of a frame of a `QueryPoolAllocator`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct QueryFrame(u64);
+
+/// Range of queries allocated by a `QueryPoolAllocator`.
+#[derive(Debug, Clone)]
+pub struct QueryAllocation {
+    pool: Arc<QueryPool>,
+    range: Range<u32>,
+}
+
+impl QueryAllocation {
+    /// Returns the query pool that contains the queries.
+    #[inline]
+    pub fn pool(&self) -> &Arc<QueryPool> {
+        &self.pool
+    }
+
+    /// Returns the range of the queries in the pool.
+    #[inline]
+    pub fn range(&self) -> Range<u32> {
+        self.range.clone()
+    }
+
+    /// Returns the number of queries.
+    #[inline]
+    pub fn num_queries(&self) -> u32 {
+        self.range.end - self.range.start
+    }
+
+    /// Returns the index in the pool of the query at `index` in the allocation.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `index` is out of range.
+    ///
+    #[inline]
+    pub fn index(&self, index: u32) -> u32 {
+        assert!(index < self.num_queries());
+        self.range.start + index
+    }
+
+    /// Returns the queries, to read their results.
+    #[inline]
+    pub fn queries(&self) -> QueriesRange<'_> {
+        self.pool.queries_range(self.range()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_buffer::AutoCommandBufferBuilder;
+    use crate::command_buffer::CommandBufferUsage;
+    use crate::query::QueryPoolAllocator;
+    use crate::query::QueryType;
+    use crate::sync;
+    use crate::sync::GpuFuture;
+    use std::sync::Arc;
+
+    #[test]
+    fn recycles_completed_frames() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let mut allocator = QueryPoolAllocator::new(device.clone(), QueryType::Timestamp, 8);
+        let first = allocator.allocate(6).unwrap();
+        let second = allocator.allocate(4).unwrap();
+        assert_eq!(allocator.pools().len(), 2);
+        assert!(!Arc::ptr_eq(first.pool(), second.pool()));
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        unsafe {
+            allocator.record_resets(&mut builder).unwrap();
+        }
+        builder.build().unwrap();
+
+        let frame = allocator.end_frame();
+        // Only `frame_completed` gives back a frame without a fence.
+        allocator.cleanup_finished().unwrap();
+        assert_eq!(allocator.num_frames_in_flight(), 1);
+        assert_eq!(allocator.num_allocated(), 10);
+        assert_eq!(allocator.num_frames_in_flight(), 1);
+
+        unsafe {
+            allocator.frame_completed(frame);
+            allocator.frame_completed(frame);
+        }
+        assert_eq!(allocator.num_allocated(), 0);
+
+        let third = allocator.allocate(8).unwrap();
+        assert!(Arc::ptr_eq(third.pool(), first.pool()));
+        assert_eq!(third.range(), 0..8);
+        assert_eq!(allocator.pools().len(), 2);
+    }
+
+    #[test]
+    fn recycles_after_fence() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let mut allocator = QueryPoolAllocator::new(device.clone(), QueryType::Timestamp, 8);
+        let first = allocator.allocate(8).unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        unsafe {
+            allocator.record_resets(&mut builder).unwrap();
+        }
+        let future = Arc::new(
+            sync::now(device.clone())
+                .then_execute(queue.clone(), builder.build().unwrap())
+                .unwrap()
+                .then_signal_fence_and_flush()
+                .unwrap(),
+        );
+        allocator.end_frame_after_fence(&future);
+
+        future.wait(None).unwrap();
+        allocator.cleanup_finished().unwrap();
+        assert_eq!(allocator.num_frames_in_flight(), 0);
+        assert_eq!(allocator.num_allocated(), 0);
+
+        let second = allocator.allocate(8).unwrap();
+        assert!(Arc::ptr_eq(second.pool(), first.pool()));
+        assert_eq!(allocator.pools().len(), 1);
+    }
+}
@@ -0,0 +1,1395 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Gather information about rendering, held in query pools.
+//!
+//! In Vulkan, queries are not created individually. Instead you manipulate **query pools**, which
+//! represent a collection of queries. Whenever you use a query, you have to specify both the query
+//! pool and the slot id within that query pool.
+
+use crate::check_errors;
+use crate::device::physical::QueueFamily;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::DeviceSize;
+use crate::Error;
+use crate::OomError;
+use crate::Success;
+use crate::VulkanObject;
+use std::convert::TryFrom;
+use std::error;
+use std::ffi::c_void;
+use std::ffi::CStr;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ops::Range;
+use std::panic::Location;
+use std::ptr;
+use std::sync::Arc;
+
+pub use self::allocator::QueryAllocation;
+pub use self::allocator::QueryFrame;
+pub use self::allocator::QueryPoolAllocator;
+pub use self::trace::CalibrationError;
+pub use self::trace::GpuTrace;
+pub use self::trace::TimestampCalibration;
+pub use self::trace::TrackId;
+
+mod allocator;
+mod trace;
+
+/// A collection of one or more queries of a particular type.
+#[derive(Debug)]
+pub struct QueryPool {
+    pool: ash::vk::QueryPool,
+    device: Arc<Device>,
+    num_slots: u32,
+    ty: QueryType,
+    performance: Option<PerformanceQueryState>,
+}
+
+// Additional state of the query pools that read performance counters.
+#[derive(Debug)]
+struct PerformanceQueryState {
+    queue_family: u32,
+    counters: Vec<PerformanceCounter>,
+    num_passes: u32,
+}
+
+impl QueryPool {
+    /// Builds a new query pool.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `ty` is `QueryType::PerformanceQuery`. Use `QueryPool::performance` instead.
+    ///
+    #[track_caller]
+    pub fn new(
+        device: Arc<Device>,
+        ty: QueryType,
+        num_slots: u32,
+    ) -> Result<QueryPool, QueryPoolCreationError> {
+        let statistics = match ty {
+            QueryType::PipelineStatistics(flags) => {
+                if !device.enabled_features().pipeline_statistics_query {
+                    return Err(QueryPoolCreationError::PipelineStatisticsQueryFeatureNotEnabled);
+                }
+
+                flags.into()
+            }
+            QueryType::Occlusion | QueryType::Timestamp => {
+                ash::vk::QueryPipelineStatisticFlags::empty()
+            }
+            QueryType::TransformFeedbackStream => {
+                if !device.enabled_extensions().ext_transform_feedback
+                    || device
+                        .physical_device()
+                        .properties()
+                        .transform_feedback_queries
+                        != Some(true)
+                {
+                    return Err(QueryPoolCreationError::TransformFeedbackQueriesNotSupported);
+                }
+
+                ash::vk::QueryPipelineStatisticFlags::empty()
+            }
+            QueryType::PerformanceQuery => {
+                panic!("performance query pools must be created with `QueryPool::performance`")
+            }
+        };
+
+        let infos = ash::vk::QueryPoolCreateInfo {
+            flags: ash::vk::QueryPoolCreateFlags::empty(),
+            query_type: ty.into(),
+            query_count: num_slots,
+            pipeline_statistics: statistics,
+            ..Default::default()
+        };
+        let pool = unsafe { QueryPool::create_raw(&device, &infos)? };
+
+        let pool = QueryPool {
+            pool,
+            device,
+            num_slots,
+            ty,
+            performance: None,
+        };
+        pool.device.auto_debug_name(&pool, Location::caller());
+        Ok(pool)
+    }
+
+    /// Builds a new query pool that reads performance counters.
+    ///
+    /// `counter_indices` are indices in the list returned by
+    /// [`QueueFamily::performance_counters`]. Each query reads all these counters at once. The
+    /// queries can only be used in command buffers of `queue_family`, and the profiling lock of
+    /// the device must be held while these command buffers are recorded and executed. See
+    /// [`Device::acquire_profiling_lock`].
+    ///
+    /// Some combinations of counters can't be read in a single execution. In that case, the
+    /// command buffers that use the queries must be submitted
+    /// [`num_passes`](QueryPool::num_passes) times, once with each pass index.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `queue_family` doesn't belong to the physical device of `device`.
+    /// - Panics if `counter_indices` is empty.
+    ///
+    #[track_caller]
+    pub fn performance(
+        device: Arc<Device>,
+        queue_family: QueueFamily,
+        counter_indices: &[u32],
+        num_slots: u32,
+    ) -> Result<QueryPool, QueryPoolCreationError> {
+        assert_eq!(
+            queue_family.physical_device().internal_object(),
+            device.physical_device().internal_object()
+        );
+        assert!(!counter_indices.is_empty());
+
+        if !device.enabled_features().performance_counter_query_pools {
+            return Err(QueryPoolCreationError::PerformanceCounterQueryPoolsFeatureNotEnabled);
+        }
+
+        let available_counters = queue_family.performance_counters()?;
+        let counters = counter_indices
+            .iter()
+            .map(|&index| {
+                available_counters
+                    .get(index as usize)
+                    .cloned()
+                    .ok_or(QueryPoolCreationError::CounterIndexOutOfRange)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let performance_info = ash::vk::QueryPoolPerformanceCreateInfoKHR {
+            queue_family_index: queue_family.id(),
+            counter_index_count: counter_indices.len() as u32,
+            p_counter_indices: counter_indices.as_ptr(),
+            ..Default::default()
+        };
+        let infos = ash::vk::QueryPoolCreateInfo {
+            p_next: &performance_info as *const _ as *const _,
+            flags: ash::vk::QueryPoolCreateFlags::empty(),
+            query_type: ash::vk::QueryType::PERFORMANCE_QUERY_KHR,
+            query_count: num_slots,
+            pipeline_statistics: ash::vk::QueryPipelineStatisticFlags::empty(),
+            ..Default::default()
+        };
+        let pool = unsafe { QueryPool::create_raw(&device, &infos)? };
+
+        let pool = QueryPool {
+            pool,
+            device,
+            num_slots,
+            ty: QueryType::PerformanceQuery,
+            performance: Some(PerformanceQueryState {
+                queue_family: queue_family.id(),
+                counters,
+                num_passes: queue_family.performance_query_passes(counter_indices),
+            }),
+        };
+        pool.device.auto_debug_name(&pool, Location::caller());
+        Ok(pool)
+    }
+
+    unsafe fn create_raw(
+        device: &Device,
+        infos: &ash::vk::QueryPoolCreateInfo,
+    ) -> Result<ash::vk::QueryPool, QueryPoolCreationError> {
+        let mut output = MaybeUninit::uninit();
+        let fns = device.fns();
+        check_errors(fns.v1_0.create_query_pool(
+            device.internal_object(),
+            infos,
+            ptr::null(),
+            output.as_mut_ptr(),
+        ))?;
+        Ok(output.assume_init())
+    }
+
+    /// Returns the [`QueryType`] that this query pool was created with.
+    #[inline]
+    pub fn ty(&self) -> QueryType {
+        self.ty
+    }
+
+    /// Returns the number of query slots of this query pool.
+    #[inline]
+    pub fn num_slots(&self) -> u32 {
+        self.num_slots
+    }
+
+    /// Returns the counters read by the queries of this pool, in the order of their results.
+    ///
+    /// Returns an empty slice if this isn't a performance query pool.
+    #[inline]
+    pub fn performance_counters(&self) -> &[PerformanceCounter] {
+        self.performance
+            .as_ref()
+            .map_or(&[], |performance| performance.counters.as_slice())
+    }
+
+    /// Returns the queue family whose command buffers can use the queries of this pool, if this
+    /// is a performance query pool.
+    #[inline]
+    pub fn performance_queue_family(&self) -> Option<QueueFamily<'_>> {
+        self.performance.as_ref().map(|performance| {
+            self.device
+                .physical_device()
+                .queue_family_by_id(performance.queue_family)
+                .unwrap()
+        })
+    }
+
+    /// Returns the number of times the command buffers that use the queries of this pool must be
+    /// submitted to get all their results.
+    ///
+    /// This is always 1, except for performance query pools.
+    #[inline]
+    pub fn num_passes(&self) -> u32 {
+        self.performance
+            .as_ref()
+            .map_or(1, |performance| performance.num_passes)
+    }
+
+    /// Returns a reference to a single query slot, or `None` if the index is out of range.
+    #[inline]
+    pub fn query(&self, index: u32) -> Option<Query> {
+        if index < self.num_slots() {
+            Some(Query { pool: self, index })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a reference to a range of queries, or `None` if out of range.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the range is empty.
+    #[inline]
+    pub fn queries_range(&self, range: Range<u32>) -> Option<QueriesRange> {
+        assert!(!range.is_empty());
+
+        if range.end <= self.num_slots() {
+            Some(QueriesRange { pool: self, range })
+        } else {
+            None
+        }
+    }
+}
+
+unsafe impl VulkanObject for QueryPool {
+    type Object = ash::vk::QueryPool;
+
+    #[inline]
+    fn internal_object(&self) -> ash::vk::QueryPool {
+        self.pool
+    }
+}
+
+unsafe impl DeviceOwned for QueryPool {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+impl Drop for QueryPool {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            let fns = self.device.fns();
+            fns.v1_0
+                .destroy_query_pool(self.device.internal_object(), self.pool, ptr::null());
+        }
+    }
+}
+
+/// Error that can happen when creating a query pool.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueryPoolCreationError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// A pipeline statistics pool was requested but the corresponding feature wasn't enabled.
+    PipelineStatisticsQueryFeatureNotEnabled,
+    /// A performance query pool was requested but the corresponding feature wasn't enabled.
+    PerformanceCounterQueryPoolsFeatureNotEnabled,
+    /// One of the counter indices is out of range of the counters of the queue family.
+    CounterIndexOutOfRange,
+    /// A transform feedback stream pool was requested but the `ext_transform_feedback`
+    /// extension wasn't enabled, or the device doesn't support transform feedback queries.
+    TransformFeedbackQueriesNotSupported,
+}
+
+impl error::Error for QueryPoolCreationError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            QueryPoolCreationError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for QueryPoolCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                QueryPoolCreationError::OomError(_) => "not enough memory available",
+                QueryPoolCreationError::PipelineStatisticsQueryFeatureNotEnabled => {
+                    "a pipeline statistics pool was requested but the corresponding feature \
+                 wasn't enabled"
+                }
+                QueryPoolCreationError::PerformanceCounterQueryPoolsFeatureNotEnabled => {
+                    "a performance query pool was requested but the corresponding feature wasn't \
+                 enabled"
+                }
+                QueryPoolCreationError::CounterIndexOutOfRange => {
+                    "one of the counter indices is out of range of the counters of the queue \
+                 family"
+                }
+                QueryPoolCreationError::TransformFeedbackQueriesNotSupported => {
+                    "a transform feedback stream pool was requested but the extension wasn't \
+                 enabled, or the device doesn't support transform feedback queries"
+                }
+            }
+        )
+    }
+}
+
+impl From<OomError> for QueryPoolCreationError {
+    #[inline]
+    fn from(err: OomError) -> QueryPoolCreationError {
+        QueryPoolCreationError::OomError(err)
+    }
+}
+
+impl From<Error> for QueryPoolCreationError {
+    #[inline]
+    fn from(err: Error) -> QueryPoolCreationError {
+        match err {
+            err @ Error::OutOfHostMemory => QueryPoolCreationError::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => QueryPoolCreationError::OomError(OomError::from(err)),
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+/// A reference to a single query slot.
+///
+/// This is created through [`QueryPool::query`].
+#[derive(Clone, Debug)]
+pub struct Query<'a> {
+    pool: &'a QueryPool,
+    index: u32,
+}
+
+impl<'a> Query<'a> {
+    /// Returns a reference to the query pool.
+    #[inline]
+    pub fn pool(&self) -> &'a QueryPool {
+        &self.pool
+    }
+
+    /// Returns the index of the query represented.
+    #[inline]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+/// A reference to a range of queries.
+///
+/// This is created through [`QueryPool::queries_range`].
+#[derive(Clone, Debug)]
+pub struct QueriesRange<'a> {
+    pool: &'a QueryPool,
+    range: Range<u32>,
+}
+
+impl<'a> QueriesRange<'a> {
+    /// Returns a reference to the query pool.
+    #[inline]
+    pub fn pool(&self) -> &'a QueryPool {
+        &self.pool
+    }
+
+    /// Returns the range of queries represented.
+    #[inline]
+    pub fn range(&self) -> Range<u32> {
+        self.range.clone()
+    }
+
+    /// Copies the results of this range of queries to a buffer on the CPU.
+    ///
+    /// [`self.pool().ty().result_size()`](QueryType::result_size) elements
+    /// will be written for each query in the range, plus 1 extra element per query if
+    /// [`QueryResultFlags::with_availability`] is enabled.
+    /// The provided buffer must be large enough to hold the data.
+    ///
+    /// `true` is returned if every result was available and written to the buffer. `false`
+    /// is returned if some results were not yet available; these will not be written to the buffer.
+    ///
+    /// See also [`copy_query_pool_results`](crate::command_buffer::AutoCommandBufferBuilder::copy_query_pool_results).
+    pub fn get_results<T>(
+        &self,
+        destination: &mut [T],
+        flags: QueryResultFlags,
+    ) -> Result<bool, GetResultsError>
+    where
+        T: QueryResultElement,
+    {
+        let stride = self.check_query_pool_results::<T>(
+            destination.as_ptr() as DeviceSize,
+            destination.len() as DeviceSize,
+            flags,
+        )?;
+
+        let result = unsafe {
+            let fns = self.pool.device.fns();
+            check_errors(fns.v1_0.get_query_pool_results(
+                self.pool.device.internal_object(),
+                self.pool.internal_object(),
+                self.range.start,
+                self.range.end - self.range.start,
+                std::mem::size_of_val(destination),
+                destination.as_mut_ptr() as *mut c_void,
+                stride,
+                ash::vk::QueryResultFlags::from(flags) | T::FLAG,
+            ))?
+        };
+
+        Ok(match result {
+            Success::Success => true,
+            Success::NotReady => false,
+            s => panic!("unexpected success value: {:?}", s),
+        })
+    }
+
+    /// Retrieves the results of this range of queries without blocking, and reports which ones
+    /// are available.
+    ///
+    /// One element is returned per query in the range: the results of the query if they are
+    /// available, or `None` if the GPU hasn't finished the query yet.
+    ///
+    /// See also [`get_results`](Self::get_results), which can wait for the results, and
+    /// [`copy_query_pool_results`](crate::command_buffer::AutoCommandBufferBuilder::copy_query_pool_results)
+    /// to retrieve them on the GPU.
+    pub fn try_get_results<T>(&self) -> Result<Vec<Option<Vec<T>>>, GetResultsError>
+    where
+        T: QueryResultElement + Copy + Default + PartialEq,
+    {
+        let flags = QueryResultFlags {
+            with_availability: true,
+            ..QueryResultFlags::default()
+        };
+
+        let count = (self.range.end - self.range.start) as usize;
+        let per_query_len = self.pool.ty.result_size() as usize + 1;
+        let mut raw_results = vec![T::default(); per_query_len * count];
+        self.get_results(&mut raw_results, flags)?;
+
+        Ok(self
+            .pool
+            .ty
+            .split_available_results(&raw_results)
+            .map(|results| results.map(|results| results.to_vec()))
+            .collect())
+    }
+
+    /// Copies the results of this range of pipeline statistics queries to the CPU, decoded into
+    /// one [`PipelineStatistics`] per query.
+    ///
+    /// `true` is returned if every result was available and written to `destination`. `false`
+    /// is returned if some results were not yet available; these will not be written.
+    /// [`QueryResultFlags::with_availability`] isn't allowed in `flags`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `destination` has less elements than the number of queries in the range.
+    ///
+    pub fn get_pipeline_statistics(
+        &self,
+        destination: &mut [PipelineStatistics],
+        flags: QueryResultFlags,
+    ) -> Result<bool, GetResultsError> {
+        let statistics = match self.pool.ty {
+            QueryType::PipelineStatistics(statistics) => statistics,
+            _ => return Err(GetResultsError::InvalidQueryType),
+        };
+
+        if flags.with_availability {
+            return Err(GetResultsError::InvalidFlags);
+        }
+
+        let count = (self.range.end - self.range.start) as usize;
+        assert!(destination.len() >= count);
+
+        let per_query_len = statistics.count() as usize;
+        let mut raw_results = vec![0u64; per_query_len * count];
+        if !self.get_results(&mut raw_results, flags)? {
+            return Ok(false);
+        }
+
+        for (index, output) in destination[..count].iter_mut().enumerate() {
+            let values = &raw_results[index * per_query_len..(index + 1) * per_query_len];
+            *output = PipelineStatistics::from_results(statistics, values);
+        }
+
+        Ok(true)
+    }
+
+    /// Copies the results of this range of performance queries to the CPU.
+    ///
+    /// The results of each query in the range are returned one after the other, each made of
+    /// one value per counter in the order of
+    /// [`self.pool().performance_counters()`](QueryPool::performance_counters).
+    ///
+    /// `None` is returned if some results were not yet available. Only
+    /// [`QueryResultFlags::wait`] is allowed in `flags`.
+    pub fn get_performance_results(
+        &self,
+        flags: QueryResultFlags,
+    ) -> Result<Option<Vec<PerformanceCounterResult>>, GetResultsError> {
+        let counters = match self.pool.performance {
+            Some(ref performance) => &performance.counters,
+            None => return Err(GetResultsError::InvalidQueryType),
+        };
+
+        if flags.with_availability || flags.partial {
+            return Err(GetResultsError::InvalidFlags);
+        }
+
+        let count = (self.range.end - self.range.start) as usize;
+        let mut raw_results =
+            vec![ash::vk::PerformanceCounterResultKHR::default(); counters.len() * count];
+        let stride = std::mem::size_of::<ash::vk::PerformanceCounterResultKHR>() * counters.len();
+
+        let result = unsafe {
+            let fns = self.pool.device.fns();
+            check_errors(fns.v1_0.get_query_pool_results(
+                self.pool.device.internal_object(),
+                self.pool.internal_object(),
+                self.range.start,
+                count as u32,
+                stride * count,
+                raw_results.as_mut_ptr() as *mut c_void,
+                stride as DeviceSize,
+                flags.into(),
+            ))?
+        };
+
+        match result {
+            Success::Success => (),
+            Success::NotReady => return Ok(None),
+            s => panic!("unexpected success value: {:?}", s),
+        }
+
+        let results = raw_results
+            .iter()
+            .zip(counters.iter().cycle())
+            .map(|(raw, counter)| unsafe {
+                PerformanceCounterResult::from_raw(raw, counter.storage)
+            })
+            .collect();
+
+        Ok(Some(results))
+    }
+
+    pub(crate) fn check_query_pool_results<T>(
+        &self,
+        buffer_start: DeviceSize,
+        buffer_len: DeviceSize,
+        flags: QueryResultFlags,
+    ) -> Result<DeviceSize, GetResultsError>
+    where
+        T: QueryResultElement,
+    {
+        assert!(buffer_len > 0);
+        debug_assert!(buffer_start % std::mem::size_of::<T>() as DeviceSize == 0);
+
+        let count = self.range.end - self.range.start;
+        let per_query_len = self.pool.ty.result_size() + flags.with_availability as DeviceSize;
+        let required_len = per_query_len * count as DeviceSize;
+
+        if buffer_len < required_len {
+            return Err(GetResultsError::BufferTooSmall {
+                required_len: required_len as DeviceSize,
+                actual_len: buffer_len as DeviceSize,
+            });
+        }
+
+        match self.pool.ty {
+            QueryType::Occlusion => (),
+            QueryType::PipelineStatistics(_) => (),
+            QueryType::TransformFeedbackStream => (),
+            QueryType::Timestamp => {
+                if flags.partial {
+                    return Err(GetResultsError::InvalidFlags);
+                }
+            }
+            QueryType::PerformanceQuery => return Err(GetResultsError::InvalidQueryType),
+        }
+
+        Ok(per_query_len * std::mem::size_of::<T>() as DeviceSize)
+    }
+}
+
+/// Error that can happen when calling [`QueriesRange::get_results`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GetResultsError {
+    /// The buffer is too small for the operation.
+    BufferTooSmall {
+        /// Required number of elements in the buffer.
+        required_len: DeviceSize,
+        /// Actual number of elements in the buffer.
+        actual_len: DeviceSize,
+    },
+    /// The connection to the device has been lost.
+    DeviceLost,
+    /// The provided flags are not allowed for this type of query.
+    InvalidFlags,
+    /// The operation isn't supported for this type of query.
+    InvalidQueryType,
+    /// Not enough memory.
+    OomError(OomError),
+}
+
+impl From<Error> for GetResultsError {
+    #[inline]
+    fn from(err: Error) -> Self {
+        match err {
+            Error::OutOfHostMemory | Error::OutOfDeviceMemory => {
+                Self::OomError(OomError::from(err))
+            }
+            Error::DeviceLost => Self::DeviceLost,
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+impl From<OomError> for GetResultsError {
+    #[inline]
+    fn from(err: OomError) -> Self {
+        Self::OomError(err)
+    }
+}
+
+impl fmt::Display for GetResultsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                Self::BufferTooSmall { .. } => {
+                    "the buffer is too small for the operation"
+                }
+                Self::DeviceLost => "the connection to the device has been lost",
+                Self::InvalidFlags => {
+                    "the provided flags are not allowed for this type of query"
+                }
+                Self::InvalidQueryType => {
+                    "the operation isn't supported for this type of query"
+                }
+                Self::OomError(_) => "not enough memory available",
+            }
+        )
+    }
+}
+
+impl error::Error for GetResultsError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Self::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// A trait for elements of buffers that can be used as a destination for query results.
+///
+/// # Safety
+/// This is implemented for `u32` and `u64`. Unless you really know what you're doing, you should
+/// not implement this trait for any other type.
+pub unsafe trait QueryResultElement {
+    const FLAG: ash::vk::QueryResultFlags;
+}
+
+unsafe impl QueryResultElement for u32 {
+    const FLAG: ash::vk::QueryResultFlags = ash::vk::QueryResultFlags::empty();
+}
+
+unsafe impl QueryResultElement for u64 {
+    const FLAG: ash::vk::QueryResultFlags = ash::vk::QueryResultFlags::TYPE_64;
+}
+
+/// The type of query that a query pool should perform.
+#[derive(Debug, Copy, Clone)]
+pub enum QueryType {
+    /// Tracks the number of samples that pass per-fragment tests (e.g. the depth test).
+    Occlusion,
+    /// Tracks statistics on pipeline invocations and their input data.
+    PipelineStatistics(QueryPipelineStatisticFlags),
+    /// Writes timestamps at chosen points in a command buffer.
+    Timestamp,
+    /// Tracks the number of primitives written to the transform feedback buffers of a vertex
+    /// stream, and the number of primitives that would have been written if the buffers were
+    /// large enough. The stream is selected with
+    /// [`begin_query_indexed`](crate::command_buffer::AutoCommandBufferBuilder::begin_query_indexed).
+    ///
+    /// Requires the `ext_transform_feedback` extension.
+    TransformFeedbackStream,
+    /// Reads performance counters of the device. Query pools of this type are created with
+    /// [`QueryPool::performance`].
+    PerformanceQuery,
+}
+
+impl QueryType {
+    /// Returns the number of [`QueryResultElement`]s that are needed to hold the result of a
+    /// single query of this type.
+    ///
+    /// - For `Occlusion` and `Timestamp` queries, this returns 1.
+    /// - For `PipelineStatistics` queries, this returns the number of statistics flags enabled.
+    /// - For `TransformFeedbackStream` queries, this returns 2: the number of primitives
+    ///   written, followed by the number of primitives needed.
+    /// - For `PerformanceQuery` queries, this returns 0, as their results can only be retrieved
+    ///   with [`QueriesRange::get_performance_results`].
+    ///
+    /// If the results are retrieved with [`QueryResultFlags::with_availability`] enabled, then
+    /// an additional element is required per query.
+    #[inline]
+    pub const fn result_size(&self) -> DeviceSize {
+        match self {
+            Self::Occlusion | Self::Timestamp => 1,
+            Self::PipelineStatistics(flags) => flags.count(),
+            Self::TransformFeedbackStream => 2,
+            Self::PerformanceQuery => 0,
+        }
+    }
+
+    /// Splits query results that were retrieved with [`QueryResultFlags::with_availability`],
+    /// for example after copying them to a buffer with
+    /// [`copy_query_pool_results`](crate::command_buffer::AutoCommandBufferBuilder::copy_query_pool_results).
+    ///
+    /// Returns the results of each query of this type in `data`, without the availability
+    /// element, or `None` if the query wasn't available.
+    #[inline]
+    pub fn split_available_results<'a, T>(
+        &self,
+        data: &'a [T],
+    ) -> impl ExactSizeIterator<Item = Option<&'a [T]>> + 'a
+    where
+        T: QueryResultElement + Copy + Default + PartialEq,
+    {
+        let result_size = self.result_size() as usize;
+
+        data.chunks_exact(result_size + 1).map(move |chunk| {
+            if chunk[result_size] != T::default() {
+                Some(&chunk[..result_size])
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl From<QueryType> for ash::vk::QueryType {
+    #[inline]
+    fn from(value: QueryType) -> Self {
+        match value {
+            QueryType::Occlusion => ash::vk::QueryType::OCCLUSION,
+            QueryType::PipelineStatistics(_) => ash::vk::QueryType::PIPELINE_STATISTICS,
+            QueryType::Timestamp => ash::vk::QueryType::TIMESTAMP,
+            QueryType::TransformFeedbackStream => ash::vk::QueryType::TRANSFORM_FEEDBACK_STREAM_EXT,
+            QueryType::PerformanceQuery => ash::vk::QueryType::PERFORMANCE_QUERY_KHR,
+        }
+    }
+}
+
+/// Flags that control how a query is to be executed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct QueryControlFlags {
+    /// For occlusion queries, specifies that the result must reflect the exact number of
+    /// tests passed. If not enabled, the query may return a result of 1 even if more fragments
+    /// passed the test.
+    pub precise: bool,
+}
+
+impl From<QueryControlFlags> for ash::vk::QueryControlFlags {
+    #[inline]
+    fn from(value: QueryControlFlags) -> Self {
+        let mut result = ash::vk::QueryControlFlags::empty();
+        if value.precise {
+            result |= ash::vk::QueryControlFlags::PRECISE;
+        }
+        result
+    }
+}
+
+/// For pipeline statistics queries, the statistics that should be gathered.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct QueryPipelineStatisticFlags {
+    /// Count the number of vertices processed by the input assembly.
+    pub input_assembly_vertices: bool,
+    /// Count the number of primitives processed by the input assembly.
+    pub input_assembly_primitives: bool,
+    /// Count the number of times a vertex shader is invoked.
+    pub vertex_shader_invocations: bool,
+    /// Count the number of times a geometry shader is invoked.
+    pub geometry_shader_invocations: bool,
+    /// Count the number of primitives generated by geometry shaders.
+    pub geometry_shader_primitives: bool,
+    /// Count the number of times the clipping stage is invoked on a primitive.
+    pub clipping_invocations: bool,
+    /// Count the number of primitives that are output by the clipping stage.
+    pub clipping_primitives: bool,
+    /// Count the number of times a fragment shader is invoked.
+    pub fragment_shader_invocations: bool,
+    /// Count the number of patches processed by a tessellation control shader.
+    pub tessellation_control_shader_patches: bool,
+    /// Count the number of times a tessellation evaluation shader is invoked.
+    pub tessellation_evaluation_shader_invocations: bool,
+    /// Count the number of times a compute shader is invoked.
+    pub compute_shader_invocations: bool,
+}
+
+impl QueryPipelineStatisticFlags {
+    #[inline]
+    pub fn none() -> QueryPipelineStatisticFlags {
+        QueryPipelineStatisticFlags {
+            input_assembly_vertices: false,
+            input_assembly_primitives: false,
+            vertex_shader_invocations: false,
+            geometry_shader_invocations: false,
+            geometry_shader_primitives: false,
+            clipping_invocations: false,
+            clipping_primitives: false,
+            fragment_shader_invocations: false,
+            tessellation_control_shader_patches: false,
+            tessellation_evaluation_shader_invocations: false,
+            compute_shader_invocations: false,
+        }
+    }
+
+    /// Returns the number of flags that are set to `true`.
+    #[inline]
+    pub const fn count(&self) -> DeviceSize {
+        let &Self {
+            input_assembly_vertices,
+            input_assembly_primitives,
+            vertex_shader_invocations,
+            geometry_shader_invocations,
+            geometry_shader_primitives,
+            clipping_invocations,
+            clipping_primitives,
+            fragment_shader_invocations,
+            tessellation_control_shader_patches,
+            tessellation_evaluation_shader_invocations,
+            compute_shader_invocations,
+        } = self;
+        input_assembly_vertices as DeviceSize
+            + input_assembly_primitives as DeviceSize
+            + vertex_shader_invocations as DeviceSize
+            + geometry_shader_invocations as DeviceSize
+            + geometry_shader_primitives as DeviceSize
+            + clipping_invocations as DeviceSize
+            + clipping_primitives as DeviceSize
+            + fragment_shader_invocations as DeviceSize
+            + tessellation_control_shader_patches as DeviceSize
+            + tessellation_evaluation_shader_invocations as DeviceSize
+            + compute_shader_invocations as DeviceSize
+    }
+
+    /// Returns `true` if any flags referring to compute operations are set to `true`.
+    #[inline]
+    pub const fn is_compute(&self) -> bool {
+        let &Self {
+            compute_shader_invocations,
+            ..
+        } = self;
+        compute_shader_invocations
+    }
+
+    /// Returns `true` if any flags referring to graphics operations are set to `true`.
+    #[inline]
+    pub const fn is_graphics(&self) -> bool {
+        let &Self {
+            input_assembly_vertices,
+            input_assembly_primitives,
+            vertex_shader_invocations,
+            geometry_shader_invocations,
+            geometry_shader_primitives,
+            clipping_invocations,
+            clipping_primitives,
+            fragment_shader_invocations,
+            tessellation_control_shader_patches,
+            tessellation_evaluation_shader_invocations,
+            ..
+        } = self;
+        input_assembly_vertices
+            || input_assembly_primitives
+            || vertex_shader_invocations
+            || geometry_shader_invocations
+            || geometry_shader_primitives
+            || clipping_invocations
+            || clipping_primitives
+            || fragment_shader_invocations
+            || tessellation_control_shader_patches
+            || tessellation_evaluation_shader_invocations
+    }
+}
+
+impl From<QueryPipelineStatisticFlags> for ash::vk::QueryPipelineStatisticFlags {
+    fn from(value: QueryPipelineStatisticFlags) -> ash::vk::QueryPipelineStatisticFlags {
+        let mut result = ash::vk::QueryPipelineStatisticFlags::empty();
+        if value.input_assembly_vertices {
+            result |= ash::vk::QueryPipelineStatisticFlags::INPUT_ASSEMBLY_VERTICES;
+        }
+        if value.input_assembly_primitives {
+            result |= ash::vk::QueryPipelineStatisticFlags::INPUT_ASSEMBLY_PRIMITIVES;
+        }
+        if value.vertex_shader_invocations {
+            result |= ash::vk::QueryPipelineStatisticFlags::VERTEX_SHADER_INVOCATIONS;
+        }
+        if value.geometry_shader_invocations {
+            result |= ash::vk::QueryPipelineStatisticFlags::GEOMETRY_SHADER_INVOCATIONS;
+        }
+        if value.geometry_shader_primitives {
+            result |= ash::vk::QueryPipelineStatisticFlags::GEOMETRY_SHADER_PRIMITIVES;
+        }
+        if value.clipping_invocations {
+            result |= ash::vk::QueryPipelineStatisticFlags::CLIPPING_INVOCATIONS;
+        }
+        if value.clipping_primitives {
+            result |= ash::vk::QueryPipelineStatisticFlags::CLIPPING_PRIMITIVES;
+        }
+        if value.fragment_shader_invocations {
+            result |= ash::vk::QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS;
+        }
+        if value.tessellation_control_shader_patches {
+            result |= ash::vk::QueryPipelineStatisticFlags::TESSELLATION_CONTROL_SHADER_PATCHES;
+        }
+        if value.tessellation_evaluation_shader_invocations {
+            result |=
+                ash::vk::QueryPipelineStatisticFlags::TESSELLATION_EVALUATION_SHADER_INVOCATIONS;
+        }
+        if value.compute_shader_invocations {
+            result |= ash::vk::QueryPipelineStatisticFlags::COMPUTE_SHADER_INVOCATIONS;
+        }
+        result
+    }
+}
+
+/// Flags to control how the results of a query should be retrieved.
+///
+/// `VK_QUERY_RESULT_64_BIT` is not included, as it is determined automatically via the
+/// [`QueryResultElement`] trait.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct QueryResultFlags {
+    /// Wait for the results to become available before writing the results.
+    pub wait: bool,
+    /// Write an additional element to the end of each query's results, indicating the availability
+    /// of the results:
+    /// - Nonzero: The results are available, and have been written to the element(s) preceding.
+    /// - Zero: The results are not yet available, and have not been written.
+    pub with_availability: bool,
+    /// Allow writing partial results to the buffer, instead of waiting until they are fully
+    /// available.
+    pub partial: bool,
+}
+
+impl From<QueryResultFlags> for ash::vk::QueryResultFlags {
+    #[inline]
+    fn from(value: QueryResultFlags) -> Self {
+        let mut result = ash::vk::QueryResultFlags::empty();
+        if value.wait {
+            result |= ash::vk::QueryResultFlags::WAIT;
+        }
+        if value.with_availability {
+            result |= ash::vk::QueryResultFlags::WITH_AVAILABILITY;
+        }
+        if value.partial {
+            result |= ash::vk::QueryResultFlags::PARTIAL;
+        }
+        result
+    }
+}
+
+/// The results of a pipeline statistics query, with one field per statistic.
+///
+/// The statistics that weren't enabled when creating the query pool are `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PipelineStatistics {
+    /// Number of vertices processed by the input assembly.
+    pub input_assembly_vertices: Option<u64>,
+    /// Number of primitives processed by the input assembly.
+    pub input_assembly_primitives: Option<u64>,
+    /// Number of times a vertex shader was invoked.
+    pub vertex_shader_invocations: Option<u64>,
+    /// Number of times a geometry shader was invoked.
+    pub geometry_shader_invocations: Option<u64>,
+    /// Number of primitives generated by geometry shaders.
+    pub geometry_shader_primitives: Option<u64>,
+    /// Number of times the clipping stage was invoked on a primitive.
+    pub clipping_invocations: Option<u64>,
+    /// Number of primitives that were output by the clipping stage.
+    pub clipping_primitives: Option<u64>,
+    /// Number of times a fragment shader was invoked.
+    pub fragment_shader_invocations: Option<u64>,
+    /// Number of patches processed by a tessellation control shader.
+    pub tessellation_control_shader_patches: Option<u64>,
+    /// Number of times a tessellation evaluation shader was invoked.
+    pub tessellation_evaluation_shader_invocations: Option<u64>,
+    /// Number of times a compute shader was invoked.
+    pub compute_shader_invocations: Option<u64>,
+}
+
+impl PipelineStatistics {
+    /// Decodes the raw results of a single pipeline statistics query, for example after copying
+    /// them to a buffer with
+    /// [`copy_query_pool_results`](crate::command_buffer::AutoCommandBufferBuilder::copy_query_pool_results).
+    ///
+    /// `flags` must be the statistics that the query pool was created with. Vulkan writes one
+    /// value for each of them, in the order of the fields of `PipelineStatistics`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `values` has less elements than the number of enabled statistics.
+    ///
+    pub fn from_results(flags: QueryPipelineStatisticFlags, values: &[u64]) -> PipelineStatistics {
+        assert!(values.len() as DeviceSize >= flags.count());
+
+        let mut values = values.iter().copied();
+        let mut next = |enabled: bool| {
+            if enabled {
+                values.next()
+            } else {
+                None
+            }
+        };
+
+        PipelineStatistics {
+            input_assembly_vertices: next(flags.input_assembly_vertices),
+            input_assembly_primitives: next(flags.input_assembly_primitives),
+            vertex_shader_invocations: next(flags.vertex_shader_invocations),
+            geometry_shader_invocations: next(flags.geometry_shader_invocations),
+            geometry_shader_primitives: next(flags.geometry_shader_primitives),
+            clipping_invocations: next(flags.clipping_invocations),
+            clipping_primitives: next(flags.clipping_primitives),
+            fragment_shader_invocations: next(flags.fragment_shader_invocations),
+            tessellation_control_shader_patches: next(flags.tessellation_control_shader_patches),
+            tessellation_evaluation_shader_invocations: next(
+                flags.tessellation_evaluation_shader_invocations,
+            ),
+            compute_shader_invocations: next(flags.compute_shader_invocations),
+        }
+    }
+}
+
+/// Description of a performance counter that can be read by the queries of a queue family.
+///
+/// This is returned by [`QueueFamily::performance_counters`].
+#[derive(Clone, Debug)]
+pub struct PerformanceCounter {
+    /// The unit of the values of the counter, or `Generic` if the unit is unknown to vulkano.
+    pub unit: PerformanceCounterUnit,
+    /// The scope that the counter belongs to, or `CommandBuffer`, the most restrictive one, if
+    /// the scope is unknown to vulkano.
+    pub scope: PerformanceCounterScope,
+    /// The type of the values of the counter, or `Uint64` if the type is unknown to vulkano, in
+    /// which case the results hold the raw bits of the values.
+    pub storage: PerformanceCounterStorage,
+    /// Universally unique identifier of the counter, which stays the same across drivers and
+    /// applications.
+    pub uuid: [u8; 16],
+    /// Short name of the counter.
+    pub name: String,
+    /// Name of the group of counters that the counter belongs to.
+    pub category: String,
+    /// Description of the counter.
+    pub description: String,
+    /// Reading the counter has a noticeable impact on performance.
+    pub performance_impacting: bool,
+    /// Reading the counter while other queue families read it too may affect its results.
+    pub concurrently_impacted: bool,
+}
+
+impl PerformanceCounter {
+    pub(crate) fn from_raw(
+        counter: &ash::vk::PerformanceCounterKHR,
+        description: &ash::vk::PerformanceCounterDescriptionKHR,
+    ) -> PerformanceCounter {
+        let string = |chars: &[std::os::raw::c_char]| unsafe {
+            CStr::from_ptr(chars.as_ptr())
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        PerformanceCounter {
+            // Drivers may report values that were added to the extension after this version of
+            // vulkano, so they are mapped to the closest known ones instead of panicking.
+            unit: PerformanceCounterUnit::try_from(counter.unit)
+                .unwrap_or(PerformanceCounterUnit::Generic),
+            scope: PerformanceCounterScope::try_from(counter.scope)
+                .unwrap_or(PerformanceCounterScope::CommandBuffer),
+            storage: PerformanceCounterStorage::try_from(counter.storage)
+                .unwrap_or(PerformanceCounterStorage::Uint64),
+            uuid: counter.uuid,
+            name: string(&description.name),
+            category: string(&description.category),
+            description: string(&description.description),
+            performance_impacting: description
+                .flags
+                .intersects(ash::vk::PerformanceCounterDescriptionFlagsKHR::PERFORMANCE_IMPACTING),
+            concurrently_impacted: description
+                .flags
+                .intersects(ash::vk::PerformanceCounterDescriptionFlagsKHR::CONCURRENTLY_IMPACTED),
+        }
+    }
+}
+
+/// The unit of the values of a performance counter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum PerformanceCounterUnit {
+    /// A unitless value.
+    Generic = ash::vk::PerformanceCounterUnitKHR::GENERIC.as_raw(),
+    /// A percentage between 0 and 100.
+    Percentage = ash::vk::PerformanceCounterUnitKHR::PERCENTAGE.as_raw(),
+    Nanoseconds = ash::vk::PerformanceCounterUnitKHR::NANOSECONDS.as_raw(),
+    Bytes = ash::vk::PerformanceCounterUnitKHR::BYTES.as_raw(),
+    BytesPerSecond = ash::vk::PerformanceCounterUnitKHR::BYTES_PER_SECOND.as_raw(),
+    Kelvin = ash::vk::PerformanceCounterUnitKHR::KELVIN.as_raw(),
+    Watts = ash::vk::PerformanceCounterUnitKHR::WATTS.as_raw(),
+    Volts = ash::vk::PerformanceCounterUnitKHR::VOLTS.as_raw(),
+    Amps = ash::vk::PerformanceCounterUnitKHR::AMPS.as_raw(),
+    Hertz = ash::vk::PerformanceCounterUnitKHR::HERTZ.as_raw(),
+    Cycles = ash::vk::PerformanceCounterUnitKHR::CYCLES.as_raw(),
+}
+
+impl TryFrom<ash::vk::PerformanceCounterUnitKHR> for PerformanceCounterUnit {
+    type Error = ();
+
+    #[inline]
+    fn try_from(val: ash::vk::PerformanceCounterUnitKHR) -> Result<Self, Self::Error> {
+        match val {
+            ash::vk::PerformanceCounterUnitKHR::GENERIC => Ok(Self::Generic),
+            ash::vk::PerformanceCounterUnitKHR::PERCENTAGE => Ok(Self::Percentage),
+            ash::vk::PerformanceCounterUnitKHR::NANOSECONDS => Ok(Self::Nanoseconds),
+            ash::vk::PerformanceCounterUnitKHR::BYTES => Ok(Self::Bytes),
+            ash::vk::PerformanceCounterUnitKHR::BYTES_PER_SECOND => Ok(Self::BytesPerSecond),
+            ash::vk::PerformanceCounterUnitKHR::KELVIN => Ok(Self::Kelvin),
+            ash::vk::PerformanceCounterUnitKHR::WATTS => Ok(Self::Watts),
+            ash::vk::PerformanceCounterUnitKHR::VOLTS => Ok(Self::Volts),
+            ash::vk::PerformanceCounterUnitKHR::AMPS => Ok(Self::Amps),
+            ash::vk::PerformanceCounterUnitKHR::HERTZ => Ok(Self::Hertz),
+            ash::vk::PerformanceCounterUnitKHR::CYCLES => Ok(Self::Cycles),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The scope that a performance counter belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum PerformanceCounterScope {
+    /// The counter measures a whole command buffer. The query must be the first command of the
+    /// command buffer to begin, and the last one to end.
+    CommandBuffer = ash::vk::PerformanceCounterScopeKHR::COMMAND_BUFFER.as_raw(),
+    /// The counter measures a whole render pass. The query must begin and end outside of the
+    /// render pass, right around it.
+    RenderPass = ash::vk::PerformanceCounterScopeKHR::RENDER_PASS.as_raw(),
+    /// The counter measures the commands recorded between the beginning and the end of the
+    /// query.
+    Command = ash::vk::PerformanceCounterScopeKHR::COMMAND.as_raw(),
+}
+
+impl TryFrom<ash::vk::PerformanceCounterScopeKHR> for PerformanceCounterScope {
+    type Error = ();
+
+    #[inline]
+    fn try_from(val: ash::vk::PerformanceCounterScopeKHR) -> Result<Self, Self::Error> {
+        match val {
+            ash::vk::PerformanceCounterScopeKHR::COMMAND_BUFFER => Ok(Self::CommandBuffer),
+            ash::vk::PerformanceCounterScopeKHR::RENDER_PASS => Ok(Self::RenderPass),
+            ash::vk::PerformanceCounterScopeKHR::COMMAND => Ok(Self::Command),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The type of the values of a performance counter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum PerformanceCounterStorage {
+    Int32 = ash::vk::PerformanceCounterStorageKHR::INT32.as_raw(),
+    Int64 = ash::vk::PerformanceCounterStorageKHR::INT64.as_raw(),
+    Uint32 = ash::vk::PerformanceCounterStorageKHR::UINT32.as_raw(),
+    Uint64 = ash::vk::PerformanceCounterStorageKHR::UINT64.as_raw(),
+    Float32 = ash::vk::PerformanceCounterStorageKHR::FLOAT32.as_raw(),
+    Float64 = ash::vk::PerformanceCounterStorageKHR::FLOAT64.as_raw(),
+}
+
+impl TryFrom<ash::vk::PerformanceCounterStorageKHR> for PerformanceCounterStorage {
+    type Error = ();
+
+    #[inline]
+    fn try_from(val: ash::vk::PerformanceCounterStorageKHR) -> Result<Self, Self::Error> {
+        match val {
+            ash::vk::PerformanceCounterStorageKHR::INT32 => Ok(Self::Int32),
+            ash::vk::PerformanceCounterStorageKHR::INT64 => Ok(Self::Int64),
+            ash::vk::PerformanceCounterStorageKHR::UINT32 => Ok(Self::Uint32),
+            ash::vk::PerformanceCounterStorageKHR::UINT64 => Ok(Self::Uint64),
+            ash::vk::PerformanceCounterStorageKHR::FLOAT32 => Ok(Self::Float32),
+            ash::vk::PerformanceCounterStorageKHR::FLOAT64 => Ok(Self::Float64),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The value of a performance counter, as returned by
+/// [`QueriesRange::get_performance_results`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PerformanceCounterResult {
+    Int32(i32),
+    Int64(i64),
+    Uint32(u32),
+    Uint64(u64),
+    Float32(f32),
+    Float64(f64),
+}
+
+impl PerformanceCounterResult {
+    /// Returns the value converted to a `f64`, which may lose precision for large 64-bits
+    /// integers.
+    #[inline]
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            PerformanceCounterResult::Int32(val) => val as f64,
+            PerformanceCounterResult::Int64(val) => val as f64,
+            PerformanceCounterResult::Uint32(val) => val as f64,
+            PerformanceCounterResult::Uint64(val) => val as f64,
+            PerformanceCounterResult::Float32(val) => val as f64,
+            PerformanceCounterResult::Float64(val) => val,
+        }
+    }
+
+    // Safety: `raw` must hold a value of type `storage`.
+    unsafe fn from_raw(
+        raw: &ash::vk::PerformanceCounterResultKHR,
+        storage: PerformanceCounterStorage,
+    ) -> PerformanceCounterResult {
+        match storage {
+            PerformanceCounterStorage::Int32 => PerformanceCounterResult::Int32(raw.int32),
+            PerformanceCounterStorage::Int64 => PerformanceCounterResult::Int64(raw.int64),
+            PerformanceCounterStorage::Uint32 => PerformanceCounterResult::Uint32(raw.uint32),
+            PerformanceCounterStorage::Uint64 => PerformanceCounterResult::Uint64(raw.uint64),
+            PerformanceCounterStorage::Float32 => PerformanceCounterResult::Float32(raw.float32),
+            PerformanceCounterStorage::Float64 => PerformanceCounterResult::Float64(raw.float64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::PerformanceCounter;
+    use crate::query::PerformanceCounterScope;
+    use crate::query::PerformanceCounterStorage;
+    use crate::query::PerformanceCounterUnit;
+    use crate::query::PipelineStatistics;
+    use crate::query::QueryPipelineStatisticFlags;
+    use crate::query::QueryPool;
+    use crate::query::QueryPoolCreationError;
+    use crate::query::QueryType;
+
+    #[test]
+    fn pipeline_statistics_feature() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let ty = QueryType::PipelineStatistics(QueryPipelineStatisticFlags::none());
+        match QueryPool::new(device, ty, 256) {
+            Err(QueryPoolCreationError::PipelineStatisticsQueryFeatureNotEnabled) => (),
+            _ => panic!(),
+        };
+    }
+
+    #[test]
+    fn pipeline_statistics_from_results() {
+        let flags = QueryPipelineStatisticFlags {
+            input_assembly_vertices: true,
+            fragment_shader_invocations: true,
+            compute_shader_invocations: true,
+            ..QueryPipelineStatisticFlags::none()
+        };
+
+        let statistics = PipelineStatistics::from_results(flags, &[3, 5, 7]);
+        assert_eq!(
+            statistics,
+            PipelineStatistics {
+                input_assembly_vertices: Some(3),
+                fragment_shader_invocations: Some(5),
+                compute_shader_invocations: Some(7),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn split_available_results() {
+        let data: [u64; 6] = [5, 1, 0, 0, 7, 1];
+        let results = QueryType::Occlusion
+            .split_available_results(&data)
+            .collect::<Vec<_>>();
+        assert_eq!(results, [Some(&[5][..]), None, Some(&[7][..])]);
+    }
+
+    #[test]
+    fn transform_feedback_extension_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match QueryPool::new(device, QueryType::TransformFeedbackStream, 256) {
+            Err(QueryPoolCreationError::TransformFeedbackQueriesNotSupported) => (),
+            _ => panic!(),
+        };
+    }
+
+    #[test]
+    fn performance_counter_query_pools_feature() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        match QueryPool::performance(device, queue.family(), &[0], 256) {
+            Err(QueryPoolCreationError::PerformanceCounterQueryPoolsFeatureNotEnabled) => (),
+            _ => panic!(),
+        };
+    }
+
+    #[test]
+    fn performance_counter_unknown_values() {
+        let counter = ash::vk::PerformanceCounterKHR {
+            unit: ash::vk::PerformanceCounterUnitKHR::from_raw(1000),
+            scope: ash::vk::PerformanceCounterScopeKHR::from_raw(1000),
+            storage: ash::vk::PerformanceCounterStorageKHR::from_raw(1000),
+            ..Default::default()
+        };
+        let description = ash::vk::PerformanceCounterDescriptionKHR::default();
+
+        let counter = PerformanceCounter::from_raw(&counter, &description);
+        assert_eq!(counter.unit, PerformanceCounterUnit::Generic);
+        assert_eq!(counter.scope, PerformanceCounterScope::CommandBuffer);
+        assert_eq!(counter.storage, PerformanceCounterStorage::Uint64);
+    }
+}
@@ -0,0 +1,474 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::check_errors;
+use crate::device::Device;
+use crate::Error;
+use crate::OomError;
+use crate::VulkanObject;
+use std::error;
+use std::fmt;
+use std::io;
+use std::io::Write;
+use std::ptr;
+
+/// Correlation between the timestamps of a device and the clock of the host.
+///
+/// Timestamp queries return a number of ticks of the device clock, whose origin is unspecified
+/// and which may drift from the clock of the host. A calibration is a pair of timestamps of both
+/// clocks sampled at the same time, which allows converting the device timestamps that are close
+/// to it into nanoseconds of the host clock.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimestampCalibration {
+    device_timestamp: u64,
+    host_timestamp: u64,
+    timestamp_period: f32,
+}
+
+impl TimestampCalibration {
+    /// Builds a calibration from a device timestamp and a host timestamp in nanoseconds that
+    /// were sampled at the same time.
+    ///
+    /// `timestamp_period` is the number of nanoseconds per tick of the device clock, as found
+    /// in the `timestamp_period` property of the physical device.
+    #[inline]
+    pub fn new(
+        device_timestamp: u64,
+        host_timestamp: u64,
+        timestamp_period: f32,
+    ) -> TimestampCalibration {
+        TimestampCalibration {
+            device_timestamp,
+            host_timestamp,
+            timestamp_period,
+        }
+    }
+
+    /// Samples the clock of `device` and the `CLOCK_MONOTONIC` clock of the host at the same
+    /// time.
+    ///
+    /// `CLOCK_MONOTONIC` is the clock used by Perfetto and by the tracing tools of Linux and
+    /// Android, so the GPU events line up with the CPU events that they record. It isn't
+    /// available on Windows.
+    ///
+    /// Requires the `ext_calibrated_timestamps` extension to be enabled on the device.
+    pub fn query(device: &Device) -> Result<TimestampCalibration, CalibrationError> {
+        if !device.enabled_extensions().ext_calibrated_timestamps {
+            return Err(CalibrationError::ExtensionNotEnabled);
+        }
+
+        let physical_device = device.physical_device();
+
+        let time_domains = unsafe {
+            let fns = device.instance().fns();
+
+            let mut num = 0;
+            check_errors(
+                fns.ext_calibrated_timestamps
+                    .get_physical_device_calibrateable_time_domains_ext(
+                        physical_device.internal_object(),
+                        &mut num,
+                        ptr::null_mut(),
+                    ),
+            )?;
+
+            let mut time_domains = Vec::with_capacity(num as usize);
+            check_errors(
+                fns.ext_calibrated_timestamps
+                    .get_physical_device_calibrateable_time_domains_ext(
+                        physical_device.internal_object(),
+                        &mut num,
+                        time_domains.as_mut_ptr(),
+                    ),
+            )?;
+            time_domains.set_len(num as usize);
+            time_domains
+        };
+
+        let required_domains = [
+            ash::vk::TimeDomainEXT::DEVICE,
+            ash::vk::TimeDomainEXT::CLOCK_MONOTONIC,
+        ];
+
+        if !required_domains
+            .iter()
+            .all(|domain| time_domains.contains(domain))
+        {
+            return Err(CalibrationError::TimeDomainNotSupported);
+        }
+
+        let infos = required_domains
+            .iter()
+            .map(|&time_domain| ash::vk::CalibratedTimestampInfoEXT {
+                time_domain,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        let mut timestamps = [0; 2];
+        let mut max_deviation = 0;
+
+        unsafe {
+            check_errors(
+                device
+                    .fns()
+                    .ext_calibrated_timestamps
+                    .get_calibrated_timestamps_ext(
+                        device.internal_object(),
+                        infos.len() as u32,
+                        infos.as_ptr(),
+                        timestamps.as_mut_ptr(),
+                        &mut max_deviation,
+                    ),
+            )?;
+        }
+
+        Ok(TimestampCalibration {
+            device_timestamp: timestamps[0],
+            host_timestamp: timestamps[1],
+            timestamp_period: physical_device.properties().timestamp_period,
+        })
+    }
+
+    // Returns the timestamp of the host, in nanoseconds.
+    #[inline]
+    pub(crate) fn host_timestamp(&self) -> u64 {
+        self.host_timestamp
+    }
+
+    /// Converts a timestamp of the device into nanoseconds of the host clock.
+    #[inline]
+    pub fn to_host_nanoseconds(&self, device_timestamp: u64) -> u64 {
+        // The timestamp can be sampled before the calibration.
+        let ticks = device_timestamp.wrapping_sub(self.device_timestamp) as i64;
+        let nanoseconds = (ticks as f64 * self.timestamp_period as f64) as i64;
+        (self.host_timestamp as i64).wrapping_add(nanoseconds) as u64
+    }
+}
+
+/// Error that can happen when calling `TimestampCalibration::query`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CalibrationError {
+    /// The `ext_calibrated_timestamps` extension wasn't enabled on the device.
+    ExtensionNotEnabled,
+    /// The device can't sample its clock at the same time as `CLOCK_MONOTONIC`.
+    TimeDomainNotSupported,
+    /// Not enough memory.
+    OomError(OomError),
+}
+
+impl error::Error for CalibrationError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            CalibrationError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CalibrationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                CalibrationError::ExtensionNotEnabled => {
+                    "the `ext_calibrated_timestamps` extension wasn't enabled on the device"
+                }
+                CalibrationError::TimeDomainNotSupported => {
+                    "the device can't sample its clock at the same time as CLOCK_MONOTONIC"
+                }
+                CalibrationError::OomError(_) => "not enough memory available",
+            }
+        )
+    }
+}
+
+impl From<OomError> for CalibrationError {
+    #[inline]
+    fn from(err: OomError) -> CalibrationError {
+        CalibrationError::OomError(err)
+    }
+}
+
+impl From<Error> for CalibrationError {
+    #[inline]
+    fn from(err: Error) -> CalibrationError {
+        match err {
+            err @ Error::OutOfHostMemory => CalibrationError::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => CalibrationError::OomError(OomError::from(err)),
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+/// Identifier of a track of a `GpuTrace`, usually one per queue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TrackId(usize);
+
+/// Timeline of GPU work, which can be written in the trace event format of `chrome://tracing`.
+///
+/// The trace is made of scopes, such as the passes of a frame measured with two timestamp
+/// queries each, and of labels, such as the debug labels inserted in command buffers. Both are
+/// given as timestamps of the device, and converted to the host clock with a
+/// `TimestampCalibration` when the trace is written. The written file can be opened with
+/// `chrome://tracing` or with [Perfetto](https://ui.perfetto.dev).
+///
+/// # Example
+///
+/// ```
+/// use vulkano::query::GpuTrace;
+/// use vulkano::query::TimestampCalibration;
+///
+/// # let (frame_start, frame_end, shadows_start, shadows_end) = (0, 0, 0, 0);
+/// // The timestamps are usually read from a timestamp query pool.
+/// let calibration = TimestampCalibration::new(frame_start, 0, 1.0);
+/// let mut trace = GpuTrace::new(calibration);
+///
+/// let graphics = trace.track("Graphics queue");
+/// trace.add_scope(graphics, "Frame", frame_start, frame_end);
+/// trace.add_scope(graphics, "Shadows", shadows_start, shadows_end);
+///
+/// let mut output = Vec::new();
+/// trace.write_chrome_trace(&mut output).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct GpuTrace {
+    calibration: TimestampCalibration,
+    tracks: Vec<String>,
+    events: Vec<TraceEvent>,
+}
+
+#[derive(Clone, Debug)]
+struct TraceEvent {
+    track: TrackId,
+    name: String,
+    start: u64,
+    // `None` for labels.
+    end: Option<u64>,
+}
+
+impl GpuTrace {
+    /// Builds a new empty trace, whose timestamps are converted with `calibration`.
+    #[inline]
+    pub fn new(calibration: TimestampCalibration) -> GpuTrace {
+        GpuTrace {
+            calibration,
+            tracks: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Returns the track with the given name, adding it if it doesn't exist yet.
+    pub fn track(&mut self, name: &str) -> TrackId {
+        match self.tracks.iter().position(|track| track == name) {
+            Some(index) => TrackId(index),
+            None => {
+                self.tracks.push(name.to_owned());
+                TrackId(self.tracks.len() - 1)
+            }
+        }
+    }
+
+    /// Adds a scope of GPU work between two device timestamps.
+    ///
+    /// Scopes of the same track that are nested are displayed on top of each other.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `track` doesn't belong to this trace.
+    ///
+    pub fn add_scope<S>(&mut self, track: TrackId, name: S, start: u64, end: u64)
+    where
+        S: Into<String>,
+    {
+        assert!(track.0 < self.tracks.len());
+
+        self.events.push(TraceEvent {
+            track,
+            name: name.into(),
+            start,
+            end: Some(end),
+        });
+    }
+
+    /// Adds a label at a device timestamp.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `track` doesn't belong to this trace.
+    ///
+    pub fn add_label<S>(&mut self, track: TrackId, name: S, timestamp: u64)
+    where
+        S: Into<String>,
+    {
+        assert!(track.0 < self.tracks.len());
+
+        self.events.push(TraceEvent {
+            track,
+            name: name.into(),
+            start: timestamp,
+            end: None,
+        });
+    }
+
+    // Returns the scopes of `track` as `(name, start, end)`, with the timestamps converted to
+    // nanoseconds of the host clock.
+    pub(crate) fn host_scopes(
+        &self,
+        track: TrackId,
+    ) -> impl Iterator<Item = (&str, u64, u64)> + '_ {
+        self.events
+            .iter()
+            .filter(move |event| event.track == track)
+            .filter_map(move |event| {
+                event.end.map(|end| {
+                    (
+                        event.name.as_str(),
+                        self.calibration.to_host_nanoseconds(event.start),
+                        self.calibration.to_host_nanoseconds(end),
+                    )
+                })
+            })
+    }
+
+    /// Removes all the scopes and labels, but keeps the tracks.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// Writes the trace as JSON in the trace event format of `chrome://tracing`.
+    ///
+    /// The timestamps are written in microseconds of the host clock.
+    pub fn write_chrome_trace<W>(&self, mut output: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        write!(output, "{{\"traceEvents\":[")?;
+
+        let mut first = true;
+        let mut separator = |output: &mut W| -> io::Result<()> {
+            if !first {
+                write!(output, ",")?;
+            }
+            first = false;
+            Ok(())
+        };
+
+        for (index, name) in self.tracks.iter().enumerate() {
+            separator(&mut output)?;
+            write!(
+                output,
+                "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":0,\"tid\":{},\"args\":{{\"name\":",
+                index
+            )?;
+            write_json_string(&mut output, name)?;
+            write!(output, "}}}}")?;
+        }
+
+        for event in &self.events {
+            let start = self.calibration.to_host_nanoseconds(event.start);
+
+            separator(&mut output)?;
+            write!(output, "{{\"name\":")?;
+            write_json_string(&mut output, &event.name)?;
+            write!(
+                output,
+                ",\"cat\":\"gpu\",\"pid\":0,\"tid\":{},\"ts\":{}",
+                event.track.0,
+                Microseconds(start)
+            )?;
+
+            match event.end {
+                Some(end) => {
+                    let end = self.calibration.to_host_nanoseconds(end);
+                    write!(
+                        output,
+                        ",\"ph\":\"X\",\"dur\":{}}}",
+                        Microseconds(end.saturating_sub(start))
+                    )?;
+                }
+                None => write!(output, ",\"ph\":\"i\",\"s\":\"t\"}}")?,
+            }
+        }
+
+        write!(output, "],\"displayTimeUnit\":\"ns\"}}")
+    }
+}
+
+// Formats nanoseconds as microseconds, without losing precision.
+struct Microseconds(u64);
+
+impl fmt::Display for Microseconds {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}.{:03}", self.0 / 1000, self.0 % 1000)
+    }
+}
+
+fn write_json_string<W>(output: &mut W, value: &str) -> io::Result<()>
+where
+    W: Write,
+{
+    write!(output, "\"")?;
+
+    for c in value.chars() {
+        match c {
+            '"' => write!(output, "\\\"")?,
+            '\\' => write!(output, "\\\\")?,
+            '\n' => write!(output, "\\n")?,
+            '\r' => write!(output, "\\r")?,
+            '\t' => write!(output, "\\t")?,
+            c if (c as u32) < 0x20 => write!(output, "\\u{:04x}", c as u32)?,
+            c => write!(output, "{}", c)?,
+        }
+    }
+
+    write!(output, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::GpuTrace;
+    use crate::query::TimestampCalibration;
+
+    #[test]
+    fn calibration() {
+        let calibration = TimestampCalibration::new(1000, 5_000_000, 2.0);
+        assert_eq!(calibration.to_host_nanoseconds(1500), 5_001_000);
+        assert_eq!(calibration.to_host_nanoseconds(500), 4_999_000);
+    }
+
+    #[test]
+    fn write_chrome_trace() {
+        let mut trace = GpuTrace::new(TimestampCalibration::new(0, 1_000_000, 1.0));
+        let queue = trace.track("Queue \"0\"");
+        assert_eq!(trace.track("Queue \"0\""), queue);
+
+        trace.add_scope(queue, "Frame", 0, 2500);
+        trace.add_label(queue, "Present", 2500);
+
+        let mut output = Vec::new();
+        trace.write_chrome_trace(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"traceEvents\":[\
+             {\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":0,\"tid\":0,\
+             \"args\":{\"name\":\"Queue \\\"0\\\"\"}},\
+             {\"name\":\"Frame\",\"cat\":\"gpu\",\"pid\":0,\"tid\":0,\"ts\":1000.000,\
+             \"ph\":\"X\",\"dur\":2.500},\
+             {\"name\":\"Present\",\"cat\":\"gpu\",\"pid\":0,\"tid\":0,\"ts\":1002.500,\
+             \"ph\":\"i\",\"s\":\"t\"}\
+             ],\"displayTimeUnit\":\"ns\"}"
+        );
+    }
+}
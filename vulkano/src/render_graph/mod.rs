@@ -0,0 +1,786 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Scheduling of passes and synchronization of the resources that they use.
+//!
+//! A `RenderGraph` is built every frame. You register the resources that the frame uses, then
+//! add *passes*. Each pass declares the resources that it reads and writes, along with the
+//! pipeline stages, accesses and image layouts of these uses, and provides a closure that records
+//! its commands.
+//!
+//! When the graph is recorded into a command buffer builder, it:
+//!
+//! - Removes the passes whose results are never used. A pass is kept if it writes a resource
+//!   that was imported into the graph, if it writes a transient image that a later pass reads,
+//!   or if it was marked with `side_effect`.
+//! - Orders the remaining passes. A pass that reads a resource comes after the passes that were
+//!   added before it and write that resource, and a pass that writes a resource comes after the
+//!   passes that were added before it and use that resource. Other than that, passes can be
+//!   moved so that a pass doesn't have to wait for the pass that runs right before it.
+//! - Records the passes in that order, with a pipeline barrier before each pass that performs
+//!   the layout transitions and memory dependencies that the pass needs. See
+//!   `AutoCommandBufferBuilder::pipeline_barrier`.
+//! - Creates the transient images. Transient images whose descriptions only differ by their
+//!   usage and whose lifetimes don't overlap share the same image, and therefore the same
+//!   memory. That image is created with all the usages of the transient images that it backs.
+//!
+//! The first use of each resource in the graph is synchronized by the command buffer builder, as
+//! usual.
+//!
+//! # Example
+//!
+//! ```
+//! # use std::sync::Arc;
+//! # use vulkano::buffer::CpuAccessibleBuffer;
+//! # use vulkano::command_buffer::AutoCommandBufferBuilder;
+//! # use vulkano::command_buffer::PrimaryAutoCommandBuffer;
+//! # use vulkano::device::Device;
+//! # let device: Arc<Device> = return;
+//! # let buffer: Arc<CpuAccessibleBuffer<[u8]>> = return;
+//! # let mut builder: AutoCommandBufferBuilder<PrimaryAutoCommandBuffer> = return;
+//! use vulkano::format::Format;
+//! use vulkano::image::ImageLayout;
+//! use vulkano::image::ImageUsage;
+//! use vulkano::image::SampleCount;
+//! use vulkano::render_graph::RenderGraph;
+//! use vulkano::render_graph::TransientImage;
+//! use vulkano::sync::AccessFlags;
+//! use vulkano::sync::PipelineStages;
+//!
+//! let mut graph = RenderGraph::new(device.clone());
+//! let output = graph.import_buffer(buffer.clone());
+//! let image = graph.transient_image(TransientImage {
+//!     dimensions: [1024, 1024],
+//!     array_layers: 1,
+//!     samples: SampleCount::Sample1,
+//!     format: Format::R8G8B8A8Unorm,
+//!     usage: ImageUsage {
+//!         storage: true,
+//!         transfer_source: true,
+//!         ..ImageUsage::none()
+//!     },
+//! });
+//!
+//! graph
+//!     .add_pass("generate", move |builder, resources| {
+//!         let image = resources.transient_image(image);
+//!         // Dispatch a compute shader that writes to `image`.
+//!         Ok(())
+//!     })
+//!     .write_image(
+//!         image,
+//!         PipelineStages {
+//!             compute_shader: true,
+//!             ..PipelineStages::none()
+//!         },
+//!         AccessFlags {
+//!             shader_write: true,
+//!             ..AccessFlags::none()
+//!         },
+//!         ImageLayout::General,
+//!     );
+//!
+//! graph
+//!     .add_pass("copy", move |builder, resources| {
+//!         builder.copy_image_to_buffer(resources.transient_image(image).clone(), buffer)?;
+//!         Ok(())
+//!     })
+//!     .read_image(
+//!         image,
+//!         PipelineStages {
+//!             transfer: true,
+//!             ..PipelineStages::none()
+//!         },
+//!         AccessFlags {
+//!             transfer_read: true,
+//!             ..AccessFlags::none()
+//!         },
+//!         ImageLayout::TransferSrcOptimal,
+//!     )
+//!     .write_buffer(
+//!         output,
+//!         PipelineStages {
+//!             transfer: true,
+//!             ..PipelineStages::none()
+//!         },
+//!         AccessFlags {
+//!             transfer_write: true,
+//!             ..AccessFlags::none()
+//!         },
+//!     );
+//!
+//! graph.record(&mut builder).unwrap();
+//! ```
+
+use self::plan::Access;
+use self::plan::PassInfo;
+use self::plan::PlanError;
+use self::plan::ResourceInfo;
+use crate::buffer::BufferAccess;
+use crate::command_buffer::pool::standard::StandardCommandPoolBuilder;
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::BarrierBuilder;
+use crate::command_buffer::BufferMemoryBarrier;
+use crate::command_buffer::ImageMemoryBarrier;
+use crate::command_buffer::PipelineBarrierError;
+use crate::device::Device;
+use crate::format::Format;
+use crate::image::AttachmentImage;
+use crate::image::ImageAccess;
+use crate::image::ImageCreationError;
+use crate::image::ImageLayout;
+use crate::image::ImageUsage;
+use crate::image::SampleCount;
+use crate::sync::AccessFlags;
+use crate::sync::PipelineStages;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+mod plan;
+
+// Closure that records the commands of a pass.
+type RecordPass<L, P> = Box<
+    dyn FnOnce(
+        &mut AutoCommandBufferBuilder<L, P>,
+        &PassResources,
+    ) -> Result<(), Box<dyn error::Error + Send + Sync>>,
+>;
+
+/// A set of passes to record into a command buffer.
+///
+/// See [the module-level documentation](self).
+pub struct RenderGraph<L, P = StandardCommandPoolBuilder> {
+    device: Arc<Device>,
+    resources: Vec<Resource>,
+    passes: Vec<PassInfo>,
+    records: Vec<(&'static str, RecordPass<L, P>)>,
+}
+
+impl<L, P> RenderGraph<L, P> {
+    /// Builds a new empty `RenderGraph`.
+    #[inline]
+    pub fn new(device: Arc<Device>) -> RenderGraph<L, P> {
+        RenderGraph {
+            device,
+            resources: Vec::new(),
+            passes: Vec::new(),
+            records: Vec::new(),
+        }
+    }
+
+    /// Returns the device of the graph.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Registers a buffer that was created outside of the graph.
+    ///
+    /// Each buffer must only be registered once per graph.
+    #[inline]
+    pub fn import_buffer<B>(&mut self, buffer: B) -> ResourceId
+    where
+        B: BufferAccess + Send + Sync + 'static,
+    {
+        self.resources.push(Resource::Buffer(Arc::new(buffer)));
+        ResourceId(self.resources.len() - 1)
+    }
+
+    /// Registers an image that was created outside of the graph.
+    ///
+    /// Each image must only be registered once per graph.
+    #[inline]
+    pub fn import_image<I>(&mut self, image: I) -> ResourceId
+    where
+        I: ImageAccess + Send + Sync + 'static,
+    {
+        self.resources.push(Resource::Image(Arc::new(image)));
+        ResourceId(self.resources.len() - 1)
+    }
+
+    /// Declares an image that the graph creates, and that only lives during the passes that use
+    /// it. Use `PassResources::transient_image` to access it from a pass.
+    ///
+    /// The content of the image is undefined before the first pass that writes it.
+    #[inline]
+    pub fn transient_image(&mut self, description: TransientImage) -> ResourceId {
+        self.resources.push(Resource::Transient(description));
+        ResourceId(self.resources.len() - 1)
+    }
+
+    /// Adds a pass to the graph. Use the returned `PassBuilder` to declare the resources that it
+    /// uses.
+    ///
+    /// `record` is called when the graph is recorded, unless the pass is removed because its
+    /// results are never used. It must only use the resources of the graph in the way that the
+    /// pass declares.
+    pub fn add_pass<F>(&mut self, name: &'static str, record: F) -> PassBuilder<'_>
+    where
+        F: FnOnce(
+                &mut AutoCommandBufferBuilder<L, P>,
+                &PassResources,
+            ) -> Result<(), Box<dyn error::Error + Send + Sync>>
+            + 'static,
+    {
+        self.passes.push(PassInfo::default());
+        self.records.push((name, Box::new(record)));
+
+        PassBuilder {
+            resources: &self.resources,
+            info: self.passes.last_mut().unwrap(),
+        }
+    }
+
+    /// Records the passes of the graph into `builder`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the resources of the graph don't belong to the same device as `builder`.
+    ///
+    pub fn record(
+        self,
+        builder: &mut AutoCommandBufferBuilder<L, P>,
+    ) -> Result<(), RenderGraphError> {
+        let resource_infos: Vec<_> = self
+            .resources
+            .iter()
+            .map(|resource| ResourceInfo {
+                transient: match resource {
+                    Resource::Transient(description) => Some(*description),
+                    _ => None,
+                },
+            })
+            .collect();
+
+        let plan = plan::plan(
+            &resource_infos,
+            &self.passes,
+            TransientImage::is_compatible_with,
+        )
+        .map_err(|err| match err {
+            PlanError::ConflictingLayouts { pass, .. } => RenderGraphError::ConflictingLayouts {
+                pass: self.records[pass].0,
+            },
+        })?;
+
+        // Create the transient images, with the usages of all the resources that they back.
+        let mut usages = vec![ImageUsage::none(); plan.physical_resources.len()];
+        for (info, physical) in resource_infos.iter().zip(&plan.physical) {
+            if let (Some(description), &Some(physical)) = (info.transient, physical) {
+                usages[physical] = usages[physical] | description.usage;
+            }
+        }
+
+        let physical_images = plan
+            .physical_resources
+            .iter()
+            .zip(usages)
+            .map(|(&resource, usage)| {
+                let description = match self.resources[resource] {
+                    Resource::Transient(ref description) => description,
+                    _ => unreachable!(),
+                };
+
+                AttachmentImage::multisampled_with_usage_with_layers(
+                    self.device.clone(),
+                    description.dimensions,
+                    description.array_layers,
+                    description.samples,
+                    description.format,
+                    usage,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let pass_resources = PassResources {
+            images: plan
+                .physical
+                .iter()
+                .map(|physical| physical.map(|index| physical_images[index].clone()))
+                .collect(),
+        };
+
+        let mut records: Vec<_> = self.records.into_iter().map(Some).collect();
+
+        for pass in plan.passes {
+            let mut barrier = BarrierBuilder::new();
+
+            for b in pass.barriers {
+                match self.resources[b.resource] {
+                    Resource::Buffer(ref buffer) => {
+                        barrier.add_buffer(
+                            buffer.clone(),
+                            BufferMemoryBarrier {
+                                source_stages: b.source_stages,
+                                source_access: b.source_access,
+                                destination_stages: b.destination_stages,
+                                destination_access: b.destination_access,
+                                ..Default::default()
+                            },
+                        );
+                    }
+                    Resource::Image(ref image) => {
+                        barrier.add_image(
+                            image.clone(),
+                            ImageMemoryBarrier {
+                                source_stages: b.source_stages,
+                                source_access: b.source_access,
+                                destination_stages: b.destination_stages,
+                                destination_access: b.destination_access,
+                                old_layout: b.old_layout,
+                                new_layout: b.new_layout,
+                                ..Default::default()
+                            },
+                        );
+                    }
+                    Resource::Transient(_) => {
+                        barrier.add_image(
+                            pass_resources
+                                .transient_image(ResourceId(b.resource))
+                                .clone(),
+                            ImageMemoryBarrier {
+                                source_stages: b.source_stages,
+                                source_access: b.source_access,
+                                destination_stages: b.destination_stages,
+                                destination_access: b.destination_access,
+                                old_layout: b.old_layout,
+                                new_layout: b.new_layout,
+                                ..Default::default()
+                            },
+                        );
+                    }
+                }
+            }
+
+            builder.pipeline_barrier(barrier)?;
+
+            let (name, record) = records[pass.index].take().unwrap();
+            record(builder, &pass_resources)
+                .map_err(|error| RenderGraphError::PassFailed { pass: name, error })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<L, P> fmt::Debug for RenderGraph<L, P> {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("RenderGraph")
+            .field("device", &self.device)
+            .field("num_resources", &self.resources.len())
+            .field(
+                "passes",
+                &self
+                    .records
+                    .iter()
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// Identifies a resource of a `RenderGraph`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResourceId(usize);
+
+/// Description of a transient image of a `RenderGraph`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TransientImage {
+    pub dimensions: [u32; 2],
+    pub array_layers: u32,
+    pub samples: SampleCount,
+    pub format: Format,
+    pub usage: ImageUsage,
+}
+
+impl TransientImage {
+    // Returns true if the two images can be backed by the same image.
+    #[inline]
+    fn is_compatible_with(&self, other: &TransientImage) -> bool {
+        self.dimensions == other.dimensions
+            && self.array_layers == other.array_layers
+            && self.samples == other.samples
+            && self.format == other.format
+    }
+}
+
+enum Resource {
+    Buffer(Arc<dyn BufferAccess + Send + Sync>),
+    Image(Arc<dyn ImageAccess + Send + Sync>),
+    Transient(TransientImage),
+}
+
+/// Declares the resources that a pass uses.
+///
+/// If a pass needs the previous content of a resource that it writes, it must declare both a read
+/// and a write.
+///
+/// # Panic
+///
+/// The methods of this struct panic if the resource doesn't belong to the graph, or if it's not a
+/// buffer, respectively an image.
+pub struct PassBuilder<'a> {
+    resources: &'a [Resource],
+    info: &'a mut PassInfo,
+}
+
+impl<'a> PassBuilder<'a> {
+    /// Declares that the pass reads a buffer.
+    #[inline]
+    pub fn read_buffer(
+        &mut self,
+        buffer: ResourceId,
+        stages: PipelineStages,
+        access: AccessFlags,
+    ) -> &mut Self {
+        self.add_buffer_access(buffer, stages, access, false)
+    }
+
+    /// Declares that the pass writes a buffer.
+    #[inline]
+    pub fn write_buffer(
+        &mut self,
+        buffer: ResourceId,
+        stages: PipelineStages,
+        access: AccessFlags,
+    ) -> &mut Self {
+        self.add_buffer_access(buffer, stages, access, true)
+    }
+
+    /// Declares that the pass reads an image, which must be in `layout`.
+    #[inline]
+    pub fn read_image(
+        &mut self,
+        image: ResourceId,
+        stages: PipelineStages,
+        access: AccessFlags,
+        layout: ImageLayout,
+    ) -> &mut Self {
+        self.add_image_access(image, stages, access, layout, false)
+    }
+
+    /// Declares that the pass writes an image, which must be in `layout`.
+    #[inline]
+    pub fn write_image(
+        &mut self,
+        image: ResourceId,
+        stages: PipelineStages,
+        access: AccessFlags,
+        layout: ImageLayout,
+    ) -> &mut Self {
+        self.add_image_access(image, stages, access, layout, true)
+    }
+
+    /// Declares that the pass must be recorded even if nothing uses the resources that it
+    /// writes.
+    #[inline]
+    pub fn side_effect(&mut self) -> &mut Self {
+        self.info.side_effect = true;
+        self
+    }
+
+    fn add_buffer_access(
+        &mut self,
+        buffer: ResourceId,
+        stages: PipelineStages,
+        access: AccessFlags,
+        write: bool,
+    ) -> &mut Self {
+        assert!(matches!(self.resources[buffer.0], Resource::Buffer(_)));
+        assert!(access.is_compatible_with(&stages));
+
+        self.info.accesses.push(Access {
+            resource: buffer.0,
+            stages,
+            access,
+            layout: ImageLayout::Undefined,
+            read: !write,
+            write,
+        });
+        self
+    }
+
+    fn add_image_access(
+        &mut self,
+        image: ResourceId,
+        stages: PipelineStages,
+        access: AccessFlags,
+        layout: ImageLayout,
+        write: bool,
+    ) -> &mut Self {
+        assert!(!matches!(self.resources[image.0], Resource::Buffer(_)));
+        assert!(access.is_compatible_with(&stages));
+        assert!(layout != ImageLayout::Undefined && layout != ImageLayout::Preinitialized);
+
+        self.info.accesses.push(Access {
+            resource: image.0,
+            stages,
+            access,
+            layout,
+            read: !write,
+            write,
+        });
+        self
+    }
+}
+
+/// The resources that the graph gives to the passes.
+#[derive(Debug)]
+pub struct PassResources {
+    images: Vec<Option<Arc<AttachmentImage>>>,
+}
+
+impl PassResources {
+    /// Returns the image that backs a transient image.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `image` isn't a transient image that is used by a pass of the graph.
+    ///
+    #[inline]
+    pub fn transient_image(&self, image: ResourceId) -> &Arc<AttachmentImage> {
+        self.images[image.0]
+            .as_ref()
+            .expect("the resource isn't a transient image used by the graph")
+    }
+}
+
+/// Error that can happen when recording a `RenderGraph`.
+#[derive(Debug)]
+pub enum RenderGraphError {
+    /// A pass uses the same image with two different layouts.
+    ConflictingLayouts {
+        /// Name of the pass.
+        pass: &'static str,
+    },
+
+    /// Error while creating a transient image.
+    ImageCreationError(ImageCreationError),
+
+    /// Error while adding a pipeline barrier between passes.
+    PipelineBarrierError(PipelineBarrierError),
+
+    /// The closure of a pass returned an error.
+    PassFailed {
+        /// Name of the pass.
+        pass: &'static str,
+        /// The error returned by the closure.
+        error: Box<dyn error::Error + Send + Sync>,
+    },
+}
+
+impl error::Error for RenderGraphError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            RenderGraphError::ImageCreationError(ref err) => Some(err),
+            RenderGraphError::PipelineBarrierError(ref err) => Some(err),
+            RenderGraphError::PassFailed { ref error, .. } => Some(&**error),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RenderGraphError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            RenderGraphError::ConflictingLayouts { pass } => write!(
+                fmt,
+                "the pass `{}` uses the same image with two different layouts",
+                pass
+            ),
+            RenderGraphError::ImageCreationError(_) => {
+                write!(fmt, "error while creating a transient image")
+            }
+            RenderGraphError::PipelineBarrierError(_) => {
+                write!(fmt, "error while adding a pipeline barrier between passes")
+            }
+            RenderGraphError::PassFailed { pass, .. } => {
+                write!(fmt, "the pass `{}` returned an error", pass)
+            }
+        }
+    }
+}
+
+impl From<ImageCreationError> for RenderGraphError {
+    #[inline]
+    fn from(err: ImageCreationError) -> RenderGraphError {
+        RenderGraphError::ImageCreationError(err)
+    }
+}
+
+impl From<PipelineBarrierError> for RenderGraphError {
+    #[inline]
+    fn from(err: PipelineBarrierError) -> RenderGraphError {
+        RenderGraphError::PipelineBarrierError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::command_buffer::AutoCommandBufferBuilder;
+    use crate::command_buffer::CommandBufferUsage;
+    use crate::command_buffer::PrimaryCommandBuffer;
+    use crate::format::ClearValue;
+    use crate::format::Format;
+    use crate::image::ImageLayout;
+    use crate::image::ImageUsage;
+    use crate::image::SampleCount;
+    use crate::render_graph::RenderGraph;
+    use crate::render_graph::RenderGraphError;
+    use crate::render_graph::TransientImage;
+    use crate::sync::AccessFlags;
+    use crate::sync::GpuFuture;
+    use crate::sync::PipelineStages;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn transfer() -> PipelineStages {
+        PipelineStages {
+            transfer: true,
+            ..PipelineStages::none()
+        }
+    }
+
+    fn transfer_write() -> AccessFlags {
+        AccessFlags {
+            transfer_write: true,
+            ..AccessFlags::none()
+        }
+    }
+
+    #[test]
+    fn record_clear_and_copy() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::all(),
+            true,
+            (0..4 * 4 * 4).map(|_| 0u8),
+        )
+        .unwrap();
+        let description = TransientImage {
+            dimensions: [4, 4],
+            array_layers: 1,
+            samples: SampleCount::Sample1,
+            format: Format::R8G8B8A8Unorm,
+            usage: ImageUsage {
+                transfer_source: true,
+                transfer_destination: true,
+                ..ImageUsage::none()
+            },
+        };
+
+        let mut graph = RenderGraph::new(device.clone());
+        let output = graph.import_buffer(buffer.clone());
+        let image = graph.transient_image(description);
+        let unused = graph.transient_image(description);
+        let recorded = Rc::new(RefCell::new(Vec::new()));
+
+        let r = recorded.clone();
+        graph
+            .add_pass("clear", move |builder, resources| {
+                r.borrow_mut().push("clear");
+                builder.clear_color_image(
+                    resources.transient_image(image).clone(),
+                    ClearValue::Float([1.0; 4]),
+                )?;
+                Ok(())
+            })
+            .write_image(
+                image,
+                transfer(),
+                transfer_write(),
+                ImageLayout::TransferDstOptimal,
+            );
+
+        // Nothing reads what this pass writes, so it isn't recorded.
+        let r = recorded.clone();
+        graph
+            .add_pass("unused", move |_, _| {
+                r.borrow_mut().push("unused");
+                Ok(())
+            })
+            .write_image(
+                unused,
+                transfer(),
+                transfer_write(),
+                ImageLayout::TransferDstOptimal,
+            );
+
+        let r = recorded.clone();
+        let destination = buffer.clone();
+        graph
+            .add_pass("copy", move |builder, resources| {
+                r.borrow_mut().push("copy");
+                builder
+                    .copy_image_to_buffer(resources.transient_image(image).clone(), destination)?;
+                Ok(())
+            })
+            .read_image(
+                image,
+                transfer(),
+                AccessFlags {
+                    transfer_read: true,
+                    ..AccessFlags::none()
+                },
+                ImageLayout::TransferSrcOptimal,
+            )
+            .write_buffer(output, transfer(), transfer_write());
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        graph.record(&mut builder).unwrap();
+        assert_eq!(*recorded.borrow(), ["clear", "copy"]);
+
+        builder
+            .build()
+            .unwrap()
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        assert!(buffer.read().unwrap().iter().all(|&byte| byte == 255));
+    }
+
+    #[test]
+    fn record_pass_error() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let mut graph = RenderGraph::new(device.clone());
+        graph
+            .add_pass("fail", |_, _| Err("failed".into()))
+            .side_effect();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        match graph.record(&mut builder) {
+            Err(RenderGraphError::PassFailed { pass: "fail", .. }) => (),
+            _ => panic!(),
+        }
+    }
+}
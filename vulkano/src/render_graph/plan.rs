@@ -0,0 +1,608 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+// Scheduling of the passes of a render graph.
+//
+// This module only works with indices and descriptions of accesses, so that it doesn't need a
+// device.
+
+use crate::image::ImageLayout;
+use crate::sync::AccessFlags;
+use crate::sync::PipelineStages;
+
+// How a pass accesses a resource.
+#[derive(Debug, Copy, Clone)]
+pub(super) struct Access {
+    pub(super) resource: usize,
+    pub(super) stages: PipelineStages,
+    pub(super) access: AccessFlags,
+    // `Undefined` for buffers.
+    pub(super) layout: ImageLayout,
+    // True if the pass needs the previous content of the resource.
+    pub(super) read: bool,
+    // True if the pass modifies the content of the resource.
+    pub(super) write: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(super) struct PassInfo {
+    pub(super) accesses: Vec<Access>,
+    // True if the pass must be executed even if nothing reads what it writes.
+    pub(super) side_effect: bool,
+}
+
+// `transient` is `None` for resources that are provided by the user. Transient resources whose
+// descriptions are compatible can share the same physical resource.
+#[derive(Debug, Clone)]
+pub(super) struct ResourceInfo<K> {
+    pub(super) transient: Option<K>,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Plan {
+    // The passes to execute, in order.
+    pub(super) passes: Vec<PlannedPass>,
+    // For each resource, the physical resource that backs it if it is transient and used.
+    pub(super) physical: Vec<Option<usize>>,
+    // For each physical resource, one of the resources that it backs.
+    pub(super) physical_resources: Vec<usize>,
+}
+
+#[derive(Debug)]
+pub(super) struct PlannedPass {
+    pub(super) index: usize,
+    // The barriers to record before the pass.
+    pub(super) barriers: Vec<Barrier>,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub(super) struct Barrier {
+    pub(super) resource: usize,
+    pub(super) source_stages: PipelineStages,
+    pub(super) source_access: AccessFlags,
+    pub(super) destination_stages: PipelineStages,
+    pub(super) destination_access: AccessFlags,
+    pub(super) old_layout: ImageLayout,
+    pub(super) new_layout: ImageLayout,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(super) enum PlanError {
+    // A pass accesses the same image with two different layouts.
+    ConflictingLayouts { pass: usize, resource: usize },
+}
+
+// State of a resource while going through the passes.
+#[derive(Debug, Copy, Clone)]
+struct State {
+    // Stages and accesses of the last write, or of the last layout transition.
+    write: Option<(PipelineStages, AccessFlags)>,
+    // Stages of the reads since the last write.
+    read_stages: PipelineStages,
+    // Stages and accesses that the last write has been made visible to.
+    visible: (PipelineStages, AccessFlags),
+    layout: ImageLayout,
+}
+
+impl State {
+    #[inline]
+    fn new(access: &Access) -> State {
+        if access.write {
+            State {
+                write: Some((access.stages, access.access)),
+                read_stages: PipelineStages::none(),
+                visible: (PipelineStages::none(), AccessFlags::none()),
+                layout: access.layout,
+            }
+        } else {
+            State {
+                write: None,
+                read_stages: access.stages,
+                visible: (access.stages, access.access),
+                layout: access.layout,
+            }
+        }
+    }
+
+    // Stages and accesses that must be finished before the resource can be written to.
+    #[inline]
+    fn source(&self) -> (PipelineStages, AccessFlags) {
+        match self.write {
+            Some((stages, access)) => (stages | self.read_stages, access),
+            None => (self.read_stages, AccessFlags::none()),
+        }
+    }
+}
+
+// Computes the order of the passes, the barriers between them and the physical resources.
+//
+// `compatible` must be an equivalence relation, which returns true if two transient resources can
+// share the same physical resource.
+pub(super) fn plan<K, C>(
+    resources: &[ResourceInfo<K>],
+    passes: &[PassInfo],
+    compatible: C,
+) -> Result<Plan, PlanError>
+where
+    C: Fn(&K, &K) -> bool,
+{
+    let accesses = passes
+        .iter()
+        .enumerate()
+        .map(|(index, pass)| merge_accesses(index, &pass.accesses))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Go through the passes backwards to find the ones whose results are used.
+    let mut needed = vec![false; passes.len()];
+    let mut live = vec![false; resources.len()];
+
+    for index in (0..passes.len()).rev() {
+        needed[index] = passes[index].side_effect
+            || accesses[index].iter().any(|a| {
+                a.write && (resources[a.resource].transient.is_none() || live[a.resource])
+            });
+
+        if needed[index] {
+            for a in &accesses[index] {
+                live[a.resource] = a.read || (live[a.resource] && !a.write);
+            }
+        }
+    }
+
+    let order = schedule(resources.len(), &accesses, &needed);
+
+    // Lifetimes of the transient resources, as positions in `order`.
+    let mut lifetimes: Vec<Option<(usize, usize)>> = vec![None; resources.len()];
+
+    for (position, &index) in order.iter().enumerate() {
+        for a in &accesses[index] {
+            if resources[a.resource].transient.is_some() {
+                let lifetime = lifetimes[a.resource].get_or_insert((position, position));
+                lifetime.1 = position;
+            }
+        }
+    }
+
+    // Assign a physical resource to each transient resource. A physical resource can be reused
+    // once the lifetime of the previous resource that it backs is over.
+    let mut plan = Plan {
+        physical: vec![None; resources.len()],
+        ..Plan::default()
+    };
+    // For each physical resource, the resource that it currently backs.
+    let mut occupants: Vec<usize> = Vec::new();
+    // For each resource, the resource that the same physical resource backed before it.
+    let mut previous_occupants: Vec<Option<usize>> = vec![None; resources.len()];
+
+    let mut transients: Vec<usize> = (0..resources.len())
+        .filter(|&r| lifetimes[r].is_some())
+        .collect();
+    transients.sort_by_key(|&r| lifetimes[r].unwrap().0);
+
+    for resource in transients {
+        let (first, _) = lifetimes[resource].unwrap();
+        let reusable = occupants.iter().position(|&occupant| {
+            compatible(
+                resources[occupant].transient.as_ref().unwrap(),
+                resources[resource].transient.as_ref().unwrap(),
+            ) && lifetimes[occupant].unwrap().1 < first
+        });
+
+        match reusable {
+            Some(physical) => {
+                previous_occupants[resource] = Some(occupants[physical]);
+                occupants[physical] = resource;
+                plan.physical[resource] = Some(physical);
+            }
+            None => {
+                plan.physical[resource] = Some(occupants.len());
+                plan.physical_resources.push(resource);
+                occupants.push(resource);
+            }
+        }
+    }
+
+    // Compute the barriers.
+    let mut states: Vec<Option<State>> = vec![None; resources.len()];
+
+    for &index in &order {
+        let mut barriers = Vec::new();
+
+        for a in &accesses[index] {
+            let state = match states[a.resource] {
+                Some(ref mut state) => state,
+                None => {
+                    // A physical resource that is reused must wait for the previous resource
+                    // that it backs. Its content is discarded.
+                    if let Some(previous) = previous_occupants[a.resource] {
+                        let (source_stages, source_access) = states[previous].unwrap().source();
+                        barriers.push(Barrier {
+                            resource: a.resource,
+                            source_stages,
+                            source_access,
+                            destination_stages: a.stages,
+                            destination_access: a.access,
+                            old_layout: ImageLayout::Undefined,
+                            new_layout: a.layout,
+                        });
+                    }
+
+                    // The first use of other resources is synchronized by the command buffer
+                    // builder.
+                    states[a.resource] = Some(State::new(a));
+                    continue;
+                }
+            };
+
+            if a.write {
+                let (source_stages, source_access) = state.source();
+                barriers.push(Barrier {
+                    resource: a.resource,
+                    source_stages,
+                    source_access,
+                    destination_stages: a.stages,
+                    destination_access: a.access,
+                    old_layout: state.layout,
+                    new_layout: a.layout,
+                });
+                *state = State::new(a);
+            } else if state.layout != a.layout {
+                // The layout transition must be finished before any other access.
+                let (source_stages, source_access) = state.source();
+                barriers.push(Barrier {
+                    resource: a.resource,
+                    source_stages,
+                    source_access,
+                    destination_stages: a.stages,
+                    destination_access: a.access,
+                    old_layout: state.layout,
+                    new_layout: a.layout,
+                });
+                *state = State {
+                    write: Some((a.stages, AccessFlags::none())),
+                    read_stages: a.stages,
+                    visible: (a.stages, a.access),
+                    layout: a.layout,
+                };
+            } else if let Some((write_stages, write_access)) = state.write {
+                if !includes(state.visible, (a.stages, a.access)) {
+                    barriers.push(Barrier {
+                        resource: a.resource,
+                        source_stages: write_stages,
+                        source_access: write_access,
+                        destination_stages: a.stages,
+                        destination_access: a.access,
+                        old_layout: state.layout,
+                        new_layout: a.layout,
+                    });
+                    state.visible.0 |= a.stages;
+                    state.visible.1 |= a.access;
+                }
+                state.read_stages |= a.stages;
+            } else {
+                // Reads that follow reads don't need to be synchronized.
+                state.read_stages |= a.stages;
+            }
+        }
+
+        plan.passes.push(PlannedPass { index, barriers });
+    }
+
+    Ok(plan)
+}
+
+// Orders the needed passes so that each pass comes after the passes whose results it uses, and
+// after the passes that use what it overwrites.
+//
+// Among the passes whose dependencies are scheduled, the ones that don't depend on the pass that
+// was just scheduled go first, so that the GPU has other work to do while the barrier between two
+// dependent passes waits. Ties are broken by the order in which the passes were added.
+fn schedule(num_resources: usize, accesses: &[Vec<Access>], needed: &[bool]) -> Vec<usize> {
+    // For each pass, the passes that must be executed before it.
+    let mut dependencies: Vec<Vec<usize>> = vec![Vec::new(); accesses.len()];
+    // For each resource, the last pass that writes it and the passes that read it since.
+    let mut writers: Vec<Option<usize>> = vec![None; num_resources];
+    let mut readers: Vec<Vec<usize>> = vec![Vec::new(); num_resources];
+
+    for (index, pass_accesses) in accesses.iter().enumerate() {
+        if !needed[index] {
+            continue;
+        }
+
+        for a in pass_accesses {
+            if let Some(writer) = writers[a.resource] {
+                dependencies[index].push(writer);
+            }
+
+            if a.write {
+                dependencies[index].append(&mut readers[a.resource]);
+                writers[a.resource] = Some(index);
+            } else {
+                readers[a.resource].push(index);
+            }
+        }
+
+        dependencies[index].retain(|&d| d != index);
+    }
+
+    let mut scheduled = vec![false; accesses.len()];
+    let mut order: Vec<usize> = Vec::new();
+    let num_needed = needed.iter().filter(|&&n| n).count();
+
+    while order.len() < num_needed {
+        let last = order.last().copied();
+        // Dependencies always point to passes that were added before, so a pass is always ready.
+        let next = (0..accesses.len())
+            .filter(|&i| {
+                needed[i] && !scheduled[i] && dependencies[i].iter().all(|&d| scheduled[d])
+            })
+            .min_by_key(|&i| (dependencies[i].iter().any(|&d| Some(d) == last), i))
+            .unwrap();
+        scheduled[next] = true;
+        order.push(next);
+    }
+
+    order
+}
+
+// Merges the accesses of a pass to the same resource.
+fn merge_accesses(pass: usize, accesses: &[Access]) -> Result<Vec<Access>, PlanError> {
+    let mut merged: Vec<Access> = Vec::with_capacity(accesses.len());
+
+    for a in accesses {
+        match merged.iter_mut().find(|m| m.resource == a.resource) {
+            Some(m) => {
+                if m.layout != a.layout {
+                    return Err(PlanError::ConflictingLayouts {
+                        pass,
+                        resource: a.resource,
+                    });
+                }
+
+                m.stages |= a.stages;
+                m.access |= a.access;
+                m.read |= a.read;
+                m.write |= a.write;
+            }
+            None => merged.push(*a),
+        }
+    }
+
+    Ok(merged)
+}
+
+// Returns true if the first scope includes the second one.
+#[inline]
+fn includes(scope: (PipelineStages, AccessFlags), other: (PipelineStages, AccessFlags)) -> bool {
+    ash::vk::PipelineStageFlags::from(scope.0).contains(other.0.into())
+        && ash::vk::AccessFlags::from(scope.1).contains(other.1.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compute() -> PipelineStages {
+        PipelineStages {
+            compute_shader: true,
+            ..PipelineStages::none()
+        }
+    }
+
+    fn fragment() -> PipelineStages {
+        PipelineStages {
+            fragment_shader: true,
+            ..PipelineStages::none()
+        }
+    }
+
+    fn write(resource: usize) -> Access {
+        Access {
+            resource,
+            stages: compute(),
+            access: AccessFlags {
+                shader_write: true,
+                ..AccessFlags::none()
+            },
+            layout: ImageLayout::General,
+            read: false,
+            write: true,
+        }
+    }
+
+    fn read(resource: usize, stages: PipelineStages, layout: ImageLayout) -> Access {
+        Access {
+            resource,
+            stages,
+            access: AccessFlags {
+                shader_read: true,
+                ..AccessFlags::none()
+            },
+            layout,
+            read: true,
+            write: false,
+        }
+    }
+
+    fn pass(accesses: Vec<Access>) -> PassInfo {
+        PassInfo {
+            accesses,
+            side_effect: false,
+        }
+    }
+
+    #[test]
+    fn culls_unused_passes() {
+        // 0 is a persistent resource, 1 and 2 are transient.
+        let resources = [
+            ResourceInfo { transient: None },
+            ResourceInfo { transient: Some(0) },
+            ResourceInfo { transient: Some(0) },
+        ];
+        let passes = [
+            pass(vec![write(1)]),
+            pass(vec![write(2)]),
+            pass(vec![read(1, compute(), ImageLayout::General), write(0)]),
+        ];
+
+        let scheduled = plan(&resources, &passes, PartialEq::eq).unwrap();
+        let order: Vec<_> = scheduled.passes.iter().map(|p| p.index).collect();
+        assert_eq!(order, [0, 2]);
+        assert_eq!(scheduled.physical, [None, Some(0), None]);
+    }
+
+    #[test]
+    fn read_after_write_barrier() {
+        let resources = [
+            ResourceInfo { transient: None },
+            ResourceInfo { transient: Some(0) },
+        ];
+        let passes = [
+            pass(vec![write(1)]),
+            pass(vec![
+                read(1, fragment(), ImageLayout::ShaderReadOnlyOptimal),
+                write(0),
+            ]),
+            pass(vec![
+                read(1, fragment(), ImageLayout::ShaderReadOnlyOptimal),
+                write(0),
+            ]),
+        ];
+
+        let scheduled = plan(&resources, &passes, PartialEq::eq).unwrap();
+        assert!(scheduled.passes[0].barriers.is_empty());
+
+        let barrier = scheduled.passes[1]
+            .barriers
+            .iter()
+            .find(|b| b.resource == 1)
+            .unwrap();
+        assert_eq!(barrier.source_stages, compute());
+        assert_eq!(barrier.destination_stages, fragment());
+        assert_eq!(barrier.old_layout, ImageLayout::General);
+        assert_eq!(barrier.new_layout, ImageLayout::ShaderReadOnlyOptimal);
+
+        // The second read is already covered, only the write after write needs a barrier.
+        let resources: Vec<_> = scheduled.passes[2]
+            .barriers
+            .iter()
+            .map(|b| b.resource)
+            .collect();
+        assert_eq!(resources, [0]);
+    }
+
+    #[test]
+    fn aliasing() {
+        let resources = [
+            ResourceInfo { transient: None },
+            ResourceInfo { transient: Some(0) },
+            ResourceInfo { transient: Some(0) },
+            ResourceInfo { transient: Some(1) },
+        ];
+        let passes = [
+            pass(vec![write(1)]),
+            pass(vec![read(1, compute(), ImageLayout::General), write(2)]),
+            pass(vec![read(2, compute(), ImageLayout::General), write(3)]),
+            pass(vec![read(3, compute(), ImageLayout::General), write(0)]),
+        ];
+
+        let scheduled = plan(&resources, &passes, PartialEq::eq).unwrap();
+        // 1 and 2 are alive at the same time, but 3 has a different description.
+        assert_eq!(scheduled.physical, [None, Some(0), Some(1), Some(2)]);
+        assert_eq!(scheduled.physical_resources, [1, 2, 3]);
+
+        let passes = [
+            pass(vec![write(1)]),
+            pass(vec![read(1, compute(), ImageLayout::General), write(3)]),
+            pass(vec![read(3, compute(), ImageLayout::General), write(2)]),
+            pass(vec![read(2, compute(), ImageLayout::General), write(0)]),
+        ];
+
+        let scheduled = plan(&resources, &passes, PartialEq::eq).unwrap();
+        // 2 reuses the physical resource of 1, and waits for its last use.
+        assert_eq!(scheduled.physical, [None, Some(0), Some(0), Some(1)]);
+        let barrier = scheduled.passes[2]
+            .barriers
+            .iter()
+            .find(|b| b.resource == 2)
+            .unwrap();
+        assert_eq!(barrier.source_stages, compute());
+        assert_eq!(barrier.old_layout, ImageLayout::Undefined);
+    }
+
+    #[test]
+    fn dependency_order() {
+        let resources = [
+            ResourceInfo { transient: None },
+            ResourceInfo { transient: None },
+            ResourceInfo { transient: Some(0) },
+        ];
+        let passes = [
+            pass(vec![write(2)]),
+            pass(vec![read(2, compute(), ImageLayout::General), write(0)]),
+            pass(vec![write(1)]),
+            pass(vec![read(0, compute(), ImageLayout::General), write(1)]),
+        ];
+
+        let scheduled = plan(&resources, &passes, PartialEq::eq).unwrap();
+        // 2 doesn't depend on anything, so it is moved between 0 and 1. 3 overwrites what 2
+        // writes, so it stays after it.
+        let order: Vec<_> = scheduled.passes.iter().map(|p| p.index).collect();
+        assert_eq!(order, [0, 2, 1, 3]);
+    }
+
+    #[test]
+    fn compatible_aliasing() {
+        // 1 and 2 only differ by something that `compatible` ignores.
+        let resources = [
+            ResourceInfo { transient: None },
+            ResourceInfo {
+                transient: Some((0, 1)),
+            },
+            ResourceInfo {
+                transient: Some((0, 2)),
+            },
+        ];
+        let passes = [
+            pass(vec![write(1)]),
+            pass(vec![read(1, compute(), ImageLayout::General), write(0)]),
+            pass(vec![read(0, compute(), ImageLayout::General), write(2)]),
+            pass(vec![read(2, compute(), ImageLayout::General), write(0)]),
+        ];
+
+        let scheduled = plan(&resources, &passes, |a: &(u32, u32), b: &(u32, u32)| {
+            a.0 == b.0
+        })
+        .unwrap();
+        assert_eq!(scheduled.physical, [None, Some(0), Some(0)]);
+
+        let scheduled = plan(&resources, &passes, PartialEq::eq).unwrap();
+        assert_eq!(scheduled.physical, [None, Some(0), Some(1)]);
+    }
+
+    #[test]
+    fn conflicting_layouts() {
+        let resources = [ResourceInfo::<u32> { transient: None }];
+        let passes = [PassInfo {
+            accesses: vec![
+                read(0, fragment(), ImageLayout::ShaderReadOnlyOptimal),
+                read(0, compute(), ImageLayout::General),
+            ],
+            side_effect: true,
+        }];
+
+        assert_eq!(
+            plan(&resources, &passes, PartialEq::eq).unwrap_err(),
+            PlanError::ConflictingLayouts {
+                pass: 0,
+                resource: 0
+            }
+        );
+    }
+}
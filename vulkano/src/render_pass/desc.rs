@@ -89,6 +89,21 @@ impl RenderPassDesc {
         &self.dependencies
     }
 
+    /// Returns the self-dependencies of the given subpass, i.e. the dependencies whose source and
+    /// destination are both `subpass`.
+    ///
+    /// A pipeline barrier can only be recorded inside a subpass if that subpass has a
+    /// self-dependency that is a superset of the barrier.
+    #[inline]
+    pub fn self_dependencies(
+        &self,
+        subpass: usize,
+    ) -> impl Iterator<Item = &SubpassDependencyDesc> + '_ {
+        self.dependencies
+            .iter()
+            .filter(move |dep| dep.is_self_dependency() && dep.source_subpass == subpass)
+    }
+
     // Returns the multiview configuration of the description.
     #[inline]
     pub fn multiview(&self) -> &Option<MultiviewDesc> {
@@ -146,6 +161,10 @@ impl RenderPassDesc {
 
     /// Returns `true` if this description is compatible with the other description,
     /// as defined in the `Render Pass Compatibility` section of the Vulkan specs.
+    ///
+    /// Two compatible render passes only differ in their load and store operations, and in the
+    /// initial, final and subpass image layouts of their attachments. Framebuffers and graphics
+    /// pipelines created for one of them can be used with the other.
     // TODO: return proper error
     pub fn is_compatible_with_desc(&self, other: &RenderPassDesc) -> bool {
         if self.attachments().len() != other.attachments().len() {
@@ -158,9 +177,87 @@ impl RenderPassDesc {
             }
         }
 
-        return true;
+        if self.subpasses.len() != other.subpasses.len() {
+            return false;
+        }
 
-        // FIXME: finish
+        // As a special case, the resolve attachments are ignored if both render passes have a
+        // single subpass.
+        let check_resolve = self.subpasses.len() > 1;
+
+        for (my_pass, other_pass) in self.subpasses.iter().zip(other.subpasses.iter()) {
+            if !self.are_references_compatible(
+                other,
+                &my_pass.color_attachments,
+                &other_pass.color_attachments,
+            ) || !self.are_references_compatible(
+                other,
+                &my_pass.input_attachments,
+                &other_pass.input_attachments,
+            ) || !self.are_references_compatible(
+                other,
+                my_pass.depth_stencil.as_slice(),
+                other_pass.depth_stencil.as_slice(),
+            ) {
+                return false;
+            }
+
+            if check_resolve
+                && !self.are_references_compatible(
+                    other,
+                    &my_pass.resolve_attachments,
+                    &other_pass.resolve_attachments,
+                )
+            {
+                return false;
+            }
+
+            if my_pass.preserve_attachments != other_pass.preserve_attachments {
+                return false;
+            }
+        }
+
+        if self.dependencies.len() != other.dependencies.len()
+            || !self
+                .dependencies
+                .iter()
+                .zip(other.dependencies.iter())
+                .all(|(my_dep, other_dep)| my_dep.is_identical_to(other_dep))
+        {
+            return false;
+        }
+
+        match (&self.multiview, &other.multiview) {
+            (None, None) => true,
+            (Some(my_multiview), Some(other_multiview)) => {
+                my_multiview.view_masks == other_multiview.view_masks
+                    && my_multiview.correlation_masks == other_multiview.correlation_masks
+                    && my_multiview.view_offsets == other_multiview.view_offsets
+            }
+            _ => false,
+        }
+    }
+
+    // Returns true if two arrays of attachment references are compatible. If the arrays have
+    // different lengths, the missing references are treated as unused.
+    fn are_references_compatible(
+        &self,
+        other: &RenderPassDesc,
+        mine: &[(usize, ImageLayout)],
+        others: &[(usize, ImageLayout)],
+    ) -> bool {
+        let len = mine.len().max(others.len());
+
+        (0..len).all(|i| {
+            match (
+                mine.get(i).map(|&(a, _)| &self.attachments[a]),
+                others.get(i).map(|&(a, _)| &other.attachments[a]),
+            ) {
+                (Some(my_atch), Some(other_atch)) => my_atch.is_compatible_with(other_atch),
+                (None, None) => true,
+                _ => false,
+            }
+        })
     }
 }
 
@@ -293,6 +390,31 @@ pub struct SubpassDependencyDesc {
     pub by_region: bool,
 }
 
+impl SubpassDependencyDesc {
+    // Returns true if both dependencies have the same subpasses, stages, accesses and flags.
+    pub(crate) fn is_identical_to(&self, other: &SubpassDependencyDesc) -> bool {
+        self.source_subpass == other.source_subpass
+            && self.destination_subpass == other.destination_subpass
+            && self.source_stages == other.source_stages
+            && self.destination_stages == other.destination_stages
+            && ash::vk::AccessFlags::from(self.source_access)
+                == ash::vk::AccessFlags::from(other.source_access)
+            && ash::vk::AccessFlags::from(self.destination_access)
+                == ash::vk::AccessFlags::from(other.destination_access)
+            && self.by_region == other.by_region
+    }
+
+    /// Returns true if the source and destination subpasses are the same.
+    ///
+    /// Self-dependencies are needed to use pipeline barriers inside a subpass, for example when a
+    /// subpass reads from an attachment as an input attachment while also writing to it.
+    #[inline]
+    pub fn is_self_dependency(&self) -> bool {
+        self.source_subpass == self.destination_subpass
+            && self.source_subpass as u32 != ash::vk::SUBPASS_EXTERNAL
+    }
+}
+
 /// Describes what the implementation should do with an attachment after all the subpasses have
 /// completed.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -412,6 +534,7 @@ pub enum ResolveMode {
 }
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ResolveModes {
     pub none: bool,
     pub sample_zero: bool,
@@ -432,3 +555,112 @@ impl From<ash::vk::ResolveModeFlags> for ResolveModes {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::format::Format;
+    use crate::image::ImageLayout;
+    use crate::image::SampleCount;
+    use crate::render_pass::AttachmentDesc;
+    use crate::render_pass::LoadOp;
+    use crate::render_pass::RenderPassDesc;
+    use crate::render_pass::StoreOp;
+    use crate::render_pass::SubpassDesc;
+
+    fn color_attachment(format: Format, load: LoadOp, layout: ImageLayout) -> AttachmentDesc {
+        AttachmentDesc {
+            format,
+            samples: SampleCount::Sample1,
+            load,
+            store: StoreOp::Store,
+            stencil_load: LoadOp::DontCare,
+            stencil_store: StoreOp::DontCare,
+            initial_layout: layout,
+            final_layout: layout,
+        }
+    }
+
+    fn single_pass(attachments: Vec<AttachmentDesc>, color: Vec<usize>) -> RenderPassDesc {
+        RenderPassDesc::new(
+            attachments,
+            vec![SubpassDesc {
+                color_attachments: color
+                    .into_iter()
+                    .map(|a| (a, ImageLayout::ColorAttachmentOptimal))
+                    .collect(),
+                depth_stencil: None,
+                input_attachments: vec![],
+                resolve_attachments: vec![],
+                preserve_attachments: vec![],
+            }],
+            vec![],
+        )
+    }
+
+    #[test]
+    fn compatible_ignores_load_ops_and_layouts() {
+        let a = single_pass(
+            vec![color_attachment(
+                Format::R8G8B8A8Unorm,
+                LoadOp::Clear,
+                ImageLayout::ColorAttachmentOptimal,
+            )],
+            vec![0],
+        );
+        let b = single_pass(
+            vec![color_attachment(
+                Format::R8G8B8A8Unorm,
+                LoadOp::Load,
+                ImageLayout::General,
+            )],
+            vec![0],
+        );
+
+        assert!(a.is_compatible_with_desc(&b));
+        assert!(b.is_compatible_with_desc(&a));
+    }
+
+    #[test]
+    fn incompatible_formats() {
+        let a = single_pass(
+            vec![color_attachment(
+                Format::R8G8B8A8Unorm,
+                LoadOp::Clear,
+                ImageLayout::ColorAttachmentOptimal,
+            )],
+            vec![0],
+        );
+        let b = single_pass(
+            vec![color_attachment(
+                Format::B8G8R8A8Srgb,
+                LoadOp::Clear,
+                ImageLayout::ColorAttachmentOptimal,
+            )],
+            vec![0],
+        );
+
+        assert!(!a.is_compatible_with_desc(&b));
+    }
+
+    #[test]
+    fn incompatible_references() {
+        let attachments = vec![
+            color_attachment(
+                Format::R8G8B8A8Unorm,
+                LoadOp::Clear,
+                ImageLayout::ColorAttachmentOptimal,
+            ),
+            color_attachment(
+                Format::R16G16B16A16Sfloat,
+                LoadOp::Clear,
+                ImageLayout::ColorAttachmentOptimal,
+            ),
+        ];
+        let a = single_pass(attachments.clone(), vec![0, 1]);
+        let b = single_pass(attachments.clone(), vec![1, 0]);
+        let c = single_pass(attachments, vec![0]);
+
+        assert!(!a.is_compatible_with_desc(&b));
+        assert!(!a.is_compatible_with_desc(&c));
+    }
+}
@@ -117,6 +117,20 @@ impl Framebuffer<()> {
     }
 }
 
+impl Framebuffer<Vec<Arc<dyn ImageViewAbstract + Send + Sync>>> {
+    // Starts building a framebuffer whose attachments are stored in a `Vec`.
+    pub(crate) fn start_dynamic(
+        render_pass: Arc<RenderPass>,
+    ) -> FramebufferBuilder<Vec<Arc<dyn ImageViewAbstract + Send + Sync>>> {
+        FramebufferBuilder {
+            render_pass,
+            raw_ids: SmallVec::new(),
+            dimensions: FramebufferBuilderDimensions::AutoIdentical(None),
+            attachments: Vec::new(),
+        }
+    }
+}
+
 /// Prototype of a framebuffer.
 pub struct FramebufferBuilder<A> {
     render_pass: Arc<RenderPass>,
@@ -160,6 +174,28 @@ where
     where
         T: ImageViewAbstract,
     {
+        let (raw_ids, dimensions) = self.check_attachment(&attachment)?;
+
+        Ok(FramebufferBuilder {
+            render_pass: self.render_pass,
+            raw_ids,
+            dimensions,
+            attachments: (self.attachments, attachment),
+        })
+    }
+
+    // Checks whether `attachment` can be appended to the prototype, and returns the new list of
+    // raw image views and dimensions if so.
+    fn check_attachment(
+        &self,
+        attachment: &dyn ImageViewAbstract,
+    ) -> Result<
+        (
+            SmallVec<[ash::vk::ImageView; 8]>,
+            FramebufferBuilderDimensions,
+        ),
+        FramebufferCreationError,
+    > {
         if self.raw_ids.len() >= self.render_pass.desc().attachments().len() {
             return Err(FramebufferCreationError::AttachmentsCountMismatch {
                 expected: self.render_pass.desc().attachments().len(),
@@ -167,7 +203,7 @@ where
             });
         }
 
-        match ensure_image_view_compatible(self.render_pass.desc(), self.raw_ids.len(), &attachment)
+        match ensure_image_view_compatible(self.render_pass.desc(), self.raw_ids.len(), attachment)
         {
             Ok(()) => (),
             Err(err) => return Err(FramebufferCreationError::IncompatibleAttachment(err)),
@@ -224,15 +260,10 @@ where
             }
         };
 
-        let mut raw_ids = self.raw_ids;
+        let mut raw_ids = self.raw_ids.clone();
         raw_ids.push(attachment.inner().internal_object());
 
-        Ok(FramebufferBuilder {
-            render_pass: self.render_pass,
-            raw_ids,
-            dimensions,
-            attachments: (self.attachments, attachment),
-        })
+        Ok((raw_ids, dimensions))
     }
 
     /// Turns this builder into a `FramebufferBuilder<Rp, Box<AttachmentsList>>`.
@@ -291,7 +322,7 @@ where
             }
         }
 
-        let mut layers = 1;
+        let mut layers = dimensions[2];
 
         if let Some(multiview) = self.render_pass.desc().multiview() {
             // There needs to be at least as many layers in the framebuffer
@@ -382,6 +413,26 @@ impl<A> Framebuffer<A> {
     pub fn render_pass(&self) -> &Arc<RenderPass> {
         &self.render_pass
     }
+
+    /// Returns the list of attachments of this framebuffer.
+    #[inline]
+    pub fn attachments(&self) -> &A {
+        &self.resources
+    }
+}
+
+impl FramebufferBuilder<Vec<Arc<dyn ImageViewAbstract + Send + Sync>>> {
+    // Same as `add`, but for a builder whose attachments are stored in a `Vec`.
+    pub(crate) fn push(
+        &mut self,
+        attachment: Arc<dyn ImageViewAbstract + Send + Sync>,
+    ) -> Result<(), FramebufferCreationError> {
+        let (raw_ids, dimensions) = self.check_attachment(&*attachment)?;
+        self.raw_ids = raw_ids;
+        self.dimensions = dimensions;
+        self.attachments.push(attachment);
+        Ok(())
+    }
 }
 
 /// Trait for objects that contain a Vulkan framebuffer object.
@@ -604,6 +655,10 @@ mod tests {
     use crate::format::Format;
     use crate::image::attachment::AttachmentImage;
     use crate::image::view::ImageView;
+    use crate::image::ImageCreateFlags;
+    use crate::image::ImageDimensions;
+    use crate::image::ImageUsage;
+    use crate::image::StorageImage;
     use crate::render_pass::Framebuffer;
     use crate::render_pass::FramebufferCreationError;
     use crate::render_pass::RenderPass;
@@ -862,6 +917,58 @@ mod tests {
         }
     }
 
+    #[test]
+    fn layered_attachment() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let render_pass = Arc::new(
+            single_pass_renderpass!(device.clone(),
+                attachments: {
+                    color: {
+                        load: Clear,
+                        store: DontCare,
+                        format: Format::R8G8B8A8Unorm,
+                        samples: 1,
+                    }
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {}
+                }
+            )
+            .unwrap(),
+        );
+
+        let image = StorageImage::with_usage(
+            device.clone(),
+            ImageDimensions::Dim2d {
+                width: 256,
+                height: 128,
+                array_layers: 2,
+            },
+            Format::R8G8B8A8Unorm,
+            ImageUsage {
+                color_attachment: true,
+                ..ImageUsage::none()
+            },
+            ImageCreateFlags::none(),
+            Some(queue.family()),
+        )
+        .unwrap();
+        let view = ImageView::new(image).unwrap();
+
+        let fb = Framebuffer::start(render_pass)
+            .add(view)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        match (fb.width(), fb.height(), fb.layers()) {
+            (256, 128, 2) => (),
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn not_enough_attachments() {
         let (device, _) = gfx_dev_and_queue!();
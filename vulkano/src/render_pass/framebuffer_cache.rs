@@ -0,0 +1,364 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::image::view::ImageViewAbstract;
+use crate::render_pass::Framebuffer;
+use crate::render_pass::FramebufferCreationError;
+use crate::render_pass::RenderPass;
+use fnv::FnvHashMap;
+use smallvec::SmallVec;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// Framebuffer whose attachments are stored in a `Vec`, as returned by a `FramebufferCache`.
+pub type CachedFramebuffer = Framebuffer<Vec<Arc<dyn ImageViewAbstract + Send + Sync>>>;
+
+/// Cache of framebuffers, keyed on a render pass and a list of image views.
+///
+/// Applications that render to a swapchain typically create a framebuffer for each swapchain
+/// image and each render pass, and have to recreate them whenever the swapchain is recreated.
+/// A `FramebufferCache` takes care of this: calling `get` with the same render pass and image
+/// views returns the framebuffer that was created the first time.
+///
+/// The entries are keyed on the identity of the `Arc`s of the render pass and of the image views,
+/// which the cached framebuffers keep alive. When the rest of the application drops a render pass
+/// or an image view, the framebuffers that use it can't be returned anymore, and are destroyed the
+/// next time `get` creates a framebuffer, or when `cleanup` is called.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use vulkano::image::view::ImageViewAbstract;
+/// # use vulkano::render_pass::RenderPass;
+/// use vulkano::render_pass::FramebufferCache;
+///
+/// # let render_pass: Arc<RenderPass> = return;
+/// # let view: Arc<dyn ImageViewAbstract + Send + Sync> = return;
+/// let cache = FramebufferCache::new();
+///
+/// let first = cache.get(render_pass.clone(), vec![view.clone()]).unwrap();
+/// let second = cache.get(render_pass.clone(), vec![view.clone()]).unwrap();
+/// assert!(Arc::ptr_eq(&first, &second));
+/// ```
+#[derive(Default)]
+pub struct FramebufferCache {
+    framebuffers: Mutex<FnvHashMap<FramebufferCacheKey, Arc<CachedFramebuffer>>>,
+}
+
+// Addresses of the render pass and of the image views. They can't be reused by other objects
+// while the entry exists, since the framebuffer keeps them alive.
+type FramebufferCacheKey = (usize, SmallVec<[usize; 8]>);
+
+impl FramebufferCache {
+    /// Builds a new empty cache.
+    #[inline]
+    pub fn new() -> FramebufferCache {
+        FramebufferCache {
+            framebuffers: Mutex::new(FnvHashMap::default()),
+        }
+    }
+
+    /// Returns the framebuffer for the given render pass and attachments, creating it if it
+    /// isn't in the cache yet.
+    ///
+    /// The dimensions of the framebuffer are the intersection of the dimensions of the
+    /// attachments, which all must be identical. Attachments must be in the same order as the
+    /// one defined in the render pass.
+    pub fn get(
+        &self,
+        render_pass: Arc<RenderPass>,
+        attachments: Vec<Arc<dyn ImageViewAbstract + Send + Sync>>,
+    ) -> Result<Arc<CachedFramebuffer>, FramebufferCreationError> {
+        let key = (
+            Arc::as_ptr(&render_pass) as usize,
+            attachments
+                .iter()
+                .map(|view| Arc::as_ptr(view) as *const () as usize)
+                .collect(),
+        );
+
+        let mut framebuffers = self.framebuffers.lock().unwrap();
+
+        if let Some(framebuffer) = framebuffers.get(&key) {
+            return Ok(framebuffer.clone());
+        }
+
+        let mut builder = Framebuffer::start_dynamic(render_pass);
+        for attachment in attachments {
+            builder.push(attachment)?;
+        }
+        let framebuffer = Arc::new(builder.build()?);
+
+        purge_unreachable(&mut framebuffers);
+        framebuffers.insert(key, framebuffer.clone());
+        Ok(framebuffer)
+    }
+
+    /// Returns the number of framebuffers in the cache.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.framebuffers.lock().unwrap().len()
+    }
+
+    /// Returns true if the cache doesn't contain any framebuffer.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.framebuffers.lock().unwrap().is_empty()
+    }
+
+    /// Removes the framebuffers that are no longer reachable from outside the cache.
+    ///
+    /// A framebuffer is removed if it isn't used outside of the cache, and if its render pass or
+    /// one of its attachments is only referenced by the cached framebuffers. Such a framebuffer
+    /// can't be returned by `get` anymore.
+    #[inline]
+    pub fn cleanup(&self) {
+        purge_unreachable(&mut self.framebuffers.lock().unwrap());
+    }
+
+    /// Removes all the framebuffers from the cache.
+    ///
+    /// Framebuffers that are still in use elsewhere are kept alive by their other owners.
+    #[inline]
+    pub fn clear(&self) {
+        self.framebuffers.lock().unwrap().clear();
+    }
+}
+
+// Removes the framebuffers that aren't used outside of the cache, and whose render pass or one of
+// whose attachments is only referenced by the cached framebuffers.
+fn purge_unreachable(framebuffers: &mut FnvHashMap<FramebufferCacheKey, Arc<CachedFramebuffer>>) {
+    // Each cached framebuffer holds a reference to its render pass and to each of its attachments,
+    // which are the objects of its key. Objects shared between entries, such as the render pass
+    // or a depth buffer, must not keep each other's entries alive.
+    let mut cache_references: FnvHashMap<usize, usize> = FnvHashMap::default();
+    for (render_pass, views) in framebuffers.keys() {
+        for &ptr in Some(render_pass).into_iter().chain(views) {
+            *cache_references.entry(ptr).or_insert(0) += 1;
+        }
+    }
+
+    let used_outside = |ptr: usize, strong_count: usize| strong_count > cache_references[&ptr];
+
+    framebuffers.retain(|(render_pass, views), framebuffer| {
+        if Arc::strong_count(framebuffer) > 1 {
+            return true;
+        }
+
+        used_outside(*render_pass, Arc::strong_count(framebuffer.render_pass()))
+            && views
+                .iter()
+                .zip(framebuffer.attachments())
+                .all(|(&ptr, view)| used_outside(ptr, Arc::strong_count(view)))
+    });
+}
+
+impl fmt::Debug for FramebufferCache {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("FramebufferCache")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::Format;
+    use crate::image::attachment::AttachmentImage;
+    use crate::image::view::ImageView;
+    use crate::image::view::ImageViewAbstract;
+    use crate::render_pass::FramebufferCache;
+    use crate::render_pass::RenderPass;
+    use std::sync::Arc;
+
+    #[test]
+    fn reuses_framebuffer() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let render_pass = Arc::new(
+            single_pass_renderpass!(device.clone(),
+                attachments: {
+                    color: {
+                        load: Clear,
+                        store: DontCare,
+                        format: Format::R8G8B8A8Unorm,
+                        samples: 1,
+                    }
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {}
+                }
+            )
+            .unwrap(),
+        );
+
+        let view: Arc<dyn ImageViewAbstract + Send + Sync> = ImageView::new(
+            AttachmentImage::new(device.clone(), [1024, 768], Format::R8G8B8A8Unorm).unwrap(),
+        )
+        .unwrap();
+
+        let cache = FramebufferCache::new();
+        let first = cache.get(render_pass.clone(), vec![view.clone()]).unwrap();
+        let second = cache.get(render_pass.clone(), vec![view.clone()]).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(cache.len(), 1);
+
+        drop((first, second));
+        cache.cleanup();
+        assert_eq!(cache.len(), 1);
+
+        drop(view);
+        cache.cleanup();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn purges_on_insert() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let render_pass = Arc::new(
+            single_pass_renderpass!(device.clone(),
+                attachments: {
+                    color: {
+                        load: Clear,
+                        store: DontCare,
+                        format: Format::R8G8B8A8Unorm,
+                        samples: 1,
+                    }
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {}
+                }
+            )
+            .unwrap(),
+        );
+
+        let new_view = || -> Arc<dyn ImageViewAbstract + Send + Sync> {
+            ImageView::new(
+                AttachmentImage::new(device.clone(), [64, 64], Format::R8G8B8A8Unorm).unwrap(),
+            )
+            .unwrap()
+        };
+
+        let cache = FramebufferCache::new();
+        let view = new_view();
+        cache.get(render_pass.clone(), vec![view.clone()]).unwrap();
+        assert_eq!(cache.len(), 1);
+
+        // Recreating the attachments, for example after a swapchain is recreated, replaces the
+        // framebuffers of the old ones.
+        drop(view);
+        let view = new_view();
+        cache.get(render_pass.clone(), vec![view.clone()]).unwrap();
+        assert_eq!(cache.len(), 1);
+
+        // Dropping the render pass makes its framebuffers unreachable as well.
+        let other_pass =
+            Arc::new(RenderPass::new(device.clone(), render_pass.desc().clone()).unwrap());
+        drop(render_pass);
+        cache.get(other_pass, vec![view]).unwrap();
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn purges_shared_objects() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let render_pass = Arc::new(
+            single_pass_renderpass!(device.clone(),
+                attachments: {
+                    color: {
+                        load: Clear,
+                        store: DontCare,
+                        format: Format::R8G8B8A8Unorm,
+                        samples: 1,
+                    },
+                    depth: {
+                        load: Clear,
+                        store: DontCare,
+                        format: Format::D16Unorm,
+                        samples: 1,
+                    }
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {depth}
+                }
+            )
+            .unwrap(),
+        );
+
+        let new_view = |format| -> Arc<dyn ImageViewAbstract + Send + Sync> {
+            ImageView::new(AttachmentImage::new(device.clone(), [64, 64], format).unwrap()).unwrap()
+        };
+
+        let cache = FramebufferCache::new();
+        let depth = new_view(Format::D16Unorm);
+        let colors = [
+            new_view(Format::R8G8B8A8Unorm),
+            new_view(Format::R8G8B8A8Unorm),
+        ];
+        for color in &colors {
+            cache
+                .get(render_pass.clone(), vec![color.clone(), depth.clone()])
+                .unwrap();
+        }
+        cache.cleanup();
+        assert_eq!(cache.len(), 2);
+
+        // The framebuffers reference the render pass and the depth view as well, but that doesn't
+        // keep them alive.
+        drop(depth);
+        cache.cleanup();
+        assert!(cache.is_empty());
+
+        let depth = new_view(Format::D16Unorm);
+        for color in &colors {
+            cache
+                .get(render_pass.clone(), vec![color.clone(), depth.clone()])
+                .unwrap();
+        }
+        assert_eq!(cache.len(), 2);
+        drop(render_pass);
+        cache.cleanup();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn attachments_count_mismatch() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let render_pass = Arc::new(
+            single_pass_renderpass!(device.clone(),
+                attachments: {
+                    color: {
+                        load: Clear,
+                        store: DontCare,
+                        format: Format::R8G8B8A8Unorm,
+                        samples: 1,
+                    }
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {}
+                }
+            )
+            .unwrap(),
+        );
+
+        let cache = FramebufferCache::new();
+        assert!(cache.get(render_pass, vec![]).is_err());
+        assert!(cache.is_empty());
+    }
+}
@@ -8,6 +8,10 @@
 // according to those terms.
 
 /// Builds a `RenderPass` object whose template parameter is of indeterminate type.
+///
+/// This is the same as `ordered_passes_renderpass!` with a single pass that has no input
+/// attachments. See the documentation of `ordered_passes_renderpass!` for the optional
+/// parameters.
 #[macro_export]
 macro_rules! single_pass_renderpass {
     (
@@ -16,8 +20,10 @@ macro_rules! single_pass_renderpass {
         pass: {
             color: [$($color_atch:ident),*],
             depth_stencil: {$($depth_atch:ident)*}$(,)*
-            $(resolve: [$($resolve_atch:ident),*])*$(,)*
-        }
+            $(resolve: [$($resolve_atch:ident),*]$(,)*)*
+            $(preserve: [$($preserve_atch:ident),*]$(,)*)*
+        }$(,)*
+        $(dependencies: [$($dependencies:tt)*]$(,)*)*
     ) => (
         $crate::ordered_passes_renderpass!(
             $device,
@@ -28,13 +34,82 @@ macro_rules! single_pass_renderpass {
                     depth_stencil: {$($depth_atch)*},
                     input: [],
                     resolve: [$($($resolve_atch),*)*]
+                    $(, preserve: [$($preserve_atch),*])*
                 }
             ]
+            $(, dependencies: [$($dependencies)*])*
         )
     )
 }
 
 /// Builds a `RenderPass` object whose template parameter is of indeterminate type.
+///
+/// Each attachment must specify its `load` and `store` operations, its `format` and its number
+/// of `samples`. The following optional parameters can be added after `samples`, in this order:
+///
+/// - `stencil_load` and `stencil_store`: The load and store operations of the stencil aspect of
+///   the attachment. Default to the values of `load` and `store`.
+/// - `initial_layout` and `final_layout`: The layouts of the attachment at the start and at the
+///   end of the render pass. Default to the first and last layout that the attachment is used
+///   with.
+///
+/// Each pass can optionally list its `preserve` attachments after `resolve`. By default, every
+/// attachment that isn't used by the pass is preserved.
+///
+/// By default, each pass depends on the previous one for all graphics stages and all accesses.
+/// The automatic dependencies can be replaced with a list of explicit ones:
+///
+/// ```
+/// # #[macro_use] extern crate vulkano;
+/// # fn main() {
+/// # let device: std::sync::Arc<vulkano::device::Device> = return;
+/// use vulkano::format::Format;
+///
+/// let render_pass = ordered_passes_renderpass!(device.clone(),
+///     attachments: {
+///         diffuse: {
+///             load: Clear,
+///             store: DontCare,
+///             format: Format::R8G8B8A8Unorm,
+///             samples: 1,
+///         },
+///         depth: {
+///             load: Clear,
+///             store: DontCare,
+///             format: Format::D24Unorm_S8Uint,
+///             samples: 1,
+///             stencil_load: Load,
+///             stencil_store: Store,
+///             initial_layout: vulkano::image::ImageLayout::DepthStencilAttachmentOptimal,
+///         }
+///     },
+///     passes: [
+///         {
+///             color: [diffuse],
+///             depth_stencil: {depth},
+///             input: []
+///         },
+///         {
+///             color: [],
+///             depth_stencil: {},
+///             input: [diffuse],
+///             preserve: [depth]
+///         }
+///     ],
+///     dependencies: [
+///         {
+///             source_subpass: 0,
+///             destination_subpass: 1,
+///             source_stages: { color_attachment_output },
+///             destination_stages: { fragment_shader },
+///             source_access: { color_attachment_write },
+///             destination_access: { input_attachment_read },
+///             by_region: true,
+///         }
+///     ]
+/// ).unwrap();
+/// # }
+/// ```
 #[macro_export]
 macro_rules! ordered_passes_renderpass {
     (
@@ -46,6 +121,8 @@ macro_rules! ordered_passes_renderpass {
                     store: $store:ident,
                     format: $format:expr,
                     samples: $samples:expr,
+                    $(stencil_load: $stencil_load:ident,)*
+                    $(stencil_store: $stencil_store:ident,)*
                     $(initial_layout: $init_layout:expr,)*
                     $(final_layout: $final_layout:expr,)*
                 }
@@ -57,10 +134,24 @@ macro_rules! ordered_passes_renderpass {
                     color: [$($color_atch:ident),*],
                     depth_stencil: {$($depth_atch:ident)*},
                     input: [$($input_atch:ident),*]$(,)*
-                    $(resolve: [$($resolve_atch:ident),*])*$(,)*
+                    $(resolve: [$($resolve_atch:ident),*]$(,)*)*
+                    $(preserve: [$($preserve_atch:ident),*]$(,)*)*
                 }
             ),*
-        ]
+        ]$(,)*
+        $(dependencies: [
+            $(
+                {
+                    source_subpass: $src_subpass:expr,
+                    destination_subpass: $dst_subpass:expr,
+                    source_stages: { $($src_stage:ident),* $(,)* },
+                    destination_stages: { $($dst_stage:ident),* $(,)* },
+                    source_access: { $($src_access:ident),* $(,)* },
+                    destination_access: { $($dst_access:ident),* $(,)* },
+                    by_region: $by_region:expr$(,)*
+                }
+            ),*
+        ]$(,)*)*
     ) => ({
         use $crate::render_pass::RenderPass;
 
@@ -123,13 +214,18 @@ macro_rules! ordered_passes_renderpass {
                                 ($resolve_atch, ImageLayout::TransferDstOptimal)
                             }),*)*
                         ],
-                        preserve_attachments: (0 .. attachment_num).filter(|&a| {
-                            $(if a == $color_atch { return false; })*
-                            $(if a == $depth_atch { return false; })*
-                            $(if a == $input_atch { return false; })*
-                            $($(if a == $resolve_atch { return false; })*)*
-                            true
-                        }).collect()
+                        preserve_attachments: {
+                            let preserve: Option<Vec<usize>> = None
+                                $(.or(Some(vec![$($preserve_atch),*])))*;
+
+                            preserve.unwrap_or_else(|| (0 .. attachment_num).filter(|&a| {
+                                $(if a == $color_atch { return false; })*
+                                $(if a == $depth_atch { return false; })*
+                                $(if a == $input_atch { return false; })*
+                                $($(if a == $resolve_atch { return false; })*)*
+                                true
+                            }).collect())
+                        }
                     };
 
                     assert!(desc.resolve_attachments.is_empty() ||
@@ -138,25 +234,54 @@ macro_rules! ordered_passes_renderpass {
                 }),*
             ];
 
-            let dependencies = (0..subpasses.len().saturating_sub(1))
-                .map(|id| {
-                    SubpassDependencyDesc {
-                        source_subpass: id,
-                        destination_subpass: id + 1,
-                        source_stages: PipelineStages {
-                            all_graphics: true,
-                            ..PipelineStages::none()
-                        }, // TODO: correct values
-                        destination_stages: PipelineStages {
-                            all_graphics: true,
-                            ..PipelineStages::none()
-                        }, // TODO: correct values
-                        source_access: AccessFlags::all(), // TODO: correct values
-                        destination_access: AccessFlags::all(), // TODO: correct values
-                        by_region: true,                      // TODO: correct values
-                    }
-                })
-                .collect();
+            let dependencies: Option<Vec<SubpassDependencyDesc>> = None
+                $(.or(Some(vec![
+                    $(
+                        SubpassDependencyDesc {
+                            source_subpass: $src_subpass,
+                            destination_subpass: $dst_subpass,
+                            source_stages: PipelineStages {
+                                $($src_stage: true,)*
+                                ..PipelineStages::none()
+                            },
+                            destination_stages: PipelineStages {
+                                $($dst_stage: true,)*
+                                ..PipelineStages::none()
+                            },
+                            source_access: AccessFlags {
+                                $($src_access: true,)*
+                                ..AccessFlags::none()
+                            },
+                            destination_access: AccessFlags {
+                                $($dst_access: true,)*
+                                ..AccessFlags::none()
+                            },
+                            by_region: $by_region,
+                        }
+                    ),*
+                ])))*;
+
+            let dependencies = dependencies.unwrap_or_else(|| {
+                (0..subpasses.len().saturating_sub(1))
+                    .map(|id| {
+                        SubpassDependencyDesc {
+                            source_subpass: id,
+                            destination_subpass: id + 1,
+                            source_stages: PipelineStages {
+                                all_graphics: true,
+                                ..PipelineStages::none()
+                            }, // TODO: correct values
+                            destination_stages: PipelineStages {
+                                all_graphics: true,
+                                ..PipelineStages::none()
+                            }, // TODO: correct values
+                            source_access: AccessFlags::all(), // TODO: correct values
+                            destination_access: AccessFlags::all(), // TODO: correct values
+                            by_region: true,                      // TODO: correct values
+                        }
+                    })
+                    .collect()
+            });
 
             let attachments = vec![
                 $({
@@ -169,8 +294,16 @@ macro_rules! ordered_passes_renderpass {
                         samples: $samples.try_into().unwrap(),
                         load: $crate::render_pass::LoadOp::$load,
                         store: $crate::render_pass::StoreOp::$store,
-                        stencil_load: $crate::render_pass::LoadOp::$load,
-                        stencil_store: $crate::render_pass::StoreOp::$store,
+                        stencil_load: {
+                            let stencil_load = $crate::render_pass::LoadOp::$load;
+                            $(let stencil_load = $crate::render_pass::LoadOp::$stencil_load;)*
+                            stencil_load
+                        },
+                        stencil_store: {
+                            let stencil_store = $crate::render_pass::StoreOp::$store;
+                            $(let stencil_store = $crate::render_pass::StoreOp::$stencil_store;)*
+                            stencil_store
+                        },
                         initial_layout: layout.0.expect(
                             format!(
                                 "Attachment {} is missing initial_layout, this is normally \
@@ -207,6 +340,8 @@ macro_rules! ordered_passes_renderpass {
 #[cfg(test)]
 mod tests {
     use crate::format::Format;
+    use crate::render_pass::LoadOp;
+    use crate::render_pass::StoreOp;
 
     #[test]
     fn single_pass_resolve() {
@@ -234,4 +369,104 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn single_pass_self_dependency() {
+        let (device, _) = gfx_dev_and_queue!();
+        let rp = single_pass_renderpass!(device.clone(),
+            attachments: {
+                a: {
+                    load: Load,
+                    store: Store,
+                    format: Format::R8G8B8A8Unorm,
+                    samples: 1,
+                },
+                b: {
+                    load: DontCare,
+                    store: Store,
+                    format: Format::R8G8B8A8Unorm,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [a],
+                depth_stencil: {},
+                preserve: [],
+            },
+            dependencies: [
+                {
+                    source_subpass: 0,
+                    destination_subpass: 0,
+                    source_stages: { color_attachment_output },
+                    destination_stages: { fragment_shader },
+                    source_access: { color_attachment_write },
+                    destination_access: { input_attachment_read },
+                    by_region: true,
+                }
+            ]
+        )
+        .unwrap();
+
+        assert!(rp.desc().subpasses()[0].preserve_attachments.is_empty());
+        assert_eq!(rp.desc().self_dependencies(0).count(), 1);
+    }
+
+    #[test]
+    fn explicit_stencil_preserve_and_dependencies() {
+        let (device, _) = gfx_dev_and_queue!();
+        let rp = ordered_passes_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: Format::R8G8B8A8Unorm,
+                    samples: 1,
+                },
+                depth: {
+                    load: Clear,
+                    store: DontCare,
+                    format: Format::D24Unorm_S8Uint,
+                    samples: 1,
+                    stencil_load: Load,
+                    stencil_store: Store,
+                    initial_layout: ImageLayout::DepthStencilAttachmentOptimal,
+                    final_layout: ImageLayout::DepthStencilAttachmentOptimal,
+                }
+            },
+            passes: [
+                {
+                    color: [color],
+                    depth_stencil: {depth},
+                    input: []
+                },
+                {
+                    color: [color],
+                    depth_stencil: {},
+                    input: [],
+                    preserve: [depth]
+                }
+            ],
+            dependencies: [
+                {
+                    source_subpass: 0,
+                    destination_subpass: 1,
+                    source_stages: { color_attachment_output },
+                    destination_stages: { color_attachment_output },
+                    source_access: { color_attachment_write },
+                    destination_access: { color_attachment_read, color_attachment_write },
+                    by_region: true,
+                }
+            ]
+        )
+        .unwrap();
+
+        let desc = rp.desc();
+        assert_eq!(desc.attachments()[1].load, LoadOp::Clear);
+        assert_eq!(desc.attachments()[1].stencil_load, LoadOp::Load);
+        assert_eq!(desc.attachments()[1].stencil_store, StoreOp::Store);
+        assert_eq!(desc.subpasses()[1].preserve_attachments, vec![1]);
+        assert_eq!(desc.dependencies().len(), 1);
+        assert!(desc.dependencies()[0].source_access.color_attachment_write);
+        assert!(!desc.dependencies()[0].source_access.color_attachment_read);
+    }
 }
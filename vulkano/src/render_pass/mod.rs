@@ -42,6 +42,8 @@ pub use self::framebuffer::FramebufferAbstract;
 pub use self::framebuffer::FramebufferBuilder;
 pub use self::framebuffer::FramebufferCreationError;
 pub use self::framebuffer::FramebufferSys;
+pub use self::framebuffer_cache::CachedFramebuffer;
+pub use self::framebuffer_cache::FramebufferCache;
 pub use self::render_pass::RenderPass;
 pub use self::render_pass::RenderPassCreationError;
 pub use self::render_pass::RenderPassSys;
@@ -53,4 +55,5 @@ mod attachments_list;
 mod compat_atch;
 mod desc;
 mod framebuffer;
+mod framebuffer_cache;
 mod render_pass;
@@ -364,6 +364,21 @@ impl RenderPass {
                         || dependency.destination_subpass < passes.len()
                 );
 
+                // VUID-VkSubpassDependency-srcSubpass-00864
+                debug_assert!(
+                    dependency.source_subpass as u32 == ash::vk::SUBPASS_EXTERNAL
+                        || dependency.destination_subpass as u32 == ash::vk::SUBPASS_EXTERNAL
+                        || dependency.source_subpass <= dependency.destination_subpass
+                );
+
+                // VUID-VkSubpassDependency-srcSubpass-02243
+                debug_assert!(
+                    !dependency.is_self_dependency()
+                        || !dependency.source_stages.has_framebuffer_space_stage()
+                        || !dependency.destination_stages.has_framebuffer_space_stage()
+                        || dependency.by_region
+                );
+
                 ash::vk::SubpassDependency {
                     src_subpass: dependency.source_subpass as u32,
                     dst_subpass: dependency.destination_subpass as u32,
@@ -549,11 +564,47 @@ impl RenderPass {
         }
     }
 
+    /// Returns true if a render area is aligned to the granularity of this render pass, which
+    /// means that using it to begin the render pass has optimal performance.
+    ///
+    /// The offset must be a multiple of the granularity, and the extent must either be a multiple
+    /// of the granularity or reach the edge of the framebuffer.
+    pub fn is_render_area_optimal(
+        &self,
+        offset: [u32; 2],
+        extent: [u32; 2],
+        framebuffer_dimensions: [u32; 2],
+    ) -> bool {
+        let granularity = self.granularity();
+
+        (0..2).all(|i| {
+            offset[i] % granularity[i] == 0
+                && (extent[i] % granularity[i] == 0
+                    || offset[i] as u64 + extent[i] as u64 == framebuffer_dimensions[i] as u64)
+        })
+    }
+
     /// Returns the description of the render pass.
     #[inline]
     pub fn desc(&self) -> &RenderPassDesc {
         &self.desc
     }
+
+    /// Returns `true` if this render pass is compatible with the other render pass,
+    /// as defined in the `Render Pass Compatibility` section of the Vulkan specs.
+    ///
+    /// Framebuffers and graphics pipelines created with a render pass can be used with any other
+    /// render pass that is compatible with it. Compatible render passes can differ in the load and
+    /// store operations and in the image layouts of their attachments, but must be otherwise
+    /// identical.
+    #[inline]
+    pub fn is_compatible_with(&self, other: &RenderPass) -> bool {
+        if self.render_pass == other.render_pass {
+            return true;
+        }
+
+        self.desc.is_compatible_with_desc(&other.desc)
+    }
 }
 
 unsafe impl DeviceOwned for RenderPass {
@@ -908,4 +959,31 @@ mod tests {
         assert_ne!(granularity[0], 0);
         assert_ne!(granularity[1], 0);
     }
+
+    #[test]
+    fn render_area_optimal() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp = single_pass_renderpass! {
+            device.clone(),
+            attachments: {
+                a: { load: Clear, store: DontCare, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: {
+                color: [a],
+                depth_stencil: {}
+            }
+        }
+        .unwrap();
+
+        let [gw, gh] = rp.granularity();
+        let dimensions = [gw * 4, gh * 4];
+        assert!(rp.is_render_area_optimal([0, 0], dimensions, dimensions));
+        assert!(rp.is_render_area_optimal([gw, gh], [gw * 3, gh * 3], dimensions));
+
+        if gw > 1 {
+            assert!(!rp.is_render_area_optimal([1, 0], [gw, gh], dimensions));
+        }
+        assert!(!rp.is_render_area_optimal([gw, 0], [u32::MAX, gh], dimensions));
+    }
 }
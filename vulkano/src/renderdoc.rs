@@ -0,0 +1,291 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Controlling RenderDoc captures from the application.
+//!
+//! When the application is launched from RenderDoc, or when the RenderDoc library is loaded
+//! before the instance is created, the [in-application API](https://renderdoc.org/docs/in_application_api.html)
+//! lets the application decide which frames are captured. This is useful for captures that are
+//! hard to trigger with a key press, or for tools that don't present to a window.
+//!
+//! This module requires the `renderdoc` feature. It doesn't depend on the debug utils
+//! extension or on debug builds, so it can be used from release builds.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use vulkano::device::Queue;
+//! use vulkano::renderdoc::RenderDoc;
+//!
+//! # let queue: Arc<Queue> = return;
+//! let renderdoc = RenderDoc::load().ok();
+//!
+//! # let frame_index = 0;
+//! if let Some(ref renderdoc) = renderdoc {
+//!     renderdoc.capture_frame(queue.device().instance(), frame_index, || {
+//!         // ... build and submit the work of the frame ...
+//!     });
+//! }
+//! ```
+
+use crate::instance::Instance;
+use crate::VulkanObject;
+use ash::vk::Handle;
+use std::error;
+use std::ffi::CString;
+use std::fmt;
+use std::mem;
+use std::os::raw::c_char;
+use std::os::raw::c_int;
+use std::os::raw::c_void;
+use std::path::Path;
+use std::ptr;
+
+// Version 1.2.0 of the API, which is the first to support `SetCaptureFileComments`.
+const API_VERSION_1_2_0: c_int = 10200;
+
+#[cfg(target_os = "windows")]
+const LIBRARY_NAME: &str = "renderdoc.dll";
+#[cfg(target_os = "android")]
+const LIBRARY_NAME: &str = "libVkLayer_GLES_RenderDoc.so";
+#[cfg(all(not(target_os = "windows"), not(target_os = "android")))]
+const LIBRARY_NAME: &str = "librenderdoc.so";
+
+type GetApiFn = extern "C" fn(version: c_int, out_api_pointers: *mut *mut c_void) -> c_int;
+type DevicePointer = *mut c_void;
+type WindowHandle = *mut c_void;
+
+// Layout of `RENDERDOC_API_1_2_0`. Only the functions that are used are typed, the others are
+// kept as opaque pointers to preserve the offsets.
+#[repr(C)]
+struct ApiTable {
+    get_api_version: *const c_void,
+    set_capture_option_u32: *const c_void,
+    set_capture_option_f32: *const c_void,
+    get_capture_option_u32: *const c_void,
+    get_capture_option_f32: *const c_void,
+    set_focus_toggle_keys: *const c_void,
+    set_capture_keys: *const c_void,
+    get_overlay_bits: *const c_void,
+    mask_overlay_bits: *const c_void,
+    remove_hooks: *const c_void,
+    unload_crash_handler: *const c_void,
+    set_capture_file_path_template: extern "C" fn(path_template: *const c_char),
+    get_capture_file_path_template: *const c_void,
+    get_num_captures: extern "C" fn() -> u32,
+    get_capture: *const c_void,
+    trigger_capture: extern "C" fn(),
+    is_target_control_connected: *const c_void,
+    launch_replay_ui: *const c_void,
+    set_active_window: *const c_void,
+    start_frame_capture: extern "C" fn(device: DevicePointer, window: WindowHandle),
+    is_frame_capturing: extern "C" fn() -> u32,
+    end_frame_capture: extern "C" fn(device: DevicePointer, window: WindowHandle) -> u32,
+    trigger_multi_frame_capture: extern "C" fn(num_frames: u32),
+    set_capture_file_comments: extern "C" fn(file_path: *const c_char, comments: *const c_char),
+}
+
+/// Handle to the in-application API of RenderDoc.
+pub struct RenderDoc {
+    // Kept loaded for as long as the function table is used.
+    _library: shared_library::dynamic_library::DynamicLibrary,
+    api: *const ApiTable,
+}
+
+// The functions of the API can be called from any thread.
+unsafe impl Send for RenderDoc {}
+unsafe impl Sync for RenderDoc {}
+
+impl RenderDoc {
+    /// Loads the RenderDoc library of the platform and retrieves its API.
+    ///
+    /// The library must be loaded before the Vulkan instance is created for the captures to
+    /// work, which is the case when the application is launched from RenderDoc.
+    #[inline]
+    pub fn load() -> Result<RenderDoc, RenderDocError> {
+        RenderDoc::load_from_path(LIBRARY_NAME)
+    }
+
+    /// Same as `load`, but loads the library at the given path.
+    pub fn load_from_path<P>(path: P) -> Result<RenderDoc, RenderDocError>
+    where
+        P: AsRef<Path>,
+    {
+        let library = shared_library::dynamic_library::DynamicLibrary::open(Some(path.as_ref()))
+            .map_err(RenderDocError::LibraryLoadFailure)?;
+
+        let api = unsafe {
+            let get_api: GetApiFn = {
+                let ptr: *mut c_void = library
+                    .symbol("RENDERDOC_GetAPI")
+                    .map_err(|_| RenderDocError::MissingEntryPoint)?;
+                mem::transmute(ptr)
+            };
+
+            let mut api = ptr::null_mut();
+            if get_api(API_VERSION_1_2_0, &mut api) != 1 || api.is_null() {
+                return Err(RenderDocError::UnsupportedVersion);
+            }
+            api as *const ApiTable
+        };
+
+        Ok(RenderDoc {
+            _library: library,
+            api,
+        })
+    }
+
+    #[inline]
+    fn api(&self) -> &ApiTable {
+        unsafe { &*self.api }
+    }
+
+    /// Captures the next frame that is presented.
+    #[inline]
+    pub fn trigger_capture(&self) {
+        (self.api().trigger_capture)()
+    }
+
+    /// Captures the next `num_frames` frames that are presented.
+    #[inline]
+    pub fn trigger_multi_frame_capture(&self, num_frames: u32) {
+        (self.api().trigger_multi_frame_capture)(num_frames)
+    }
+
+    /// Starts capturing the commands that are submitted to the devices of `instance`, until
+    /// `end_frame_capture` is called.
+    ///
+    /// This doesn't depend on presentation, so it also works for applications that don't have
+    /// a window.
+    #[inline]
+    pub fn start_frame_capture(&self, instance: &Instance) {
+        (self.api().start_frame_capture)(device_pointer(instance), ptr::null_mut())
+    }
+
+    /// Returns true if a frame is being captured.
+    #[inline]
+    pub fn is_frame_capturing(&self) -> bool {
+        (self.api().is_frame_capturing)() != 0
+    }
+
+    /// Ends the capture that was started with `start_frame_capture`, and returns true if the
+    /// capture was saved.
+    #[inline]
+    pub fn end_frame_capture(&self, instance: &Instance) -> bool {
+        (self.api().end_frame_capture)(device_pointer(instance), ptr::null_mut()) != 0
+    }
+
+    /// Captures the commands that are submitted by `f`, and annotates the capture with the
+    /// index of the frame.
+    ///
+    /// The submissions must be flushed before `f` returns to be part of the capture.
+    pub fn capture_frame<F, R>(&self, instance: &Instance, frame_index: u64, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        self.start_frame_capture(instance);
+        let result = f();
+
+        if self.end_frame_capture(instance) {
+            self.set_capture_comments(&format!("Frame {}", frame_index));
+        }
+
+        result
+    }
+
+    /// Returns the number of captures that have been saved so far.
+    #[inline]
+    pub fn num_captures(&self) -> u32 {
+        (self.api().get_num_captures)()
+    }
+
+    /// Sets the comments of the latest capture.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `comments` contains a nul byte.
+    #[inline]
+    pub fn set_capture_comments(&self, comments: &str) {
+        let comments = CString::new(comments).unwrap();
+        (self.api().set_capture_file_comments)(ptr::null(), comments.as_ptr())
+    }
+
+    /// Sets the template of the paths of the capture files. RenderDoc appends a suffix such as
+    /// the date and the frame number to it.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `path_template` contains a nul byte.
+    #[inline]
+    pub fn set_capture_path_template(&self, path_template: &str) {
+        let path_template = CString::new(path_template).unwrap();
+        (self.api().set_capture_file_path_template)(path_template.as_ptr())
+    }
+}
+
+impl fmt::Debug for RenderDoc {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<RenderDoc {:?}>", self.api)
+    }
+}
+
+// RenderDoc identifies a Vulkan instance by the pointer to its dispatch table, which is stored
+// at the start of the object.
+#[inline]
+fn device_pointer(instance: &Instance) -> DevicePointer {
+    unsafe { *(instance.internal_object().as_raw() as *const DevicePointer) }
+}
+
+/// Error that can happen when loading RenderDoc.
+#[derive(Clone, Debug)]
+pub enum RenderDocError {
+    /// Failed to load the RenderDoc library.
+    LibraryLoadFailure(String),
+    /// The library doesn't contain `RENDERDOC_GetAPI`.
+    MissingEntryPoint,
+    /// The library doesn't support version 1.2.0 of the API.
+    UnsupportedVersion,
+}
+
+impl error::Error for RenderDocError {}
+
+impl fmt::Display for RenderDocError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                RenderDocError::LibraryLoadFailure(_) => "failed to load the RenderDoc library",
+                RenderDocError::MissingEntryPoint => {
+                    "the library doesn't contain `RENDERDOC_GetAPI`"
+                }
+                RenderDocError::UnsupportedVersion => {
+                    "the library doesn't support version 1.2.0 of the API"
+                }
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderdoc::RenderDoc;
+    use crate::renderdoc::RenderDocError;
+
+    #[test]
+    fn missing_library() {
+        match RenderDoc::load_from_path("/nonexistent/librenderdoc.so") {
+            Err(RenderDocError::LibraryLoadFailure(_)) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -72,6 +72,7 @@ use crate::VulkanObject;
 use std::error;
 use std::fmt;
 use std::mem::MaybeUninit;
+use std::panic::Location;
 use std::ptr;
 use std::sync::Arc;
 
@@ -97,6 +98,7 @@ impl Sampler {
     /// - Panics if out of memory or the maximum number of samplers has exceeded.
     ///
     #[inline]
+    #[track_caller]
     pub fn simple_repeat_linear(device: Arc<Device>) -> Arc<Sampler> {
         Sampler::new(
             device,
@@ -124,6 +126,7 @@ impl Sampler {
     /// - Panics if out of memory or the maximum number of samplers has exceeded.
     ///
     #[inline]
+    #[track_caller]
     pub fn simple_repeat_linear_no_mipmap(device: Arc<Device>) -> Arc<Sampler> {
         Sampler::new(
             device,
@@ -167,6 +170,7 @@ impl Sampler {
     /// - Panics if `min_lod > max_lod`.
     ///
     #[inline(always)]
+    #[track_caller]
     pub fn new(
         device: Arc<Device>,
         mag_filter: Filter,
@@ -213,6 +217,7 @@ impl Sampler {
     /// Same panic reasons as `new`.
     ///
     #[inline(always)]
+    #[track_caller]
     pub fn compare(
         device: Arc<Device>,
         mag_filter: Filter,
@@ -243,6 +248,7 @@ impl Sampler {
         )
     }
 
+    #[track_caller]
     fn new_impl(
         device: Arc<Device>,
         mag_filter: Filter,
@@ -290,6 +296,13 @@ impl Sampler {
                     maximum: limit,
                 });
             }
+
+            if mip_lod_bias != 0.0
+                && device.enabled_extensions().khr_portability_subset
+                && !device.enabled_features().sampler_mip_lod_bias
+            {
+                return Err(SamplerCreationError::SamplerMipLodBiasFeatureNotEnabled);
+            }
         }
 
         // Check MirrorClampToEdge extension support
@@ -321,76 +334,39 @@ impl Sampler {
             (b, None) => b,
         };
 
-        let fns = device.fns();
-        let sampler = unsafe {
-            let infos = ash::vk::SamplerCreateInfo {
-                flags: ash::vk::SamplerCreateFlags::empty(),
-                mag_filter: mag_filter.into(),
-                min_filter: min_filter.into(),
-                mipmap_mode: mipmap_mode.into(),
-                address_mode_u: address_u.into(),
-                address_mode_v: address_v.into(),
-                address_mode_w: address_w.into(),
-                mip_lod_bias: mip_lod_bias,
-                anisotropy_enable: if max_anisotropy > 1.0 {
-                    ash::vk::TRUE
-                } else {
-                    ash::vk::FALSE
-                },
-                max_anisotropy: max_anisotropy,
-                compare_enable: if compare.is_some() {
-                    ash::vk::TRUE
-                } else {
-                    ash::vk::FALSE
-                },
-                compare_op: compare
-                    .map(|c| c.into())
-                    .unwrap_or(ash::vk::CompareOp::NEVER),
-                min_lod: min_lod,
-                max_lod: max_lod,
-                border_color: border_color
-                    .map(|b| b.into())
-                    .unwrap_or(ash::vk::BorderColor::FLOAT_TRANSPARENT_BLACK),
-                unnormalized_coordinates: ash::vk::FALSE,
-                ..Default::default()
-            };
-
-            let mut output = MaybeUninit::uninit();
-            check_errors(fns.v1_0.create_sampler(
-                device.internal_object(),
-                &infos,
-                ptr::null(),
-                output.as_mut_ptr(),
-            ))?;
-            output.assume_init()
-        };
-
-        Ok(Arc::new(Sampler {
-            sampler: sampler,
-            device: device.clone(),
-            compare_mode: compare.is_some(),
-            unnormalized: false,
-            usable_with_float_formats: match border_color {
-                Some(BorderColor::FloatTransparentBlack) => true,
-                Some(BorderColor::FloatOpaqueBlack) => true,
-                Some(BorderColor::FloatOpaqueWhite) => true,
-                Some(_) => false,
-                None => true,
+        let infos = ash::vk::SamplerCreateInfo {
+            flags: ash::vk::SamplerCreateFlags::empty(),
+            mag_filter: mag_filter.into(),
+            min_filter: min_filter.into(),
+            mipmap_mode: mipmap_mode.into(),
+            address_mode_u: address_u.into(),
+            address_mode_v: address_v.into(),
+            address_mode_w: address_w.into(),
+            mip_lod_bias,
+            anisotropy_enable: if max_anisotropy > 1.0 {
+                ash::vk::TRUE
+            } else {
+                ash::vk::FALSE
             },
-            usable_with_int_formats: compare.is_none()
-                && match border_color {
-                    Some(BorderColor::IntTransparentBlack) => true,
-                    Some(BorderColor::IntOpaqueBlack) => true,
-                    Some(BorderColor::IntOpaqueWhite) => true,
-                    Some(_) => false,
-                    None => true,
-                },
-            usable_with_swizzling: match border_color {
-                Some(BorderColor::FloatOpaqueBlack) => false,
-                Some(BorderColor::IntOpaqueBlack) => false,
-                _ => true,
+            max_anisotropy,
+            compare_enable: if compare.is_some() {
+                ash::vk::TRUE
+            } else {
+                ash::vk::FALSE
             },
-        }))
+            compare_op: compare
+                .map(|c| c.into())
+                .unwrap_or(ash::vk::CompareOp::NEVER),
+            min_lod,
+            max_lod,
+            border_color: border_color
+                .map(|b| b.into())
+                .unwrap_or(ash::vk::BorderColor::FLOAT_TRANSPARENT_BLACK),
+            unnormalized_coordinates: ash::vk::FALSE,
+            ..Default::default()
+        };
+
+        Sampler::from_create_info(device, infos, compare.is_some(), false, border_color)
     }
 
     /// Creates a sampler with unnormalized coordinates. This means that texture coordinates won't
@@ -406,14 +382,13 @@ impl Sampler {
     ///
     /// - Panics if multiple `ClampToBorder` values are passed and the border color is different.
     ///
+    #[track_caller]
     pub fn unnormalized(
         device: Arc<Device>,
         filter: Filter,
         address_u: UnnormalizedSamplerAddressMode,
         address_v: UnnormalizedSamplerAddressMode,
     ) -> Result<Arc<Sampler>, SamplerCreationError> {
-        let fns = device.fns();
-
         let border_color = address_u.border_color();
         let border_color = match (border_color, address_v.border_color()) {
             (Some(b1), Some(b2)) => {
@@ -424,29 +399,57 @@ impl Sampler {
             (b, None) => b,
         };
 
-        let sampler = unsafe {
-            let infos = ash::vk::SamplerCreateInfo {
-                flags: ash::vk::SamplerCreateFlags::empty(),
-                mag_filter: filter.into(),
-                min_filter: filter.into(),
-                mipmap_mode: ash::vk::SamplerMipmapMode::NEAREST,
-                address_mode_u: address_u.into(),
-                address_mode_v: address_v.into(),
-                address_mode_w: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE, // unused by the impl
-                mip_lod_bias: 0.0,
-                anisotropy_enable: ash::vk::FALSE,
-                max_anisotropy: 1.0,
-                compare_enable: ash::vk::FALSE,
-                compare_op: ash::vk::CompareOp::NEVER,
-                min_lod: 0.0,
-                max_lod: 0.0,
-                border_color: border_color
-                    .map(|b| b.into())
-                    .unwrap_or(ash::vk::BorderColor::FLOAT_TRANSPARENT_BLACK),
-                unnormalized_coordinates: ash::vk::TRUE,
-                ..Default::default()
-            };
+        let infos = ash::vk::SamplerCreateInfo {
+            flags: ash::vk::SamplerCreateFlags::empty(),
+            mag_filter: filter.into(),
+            min_filter: filter.into(),
+            mipmap_mode: ash::vk::SamplerMipmapMode::NEAREST,
+            address_mode_u: address_u.into(),
+            address_mode_v: address_v.into(),
+            address_mode_w: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE, // unused by the impl
+            mip_lod_bias: 0.0,
+            anisotropy_enable: ash::vk::FALSE,
+            max_anisotropy: 1.0,
+            compare_enable: ash::vk::FALSE,
+            compare_op: ash::vk::CompareOp::NEVER,
+            min_lod: 0.0,
+            max_lod: 0.0,
+            border_color: border_color
+                .map(|b| b.into())
+                .unwrap_or(ash::vk::BorderColor::FLOAT_TRANSPARENT_BLACK),
+            unnormalized_coordinates: ash::vk::TRUE,
+            ..Default::default()
+        };
+
+        Sampler::from_create_info(device, infos, false, true, border_color)
+    }
+
+    // Creates the sampler described by `infos`, or returns the existing one from the sampler
+    // cache of the device.
+    #[track_caller]
+    fn from_create_info(
+        device: Arc<Device>,
+        infos: ash::vk::SamplerCreateInfo,
+        compare_mode: bool,
+        unnormalized: bool,
+        border_color: Option<BorderColor>,
+    ) -> Result<Arc<Sampler>, SamplerCreationError> {
+        let key = SamplerKey::from(&infos);
 
+        // The lock is kept while the sampler is created, so that two threads don't create the
+        // same sampler.
+        let mut cache = device.samplers();
+
+        if let Some(sampler) = cache
+            .as_ref()
+            .and_then(|cache| cache.get(&key))
+            .and_then(|sampler| sampler.upgrade())
+        {
+            return Ok(sampler);
+        }
+
+        let sampler = unsafe {
+            let fns = device.fns();
             let mut output = MaybeUninit::uninit();
             check_errors(fns.v1_0.create_sampler(
                 device.internal_object(),
@@ -457,11 +460,11 @@ impl Sampler {
             output.assume_init()
         };
 
-        Ok(Arc::new(Sampler {
+        let sampler = Arc::new(Sampler {
             sampler: sampler,
             device: device.clone(),
-            compare_mode: false,
-            unnormalized: true,
+            compare_mode,
+            unnormalized,
             usable_with_float_formats: match border_color {
                 Some(BorderColor::FloatTransparentBlack) => true,
                 Some(BorderColor::FloatOpaqueBlack) => true,
@@ -469,19 +472,31 @@ impl Sampler {
                 Some(_) => false,
                 None => true,
             },
-            usable_with_int_formats: match border_color {
-                Some(BorderColor::IntTransparentBlack) => true,
-                Some(BorderColor::IntOpaqueBlack) => true,
-                Some(BorderColor::IntOpaqueWhite) => true,
-                Some(_) => false,
-                None => true,
-            },
+            usable_with_int_formats: !compare_mode
+                && match border_color {
+                    Some(BorderColor::IntTransparentBlack) => true,
+                    Some(BorderColor::IntOpaqueBlack) => true,
+                    Some(BorderColor::IntOpaqueWhite) => true,
+                    Some(_) => false,
+                    None => true,
+                },
             usable_with_swizzling: match border_color {
                 Some(BorderColor::FloatOpaqueBlack) => false,
                 Some(BorderColor::IntOpaqueBlack) => false,
                 _ => true,
             },
-        }))
+        });
+
+        if let Some(cache) = cache.as_mut() {
+            cache.retain(|_, sampler| sampler.strong_count() != 0);
+            cache.insert(key, Arc::downgrade(&sampler));
+        }
+        drop(cache);
+
+        sampler
+            .device
+            .auto_debug_name(&*sampler, Location::caller());
+        Ok(sampler)
     }
 
     /// Returns true if the sampler is a compare-mode sampler.
@@ -552,6 +567,57 @@ impl Drop for Sampler {
     }
 }
 
+// Key of the sampler cache of a device: all the parameters of the sampler, as given to
+// `vkCreateSampler`. The floating-point values are compared bit for bit.
+//
+// Parameters that are given in the `p_next` chain of the create info, such as the reduction mode,
+// must be added here once samplers support them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct SamplerKey {
+    flags: ash::vk::SamplerCreateFlags,
+    mag_filter: ash::vk::Filter,
+    min_filter: ash::vk::Filter,
+    mipmap_mode: ash::vk::SamplerMipmapMode,
+    address_modes: [ash::vk::SamplerAddressMode; 3],
+    mip_lod_bias: u32,
+    anisotropy_enable: ash::vk::Bool32,
+    max_anisotropy: u32,
+    compare_enable: ash::vk::Bool32,
+    compare_op: ash::vk::CompareOp,
+    min_lod: u32,
+    max_lod: u32,
+    border_color: ash::vk::BorderColor,
+    unnormalized_coordinates: ash::vk::Bool32,
+}
+
+impl From<&ash::vk::SamplerCreateInfo> for SamplerKey {
+    #[inline]
+    fn from(infos: &ash::vk::SamplerCreateInfo) -> SamplerKey {
+        debug_assert!(infos.p_next.is_null());
+
+        SamplerKey {
+            flags: infos.flags,
+            mag_filter: infos.mag_filter,
+            min_filter: infos.min_filter,
+            mipmap_mode: infos.mipmap_mode,
+            address_modes: [
+                infos.address_mode_u,
+                infos.address_mode_v,
+                infos.address_mode_w,
+            ],
+            mip_lod_bias: infos.mip_lod_bias.to_bits(),
+            anisotropy_enable: infos.anisotropy_enable,
+            max_anisotropy: infos.max_anisotropy.to_bits(),
+            compare_enable: infos.compare_enable,
+            compare_op: infos.compare_op,
+            min_lod: infos.min_lod.to_bits(),
+            max_lod: infos.max_lod.to_bits(),
+            border_color: infos.border_color,
+            unnormalized_coordinates: infos.unnormalized_coordinates,
+        }
+    }
+}
+
 /// Describes how the color of each pixel should be determined.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[repr(i32)]
@@ -755,6 +821,10 @@ pub enum SamplerCreationError {
     /// Using `MirrorClampToEdge` requires enabling the `VK_KHR_sampler_mirror_clamp_to_edge`
     /// extension when creating the device.
     SamplerMirrorClampToEdgeExtensionNotEnabled,
+
+    /// Using a mip lod bias different from 0.0 on a portability subset device requires enabling
+    /// the `sampler_mip_lod_bias` feature when creating the device.
+    SamplerMipLodBiasFeatureNotEnabled,
 }
 
 impl error::Error for SamplerCreationError {
@@ -785,6 +855,9 @@ impl fmt::Display for SamplerCreationError {
                 SamplerCreationError::SamplerMirrorClampToEdgeExtensionNotEnabled => {
                     "the device extension `VK_KHR_sampler_mirror_clamp_to_edge` is not enabled"
                 }
+                SamplerCreationError::SamplerMipLodBiasFeatureNotEnabled => {
+                    "the `sampler_mip_lod_bias` feature is not enabled"
+                }
             }
         )
     }
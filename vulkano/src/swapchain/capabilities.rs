@@ -55,6 +55,19 @@ pub struct Capabilities {
 
     /// List of present modes that are supported. `Fifo` is always guaranteed to be supported.
     pub present_modes: SupportedPresentModes,
+
+    /// Whether a swapchain created with this surface can use protected images.
+    ///
+    /// `None` if the `khr_get_surface_capabilities2` and `khr_surface_protected_capabilities`
+    /// instance extensions are not enabled.
+    pub supports_protected: Option<bool>,
+
+    /// Whether a swapchain created with this surface can use `FullscreenExclusive::AppControlled`
+    /// or `FullscreenExclusive::Allowed`.
+    ///
+    /// `None` if the `khr_get_surface_capabilities2` instance extension is not enabled, or if the
+    /// physical device doesn't support the `ext_full_screen_exclusive` extension.
+    pub full_screen_exclusive_supported: Option<bool>,
 }
 
 /// The way presenting a swapchain is accomplished.
@@ -342,6 +355,132 @@ impl Iterator for SupportedCompositeAlphaIter {
     }
 }
 
+/// Ways the images of a swapchain can be presented by the physical devices of a device group.
+///
+/// Used both for the modes supported by a surface and for the modes a swapchain is created with.
+/// Alternate frame rendering (AFR) is done with `local` or `remote` presentation: each frame
+/// acquires its image with the mask of the physical device that renders it, see
+/// [`acquire_next_image_with_device_mask`](crate::swapchain::acquire_next_image_with_device_mask),
+/// executes its command buffers on that device, see
+/// [`CommandBufferExecFuture::with_device_mask`](crate::command_buffer::CommandBufferExecFuture::with_device_mask),
+/// and is presented from that device, see
+/// [`PresentFuture::with_device_group_present`](crate::swapchain::PresentFuture::with_device_group_present).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DeviceGroupPresentModes {
+    /// Each physical device can present the images that are in its own memory.
+    pub local: bool,
+    /// A physical device can present the images that are in the memory of another physical
+    /// device of the group.
+    pub remote: bool,
+    /// A physical device can present the sum of the images that are in the memory of several
+    /// physical devices of the group.
+    pub sum: bool,
+    /// Several physical devices can each present the images that are in their own memory, for
+    /// example for a surface that spans several displays.
+    pub local_multi_device: bool,
+}
+
+impl DeviceGroupPresentModes {
+    /// Builds a `DeviceGroupPresentModes` with all fields set to false.
+    #[inline]
+    pub fn none() -> DeviceGroupPresentModes {
+        DeviceGroupPresentModes {
+            local: false,
+            remote: false,
+            sum: false,
+            local_multi_device: false,
+        }
+    }
+
+    /// Returns true if all the modes of `other` are also in `self`.
+    #[inline]
+    pub fn contains(&self, other: &DeviceGroupPresentModes) -> bool {
+        (self.local || !other.local)
+            && (self.remote || !other.remote)
+            && (self.sum || !other.sum)
+            && (self.local_multi_device || !other.local_multi_device)
+    }
+}
+
+impl From<ash::vk::DeviceGroupPresentModeFlagsKHR> for DeviceGroupPresentModes {
+    #[inline]
+    fn from(val: ash::vk::DeviceGroupPresentModeFlagsKHR) -> DeviceGroupPresentModes {
+        DeviceGroupPresentModes {
+            local: !(val & ash::vk::DeviceGroupPresentModeFlagsKHR::LOCAL).is_empty(),
+            remote: !(val & ash::vk::DeviceGroupPresentModeFlagsKHR::REMOTE).is_empty(),
+            sum: !(val & ash::vk::DeviceGroupPresentModeFlagsKHR::SUM).is_empty(),
+            local_multi_device: !(val
+                & ash::vk::DeviceGroupPresentModeFlagsKHR::LOCAL_MULTI_DEVICE)
+                .is_empty(),
+        }
+    }
+}
+
+impl From<DeviceGroupPresentModes> for ash::vk::DeviceGroupPresentModeFlagsKHR {
+    #[inline]
+    fn from(val: DeviceGroupPresentModes) -> Self {
+        let mut result = ash::vk::DeviceGroupPresentModeFlagsKHR::empty();
+        if val.local {
+            result |= ash::vk::DeviceGroupPresentModeFlagsKHR::LOCAL;
+        }
+        if val.remote {
+            result |= ash::vk::DeviceGroupPresentModeFlagsKHR::REMOTE;
+        }
+        if val.sum {
+            result |= ash::vk::DeviceGroupPresentModeFlagsKHR::SUM;
+        }
+        if val.local_multi_device {
+            result |= ash::vk::DeviceGroupPresentModeFlagsKHR::LOCAL_MULTI_DEVICE;
+        }
+        result
+    }
+}
+
+/// The way the physical devices of a device group present one image of a swapchain.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum DeviceGroupPresentMode {
+    /// The physical device of the device mask presents the image from its own memory.
+    Local = ash::vk::DeviceGroupPresentModeFlagsKHR::LOCAL.as_raw(),
+    /// The physical device of the device mask presents the image from the memory of another
+    /// physical device.
+    Remote = ash::vk::DeviceGroupPresentModeFlagsKHR::REMOTE.as_raw(),
+    /// The sum of the images in the memory of the physical devices of the device mask is
+    /// presented.
+    Sum = ash::vk::DeviceGroupPresentModeFlagsKHR::SUM.as_raw(),
+    /// Each physical device of the device mask presents the image from its own memory.
+    LocalMultiDevice = ash::vk::DeviceGroupPresentModeFlagsKHR::LOCAL_MULTI_DEVICE.as_raw(),
+}
+
+impl DeviceGroupPresentMode {
+    // Returns true if `device_mask` has a valid number of physical devices for this mode.
+    #[inline]
+    pub(crate) fn is_valid_device_mask(&self, device_mask: u32) -> bool {
+        match *self {
+            DeviceGroupPresentMode::Local | DeviceGroupPresentMode::Remote => {
+                device_mask.count_ones() == 1
+            }
+            DeviceGroupPresentMode::Sum | DeviceGroupPresentMode::LocalMultiDevice => {
+                device_mask != 0
+            }
+        }
+    }
+}
+
+impl From<DeviceGroupPresentMode> for DeviceGroupPresentModes {
+    #[inline]
+    fn from(val: DeviceGroupPresentMode) -> Self {
+        ash::vk::DeviceGroupPresentModeFlagsKHR::from(val).into()
+    }
+}
+
+impl From<DeviceGroupPresentMode> for ash::vk::DeviceGroupPresentModeFlagsKHR {
+    #[inline]
+    fn from(val: DeviceGroupPresentMode) -> Self {
+        Self::from_raw(val as u32)
+    }
+}
+
 /// List of supported composite alpha modes.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct SupportedSurfaceTransforms {
@@ -608,6 +747,13 @@ impl Default for SurfaceTransform {
 /// Additionally you can try detect whether the implementation supports any additional color space
 /// and perform a manual conversion to that color space from inside your shader.
 ///
+/// # HDR
+///
+/// The color spaces other than `SrgbNonLinear` and `DisplayNative` require the
+/// `VK_EXT_swapchain_colorspace` instance extension. To output HDR content, create the swapchain
+/// with one of them, such as `Hdr10St2084` with a 10-bit format or `ExtendedSrgbLinear` with a
+/// 16-bit float format, then describe the content with `Swapchain::set_hdr_metadata`.
+///
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(i32)]
 pub enum ColorSpace {
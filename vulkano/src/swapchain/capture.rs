@@ -0,0 +1,411 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::buffer::cpu_access::ReadLockError;
+use crate::buffer::BufferUsage;
+use crate::buffer::CpuAccessibleBuffer;
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::BlitImageError;
+use crate::command_buffer::BuildError;
+use crate::command_buffer::CommandBufferExecError;
+use crate::command_buffer::CommandBufferExecFuture;
+use crate::command_buffer::CommandBufferUsage;
+use crate::command_buffer::CopyBufferImageError;
+use crate::command_buffer::PrimaryAutoCommandBuffer;
+use crate::device::Queue;
+use crate::format::Format;
+use crate::image::ImageAccess;
+use crate::image::ImageCreateFlags;
+use crate::image::ImageCreationError;
+use crate::image::ImageDimensions;
+use crate::image::ImageUsage;
+use crate::image::StorageImage;
+use crate::memory::DeviceMemoryAllocError;
+use crate::sampler::Filter;
+use crate::sync::GpuFuture;
+use crate::DeviceSize;
+use crate::OomError;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+/// Copies the content of an image, typically a swapchain image, into a buffer that can be read
+/// from the CPU.
+///
+/// The copy is executed on `queue` after `before`. For a swapchain image, `before` must have
+/// acquired the image, and the copy is usually inserted between rendering and presenting. The
+/// swapchain must have been created with the `transfer_source` usage.
+///
+/// Images in the `R8G8B8A8` and `B8G8R8A8` formats are copied directly. Images in other formats
+/// are first blitted into an intermediate `R8G8B8A8Srgb` image, which requires the format to
+/// support being the source of a blit. Only the first layer of the image is copied.
+///
+/// Once the returned future has been flushed and its fence has been signalled, the pixels can
+/// be read with `Capture::pixels`.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use vulkano::device::Queue;
+/// # use vulkano::image::SwapchainImage;
+/// # use vulkano::swapchain::Swapchain;
+/// # use vulkano::swapchain::SwapchainAcquireFuture;
+/// use vulkano::swapchain;
+/// use vulkano::sync::GpuFuture;
+///
+/// # let queue: Arc<Queue> = return;
+/// # let swapchain: Arc<Swapchain<()>> = return;
+/// # let images: Vec<Arc<SwapchainImage<()>>> = return;
+/// # let image_num: usize = return;
+/// # let render_future: SwapchainAcquireFuture<()> = return;
+/// let (future, capture) =
+///     swapchain::capture(render_future, queue.clone(), images[image_num].clone()).unwrap();
+///
+/// future
+///     .then_swapchain_present(queue.clone(), swapchain.clone(), image_num)
+///     .then_signal_fence_and_flush()
+///     .unwrap()
+///     .wait(None)
+///     .unwrap();
+///
+/// let pixels = capture.pixels().unwrap();
+/// ```
+pub fn capture<F, I>(
+    before: F,
+    queue: Arc<Queue>,
+    image: I,
+) -> Result<
+    (
+        CommandBufferExecFuture<F, PrimaryAutoCommandBuffer>,
+        Capture,
+    ),
+    CaptureError,
+>
+where
+    F: GpuFuture,
+    I: ImageAccess + Send + Sync + 'static,
+{
+    let device = queue.device().clone();
+    let format = image.format();
+    let [width, height] = image.dimensions().width_height();
+
+    if !image.inner().image.usage().transfer_source {
+        return Err(CaptureError::MissingTransferSourceUsage);
+    }
+
+    let swizzle_bgra = match format {
+        Format::R8G8B8A8Unorm | Format::R8G8B8A8Srgb => Some(false),
+        Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb => Some(true),
+        _ => None,
+    };
+
+    let buffer = unsafe {
+        CpuAccessibleBuffer::<[u8]>::uninitialized_array(
+            device.clone(),
+            width as DeviceSize * height as DeviceSize * 4,
+            BufferUsage::transfer_destination(),
+            true,
+        )?
+    };
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        device.clone(),
+        queue.family(),
+        CommandBufferUsage::OneTimeSubmit,
+    )?;
+
+    let swizzle_bgra = match swizzle_bgra {
+        Some(swizzle_bgra) => {
+            builder.copy_image_to_buffer_dimensions(
+                image,
+                buffer.clone(),
+                [0, 0, 0],
+                [width, height, 1],
+                0,
+                1,
+                0,
+            )?;
+            swizzle_bgra
+        }
+        None => {
+            let intermediate_format = Format::R8G8B8A8Srgb;
+
+            if !image.supports_blit_source()
+                || !intermediate_format
+                    .properties(device.physical_device())
+                    .optimal_tiling_features
+                    .blit_dst
+            {
+                return Err(CaptureError::UnsupportedFormat(format));
+            }
+
+            let intermediate = StorageImage::with_usage(
+                device.clone(),
+                ImageDimensions::Dim2d {
+                    width,
+                    height,
+                    array_layers: 1,
+                },
+                intermediate_format,
+                ImageUsage {
+                    transfer_source: true,
+                    transfer_destination: true,
+                    ..ImageUsage::none()
+                },
+                ImageCreateFlags::none(),
+                Some(queue.family()),
+            )?;
+
+            builder
+                .blit_image(
+                    image,
+                    [0, 0, 0],
+                    [width as i32, height as i32, 1],
+                    0,
+                    0,
+                    intermediate.clone(),
+                    [0, 0, 0],
+                    [width as i32, height as i32, 1],
+                    0,
+                    0,
+                    1,
+                    Filter::Nearest,
+                )?
+                .copy_image_to_buffer(intermediate, buffer.clone())?;
+            false
+        }
+    };
+
+    let future = before.then_execute(queue, builder.build()?)?;
+
+    Ok((
+        future,
+        Capture {
+            buffer,
+            dimensions: [width, height],
+            swizzle_bgra,
+        },
+    ))
+}
+
+/// Pixels of an image copied by `capture`.
+#[derive(Debug)]
+pub struct Capture {
+    buffer: Arc<CpuAccessibleBuffer<[u8]>>,
+    dimensions: [u32; 2],
+    swizzle_bgra: bool,
+}
+
+impl Capture {
+    /// Returns the width and height of the captured image.
+    #[inline]
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.dimensions
+    }
+
+    /// Returns the pixels of the image, as tightly-packed rows of RGBA values with 8 bits per
+    /// component.
+    ///
+    /// Returns an error if the GPU hasn't finished copying the image yet.
+    pub fn pixels(&self) -> Result<Vec<u8>, ReadLockError> {
+        let content = self.buffer.read()?;
+        let mut pixels = content.to_vec();
+
+        if self.swizzle_bgra {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        Ok(pixels)
+    }
+}
+
+/// Error that can happen when capturing an image.
+#[derive(Debug, Clone)]
+pub enum CaptureError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// Error while allocating the buffer that receives the pixels.
+    DeviceMemoryAllocError(DeviceMemoryAllocError),
+    /// Error while creating the intermediate image used for format conversion.
+    ImageCreationError(ImageCreationError),
+    /// Error while recording the blit command.
+    BlitImageError(BlitImageError),
+    /// Error while recording the copy command.
+    CopyBufferImageError(CopyBufferImageError),
+    /// Error while building the command buffer.
+    BuildError(BuildError),
+    /// Error while executing the command buffer.
+    CommandBufferExecError(CommandBufferExecError),
+    /// The image wasn't created with the `transfer_source` usage.
+    MissingTransferSourceUsage,
+    /// The format of the image can't be converted to RGBA.
+    UnsupportedFormat(Format),
+}
+
+impl error::Error for CaptureError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            CaptureError::OomError(ref err) => Some(err),
+            CaptureError::DeviceMemoryAllocError(ref err) => Some(err),
+            CaptureError::ImageCreationError(ref err) => Some(err),
+            CaptureError::BlitImageError(ref err) => Some(err),
+            CaptureError::CopyBufferImageError(ref err) => Some(err),
+            CaptureError::BuildError(ref err) => Some(err),
+            CaptureError::CommandBufferExecError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CaptureError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                CaptureError::OomError(_) => "not enough memory",
+                CaptureError::DeviceMemoryAllocError(_) => {
+                    "error while allocating the buffer that receives the pixels"
+                }
+                CaptureError::ImageCreationError(_) => {
+                    "error while creating the intermediate image"
+                }
+                CaptureError::BlitImageError(_) => "error while recording the blit command",
+                CaptureError::CopyBufferImageError(_) => "error while recording the copy command",
+                CaptureError::BuildError(_) => "error while building the command buffer",
+                CaptureError::CommandBufferExecError(_) => {
+                    "error while executing the command buffer"
+                }
+                CaptureError::MissingTransferSourceUsage => {
+                    "the image wasn't created with the `transfer_source` usage"
+                }
+                CaptureError::UnsupportedFormat(_) => {
+                    "the format of the image can't be converted to RGBA"
+                }
+            }
+        )
+    }
+}
+
+impl From<OomError> for CaptureError {
+    #[inline]
+    fn from(err: OomError) -> CaptureError {
+        CaptureError::OomError(err)
+    }
+}
+
+impl From<DeviceMemoryAllocError> for CaptureError {
+    #[inline]
+    fn from(err: DeviceMemoryAllocError) -> CaptureError {
+        CaptureError::DeviceMemoryAllocError(err)
+    }
+}
+
+impl From<ImageCreationError> for CaptureError {
+    #[inline]
+    fn from(err: ImageCreationError) -> CaptureError {
+        CaptureError::ImageCreationError(err)
+    }
+}
+
+impl From<BlitImageError> for CaptureError {
+    #[inline]
+    fn from(err: BlitImageError) -> CaptureError {
+        CaptureError::BlitImageError(err)
+    }
+}
+
+impl From<CopyBufferImageError> for CaptureError {
+    #[inline]
+    fn from(err: CopyBufferImageError) -> CaptureError {
+        CaptureError::CopyBufferImageError(err)
+    }
+}
+
+impl From<BuildError> for CaptureError {
+    #[inline]
+    fn from(err: BuildError) -> CaptureError {
+        CaptureError::BuildError(err)
+    }
+}
+
+impl From<CommandBufferExecError> for CaptureError {
+    #[inline]
+    fn from(err: CommandBufferExecError) -> CaptureError {
+        CaptureError::CommandBufferExecError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_buffer::AutoCommandBufferBuilder;
+    use crate::command_buffer::CommandBufferUsage;
+    use crate::format::ClearValue;
+    use crate::format::Format;
+    use crate::image::ImageCreateFlags;
+    use crate::image::ImageDimensions;
+    use crate::image::ImageUsage;
+    use crate::image::StorageImage;
+    use crate::swapchain::capture;
+    use crate::sync::now;
+    use crate::sync::GpuFuture;
+
+    #[test]
+    fn bgra_is_swizzled() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = StorageImage::with_usage(
+            device.clone(),
+            ImageDimensions::Dim2d {
+                width: 4,
+                height: 2,
+                array_layers: 1,
+            },
+            Format::B8G8R8A8Unorm,
+            ImageUsage {
+                transfer_source: true,
+                transfer_destination: true,
+                ..ImageUsage::none()
+            },
+            ImageCreateFlags::none(),
+            Some(queue.family()),
+        )
+        .unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder
+            .clear_color_image(image.clone(), ClearValue::Float([1.0, 0.0, 0.0, 1.0]))
+            .unwrap();
+        let future = now(device.clone())
+            .then_execute(queue.clone(), builder.build().unwrap())
+            .unwrap();
+
+        let (future, capture) = capture(future, queue.clone(), image).unwrap();
+        future
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        assert_eq!(capture.dimensions(), [4, 2]);
+        let pixels = capture.pixels().unwrap();
+        assert_eq!(pixels.len(), 4 * 2 * 4);
+        assert!(pixels.chunks(4).all(|pixel| pixel == [255, 0, 0, 255]));
+    }
+}
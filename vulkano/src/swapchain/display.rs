@@ -20,10 +20,16 @@
 //!   `Display::enumerate`.
 //! - Choose a `DisplayMode`, which is the combination of a display, a resolution and a refresh
 //!   rate. You can enumerate the modes available on a display with `Display::display_modes`, or
-//!   attempt to create your own mode with `TODO`.
-//! - Choose a `DisplayPlane`. A display can show multiple planes in a stacking fashion.
+//!   attempt to create your own mode with `DisplayMode::new`.
+//! - Choose a `DisplayPlane`. A display can show multiple planes in a stacking fashion. The
+//!   capabilities of a plane for a given mode can be queried with `DisplayPlane::capabilities`.
 //! - Create a `Surface` object with `Surface::from_display_mode` and pass the chosen `DisplayMode`
-//!   and `DisplayPlane`.
+//!   and `DisplayPlane`, or with `Surface::from_display_plane` to also choose how the surface is
+//!   placed on the plane.
+//!
+//! Swapchains created on such a surface can be presented with `swapchain::present_display` to
+//! choose which region of the images is shown on which region of the display. This requires the
+//! `VK_KHR_display_swapchain` device extension.
 
 #![allow(dead_code)] // TODO: this module isn't finished
 #![allow(unused_variables)] // TODO: this module isn't finished
@@ -32,18 +38,20 @@ use crate::check_errors;
 use crate::device::physical::PhysicalDevice;
 use crate::instance::Instance;
 use crate::swapchain::SupportedSurfaceTransforms;
+use crate::Error;
 use crate::OomError;
 use crate::VulkanObject;
+use std::error;
 use std::ffi::CStr;
 use std::fmt::Formatter;
+use std::mem::MaybeUninit;
 use std::sync::Arc;
 use std::vec::IntoIter;
 use std::{fmt, ptr};
 
 // TODO: extract this to a `display` module and solve the visibility problems
 
-/// ?
-// TODO: plane capabilities
+/// A plane of a physical device, on which a surface can be shown.
 // TODO: store properties in the instance?
 pub struct DisplayPlane {
     instance: Arc<Instance>,
@@ -154,6 +162,57 @@ impl DisplayPlane {
         self.index
     }
 
+    /// Returns the index of the plane in the stack of the display it is currently associated
+    /// with.
+    #[inline]
+    pub fn current_stack_index(&self) -> u32 {
+        self.properties.current_stack_index
+    }
+
+    /// Queries the capabilities of this plane when used with the given display mode.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `display_mode` doesn't belong to the same physical device as this plane.
+    ///
+    pub fn capabilities(
+        &self,
+        display_mode: &DisplayMode,
+    ) -> Result<DisplayPlaneCapabilities, OomError> {
+        assert_eq!(
+            self.physical_device().internal_object(),
+            display_mode.display().physical_device().internal_object()
+        );
+
+        let fns = self.instance.fns();
+
+        let capabilities = unsafe {
+            let mut output = MaybeUninit::uninit();
+            check_errors(fns.khr_display.get_display_plane_capabilities_khr(
+                self.physical_device().internal_object(),
+                display_mode.internal_object(),
+                self.index,
+                output.as_mut_ptr(),
+            ))?;
+            output.assume_init()
+        };
+
+        let offset = |o: ash::vk::Offset2D| [o.x, o.y];
+        let extent = |e: ash::vk::Extent2D| [e.width, e.height];
+
+        Ok(DisplayPlaneCapabilities {
+            supported_alpha: capabilities.supported_alpha.into(),
+            min_src_position: offset(capabilities.min_src_position),
+            max_src_position: offset(capabilities.max_src_position),
+            min_src_extent: extent(capabilities.min_src_extent),
+            max_src_extent: extent(capabilities.max_src_extent),
+            min_dst_position: offset(capabilities.min_dst_position),
+            max_dst_position: offset(capabilities.max_dst_position),
+            min_dst_extent: extent(capabilities.min_dst_extent),
+            max_dst_extent: extent(capabilities.max_dst_extent),
+        })
+    }
+
     /// Returns true if this plane supports the given display.
     #[inline]
     pub fn supports(&self, display: &Display) -> bool {
@@ -169,6 +228,80 @@ impl DisplayPlane {
     }
 }
 
+/// Capabilities of a display plane when used with a display mode.
+///
+/// The positions and extents describe the regions of the presented images (`src`) and of the
+/// display mode (`dst`) that can be used when presenting on a surface of this plane.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DisplayPlaneCapabilities {
+    /// The ways the plane can be blended with the planes below it.
+    pub supported_alpha: SupportedDisplayPlaneAlpha,
+    pub min_src_position: [i32; 2],
+    pub max_src_position: [i32; 2],
+    pub min_src_extent: [u32; 2],
+    pub max_src_extent: [u32; 2],
+    pub min_dst_position: [i32; 2],
+    pub max_dst_position: [i32; 2],
+    pub min_dst_extent: [u32; 2],
+    pub max_dst_extent: [u32; 2],
+}
+
+/// How a display plane is blended with the planes below it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum DisplayPlaneAlpha {
+    /// The plane is opaque.
+    Opaque = ash::vk::DisplayPlaneAlphaFlagsKHR::OPAQUE.as_raw(),
+    /// The plane is blended using the global alpha value passed when creating the surface.
+    Global = ash::vk::DisplayPlaneAlphaFlagsKHR::GLOBAL.as_raw(),
+    /// The plane is blended using the alpha component of the presented images.
+    PerPixel = ash::vk::DisplayPlaneAlphaFlagsKHR::PER_PIXEL.as_raw(),
+    /// Same as `PerPixel`, except that the color components are premultiplied by alpha.
+    PerPixelPremultiplied = ash::vk::DisplayPlaneAlphaFlagsKHR::PER_PIXEL_PREMULTIPLIED.as_raw(),
+}
+
+impl From<DisplayPlaneAlpha> for ash::vk::DisplayPlaneAlphaFlagsKHR {
+    #[inline]
+    fn from(val: DisplayPlaneAlpha) -> Self {
+        Self::from_raw(val as u32)
+    }
+}
+
+/// List of supported blending modes of a display plane.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SupportedDisplayPlaneAlpha {
+    pub opaque: bool,
+    pub global: bool,
+    pub per_pixel: bool,
+    pub per_pixel_premultiplied: bool,
+}
+
+impl From<ash::vk::DisplayPlaneAlphaFlagsKHR> for SupportedDisplayPlaneAlpha {
+    #[inline]
+    fn from(val: ash::vk::DisplayPlaneAlphaFlagsKHR) -> SupportedDisplayPlaneAlpha {
+        SupportedDisplayPlaneAlpha {
+            opaque: val.intersects(ash::vk::DisplayPlaneAlphaFlagsKHR::OPAQUE),
+            global: val.intersects(ash::vk::DisplayPlaneAlphaFlagsKHR::GLOBAL),
+            per_pixel: val.intersects(ash::vk::DisplayPlaneAlphaFlagsKHR::PER_PIXEL),
+            per_pixel_premultiplied: val
+                .intersects(ash::vk::DisplayPlaneAlphaFlagsKHR::PER_PIXEL_PREMULTIPLIED),
+        }
+    }
+}
+
+impl SupportedDisplayPlaneAlpha {
+    /// Returns true if the given blending mode is in this list.
+    #[inline]
+    pub fn supports(&self, value: DisplayPlaneAlpha) -> bool {
+        match value {
+            DisplayPlaneAlpha::Opaque => self.opaque,
+            DisplayPlaneAlpha::Global => self.global,
+            DisplayPlaneAlpha::PerPixel => self.per_pixel,
+            DisplayPlaneAlpha::PerPixelPremultiplied => self.per_pixel_premultiplied,
+        }
+    }
+}
+
 /// Represents a monitor connected to a physical device.
 // TODO: store properties in the instance?
 #[derive(Clone)]
@@ -351,35 +484,49 @@ pub struct DisplayMode {
 }
 
 impl DisplayMode {
-    /*pub fn new(display: &Display) -> Result<Arc<DisplayMode>, OomError> {
-        let fns = instance.fns();
-        assert!(device.instance().enabled_extensions().khr_display);     // TODO: return error instead
+    /// Creates a new display mode on `display`, with the given visible region and refresh rate.
+    ///
+    /// The refresh rate is in millihertz, as returned by `refresh_rate`.
+    pub fn new(
+        display: &Display,
+        visible_region: [u32; 2],
+        refresh_rate: u32,
+    ) -> Result<DisplayMode, DisplayModeCreationError> {
+        let fns = display.instance.fns();
+        assert!(display.instance.enabled_extensions().khr_display); // TODO: return error instead
 
         let parameters = ash::vk::DisplayModeParametersKHR {
-            visibleRegion: ash::vk::Extent2D { width: , height:  },
-            refreshRate: ,
+            visible_region: ash::vk::Extent2D {
+                width: visible_region[0],
+                height: visible_region[1],
+            },
+            refresh_rate,
         };
 
-        let display_mode = {
+        let display_mode = unsafe {
             let infos = ash::vk::DisplayModeCreateInfoKHR {
-                flags: ash::vk::DisplayModeCreateFlags::empty(),
-                parameters: parameters,
+                flags: ash::vk::DisplayModeCreateFlagsKHR::empty(),
+                parameters,
                 ..Default::default()
             };
 
-            let mut output = mem::uninitialized();
-            check_errors(fns.v1_0.CreateDisplayModeKHR(display.device.internal_object(),
-                                                      display.display, &infos, ptr::null(),
-                                                      &mut output))?;
-            output
+            let mut output = MaybeUninit::uninit();
+            check_errors(fns.khr_display.create_display_mode_khr(
+                display.physical_device().internal_object(),
+                display.internal_object(),
+                &infos,
+                ptr::null(),
+                output.as_mut_ptr(),
+            ))?;
+            output.assume_init()
         };
 
-        Ok(Arc::new(DisplayMode {
-            instance: display.device.instance().clone(),
-            display_mode: display_mode,
-            parameters: ,
-        }))
-    }*/
+        Ok(DisplayMode {
+            display: display.clone(),
+            display_mode,
+            parameters,
+        })
+    }
 
     /// Returns the display corresponding to this mode.
     #[inline]
@@ -428,3 +575,136 @@ unsafe impl VulkanObject for DisplayMode {
         self.display_mode
     }
 }
+
+/// Error that can happen when creating a display mode.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DisplayModeCreationError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The display doesn't support the requested parameters.
+    UnsupportedParameters,
+}
+
+impl error::Error for DisplayModeCreationError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            DisplayModeCreationError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DisplayModeCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                DisplayModeCreationError::OomError(_) => "not enough memory available",
+                DisplayModeCreationError::UnsupportedParameters => {
+                    "the display doesn't support the requested parameters"
+                }
+            }
+        )
+    }
+}
+
+impl From<Error> for DisplayModeCreationError {
+    #[inline]
+    fn from(err: Error) -> DisplayModeCreationError {
+        match err {
+            err @ Error::OutOfHostMemory => DisplayModeCreationError::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => {
+                DisplayModeCreationError::OomError(OomError::from(err))
+            }
+            Error::InitializationFailed => DisplayModeCreationError::UnsupportedParameters,
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+/// Parameters of the presentation of an image on a display, to be passed to
+/// `swapchain::present_display`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DisplayPresentInfo {
+    /// Offset of the region of the image to present.
+    pub src_offset: [i32; 2],
+    /// Extent of the region of the image to present.
+    pub src_extent: [u32; 2],
+    /// Offset of the region of the visible region of the display mode to present to.
+    pub dst_offset: [i32; 2],
+    /// Extent of the region of the visible region of the display mode to present to.
+    pub dst_extent: [u32; 2],
+    /// If true, the display engine enables buffered mode on displays that support it, so that
+    /// the image stays on the display without being refreshed.
+    pub persistent: bool,
+}
+
+impl From<&DisplayPresentInfo> for ash::vk::DisplayPresentInfoKHR {
+    #[inline]
+    fn from(val: &DisplayPresentInfo) -> Self {
+        let rect = |offset: [i32; 2], extent: [u32; 2]| ash::vk::Rect2D {
+            offset: ash::vk::Offset2D {
+                x: offset[0],
+                y: offset[1],
+            },
+            extent: ash::vk::Extent2D {
+                width: extent[0],
+                height: extent[1],
+            },
+        };
+
+        Self {
+            src_rect: rect(val.src_offset, val.src_extent),
+            dst_rect: rect(val.dst_offset, val.dst_extent),
+            persistent: val.persistent as ash::vk::Bool32,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::device::physical::PhysicalDevice;
+    use crate::swapchain::display::Display;
+    use crate::swapchain::display::DisplayPlane;
+    use crate::swapchain::display::DisplayPresentInfo;
+
+    #[test]
+    fn khr_display_ext_missing() {
+        let instance = instance!();
+        let physical = match PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return,
+        };
+
+        assert_should_panic!({
+            let _ = Display::enumerate_raw(physical);
+        });
+        assert_should_panic!({
+            let _ = DisplayPlane::enumerate_raw(physical);
+        });
+    }
+
+    #[test]
+    fn present_info_to_vk() {
+        let info = DisplayPresentInfo {
+            src_offset: [0, 0],
+            src_extent: [640, 480],
+            dst_offset: [-16, 32],
+            dst_extent: [1280, 960],
+            persistent: true,
+        };
+
+        let vk = ash::vk::DisplayPresentInfoKHR::from(&info);
+        assert_eq!(vk.s_type, ash::vk::StructureType::DISPLAY_PRESENT_INFO_KHR);
+        assert_eq!(
+            (vk.src_rect.extent.width, vk.src_rect.extent.height),
+            (640, 480)
+        );
+        assert_eq!((vk.dst_rect.offset.x, vk.dst_rect.offset.y), (-16, 32));
+        assert_eq!(vk.persistent, ash::vk::TRUE);
+    }
+}
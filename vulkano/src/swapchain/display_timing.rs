@@ -0,0 +1,311 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Frame pacing with the `VK_GOOGLE_display_timing` extension.
+//!
+//! With this extension, each present can be tagged with an identifier and the earliest time at
+//! which the image should be shown. The presentation engine later reports when each tagged image
+//! was actually shown, which can be queried with `Swapchain::past_presentation_timing`.
+//!
+//! `FramePacer` uses this feedback to compute the desired presentation times of the next frames,
+//! so that frames are shown at a stable rate of one every N refresh cycles.
+
+use crate::Error;
+use crate::OomError;
+use std::error;
+use std::fmt;
+use std::time::Duration;
+
+/// Identifier and desired presentation time of a present, to be passed to
+/// `swapchain::present_timed`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PresentTime {
+    /// Identifier of the present, which is returned by `Swapchain::past_presentation_timing`.
+    /// The value 0 means that the present has no identifier.
+    pub present_id: u32,
+    /// The earliest time at which the image should be shown, in nanoseconds, in the same time
+    /// domain as the times returned by `Swapchain::past_presentation_timing`. The value 0 means
+    /// that the image can be shown at any time.
+    pub desired_present_time: u64,
+}
+
+impl From<PresentTime> for ash::vk::PresentTimeGOOGLE {
+    #[inline]
+    fn from(val: PresentTime) -> Self {
+        Self {
+            present_id: val.present_id,
+            desired_present_time: val.desired_present_time,
+        }
+    }
+}
+
+/// Timing of a past present, as returned by `Swapchain::past_presentation_timing`.
+///
+/// All times are in nanoseconds.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PastPresentationTiming {
+    /// Identifier that was passed when presenting.
+    pub present_id: u32,
+    /// Desired presentation time that was passed when presenting.
+    pub desired_present_time: u64,
+    /// The time at which the image was actually shown.
+    pub actual_present_time: u64,
+    /// The earliest time at which the image could have been shown.
+    pub earliest_present_time: u64,
+    /// How early the present was processed compared to the time it was shown.
+    pub present_margin: u64,
+}
+
+impl From<ash::vk::PastPresentationTimingGOOGLE> for PastPresentationTiming {
+    #[inline]
+    fn from(val: ash::vk::PastPresentationTimingGOOGLE) -> Self {
+        Self {
+            present_id: val.present_id,
+            desired_present_time: val.desired_present_time,
+            actual_present_time: val.actual_present_time,
+            earliest_present_time: val.earliest_present_time,
+            present_margin: val.present_margin,
+        }
+    }
+}
+
+/// Computes the desired presentation times of successive frames from past presentation feedback.
+///
+/// Each frame is meant to be shown `frame_interval` refresh cycles after the previous one. For
+/// example, with a 60 Hz display, an interval of 2 targets a stable 30 fps.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use vulkano::swapchain::display_timing::FramePacer;
+///
+/// // Normally obtained with `Swapchain::refresh_cycle_duration`.
+/// let refresh_cycle_duration = Duration::from_nanos(16_666_667);
+/// let mut pacer = FramePacer::new(refresh_cycle_duration, 2);
+///
+/// // Every frame:
+/// // pacer.update(&swapchain.past_presentation_timing().unwrap());
+/// let present_time = pacer.next_present_time();
+/// // swapchain::present_timed(swapchain.clone(), future, queue.clone(), image_num, present_time);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FramePacer {
+    refresh_cycle_duration: u64,
+    frame_interval: u32,
+    next_present_id: u32,
+    last_timing: Option<PastPresentationTiming>,
+}
+
+impl FramePacer {
+    /// Builds a new `FramePacer` that targets one frame every `frame_interval` refresh cycles.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `frame_interval` is 0.
+    ///
+    #[inline]
+    pub fn new(refresh_cycle_duration: Duration, frame_interval: u32) -> FramePacer {
+        assert_ne!(frame_interval, 0);
+
+        FramePacer {
+            refresh_cycle_duration: refresh_cycle_duration.as_nanos() as u64,
+            frame_interval,
+            next_present_id: 1,
+            last_timing: None,
+        }
+    }
+
+    /// Returns the number of refresh cycles between two frames.
+    #[inline]
+    pub fn frame_interval(&self) -> u32 {
+        self.frame_interval
+    }
+
+    /// Sets the number of refresh cycles between two frames.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `frame_interval` is 0.
+    ///
+    #[inline]
+    pub fn set_frame_interval(&mut self, frame_interval: u32) {
+        assert_ne!(frame_interval, 0);
+        self.frame_interval = frame_interval;
+    }
+
+    /// Takes into account the feedback returned by `Swapchain::past_presentation_timing`.
+    pub fn update(&mut self, timings: &[PastPresentationTiming]) {
+        for timing in timings {
+            let is_newer = match self.last_timing {
+                Some(last) => timing.present_id > last.present_id,
+                None => true,
+            };
+
+            if is_newer && timing.actual_present_time != 0 {
+                self.last_timing = Some(*timing);
+            }
+        }
+    }
+
+    /// Returns the identifier and desired presentation time of the next frame.
+    ///
+    /// Until some feedback has been passed to `update`, the desired presentation time is 0,
+    /// meaning that the frame is to be shown as soon as possible.
+    pub fn next_present_time(&mut self) -> PresentTime {
+        let present_id = self.next_present_id;
+        self.next_present_id = self.next_present_id.wrapping_add(1).max(1);
+
+        let desired_present_time = match self.last_timing {
+            Some(last) => {
+                let frames_since = present_id.wrapping_sub(last.present_id) as u64;
+                let period = self.refresh_cycle_duration * self.frame_interval as u64;
+                let target = last.actual_present_time + period * frames_since;
+
+                // Stay half a refresh cycle early, so that small variations in the time at which
+                // the presentation engine latches the image don't make it miss the target cycle.
+                target.saturating_sub(self.refresh_cycle_duration / 2)
+            }
+            None => 0,
+        };
+
+        PresentTime {
+            present_id,
+            desired_present_time,
+        }
+    }
+}
+
+/// Error that can happen when querying the display timing of a swapchain.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DisplayTimingError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The connection to the device has been lost.
+    DeviceLost,
+    /// The surface is no longer accessible and must be recreated.
+    SurfaceLost,
+    /// The swapchain is out of date and must be recreated.
+    OutOfDate,
+    /// The `VK_GOOGLE_display_timing` extension was not enabled.
+    MissingExtension,
+}
+
+impl error::Error for DisplayTimingError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            DisplayTimingError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DisplayTimingError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                DisplayTimingError::OomError(_) => "not enough memory",
+                DisplayTimingError::DeviceLost => "the connection to the device has been lost",
+                DisplayTimingError::SurfaceLost =>
+                    "the surface of this swapchain is no longer valid",
+                DisplayTimingError::OutOfDate => "the swapchain needs to be recreated",
+                DisplayTimingError::MissingExtension => {
+                    "the `VK_GOOGLE_display_timing` extension was not enabled"
+                }
+            }
+        )
+    }
+}
+
+impl From<Error> for DisplayTimingError {
+    #[inline]
+    fn from(err: Error) -> DisplayTimingError {
+        match err {
+            err @ Error::OutOfHostMemory => DisplayTimingError::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => DisplayTimingError::OomError(OomError::from(err)),
+            Error::DeviceLost => DisplayTimingError::DeviceLost,
+            Error::SurfaceLost => DisplayTimingError::SurfaceLost,
+            Error::OutOfDate => DisplayTimingError::OutOfDate,
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FramePacer;
+    use super::PastPresentationTiming;
+    use std::time::Duration;
+
+    #[test]
+    fn no_feedback() {
+        let mut pacer = FramePacer::new(Duration::from_nanos(1000), 1);
+
+        let first = pacer.next_present_time();
+        let second = pacer.next_present_time();
+        assert_eq!(first.present_id, 1);
+        assert_eq!(second.present_id, 2);
+        assert_eq!(first.desired_present_time, 0);
+        assert_eq!(second.desired_present_time, 0);
+    }
+
+    #[test]
+    fn targets_interval() {
+        let mut pacer = FramePacer::new(Duration::from_nanos(1000), 2);
+
+        for _ in 0..3 {
+            pacer.next_present_time();
+        }
+
+        pacer.update(&[
+            PastPresentationTiming {
+                present_id: 1,
+                actual_present_time: 10_000,
+                ..Default::default()
+            },
+            PastPresentationTiming {
+                present_id: 2,
+                actual_present_time: 12_000,
+                ..Default::default()
+            },
+        ]);
+
+        // Frame 4 is two frames after frame 2, which is four refresh cycles.
+        let next = pacer.next_present_time();
+        assert_eq!(next.present_id, 4);
+        assert_eq!(next.desired_present_time, 12_000 + 4_000 - 500);
+    }
+
+    #[test]
+    fn ignores_older_feedback() {
+        let mut pacer = FramePacer::new(Duration::from_nanos(1000), 1);
+
+        for _ in 0..2 {
+            pacer.next_present_time();
+        }
+
+        pacer.update(&[PastPresentationTiming {
+            present_id: 2,
+            actual_present_time: 5_000,
+            ..Default::default()
+        }]);
+        pacer.update(&[PastPresentationTiming {
+            present_id: 1,
+            actual_present_time: 4_000,
+            ..Default::default()
+        }]);
+
+        let next = pacer.next_present_time();
+        assert_eq!(next.desired_present_time, 5_000 + 1_000 - 500);
+    }
+}
@@ -0,0 +1,551 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::device::Queue;
+use crate::image::view::ImageView;
+use crate::image::view::ImageViewCreationError;
+use crate::image::SwapchainImage;
+use crate::swapchain::acquire_next_image;
+use crate::swapchain::present;
+use crate::swapchain::AcquireError;
+use crate::swapchain::PresentFuture;
+use crate::swapchain::Surface;
+use crate::swapchain::Swapchain;
+use crate::swapchain::SwapchainAcquireFuture;
+use crate::swapchain::SwapchainBuilder;
+use crate::swapchain::SwapchainCreationError;
+use crate::sync::FlushError;
+use crate::sync::GpuFuture;
+#[cfg(feature = "winit_")]
+use crate::SafeBorrow;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "winit_")]
+use winit::event::{Event, WindowEvent};
+
+/// Image view of a swapchain image, as created by a `SwapchainManager`.
+pub type SwapchainImageView<W> = ImageView<Arc<SwapchainImage<W>>>;
+
+type RecreateCallback<W> = Box<dyn FnMut(&Arc<Swapchain<W>>, &[Arc<SwapchainImageView<W>>]) + Send>;
+
+/// Owns a swapchain and the views of its images, and recreates them when needed.
+///
+/// A swapchain has to be recreated whenever it becomes out of date or suboptimal, typically
+/// after the window has been resized. The `SwapchainManager` keeps track of this: when
+/// `acquire_next_image` or `present` report that the swapchain is no longer adequate, it is
+/// recreated with the same parameters as before the next time an image is acquired. The callback
+/// passed to `set_recreate_callback` is then called so that the resources that depend on the
+/// swapchain images, such as framebuffers, can be rebuilt.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use vulkano::device::Queue;
+/// # use vulkano::swapchain::SwapchainBuilder;
+/// # use vulkano::sync::GpuFuture;
+/// use vulkano::swapchain::SwapchainManager;
+///
+/// # let builder: SwapchainBuilder<()> = return;
+/// # let queue: Arc<Queue> = return;
+/// let mut manager = SwapchainManager::new(builder).unwrap();
+/// manager.set_recreate_callback(|swapchain, image_views| {
+///     // Rebuild the framebuffers here.
+/// });
+///
+/// // In the event loop, when the window is resized:
+/// manager.set_dimensions([1024, 768]);
+///
+/// // When drawing a frame:
+/// let (image_num, acquire_future) = match manager.acquire_next_image(None) {
+///     Ok(r) => r,
+///     // This happens for example when the window is minimized.
+///     Err(_) => return,
+/// };
+///
+/// // ... build the command buffer that draws to `manager.image_views()[image_num]` ...
+///
+/// let future = manager
+///     .present(acquire_future, queue.clone(), image_num)
+///     .then_signal_fence_and_flush();
+/// let future = manager.handle_flush_result(future).unwrap();
+/// ```
+pub struct SwapchainManager<W> {
+    swapchain: Arc<Swapchain<W>>,
+    images: Vec<Arc<SwapchainImage<W>>>,
+    image_views: Vec<Arc<SwapchainImageView<W>>>,
+    dimensions: Option<[u32; 2]>,
+    needs_recreate: bool,
+    on_recreate: Option<RecreateCallback<W>>,
+}
+
+impl<W> SwapchainManager<W> {
+    /// Builds a new swapchain from `builder` and creates views of its images.
+    ///
+    /// Until `set_dimensions` is called, the swapchain is recreated with the current extent of
+    /// the surface.
+    pub fn new(builder: SwapchainBuilder<W>) -> Result<SwapchainManager<W>, SwapchainManagerError> {
+        let (swapchain, images) = builder.build()?;
+        let image_views = create_image_views(&images)?;
+
+        Ok(SwapchainManager {
+            dimensions: None,
+            swapchain,
+            images,
+            image_views,
+            needs_recreate: false,
+            on_recreate: None,
+        })
+    }
+
+    /// Sets the function that is called after the swapchain has been recreated.
+    ///
+    /// The function receives the new swapchain and the views of its images. It isn't called
+    /// for the swapchain that was created by `new`.
+    #[inline]
+    pub fn set_recreate_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(&Arc<Swapchain<W>>, &[Arc<SwapchainImageView<W>>]) + Send + 'static,
+    {
+        self.on_recreate = Some(Box::new(callback));
+    }
+
+    /// Returns the surface of the swapchain.
+    #[inline]
+    pub fn surface(&self) -> &Arc<Surface<W>> {
+        self.swapchain.surface()
+    }
+
+    /// Returns the current swapchain.
+    #[inline]
+    pub fn swapchain(&self) -> &Arc<Swapchain<W>> {
+        &self.swapchain
+    }
+
+    /// Returns the images of the current swapchain.
+    #[inline]
+    pub fn images(&self) -> &[Arc<SwapchainImage<W>>] {
+        &self.images
+    }
+
+    /// Returns the views of the images of the current swapchain.
+    #[inline]
+    pub fn image_views(&self) -> &[Arc<SwapchainImageView<W>>] {
+        &self.image_views
+    }
+
+    /// Sets the dimensions of the images of the swapchain, and marks it for recreation if they
+    /// are different from the current ones.
+    ///
+    /// Call this when the window is resized. This is mandatory on platforms where the surface
+    /// doesn't report its current extent, such as Wayland.
+    #[inline]
+    pub fn set_dimensions(&mut self, dimensions: [u32; 2]) {
+        if dimensions != self.swapchain.dimensions() {
+            self.needs_recreate = true;
+        }
+
+        self.dimensions = Some(dimensions);
+    }
+
+    /// Marks the swapchain for recreation the next time an image is acquired.
+    #[inline]
+    pub fn invalidate(&mut self) {
+        self.needs_recreate = true;
+    }
+
+    /// Returns true if the swapchain will be recreated the next time an image is acquired.
+    #[inline]
+    pub fn needs_recreate(&self) -> bool {
+        self.needs_recreate
+    }
+
+    /// Recreates the swapchain immediately with the same parameters, except for the dimensions,
+    /// then calls the recreate callback.
+    ///
+    /// If this returns an error, the previous swapchain is kept and recreation will be attempted
+    /// again the next time an image is acquired.
+    pub fn recreate(&mut self) -> Result<(), SwapchainManagerError> {
+        // Stays set if recreating fails, so that it is attempted again.
+        self.needs_recreate = true;
+
+        let mut builder = self.swapchain.recreate();
+        if let Some(dimensions) = self.dimensions {
+            builder = builder.dimensions(dimensions);
+        }
+
+        let (swapchain, images) = builder.build()?;
+        let image_views = create_image_views(&images)?;
+
+        self.swapchain = swapchain;
+        self.images = images;
+        self.image_views = image_views;
+        self.needs_recreate = false;
+
+        if let Some(callback) = self.on_recreate.as_mut() {
+            callback(&self.swapchain, &self.image_views);
+        }
+
+        Ok(())
+    }
+
+    /// Acquires the next image of the swapchain, recreating the swapchain first if needed.
+    ///
+    /// Returns the index of the image and a future that represents the moment when the image
+    /// becomes available. If the swapchain is out of date, it is recreated and the acquisition
+    /// is attempted again, up to three times in total. If the acquisition is suboptimal, the image
+    /// is returned and the swapchain will be recreated the next time.
+    ///
+    /// An error is returned if the swapchain can't be recreated, for example because the window
+    /// is minimized, or if it is still out of date after being recreated. In this case, the frame
+    /// should be skipped.
+    pub fn acquire_next_image(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<(usize, SwapchainAcquireFuture<W>), SwapchainManagerError> {
+        acquire_with_recreate(self, timeout)
+    }
+
+    /// Presents an image of the current swapchain. Same as the `present` function.
+    #[inline]
+    pub fn present<F>(&self, before: F, queue: Arc<Queue>, index: usize) -> PresentFuture<F, W>
+    where
+        F: GpuFuture,
+    {
+        present(self.swapchain.clone(), before, queue, index)
+    }
+
+    /// Inspects the result of flushing a future that presents an image.
+    ///
+    /// If the error is `FlushError::OutOfDate`, the swapchain is marked for recreation and
+    /// `Ok(None)` is returned. Other errors are returned unchanged.
+    #[inline]
+    pub fn handle_flush_result<T>(
+        &mut self,
+        result: Result<T, FlushError>,
+    ) -> Result<Option<T>, FlushError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(FlushError::OutOfDate) => {
+                self.needs_recreate = true;
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(feature = "winit_")]
+impl<W> SwapchainManager<W>
+where
+    W: SafeBorrow<winit::window::Window>,
+{
+    /// Updates the dimensions of the swapchain from an event of the event loop, if the event
+    /// concerns the window of the surface.
+    ///
+    /// Requires the `winit_` feature. Call this with every event that the event loop receives.
+    /// The swapchain is marked for recreation when the window is resized, or when it's moved to
+    /// a monitor with a different scale factor.
+    pub fn handle_winit_event<T>(&mut self, event: &Event<T>) {
+        if let Event::WindowEvent {
+            window_id,
+            ref event,
+        } = *event
+        {
+            let window: &winit::window::Window = self.surface().window().borrow();
+            if window_id != window.id() {
+                return;
+            }
+
+            match *event {
+                WindowEvent::Resized(size) => self.set_dimensions([size.width, size.height]),
+                WindowEvent::ScaleFactorChanged {
+                    ref new_inner_size, ..
+                } => self.set_dimensions([new_inner_size.width, new_inner_size.height]),
+                _ => (),
+            }
+        }
+    }
+}
+
+impl<W> fmt::Debug for SwapchainManager<W> {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("SwapchainManager")
+            .field("swapchain", &self.swapchain)
+            .field("dimensions", &self.dimensions)
+            .field("needs_recreate", &self.needs_recreate)
+            .finish()
+    }
+}
+
+// Number of times `acquire_next_image` tries to acquire an image before giving up, if the
+// swapchain is out of date every time.
+const MAX_ACQUIRE_ATTEMPTS: usize = 3;
+
+// Operations of a `SwapchainManager` used by `acquire_with_recreate`. This lets the retry logic
+// be tested without a surface.
+trait RecreateSwapchain {
+    type Future;
+
+    fn needs_recreate(&self) -> bool;
+    fn invalidate(&mut self);
+    fn recreate(&mut self) -> Result<(), SwapchainManagerError>;
+    fn try_acquire(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<(usize, bool, Self::Future), AcquireError>;
+}
+
+impl<W> RecreateSwapchain for SwapchainManager<W> {
+    type Future = SwapchainAcquireFuture<W>;
+
+    #[inline]
+    fn needs_recreate(&self) -> bool {
+        SwapchainManager::needs_recreate(self)
+    }
+
+    #[inline]
+    fn invalidate(&mut self) {
+        SwapchainManager::invalidate(self)
+    }
+
+    #[inline]
+    fn recreate(&mut self) -> Result<(), SwapchainManagerError> {
+        SwapchainManager::recreate(self)
+    }
+
+    #[inline]
+    fn try_acquire(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<(usize, bool, SwapchainAcquireFuture<W>), AcquireError> {
+        acquire_next_image(self.swapchain.clone(), timeout)
+    }
+}
+
+fn acquire_with_recreate<M>(
+    manager: &mut M,
+    timeout: Option<Duration>,
+) -> Result<(usize, M::Future), SwapchainManagerError>
+where
+    M: RecreateSwapchain,
+{
+    for _ in 0..MAX_ACQUIRE_ATTEMPTS {
+        if manager.needs_recreate() {
+            manager.recreate()?;
+        }
+
+        match manager.try_acquire(timeout) {
+            Ok((image_num, suboptimal, future)) => {
+                if suboptimal {
+                    manager.invalidate();
+                }
+
+                return Ok((image_num, future));
+            }
+            Err(AcquireError::OutOfDate) => manager.invalidate(),
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Err(AcquireError::OutOfDate.into())
+}
+
+fn create_image_views<W>(
+    images: &[Arc<SwapchainImage<W>>],
+) -> Result<Vec<Arc<SwapchainImageView<W>>>, ImageViewCreationError> {
+    images
+        .iter()
+        .map(|image| ImageView::new(image.clone()))
+        .collect()
+}
+
+/// Error that can happen when using a `SwapchainManager`.
+#[derive(Debug, Clone)]
+pub enum SwapchainManagerError {
+    /// Error while acquiring an image.
+    AcquireError(AcquireError),
+    /// Error while creating or recreating the swapchain.
+    SwapchainCreationError(SwapchainCreationError),
+    /// Error while creating the views of the swapchain images.
+    ImageViewCreationError(ImageViewCreationError),
+}
+
+impl error::Error for SwapchainManagerError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            SwapchainManagerError::AcquireError(ref err) => Some(err),
+            SwapchainManagerError::SwapchainCreationError(ref err) => Some(err),
+            SwapchainManagerError::ImageViewCreationError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for SwapchainManagerError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                SwapchainManagerError::AcquireError(_) => "error while acquiring an image",
+                SwapchainManagerError::SwapchainCreationError(_) => {
+                    "error while creating the swapchain"
+                }
+                SwapchainManagerError::ImageViewCreationError(_) => {
+                    "error while creating the views of the swapchain images"
+                }
+            }
+        )
+    }
+}
+
+impl From<AcquireError> for SwapchainManagerError {
+    #[inline]
+    fn from(err: AcquireError) -> SwapchainManagerError {
+        SwapchainManagerError::AcquireError(err)
+    }
+}
+
+impl From<SwapchainCreationError> for SwapchainManagerError {
+    #[inline]
+    fn from(err: SwapchainCreationError) -> SwapchainManagerError {
+        SwapchainManagerError::SwapchainCreationError(err)
+    }
+}
+
+impl From<ImageViewCreationError> for SwapchainManagerError {
+    #[inline]
+    fn from(err: ImageViewCreationError) -> SwapchainManagerError {
+        SwapchainManagerError::ImageViewCreationError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    // Manager whose acquisitions return the results of `acquires` in order, and whose
+    // recreations fail while `recreate_fails` is true.
+    struct FakeManager {
+        acquires: VecDeque<Result<(usize, bool), AcquireError>>,
+        needs_recreate: bool,
+        recreate_fails: bool,
+        num_recreates: u32,
+    }
+
+    impl FakeManager {
+        fn new(acquires: Vec<Result<(usize, bool), AcquireError>>) -> FakeManager {
+            FakeManager {
+                acquires: acquires.into(),
+                needs_recreate: false,
+                recreate_fails: false,
+                num_recreates: 0,
+            }
+        }
+    }
+
+    impl RecreateSwapchain for FakeManager {
+        type Future = ();
+
+        fn needs_recreate(&self) -> bool {
+            self.needs_recreate
+        }
+
+        fn invalidate(&mut self) {
+            self.needs_recreate = true;
+        }
+
+        fn recreate(&mut self) -> Result<(), SwapchainManagerError> {
+            if self.recreate_fails {
+                return Err(SwapchainCreationError::UnsupportedSurfaceTransform.into());
+            }
+
+            self.needs_recreate = false;
+            self.num_recreates += 1;
+            Ok(())
+        }
+
+        fn try_acquire(&mut self, _: Option<Duration>) -> Result<(usize, bool, ()), AcquireError> {
+            self.acquires
+                .pop_front()
+                .unwrap()
+                .map(|(image_num, suboptimal)| (image_num, suboptimal, ()))
+        }
+    }
+
+    #[test]
+    fn suboptimal_recreates_next_time() {
+        let mut manager = FakeManager::new(vec![Ok((1, true)), Ok((2, false))]);
+
+        // The suboptimal image is still returned.
+        assert_eq!(acquire_with_recreate(&mut manager, None).unwrap().0, 1);
+        assert!(manager.needs_recreate);
+        assert_eq!(manager.num_recreates, 0);
+
+        assert_eq!(acquire_with_recreate(&mut manager, None).unwrap().0, 2);
+        assert!(!manager.needs_recreate);
+        assert_eq!(manager.num_recreates, 1);
+    }
+
+    #[test]
+    fn out_of_date_recreates_and_retries() {
+        let mut manager = FakeManager::new(vec![Err(AcquireError::OutOfDate), Ok((0, false))]);
+
+        assert_eq!(acquire_with_recreate(&mut manager, None).unwrap().0, 0);
+        assert_eq!(manager.num_recreates, 1);
+    }
+
+    #[test]
+    fn out_of_date_gives_up() {
+        let mut manager = FakeManager::new(vec![Err(AcquireError::OutOfDate); 4]);
+
+        match acquire_with_recreate(&mut manager, None) {
+            Err(SwapchainManagerError::AcquireError(AcquireError::OutOfDate)) => (),
+            _ => panic!(),
+        }
+        assert_eq!(manager.acquires.len(), 4 - MAX_ACQUIRE_ATTEMPTS);
+        assert_eq!(manager.num_recreates as usize, MAX_ACQUIRE_ATTEMPTS - 1);
+        assert!(manager.needs_recreate);
+    }
+
+    #[test]
+    fn recreate_error() {
+        let mut manager = FakeManager::new(vec![Ok((0, false))]);
+        manager.needs_recreate = true;
+        manager.recreate_fails = true;
+
+        match acquire_with_recreate(&mut manager, None) {
+            Err(SwapchainManagerError::SwapchainCreationError(_)) => (),
+            _ => panic!(),
+        }
+        assert!(manager.needs_recreate);
+
+        // Recreation is attempted again the next time.
+        manager.recreate_fails = false;
+        assert_eq!(acquire_with_recreate(&mut manager, None).unwrap().0, 0);
+        assert_eq!(manager.num_recreates, 1);
+    }
+
+    #[test]
+    fn other_errors_are_returned() {
+        let mut manager = FakeManager::new(vec![Err(AcquireError::Timeout)]);
+
+        match acquire_with_recreate(&mut manager, None) {
+            Err(SwapchainManagerError::AcquireError(AcquireError::Timeout)) => (),
+            _ => panic!(),
+        }
+        assert!(!manager.needs_recreate);
+    }
+}
@@ -301,6 +301,8 @@ use std::sync::atomic::AtomicBool;
 pub use self::capabilities::Capabilities;
 pub use self::capabilities::ColorSpace;
 pub use self::capabilities::CompositeAlpha;
+pub use self::capabilities::DeviceGroupPresentMode;
+pub use self::capabilities::DeviceGroupPresentModes;
 pub use self::capabilities::PresentMode;
 pub use self::capabilities::SupportedCompositeAlpha;
 pub use self::capabilities::SupportedCompositeAlphaIter;
@@ -309,6 +311,12 @@ pub use self::capabilities::SupportedPresentModesIter;
 pub use self::capabilities::SupportedSurfaceTransforms;
 pub use self::capabilities::SupportedSurfaceTransformsIter;
 pub use self::capabilities::SurfaceTransform;
+pub use self::capture::capture;
+pub use self::capture::Capture;
+pub use self::capture::CaptureError;
+pub use self::manager::SwapchainImageView;
+pub use self::manager::SwapchainManager;
+pub use self::manager::SwapchainManagerError;
 pub use self::present_region::PresentRegion;
 pub use self::present_region::RectangleLayer;
 pub use self::surface::CapabilitiesError;
@@ -317,19 +325,26 @@ pub use self::surface::SurfaceCreationError;
 pub use self::swapchain::acquire_next_image;
 pub use self::swapchain::acquire_next_image_raw;
 pub use self::swapchain::present;
+pub use self::swapchain::present_display;
 pub use self::swapchain::present_incremental;
+pub use self::swapchain::present_timed;
 pub use self::swapchain::AcquireError;
 pub use self::swapchain::AcquiredImage;
 pub use self::swapchain::FullscreenExclusive;
 pub use self::swapchain::FullscreenExclusiveError;
+pub use self::swapchain::HdrMetadata;
 pub use self::swapchain::PresentFuture;
+pub use self::swapchain::PresentWaitError;
 pub use self::swapchain::Swapchain;
 pub use self::swapchain::SwapchainAcquireFuture;
 pub use self::swapchain::SwapchainBuilder;
 pub use self::swapchain::SwapchainCreationError;
 
 mod capabilities;
+mod capture;
 pub mod display;
+pub mod display_timing;
+mod manager;
 mod present_region;
 mod surface;
 mod swapchain;
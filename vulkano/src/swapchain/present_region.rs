@@ -11,13 +11,34 @@ use crate::swapchain::Swapchain;
 
 /// Represents a region on an image.
 ///
-/// A region consists of an arbitrary amount of rectangles.
-#[derive(Debug, Clone)]
+/// A region consists of an arbitrary amount of rectangles. It is typically used to describe the
+/// parts of an image that have changed since the previous present (the damage), so that only
+/// these parts have to be updated by the presentation engine.
+#[derive(Debug, Clone, Default)]
 pub struct PresentRegion {
     pub rectangles: Vec<RectangleLayer>,
 }
 
 impl PresentRegion {
+    /// Builds a new empty present region.
+    #[inline]
+    pub fn new() -> PresentRegion {
+        PresentRegion {
+            rectangles: Vec::new(),
+        }
+    }
+
+    /// Adds a rectangle of the first layer of the image to the region.
+    #[inline]
+    pub fn add_rectangle(&mut self, offset: [i32; 2], extent: [u32; 2]) -> &mut Self {
+        self.rectangles.push(RectangleLayer {
+            offset,
+            extent,
+            layer: 0,
+        });
+        self
+    }
+
     /// Returns true if this present region is compatible with swapchain.
     pub fn is_compatible_with<W>(&self, swapchain: &Swapchain<W>) -> bool {
         self.rectangles
@@ -43,8 +64,10 @@ impl RectangleLayer {
     /// Returns true if this rectangle layer is compatible with swapchain.
     pub fn is_compatible_with<W>(&self, swapchain: &Swapchain<W>) -> bool {
         // FIXME negative offset is not disallowed by spec, but semantically should not be possible
-        debug_assert!(self.offset[0] >= 0);
-        debug_assert!(self.offset[1] >= 0);
+        if self.offset[0] < 0 || self.offset[1] < 0 {
+            return false;
+        }
+
         self.offset[0] as u32 + self.extent[0] <= swapchain.dimensions()[0]
             && self.offset[1] as u32 + self.extent[1] <= swapchain.dimensions()[1]
             && self.layer < swapchain.layers()
@@ -10,17 +10,28 @@
 use crate::check_errors;
 use crate::device::physical::PhysicalDevice;
 use crate::device::physical::QueueFamily;
+use crate::device::Device;
 use crate::format::Format;
 use crate::image::ImageUsage;
 use crate::instance::Instance;
+use crate::pnext::PNextChain;
 use crate::swapchain::capabilities::SupportedSurfaceTransforms;
 use crate::swapchain::display::DisplayMode;
 use crate::swapchain::display::DisplayPlane;
+use crate::swapchain::display::DisplayPlaneAlpha;
 use crate::swapchain::Capabilities;
+use crate::swapchain::DeviceGroupPresentModes;
+use crate::swapchain::SupportedPresentModes;
 use crate::swapchain::SurfaceSwapchainLock;
+use crate::swapchain::SurfaceTransform;
 use crate::Error;
 use crate::OomError;
+#[cfg(feature = "winit_")]
+use crate::SafeBorrow;
+use crate::Version;
 use crate::VulkanObject;
+#[cfg(feature = "raw-window-handle")]
+use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
 use std::convert::TryFrom;
 use std::error;
 use std::fmt;
@@ -63,14 +74,48 @@ impl<W> Surface<W> {
 
     /// Creates a `Surface` that covers a display mode.
     ///
+    /// The surface covers the whole visible region of the mode, is opaque and isn't transformed.
+    ///
     /// # Panic
     ///
     /// - Panics if `display_mode` and `plane` don't belong to the same physical device.
     /// - Panics if `plane` doesn't support the display of `display_mode`.
     ///
+    #[inline]
     pub fn from_display_mode(
         display_mode: &DisplayMode,
         plane: &DisplayPlane,
+    ) -> Result<Arc<Surface<()>>, SurfaceCreationError> {
+        Surface::<()>::from_display_plane(
+            display_mode,
+            plane,
+            SurfaceTransform::Identity,
+            DisplayPlaneAlpha::Opaque,
+            1.0,
+            display_mode.visible_region(),
+        )
+    }
+
+    /// Creates a `Surface` on a display plane, using the given display mode.
+    ///
+    /// `alpha_mode` must be supported by the plane, as returned by `DisplayPlane::capabilities`.
+    /// `global_alpha` is only used if `alpha_mode` is `DisplayPlaneAlpha::Global`, and must be
+    /// between 0.0 and 1.0.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `display_mode` and `plane` don't belong to the same physical device.
+    /// - Panics if `plane` doesn't support the display of `display_mode`.
+    /// - Panics if `transform` isn't supported by the display of `display_mode`.
+    /// - Panics if `global_alpha` is out of range.
+    ///
+    pub fn from_display_plane(
+        display_mode: &DisplayMode,
+        plane: &DisplayPlane,
+        transform: SurfaceTransform,
+        alpha_mode: DisplayPlaneAlpha,
+        global_alpha: f32,
+        image_extent: [u32; 2],
     ) -> Result<Arc<Surface<()>>, SurfaceCreationError> {
         if !display_mode
             .display()
@@ -89,6 +134,11 @@ impl<W> Surface<W> {
             plane.physical_device().internal_object()
         );
         assert!(plane.supports(display_mode.display()));
+        assert!(display_mode
+            .display()
+            .supported_transforms()
+            .supports(transform));
+        assert!((0.0..=1.0).contains(&global_alpha));
 
         let instance = display_mode.display().physical_device().instance();
         let fns = instance.fns();
@@ -98,14 +148,13 @@ impl<W> Surface<W> {
                 flags: ash::vk::DisplaySurfaceCreateFlagsKHR::empty(),
                 display_mode: display_mode.internal_object(),
                 plane_index: plane.index(),
-                plane_stack_index: 0, // FIXME: plane.properties.currentStackIndex,
-                transform: ash::vk::SurfaceTransformFlagsKHR::IDENTITY, // TODO: let user choose
-                global_alpha: 0.0,    // TODO: let user choose
-                alpha_mode: ash::vk::DisplayPlaneAlphaFlagsKHR::OPAQUE, // TODO: let user choose
+                plane_stack_index: plane.current_stack_index(),
+                transform: transform.into(),
+                global_alpha,
+                alpha_mode: alpha_mode.into(),
                 image_extent: ash::vk::Extent2D {
-                    // TODO: let user choose
-                    width: display_mode.visible_region()[0],
-                    height: display_mode.visible_region()[1],
+                    width: image_extent[0],
+                    height: image_extent[1],
                 },
                 ..Default::default()
             };
@@ -128,6 +177,98 @@ impl<W> Surface<W> {
         }))
     }
 
+    /// Creates a `Surface` from a window that exposes its platform-specific handle through
+    /// `raw-window-handle`.
+    ///
+    /// This covers Win32, Xlib, XCB, Wayland, macOS and iOS (through MoltenVK) and Android
+    /// windows. The instance extension of the corresponding platform must be enabled. On macOS
+    /// and iOS, the view of the window must be backed by a `CAMetalLayer`.
+    ///
+    /// The window is stored in the surface, which ensures that it stays alive for the entire
+    /// lifetime of the surface.
+    #[cfg(feature = "raw-window-handle")]
+    pub fn from_window_handle(
+        instance: Arc<Instance>,
+        window: W,
+    ) -> Result<Arc<Surface<W>>, SurfaceCreationError>
+    where
+        W: HasRawWindowHandle,
+    {
+        // Safety: `HasRawWindowHandle` guarantees that the handles are valid, and the window is
+        // kept alive by the surface.
+        unsafe {
+            match window.raw_window_handle() {
+                #[cfg(target_os = "windows")]
+                RawWindowHandle::Windows(h) => {
+                    Surface::from_hwnd(instance, h.hinstance, h.hwnd, window)
+                }
+                #[cfg(any(
+                    target_os = "linux",
+                    target_os = "dragonfly",
+                    target_os = "freebsd",
+                    target_os = "netbsd",
+                    target_os = "openbsd"
+                ))]
+                RawWindowHandle::Xlib(h) => {
+                    Surface::from_xlib(instance, h.display, h.window, window)
+                }
+                #[cfg(any(
+                    target_os = "linux",
+                    target_os = "dragonfly",
+                    target_os = "freebsd",
+                    target_os = "netbsd",
+                    target_os = "openbsd"
+                ))]
+                RawWindowHandle::Xcb(h) => {
+                    Surface::from_xcb(instance, h.connection, h.window, window)
+                }
+                #[cfg(any(
+                    target_os = "linux",
+                    target_os = "dragonfly",
+                    target_os = "freebsd",
+                    target_os = "netbsd",
+                    target_os = "openbsd"
+                ))]
+                RawWindowHandle::Wayland(h) => {
+                    Surface::from_wayland(instance, h.display, h.surface, window)
+                }
+                #[cfg(target_os = "macos")]
+                RawWindowHandle::MacOS(h) => {
+                    Surface::from_macos_moltenvk(instance, h.ns_view, window)
+                }
+                #[cfg(target_os = "ios")]
+                RawWindowHandle::IOS(h) => Surface::from_ios_moltenvk(instance, h.ui_view, window),
+                #[cfg(target_os = "android")]
+                RawWindowHandle::Android(h) => {
+                    Surface::from_anativewindow(instance, h.a_native_window, window)
+                }
+                _ => Err(SurfaceCreationError::UnsupportedWindowHandle),
+            }
+        }
+    }
+
+    /// Creates a `Surface` from a winit window.
+    ///
+    /// Requires the `winit_` feature. The `khr_surface` instance extension and the extension of
+    /// the platform must be enabled: `khr_win32_surface` on Windows, `khr_wayland_surface`
+    /// and either `khr_xlib_surface` or `khr_xcb_surface` on Linux and BSD, `mvk_macos_surface`
+    /// on macOS, and `khr_android_surface` on Android. On macOS, the view of the window is
+    /// backed by a new `CAMetalLayer`.
+    ///
+    /// The window, which can be owned, a reference or an `Arc`, is stored in the surface, which ensures
+    /// that it stays alive for the entire lifetime of the surface.
+    #[cfg(feature = "winit_")]
+    pub fn from_winit(
+        instance: Arc<Instance>,
+        window: W,
+    ) -> Result<Arc<Surface<W>>, SurfaceCreationError>
+    where
+        W: SafeBorrow<winit::window::Window>,
+    {
+        // Safety: the handles come from a window that is kept alive by the surface.
+        unsafe { winit_to_surface(instance, window) }
+    }
+
     /// Creates a `Surface` from a Win32 window.
     ///
     /// The surface's min, max and current extent will always match the window's dimensions.
@@ -560,8 +701,45 @@ impl<W> Surface<W> {
         }
     }
 
+    /// Returns a queue family of `physical_device` that can present to this surface.
+    ///
+    /// If `preferred`, typically the family used for rendering, can present to this surface then
+    /// it is returned, so that rendering and presenting can happen on the same queue. Otherwise
+    /// the first queue family that supports presenting is returned, or `None` if there is none.
+    ///
+    /// When the returned family differs from the one used for rendering, the swapchain must be
+    /// created with a concurrent sharing mode that includes both families, for example with
+    /// `.sharing_mode(&[&graphics_queue, &present_queue][..])`. `swapchain::present` then
+    /// automatically synchronizes the present with the previous submission using a semaphore.
+    pub fn present_queue_family<'a>(
+        &self,
+        physical_device: PhysicalDevice<'a>,
+        preferred: QueueFamily<'a>,
+    ) -> Result<Option<QueueFamily<'a>>, CapabilitiesError> {
+        assert_eq!(
+            physical_device.internal_object(),
+            preferred.physical_device().internal_object()
+        );
+
+        if self.is_supported(preferred)? {
+            return Ok(Some(preferred));
+        }
+
+        for family in physical_device.queue_families() {
+            if self.is_supported(family)? {
+                return Ok(Some(family));
+            }
+        }
+
+        Ok(None)
+    }
+
     /// Retrieves the capabilities of a surface when used by a certain device.
     ///
+    /// If the `khr_get_surface_capabilities2` instance extension is enabled, this uses
+    /// `vkGetPhysicalDeviceSurfaceCapabilities2KHR`, which allows retrieving additional
+    /// information.
+    ///
     /// # Notes
     ///
     /// - Capabilities that are not supported in `vk-sys` are silently dropped
@@ -580,37 +758,126 @@ impl<W> Surface<W> {
 
             let fns = self.instance.fns();
 
-            let caps = {
-                let mut out: MaybeUninit<ash::vk::SurfaceCapabilitiesKHR> = MaybeUninit::uninit();
-                check_errors(
-                    fns.khr_surface
-                        .get_physical_device_surface_capabilities_khr(
-                            device.internal_object(),
-                            self.surface,
-                            out.as_mut_ptr(),
-                        ),
-                )?;
-                out.assume_init()
-            };
+            let mut supports_protected = None;
+            let mut full_screen_exclusive_supported = None;
+
+            let (caps, formats) = if self
+                .instance
+                .enabled_extensions()
+                .khr_get_surface_capabilities2
+            {
+                let surface_info = ash::vk::PhysicalDeviceSurfaceInfo2KHR {
+                    surface: self.surface,
+                    ..Default::default()
+                };
+
+                let mut protected_capabilities = if self
+                    .instance
+                    .enabled_extensions()
+                    .khr_surface_protected_capabilities
+                {
+                    Some(ash::vk::SurfaceProtectedCapabilitiesKHR::default())
+                } else {
+                    None
+                };
 
-            let formats = {
-                let mut num = 0;
-                check_errors(fns.khr_surface.get_physical_device_surface_formats_khr(
-                    device.internal_object(),
-                    self.surface,
-                    &mut num,
-                    ptr::null_mut(),
-                ))?;
-
-                let mut formats = Vec::with_capacity(num as usize);
-                check_errors(fns.khr_surface.get_physical_device_surface_formats_khr(
-                    device.internal_object(),
-                    self.surface,
-                    &mut num,
-                    formats.as_mut_ptr(),
-                ))?;
-                formats.set_len(num as usize);
-                formats
+                let mut full_screen_exclusive_capabilities =
+                    if device.supported_extensions().ext_full_screen_exclusive {
+                        Some(ash::vk::SurfaceCapabilitiesFullScreenExclusiveEXT::default())
+                    } else {
+                        None
+                    };
+
+                let mut caps2 = ash::vk::SurfaceCapabilities2KHR::default();
+
+                {
+                    let mut chain = PNextChain::<ash::vk::SurfaceCapabilities2KHR>::new();
+                    if let Some(next) = protected_capabilities.as_mut() {
+                        chain.push(next);
+                    }
+                    if let Some(next) = full_screen_exclusive_capabilities.as_mut() {
+                        chain.push(next);
+                    }
+                    caps2.p_next = chain.link(ptr::null()) as *mut _;
+
+                    check_errors(
+                        fns.khr_get_surface_capabilities2
+                            .get_physical_device_surface_capabilities2_khr(
+                                device.internal_object(),
+                                &surface_info,
+                                &mut caps2,
+                            ),
+                    )?;
+                }
+
+                supports_protected =
+                    protected_capabilities.map(|caps| caps.supports_protected != 0);
+                full_screen_exclusive_supported = full_screen_exclusive_capabilities
+                    .map(|caps| caps.full_screen_exclusive_supported != 0);
+
+                let formats = {
+                    let mut num = 0;
+                    check_errors(
+                        fns.khr_get_surface_capabilities2
+                            .get_physical_device_surface_formats2_khr(
+                                device.internal_object(),
+                                &surface_info,
+                                &mut num,
+                                ptr::null_mut(),
+                            ),
+                    )?;
+
+                    let mut formats = vec![ash::vk::SurfaceFormat2KHR::default(); num as usize];
+                    check_errors(
+                        fns.khr_get_surface_capabilities2
+                            .get_physical_device_surface_formats2_khr(
+                                device.internal_object(),
+                                &surface_info,
+                                &mut num,
+                                formats.as_mut_ptr(),
+                            ),
+                    )?;
+                    formats.truncate(num as usize);
+                    formats.into_iter().map(|f| f.surface_format).collect()
+                };
+
+                (caps2.surface_capabilities, formats)
+            } else {
+                let caps = {
+                    let mut out: MaybeUninit<ash::vk::SurfaceCapabilitiesKHR> =
+                        MaybeUninit::uninit();
+                    check_errors(
+                        fns.khr_surface
+                            .get_physical_device_surface_capabilities_khr(
+                                device.internal_object(),
+                                self.surface,
+                                out.as_mut_ptr(),
+                            ),
+                    )?;
+                    out.assume_init()
+                };
+
+                let formats: Vec<ash::vk::SurfaceFormatKHR> = {
+                    let mut num = 0;
+                    check_errors(fns.khr_surface.get_physical_device_surface_formats_khr(
+                        device.internal_object(),
+                        self.surface,
+                        &mut num,
+                        ptr::null_mut(),
+                    ))?;
+
+                    let mut formats = Vec::with_capacity(num as usize);
+                    check_errors(fns.khr_surface.get_physical_device_surface_formats_khr(
+                        device.internal_object(),
+                        self.surface,
+                        &mut num,
+                        formats.as_mut_ptr(),
+                    ))?;
+                    formats.set_len(num as usize);
+                    formats
+                };
+
+                (caps, formats)
             };
 
             let modes = {
@@ -644,46 +911,64 @@ impl<W> Surface<W> {
                 modes.into_iter().collect()
             };
 
-            Ok(Capabilities {
-                min_image_count: caps.min_image_count,
-                max_image_count: if caps.max_image_count == 0 {
-                    None
-                } else {
-                    Some(caps.max_image_count)
-                },
-                current_extent: if caps.current_extent.width == 0xffffffff
-                    && caps.current_extent.height == 0xffffffff
-                {
-                    None
-                } else {
-                    Some([caps.current_extent.width, caps.current_extent.height])
-                },
-                min_image_extent: [caps.min_image_extent.width, caps.min_image_extent.height],
-                max_image_extent: [caps.max_image_extent.width, caps.max_image_extent.height],
-                max_image_array_layers: caps.max_image_array_layers,
-                supported_transforms: caps.supported_transforms.into(),
+            Ok(capabilities_from_raw(
+                &caps,
+                formats,
+                modes,
+                supports_protected,
+                full_screen_exclusive_supported,
+            ))
+        }
+    }
 
-                current_transform: SupportedSurfaceTransforms::from(caps.current_transform)
-                    .iter()
-                    .next()
-                    .unwrap(), // TODO:
-                supported_composite_alpha: caps.supported_composite_alpha.into(),
-                supported_usage_flags: {
-                    let usage = ImageUsage::from(caps.supported_usage_flags);
-                    debug_assert!(usage.color_attachment); // specs say that this must be true
-                    usage
-                },
-                supported_formats: formats
-                    .into_iter()
-                    .filter_map(|f| {
-                        // TODO: Change the way capabilities not supported in vk-sys are handled
-                        Format::try_from(f.format)
-                            .ok()
-                            .map(|format| (format, f.color_space.into()))
-                    })
-                    .collect(),
-                present_modes: modes,
-            })
+    /// Returns the ways the physical devices of `device` can present images to this surface.
+    ///
+    /// Returns `None` if the device doesn't support device groups, which is the case if its API
+    /// version is lower than 1.1 and the `khr_device_group` extension isn't enabled.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device and the surface don't belong to the same instance.
+    /// - Panics if the `khr_swapchain` extension isn't enabled on the device.
+    ///
+    pub fn device_group_present_modes(
+        &self,
+        device: &Device,
+    ) -> Result<Option<DeviceGroupPresentModes>, CapabilitiesError> {
+        assert_eq!(
+            &*self.instance as *const _,
+            &**device.instance() as *const _,
+            "Instance mismatch in Surface::device_group_present_modes"
+        );
+        assert!(device.enabled_extensions().khr_swapchain);
+
+        unsafe {
+            let fns = device.fns();
+            let mut modes = ash::vk::DeviceGroupPresentModeFlagsKHR::empty();
+
+            if device.api_version() >= Version::V1_1 {
+                check_errors(
+                    fns.khr_swapchain
+                        .get_device_group_surface_present_modes_khr(
+                            device.internal_object(),
+                            self.surface,
+                            &mut modes,
+                        ),
+                )?;
+            } else if device.enabled_extensions().khr_device_group {
+                check_errors(
+                    fns.khr_device_group
+                        .get_device_group_surface_present_modes_khr(
+                            device.internal_object(),
+                            self.surface,
+                            &mut modes,
+                        ),
+                )?;
+            } else {
+                return Ok(None);
+            }
+
+            Ok(Some(modes.into()))
         }
     }
 
@@ -706,6 +991,59 @@ unsafe impl<W> SurfaceSwapchainLock for Surface<W> {
     }
 }
 
+// Builds the capabilities of a surface from the structures returned by Vulkan. The values that
+// are only returned by `vkGetPhysicalDeviceSurfaceCapabilities2KHR` are `None` if it wasn't used.
+fn capabilities_from_raw(
+    caps: &ash::vk::SurfaceCapabilitiesKHR,
+    formats: Vec<ash::vk::SurfaceFormatKHR>,
+    present_modes: SupportedPresentModes,
+    supports_protected: Option<bool>,
+    full_screen_exclusive_supported: Option<bool>,
+) -> Capabilities {
+    Capabilities {
+        min_image_count: caps.min_image_count,
+        max_image_count: if caps.max_image_count == 0 {
+            None
+        } else {
+            Some(caps.max_image_count)
+        },
+        current_extent: if caps.current_extent.width == 0xffffffff
+            && caps.current_extent.height == 0xffffffff
+        {
+            None
+        } else {
+            Some([caps.current_extent.width, caps.current_extent.height])
+        },
+        min_image_extent: [caps.min_image_extent.width, caps.min_image_extent.height],
+        max_image_extent: [caps.max_image_extent.width, caps.max_image_extent.height],
+        max_image_array_layers: caps.max_image_array_layers,
+        supported_transforms: caps.supported_transforms.into(),
+
+        current_transform: SupportedSurfaceTransforms::from(caps.current_transform)
+            .iter()
+            .next()
+            .unwrap(), // TODO:
+        supported_composite_alpha: caps.supported_composite_alpha.into(),
+        supported_usage_flags: {
+            let usage = ImageUsage::from(caps.supported_usage_flags);
+            debug_assert!(usage.color_attachment); // specs say that this must be true
+            usage
+        },
+        supported_formats: formats
+            .into_iter()
+            .filter_map(|f| {
+                // TODO: Change the way capabilities not supported in vk-sys are handled
+                Format::try_from(f.format)
+                    .ok()
+                    .map(|format| (format, f.color_space.into()))
+            })
+            .collect(),
+        present_modes,
+        supports_protected,
+        full_screen_exclusive_supported,
+    }
+}
+
 unsafe impl<W> VulkanObject for Surface<W> {
     type Object = ash::vk::SurfaceKHR;
 
@@ -736,6 +1074,132 @@ impl<W> Drop for Surface<W> {
     }
 }
 
+#[cfg(all(feature = "winit_", target_os = "windows"))]
+unsafe fn winit_to_surface<W>(
+    instance: Arc<Instance>,
+    win: W,
+) -> Result<Arc<Surface<W>>, SurfaceCreationError>
+where
+    W: SafeBorrow<winit::window::Window>,
+{
+    use winit::platform::windows::WindowExtWindows;
+
+    let window: &winit::window::Window = win.borrow();
+
+    Surface::from_hwnd(instance, window.hinstance(), window.hwnd(), win)
+}
+
+#[cfg(all(
+    feature = "winit_",
+    any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    )
+))]
+unsafe fn winit_to_surface<W>(
+    instance: Arc<Instance>,
+    win: W,
+) -> Result<Arc<Surface<W>>, SurfaceCreationError>
+where
+    W: SafeBorrow<winit::window::Window>,
+{
+    use winit::platform::unix::WindowExtUnix;
+
+    let window: &winit::window::Window = win.borrow();
+
+    match (window.wayland_display(), window.wayland_surface()) {
+        (Some(display), Some(surface)) => Surface::from_wayland(instance, display, surface, win),
+        // Without Wayland, Xlib is preferred over XCB if its extension is enabled.
+        _ => match (
+            window.xlib_display(),
+            window.xlib_window(),
+            window.xcb_connection(),
+        ) {
+            (Some(display), Some(window), _) if instance.enabled_extensions().khr_xlib_surface => {
+                Surface::from_xlib(instance, display, window, win)
+            }
+            (_, Some(window), Some(connection)) => {
+                Surface::from_xcb(instance, connection, window as u32, win)
+            }
+            _ => Err(SurfaceCreationError::UnsupportedWindowHandle),
+        },
+    }
+}
+
+#[cfg(all(feature = "winit_", target_os = "macos"))]
+unsafe fn winit_to_surface<W>(
+    instance: Arc<Instance>,
+    win: W,
+) -> Result<Arc<Surface<W>>, SurfaceCreationError>
+where
+    W: SafeBorrow<winit::window::Window>,
+{
+    use cocoa::appkit::NSView;
+    use cocoa::appkit::NSWindow;
+    use cocoa::base::id as cocoa_id;
+    use metal::CoreAnimationLayer;
+    use objc::runtime::YES;
+    use winit::platform::macos::WindowExtMacOS;
+
+    let window: &winit::window::Window = win.borrow();
+
+    let wnd = window.ns_window() as cocoa_id;
+    let layer = CoreAnimationLayer::new();
+
+    layer.set_edge_antialiasing_mask(0);
+    layer.set_presents_with_transaction(false);
+    layer.remove_all_animations();
+
+    let view = wnd.contentView();
+
+    layer.set_contents_scale(view.backingScaleFactor());
+    view.setLayer(layer.as_ref() as *const _ as cocoa_id);
+    view.setWantsLayer(YES);
+
+    Surface::from_macos_moltenvk(instance, window.ns_view() as *const (), win)
+}
+
+#[cfg(all(feature = "winit_", target_os = "android"))]
+unsafe fn winit_to_surface<W>(
+    instance: Arc<Instance>,
+    win: W,
+) -> Result<Arc<Surface<W>>, SurfaceCreationError>
+where
+    W: SafeBorrow<winit::window::Window>,
+{
+    use winit::platform::android::WindowExtAndroid;
+
+    let window: &winit::window::Window = win.borrow();
+
+    Surface::from_anativewindow(instance, window.native_window(), win)
+}
+
+#[cfg(all(
+    feature = "winit_",
+    not(any(
+        target_os = "windows",
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "macos",
+        target_os = "android"
+    ))
+))]
+unsafe fn winit_to_surface<W>(
+    _instance: Arc<Instance>,
+    _win: W,
+) -> Result<Arc<Surface<W>>, SurfaceCreationError>
+where
+    W: SafeBorrow<winit::window::Window>,
+{
+    Err(SurfaceCreationError::UnsupportedWindowHandle)
+}
+
 /// Error that can happen when creating a debug callback.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum SurfaceCreationError {
@@ -747,6 +1211,9 @@ pub enum SurfaceCreationError {
         /// Name of the missing extension.
         name: &'static str,
     },
+
+    /// The kind of window handle isn't supported on this platform.
+    UnsupportedWindowHandle,
 }
 
 impl error::Error for SurfaceCreationError {
@@ -770,6 +1237,9 @@ impl fmt::Display for SurfaceCreationError {
                 SurfaceCreationError::MissingExtension { .. } => {
                     "the extension required for this function was not enabled"
                 }
+                SurfaceCreationError::UnsupportedWindowHandle => {
+                    "the kind of window handle isn't supported on this platform"
+                }
             }
         )
     }
@@ -849,10 +1319,102 @@ impl From<Error> for CapabilitiesError {
 
 #[cfg(test)]
 mod tests {
+    use crate::format::Format;
+    use crate::pnext::PNextChain;
+    use crate::swapchain::surface::capabilities_from_raw;
+    use crate::swapchain::ColorSpace;
+    use crate::swapchain::PresentMode;
     use crate::swapchain::Surface;
     use crate::swapchain::SurfaceCreationError;
+    use crate::swapchain::SurfaceTransform;
     use std::ptr;
 
+    fn raw_capabilities() -> ash::vk::SurfaceCapabilitiesKHR {
+        ash::vk::SurfaceCapabilitiesKHR {
+            min_image_count: 2,
+            max_image_count: 0,
+            current_extent: ash::vk::Extent2D {
+                width: 0xffffffff,
+                height: 0xffffffff,
+            },
+            min_image_extent: ash::vk::Extent2D {
+                width: 1,
+                height: 1,
+            },
+            max_image_extent: ash::vk::Extent2D {
+                width: 4096,
+                height: 4096,
+            },
+            max_image_array_layers: 1,
+            supported_transforms: ash::vk::SurfaceTransformFlagsKHR::IDENTITY,
+            current_transform: ash::vk::SurfaceTransformFlagsKHR::IDENTITY,
+            supported_composite_alpha: ash::vk::CompositeAlphaFlagsKHR::OPAQUE,
+            supported_usage_flags: ash::vk::ImageUsageFlags::COLOR_ATTACHMENT,
+        }
+    }
+
+    #[test]
+    fn capabilities_fallback() {
+        let formats = vec![ash::vk::SurfaceFormatKHR {
+            format: ash::vk::Format::B8G8R8A8_SRGB,
+            color_space: ash::vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        }];
+        let modes = vec![ash::vk::PresentModeKHR::FIFO].into_iter().collect();
+
+        let caps = capabilities_from_raw(&raw_capabilities(), formats, modes, None, None);
+        assert_eq!(caps.max_image_count, None);
+        assert_eq!(caps.current_extent, None);
+        assert_eq!(caps.current_transform, SurfaceTransform::Identity);
+        assert_eq!(
+            caps.supported_formats,
+            [(Format::B8G8R8A8Srgb, ColorSpace::SrgbNonLinear)]
+        );
+        assert!(caps.present_modes.supports(PresentMode::Fifo));
+        assert!(!caps.present_modes.supports(PresentMode::Mailbox));
+
+        // These are only known through `vkGetPhysicalDeviceSurfaceCapabilities2KHR`.
+        assert_eq!(caps.supports_protected, None);
+        assert_eq!(caps.full_screen_exclusive_supported, None);
+    }
+
+    #[test]
+    fn capabilities2() {
+        let mut raw = raw_capabilities();
+        raw.max_image_count = 3;
+        raw.current_extent = ash::vk::Extent2D {
+            width: 800,
+            height: 600,
+        };
+        let modes = vec![ash::vk::PresentModeKHR::FIFO].into_iter().collect();
+
+        let caps = capabilities_from_raw(&raw, vec![], modes, Some(true), Some(false));
+        assert_eq!(caps.max_image_count, Some(3));
+        assert_eq!(caps.current_extent, Some([800, 600]));
+        assert_eq!(caps.supports_protected, Some(true));
+        assert_eq!(caps.full_screen_exclusive_supported, Some(false));
+    }
+
+    #[test]
+    fn capabilities2_chain() {
+        let mut protected = ash::vk::SurfaceProtectedCapabilitiesKHR::default();
+        let mut full_screen_exclusive =
+            ash::vk::SurfaceCapabilitiesFullScreenExclusiveEXT::default();
+        let mut caps2 = ash::vk::SurfaceCapabilities2KHR::default();
+
+        {
+            let mut chain = PNextChain::<ash::vk::SurfaceCapabilities2KHR>::new();
+            chain.push(&mut protected).push(&mut full_screen_exclusive);
+            caps2.p_next = unsafe { chain.link(ptr::null()) } as *mut _;
+        }
+
+        assert!(ptr::eq(caps2.p_next, &protected as *const _ as *const _));
+        assert!(ptr::eq(
+            protected.p_next,
+            &full_screen_exclusive as *const _ as *const _
+        ));
+        assert!(full_screen_exclusive.p_next.is_null());
+    }
+
     #[test]
     fn khr_win32_surface_ext_missing() {
         let instance = instance!();
@@ -880,6 +1442,40 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(all(
+        feature = "raw-window-handle",
+        any(
+            target_os = "linux",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        )
+    ))]
+    fn from_window_handle_ext_missing() {
+        use raw_window_handle::unix::XlibHandle;
+        use raw_window_handle::HasRawWindowHandle;
+        use raw_window_handle::RawWindowHandle;
+
+        struct XlibWindow;
+
+        unsafe impl HasRawWindowHandle for XlibWindow {
+            fn raw_window_handle(&self) -> RawWindowHandle {
+                RawWindowHandle::Xlib(XlibHandle::empty())
+            }
+        }
+
+        // The handle is dispatched to `from_xlib`, which checks the extension before using it.
+        let instance = instance!();
+        match Surface::from_window_handle(instance, XlibWindow) {
+            Err(SurfaceCreationError::MissingExtension {
+                name: "VK_KHR_xlib_surface",
+            }) => (),
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn khr_wayland_surface_ext_missing() {
         let instance = instance!();
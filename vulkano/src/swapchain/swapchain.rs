@@ -9,10 +9,21 @@
 
 use crate::buffer::BufferAccess;
 use crate::check_errors;
+use crate::command_buffer::pool::standard::StandardCommandPoolAlloc;
+use crate::command_buffer::pool::CommandPool;
+use crate::command_buffer::pool::CommandPoolBuilderAlloc;
 use crate::command_buffer::submit::SubmitAnyBuilder;
+use crate::command_buffer::submit::SubmitCommandBufferBuilder;
 use crate::command_buffer::submit::SubmitPresentBuilder;
 use crate::command_buffer::submit::SubmitPresentError;
 use crate::command_buffer::submit::SubmitSemaphoresWaitBuilder;
+use crate::command_buffer::sys::UnsafeCommandBuffer;
+use crate::command_buffer::sys::UnsafeCommandBufferBuilder;
+use crate::command_buffer::sys::UnsafeCommandBufferBuilderPipelineBarrier;
+use crate::command_buffer::validity::check_device_mask;
+use crate::command_buffer::CommandBufferLevel;
+use crate::command_buffer::CommandBufferUsage;
+use crate::device::physical::QueueFamily;
 use crate::device::Device;
 use crate::device::DeviceOwned;
 use crate::device::Queue;
@@ -28,34 +39,47 @@ use crate::image::ImageTiling;
 use crate::image::ImageType;
 use crate::image::ImageUsage;
 use crate::image::SampleCount;
+use crate::instance::InstanceExtensions;
+use crate::pnext::PNextChain;
+use crate::swapchain::display::DisplayPresentInfo;
+use crate::swapchain::display_timing::DisplayTimingError;
+use crate::swapchain::display_timing::PastPresentationTiming;
+use crate::swapchain::display_timing::PresentTime;
 use crate::swapchain::CapabilitiesError;
 use crate::swapchain::ColorSpace;
 use crate::swapchain::CompositeAlpha;
+use crate::swapchain::DeviceGroupPresentMode;
+use crate::swapchain::DeviceGroupPresentModes;
 use crate::swapchain::PresentMode;
 use crate::swapchain::PresentRegion;
 use crate::swapchain::Surface;
 use crate::swapchain::SurfaceSwapchainLock;
 use crate::swapchain::SurfaceTransform;
+use crate::sync::ownership_transfer_barriers;
 use crate::sync::semaphore::SemaphoreError;
+use crate::sync::semaphore_flush_error;
 use crate::sync::AccessCheckError;
 use crate::sync::AccessError;
 use crate::sync::AccessFlags;
 use crate::sync::Fence;
 use crate::sync::FlushError;
 use crate::sync::GpuFuture;
+use crate::sync::HandoffScope;
 use crate::sync::PipelineStages;
 use crate::sync::Semaphore;
 use crate::sync::SharingMode;
+use crate::sync::TransferBarrier;
 use crate::Error;
 use crate::OomError;
 use crate::Success;
+use crate::Version;
 use crate::VulkanObject;
 use std::error;
 use std::fmt;
-use std::mem;
 use std::mem::MaybeUninit;
 use std::ptr;
 use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -89,6 +113,110 @@ impl From<FullscreenExclusive> for ash::vk::FullScreenExclusiveEXT {
     }
 }
 
+/// HDR metadata of the content of a swapchain, to be passed to `Swapchain::set_hdr_metadata`.
+///
+/// This describes the mastering display that was used to create the content, so that the
+/// presentation engine can map it to the capabilities of the actual display. Chromaticity
+/// coordinates are given in CIE 1931 xy, and luminances in nits (candelas per square meter).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HdrMetadata {
+    /// Chromaticity of the red primary of the mastering display.
+    pub display_primary_red: [f32; 2],
+    /// Chromaticity of the green primary of the mastering display.
+    pub display_primary_green: [f32; 2],
+    /// Chromaticity of the blue primary of the mastering display.
+    pub display_primary_blue: [f32; 2],
+    /// Chromaticity of the white point of the mastering display.
+    pub white_point: [f32; 2],
+    /// Maximum luminance of the mastering display.
+    pub max_luminance: f32,
+    /// Minimum luminance of the mastering display.
+    pub min_luminance: f32,
+    /// Maximum luminance of any single pixel of the content.
+    pub max_content_light_level: f32,
+    /// Maximum average luminance of a frame of the content.
+    pub max_frame_average_light_level: f32,
+}
+
+impl From<HdrMetadata> for ash::vk::HdrMetadataEXT {
+    #[inline]
+    fn from(val: HdrMetadata) -> Self {
+        let xy = |[x, y]: [f32; 2]| ash::vk::XYColorEXT { x, y };
+
+        Self {
+            display_primary_red: xy(val.display_primary_red),
+            display_primary_green: xy(val.display_primary_green),
+            display_primary_blue: xy(val.display_primary_blue),
+            white_point: xy(val.white_point),
+            max_luminance: val.max_luminance,
+            min_luminance: val.min_luminance,
+            max_content_light_level: val.max_content_light_level,
+            max_frame_average_light_level: val.max_frame_average_light_level,
+            ..Default::default()
+        }
+    }
+}
+
+// Checks that the instance extensions required by `color_space` are enabled.
+fn check_color_space(
+    color_space: ColorSpace,
+    instance_extensions: &InstanceExtensions,
+) -> Result<(), SwapchainCreationError> {
+    // `DisplayNative` belongs to `VK_AMD_display_native_hdr` instead.
+    if color_space != ColorSpace::SrgbNonLinear
+        && color_space != ColorSpace::DisplayNative
+        && !instance_extensions.ext_swapchain_colorspace
+    {
+        return Err(SwapchainCreationError::MissingExtensionExtSwapchainColorspace);
+    }
+
+    Ok(())
+}
+
+#[inline]
+fn assert_display_swapchain_enabled(device: &Device) {
+    assert!(
+        device.enabled_extensions().khr_display_swapchain,
+        "the `khr_display_swapchain` extension must be enabled"
+    );
+}
+
+#[inline]
+fn assert_present_id_enabled(device: &Device) {
+    assert!(
+        device.enabled_features().present_id,
+        "the `present_id` feature must be enabled"
+    );
+}
+
+#[inline]
+fn assert_present_wait_enabled(device: &Device) {
+    assert!(
+        device.enabled_features().present_wait,
+        "the `present_wait` feature must be enabled"
+    );
+}
+
+// Panics if `present_id` is 0 or isn't greater than `prev_present_id`, which is then updated.
+fn check_present_id(prev_present_id: &AtomicU64, present_id: u64) {
+    assert_ne!(present_id, 0);
+
+    // VUID-VkPresentIdKHR-presentIds-04999
+    let prev_present_id = prev_present_id.fetch_max(present_id, Ordering::SeqCst);
+    assert!(
+        present_id > prev_present_id,
+        "present IDs must be increasing for a swapchain"
+    );
+}
+
+#[inline]
+fn assert_hdr_metadata_enabled(device: &Device) {
+    assert!(
+        device.enabled_extensions().ext_hdr_metadata,
+        "the `ext_hdr_metadata` extension must be enabled"
+    );
+}
+
 /// Tries to take ownership of an image in order to draw on it.
 ///
 /// The function returns the index of the image in the array of images that was returned
@@ -104,6 +232,38 @@ impl From<FullscreenExclusive> for ash::vk::FullScreenExclusiveEXT {
 pub fn acquire_next_image<W>(
     swapchain: Arc<Swapchain<W>>,
     timeout: Option<Duration>,
+) -> Result<(usize, bool, SwapchainAcquireFuture<W>), AcquireError> {
+    acquire_next_image_impl(swapchain, timeout, None)
+}
+
+/// Same as `acquire_next_image`, but the semaphore and the fence of the returned future are only
+/// signaled for the physical devices of `device_mask` of a device group.
+///
+/// This is used for alternate frame rendering, where `device_mask` only contains the physical
+/// device that renders the frame.
+///
+/// # Panic
+///
+/// - Panics if the device doesn't support device groups, which is the case if its API version
+///   is lower than 1.1 and the `khr_device_group` extension isn't enabled.
+/// - Panics if `device_mask` is 0 or contains physical devices that aren't part of the device.
+///
+pub fn acquire_next_image_with_device_mask<W>(
+    swapchain: Arc<Swapchain<W>>,
+    timeout: Option<Duration>,
+    device_mask: u32,
+) -> Result<(usize, bool, SwapchainAcquireFuture<W>), AcquireError> {
+    if let Err(err) = check_device_mask(&swapchain.device, device_mask) {
+        panic!("invalid device mask: {}", err);
+    }
+
+    acquire_next_image_impl(swapchain, timeout, Some(device_mask))
+}
+
+fn acquire_next_image_impl<W>(
+    swapchain: Arc<Swapchain<W>>,
+    timeout: Option<Duration>,
+    device_mask: Option<u32>,
 ) -> Result<(usize, bool, SwapchainAcquireFuture<W>), AcquireError> {
     let semaphore = Semaphore::from_pool(swapchain.device.clone())?;
     let fence = Fence::from_pool(swapchain.device.clone())?;
@@ -117,8 +277,15 @@ pub fn acquire_next_image<W>(
             return Err(AcquireError::OutOfDate);
         }
 
-        let acquire_result =
-            unsafe { acquire_next_image_raw(&swapchain, timeout, Some(&semaphore), Some(&fence)) };
+        let acquire_result = unsafe {
+            acquire_next_image_raw_impl(
+                &swapchain,
+                timeout,
+                Some(&semaphore),
+                Some(&fence),
+                device_mask,
+            )
+        };
 
         if let &Err(AcquireError::FullscreenExclusiveLost) = &acquire_result {
             swapchain
@@ -149,6 +316,18 @@ pub fn acquire_next_image<W>(
 ///
 /// The actual behavior depends on the present mode that you passed when creating the
 /// swapchain.
+///
+/// `queue` doesn't need to be the queue of `before`. If it is a different queue, the present
+/// waits on a semaphore signalled at the end of the previous submission. If the queues also
+/// belong to different families and the swapchain was created with an exclusive sharing mode,
+/// the ownership of the image is transferred to the family of `queue` in between, with a pair of
+/// pipeline barriers. Errors while creating the semaphores or the command buffers of the
+/// transfer are returned when the future is flushed.
+///
+/// # Panic
+///
+/// - Panics if `index` is out of range.
+///
 pub fn present<F, W>(
     swapchain: Arc<Swapchain<W>>,
     before: F,
@@ -167,12 +346,37 @@ where
     // always returns false anyway (by design), we don't need to do it.
     assert!(before.check_image_access(&swapchain_image, ImageLayout::PresentSrc, true, &queue).is_ok());         // TODO: return error instead*/
 
+    // If the previous submission happens on another queue, the present has to wait on a
+    // semaphore that is signalled at the end of it.
+    let queue_change = match before.queue() {
+        Some(previous_queue) if !previous_queue.is_same(&queue) => Some(unsafe {
+            SwapchainImage::from_raw(swapchain.clone(), index)
+                .map_err(FlushError::OomError)
+                .and_then(|image| {
+                    QueueChange::new(
+                        &*image,
+                        ImageLayout::PresentSrc,
+                        &previous_queue,
+                        &queue,
+                        &swapchain.sharing_mode,
+                    )
+                })
+        }),
+        _ => None,
+    };
+
     PresentFuture {
         previous: before,
         queue,
         swapchain,
         image_id: index,
         present_region: None,
+        display_present_info: None,
+        present_time: None,
+        present_id: None,
+        device_group_present: None,
+        queue_change,
+        previous_submitted: AtomicBool::new(false),
         flushed: AtomicBool::new(false),
         finished: AtomicBool::new(false),
     }
@@ -191,27 +395,68 @@ pub fn present_incremental<F, W>(
     index: usize,
     present_region: PresentRegion,
 ) -> PresentFuture<F, W>
+where
+    F: GpuFuture,
+{
+    let mut future = present(swapchain, before, queue, index);
+    future.present_region = Some(present_region);
+    future
+}
+
+/// Same as `swapchain::present`, except it allows specifying which region of the image is shown
+/// on which region of the display, for a swapchain created on a display surface.
+///
+/// # Panic
+///
+/// - Panics if the `VK_KHR_display_swapchain` extension is not enabled on the device.
+///
+pub fn present_display<F, W>(
+    swapchain: Arc<Swapchain<W>>,
+    before: F,
+    queue: Arc<Queue>,
+    index: usize,
+    display_present_info: DisplayPresentInfo,
+) -> PresentFuture<F, W>
 where
     F: GpuFuture,
 {
     assert!(index < swapchain.images.len());
+    assert_display_swapchain_enabled(&swapchain.device);
 
-    // TODO: restore this check with a dummy ImageAccess implementation
-    /*let swapchain_image = me.images.lock().unwrap().get(index).unwrap().0.upgrade().unwrap();       // TODO: return error instead
-    // Normally if `check_image_access` returns false we're supposed to call the `gpu_access`
-    // function on the image instead. But since we know that this method on `SwapchainImage`
-    // always returns false anyway (by design), we don't need to do it.
-    assert!(before.check_image_access(&swapchain_image, ImageLayout::PresentSrc, true, &queue).is_ok());         // TODO: return error instead*/
+    let mut future = present(swapchain, before, queue, index);
+    future.display_present_info = Some(display_present_info);
+    future
+}
 
-    PresentFuture {
-        previous: before,
-        queue,
-        swapchain,
-        image_id: index,
-        present_region: Some(present_region),
-        flushed: AtomicBool::new(false),
-        finished: AtomicBool::new(false),
-    }
+/// Same as `swapchain::present`, except it allows specifying an identifier and a desired
+/// presentation time for the image.
+///
+/// The image isn't shown before the desired presentation time. The actual time at which it was
+/// shown can later be queried with `Swapchain::past_presentation_timing`.
+///
+/// # Panic
+///
+/// - Panics if the `VK_GOOGLE_display_timing` extension is not enabled on the device.
+///
+pub fn present_timed<F, W>(
+    swapchain: Arc<Swapchain<W>>,
+    before: F,
+    queue: Arc<Queue>,
+    index: usize,
+    present_time: PresentTime,
+) -> PresentFuture<F, W>
+where
+    F: GpuFuture,
+{
+    assert!(index < swapchain.images.len());
+    assert!(
+        swapchain.device.enabled_extensions().google_display_timing,
+        "the `google_display_timing` extension must be enabled"
+    );
+
+    let mut future = present(swapchain, before, queue, index);
+    future.present_time = Some(present_time);
+    future
 }
 
 /// Contains the swapping system and the images that can be shown on a surface.
@@ -246,6 +491,9 @@ pub struct Swapchain<W> {
     present_mode: PresentMode,
     fullscreen_exclusive: FullscreenExclusive,
     fullscreen_exclusive_held: AtomicBool,
+    device_group_present_modes: Option<DeviceGroupPresentModes>,
+    // The highest present ID that was used with this swapchain.
+    prev_present_id: AtomicU64,
     clipped: bool,
 }
 
@@ -275,6 +523,7 @@ impl<W> Swapchain<W> {
             composite_alpha: CompositeAlpha::Opaque,
             present_mode: PresentMode::Fifo,
             fullscreen_exclusive: FullscreenExclusive::Default,
+            device_group_present_modes: None,
             clipped: true,
 
             old_swapchain: None,
@@ -303,6 +552,7 @@ impl<W> Swapchain<W> {
             composite_alpha: self.composite_alpha,
             present_mode: self.present_mode,
             fullscreen_exclusive: self.fullscreen_exclusive,
+            device_group_present_modes: self.device_group_present_modes,
             clipped: self.clipped,
 
             old_swapchain: Some(self.clone()),
@@ -339,6 +589,12 @@ impl<W> Swapchain<W> {
         self.format
     }
 
+    /// Returns the color space of the images of the swapchain.
+    #[inline]
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
     /// Returns the dimensions of the images of the swapchain.
     #[inline]
     pub fn dimensions(&self) -> [u32; 2] {
@@ -381,6 +637,12 @@ impl<W> Swapchain<W> {
         self.fullscreen_exclusive
     }
 
+    /// Returns the device group present modes that were passed when creating the swapchain.
+    #[inline]
+    pub fn device_group_present_modes(&self) -> Option<DeviceGroupPresentModes> {
+        self.device_group_present_modes
+    }
+
     /// `FullscreenExclusive::AppControlled` must be the active fullscreen exclusivity mode.
     /// Acquire fullscreen exclusivity until either the `release_fullscreen_exclusive` is
     /// called, or if any of the the other `Swapchain` functions return `FullscreenExclusiveLost`.
@@ -447,6 +709,138 @@ impl<W> Swapchain<W> {
         }
     }
 
+    /// Returns the duration of a refresh cycle of the display the swapchain is presented on.
+    pub fn refresh_cycle_duration(&self) -> Result<Duration, DisplayTimingError> {
+        if !self.device.enabled_extensions().google_display_timing {
+            return Err(DisplayTimingError::MissingExtension);
+        }
+
+        let properties = unsafe {
+            let mut output = MaybeUninit::uninit();
+            check_errors(
+                self.device
+                    .fns()
+                    .google_display_timing
+                    .get_refresh_cycle_duration_google(
+                        self.device.internal_object(),
+                        self.swapchain,
+                        output.as_mut_ptr(),
+                    ),
+            )?;
+            output.assume_init()
+        };
+
+        Ok(Duration::from_nanos(properties.refresh_duration))
+    }
+
+    /// Returns the timing of the presents that were made with `swapchain::present_timed` and
+    /// that have been shown since the last call.
+    ///
+    /// The presentation engine only keeps a limited number of results, so this should be called
+    /// regularly.
+    pub fn past_presentation_timing(
+        &self,
+    ) -> Result<Vec<PastPresentationTiming>, DisplayTimingError> {
+        if !self.device.enabled_extensions().google_display_timing {
+            return Err(DisplayTimingError::MissingExtension);
+        }
+
+        let fns = self.device.fns();
+
+        unsafe {
+            let mut num = 0;
+            check_errors(
+                fns.google_display_timing
+                    .get_past_presentation_timing_google(
+                        self.device.internal_object(),
+                        self.swapchain,
+                        &mut num,
+                        ptr::null_mut(),
+                    ),
+            )?;
+
+            let mut timings = Vec::with_capacity(num as usize);
+            check_errors(
+                fns.google_display_timing
+                    .get_past_presentation_timing_google(
+                        self.device.internal_object(),
+                        self.swapchain,
+                        &mut num,
+                        timings.as_mut_ptr(),
+                    ),
+            )?;
+            timings.set_len(num as usize);
+
+            Ok(timings.into_iter().map(Into::into).collect())
+        }
+    }
+
+    /// Waits until the present that was tagged with `present_id`, or a later one, has been shown
+    /// on the display.
+    ///
+    /// Returns `Err(PresentWaitError::Timeout)` if `timeout` elapses first.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the `present_wait` feature is not enabled on the device.
+    /// - Panics if `present_id` is 0.
+    ///
+    pub fn wait_for_present(
+        &self,
+        present_id: u64,
+        timeout: Option<Duration>,
+    ) -> Result<(), PresentWaitError> {
+        assert_present_wait_enabled(&self.device);
+        assert_ne!(present_id, 0);
+
+        let timeout_ns = if let Some(timeout) = timeout {
+            timeout
+                .as_secs()
+                .saturating_mul(1_000_000_000)
+                .saturating_add(timeout.subsec_nanos() as u64)
+        } else {
+            u64::MAX
+        };
+
+        let r = unsafe {
+            check_errors(self.device.fns().khr_present_wait.wait_for_present_khr(
+                self.device.internal_object(),
+                self.swapchain,
+                present_id,
+                timeout_ns,
+            ))?
+        };
+
+        match r {
+            Success::Success | Success::Suboptimal => Ok(()),
+            Success::Timeout => Err(PresentWaitError::Timeout),
+            s => panic!("unexpected success value: {:?}", s),
+        }
+    }
+
+    /// Sets the HDR metadata of the content that is presented on this swapchain.
+    ///
+    /// The metadata applies to the images that are presented after this call.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the `ext_hdr_metadata` extension is not enabled on the device.
+    ///
+    pub fn set_hdr_metadata(&self, metadata: HdrMetadata) {
+        assert_hdr_metadata_enabled(&self.device);
+
+        let metadata = ash::vk::HdrMetadataEXT::from(metadata);
+
+        unsafe {
+            self.device.fns().ext_hdr_metadata.set_hdr_metadata_ext(
+                self.device.internal_object(),
+                1,
+                &self.swapchain,
+                &metadata,
+            );
+        }
+    }
+
     // This method is necessary to allow `SwapchainImage`s to signal when they have been
     // transitioned out of their initial `undefined` image layout.
     //
@@ -523,6 +917,7 @@ pub struct SwapchainBuilder<W> {
     composite_alpha: CompositeAlpha,
     present_mode: PresentMode,
     fullscreen_exclusive: FullscreenExclusive,
+    device_group_present_modes: Option<DeviceGroupPresentModes>,
     clipped: bool,
 }
 
@@ -543,8 +938,32 @@ impl<W> SwapchainBuilder<W> {
     /// - Panics if `usage` is empty.
     ///
     // TODO: isn't it unsafe to take the surface through an Arc when it comes to vulkano-win?
+    #[inline]
     pub fn build(
         self,
+    ) -> Result<(Arc<Swapchain<W>>, Vec<Arc<SwapchainImage<W>>>), SwapchainCreationError> {
+        unsafe { self.build_with_p_next_chain(PNextChain::new()) }
+    }
+
+    /// Same as `build`, but also appends structures to the `pNext` chain of the
+    /// `VkSwapchainCreateInfoKHR` structure. See the `pnext` module.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device and the surface don't belong to the same instance.
+    /// - Panics if `usage` is empty.
+    ///
+    /// # Safety
+    ///
+    /// - The structures of `p_next_chain` must be valid, and the extensions that define them
+    ///   must be enabled on the device.
+    /// - `p_next_chain` must not contain a `VkSurfaceFullScreenExclusiveInfoEXT` or a
+    ///   `VkDeviceGroupSwapchainCreateInfoKHR` structure. Use `fullscreen_exclusive` and
+    ///   `device_group_present_modes` instead.
+    ///
+    pub unsafe fn build_with_p_next_chain(
+        self,
+        mut p_next_chain: PNextChain<ash::vk::SwapchainCreateInfoKHR>,
     ) -> Result<(Arc<Swapchain<W>>, Vec<Arc<SwapchainImage<W>>>), SwapchainCreationError> {
         let SwapchainBuilder {
             device,
@@ -562,6 +981,7 @@ impl<W> SwapchainBuilder<W> {
             composite_alpha,
             present_mode,
             fullscreen_exclusive,
+            device_group_present_modes,
             clipped,
         } = self;
 
@@ -647,6 +1067,17 @@ impl<W> SwapchainBuilder<W> {
         if !capabilities.present_modes.supports(present_mode) {
             return Err(SwapchainCreationError::UnsupportedPresentMode);
         }
+        if let Some(modes) = device_group_present_modes {
+            if !device.enabled_extensions().khr_swapchain {
+                return Err(SwapchainCreationError::MissingExtensionKHRSwapchain);
+            }
+            let supported = surface
+                .device_group_present_modes(&device)?
+                .ok_or(SwapchainCreationError::UnsupportedDeviceGroupPresentModes)?;
+            if modes == DeviceGroupPresentModes::none() || !supported.contains(&modes) {
+                return Err(SwapchainCreationError::UnsupportedDeviceGroupPresentModes);
+            }
+        }
 
         let flags = ImageCreateFlags::none();
 
@@ -682,6 +1113,8 @@ impl<W> SwapchainBuilder<W> {
             return Err(SwapchainCreationError::MissingExtensionKHRSwapchain);
         }
 
+        check_color_space(color_space, surface.instance().enabled_extensions())?;
+
         let mut surface_full_screen_exclusive_info = None;
 
         // TODO: VK_EXT_FULL_SCREEN_EXCLUSIVE requires these extensions, so they should always
@@ -703,10 +1136,25 @@ impl<W> SwapchainBuilder<W> {
             });
         }
 
-        let p_next = match surface_full_screen_exclusive_info.as_ref() {
-            Some(some) => unsafe { mem::transmute(some as *const _) },
-            None => ptr::null(),
-        };
+        let mut device_group_swapchain_info =
+            device_group_present_modes.map(|modes| ash::vk::DeviceGroupSwapchainCreateInfoKHR {
+                modes: modes.into(),
+                ..Default::default()
+            });
+
+        let mut p_next = ptr::null();
+
+        if let Some(next) = surface_full_screen_exclusive_info.as_mut() {
+            next.p_next = p_next as *mut _;
+            p_next = next as *const _ as *const _;
+        }
+
+        if let Some(next) = device_group_swapchain_info.as_mut() {
+            next.p_next = p_next;
+            p_next = next as *const _ as *const _;
+        }
+
+        p_next = p_next_chain.link(p_next);
 
         // Required by the specs.
         assert_ne!(usage, ImageUsage::none());
@@ -861,6 +1309,8 @@ impl<W> SwapchainBuilder<W> {
             present_mode,
             fullscreen_exclusive,
             fullscreen_exclusive_held: AtomicBool::new(fullscreen_exclusive_held),
+            device_group_present_modes,
+            prev_present_id: AtomicU64::new(0),
             clipped,
         });
 
@@ -915,7 +1365,13 @@ impl<W> SwapchainBuilder<W> {
 
     /// Sets the number of layers for each image.
     ///
-    /// The default is 1.
+    /// The default is 1. Using more than one layer is useful for stereoscopic displays, where each
+    /// layer contains the image for one eye. The value must not be greater than
+    /// `max_image_array_layers` in the capabilities of the surface.
+    ///
+    /// `ImageView::new` creates a `Dim2dArray` view covering all the layers of a multi-layer
+    /// image, which can be rendered into with layered rendering or multiview. Views of individual
+    /// layers can be created with `ImageView::start` and `with_array_layers`.
     #[inline]
     pub fn layers(mut self, layers: u32) -> Self {
         self.layers = layers;
@@ -979,6 +1435,23 @@ impl<W> SwapchainBuilder<W> {
         self
     }
 
+    /// Sets how the physical devices of a device group present the images of the swapchain. For
+    /// alternate frame rendering, use `local` or `remote` presentation.
+    ///
+    /// The modes must be supported by the surface, as returned by
+    /// `Surface::device_group_present_modes`. If `None`, the images are presented with `local`
+    /// presentation.
+    ///
+    /// The default is `None`.
+    #[inline]
+    pub fn device_group_present_modes(
+        mut self,
+        device_group_present_modes: Option<DeviceGroupPresentModes>,
+    ) -> Self {
+        self.device_group_present_modes = device_group_present_modes;
+        self
+    }
+
     /// Sets whether the implementation is allowed to discard rendering operations that affect
     /// regions of the surface which aren't visible. This is important to take into account if
     /// your fragment shader has side-effects or if you want to read back the content of the image
@@ -1009,6 +1482,8 @@ pub enum SwapchainCreationError {
     MissingExtensionKHRSwapchain,
     /// The `VK_EXT_full_screen_exclusive` extension was not enabled.
     MissingExtensionExtFullScreenExclusive,
+    /// The `VK_EXT_swapchain_colorspace` extension was not enabled.
+    MissingExtensionExtSwapchainColorspace,
     /// Surface mismatch between old and new swapchain.
     OldSwapchainSurfaceMismatch,
     /// The old swapchain has already been used to recreate another one.
@@ -1033,6 +1508,8 @@ pub enum SwapchainCreationError {
     UnsupportedPresentMode,
     /// The image configuration is not supported by the physical device.
     UnsupportedImageConfiguration,
+    /// The requested device group present modes are empty or not supported by the surface.
+    UnsupportedDeviceGroupPresentModes,
 }
 
 impl error::Error for SwapchainCreationError {
@@ -1067,6 +1544,9 @@ impl fmt::Display for SwapchainCreationError {
                 SwapchainCreationError::MissingExtensionExtFullScreenExclusive => {
                     "the `VK_EXT_full_screen_exclusive` extension was not enabled"
                 }
+                SwapchainCreationError::MissingExtensionExtSwapchainColorspace => {
+                    "the `VK_EXT_swapchain_colorspace` extension was not enabled"
+                }
                 SwapchainCreationError::OldSwapchainSurfaceMismatch => {
                     "surface mismatch between old and new swapchain"
                 }
@@ -1103,6 +1583,9 @@ impl fmt::Display for SwapchainCreationError {
                 SwapchainCreationError::UnsupportedImageConfiguration => {
                     "the requested image configuration is not supported by the physical device"
                 }
+                SwapchainCreationError::UnsupportedDeviceGroupPresentModes => {
+                    "the requested device group present modes are not supported by the surface"
+                }
             }
         )
     }
@@ -1347,6 +1830,68 @@ impl fmt::Display for FullscreenExclusiveError {
     }
 }
 
+/// Error that can happen when calling `Swapchain::wait_for_present`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PresentWaitError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The connection to the device has been lost.
+    DeviceLost,
+    /// The timeout of the function has been reached before the present was shown.
+    Timeout,
+    /// The surface is no longer accessible and must be recreated.
+    SurfaceLost,
+    /// The swapchain has lost or doesn't have fullscreen exclusivity.
+    FullscreenExclusiveLost,
+    /// The swapchain is out of date and must be recreated.
+    OutOfDate,
+}
+
+impl error::Error for PresentWaitError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            PresentWaitError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PresentWaitError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                PresentWaitError::OomError(_) => "not enough memory",
+                PresentWaitError::DeviceLost => "the connection to the device has been lost",
+                PresentWaitError::Timeout => "the timeout has been reached",
+                PresentWaitError::SurfaceLost => "the surface of this swapchain is no longer valid",
+                PresentWaitError::FullscreenExclusiveLost => {
+                    "the swapchain lost or doesn't have fullscreen exclusivity"
+                }
+                PresentWaitError::OutOfDate => "the swapchain needs to be recreated",
+            }
+        )
+    }
+}
+
+impl From<Error> for PresentWaitError {
+    #[inline]
+    fn from(err: Error) -> PresentWaitError {
+        match err {
+            err @ Error::OutOfHostMemory => PresentWaitError::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => PresentWaitError::OomError(OomError::from(err)),
+            Error::DeviceLost => PresentWaitError::DeviceLost,
+            Error::SurfaceLost => PresentWaitError::SurfaceLost,
+            Error::FullscreenExclusiveLost => PresentWaitError::FullscreenExclusiveLost,
+            Error::OutOfDate => PresentWaitError::OutOfDate,
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
 /// Error that can happen when calling `acquire_next_image`.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(u32)]
@@ -1445,6 +1990,16 @@ where
     swapchain: Arc<Swapchain<W>>,
     image_id: usize,
     present_region: Option<PresentRegion>,
+    display_present_info: Option<DisplayPresentInfo>,
+    present_time: Option<PresentTime>,
+    present_id: Option<u64>,
+    device_group_present: Option<(u32, DeviceGroupPresentMode)>,
+    // What to submit on top of the present if the previous submission is on another queue, or the
+    // error that happened while creating it.
+    queue_change: Option<Result<QueueChange, FlushError>>,
+    // True if the previous submission has been submitted with the signal operation of
+    // `queue_change`.
+    previous_submitted: AtomicBool,
     // True if `flush()` has been called on the future, which means that the present command has
     // been submitted.
     flushed: AtomicBool,
@@ -1468,6 +2023,111 @@ where
     pub fn swapchain(&self) -> &Arc<Swapchain<W>> {
         &self.swapchain
     }
+
+    /// Tags the present with an ID, which can be passed to `Swapchain::wait_for_present` to wait
+    /// until the image has been shown.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the `present_id` feature is not enabled on the device.
+    /// - Panics if `present_id` is 0, or isn't greater than the IDs that were previously used
+    ///   with the same swapchain.
+    ///
+    pub fn with_present_id(mut self, present_id: u64) -> Self {
+        assert_present_id_enabled(&self.swapchain.device);
+        check_present_id(&self.swapchain.prev_present_id, present_id);
+
+        self.present_id = Some(present_id);
+        self
+    }
+
+    /// Attaches a present region to the present, describing the areas of the image that have
+    /// changed since the previous present. Same as using `present_incremental`.
+    ///
+    /// If `VK_KHR_incremental_present` is not enabled on the device, the region is ignored.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the rectangles of the region is outside of the swapchain images.
+    ///
+    pub fn with_present_region(mut self, present_region: PresentRegion) -> Self {
+        assert!(present_region.is_compatible_with(&self.swapchain));
+        self.present_region = Some(present_region);
+        self
+    }
+
+    /// Presents the image from the physical devices of `device_mask` of a device group, in the
+    /// way given by `mode`.
+    ///
+    /// This is used for alternate frame rendering, where `device_mask` only contains the physical
+    /// device that rendered the frame, and `mode` is `Local` or `Remote`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device doesn't support device groups, or if `device_mask` contains
+    ///   physical devices that aren't part of the device.
+    /// - Panics if `mode` isn't one of the modes that the swapchain was created with, which is
+    ///   only `Local` if it was created without device group present modes.
+    /// - Panics if `mode` is `Local` or `Remote` and `device_mask` doesn't contain exactly one
+    ///   physical device, or if `device_mask` is 0.
+    ///
+    pub fn with_device_group_present(
+        mut self,
+        device_mask: u32,
+        mode: DeviceGroupPresentMode,
+    ) -> Self {
+        if let Err(err) = check_device_mask(&self.swapchain.device, device_mask) {
+            panic!("invalid device mask: {}", err);
+        }
+
+        let modes = self
+            .swapchain
+            .device_group_present_modes
+            .unwrap_or(DeviceGroupPresentModes {
+                local: true,
+                ..DeviceGroupPresentModes::none()
+            });
+        assert!(
+            modes.contains(&mode.into()),
+            "the swapchain wasn't created with the device group present mode {:?}",
+            mode
+        );
+        assert!(
+            mode.is_valid_device_mask(device_mask),
+            "the device mask {:#b} is not valid for the device group present mode {:?}",
+            device_mask,
+            mode
+        );
+
+        self.device_group_present = Some((device_mask, mode));
+        self
+    }
+
+    // Adds the image to present and the parameters of the presentation to `builder`.
+    unsafe fn add_to_builder<'a>(&'a self, builder: &mut SubmitPresentBuilder<'a>) {
+        builder.add_swapchain(
+            &self.swapchain,
+            self.image_id as u32,
+            self.present_region.as_ref(),
+        );
+
+        if let Some(display_present_info) = self.display_present_info.as_ref() {
+            builder.set_display_present_info(display_present_info);
+        }
+
+        if let Some(present_time) = self.present_time {
+            builder.set_present_time(present_time);
+        }
+
+        if let Some(present_id) = self.present_id {
+            builder.set_present_id(present_id);
+        }
+
+        if let Some((device_mask, mode)) = self.device_group_present {
+            builder.set_device_mask(device_mask);
+            builder.set_device_group_present_mode(mode);
+        }
+    }
 }
 
 unsafe impl<P, W> GpuFuture for PresentFuture<P, W>
@@ -1485,55 +2145,33 @@ where
             return Ok(SubmitAnyBuilder::Empty);
         }
 
-        let queue = self.previous.queue().map(|q| q.clone());
+        let queue_change = match self.queue_change {
+            Some(Ok(ref queue_change)) => Some(queue_change),
+            Some(Err(ref err)) => return Err(err.clone()),
+            None => None,
+        };
 
         // TODO: if the swapchain image layout is not PRESENT, should add a transition command
         // buffer
 
-        Ok(match self.previous.build_submission()? {
-            SubmitAnyBuilder::Empty => {
-                let mut builder = SubmitPresentBuilder::new();
-                builder.add_swapchain(
-                    &self.swapchain,
-                    self.image_id as u32,
-                    self.present_region.as_ref(),
-                );
-                SubmitAnyBuilder::QueuePresent(builder)
+        let (mut builder, previous_submission) = match self.previous.build_submission()? {
+            SubmitAnyBuilder::Empty => (SubmitPresentBuilder::new(), None),
+            SubmitAnyBuilder::SemaphoresWait(sem) => (sem.into(), None),
+            SubmitAnyBuilder::CommandBuffer(cb) if queue_change.is_some() => {
+                // The previous submission is on another queue, so we submit it ourselves below.
+                (SubmitPresentBuilder::new(), Some(cb))
             }
-            SubmitAnyBuilder::SemaphoresWait(sem) => {
-                let mut builder: SubmitPresentBuilder = sem.into();
-                builder.add_swapchain(
-                    &self.swapchain,
-                    self.image_id as u32,
-                    self.present_region.as_ref(),
-                );
-                SubmitAnyBuilder::QueuePresent(builder)
-            }
-            SubmitAnyBuilder::CommandBuffer(cb) => {
+            SubmitAnyBuilder::CommandBuffer(_) => {
                 // submit the command buffer by flushing previous.
                 // Since the implementation should remember being flushed it's safe to call build_submission multiple times
                 self.previous.flush()?;
-
-                let mut builder = SubmitPresentBuilder::new();
-                builder.add_swapchain(
-                    &self.swapchain,
-                    self.image_id as u32,
-                    self.present_region.as_ref(),
-                );
-                SubmitAnyBuilder::QueuePresent(builder)
+                (SubmitPresentBuilder::new(), None)
             }
-            SubmitAnyBuilder::BindSparse(cb) => {
+            SubmitAnyBuilder::BindSparse(_) => {
                 // submit the command buffer by flushing previous.
                 // Since the implementation should remember being flushed it's safe to call build_submission multiple times
                 self.previous.flush()?;
-
-                let mut builder = SubmitPresentBuilder::new();
-                builder.add_swapchain(
-                    &self.swapchain,
-                    self.image_id as u32,
-                    self.present_region.as_ref(),
-                );
-                SubmitAnyBuilder::QueuePresent(builder)
+                (SubmitPresentBuilder::new(), None)
             }
             SubmitAnyBuilder::QueuePresent(present) => {
                 unimplemented!() // TODO:
@@ -1542,7 +2180,24 @@ where
                                  builder.add_swapchain(self.command_buffer.inner(), self.image_id);
                                  SubmitAnyBuilder::CommandBuffer(builder)*/
             }
-        })
+        };
+
+        if let Some(queue_change) = queue_change {
+            if !self.previous_submitted.load(Ordering::SeqCst) {
+                let previous_queue = self.previous.queue().unwrap();
+                queue_change.submit(
+                    previous_submission.unwrap_or_else(SubmitCommandBufferBuilder::new),
+                    &previous_queue,
+                    &self.queue,
+                )?;
+                self.previous_submitted.store(true, Ordering::SeqCst);
+            }
+
+            builder.add_wait_semaphore(queue_change.present_semaphore());
+        }
+
+        self.add_to_builder(&mut builder);
+        Ok(SubmitAnyBuilder::QueuePresent(builder))
     }
 
     #[inline]
@@ -1593,11 +2248,6 @@ where
 
     #[inline]
     fn queue(&self) -> Option<Arc<Queue>> {
-        debug_assert!(match self.previous.queue() {
-            None => true,
-            Some(q) => q.is_same(&self.queue),
-        });
-
         Some(self.queue.clone())
     }
 
@@ -1666,6 +2316,171 @@ where
     }
 }
 
+// What is submitted on top of the present if the previous submission is on another queue.
+struct QueueChange {
+    // Signalled at the end of the previous submission.
+    semaphore: Semaphore,
+    // Set if the ownership of the image is transferred to the family of the present queue.
+    ownership_transfer: Option<OwnershipTransfer>,
+}
+
+struct OwnershipTransfer {
+    // Executed at the end of the previous submission.
+    release: TransferCommandBuffer,
+    // Executed on the present queue, after `QueueChange::semaphore` has been signalled.
+    acquire: TransferCommandBuffer,
+    // Signalled once `acquire` has been executed.
+    semaphore: Semaphore,
+}
+
+// A command buffer that only contains one of the barriers of an ownership transfer.
+struct TransferCommandBuffer {
+    command_buffer: UnsafeCommandBuffer,
+    // The allocation of `command_buffer`, which must outlive it.
+    _alloc: StandardCommandPoolAlloc,
+}
+
+impl QueueChange {
+    // Creates the semaphores, and records the barriers of the ownership transfer of `image` if
+    // the queues belong to different families and `sharing_mode` is exclusive. The image must be
+    // in `layout`, and stays in it.
+    unsafe fn new<I>(
+        image: &I,
+        layout: ImageLayout,
+        previous_queue: &Queue,
+        queue: &Queue,
+        sharing_mode: &SharingMode,
+    ) -> Result<QueueChange, FlushError>
+    where
+        I: ?Sized + ImageAccess,
+    {
+        let device = queue.device();
+        let scope = HandoffScope {
+            exclusive_sharing: matches!(sharing_mode, SharingMode::Exclusive),
+            ..HandoffScope::default()
+        };
+        let barriers =
+            ownership_transfer_barriers(previous_queue.family().id(), queue.family().id(), &scope);
+
+        let ownership_transfer = match barriers {
+            Some(barriers) => Some(OwnershipTransfer {
+                release: TransferCommandBuffer::new(
+                    device,
+                    previous_queue.family(),
+                    image,
+                    layout,
+                    &barriers.release,
+                )?,
+                acquire: TransferCommandBuffer::new(
+                    device,
+                    queue.family(),
+                    image,
+                    layout,
+                    &barriers.acquire,
+                )?,
+                semaphore: Semaphore::from_pool(device.clone()).map_err(semaphore_flush_error)?,
+            }),
+            None => None,
+        };
+
+        Ok(QueueChange {
+            semaphore: Semaphore::from_pool(device.clone()).map_err(semaphore_flush_error)?,
+            ownership_transfer,
+        })
+    }
+
+    // Returns the semaphore that the present must wait on.
+    #[inline]
+    fn present_semaphore(&self) -> &Semaphore {
+        match self.ownership_transfer {
+            Some(ref ownership_transfer) => &ownership_transfer.semaphore,
+            None => &self.semaphore,
+        }
+    }
+
+    // Submits `previous` on `previous_queue` with the release barrier and the signal operation,
+    // then the acquire barrier on `queue`.
+    unsafe fn submit<'a>(
+        &'a self,
+        mut previous: SubmitCommandBufferBuilder<'a>,
+        previous_queue: &Queue,
+        queue: &Queue,
+    ) -> Result<(), FlushError> {
+        debug_assert_eq!(previous.num_signal_semaphores(), 0);
+
+        if let Some(ref ownership_transfer) = self.ownership_transfer {
+            previous.add_command_buffer(&ownership_transfer.release.command_buffer);
+        }
+
+        previous.add_signal_semaphore(&self.semaphore);
+        previous.submit(previous_queue)?;
+
+        if let Some(ref ownership_transfer) = self.ownership_transfer {
+            let mut acquire = SubmitCommandBufferBuilder::new();
+            acquire.add_wait_semaphore(
+                &self.semaphore,
+                PipelineStages {
+                    all_commands: true,
+                    ..PipelineStages::none()
+                },
+            );
+            acquire.add_command_buffer(&ownership_transfer.acquire.command_buffer);
+            acquire.add_signal_semaphore(&ownership_transfer.semaphore);
+            acquire.submit(queue)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl TransferCommandBuffer {
+    // Records `barrier` on the whole `image` in a command buffer of `family`.
+    unsafe fn new<I>(
+        device: &Arc<Device>,
+        family: QueueFamily,
+        image: &I,
+        layout: ImageLayout,
+        barrier: &TransferBarrier,
+    ) -> Result<TransferCommandBuffer, FlushError>
+    where
+        I: ?Sized + ImageAccess,
+    {
+        let alloc = Device::standard_command_pool(device, family)
+            .alloc(false, 1)
+            .map_err(FlushError::OomError)?
+            .next()
+            .unwrap();
+
+        let mut builder = UnsafeCommandBufferBuilder::new(
+            alloc.inner(),
+            CommandBufferLevel::primary(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .map_err(FlushError::OomError)?;
+
+        let mut pipeline_barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        pipeline_barrier.add_image_memory_barrier(
+            image,
+            0..image.mipmap_levels(),
+            0..image.dimensions().array_layers(),
+            barrier.source_stages,
+            barrier.source_access,
+            barrier.destination_stages,
+            barrier.destination_access,
+            false,
+            Some(barrier.queue_families),
+            layout,
+            layout,
+        );
+        builder.pipeline_barrier(&pipeline_barrier);
+
+        Ok(TransferCommandBuffer {
+            command_buffer: builder.build().map_err(FlushError::OomError)?,
+            _alloc: alloc.into_alloc(),
+        })
+    }
+}
+
 pub struct AcquiredImage {
     pub id: usize,
     pub suboptimal: bool,
@@ -1683,6 +2498,18 @@ pub unsafe fn acquire_next_image_raw<W>(
     timeout: Option<Duration>,
     semaphore: Option<&Semaphore>,
     fence: Option<&Fence>,
+) -> Result<AcquiredImage, AcquireError> {
+    acquire_next_image_raw_impl(swapchain, timeout, semaphore, fence, None)
+}
+
+// Calls `vkAcquireNextImage2KHR` if there is a device mask, and `vkAcquireNextImageKHR`
+// otherwise.
+unsafe fn acquire_next_image_raw_impl<W>(
+    swapchain: &Swapchain<W>,
+    timeout: Option<Duration>,
+    semaphore: Option<&Semaphore>,
+    fence: Option<&Fence>,
+    device_mask: Option<u32>,
 ) -> Result<AcquiredImage, AcquireError> {
     let fns = swapchain.device.fns();
 
@@ -1695,21 +2522,47 @@ pub unsafe fn acquire_next_image_raw<W>(
         u64::MAX
     };
 
+    let semaphore = semaphore
+        .map(|s| s.internal_object())
+        .unwrap_or(ash::vk::Semaphore::null());
+    let fence = fence
+        .map(|f| f.internal_object())
+        .unwrap_or(ash::vk::Fence::null());
+
     let mut out = MaybeUninit::uninit();
-    let r = check_errors(
-        fns.khr_swapchain.acquire_next_image_khr(
+    let r = if let Some(device_mask) = device_mask {
+        let info = ash::vk::AcquireNextImageInfoKHR {
+            swapchain: swapchain.swapchain,
+            timeout: timeout_ns,
+            semaphore,
+            fence,
+            device_mask,
+            ..Default::default()
+        };
+
+        if swapchain.device.api_version() >= Version::V1_1 {
+            check_errors(fns.khr_swapchain.acquire_next_image2_khr(
+                swapchain.device.internal_object(),
+                &info,
+                out.as_mut_ptr(),
+            ))?
+        } else {
+            check_errors(fns.khr_device_group.acquire_next_image2_khr(
+                swapchain.device.internal_object(),
+                &info,
+                out.as_mut_ptr(),
+            ))?
+        }
+    } else {
+        check_errors(fns.khr_swapchain.acquire_next_image_khr(
             swapchain.device.internal_object(),
             swapchain.swapchain,
             timeout_ns,
-            semaphore
-                .map(|s| s.internal_object())
-                .unwrap_or(ash::vk::Semaphore::null()),
-            fence
-                .map(|f| f.internal_object())
-                .unwrap_or(ash::vk::Fence::null()),
+            semaphore,
+            fence,
             out.as_mut_ptr(),
-        ),
-    )?;
+        ))?
+    };
 
     let out = out.assume_init();
     let (id, suboptimal) = match r {
@@ -1722,3 +2575,232 @@ pub unsafe fn acquire_next_image_raw<W>(
 
     Ok(AcquiredImage { id, suboptimal })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_buffer::AutoCommandBufferBuilder;
+    use crate::format::ClearValue;
+    use crate::image::StorageImage;
+    use crate::sync::now;
+
+    #[test]
+    fn color_space_extensions() {
+        let none = InstanceExtensions::none();
+        let colorspace = InstanceExtensions {
+            ext_swapchain_colorspace: true,
+            ..InstanceExtensions::none()
+        };
+
+        for &color_space in &[ColorSpace::SrgbNonLinear, ColorSpace::DisplayNative] {
+            assert!(check_color_space(color_space, &none).is_ok());
+        }
+
+        for &color_space in &[
+            ColorSpace::ExtendedSrgbLinear,
+            ColorSpace::Hdr10St2084,
+            ColorSpace::DisplayP3NonLinear,
+            ColorSpace::PassThrough,
+        ] {
+            assert_eq!(
+                check_color_space(color_space, &none),
+                Err(SwapchainCreationError::MissingExtensionExtSwapchainColorspace)
+            );
+            assert!(check_color_space(color_space, &colorspace).is_ok());
+        }
+    }
+
+    #[test]
+    fn hdr_metadata_to_vk() {
+        let metadata = HdrMetadata {
+            display_primary_red: [0.708, 0.292],
+            display_primary_green: [0.170, 0.797],
+            display_primary_blue: [0.131, 0.046],
+            white_point: [0.3127, 0.3290],
+            max_luminance: 1000.0,
+            min_luminance: 0.001,
+            max_content_light_level: 800.0,
+            max_frame_average_light_level: 400.0,
+        };
+
+        let vk = ash::vk::HdrMetadataEXT::from(metadata);
+        assert_eq!(vk.s_type, ash::vk::StructureType::HDR_METADATA_EXT);
+        assert_eq!(
+            (vk.display_primary_green.x, vk.display_primary_green.y),
+            (0.170, 0.797)
+        );
+        assert_eq!((vk.white_point.x, vk.white_point.y), (0.3127, 0.3290));
+        assert_eq!(vk.max_luminance, 1000.0);
+        assert_eq!(vk.max_frame_average_light_level, 400.0);
+    }
+
+    #[test]
+    fn display_swapchain_extension_missing() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        assert_should_panic!("the `khr_display_swapchain` extension must be enabled", {
+            assert_display_swapchain_enabled(&device);
+        });
+    }
+
+    #[test]
+    fn present_id_feature_missing() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        assert_should_panic!("the `present_id` feature must be enabled", {
+            assert_present_id_enabled(&device);
+        });
+        assert_should_panic!("the `present_wait` feature must be enabled", {
+            assert_present_wait_enabled(&device);
+        });
+    }
+
+    #[test]
+    fn present_ids_increase() {
+        let prev_present_id = AtomicU64::new(0);
+        check_present_id(&prev_present_id, 1);
+        check_present_id(&prev_present_id, 5);
+        assert_should_panic!("present IDs must be increasing for a swapchain", {
+            check_present_id(&prev_present_id, 5);
+        });
+        assert_should_panic!("present IDs must be increasing for a swapchain", {
+            check_present_id(&prev_present_id, 3);
+        });
+        assert_eq!(prev_present_id.load(Ordering::SeqCst), 5);
+        assert_should_panic!({
+            check_present_id(&prev_present_id, 0);
+        });
+    }
+
+    #[test]
+    fn hdr_metadata_extension_missing() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        assert_should_panic!("the `ext_hdr_metadata` extension must be enabled", {
+            assert_hdr_metadata_enabled(&device);
+        });
+    }
+
+    // Submits an empty previous submission with `queue_change`, then waits on `queue` for the
+    // semaphore that a present would wait on.
+    unsafe fn submit_queue_change(
+        queue_change: &QueueChange,
+        previous_queue: &Queue,
+        queue: &Queue,
+    ) {
+        queue_change
+            .submit(SubmitCommandBufferBuilder::new(), previous_queue, queue)
+            .unwrap();
+
+        let fence = Fence::alloc(queue.device().clone()).unwrap();
+        let mut wait = SubmitCommandBufferBuilder::new();
+        wait.add_wait_semaphore(
+            queue_change.present_semaphore(),
+            PipelineStages {
+                all_commands: true,
+                ..PipelineStages::none()
+            },
+        );
+        wait.set_fence_signal(&fence);
+        wait.submit(queue).unwrap();
+        fence.wait(None).unwrap();
+    }
+
+    #[test]
+    fn queue_change_ownership_transfer() {
+        use crate::device::physical::PhysicalDevice;
+        use crate::device::DeviceExtensions;
+        use crate::device::Features;
+
+        let instance = instance!();
+
+        let physical = match PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return,
+        };
+
+        // The image is cleared on the first queue, which needs graphics or compute.
+        let families: Vec<_> = physical
+            .queue_families()
+            .filter(|q| q.supports_graphics() || q.supports_compute())
+            .take(2)
+            .collect();
+        if families.len() < 2 {
+            return;
+        }
+
+        let (device, queues) = match Device::new(
+            physical,
+            &Features::none(),
+            &DeviceExtensions::none(),
+            families.iter().map(|&family| (family, 0.5)),
+        ) {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+        let queues: Vec<_> = queues.collect();
+
+        let image = StorageImage::new(
+            device.clone(),
+            ImageDimensions::Dim2d {
+                width: 4,
+                height: 4,
+                array_layers: 1,
+            },
+            Format::R8G8B8A8Unorm,
+            Some(queues[0].family()),
+        )
+        .unwrap();
+
+        // Puts the image in the general layout on the first queue.
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queues[0].family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder
+            .clear_color_image(image.clone(), ClearValue::Float([0.0; 4]))
+            .unwrap();
+        now(device.clone())
+            .then_execute(queues[0].clone(), builder.build().unwrap())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        unsafe {
+            let exclusive = QueueChange::new(
+                &*image,
+                ImageLayout::General,
+                &queues[0],
+                &queues[1],
+                &SharingMode::Exclusive,
+            )
+            .unwrap();
+            assert!(exclusive.ownership_transfer.is_some());
+            assert!(!ptr::eq(
+                exclusive.present_semaphore(),
+                &exclusive.semaphore
+            ));
+            submit_queue_change(&exclusive, &queues[0], &queues[1]);
+
+            let families = queues.iter().map(|q| q.family().id()).collect();
+            let concurrent = QueueChange::new(
+                &*image,
+                ImageLayout::General,
+                &queues[1],
+                &queues[0],
+                &SharingMode::Concurrent(families),
+            )
+            .unwrap();
+            assert!(concurrent.ownership_transfer.is_none());
+            assert!(ptr::eq(
+                concurrent.present_semaphore(),
+                &concurrent.semaphore
+            ));
+            submit_queue_change(&concurrent, &queues[1], &queues[0]);
+        }
+    }
+}
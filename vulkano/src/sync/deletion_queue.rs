@@ -0,0 +1,324 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::sync::FenceSignalFuture;
+use crate::sync::GpuFuture;
+use crate::sync::TimelineSemaphore;
+use crate::OomError;
+use crate::VulkanObject;
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+/// Keeps objects alive until the GPU has finished the submissions that use them.
+///
+/// Each object is pushed together with the point that marks the end of the last submission using
+/// it: either a fence, or a value of a timeline semaphore. Calling `cleanup_finished` destroys
+/// all the objects whose point has been signaled, without blocking. This is usually done once per
+/// frame.
+///
+/// Any object that implements `Send` can be pushed, such as buffers, images, descriptor sets or
+/// pipelines. The queue
+/// holds a reference to the fences it waits for, so the submissions don't need to be kept alive
+/// by the caller either. Dropping the queue destroys all the remaining objects; since dropping a
+/// `FenceSignalFuture` blocks until it is signaled, the objects that are pushed together with a
+/// fence are never destroyed while still in use.
+///
+/// Contrary to `Frames`, a `DeletionQueue` doesn't assume that submissions finish in the order
+/// they were made, so it can be shared between several queues.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use vulkano::buffer::CpuAccessibleBuffer;
+/// # use vulkano::command_buffer::PrimaryAutoCommandBuffer;
+/// # use vulkano::device::Device;
+/// # use vulkano::device::Queue;
+/// # let device: Arc<Device> = return;
+/// # let queue: Arc<Queue> = return;
+/// # let command_buffer: PrimaryAutoCommandBuffer = return;
+/// # let old_buffer: Arc<CpuAccessibleBuffer<[u8]>> = return;
+/// use vulkano::sync;
+/// use vulkano::sync::DeletionQueue;
+/// use vulkano::sync::GpuFuture;
+///
+/// let mut deletion_queue = DeletionQueue::new(device.clone());
+///
+/// let future = Arc::new(
+///     sync::now(device.clone())
+///         .then_execute(queue.clone(), command_buffer)
+///         .unwrap()
+///         .then_signal_fence_and_flush()
+///         .unwrap(),
+/// );
+///
+/// // `old_buffer` is destroyed once the fence is signaled.
+/// deletion_queue.destroy_after_fence(old_buffer, &future);
+///
+/// // Later, for example at the start of the next frame.
+/// deletion_queue.cleanup_finished().unwrap();
+/// ```
+pub struct DeletionQueue {
+    device: Arc<Device>,
+    pending: Vec<PendingDeletion>,
+}
+
+// Objects waiting for the same signal point.
+struct PendingDeletion {
+    point: SignalPoint,
+    objects: Vec<Box<dyn Any + Send>>,
+}
+
+// Point that marks the end of the last submission using some objects.
+pub(crate) enum SignalPoint {
+    Fence(Arc<dyn FenceStatus>),
+    Timeline(Arc<TimelineSemaphore>, u64),
+}
+
+impl SignalPoint {
+    pub(crate) fn is_same(&self, other: &SignalPoint) -> bool {
+        match (self, other) {
+            (SignalPoint::Fence(a), SignalPoint::Fence(b)) => {
+                Arc::as_ptr(a) as *const () == Arc::as_ptr(b) as *const ()
+            }
+            (SignalPoint::Timeline(a, a_value), SignalPoint::Timeline(b, b_value)) => {
+                Arc::ptr_eq(a, b) && a_value == b_value
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn is_signaled(&self) -> Result<bool, OomError> {
+        match self {
+            SignalPoint::Fence(fence) => fence.is_signaled(),
+            SignalPoint::Timeline(semaphore, value) => Ok(semaphore.value()? >= *value),
+        }
+    }
+}
+
+impl fmt::Debug for SignalPoint {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            SignalPoint::Fence(_) => fmt.write_str("Fence"),
+            SignalPoint::Timeline(semaphore, value) => fmt
+                .debug_tuple("Timeline")
+                .field(semaphore)
+                .field(value)
+                .finish(),
+        }
+    }
+}
+
+// Allows storing fence signal futures of different types in the same list.
+pub(crate) trait FenceStatus: Send + Sync {
+    fn is_signaled(&self) -> Result<bool, OomError>;
+}
+
+impl<F> FenceStatus for FenceSignalFuture<F>
+where
+    F: GpuFuture + Send,
+{
+    #[inline]
+    fn is_signaled(&self) -> Result<bool, OomError> {
+        FenceSignalFuture::is_signaled(self)
+    }
+}
+
+impl DeletionQueue {
+    /// Builds a new empty `DeletionQueue`.
+    #[inline]
+    pub fn new(device: Arc<Device>) -> DeletionQueue {
+        DeletionQueue {
+            device,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Keeps `object` alive until `fence` is signaled.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `fence` doesn't belong to the same device.
+    ///
+    pub fn destroy_after_fence<T, F>(&mut self, object: T, fence: &Arc<FenceSignalFuture<F>>)
+    where
+        T: Send + 'static,
+        F: GpuFuture + Send + 'static,
+    {
+        assert_eq!(
+            fence.device().internal_object(),
+            self.device.internal_object()
+        );
+
+        self.push(SignalPoint::Fence(fence.clone()), Box::new(object));
+    }
+
+    /// Keeps `object` alive until `semaphore` reaches `value`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `semaphore` doesn't belong to the same device.
+    ///
+    pub fn destroy_after_timeline<T>(
+        &mut self,
+        object: T,
+        semaphore: &Arc<TimelineSemaphore>,
+        value: u64,
+    ) where
+        T: Send + 'static,
+    {
+        assert_eq!(
+            semaphore.device().internal_object(),
+            self.device.internal_object()
+        );
+
+        self.push(
+            SignalPoint::Timeline(semaphore.clone(), value),
+            Box::new(object),
+        );
+    }
+
+    fn push(&mut self, point: SignalPoint, object: Box<dyn Any + Send>) {
+        // Objects are usually pushed in batches for the same submission.
+        if let Some(last) = self.pending.last_mut() {
+            if last.point.is_same(&point) {
+                last.objects.push(object);
+                return;
+            }
+        }
+
+        self.pending.push(PendingDeletion {
+            point,
+            objects: vec![object],
+        });
+    }
+
+    /// Destroys all the objects whose fence or timeline value has been signaled.
+    ///
+    /// This function never blocks.
+    pub fn cleanup_finished(&mut self) -> Result<(), OomError> {
+        let mut index = 0;
+
+        while index < self.pending.len() {
+            if self.pending[index].point.is_signaled()? {
+                self.pending.remove(index);
+            } else {
+                index += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of objects that haven't been destroyed yet.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pending
+            .iter()
+            .map(|pending| pending.objects.len())
+            .sum()
+    }
+
+    /// Returns true if all the objects have been destroyed.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+unsafe impl DeviceOwned for DeletionQueue {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+impl fmt::Debug for DeletionQueue {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("DeletionQueue")
+            .field("pending", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_buffer::AutoCommandBufferBuilder;
+    use crate::command_buffer::CommandBufferUsage;
+    use crate::sync::now;
+    use crate::sync::DeletionQueue;
+    use crate::sync::GpuFuture;
+    use crate::sync::TimelineSemaphore;
+    use std::sync::Arc;
+
+    #[test]
+    fn send() {
+        fn is_send<T: Send>() {}
+        is_send::<DeletionQueue>();
+    }
+
+    #[test]
+    fn destroy_after_fence() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let mut deletion_queue = DeletionQueue::new(device.clone());
+        let marker = Arc::new(());
+
+        let cb = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+        let future = Arc::new(
+            now(device.clone())
+                .then_execute(queue.clone(), cb)
+                .unwrap()
+                .then_signal_fence_and_flush()
+                .unwrap(),
+        );
+
+        deletion_queue.destroy_after_fence(marker.clone(), &future);
+        deletion_queue.destroy_after_fence(marker.clone(), &future);
+        assert_eq!(deletion_queue.len(), 2);
+
+        future.wait(None).unwrap();
+        deletion_queue.cleanup_finished().unwrap();
+        assert!(deletion_queue.is_empty());
+        assert_eq!(Arc::strong_count(&marker), 1);
+    }
+
+    #[test]
+    fn destroy_after_timeline() {
+        let (device, _) = gfx_dev_and_queue!(timeline_semaphore);
+
+        let mut deletion_queue = DeletionQueue::new(device.clone());
+        let semaphore = Arc::new(TimelineSemaphore::new(device, 0).unwrap());
+        let marker = Arc::new(());
+
+        deletion_queue.destroy_after_timeline(marker.clone(), &semaphore, 1);
+        deletion_queue.destroy_after_timeline(marker.clone(), &semaphore, 2);
+
+        semaphore.signal(1).unwrap();
+        deletion_queue.cleanup_finished().unwrap();
+        assert_eq!(deletion_queue.len(), 1);
+        assert_eq!(Arc::strong_count(&marker), 2);
+
+        semaphore.signal(2).unwrap();
+        deletion_queue.cleanup_finished().unwrap();
+        assert!(deletion_queue.is_empty());
+        assert_eq!(Arc::strong_count(&marker), 1);
+    }
+}
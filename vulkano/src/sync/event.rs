@@ -14,6 +14,7 @@ use crate::OomError;
 use crate::Success;
 use crate::VulkanObject;
 use std::mem::MaybeUninit;
+use std::panic::Location;
 use std::ptr;
 use std::sync::Arc;
 
@@ -40,7 +41,7 @@ impl Event {
     /// For most applications, using the event pool should be preferred,
     /// in order to avoid creating new events every frame.
     pub fn from_pool(device: Arc<Device>) -> Result<Event, OomError> {
-        let maybe_raw_event = device.event_pool().lock().unwrap().pop();
+        let maybe_raw_event = device.event_pool().pop();
         match maybe_raw_event {
             Some(raw_event) => {
                 unsafe {
@@ -63,10 +64,29 @@ impl Event {
 
     /// Builds a new event.
     #[inline]
+    #[track_caller]
     pub fn alloc(device: Arc<Device>) -> Result<Event, OomError> {
         Event::alloc_impl(device, false)
     }
 
+    /// Builds an `Event` from an event that was created outside of vulkano.
+    ///
+    /// The `Event` takes ownership of the handle, and destroys it when it is dropped.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid event that was created from `device`.
+    /// - The event must not be destroyed by anything else than the returned `Event`.
+    #[inline]
+    pub unsafe fn from_handle(device: Arc<Device>, handle: ash::vk::Event) -> Event {
+        Event {
+            event: handle,
+            device,
+            must_put_in_pool: false,
+        }
+    }
+
+    #[track_caller]
     fn alloc_impl(device: Arc<Device>, must_put_in_pool: bool) -> Result<Event, OomError> {
         let event = unsafe {
             let infos = ash::vk::EventCreateInfo {
@@ -85,11 +105,13 @@ impl Event {
             output.assume_init()
         };
 
-        Ok(Event {
+        let event = Event {
             device: device,
             event: event,
             must_put_in_pool: must_put_in_pool,
-        })
+        };
+        event.device.auto_debug_name(&event, Location::caller());
+        Ok(event)
     }
 
     /// Returns true if the event is signaled.
@@ -182,7 +204,7 @@ impl Drop for Event {
         unsafe {
             if self.must_put_in_pool {
                 let raw_event = self.event;
-                self.device.event_pool().lock().unwrap().push(raw_event);
+                self.device.event_pool().push(raw_event);
             } else {
                 let fns = self.device.fns();
                 fns.v1_0
@@ -230,16 +252,16 @@ mod tests {
     fn event_pool() {
         let (device, _) = gfx_dev_and_queue!();
 
-        assert_eq!(device.event_pool().lock().unwrap().len(), 0);
+        assert_eq!(device.event_pool().len(), 0);
         let event1_internal_obj = {
             let event = Event::from_pool(device.clone()).unwrap();
-            assert_eq!(device.event_pool().lock().unwrap().len(), 0);
+            assert_eq!(device.event_pool().len(), 0);
             event.internal_object()
         };
 
-        assert_eq!(device.event_pool().lock().unwrap().len(), 1);
+        assert_eq!(device.event_pool().len(), 1);
         let event2 = Event::from_pool(device.clone()).unwrap();
-        assert_eq!(device.event_pool().lock().unwrap().len(), 0);
+        assert_eq!(device.event_pool().len(), 0);
         assert_eq!(event2.internal_object(), event1_internal_obj);
     }
 }
@@ -18,11 +18,20 @@ use crate::VulkanObject;
 use smallvec::SmallVec;
 use std::error;
 use std::fmt;
+use std::future::Future;
 use std::mem::MaybeUninit;
+use std::panic::Location;
+use std::pin::Pin;
 use std::ptr;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+use std::thread;
+use std::thread::JoinHandle;
 use std::time::Duration;
 
 /// A fence is used to know when a command buffer submission has finished its execution.
@@ -30,6 +39,10 @@ use std::time::Duration;
 /// When a command buffer accesses a resource, you have to ensure that the CPU doesn't access
 /// the same resource simultaneously (except for concurrent reads). Therefore in order to know
 /// when the CPU can access a resource again, a fence has to be used.
+///
+/// A `Fence` also implements `std::future::Future`, so that it can be awaited from asynchronous
+/// code without blocking the executor. The wait is performed by a background thread which is
+/// started the first time the fence is polled.
 #[derive(Debug)]
 pub struct Fence<D = Arc<Device>>
 where
@@ -47,6 +60,9 @@ where
     // Indicates whether this fence was taken from the fence pool.
     // If true, will be put back into fence pool on drop.
     must_put_in_pool: bool,
+
+    // Background thread that waits for the fence when it is polled as a `Future`.
+    waiter: Mutex<Option<FenceWaiter>>,
 }
 
 impl<D> Fence<D>
@@ -60,7 +76,7 @@ where
     /// For most applications, using the fence pool should be preferred,
     /// in order to avoid creating new fences every frame.
     pub fn from_pool(device: D) -> Result<Fence<D>, OomError> {
-        let maybe_raw_fence = device.fence_pool().lock().unwrap().pop();
+        let maybe_raw_fence = device.fence_pool().pop();
         match maybe_raw_fence {
             Some(raw_fence) => {
                 unsafe {
@@ -76,6 +92,7 @@ where
                     device: device,
                     signaled: AtomicBool::new(false),
                     must_put_in_pool: true,
+                    waiter: Mutex::new(None),
                 })
             }
             None => {
@@ -87,16 +104,38 @@ where
 
     /// Builds a new fence.
     #[inline]
+    #[track_caller]
     pub fn alloc(device: D) -> Result<Fence<D>, OomError> {
         Fence::alloc_impl(device, false, false)
     }
 
     /// Builds a new fence in signaled state.
     #[inline]
+    #[track_caller]
     pub fn alloc_signaled(device: D) -> Result<Fence<D>, OomError> {
         Fence::alloc_impl(device, true, false)
     }
 
+    /// Builds a `Fence` from a fence that was created outside of vulkano.
+    ///
+    /// The `Fence` takes ownership of the handle, and destroys it when it is dropped.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid fence that was created from `device`.
+    /// - The fence must not be destroyed by anything else than the returned `Fence`.
+    #[inline]
+    pub unsafe fn from_handle(device: D, handle: ash::vk::Fence) -> Fence<D> {
+        Fence {
+            fence: handle,
+            device,
+            signaled: AtomicBool::new(false),
+            must_put_in_pool: false,
+            waiter: Mutex::new(None),
+        }
+    }
+
+    #[track_caller]
     fn alloc_impl(device: D, signaled: bool, must_put_in_pool: bool) -> Result<Fence<D>, OomError> {
         let fence = unsafe {
             let infos = ash::vk::FenceCreateInfo {
@@ -119,12 +158,15 @@ where
             output.assume_init()
         };
 
-        Ok(Fence {
+        let fence = Fence {
             fence: fence,
             device: device,
             signaled: AtomicBool::new(signaled),
             must_put_in_pool: must_put_in_pool,
-        })
+            waiter: Mutex::new(None),
+        };
+        fence.device.auto_debug_name(&fence, Location::caller());
+        Ok(fence)
     }
 
     /// Returns true if the fence is signaled.
@@ -255,11 +297,101 @@ where
         }
     }
 
+    /// Waits until at least one of multiple fences is signaled, or until the timeout duration has
+    /// elapsed.
+    ///
+    /// Returns the indices, in the order of `iter`, of the fences that are signaled. If one of
+    /// the fences is already signaled, returns without blocking. Returns an empty list if `iter`
+    /// is empty.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if not all fences belong to the same device.
+    ///
+    pub fn multi_wait_any<'a, I>(
+        iter: I,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<usize>, FenceWaitError>
+    where
+        I: IntoIterator<Item = &'a Fence<D>>,
+        D: 'a,
+    {
+        let fences: SmallVec<[&Fence<D>; 8]> = iter.into_iter().collect();
+        let mut device: Option<&Device> = None;
+
+        for fence in &fences {
+            match device {
+                None => device = Some(&*fence.device),
+                Some(dev) if ptr::eq(dev, &*fence.device) => {}
+                _ => panic!(
+                    "Tried to wait for multiple fences that didn't belong to the same device"
+                ),
+            }
+        }
+
+        let device = match device {
+            Some(device) => device,
+            None => return Ok(Vec::new()),
+        };
+
+        // `ready` also queries the fences whose signal hasn't been observed yet.
+        let mut signaled = Vec::new();
+        for (i, fence) in fences.iter().enumerate() {
+            if fence.ready().map_err(FenceWaitError::OomError)? {
+                signaled.push(i);
+            }
+        }
+
+        if !signaled.is_empty() {
+            return Ok(signaled);
+        }
+
+        let timeout_ns = if let Some(timeout) = timeout {
+            timeout
+                .as_secs()
+                .saturating_mul(1_000_000_000)
+                .saturating_add(timeout.subsec_nanos() as u64)
+        } else {
+            u64::MAX
+        };
+
+        unsafe {
+            let fns = device.fns();
+            let raw_fences: SmallVec<[ash::vk::Fence; 8]> =
+                fences.iter().map(|fence| fence.fence).collect();
+
+            let r = check_errors(fns.v1_0.wait_for_fences(
+                device.internal_object(),
+                raw_fences.len() as u32,
+                raw_fences.as_ptr(),
+                ash::vk::FALSE,
+                timeout_ns,
+            ))?;
+
+            match r {
+                Success::Success => (),
+                Success::Timeout => return Err(FenceWaitError::Timeout),
+                _ => unreachable!(),
+            }
+
+            // Find out which fences are signaled.
+            for (i, fence) in fences.iter().enumerate() {
+                if fence.ready().map_err(FenceWaitError::OomError)? {
+                    signaled.push(i);
+                }
+            }
+
+            Ok(signaled)
+        }
+    }
+
     /// Resets the fence.
     // This function takes a `&mut self` because the Vulkan API requires that the fence be
     // externally synchronized.
     #[inline]
     pub fn reset(&mut self) -> Result<(), OomError> {
+        self.stop_waiter();
+
         unsafe {
             let fns = self.device.fns();
             check_errors(
@@ -297,6 +429,7 @@ where
                     ),
                 };
 
+                fence.stop_waiter();
                 fence.signaled.store(false, Ordering::Relaxed);
                 fence.fence
             })
@@ -314,6 +447,105 @@ where
         }
         Ok(())
     }
+
+    // Stops the background thread started by `poll_wait`, if any.
+    fn stop_waiter(&self) {
+        if let Some(waiter) = self.waiter.lock().unwrap().take() {
+            waiter.cancel.store(true, Ordering::Relaxed);
+            let _ = waiter.thread.join();
+        }
+    }
+}
+
+impl Fence {
+    /// Polls the fence, and if it isn't signaled yet, arranges for the task of `cx` to be woken
+    /// up once it is.
+    pub(crate) fn poll_wait(&self, cx: &mut Context) -> Poll<Result<(), FenceWaitError>> {
+        match self.wait(Some(Duration::from_secs(0))) {
+            Ok(()) => {
+                self.stop_waiter();
+                return Poll::Ready(Ok(()));
+            }
+            Err(FenceWaitError::Timeout) => (),
+            Err(err) => return Poll::Ready(Err(err)),
+        }
+
+        let mut waiter = self.waiter.lock().unwrap();
+        let waiter =
+            waiter.get_or_insert_with(|| FenceWaiter::spawn(self.device.clone(), self.fence));
+        *waiter.waker.lock().unwrap() = Some(cx.waker().clone());
+
+        // The fence may have been signaled before the waker was registered, in which case the
+        // background thread could already have woken up the previous waker.
+        match self.wait(Some(Duration::from_secs(0))) {
+            Ok(()) => Poll::Ready(Ok(())),
+            Err(FenceWaitError::Timeout) => Poll::Pending,
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+}
+
+impl Future for Fence {
+    type Output = Result<(), FenceWaitError>;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.poll_wait(cx)
+    }
+}
+
+// Thread that blocks on a fence and wakes up a task once it is signaled.
+#[derive(Debug)]
+struct FenceWaiter {
+    thread: JoinHandle<()>,
+    waker: Arc<Mutex<Option<Waker>>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl FenceWaiter {
+    // How long each call to `vkWaitForFences` blocks, so that the thread can notice that it has
+    // been cancelled before the fence is reset or destroyed.
+    const WAIT_STEP_NS: u64 = 10_000_000;
+
+    fn spawn(device: Arc<Device>, fence: ash::vk::Fence) -> FenceWaiter {
+        let waker = Arc::new(Mutex::new(None::<Waker>));
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let waker = waker.clone();
+            let cancel = cancel.clone();
+
+            thread::spawn(move || {
+                let fns = device.fns();
+
+                while !cancel.load(Ordering::Relaxed) {
+                    let result = unsafe {
+                        fns.v1_0.wait_for_fences(
+                            device.internal_object(),
+                            1,
+                            &fence,
+                            ash::vk::TRUE,
+                            FenceWaiter::WAIT_STEP_NS,
+                        )
+                    };
+
+                    // Errors are reported to the task the next time it polls the fence.
+                    if result != ash::vk::Result::TIMEOUT {
+                        if let Some(waker) = waker.lock().unwrap().take() {
+                            waker.wake();
+                        }
+                        break;
+                    }
+                }
+            })
+        };
+
+        FenceWaiter {
+            thread,
+            waker,
+            cancel,
+        }
+    }
 }
 
 unsafe impl DeviceOwned for Fence {
@@ -341,10 +573,12 @@ where
 {
     #[inline]
     fn drop(&mut self) {
+        self.stop_waiter();
+
         unsafe {
             if self.must_put_in_pool {
                 let raw_fence = self.fence;
-                self.device.fence_pool().lock().unwrap().push(raw_fence);
+                self.device.fence_pool().push(raw_fence);
             } else {
                 let fns = self.device.fns();
                 fns.v1_0
@@ -406,10 +640,48 @@ impl From<Error> for FenceWaitError {
 
 #[cfg(test)]
 mod tests {
+    use crate::check_errors;
+    use crate::command_buffer::AutoCommandBufferBuilder;
+    use crate::command_buffer::CommandBufferUsage;
+    use crate::sync::now;
     use crate::sync::Fence;
+    use crate::sync::FenceWaitError;
+    use crate::sync::GpuFuture;
     use crate::VulkanObject;
+    use std::future::Future;
+    use std::mem::MaybeUninit;
+    use std::pin::Pin;
+    use std::ptr;
+    use std::sync::Arc;
+    use std::task::Context;
+    use std::task::Poll;
+    use std::task::Wake;
+    use std::thread;
+    use std::thread::Thread;
     use std::time::Duration;
 
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    // Minimal executor that polls `future` on the current thread until it completes.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
     #[test]
     fn fence_create() {
         let (device, _) = gfx_dev_and_queue!();
@@ -426,6 +698,31 @@ mod tests {
         assert!(fence.ready().unwrap());
     }
 
+    #[test]
+    fn fence_from_handle() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let handle = unsafe {
+            let infos = ash::vk::FenceCreateInfo {
+                flags: ash::vk::FenceCreateFlags::SIGNALED,
+                ..Default::default()
+            };
+            let mut output = MaybeUninit::uninit();
+            check_errors(device.fns().v1_0.create_fence(
+                device.internal_object(),
+                &infos,
+                ptr::null(),
+                output.as_mut_ptr(),
+            ))
+            .unwrap();
+            output.assume_init()
+        };
+
+        let fence = unsafe { Fence::from_handle(device.clone(), handle) };
+        assert_eq!(fence.internal_object(), handle);
+        assert!(fence.ready().unwrap());
+    }
+
     #[test]
     fn fence_signaled_wait() {
         let (device, _) = gfx_dev_and_queue!();
@@ -463,6 +760,78 @@ mod tests {
         );
     }
 
+    #[test]
+    fn multiwait_any() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let fence1 = Fence::alloc(device.clone()).unwrap();
+        let fence2 = Fence::alloc_signaled(device.clone()).unwrap();
+        let fence3 = Fence::alloc_signaled(device.clone()).unwrap();
+
+        let signaled = Fence::multi_wait_any(
+            [&fence1, &fence2, &fence3].iter().cloned(),
+            Some(Duration::new(0, 10)),
+        )
+        .unwrap();
+        assert_eq!(signaled, [1, 2]);
+
+        assert_eq!(
+            Fence::multi_wait_any(Some(&fence1), Some(Duration::new(0, 10))),
+            Err(FenceWaitError::Timeout)
+        );
+    }
+
+    #[test]
+    fn multiwait_any_uncached() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        // A fence that is signaled without its signal having been observed yet.
+        let handle = unsafe {
+            let infos = ash::vk::FenceCreateInfo {
+                flags: ash::vk::FenceCreateFlags::SIGNALED,
+                ..Default::default()
+            };
+            let mut output = MaybeUninit::uninit();
+            check_errors(device.fns().v1_0.create_fence(
+                device.internal_object(),
+                &infos,
+                ptr::null(),
+                output.as_mut_ptr(),
+            ))
+            .unwrap();
+            output.assume_init()
+        };
+
+        let fence1 = Fence::alloc_signaled(device.clone()).unwrap();
+        let fence2 = unsafe { Fence::from_handle(device.clone(), handle) };
+
+        let signaled = Fence::multi_wait_any(
+            [&fence1, &fence2].iter().cloned(),
+            Some(Duration::new(0, 0)),
+        )
+        .unwrap();
+        assert_eq!(signaled, [0, 1]);
+    }
+
+    #[test]
+    fn multiwait_any_different_devices() {
+        let (device1, _) = gfx_dev_and_queue!();
+        let (device2, _) = gfx_dev_and_queue!();
+
+        assert_should_panic!(
+            "Tried to wait for multiple fences that didn't belong to the same device",
+            {
+                let fence1 = Fence::alloc_signaled(device1.clone()).unwrap();
+                let fence2 = Fence::alloc_signaled(device2.clone()).unwrap();
+
+                let _ = Fence::multi_wait_any(
+                    [&fence1, &fence2].iter().cloned(),
+                    Some(Duration::new(0, 10)),
+                );
+            }
+        );
+    }
+
     #[test]
     fn multireset_different_devices() {
         use std::iter::once;
@@ -486,16 +855,56 @@ mod tests {
     fn fence_pool() {
         let (device, _) = gfx_dev_and_queue!();
 
-        assert_eq!(device.fence_pool().lock().unwrap().len(), 0);
+        assert_eq!(device.fence_pool().len(), 0);
         let fence1_internal_obj = {
             let fence = Fence::from_pool(device.clone()).unwrap();
-            assert_eq!(device.fence_pool().lock().unwrap().len(), 0);
+            assert_eq!(device.fence_pool().len(), 0);
             fence.internal_object()
         };
 
-        assert_eq!(device.fence_pool().lock().unwrap().len(), 1);
+        assert_eq!(device.fence_pool().len(), 1);
         let fence2 = Fence::from_pool(device.clone()).unwrap();
-        assert_eq!(device.fence_pool().lock().unwrap().len(), 0);
+        assert_eq!(device.fence_pool().len(), 0);
         assert_eq!(fence2.internal_object(), fence1_internal_obj);
     }
+
+    #[test]
+    fn fence_await_signaled() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let fence = Fence::alloc_signaled(device.clone()).unwrap();
+        block_on(fence).unwrap();
+    }
+
+    #[test]
+    fn fence_poll_then_drop() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        // Dropping a fence that is being waited on must stop the background thread.
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut fence = Fence::alloc(device.clone()).unwrap();
+        assert!(Pin::new(&mut fence).poll(&mut cx).is_pending());
+        drop(fence);
+    }
+
+    #[test]
+    fn fence_signal_future_await() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let cb = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+
+        let future = now(device.clone())
+            .then_execute(queue.clone(), cb)
+            .unwrap()
+            .then_signal_fence();
+        block_on(future).unwrap();
+    }
 }
@@ -0,0 +1,162 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::sync::SignalPoint;
+use crate::OomError;
+use std::collections::VecDeque;
+use std::mem;
+
+/// Groups items, such as freed ranges, by the frame during which they were added, and gives them
+/// back once the frame is completed.
+///
+/// A frame is completed either when the fence or timeline value it was ended with is signaled,
+/// which `take_finished` checks, or when the owner of the tracker says so with `take_frame`.
+/// Whether the latter is safe depends on what the items are, so it's up to the owner.
+#[derive(Debug)]
+pub(crate) struct FrameTracker<T> {
+    // The items of the current frame.
+    current: Vec<T>,
+    // The frames that ended but aren't completed yet.
+    in_flight: VecDeque<InFlightFrame<T>>,
+    next_frame: u64,
+}
+
+#[derive(Debug)]
+struct InFlightFrame<T> {
+    frame: u64,
+    // The point that marks the end of the frame, if it was given.
+    point: Option<SignalPoint>,
+    items: Vec<T>,
+}
+
+impl<T> FrameTracker<T> {
+    #[inline]
+    pub fn new() -> FrameTracker<T> {
+        FrameTracker {
+            current: Vec::new(),
+            in_flight: VecDeque::new(),
+            next_frame: 0,
+        }
+    }
+
+    /// Adds an item to the current frame.
+    #[inline]
+    pub fn push(&mut self, item: T) {
+        self.current.push(item);
+    }
+
+    /// Ends the current frame, and returns its identifier. If `point` is `Some`, the frame is
+    /// completed once it is signaled.
+    pub fn end_frame(&mut self, point: Option<SignalPoint>) -> u64 {
+        let frame = self.next_frame;
+        self.next_frame += 1;
+        self.in_flight.push_back(InFlightFrame {
+            frame,
+            point,
+            items: mem::take(&mut self.current),
+        });
+        frame
+    }
+
+    /// Removes `frame` from the frames in flight and returns its items, or returns `None` if it
+    /// isn't in flight.
+    pub fn take_frame(&mut self, frame: u64) -> Option<Vec<T>> {
+        let position = self.in_flight.iter().position(|f| f.frame == frame)?;
+        Some(self.in_flight.remove(position).unwrap().items)
+    }
+
+    /// Removes the frames whose fence or timeline value has been signaled from the frames in
+    /// flight, and returns their items.
+    ///
+    /// The frames that were ended without a point are left alone. This function never blocks.
+    pub fn take_finished(&mut self) -> Result<Vec<T>, OomError> {
+        let mut items = Vec::new();
+        let mut index = 0;
+
+        while index < self.in_flight.len() {
+            let signaled = match self.in_flight[index].point {
+                Some(ref point) => point.is_signaled()?,
+                None => false,
+            };
+
+            if signaled {
+                items.extend(self.in_flight.remove(index).unwrap().items);
+            } else {
+                index += 1;
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// Returns the items of the current frame and of the frames in flight.
+    #[inline]
+    pub fn items(&self) -> impl Iterator<Item = &T> {
+        self.current
+            .iter()
+            .chain(self.in_flight.iter().flat_map(|f| &f.items))
+    }
+
+    /// Returns the number of frames that ended but weren't completed yet.
+    #[inline]
+    pub fn num_frames_in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Drops the items of the current frame and of the frames in flight.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.current.clear();
+        self.in_flight.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sync::FrameTracker;
+    use crate::sync::SignalPoint;
+    use crate::sync::TimelineSemaphore;
+    use std::sync::Arc;
+
+    #[test]
+    fn manual_frames() {
+        let mut tracker = FrameTracker::new();
+        tracker.push(1);
+        let first = tracker.end_frame(None);
+        tracker.push(2);
+        tracker.push(3);
+        let second = tracker.end_frame(None);
+        tracker.push(4);
+        assert_eq!(tracker.items().copied().collect::<Vec<_>>(), [4, 1, 2, 3]);
+        assert_eq!(tracker.num_frames_in_flight(), 2);
+
+        // Frames without a point are only completed manually.
+        assert!(tracker.take_finished().unwrap().is_empty());
+        assert_eq!(tracker.take_frame(second), Some(vec![2, 3]));
+        assert_eq!(tracker.take_frame(second), None);
+        assert_eq!(tracker.take_frame(first), Some(vec![1]));
+        assert_eq!(tracker.num_frames_in_flight(), 0);
+    }
+
+    #[test]
+    fn timeline_frames() {
+        let (device, _) = gfx_dev_and_queue!(timeline_semaphore);
+
+        let semaphore = Arc::new(TimelineSemaphore::new(device, 0).unwrap());
+        let mut tracker = FrameTracker::new();
+        tracker.push(1);
+        tracker.end_frame(Some(SignalPoint::Timeline(semaphore.clone(), 1)));
+        tracker.push(2);
+        tracker.end_frame(Some(SignalPoint::Timeline(semaphore.clone(), 2)));
+
+        semaphore.signal(1).unwrap();
+        assert_eq!(tracker.take_finished().unwrap(), [1]);
+        assert_eq!(tracker.num_frames_in_flight(), 1);
+    }
+}
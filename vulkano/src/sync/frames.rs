@@ -0,0 +1,368 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::command_buffer::pool::StandardCommandPool;
+use crate::device::physical::QueueFamily;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::sync::FenceSignalFuture;
+use crate::sync::FlushError;
+use crate::sync::GpuFuture;
+use crate::sync::Semaphore;
+use crate::sync::SemaphoreError;
+use crate::VulkanObject;
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+/// Keeps track of a fixed number of frames that can be processed by the GPU at the same time.
+///
+/// Rendering usually works by letting the CPU prepare a frame while the GPU is still processing
+/// the previous ones. To avoid the CPU getting too far ahead, and to know when the resources used
+/// by a frame can be reused or destroyed, each frame is associated with a slot. There are as many
+/// slots as frames in flight, and they are used in a round-robin fashion.
+///
+/// `begin_frame` waits until the GPU has finished processing the last frame that used the
+/// current slot, then destroys the objects that were passed to `FrameContext::destroy_later`
+/// during that frame. `end_frame` takes the future that represents the whole frame, signals a
+/// fence and flushes it, then moves on to the next slot.
+///
+/// Each slot also owns the primitives that are only reused once its fence is signaled: a
+/// command pool, which `AutoCommandBufferBuilder::primary_with_pool` can allocate from, and two
+/// semaphores for code that builds its submissions by hand, one to be signaled when the
+/// swapchain image is acquired and one to be signaled when rendering is done. Code that only
+/// uses futures can ignore the semaphores, as the futures create their own.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use vulkano::device::Device;
+/// # use vulkano::device::Queue;
+/// # use vulkano::swapchain::Swapchain;
+/// # use vulkano::sync::GpuFuture;
+/// use vulkano::swapchain;
+/// use vulkano::sync::Frames;
+///
+/// # let device: Arc<Device> = return;
+/// # let queue: Arc<Queue> = return;
+/// # let swapchain: Arc<Swapchain<()>> = return;
+/// let mut frames = Frames::new(device.clone(), queue.family(), 2).unwrap();
+///
+/// loop {
+///     let frame = frames.begin_frame().unwrap();
+///
+///     let (image_num, _suboptimal, acquire_future) =
+///         swapchain::acquire_next_image(swapchain.clone(), None).unwrap();
+///
+///     // Build command buffers from `frame.command_pool()`, and keep alive what has to be
+///     // destroyed after the frame with `frame.destroy_later(...)`.
+///
+///     let future = acquire_future.then_swapchain_present(queue.clone(), swapchain.clone(), image_num);
+///     frames.end_frame(future).unwrap();
+/// }
+/// ```
+pub struct Frames {
+    device: Arc<Device>,
+    frames: Vec<FrameContext>,
+    current: usize,
+    in_frame: bool,
+}
+
+impl Frames {
+    /// Builds a new `Frames` that allows `frames_in_flight` frames to be processed by the GPU at
+    /// the same time.
+    ///
+    /// The command pools of the slots allocate command buffers for `queue_family`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `frames_in_flight` is 0.
+    /// - Panics if the queue family doesn't belong to the same physical device as `device`.
+    ///
+    pub fn new(
+        device: Arc<Device>,
+        queue_family: QueueFamily,
+        frames_in_flight: usize,
+    ) -> Result<Frames, SemaphoreError> {
+        assert_ne!(frames_in_flight, 0);
+
+        let frames = (0..frames_in_flight)
+            .map(|index| {
+                Ok(FrameContext {
+                    index,
+                    fence: None,
+                    flushed: false,
+                    acquire_semaphore: Arc::new(Semaphore::from_pool(device.clone())?),
+                    render_semaphore: Arc::new(Semaphore::from_pool(device.clone())?),
+                    command_pool: Arc::new(StandardCommandPool::new(device.clone(), queue_family)),
+                    pending_destruction: Vec::new(),
+                })
+            })
+            .collect::<Result<_, SemaphoreError>>()?;
+
+        Ok(Frames {
+            device,
+            frames,
+            current: 0,
+            in_frame: false,
+        })
+    }
+
+    /// Returns the maximum number of frames that can be processed by the GPU at the same time.
+    #[inline]
+    pub fn frames_in_flight(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns the index of the slot of the current frame, between 0 and `frames_in_flight`.
+    ///
+    /// This can be used to index per-frame resources, such as uniform buffers.
+    #[inline]
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    /// Starts a new frame.
+    ///
+    /// Blocks until the GPU has finished processing the last frame that used the same slot,
+    /// then destroys the objects that were queued for destruction during that frame.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the previous frame hasn't been ended with `end_frame`.
+    ///
+    pub fn begin_frame(&mut self) -> Result<&mut FrameContext, FlushError> {
+        assert!(!self.in_frame, "`begin_frame` was called twice in a row");
+
+        let frame = &mut self.frames[self.current];
+        frame.wait()?;
+        self.in_frame = true;
+
+        Ok(frame)
+    }
+
+    /// Ends the current frame.
+    ///
+    /// `future` must represent all the GPU work of the frame, usually up to and including
+    /// presenting the swapchain image. A fence is signalled after it and the future is flushed,
+    /// then the next call to `begin_frame` moves on to the next slot.
+    ///
+    /// If flushing returns an error, the frame is still ended. Errors such as
+    /// `FlushError::OutOfDate` can be handled by recreating the swapchain. The future is kept in
+    /// the slot either way, and the next `begin_frame` that reaches the slot blocks until what
+    /// was submitted of it is finished.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `begin_frame` hasn't been called.
+    /// - Panics if `future` doesn't belong to the same device.
+    ///
+    pub fn end_frame<F>(&mut self, future: F) -> Result<(), FlushError>
+    where
+        F: GpuFuture + Send + Sync + 'static,
+    {
+        assert!(
+            self.in_frame,
+            "`end_frame` was called without `begin_frame`"
+        );
+        assert_eq!(
+            future.device().internal_object(),
+            self.device.internal_object()
+        );
+
+        self.in_frame = false;
+        let index = self.current;
+        self.current = (self.current + 1) % self.frames.len();
+        let frame = &mut self.frames[index];
+
+        let future: Box<dyn GpuFuture + Send + Sync> = Box::new(future);
+        let fence = future.then_signal_fence();
+        let result = fence.flush();
+        frame.fence = Some(fence);
+        frame.flushed = result.is_ok();
+
+        result
+    }
+
+    /// Blocks until the GPU has finished processing all the frames, then destroys all the
+    /// objects that were queued for destruction.
+    ///
+    /// This is typically called before recreating resources that are shared between all frames,
+    /// or before exiting.
+    pub fn wait_idle(&mut self) -> Result<(), FlushError> {
+        for frame in &mut self.frames {
+            frame.wait()?;
+        }
+
+        Ok(())
+    }
+}
+
+unsafe impl DeviceOwned for Frames {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+impl fmt::Debug for Frames {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("Frames")
+            .field("frames", &self.frames)
+            .field("current", &self.current)
+            .field("in_frame", &self.in_frame)
+            .finish()
+    }
+}
+
+/// State of one of the slots of a `Frames`, as returned by `Frames::begin_frame`.
+pub struct FrameContext {
+    index: usize,
+    fence: Option<FenceSignalFuture<Box<dyn GpuFuture + Send + Sync>>>,
+    // False if flushing `fence` returned an error in `end_frame`.
+    flushed: bool,
+    acquire_semaphore: Arc<Semaphore>,
+    render_semaphore: Arc<Semaphore>,
+    command_pool: Arc<StandardCommandPool>,
+    pending_destruction: Vec<Box<dyn Any + Send + Sync>>,
+}
+
+impl FrameContext {
+    /// Returns the index of the slot, between 0 and `Frames::frames_in_flight`.
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the semaphore of this slot that is meant to be signaled when the swapchain image
+    /// of the frame is acquired.
+    ///
+    /// The semaphore must be waited upon during the frame, so that it is unsignaled by the time
+    /// the slot is used again.
+    #[inline]
+    pub fn acquire_semaphore(&self) -> &Arc<Semaphore> {
+        &self.acquire_semaphore
+    }
+
+    /// Returns the semaphore of this slot that is meant to be signaled when the rendering of the
+    /// frame is done, and waited upon when presenting.
+    ///
+    /// The semaphore must be waited upon during the frame, so that it is unsignaled by the time
+    /// the slot is used again.
+    #[inline]
+    pub fn render_semaphore(&self) -> &Arc<Semaphore> {
+        &self.render_semaphore
+    }
+
+    /// Returns the command pool of this slot.
+    ///
+    /// Command buffers allocated from it go back to the pool when they are destroyed, so the
+    /// pool only grows up to what a single frame needs.
+    #[inline]
+    pub fn command_pool(&self) -> &Arc<StandardCommandPool> {
+        &self.command_pool
+    }
+
+    /// Keeps `object` alive until the GPU has finished processing this frame.
+    ///
+    /// The object is destroyed the next time `begin_frame` reaches this slot, or when
+    /// `Frames::wait_idle` is called. This is useful for resources that are replaced while they
+    /// may still be in use by the GPU, such as framebuffers of an old swapchain.
+    #[inline]
+    pub fn destroy_later<T>(&mut self, object: T)
+    where
+        T: Send + Sync + 'static,
+    {
+        self.pending_destruction.push(Box::new(object));
+    }
+
+    // Waits for the fence of the last frame that used this slot, and destroys the pending
+    // objects.
+    fn wait(&mut self) -> Result<(), FlushError> {
+        if let Some(fence) = self.fence.take() {
+            if self.flushed {
+                fence.wait(None)?;
+            }
+            // If flushing failed, the error was already returned by `end_frame` and flushing again
+            // would submit the same work twice. Dropping the future instead blocks until what was
+            // submitted is finished.
+        }
+
+        self.pending_destruction.clear();
+        Ok(())
+    }
+}
+
+impl fmt::Debug for FrameContext {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("FrameContext")
+            .field("index", &self.index)
+            .field("in_flight", &self.fence.is_some())
+            .field("pending_destruction", &self.pending_destruction.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_buffer::AutoCommandBufferBuilder;
+    use crate::command_buffer::CommandBufferUsage;
+    use crate::sync::now;
+    use crate::sync::Frames;
+    use crate::sync::GpuFuture;
+    use std::sync::Arc;
+
+    #[test]
+    fn round_robin() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let mut frames = Frames::new(device.clone(), queue.family(), 2).unwrap();
+        let marker = Arc::new(());
+
+        for expected in [0, 1, 0, 1].iter() {
+            let frame = frames.begin_frame().unwrap();
+            assert_eq!(frame.index(), *expected);
+            frame.destroy_later(marker.clone());
+
+            let cb = AutoCommandBufferBuilder::primary_with_pool(
+                frame.command_pool().clone(),
+                CommandBufferUsage::OneTimeSubmit,
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+            let future = now(device.clone()).then_execute(queue.clone(), cb).unwrap();
+            frames.end_frame(future).unwrap();
+        }
+
+        frames.wait_idle().unwrap();
+        assert_eq!(Arc::strong_count(&marker), 1);
+    }
+
+    #[test]
+    fn send_sync() {
+        fn is_send_sync<T: Send + Sync>() {}
+        is_send_sync::<Frames>();
+    }
+
+    #[test]
+    fn begin_twice() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let mut frames = Frames::new(device, queue.family(), 2).unwrap();
+        frames.begin_frame().unwrap();
+
+        assert_should_panic!("`begin_frame` was called twice in a row", {
+            let _ = frames.begin_frame();
+        });
+    }
+}
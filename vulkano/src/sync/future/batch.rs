@@ -0,0 +1,841 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::buffer::BufferAccess;
+use crate::command_buffer::submit::SubmitAnyBuilder;
+use crate::command_buffer::submit::SubmitCommandBufferBuilder;
+use crate::command_buffer::CommandBufferExecError;
+use crate::command_buffer::PrimaryCommandBuffer;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::device::Queue;
+use crate::image::ImageAccess;
+use crate::image::ImageLayout;
+use crate::sync::now;
+use crate::sync::AccessCheckError;
+use crate::sync::AccessFlags;
+use crate::sync::FlushError;
+use crate::sync::GpuFuture;
+use crate::sync::NowFuture;
+use crate::sync::PipelineStages;
+use crate::sync::Semaphore;
+use crate::sync::TimelineSemaphore;
+use crate::VulkanObject;
+use smallvec::SmallVec;
+
+/// Accumulates command buffers that are submitted to a queue all at once.
+///
+/// Every command buffer added to the batch is validated and locked against the previous future
+/// and the command buffers that were added before it, exactly like when chaining
+/// `then_execute` calls. Once flushed, all the command buffers are submitted with a single
+/// `vkQueueSubmit`, together with the semaphores that the previous future waits on.
+///
+/// A batch can also wait on any number of other futures, each with its own pipeline stages, with
+/// `add_wait`, and signal any number of semaphores with `add_signal_semaphore` and
+/// `add_signal_timeline_semaphore`. The waits must be added before the command buffers; use a
+/// `SubmitBuilder` to add them in any order.
+///
+/// A batch is a `GpuFuture` itself, which means that the whole frame can be followed with a
+/// single fence or timeline semaphore signal.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use vulkano::command_buffer::PrimaryAutoCommandBuffer;
+/// # use vulkano::device::Queue;
+/// # let queue: Arc<Queue> = return;
+/// # let shadow_pass: PrimaryAutoCommandBuffer = return;
+/// # let main_pass: PrimaryAutoCommandBuffer = return;
+/// use vulkano::sync::GpuFuture;
+/// use vulkano::sync::SubmissionBatch;
+///
+/// let mut batch = SubmissionBatch::new(queue.clone());
+/// batch.add_command_buffer(shadow_pass).unwrap();
+/// batch.add_command_buffer(main_pass).unwrap();
+///
+/// // One submission and one fence for the whole frame.
+/// let fence = batch.then_signal_fence_and_flush().unwrap();
+/// fence.wait(None).unwrap();
+/// ```
+#[must_use = "Dropping this object will immediately block the thread until the GPU has finished \
+              processing the submission"]
+pub struct SubmissionBatch<F>
+where
+    F: GpuFuture,
+{
+    previous: F,
+    queue: Arc<Queue>,
+    waits: SmallVec<[(Box<dyn GpuFuture>, PipelineStages); 4]>,
+    command_buffers: SmallVec<[Box<dyn PrimaryCommandBuffer>; 4]>,
+    signals: SmallVec<[Arc<Semaphore>; 2]>,
+    timeline_signals: SmallVec<[(Arc<TimelineSemaphore>, u64); 2]>,
+    // True if the batch has already been submitted.
+    // If flush is called multiple times, we want to block so that only one flushing is executed.
+    // Therefore we use a `Mutex<bool>` and not an `AtomicBool`.
+    submitted: Mutex<bool>,
+    finished: AtomicBool,
+}
+
+impl SubmissionBatch<NowFuture> {
+    /// Builds a new empty batch that will be submitted to `queue`.
+    #[inline]
+    pub fn new(queue: Arc<Queue>) -> SubmissionBatch<NowFuture> {
+        let device = queue.device().clone();
+        SubmissionBatch::after(now(device), queue)
+    }
+}
+
+impl<F> SubmissionBatch<F>
+where
+    F: GpuFuture,
+{
+    /// Builds a new empty batch that will be submitted to `queue` after `future`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `future` doesn't belong to the same device as `queue`.
+    /// - Panics if `future` is bound to a different queue.
+    ///
+    pub fn after(future: F, queue: Arc<Queue>) -> SubmissionBatch<F> {
+        assert_eq!(
+            future.device().internal_object(),
+            queue.device().internal_object()
+        );
+
+        if !future.queue_change_allowed() {
+            assert!(future.queue().unwrap().is_same(&queue));
+        }
+
+        SubmissionBatch {
+            previous: future,
+            queue,
+            waits: SmallVec::new(),
+            command_buffers: SmallVec::new(),
+            signals: SmallVec::new(),
+            timeline_signals: SmallVec::new(),
+            submitted: Mutex::new(false),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    /// Adds a command buffer at the end of the batch.
+    ///
+    /// The command buffer is checked and locked immediately. If an error is returned, the batch
+    /// is left unchanged.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the command buffer doesn't belong to the same device.
+    /// - Panics if the batch has already been flushed.
+    ///
+    pub fn add_command_buffer<Cb>(
+        &mut self,
+        command_buffer: Cb,
+    ) -> Result<(), CommandBufferExecError>
+    where
+        Cb: PrimaryCommandBuffer + 'static,
+    {
+        assert_eq!(
+            command_buffer.device().internal_object(),
+            self.queue.device().internal_object()
+        );
+        assert!(
+            !*self.submitted.get_mut().unwrap(),
+            "the batch has already been flushed"
+        );
+
+        command_buffer.lock_submit(&*self, &self.queue)?;
+        self.command_buffers.push(Box::new(command_buffer));
+        Ok(())
+    }
+
+    /// Makes the command buffers of the batch wait for `future`.
+    ///
+    /// Only the given `stages` of the command buffers wait, which makes it possible to start the
+    /// work that doesn't depend on `future` earlier. For example a frame that waits for a
+    /// swapchain image only needs to wait at the `color_attachment_output` stage.
+    ///
+    /// `future` is typically a semaphore signal or a swapchain acquire. Work that was submitted
+    /// to the same queue is merged into the batch.
+    ///
+    /// The command buffers of the batch are checked against the waits when they are added, so
+    /// all the waits must be added first.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `future` doesn't belong to the same device.
+    /// - Panics if `future` is bound to a different queue.
+    /// - Panics if `stages` is empty.
+    /// - Panics if a command buffer has already been added to the batch.
+    /// - Panics if the batch has already been flushed.
+    ///
+    pub fn add_wait<W>(&mut self, future: W, stages: PipelineStages)
+    where
+        W: GpuFuture + 'static,
+    {
+        assert_eq!(
+            future.device().internal_object(),
+            self.queue.device().internal_object()
+        );
+        assert!(!ash::vk::PipelineStageFlags::from(stages).is_empty());
+        assert!(
+            self.command_buffers.is_empty(),
+            "`add_wait` was called after `add_command_buffer`"
+        );
+        assert!(
+            !*self.submitted.get_mut().unwrap(),
+            "the batch has already been flushed"
+        );
+
+        if !future.queue_change_allowed() {
+            assert!(future.queue().unwrap().is_same(&self.queue));
+        }
+
+        self.waits.push((Box::new(future), stages));
+    }
+
+    /// Signals a binary semaphore once all the command buffers of the batch have finished
+    /// executing.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the semaphore doesn't belong to the same device.
+    /// - Panics if the batch has already been flushed.
+    ///
+    /// # Safety
+    ///
+    /// - The semaphore must not be signaled, and must not have a pending signal operation, when
+    ///   the batch is flushed.
+    /// - The work that waits on the semaphore must not access the resources of the batch without
+    ///   also being ordered after the batch by a future.
+    ///
+    pub unsafe fn add_signal_semaphore(&mut self, semaphore: Arc<Semaphore>) {
+        assert_eq!(
+            semaphore.device().internal_object(),
+            self.queue.device().internal_object()
+        );
+        assert!(
+            !*self.submitted.get_mut().unwrap(),
+            "the batch has already been flushed"
+        );
+
+        self.signals.push(semaphore);
+    }
+
+    /// Sets the value of a timeline semaphore once all the command buffers of the batch have
+    /// finished executing.
+    ///
+    /// Any number of timeline semaphores can be signalled by the same submission, and any number
+    /// of submissions on other queues or threads on the CPU can wait for them.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the semaphore doesn't belong to the same device.
+    /// - Panics if the batch has already been flushed.
+    ///
+    pub fn add_signal_timeline_semaphore(&mut self, semaphore: Arc<TimelineSemaphore>, value: u64) {
+        assert_eq!(
+            semaphore.device().internal_object(),
+            self.queue.device().internal_object()
+        );
+        assert!(
+            !*self.submitted.get_mut().unwrap(),
+            "the batch has already been flushed"
+        );
+
+        self.timeline_signals.push((semaphore, value));
+    }
+
+    /// Returns the number of command buffers in the batch.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.command_buffers.len()
+    }
+
+    /// Returns true if no command buffer has been added to the batch.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.command_buffers.is_empty()
+    }
+}
+
+/// Collects the waits, command buffers and signals of a single `vkQueueSubmit`, in any order.
+///
+/// A `SubmissionBatch` checks and locks each command buffer as soon as it is added, against the
+/// waits and the command buffers that were added before it, which means that its waits must be
+/// added first. A `SubmitBuilder` only stores what it is given, and `build` turns it into a
+/// `SubmissionBatch` by adding the waits first, then the command buffers in the order in which
+/// they were added, then the signals.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use vulkano::command_buffer::PrimaryAutoCommandBuffer;
+/// # use vulkano::device::Queue;
+/// # use vulkano::sync::GpuFuture;
+/// # let queue: Arc<Queue> = return;
+/// # let main_pass: PrimaryAutoCommandBuffer = return;
+/// # let upload_finished: Box<dyn GpuFuture> = return;
+/// use vulkano::sync::PipelineStages;
+/// use vulkano::sync::SubmitBuilder;
+///
+/// let mut builder = SubmitBuilder::new(queue.clone());
+/// builder.add_command_buffer(main_pass);
+/// builder.add_wait(
+///     upload_finished,
+///     PipelineStages {
+///         vertex_input: true,
+///         ..PipelineStages::none()
+///     },
+/// );
+///
+/// let fence = builder
+///     .build()
+///     .unwrap()
+///     .then_signal_fence_and_flush()
+///     .unwrap();
+/// ```
+pub struct SubmitBuilder<F>
+where
+    F: GpuFuture,
+{
+    previous: F,
+    queue: Arc<Queue>,
+    waits: SmallVec<[(Box<dyn GpuFuture>, PipelineStages); 4]>,
+    command_buffers: SmallVec<[Box<dyn PrimaryCommandBuffer>; 4]>,
+    signals: SmallVec<[Arc<Semaphore>; 2]>,
+    timeline_signals: SmallVec<[(Arc<TimelineSemaphore>, u64); 2]>,
+}
+
+impl SubmitBuilder<NowFuture> {
+    /// Builds a new empty submission to `queue`.
+    #[inline]
+    pub fn new(queue: Arc<Queue>) -> SubmitBuilder<NowFuture> {
+        let device = queue.device().clone();
+        SubmitBuilder::after(now(device), queue)
+    }
+}
+
+impl<F> SubmitBuilder<F>
+where
+    F: GpuFuture,
+{
+    /// Builds a new empty submission to `queue` that happens after `future`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `future` doesn't belong to the same device as `queue`.
+    ///
+    #[inline]
+    pub fn after(future: F, queue: Arc<Queue>) -> SubmitBuilder<F> {
+        assert_eq!(
+            future.device().internal_object(),
+            queue.device().internal_object()
+        );
+
+        SubmitBuilder {
+            previous: future,
+            queue,
+            waits: SmallVec::new(),
+            command_buffers: SmallVec::new(),
+            signals: SmallVec::new(),
+            timeline_signals: SmallVec::new(),
+        }
+    }
+
+    /// Adds a command buffer to the submission. It is checked and locked by `build`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the command buffer doesn't belong to the same device.
+    ///
+    #[inline]
+    pub fn add_command_buffer<Cb>(&mut self, command_buffer: Cb)
+    where
+        Cb: PrimaryCommandBuffer + 'static,
+    {
+        assert_eq!(
+            command_buffer.device().internal_object(),
+            self.queue.device().internal_object()
+        );
+
+        self.command_buffers.push(Box::new(command_buffer));
+    }
+
+    /// Makes the given `stages` of the command buffers of the submission wait for `future`. See
+    /// `SubmissionBatch::add_wait`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `future` doesn't belong to the same device.
+    /// - Panics if `stages` is empty.
+    ///
+    #[inline]
+    pub fn add_wait<W>(&mut self, future: W, stages: PipelineStages)
+    where
+        W: GpuFuture + 'static,
+    {
+        assert_eq!(
+            future.device().internal_object(),
+            self.queue.device().internal_object()
+        );
+        assert!(!ash::vk::PipelineStageFlags::from(stages).is_empty());
+
+        self.waits.push((Box::new(future), stages));
+    }
+
+    /// Signals a binary semaphore once the command buffers of the submission have finished
+    /// executing.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the semaphore doesn't belong to the same device.
+    ///
+    /// # Safety
+    ///
+    /// Same as `SubmissionBatch::add_signal_semaphore`.
+    ///
+    #[inline]
+    pub unsafe fn add_signal_semaphore(&mut self, semaphore: Arc<Semaphore>) {
+        assert_eq!(
+            semaphore.device().internal_object(),
+            self.queue.device().internal_object()
+        );
+
+        self.signals.push(semaphore);
+    }
+
+    /// Sets the value of a timeline semaphore once the command buffers of the submission have
+    /// finished executing.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the semaphore doesn't belong to the same device.
+    ///
+    #[inline]
+    pub fn add_signal_timeline_semaphore(&mut self, semaphore: Arc<TimelineSemaphore>, value: u64) {
+        assert_eq!(
+            semaphore.device().internal_object(),
+            self.queue.device().internal_object()
+        );
+
+        self.timeline_signals.push((semaphore, value));
+    }
+
+    /// Checks and locks the command buffers, and returns the `SubmissionBatch` that submits
+    /// everything that was added at once.
+    ///
+    /// If a command buffer can't be locked, the command buffers that were already locked are
+    /// unlocked and nothing is submitted.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `future` or one of the waits is bound to a different queue.
+    ///
+    pub fn build(self) -> Result<SubmissionBatch<F>, CommandBufferExecError> {
+        let mut batch = SubmissionBatch::after(self.previous, self.queue);
+
+        for (wait, stages) in self.waits {
+            batch.add_wait(wait, stages);
+        }
+
+        for command_buffer in self.command_buffers {
+            if let Err(err) = batch.add_command_buffer(command_buffer) {
+                // Nothing has been submitted, so release the command buffers that were locked
+                // and don't let the batch flush itself when it is dropped.
+                unsafe {
+                    for command_buffer in &batch.command_buffers {
+                        command_buffer.unlock();
+                    }
+                }
+                *batch.finished.get_mut() = true;
+                return Err(err);
+            }
+        }
+
+        batch.signals = self.signals;
+        batch.timeline_signals = self.timeline_signals;
+        Ok(batch)
+    }
+}
+
+unsafe impl<F> GpuFuture for SubmissionBatch<F>
+where
+    F: GpuFuture,
+{
+    #[inline]
+    fn cleanup_finished(&mut self) {
+        self.previous.cleanup_finished();
+
+        for (wait, _) in &mut self.waits {
+            wait.cleanup_finished();
+        }
+    }
+
+    unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
+        let mut builder = match self.previous.build_submission()? {
+            SubmitAnyBuilder::Empty => SubmitCommandBufferBuilder::new(),
+            SubmitAnyBuilder::SemaphoresWait(sem) => sem.into(),
+            SubmitAnyBuilder::CommandBuffer(builder) => builder,
+            SubmitAnyBuilder::QueuePresent(_) | SubmitAnyBuilder::BindSparse(_) => {
+                self.previous.flush()?;
+                SubmitCommandBufferBuilder::new()
+            }
+        };
+
+        for (wait, stages) in &self.waits {
+            builder = match wait.build_submission()? {
+                SubmitAnyBuilder::Empty => builder,
+                SubmitAnyBuilder::SemaphoresWait(sem) => {
+                    builder.merge(sem.into_command_buffer_builder(*stages))
+                }
+                SubmitAnyBuilder::CommandBuffer(other) => builder.merge(other),
+                SubmitAnyBuilder::QueuePresent(_) | SubmitAnyBuilder::BindSparse(_) => {
+                    wait.flush()?;
+                    builder
+                }
+            };
+        }
+
+        for command_buffer in &self.command_buffers {
+            builder.add_command_buffer(command_buffer.inner());
+        }
+
+        for semaphore in &self.signals {
+            builder.add_signal_semaphore(semaphore);
+        }
+
+        for (semaphore, value) in &self.timeline_signals {
+            builder.add_signal_timeline_semaphore(semaphore, *value);
+        }
+
+        Ok(SubmitAnyBuilder::CommandBuffer(builder))
+    }
+
+    #[inline]
+    fn flush(&self) -> Result<(), FlushError> {
+        unsafe {
+            let mut submitted = self.submitted.lock().unwrap();
+            if *submitted {
+                return Ok(());
+            }
+
+            match self.build_submission()? {
+                SubmitAnyBuilder::CommandBuffer(builder) => {
+                    builder.submit(&self.queue)?;
+                }
+                _ => unreachable!(),
+            };
+
+            // Only write `true` here in order to try again next time if we failed to submit.
+            *submitted = true;
+            Ok(())
+        }
+    }
+
+    #[inline]
+    unsafe fn signal_finished(&self) {
+        if !self.finished.swap(true, Ordering::SeqCst) {
+            for command_buffer in &self.command_buffers {
+                command_buffer.unlock();
+            }
+        }
+
+        self.previous.signal_finished();
+
+        for (wait, _) in &self.waits {
+            wait.signal_finished();
+        }
+    }
+
+    #[inline]
+    fn queue_change_allowed(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<Arc<Queue>> {
+        Some(self.queue.clone())
+    }
+
+    fn check_buffer_access(
+        &self,
+        buffer: &dyn BufferAccess,
+        exclusive: bool,
+        queue: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+        // The last command buffer that uses the resource determines the access.
+        for command_buffer in self.command_buffers.iter().rev() {
+            match command_buffer.check_buffer_access(buffer, exclusive, queue) {
+                Err(AccessCheckError::Unknown) => (),
+                result => return result,
+            }
+        }
+
+        match self.previous.check_buffer_access(buffer, exclusive, queue) {
+            Err(AccessCheckError::Unknown) => (),
+            result => return result,
+        }
+
+        for (wait, _) in &self.waits {
+            match wait.check_buffer_access(buffer, exclusive, queue) {
+                Err(AccessCheckError::Unknown) => (),
+                result => return result,
+            }
+        }
+
+        Err(AccessCheckError::Unknown)
+    }
+
+    fn check_image_access(
+        &self,
+        image: &dyn ImageAccess,
+        layout: ImageLayout,
+        exclusive: bool,
+        queue: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+        for command_buffer in self.command_buffers.iter().rev() {
+            match command_buffer.check_image_access(image, layout, exclusive, queue) {
+                Err(AccessCheckError::Unknown) => (),
+                result => return result,
+            }
+        }
+
+        match self
+            .previous
+            .check_image_access(image, layout, exclusive, queue)
+        {
+            Err(AccessCheckError::Unknown) => (),
+            result => return result,
+        }
+
+        for (wait, _) in &self.waits {
+            match wait.check_image_access(image, layout, exclusive, queue) {
+                Err(AccessCheckError::Unknown) => (),
+                result => return result,
+            }
+        }
+
+        Err(AccessCheckError::Unknown)
+    }
+}
+
+unsafe impl<F> DeviceOwned for SubmissionBatch<F>
+where
+    F: GpuFuture,
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.queue.device()
+    }
+}
+
+impl<F> Drop for SubmissionBatch<F>
+where
+    F: GpuFuture,
+{
+    fn drop(&mut self) {
+        unsafe {
+            if !*self.finished.get_mut() {
+                // TODO: handle errors?
+                self.flush().unwrap();
+                // Block until the queue finished.
+                self.queue.wait().unwrap();
+                for command_buffer in &self.command_buffers {
+                    command_buffer.unlock();
+                }
+                self.previous.signal_finished();
+                for (wait, _) in &self.waits {
+                    wait.signal_finished();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::command_buffer::AutoCommandBufferBuilder;
+    use crate::command_buffer::CommandBufferUsage;
+    use crate::command_buffer::PrimaryCommandBuffer;
+    use crate::sync::now;
+    use crate::sync::GpuFuture;
+    use crate::sync::PipelineStages;
+    use crate::sync::Semaphore;
+    use crate::sync::SubmissionBatch;
+    use crate::sync::SubmitBuilder;
+    use std::sync::Arc;
+
+    #[test]
+    fn batch_dependent_command_buffers() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_data(
+            device.clone(),
+            BufferUsage::transfer_destination(),
+            false,
+            0u32,
+        )
+        .unwrap();
+
+        let mut batch = SubmissionBatch::new(queue.clone());
+        for value in [1, 2].iter() {
+            let mut builder = AutoCommandBufferBuilder::primary(
+                device.clone(),
+                queue.family(),
+                CommandBufferUsage::OneTimeSubmit,
+            )
+            .unwrap();
+            builder.fill_buffer(buffer.clone(), *value).unwrap();
+            batch.add_command_buffer(builder.build().unwrap()).unwrap();
+        }
+        assert_eq!(batch.len(), 2);
+
+        batch
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        assert_eq!(*buffer.read().unwrap(), 2);
+    }
+
+    #[test]
+    fn batch_wait_semaphore() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_data(
+            device.clone(),
+            BufferUsage::transfer_destination(),
+            false,
+            0u32,
+        )
+        .unwrap();
+
+        let fill = |value| {
+            let mut builder = AutoCommandBufferBuilder::primary(
+                device.clone(),
+                queue.family(),
+                CommandBufferUsage::OneTimeSubmit,
+            )
+            .unwrap();
+            builder.fill_buffer(buffer.clone(), value).unwrap();
+            builder.build().unwrap()
+        };
+
+        let first = fill(1)
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_semaphore();
+
+        let mut batch = SubmissionBatch::new(queue.clone());
+        batch.add_wait(
+            first,
+            PipelineStages {
+                transfer: true,
+                ..PipelineStages::none()
+            },
+        );
+        batch.add_command_buffer(fill(2)).unwrap();
+
+        batch
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        assert_eq!(*buffer.read().unwrap(), 2);
+    }
+
+    #[test]
+    fn submit_builder_any_order() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_data(
+            device.clone(),
+            BufferUsage::transfer_destination(),
+            false,
+            0u32,
+        )
+        .unwrap();
+
+        let fill = |value| {
+            let mut builder = AutoCommandBufferBuilder::primary(
+                device.clone(),
+                queue.family(),
+                CommandBufferUsage::OneTimeSubmit,
+            )
+            .unwrap();
+            builder.fill_buffer(buffer.clone(), value).unwrap();
+            builder.build().unwrap()
+        };
+
+        let first = fill(1)
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_semaphore();
+        let semaphore = Arc::new(Semaphore::from_pool(device.clone()).unwrap());
+
+        let mut builder = SubmitBuilder::new(queue.clone());
+        builder.add_command_buffer(fill(2));
+        builder.add_wait(
+            first,
+            PipelineStages {
+                transfer: true,
+                ..PipelineStages::none()
+            },
+        );
+        unsafe {
+            builder.add_signal_semaphore(semaphore);
+        }
+
+        builder
+            .build()
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        assert_eq!(*buffer.read().unwrap(), 2);
+    }
+
+    #[test]
+    fn add_wait_after_command_buffer() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let command_buffer = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+
+        let mut batch = SubmissionBatch::new(queue.clone());
+        batch.add_command_buffer(command_buffer).unwrap();
+
+        assert_should_panic!("`add_wait` was called after `add_command_buffer`", {
+            batch.add_wait(
+                now(device.clone()),
+                PipelineStages {
+                    transfer: true,
+                    ..PipelineStages::none()
+                },
+            );
+        });
+    }
+}
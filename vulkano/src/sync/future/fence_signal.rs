@@ -7,10 +7,14 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::future::Future;
 use std::mem;
+use std::pin::Pin;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::MutexGuard;
+use std::task::Context;
+use std::task::Poll;
 use std::time::Duration;
 
 use crate::buffer::BufferAccess;
@@ -27,6 +31,7 @@ use crate::sync::Fence;
 use crate::sync::FlushError;
 use crate::sync::GpuFuture;
 use crate::sync::PipelineStages;
+use crate::OomError;
 
 /// Builds a new fence signal future.
 #[inline]
@@ -88,6 +93,18 @@ pub enum FenceSignalFutureBehavior {
 /// // Later you can wait until you reach the point of `fence_signal`:
 /// fence_signal.wait(None).unwrap();
 /// ```
+///
+/// `FenceSignalFuture` also implements `std::future::Future`. Awaiting it flushes the future if
+/// necessary, then completes once the fence is signaled, without blocking the current thread.
+///
+/// ```
+/// use vulkano::sync::GpuFuture;
+///
+/// # async fn example(future: Box<dyn GpuFuture>) {
+/// future.then_signal_fence().await.unwrap();
+/// // The GPU has now finished processing the chain of operations.
+/// # }
+/// ```
 #[must_use = "Dropping this object will immediately block the thread until the GPU has finished \
               processing the submission"]
 pub struct FenceSignalFuture<F>
@@ -154,6 +171,57 @@ where
             _ => unreachable!(),
         }
     }
+
+    /// Returns true if the fence has been signaled by the GPU.
+    ///
+    /// Contrary to `wait`, this function doesn't flush the future and never blocks. Returns
+    /// `false` if the future hasn't been flushed yet, or if a previous flush panicked.
+    pub fn is_signaled(&self) -> Result<bool, OomError> {
+        let state = self.state.lock().unwrap();
+
+        match *state {
+            FenceSignalFutureState::Pending(_, _)
+            | FenceSignalFutureState::PartiallyFlushed(_, _) => Ok(false),
+            FenceSignalFutureState::Flushed(_, ref fence) => fence.ready(),
+            FenceSignalFutureState::Cleaned => Ok(true),
+            // Nothing is known about what was submitted, so the fence can't be trusted.
+            FenceSignalFutureState::Poisoned => Ok(false),
+        }
+    }
+}
+
+impl<F> Future for FenceSignalFuture<F>
+where
+    F: GpuFuture,
+{
+    type Output = Result<(), FlushError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+
+        if let Err(err) = self.flush_impl(&mut state) {
+            return Poll::Ready(Err(err));
+        }
+
+        match *state {
+            FenceSignalFutureState::Flushed(_, ref fence) => match fence.poll_wait(cx) {
+                Poll::Ready(Ok(())) => (),
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err.into())),
+                Poll::Pending => return Poll::Pending,
+            },
+            FenceSignalFutureState::Cleaned => return Poll::Ready(Ok(())),
+            _ => unreachable!(),
+        }
+
+        match mem::replace(&mut *state, FenceSignalFutureState::Cleaned) {
+            FenceSignalFutureState::Flushed(previous, _) => unsafe {
+                previous.signal_finished();
+            },
+            _ => unreachable!(),
+        }
+
+        Poll::Ready(Ok(()))
+    }
 }
 
 impl<F> FenceSignalFuture<F>
@@ -260,16 +328,18 @@ where
                         .map_err(|err| OutcomeErr::Full(err.into()))
                 }
                 SubmitAnyBuilder::QueuePresent(present) => {
+                    // Lock the queue only once for both submissions.
+                    let mut queue = queue.lock();
                     let intermediary_result = if partially_flushed {
                         Ok(())
                     } else {
-                        present.submit(&queue)
+                        present.submit_locked(&mut queue)
                     };
                     match intermediary_result {
                         Ok(()) => {
                             let mut b = SubmitCommandBufferBuilder::new();
                             b.set_fence_signal(&fence);
-                            b.submit(&queue)
+                            b.submit_locked(&mut queue)
                                 .map_err(|err| OutcomeErr::Partial(err.into()))
                         }
                         Err(err) => Err(OutcomeErr::Full(err.into())),
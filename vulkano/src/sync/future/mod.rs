@@ -7,10 +7,16 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+pub use self::batch::SubmissionBatch;
+pub use self::batch::SubmitBuilder;
 pub use self::fence_signal::{FenceSignalFuture, FenceSignalFutureBehavior};
 pub use self::join::JoinFuture;
 pub use self::now::{now, NowFuture};
 pub use self::semaphore_signal::SemaphoreSignalFuture;
+pub use self::semaphore_wait::{wait_semaphore, SemaphoreWaitFuture};
+pub use self::timeline::{
+    wait_timeline_semaphore, TimelineSemaphoreSignalFuture, TimelineSemaphoreWaitFuture,
+};
 use crate::buffer::BufferAccess;
 use crate::command_buffer::submit::SubmitAnyBuilder;
 use crate::command_buffer::submit::SubmitBindSparseError;
@@ -30,15 +36,20 @@ use crate::swapchain::Swapchain;
 use crate::sync::AccessFlags;
 use crate::sync::FenceWaitError;
 use crate::sync::PipelineStages;
+use crate::sync::SemaphoreError;
+use crate::sync::TimelineSemaphore;
 use crate::OomError;
 use std::error;
 use std::fmt;
 use std::sync::Arc;
 
+mod batch;
 mod fence_signal;
 mod join;
 mod now;
 mod semaphore_signal;
+mod semaphore_wait;
+mod timeline;
 
 /// Represents an event that will happen on the GPU in the future.
 ///
@@ -221,6 +232,31 @@ pub unsafe trait GpuFuture: DeviceOwned {
         Ok(f)
     }
 
+    /// Sets the value of a timeline semaphore after this future. Returns another future that
+    /// represents the moment when the value is reached.
+    ///
+    /// Contrary to `then_signal_semaphore`, other submissions and the CPU can wait on the value
+    /// any number of times, with `sync::wait_timeline_semaphore` or `TimelineSemaphore::wait`.
+    /// `value` must be greater than the current value of the semaphore and than the values of the
+    /// signal operations that are still pending.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if this future isn't bound to a queue.
+    /// - Panics if the semaphore doesn't belong to the same device.
+    ///
+    #[inline]
+    fn then_signal_timeline_semaphore(
+        self,
+        semaphore: Arc<TimelineSemaphore>,
+        value: u64,
+    ) -> TimelineSemaphoreSignalFuture<Self>
+    where
+        Self: Sized,
+    {
+        timeline::then_signal_timeline_semaphore(self, semaphore, value)
+    }
+
     /// Signals a fence after this future. Returns another future that represents the signal.
     ///
     /// > **Note**: More often than not you want to immediately flush the future after calling this
@@ -553,3 +589,12 @@ impl From<FenceWaitError> for FlushError {
         }
     }
 }
+
+// Turns an error while creating a binary semaphore, which doesn't need any extension or feature,
+// into a `FlushError`.
+pub(crate) fn semaphore_flush_error(err: SemaphoreError) -> FlushError {
+    match err {
+        SemaphoreError::OomError(err) => FlushError::OomError(err),
+        _ => panic!("unexpected error: {:?}", err),
+    }
+}
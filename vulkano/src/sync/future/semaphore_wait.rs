@@ -0,0 +1,107 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+
+use crate::buffer::BufferAccess;
+use crate::command_buffer::submit::SubmitAnyBuilder;
+use crate::command_buffer::submit::SubmitSemaphoresWaitBuilder;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::device::Queue;
+use crate::image::ImageAccess;
+use crate::image::ImageLayout;
+use crate::sync::AccessCheckError;
+use crate::sync::AccessFlags;
+use crate::sync::FlushError;
+use crate::sync::GpuFuture;
+use crate::sync::PipelineStages;
+use crate::sync::Semaphore;
+
+/// Builds a future that represents the moment when a semaphore is signaled by something that
+/// vulkano doesn't know about, for example an imported sync file or another API.
+///
+/// Operations that are executed after this future wait for the semaphore on the GPU, which
+/// unsignals it. The semaphore must be signaled, or have a pending signal operation, when the
+/// operations are submitted, and must not be waited on by anything else.
+#[inline]
+pub fn wait_semaphore(semaphore: Arc<Semaphore>) -> SemaphoreWaitFuture {
+    SemaphoreWaitFuture { semaphore }
+}
+
+/// Represents the moment when a semaphore is signaled.
+pub struct SemaphoreWaitFuture {
+    semaphore: Arc<Semaphore>,
+}
+
+impl SemaphoreWaitFuture {
+    /// Returns the semaphore that is waited on.
+    #[inline]
+    pub fn semaphore(&self) -> &Arc<Semaphore> {
+        &self.semaphore
+    }
+}
+
+unsafe impl GpuFuture for SemaphoreWaitFuture {
+    #[inline]
+    fn cleanup_finished(&mut self) {}
+
+    #[inline]
+    unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
+        let mut sem = SubmitSemaphoresWaitBuilder::new();
+        sem.add_wait_semaphore(&self.semaphore);
+        Ok(SubmitAnyBuilder::SemaphoresWait(sem))
+    }
+
+    #[inline]
+    fn flush(&self) -> Result<(), FlushError> {
+        Ok(())
+    }
+
+    #[inline]
+    unsafe fn signal_finished(&self) {}
+
+    #[inline]
+    fn queue_change_allowed(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<Arc<Queue>> {
+        None
+    }
+
+    #[inline]
+    fn check_buffer_access(
+        &self,
+        _: &dyn BufferAccess,
+        _: bool,
+        _: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+        Err(AccessCheckError::Unknown)
+    }
+
+    #[inline]
+    fn check_image_access(
+        &self,
+        _: &dyn ImageAccess,
+        _: ImageLayout,
+        _: bool,
+        _: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+        Err(AccessCheckError::Unknown)
+    }
+}
+
+unsafe impl DeviceOwned for SemaphoreWaitFuture {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.semaphore.device()
+    }
+}
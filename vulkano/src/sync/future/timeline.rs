@@ -0,0 +1,346 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::buffer::BufferAccess;
+use crate::command_buffer::submit::SubmitAnyBuilder;
+use crate::command_buffer::submit::SubmitCommandBufferBuilder;
+use crate::command_buffer::submit::SubmitSemaphoresWaitBuilder;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::device::Queue;
+use crate::image::ImageAccess;
+use crate::image::ImageLayout;
+use crate::sync::semaphore_flush_error;
+use crate::sync::AccessCheckError;
+use crate::sync::AccessFlags;
+use crate::sync::FlushError;
+use crate::sync::GpuFuture;
+use crate::sync::PipelineStages;
+use crate::sync::Semaphore;
+use crate::sync::TimelineSemaphore;
+use crate::VulkanObject;
+
+/// Builds a new timeline semaphore signal future.
+#[inline]
+pub fn then_signal_timeline_semaphore<F>(
+    future: F,
+    semaphore: Arc<TimelineSemaphore>,
+    value: u64,
+) -> TimelineSemaphoreSignalFuture<F>
+where
+    F: GpuFuture,
+{
+    assert!(future.queue().is_some()); // TODO: document
+    assert_eq!(
+        future.device().internal_object(),
+        semaphore.device().internal_object()
+    );
+
+    TimelineSemaphoreSignalFuture {
+        previous: future,
+        semaphore,
+        value,
+        wait_submitted: Mutex::new(false),
+        previous_submitted: Mutex::new(None),
+        finished: AtomicBool::new(false),
+    }
+}
+
+/// Represents a timeline semaphore being set to a value after a previous event.
+#[must_use = "Dropping this object will immediately block the thread until the GPU has finished \
+              processing the submission"]
+pub struct TimelineSemaphoreSignalFuture<F>
+where
+    F: GpuFuture,
+{
+    previous: F,
+    semaphore: Arc<TimelineSemaphore>,
+    value: u64,
+    // True if the signaling command has already been submitted.
+    // If flush is called multiple times, we want to block so that only one flushing is executed.
+    // Therefore we use a `Mutex<bool>` and not an `AtomicBool`.
+    wait_submitted: Mutex<bool>,
+    // Set if the previous submission can't signal the timeline semaphore itself, and has already
+    // been submitted on its own. The signal operation is then submitted separately, after it.
+    previous_submitted: Mutex<Option<PreviousSubmission>>,
+    finished: AtomicBool,
+}
+
+// A previous submission that has been submitted without the signal operation.
+enum PreviousSubmission {
+    // A present, which is followed in the queue by the signal operation.
+    Present,
+    // A sparse binding, which signals a binary semaphore that the signal operation waits on.
+    // The semaphore is kept alive until the future is dropped.
+    BindSparse(Arc<Semaphore>),
+}
+
+impl<F> TimelineSemaphoreSignalFuture<F>
+where
+    F: GpuFuture,
+{
+    /// Returns the semaphore that is signalled.
+    #[inline]
+    pub fn semaphore(&self) -> &Arc<TimelineSemaphore> {
+        &self.semaphore
+    }
+
+    /// Returns the value that the semaphore is set to.
+    #[inline]
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+}
+
+unsafe impl<F> GpuFuture for TimelineSemaphoreSignalFuture<F>
+where
+    F: GpuFuture,
+{
+    #[inline]
+    fn cleanup_finished(&mut self) {
+        self.previous.cleanup_finished();
+    }
+
+    #[inline]
+    unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
+        // Flushing the signaling part, since it must always be submitted before the waiting part.
+        self.flush()?;
+
+        let mut sem = SubmitSemaphoresWaitBuilder::new();
+        sem.add_wait_timeline_semaphore(&self.semaphore, self.value);
+        Ok(SubmitAnyBuilder::SemaphoresWait(sem))
+    }
+
+    fn flush(&self) -> Result<(), FlushError> {
+        unsafe {
+            let mut wait_submitted = self.wait_submitted.lock().unwrap();
+
+            if *wait_submitted {
+                return Ok(());
+            }
+
+            let queue = self.previous.queue().unwrap().clone();
+            let mut previous_submitted = self.previous_submitted.lock().unwrap();
+
+            if previous_submitted.is_none() {
+                // Declared before the submission, which borrows it until the end of the block. It
+                // is shared with `previous_submitted` since it can't be moved there.
+                let semaphore;
+                let submission = self.previous.build_submission()?;
+                match submission {
+                    SubmitAnyBuilder::Empty => {
+                        let mut builder = SubmitCommandBufferBuilder::new();
+                        builder.add_signal_timeline_semaphore(&self.semaphore, self.value);
+                        builder.submit(&queue)?;
+                    }
+                    SubmitAnyBuilder::SemaphoresWait(sem) => {
+                        let mut builder: SubmitCommandBufferBuilder = sem.into();
+                        builder.add_signal_timeline_semaphore(&self.semaphore, self.value);
+                        builder.submit(&queue)?;
+                    }
+                    SubmitAnyBuilder::CommandBuffer(mut builder) => {
+                        builder.add_signal_timeline_semaphore(&self.semaphore, self.value);
+                        builder.submit(&queue)?;
+                    }
+                    SubmitAnyBuilder::BindSparse(mut builder) => {
+                        semaphore = Arc::new(
+                            Semaphore::from_pool(self.device().clone())
+                                .map_err(semaphore_flush_error)?,
+                        );
+                        builder.add_signal_semaphore(&semaphore);
+                        builder.submit(&queue)?;
+                        *previous_submitted =
+                            Some(PreviousSubmission::BindSparse(semaphore.clone()));
+                    }
+                    SubmitAnyBuilder::QueuePresent(present) => {
+                        present.submit(&queue)?;
+                        *previous_submitted = Some(PreviousSubmission::Present);
+                    }
+                };
+            }
+
+            // If the previous submission was submitted on its own, possibly during an earlier
+            // call that failed afterwards, only the signal operation is left to submit.
+            if let Some(ref previous_submission) = *previous_submitted {
+                let mut builder = SubmitCommandBufferBuilder::new();
+                if let PreviousSubmission::BindSparse(ref semaphore) = *previous_submission {
+                    builder.add_wait_semaphore(
+                        semaphore,
+                        PipelineStages {
+                            all_commands: true,
+                            ..PipelineStages::none()
+                        },
+                    );
+                }
+                builder.add_signal_timeline_semaphore(&self.semaphore, self.value);
+                builder.submit(&queue)?;
+            }
+
+            // Only write `true` here in order to try again next time if an error occurs.
+            *wait_submitted = true;
+            Ok(())
+        }
+    }
+
+    #[inline]
+    unsafe fn signal_finished(&self) {
+        debug_assert!(*self.wait_submitted.lock().unwrap());
+        self.finished.store(true, Ordering::SeqCst);
+        self.previous.signal_finished();
+    }
+
+    #[inline]
+    fn queue_change_allowed(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<Arc<Queue>> {
+        self.previous.queue()
+    }
+
+    #[inline]
+    fn check_buffer_access(
+        &self,
+        buffer: &dyn BufferAccess,
+        exclusive: bool,
+        queue: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+        self.previous
+            .check_buffer_access(buffer, exclusive, queue)
+            .map(|_| None)
+    }
+
+    #[inline]
+    fn check_image_access(
+        &self,
+        image: &dyn ImageAccess,
+        layout: ImageLayout,
+        exclusive: bool,
+        queue: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+        self.previous
+            .check_image_access(image, layout, exclusive, queue)
+            .map(|_| None)
+    }
+}
+
+unsafe impl<F> DeviceOwned for TimelineSemaphoreSignalFuture<F>
+where
+    F: GpuFuture,
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.semaphore.device()
+    }
+}
+
+impl<F> Drop for TimelineSemaphoreSignalFuture<F>
+where
+    F: GpuFuture,
+{
+    fn drop(&mut self) {
+        unsafe {
+            if !*self.finished.get_mut() {
+                // TODO: handle errors?
+                self.flush().unwrap();
+                // Block until the queue finished.
+                self.queue().unwrap().wait().unwrap();
+                self.previous.signal_finished();
+            }
+        }
+    }
+}
+
+/// Builds a future that represents the moment when the value of a timeline semaphore becomes
+/// greater than or equal to `value`.
+///
+/// Operations that are executed after this future wait for the value on the GPU. The operation
+/// that signals the value doesn't need to be submitted yet, it can even be signalled from the
+/// CPU with `TimelineSemaphore::signal`. It must eventually be signalled though, otherwise the
+/// queue will be blocked forever.
+///
+/// Presenting a swapchain image can't wait on a timeline semaphore directly. Execute a command
+/// buffer after this future first.
+#[inline]
+pub fn wait_timeline_semaphore(
+    semaphore: Arc<TimelineSemaphore>,
+    value: u64,
+) -> TimelineSemaphoreWaitFuture {
+    TimelineSemaphoreWaitFuture { semaphore, value }
+}
+
+/// Represents the moment when the value of a timeline semaphore is reached.
+pub struct TimelineSemaphoreWaitFuture {
+    semaphore: Arc<TimelineSemaphore>,
+    value: u64,
+}
+
+unsafe impl GpuFuture for TimelineSemaphoreWaitFuture {
+    #[inline]
+    fn cleanup_finished(&mut self) {}
+
+    #[inline]
+    unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
+        let mut sem = SubmitSemaphoresWaitBuilder::new();
+        sem.add_wait_timeline_semaphore(&self.semaphore, self.value);
+        Ok(SubmitAnyBuilder::SemaphoresWait(sem))
+    }
+
+    #[inline]
+    fn flush(&self) -> Result<(), FlushError> {
+        Ok(())
+    }
+
+    #[inline]
+    unsafe fn signal_finished(&self) {}
+
+    #[inline]
+    fn queue_change_allowed(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<Arc<Queue>> {
+        None
+    }
+
+    #[inline]
+    fn check_buffer_access(
+        &self,
+        _: &dyn BufferAccess,
+        _: bool,
+        _: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+        Err(AccessCheckError::Unknown)
+    }
+
+    #[inline]
+    fn check_image_access(
+        &self,
+        _: &dyn ImageAccess,
+        _: ImageLayout,
+        _: bool,
+        _: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+        Err(AccessCheckError::Unknown)
+    }
+}
+
+unsafe impl DeviceOwned for TimelineSemaphoreWaitFuture {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.semaphore.device()
+    }
+}
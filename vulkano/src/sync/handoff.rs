@@ -0,0 +1,659 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::buffer::BufferAccess;
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::BarrierBuilder;
+use crate::command_buffer::BufferMemoryBarrier;
+use crate::command_buffer::BuildError;
+use crate::command_buffer::CommandBufferExecError;
+use crate::command_buffer::CommandBufferExecFuture;
+use crate::command_buffer::CommandBufferUsage;
+use crate::command_buffer::ImageMemoryBarrier;
+use crate::command_buffer::PipelineBarrierError;
+use crate::command_buffer::PrimaryCommandBuffer;
+use crate::device::DeviceOwned;
+use crate::device::Queue;
+use crate::image::ImageAccess;
+use crate::sync::AccessFlags;
+use crate::sync::FlushError;
+use crate::sync::GpuFuture;
+use crate::sync::PipelineStages;
+use crate::OomError;
+use crate::VulkanObject;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+/// A resource that can be used on a queue once a future has been reached.
+///
+/// This is the result of handing off a resource from the queue it was last used on to another
+/// queue, for example to run compute or transfer work on a dedicated queue concurrently with
+/// graphics. `QueueHandoff::buffer` and `QueueHandoff::image` take care of the whole handoff:
+///
+/// - If the two queues belong to different families and the resource was created with
+///   `Sharing::Exclusive`, its ownership is released on the source queue and acquired on the
+///   destination queue with a pair of pipeline barriers.
+/// - A semaphore is signaled on the source queue and waited for on the destination queue. It is
+///   omitted if both queues are the same.
+///
+/// The work that uses the resource on the destination queue must then be executed after the
+/// future of the `QueueHandoff`, which is what `then_execute` does.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use vulkano::buffer::CpuAccessibleBuffer;
+/// # use vulkano::command_buffer::PrimaryAutoCommandBuffer;
+/// # use vulkano::device::Queue;
+/// # use vulkano::sync::GpuFuture;
+/// # let graphics_queue: Arc<Queue> = return;
+/// # let compute_queue: Arc<Queue> = return;
+/// # let graphics_future: Box<dyn GpuFuture> = return;
+/// # let compute_command_buffer: PrimaryAutoCommandBuffer = return;
+/// # let buffer: Arc<CpuAccessibleBuffer<[u32]>> = return;
+/// use vulkano::sync::AccessFlags;
+/// use vulkano::sync::HandoffScope;
+/// use vulkano::sync::PipelineStages;
+/// use vulkano::sync::QueueHandoff;
+///
+/// // The buffer was written by a vertex shader on the graphics queue, and is read by a compute
+/// // shader on the compute queue.
+/// let handoff = QueueHandoff::buffer(
+///     graphics_future,
+///     buffer.clone(),
+///     compute_queue.clone(),
+///     HandoffScope {
+///         source_stages: PipelineStages {
+///             vertex_shader: true,
+///             ..PipelineStages::none()
+///         },
+///         source_access: AccessFlags {
+///             shader_write: true,
+///             ..AccessFlags::none()
+///         },
+///         destination_stages: PipelineStages {
+///             compute_shader: true,
+///             ..PipelineStages::none()
+///         },
+///         destination_access: AccessFlags {
+///             shader_read: true,
+///             ..AccessFlags::none()
+///         },
+///         exclusive_sharing: true,
+///     },
+/// )
+/// .unwrap();
+///
+/// let compute_future = handoff.then_execute(compute_command_buffer).unwrap();
+/// ```
+pub struct QueueHandoff<R> {
+    resource: R,
+    queue: Arc<Queue>,
+    future: Box<dyn GpuFuture>,
+}
+
+/// How a resource is used before and after a `QueueHandoff`.
+#[derive(Debug, Copy, Clone)]
+pub struct HandoffScope {
+    /// The stages that use the resource on the source queue.
+    pub source_stages: PipelineStages,
+    /// The memory accesses of `source_stages`.
+    pub source_access: AccessFlags,
+    /// The stages that use the resource on the destination queue.
+    pub destination_stages: PipelineStages,
+    /// The memory accesses of `destination_stages`.
+    pub destination_access: AccessFlags,
+    /// Must be true if the resource was created with `Sharing::Exclusive`, in which case its
+    /// ownership is transferred between queue families. Must be false if it was created with
+    /// `Sharing::Concurrent`.
+    pub exclusive_sharing: bool,
+}
+
+impl Default for HandoffScope {
+    /// Returns a scope that synchronizes all the commands and memory accesses, for a resource
+    /// created with `Sharing::Exclusive`.
+    #[inline]
+    fn default() -> HandoffScope {
+        HandoffScope {
+            source_stages: PipelineStages {
+                all_commands: true,
+                ..PipelineStages::none()
+            },
+            source_access: AccessFlags::all(),
+            destination_stages: PipelineStages {
+                all_commands: true,
+                ..PipelineStages::none()
+            },
+            destination_access: AccessFlags::all(),
+            exclusive_sharing: true,
+        }
+    }
+}
+
+impl<B> QueueHandoff<B>
+where
+    B: BufferAccess + Clone + Send + Sync + 'static,
+{
+    /// Hands off `buffer`, which is used by `future`, to `queue`.
+    ///
+    /// `future` is flushed. The barriers of an ownership transfer, if any, cover the whole
+    /// buffer.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `future` isn't tied to a queue, for example if it is a `NowFuture`.
+    /// - Panics if `future`, `buffer` and `queue` don't belong to the same device.
+    ///
+    pub fn buffer<F>(
+        future: F,
+        buffer: B,
+        queue: Arc<Queue>,
+        scope: HandoffScope,
+    ) -> Result<QueueHandoff<B>, QueueHandoffError>
+    where
+        F: GpuFuture + 'static,
+    {
+        assert_eq!(
+            buffer.inner().buffer.device().internal_object(),
+            future.device().internal_object()
+        );
+
+        let source_family = future
+            .queue()
+            .expect("Tried to hand off a resource from a future that isn't tied to a queue")
+            .family()
+            .id();
+        let barriers = ownership_transfer_barriers(source_family, queue.family().id(), &scope);
+        let barriers = barriers.map(|barriers| {
+            barriers.map(|b| {
+                let mut barrier = BarrierBuilder::new();
+                barrier.add_buffer(
+                    buffer.clone(),
+                    BufferMemoryBarrier {
+                        source_stages: b.source_stages,
+                        source_access: b.source_access,
+                        destination_stages: b.destination_stages,
+                        destination_access: b.destination_access,
+                        range: None,
+                        queue_family_transfer: Some(b.queue_families),
+                    },
+                );
+                barrier
+            })
+        });
+
+        let future = hand_off(future, &queue, barriers)?;
+
+        Ok(QueueHandoff {
+            resource: buffer,
+            queue,
+            future,
+        })
+    }
+}
+
+impl<I> QueueHandoff<I>
+where
+    I: ImageAccess + Clone + Send + Sync + 'static,
+{
+    /// Hands off `image`, which is used by `future`, to `queue`.
+    ///
+    /// `future` is flushed. The barriers of an ownership transfer, if any, cover the whole image
+    /// and keep it in the layout returned by `initial_layout_requirement`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `future` isn't tied to a queue, for example if it is a `NowFuture`.
+    /// - Panics if `future`, `image` and `queue` don't belong to the same device.
+    ///
+    pub fn image<F>(
+        future: F,
+        image: I,
+        queue: Arc<Queue>,
+        scope: HandoffScope,
+    ) -> Result<QueueHandoff<I>, QueueHandoffError>
+    where
+        F: GpuFuture + 'static,
+    {
+        assert_eq!(
+            image.inner().image.device().internal_object(),
+            future.device().internal_object()
+        );
+
+        let layout = image.initial_layout_requirement();
+        let source_family = future
+            .queue()
+            .expect("Tried to hand off a resource from a future that isn't tied to a queue")
+            .family()
+            .id();
+        let barriers = ownership_transfer_barriers(source_family, queue.family().id(), &scope);
+        let barriers = barriers.map(|barriers| {
+            barriers.map(|b| {
+                let mut barrier = BarrierBuilder::new();
+                barrier.add_image(
+                    image.clone(),
+                    ImageMemoryBarrier {
+                        source_stages: b.source_stages,
+                        source_access: b.source_access,
+                        destination_stages: b.destination_stages,
+                        destination_access: b.destination_access,
+                        old_layout: layout,
+                        new_layout: layout,
+                        mipmap_levels: None,
+                        array_layers: None,
+                        queue_family_transfer: Some(b.queue_families),
+                    },
+                );
+                barrier
+            })
+        });
+
+        let future = hand_off(future, &queue, barriers)?;
+
+        Ok(QueueHandoff {
+            resource: image,
+            queue,
+            future,
+        })
+    }
+}
+
+impl<R> QueueHandoff<R> {
+    /// Returns the resource that was handed off.
+    #[inline]
+    pub fn resource(&self) -> &R {
+        &self.resource
+    }
+
+    /// Returns the queue the resource can be used on.
+    #[inline]
+    pub fn queue(&self) -> &Arc<Queue> {
+        &self.queue
+    }
+
+    /// Executes a command buffer that uses the resource on its new queue.
+    #[inline]
+    pub fn then_execute<Cb>(
+        self,
+        command_buffer: Cb,
+    ) -> Result<CommandBufferExecFuture<Box<dyn GpuFuture>, Cb>, CommandBufferExecError>
+    where
+        Cb: PrimaryCommandBuffer + 'static,
+    {
+        self.future.then_execute(self.queue, command_buffer)
+    }
+
+    /// Returns the resource, its new queue, and the future after which it can be used on that
+    /// queue.
+    #[inline]
+    pub fn into_parts(self) -> (R, Arc<Queue>, Box<dyn GpuFuture>) {
+        (self.resource, self.queue, self.future)
+    }
+}
+
+impl<R> fmt::Debug for QueueHandoff<R>
+where
+    R: fmt::Debug,
+{
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("QueueHandoff")
+            .field("resource", &self.resource)
+            .field("queue", &self.queue)
+            .finish()
+    }
+}
+
+// One of the two barriers of an ownership transfer.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct TransferBarrier {
+    pub source_stages: PipelineStages,
+    pub source_access: AccessFlags,
+    pub destination_stages: PipelineStages,
+    pub destination_access: AccessFlags,
+    pub queue_families: (u32, u32),
+}
+
+// The release barrier, recorded on the source queue, and the acquire barrier, recorded on the
+// destination queue.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct TransferBarriers<T> {
+    pub release: T,
+    pub acquire: T,
+}
+
+impl<T> TransferBarriers<T> {
+    fn map<U, F>(self, mut f: F) -> TransferBarriers<U>
+    where
+        F: FnMut(T) -> U,
+    {
+        TransferBarriers {
+            release: f(self.release),
+            acquire: f(self.acquire),
+        }
+    }
+}
+
+// Returns the barriers to use, or `None` if the ownership of the resource doesn't need to be
+// transferred.
+pub(crate) fn ownership_transfer_barriers(
+    source_family: u32,
+    destination_family: u32,
+    scope: &HandoffScope,
+) -> Option<TransferBarriers<TransferBarrier>> {
+    if !scope.exclusive_sharing || source_family == destination_family {
+        return None;
+    }
+
+    // The destination scope of the release and the source scope of the acquire are ignored.
+    Some(TransferBarriers {
+        release: TransferBarrier {
+            source_stages: scope.source_stages,
+            source_access: scope.source_access,
+            destination_stages: PipelineStages {
+                bottom_of_pipe: true,
+                ..PipelineStages::none()
+            },
+            destination_access: AccessFlags::none(),
+            queue_families: (source_family, destination_family),
+        },
+        acquire: TransferBarrier {
+            source_stages: PipelineStages {
+                top_of_pipe: true,
+                ..PipelineStages::none()
+            },
+            source_access: AccessFlags::none(),
+            destination_stages: scope.destination_stages,
+            destination_access: scope.destination_access,
+            queue_families: (source_family, destination_family),
+        },
+    })
+}
+
+// Records the release barrier on the queue of `future`, signals a semaphore, and records the
+// acquire barrier on `queue`.
+fn hand_off<F>(
+    future: F,
+    queue: &Arc<Queue>,
+    barriers: Option<TransferBarriers<BarrierBuilder>>,
+) -> Result<Box<dyn GpuFuture>, QueueHandoffError>
+where
+    F: GpuFuture + 'static,
+{
+    assert_eq!(
+        queue.device().internal_object(),
+        future.device().internal_object()
+    );
+
+    let device = future.device().clone();
+    let source_queue = future
+        .queue()
+        .expect("Tried to hand off a resource from a future that isn't tied to a queue");
+
+    let (release, acquire) = match barriers {
+        Some(barriers) => (Some(barriers.release), Some(barriers.acquire)),
+        None => (None, None),
+    };
+
+    let mut future: Box<dyn GpuFuture> = Box::new(future);
+
+    if let Some(release) = release {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            source_queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        builder.pipeline_barrier(release)?;
+        future = Box::new(future.then_execute(source_queue.clone(), builder.build()?)?);
+    }
+
+    if !source_queue.is_same(queue) {
+        future = Box::new(future.then_signal_semaphore_and_flush()?);
+    } else {
+        future.flush()?;
+    }
+
+    if let Some(acquire) = acquire {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device,
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        builder.pipeline_barrier(acquire)?;
+        future = Box::new(future.then_execute(queue.clone(), builder.build()?)?);
+    }
+
+    Ok(future)
+}
+
+/// Error that can happen when handing off a resource to another queue.
+#[derive(Debug, Clone)]
+pub enum QueueHandoffError {
+    /// Not enough memory to create the command buffers of the ownership transfer.
+    OomError(OomError),
+    /// Error while recording the barriers of the ownership transfer.
+    PipelineBarrierError(PipelineBarrierError),
+    /// Error while building the command buffers of the ownership transfer.
+    BuildError(BuildError),
+    /// Error while executing the command buffers of the ownership transfer.
+    CommandBufferExecError(CommandBufferExecError),
+    /// Error while flushing the source queue.
+    FlushError(FlushError),
+}
+
+impl error::Error for QueueHandoffError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            QueueHandoffError::OomError(ref err) => Some(err),
+            QueueHandoffError::PipelineBarrierError(ref err) => Some(err),
+            QueueHandoffError::BuildError(ref err) => Some(err),
+            QueueHandoffError::CommandBufferExecError(ref err) => Some(err),
+            QueueHandoffError::FlushError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for QueueHandoffError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                QueueHandoffError::OomError(_) => {
+                    "not enough memory to create the command buffers of the ownership transfer"
+                }
+                QueueHandoffError::PipelineBarrierError(_) => {
+                    "error while recording the barriers of the ownership transfer"
+                }
+                QueueHandoffError::BuildError(_) => {
+                    "error while building the command buffers of the ownership transfer"
+                }
+                QueueHandoffError::CommandBufferExecError(_) => {
+                    "error while executing the command buffers of the ownership transfer"
+                }
+                QueueHandoffError::FlushError(_) => "error while flushing the source queue",
+            }
+        )
+    }
+}
+
+impl From<OomError> for QueueHandoffError {
+    #[inline]
+    fn from(err: OomError) -> QueueHandoffError {
+        QueueHandoffError::OomError(err)
+    }
+}
+
+impl From<PipelineBarrierError> for QueueHandoffError {
+    #[inline]
+    fn from(err: PipelineBarrierError) -> QueueHandoffError {
+        QueueHandoffError::PipelineBarrierError(err)
+    }
+}
+
+impl From<BuildError> for QueueHandoffError {
+    #[inline]
+    fn from(err: BuildError) -> QueueHandoffError {
+        QueueHandoffError::BuildError(err)
+    }
+}
+
+impl From<CommandBufferExecError> for QueueHandoffError {
+    #[inline]
+    fn from(err: CommandBufferExecError) -> QueueHandoffError {
+        QueueHandoffError::CommandBufferExecError(err)
+    }
+}
+
+impl From<FlushError> for QueueHandoffError {
+    #[inline]
+    fn from(err: FlushError) -> QueueHandoffError {
+        QueueHandoffError::FlushError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::command_buffer::AutoCommandBufferBuilder;
+    use crate::command_buffer::CommandBufferUsage;
+    use crate::sync::now;
+    use crate::sync::ownership_transfer_barriers;
+    use crate::sync::GpuFuture;
+    use crate::sync::HandoffScope;
+    use crate::sync::QueueHandoff;
+    use std::sync::Arc;
+
+    #[test]
+    fn transfer_barriers() {
+        let exclusive = HandoffScope::default();
+        let concurrent = HandoffScope {
+            exclusive_sharing: false,
+            ..HandoffScope::default()
+        };
+        assert!(ownership_transfer_barriers(0, 0, &exclusive).is_none());
+        assert!(ownership_transfer_barriers(0, 1, &concurrent).is_none());
+
+        let barriers = ownership_transfer_barriers(0, 1, &exclusive).unwrap();
+        assert_eq!(barriers.release.queue_families, (0, 1));
+        assert_eq!(barriers.acquire.queue_families, (0, 1));
+        assert!(barriers.release.destination_stages.bottom_of_pipe);
+        assert!(barriers.acquire.source_stages.top_of_pipe);
+    }
+
+    #[test]
+    fn same_queue() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffer =
+            CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                .unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder.fill_buffer(buffer.clone(), 1).unwrap();
+        let future = now(device.clone())
+            .then_execute(queue.clone(), builder.build().unwrap())
+            .unwrap();
+
+        let handoff =
+            QueueHandoff::buffer(future, buffer, queue.clone(), HandoffScope::default()).unwrap();
+        assert!(handoff.queue().is_same(&queue));
+
+        let (_, _, future) = handoff.into_parts();
+        future
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+    }
+
+    #[test]
+    fn ownership_transfer() {
+        use crate::device::physical::PhysicalDevice;
+        use crate::device::Device;
+        use crate::device::DeviceExtensions;
+        use crate::device::Features;
+        use crate::device::Queue;
+
+        let instance = instance!();
+
+        let physical = match PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return,
+        };
+
+        // The transfer needs two queue families that can both fill buffers.
+        let families: Vec<_> = physical
+            .queue_families()
+            .filter(|q| q.supports_graphics() || q.supports_compute())
+            .take(2)
+            .collect();
+        if families.len() < 2 {
+            return;
+        }
+
+        let (device, queues) = match Device::new(
+            physical,
+            &Features::none(),
+            &DeviceExtensions::none(),
+            families.iter().map(|&family| (family, 0.5)),
+        ) {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+        let queues: Vec<_> = queues.collect();
+
+        let buffer =
+            CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                .unwrap();
+
+        let fill = |queue: &Arc<Queue>, value| {
+            let mut builder = AutoCommandBufferBuilder::primary(
+                device.clone(),
+                queue.family(),
+                CommandBufferUsage::OneTimeSubmit,
+            )
+            .unwrap();
+            builder.fill_buffer(buffer.clone(), value).unwrap();
+            builder.build().unwrap()
+        };
+
+        let future = now(device.clone())
+            .then_execute(queues[0].clone(), fill(&queues[0], 1))
+            .unwrap();
+
+        let handoff = QueueHandoff::buffer(
+            future,
+            buffer.clone(),
+            queues[1].clone(),
+            HandoffScope::default(),
+        )
+        .unwrap();
+        assert!(handoff.queue().is_same(&queues[1]));
+
+        handoff
+            .then_execute(fill(&queues[1], 2))
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        assert_eq!(*buffer.read().unwrap(), 2);
+    }
+}
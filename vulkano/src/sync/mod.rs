@@ -106,10 +106,15 @@
 use crate::device::Queue;
 use std::sync::Arc;
 
+pub use self::deletion_queue::DeletionQueue;
 pub use self::event::Event;
 pub use self::fence::Fence;
 pub use self::fence::FenceWaitError;
+pub use self::frames::FrameContext;
+pub use self::frames::Frames;
 pub use self::future::now;
+pub use self::future::wait_semaphore;
+pub use self::future::wait_timeline_semaphore;
 pub use self::future::AccessCheckError;
 pub use self::future::AccessError;
 pub use self::future::FenceSignalFuture;
@@ -118,18 +123,40 @@ pub use self::future::GpuFuture;
 pub use self::future::JoinFuture;
 pub use self::future::NowFuture;
 pub use self::future::SemaphoreSignalFuture;
+pub use self::future::SemaphoreWaitFuture;
+pub use self::future::SubmissionBatch;
+pub use self::future::SubmitBuilder;
+pub use self::future::TimelineSemaphoreSignalFuture;
+pub use self::future::TimelineSemaphoreWaitFuture;
+pub use self::handoff::HandoffScope;
+pub use self::handoff::QueueHandoff;
+pub use self::handoff::QueueHandoffError;
 pub use self::pipeline::AccessFlags;
 pub use self::pipeline::PipelineMemoryAccess;
 pub use self::pipeline::PipelineStage;
 pub use self::pipeline::PipelineStages;
+pub use self::recycler::Recycler;
 pub use self::semaphore::ExternalSemaphoreHandleType;
 pub use self::semaphore::Semaphore;
 pub use self::semaphore::SemaphoreError;
+pub use self::semaphore::SemaphoreWaitError;
+pub use self::semaphore::TimelineSemaphore;
 
+pub(crate) use self::deletion_queue::SignalPoint;
+pub(crate) use self::frame_tracker::FrameTracker;
+pub(crate) use self::future::semaphore_flush_error;
+pub(crate) use self::handoff::ownership_transfer_barriers;
+pub(crate) use self::handoff::TransferBarrier;
+
+mod deletion_queue;
 mod event;
 mod fence;
+mod frame_tracker;
+mod frames;
 mod future;
+mod handoff;
 mod pipeline;
+mod recycler;
 pub(crate) mod semaphore;
 
 /// Declares in which queue(s) a resource can be used.
@@ -7,6 +7,7 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::convert::TryFrom;
 use std::ops;
 
 macro_rules! pipeline_stages {
@@ -70,6 +71,20 @@ macro_rules! pipeline_stages {
             )+
         }
 
+        impl TryFrom<ash::vk::PipelineStageFlags> for PipelineStage {
+            type Error = ();
+
+            #[inline]
+            fn try_from(val: ash::vk::PipelineStageFlags) -> Result<Self, Self::Error> {
+                $(
+                    if val == $val {
+                        return Ok(Self::$var);
+                    }
+                )+
+                Err(())
+            }
+        }
+
         impl PipelineStage {
             #[inline]
             pub fn required_queue_flags(&self) -> ash::vk::QueueFlags {
@@ -83,6 +98,18 @@ macro_rules! pipeline_stages {
     );
 }
 
+impl PipelineStages {
+    /// Returns true if any of the framebuffer-space stages is set. These are the fragment shader,
+    /// early and late fragment tests, and color attachment output stages.
+    #[inline]
+    pub fn has_framebuffer_space_stage(&self) -> bool {
+        self.fragment_shader
+            || self.early_fragment_tests
+            || self.late_fragment_tests
+            || self.color_attachment_output
+    }
+}
+
 impl From<PipelineStage> for ash::vk::PipelineStageFlags {
     #[inline]
     fn from(val: PipelineStage) -> Self {
@@ -0,0 +1,238 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::sync::deletion_queue::SignalPoint;
+use crate::sync::FenceSignalFuture;
+use crate::sync::GpuFuture;
+use crate::sync::TimelineSemaphore;
+use crate::OomError;
+use crate::VulkanObject;
+use std::fmt;
+use std::sync::Arc;
+
+/// Lends out reusable objects, and gets them back once the GPU has finished using them.
+///
+/// This is the counterpart of `DeletionQueue` for objects that are expensive to create and can
+/// be reused from one frame to the next, such as staging buffers, query pools or command pools.
+/// Instead of being destroyed once the fence or timeline value of their last submission is
+/// signaled, the objects are put back in a free list, and returned by the next calls to `take`.
+///
+/// The objects are given back as they were, so objects that need to be reset before being
+/// reused, such as query pools, must be reset by the caller.
+///
+/// A `Recycler<T>` can be sent to another thread if `T` can.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use vulkano::buffer::CpuAccessibleBuffer;
+/// # use vulkano::command_buffer::PrimaryAutoCommandBuffer;
+/// # use vulkano::device::Device;
+/// # use vulkano::device::Queue;
+/// # let device: Arc<Device> = return;
+/// # let queue: Arc<Queue> = return;
+/// # let command_buffer: PrimaryAutoCommandBuffer = return;
+/// # fn create_staging_buffer() -> Arc<CpuAccessibleBuffer<[u8]>> { unimplemented!() }
+/// use vulkano::sync;
+/// use vulkano::sync::GpuFuture;
+/// use vulkano::sync::Recycler;
+///
+/// let mut recycler = Recycler::new(device.clone());
+///
+/// // Reuse a staging buffer of a previous frame if possible.
+/// recycler.cleanup_finished().unwrap();
+/// let staging_buffer = recycler.take().unwrap_or_else(|| create_staging_buffer());
+///
+/// let future = Arc::new(
+///     sync::now(device.clone())
+///         .then_execute(queue.clone(), command_buffer)
+///         .unwrap()
+///         .then_signal_fence_and_flush()
+///         .unwrap(),
+/// );
+///
+/// // The buffer can be taken again once the fence is signaled.
+/// recycler.recycle_after_fence(staging_buffer, &future);
+/// ```
+pub struct Recycler<T> {
+    device: Arc<Device>,
+    free: Vec<T>,
+    in_flight: Vec<(SignalPoint, Vec<T>)>,
+}
+
+impl<T> Recycler<T> {
+    /// Builds a new empty `Recycler`.
+    #[inline]
+    pub fn new(device: Arc<Device>) -> Recycler<T> {
+        Recycler {
+            device,
+            free: Vec::new(),
+            in_flight: Vec::new(),
+        }
+    }
+
+    /// Takes an object from the free list, or returns `None` if it is empty.
+    ///
+    /// The free list is only refilled by `recycle` and `cleanup_finished`.
+    #[inline]
+    pub fn take(&mut self) -> Option<T> {
+        self.free.pop()
+    }
+
+    /// Puts `object` in the free list right away.
+    ///
+    /// This is meant for objects that were taken but ended up not being used by the GPU.
+    #[inline]
+    pub fn recycle(&mut self, object: T) {
+        self.free.push(object);
+    }
+
+    /// Puts `object` back in the free list once `fence` is signaled.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `fence` doesn't belong to the same device.
+    ///
+    pub fn recycle_after_fence<F>(&mut self, object: T, fence: &Arc<FenceSignalFuture<F>>)
+    where
+        F: GpuFuture + Send + 'static,
+    {
+        assert_eq!(
+            fence.device().internal_object(),
+            self.device.internal_object()
+        );
+
+        self.push(SignalPoint::Fence(fence.clone()), object);
+    }
+
+    /// Puts `object` back in the free list once `semaphore` reaches `value`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `semaphore` doesn't belong to the same device.
+    ///
+    pub fn recycle_after_timeline(
+        &mut self,
+        object: T,
+        semaphore: &Arc<TimelineSemaphore>,
+        value: u64,
+    ) {
+        assert_eq!(
+            semaphore.device().internal_object(),
+            self.device.internal_object()
+        );
+
+        self.push(SignalPoint::Timeline(semaphore.clone(), value), object);
+    }
+
+    fn push(&mut self, point: SignalPoint, object: T) {
+        if let Some((last_point, objects)) = self.in_flight.last_mut() {
+            if last_point.is_same(&point) {
+                objects.push(object);
+                return;
+            }
+        }
+
+        self.in_flight.push((point, vec![object]));
+    }
+
+    /// Moves all the objects whose fence or timeline value has been signaled to the free list.
+    ///
+    /// This function never blocks.
+    pub fn cleanup_finished(&mut self) -> Result<(), OomError> {
+        let mut index = 0;
+
+        while index < self.in_flight.len() {
+            if self.in_flight[index].0.is_signaled()? {
+                let (_, objects) = self.in_flight.remove(index);
+                self.free.extend(objects);
+            } else {
+                index += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of objects in the free list.
+    #[inline]
+    pub fn free_len(&self) -> usize {
+        self.free.len()
+    }
+
+    /// Returns the number of objects waiting for the GPU before going back to the free list.
+    #[inline]
+    pub fn in_flight_len(&self) -> usize {
+        self.in_flight
+            .iter()
+            .map(|(_, objects)| objects.len())
+            .sum()
+    }
+}
+
+unsafe impl<T> DeviceOwned for Recycler<T> {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+impl<T> fmt::Debug for Recycler<T> {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("Recycler")
+            .field("free", &self.free_len())
+            .field("in_flight", &self.in_flight_len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sync::Recycler;
+    use crate::sync::TimelineSemaphore;
+    use std::sync::Arc;
+
+    #[test]
+    fn send_sync() {
+        fn is_send_sync<T: Send + Sync>() {}
+        is_send_sync::<Recycler<Arc<()>>>();
+    }
+
+    #[test]
+    fn recycle_after_timeline() {
+        let (device, _) = gfx_dev_and_queue!(timeline_semaphore);
+
+        let mut recycler = Recycler::new(device.clone());
+        let semaphore = Arc::new(TimelineSemaphore::new(device, 0).unwrap());
+
+        recycler.recycle(1);
+        assert_eq!(recycler.take(), Some(1));
+        assert_eq!(recycler.take(), None);
+
+        recycler.recycle_after_timeline(2, &semaphore, 1);
+        recycler.recycle_after_timeline(3, &semaphore, 2);
+        recycler.cleanup_finished().unwrap();
+        assert_eq!(recycler.take(), None);
+        assert_eq!(recycler.in_flight_len(), 2);
+
+        semaphore.signal(1).unwrap();
+        recycler.cleanup_finished().unwrap();
+        assert_eq!(recycler.take(), Some(2));
+        assert_eq!(recycler.take(), None);
+
+        semaphore.signal(2).unwrap();
+        recycler.cleanup_finished().unwrap();
+        assert_eq!(recycler.take(), Some(3));
+        assert_eq!(recycler.in_flight_len(), 0);
+    }
+}
@@ -10,6 +10,9 @@
 pub use self::external_semaphore_handle_type::ExternalSemaphoreHandleType;
 pub use self::semaphore::Semaphore;
 pub use self::semaphore::SemaphoreError;
+pub use self::timeline::SemaphoreWaitError;
+pub use self::timeline::TimelineSemaphore;
 
 mod external_semaphore_handle_type;
 mod semaphore;
+mod timeline;
@@ -19,7 +19,8 @@ use std::fmt;
 use std::fs::File;
 use std::mem::MaybeUninit;
 #[cfg(target_os = "linux")]
-use std::os::unix::io::FromRawFd;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd};
+use std::panic::Location;
 use std::ptr;
 use std::sync::Arc;
 
@@ -40,7 +41,6 @@ where
 }
 
 // TODO: Add support for VkExportSemaphoreWin32HandleInfoKHR
-// TODO: Add suport for importable semaphores
 pub struct SemaphoreBuilder<D = Arc<Device>>
 where
     D: SafeDeref<Target = Device>,
@@ -84,12 +84,15 @@ where
         };
 
         self.export_info = Some(export_info);
-        self.create.p_next = unsafe { std::mem::transmute(&export_info) };
-
         self
     }
 
-    pub fn build(self) -> Result<Semaphore<D>, SemaphoreError> {
+    #[track_caller]
+    pub fn build(mut self) -> Result<Semaphore<D>, SemaphoreError> {
+        if let Some(ref export_info) = self.export_info {
+            self.create.p_next = export_info as *const _ as *const _;
+        }
+
         if self.export_info.is_some()
             && !self
                 .device
@@ -113,11 +116,15 @@ where
                 output.assume_init()
             };
 
-            Ok(Semaphore {
+            let semaphore = Semaphore {
                 device: self.device,
                 semaphore,
                 must_put_in_pool: self.must_put_in_pool,
-            })
+            };
+            semaphore
+                .device
+                .auto_debug_name(&semaphore, Location::caller());
+            Ok(semaphore)
         }
     }
 }
@@ -133,7 +140,7 @@ where
     /// For most applications, using the pool should be preferred,
     /// in order to avoid creating new semaphores every frame.
     pub fn from_pool(device: D) -> Result<Semaphore<D>, SemaphoreError> {
-        let maybe_raw_sem = device.semaphore_pool().lock().unwrap().pop();
+        let maybe_raw_sem = device.semaphore_pool().pop();
         match maybe_raw_sem {
             Some(raw_sem) => Ok(Semaphore {
                 device,
@@ -149,6 +156,7 @@ where
 
     /// Builds a new semaphore.
     #[inline]
+    #[track_caller]
     pub fn alloc(device: D) -> Result<Semaphore<D>, SemaphoreError> {
         SemaphoreBuilder::new(device).build()
     }
@@ -156,12 +164,43 @@ where
     /// Same as `alloc`, but allows exportable opaque file descriptor on Linux
     #[inline]
     #[cfg(target_os = "linux")]
+    #[track_caller]
     pub fn alloc_with_exportable_fd(device: D) -> Result<Semaphore<D>, SemaphoreError> {
         SemaphoreBuilder::new(device)
             .export_info(ExternalSemaphoreHandleType::posix())
             .build()
     }
 
+    /// Same as `alloc`, but allows exporting its signal operations as sync files on Linux.
+    #[inline]
+    #[cfg(target_os = "linux")]
+    #[track_caller]
+    pub fn alloc_with_exportable_sync_fd(device: D) -> Result<Semaphore<D>, SemaphoreError> {
+        SemaphoreBuilder::new(device)
+            .export_info(ExternalSemaphoreHandleType {
+                sync_fd: true,
+                ..ExternalSemaphoreHandleType::none()
+            })
+            .build()
+    }
+
+    /// Builds a `Semaphore` from a semaphore that was created outside of vulkano.
+    ///
+    /// The `Semaphore` takes ownership of the handle, and destroys it when it is dropped.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid binary semaphore that was created from `device`.
+    /// - The semaphore must not be destroyed by anything else than the returned `Semaphore`.
+    #[inline]
+    pub unsafe fn from_handle(device: D, handle: ash::vk::Semaphore) -> Semaphore<D> {
+        Semaphore {
+            semaphore: handle,
+            device,
+            must_put_in_pool: false,
+        }
+    }
+
     #[cfg(target_os = "linux")]
     pub fn export_opaque_fd(&self) -> Result<File, SemaphoreError> {
         let fns = self.device.fns();
@@ -187,6 +226,192 @@ where
         let file = unsafe { File::from_raw_fd(fd) };
         Ok(file)
     }
+
+    /// Exports the pending signal operation of the semaphore into a sync file, for example to
+    /// pass it to a Linux driver or to another API.
+    ///
+    /// The semaphore must have been built with `sync_fd` in its export info. Exporting it also
+    /// waits on the semaphore, which becomes unsignaled. The file is owned by the caller.
+    ///
+    /// # Safety
+    ///
+    /// - A signal operation of the semaphore must have been submitted, and no wait operation
+    ///   must have been submitted since then.
+    #[cfg(target_os = "linux")]
+    pub unsafe fn export_sync_fd(&self) -> Result<File, SemaphoreError> {
+        if !self.device.enabled_extensions().khr_external_semaphore_fd {
+            return Err(SemaphoreError::MissingExtension(
+                "khr_external_semaphore_fd",
+            ));
+        }
+
+        let fns = self.device.fns();
+        let info = ash::vk::SemaphoreGetFdInfoKHR {
+            semaphore: self.semaphore,
+            handle_type: ash::vk::ExternalSemaphoreHandleTypeFlags::SYNC_FD,
+            ..Default::default()
+        };
+
+        let mut output = MaybeUninit::uninit();
+        check_errors(fns.khr_external_semaphore_fd.get_semaphore_fd_khr(
+            self.device.internal_object(),
+            &info,
+            output.as_mut_ptr(),
+        ))?;
+
+        Ok(File::from_raw_fd(output.assume_init()))
+    }
+
+    /// Imports a sync file into the semaphore, for example a fence of a Linux driver attached
+    /// to a dma-buf.
+    ///
+    /// The import is temporary: the semaphore is signaled when the sync file is, and its
+    /// previous payload is restored once a submission has waited on it. The semaphore takes the
+    /// ownership of the file.
+    ///
+    /// # Safety
+    ///
+    /// - `fd` must be a sync file.
+    /// - The semaphore must not be in use by a pending queue operation.
+    #[cfg(target_os = "linux")]
+    pub unsafe fn import_sync_fd(&self, fd: File) -> Result<(), SemaphoreError> {
+        if !self.device.enabled_extensions().khr_external_semaphore_fd {
+            return Err(SemaphoreError::MissingExtension(
+                "khr_external_semaphore_fd",
+            ));
+        }
+
+        let fns = self.device.fns();
+        let info = ash::vk::ImportSemaphoreFdInfoKHR {
+            semaphore: self.semaphore,
+            flags: ash::vk::SemaphoreImportFlags::TEMPORARY,
+            handle_type: ash::vk::ExternalSemaphoreHandleTypeFlags::SYNC_FD,
+            fd: fd.as_raw_fd(),
+            ..Default::default()
+        };
+
+        check_errors(
+            fns.khr_external_semaphore_fd
+                .import_semaphore_fd_khr(self.device.internal_object(), &info),
+        )?;
+
+        // The implementation owns the file descriptor once the import has succeeded. On error,
+        // `fd` is dropped and the file closed.
+        let _ = fd.into_raw_fd();
+        Ok(())
+    }
+
+    /// Exports the semaphore into a Windows handle, for example to open it in Direct3D 12 with
+    /// `ID3D12Device::OpenSharedHandle` if `handle_type` is `d3d12_fence`.
+    ///
+    /// The semaphore must have been built with `handle_type` in its export info. An NT handle is
+    /// owned by the caller, who must close it with `CloseHandle`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `handle_type` doesn't contain exactly one Windows handle type.
+    pub fn export_win32_handle(
+        &self,
+        handle_type: ExternalSemaphoreHandleType,
+    ) -> Result<ash::vk::HANDLE, SemaphoreError> {
+        let handle_type = win32_handle_type(handle_type);
+
+        if !self
+            .device
+            .enabled_extensions()
+            .khr_external_semaphore_win32
+        {
+            return Err(SemaphoreError::MissingExtension(
+                "khr_external_semaphore_win32",
+            ));
+        }
+
+        let handle = unsafe {
+            let fns = self.device.fns();
+            let info = ash::vk::SemaphoreGetWin32HandleInfoKHR {
+                semaphore: self.semaphore,
+                handle_type,
+                ..Default::default()
+            };
+
+            let mut output = MaybeUninit::uninit();
+            check_errors(
+                fns.khr_external_semaphore_win32
+                    .get_semaphore_win32_handle_khr(
+                        self.device.internal_object(),
+                        &info,
+                        output.as_mut_ptr(),
+                    ),
+            )?;
+            output.assume_init()
+        };
+
+        Ok(handle)
+    }
+
+    /// Imports the payload of a Windows handle into the semaphore, for example a Direct3D 12
+    /// fence shared with `ID3D12Device::CreateSharedHandle` if `handle_type` is `d3d12_fence`.
+    ///
+    /// The semaphore then refers to the same payload as the object of the other API.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid handle of type `handle_type`.
+    /// - The semaphore must not be in use by a pending queue operation.
+    /// - If `handle_type` is an NT handle type, the caller keeps the ownership of the handle.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `handle_type` doesn't contain exactly one Windows handle type.
+    pub unsafe fn import_win32_handle(
+        &self,
+        handle_type: ExternalSemaphoreHandleType,
+        handle: ash::vk::HANDLE,
+    ) -> Result<(), SemaphoreError> {
+        import_win32_handle(&self.device, self.semaphore, handle_type, handle)
+    }
+}
+
+// Returns the Vulkan bits of `handle_type`, which must be a single Windows handle type.
+fn win32_handle_type(
+    handle_type: ExternalSemaphoreHandleType,
+) -> ash::vk::ExternalSemaphoreHandleTypeFlags {
+    let bits = ash::vk::ExternalSemaphoreHandleTypeFlags::from(handle_type);
+    assert!(
+        bits == ash::vk::ExternalSemaphoreHandleTypeFlags::OPAQUE_WIN32
+            || bits == ash::vk::ExternalSemaphoreHandleTypeFlags::OPAQUE_WIN32_KMT
+            || bits == ash::vk::ExternalSemaphoreHandleTypeFlags::D3D12_FENCE
+    );
+    bits
+}
+
+pub(super) unsafe fn import_win32_handle(
+    device: &Device,
+    semaphore: ash::vk::Semaphore,
+    handle_type: ExternalSemaphoreHandleType,
+    handle: ash::vk::HANDLE,
+) -> Result<(), SemaphoreError> {
+    let handle_type = win32_handle_type(handle_type);
+
+    if !device.enabled_extensions().khr_external_semaphore_win32 {
+        return Err(SemaphoreError::MissingExtension(
+            "khr_external_semaphore_win32",
+        ));
+    }
+
+    let fns = device.fns();
+    let info = ash::vk::ImportSemaphoreWin32HandleInfoKHR {
+        semaphore,
+        handle_type,
+        handle,
+        ..Default::default()
+    };
+
+    check_errors(
+        fns.khr_external_semaphore_win32
+            .import_semaphore_win32_handle_khr(device.internal_object(), &info),
+    )?;
+    Ok(())
 }
 
 unsafe impl DeviceOwned for Semaphore {
@@ -217,7 +442,7 @@ where
         unsafe {
             if self.must_put_in_pool {
                 let raw_sem = self.semaphore;
-                self.device.semaphore_pool().lock().unwrap().push(raw_sem);
+                self.device.semaphore_pool().push(raw_sem);
             } else {
                 let fns = self.device.fns();
                 fns.v1_0.destroy_semaphore(
@@ -236,6 +461,8 @@ pub enum SemaphoreError {
     OomError(OomError),
     /// An extensions is missing.
     MissingExtension(&'static str),
+    /// A feature is not enabled.
+    MissingFeature(&'static str),
 }
 
 impl fmt::Display for SemaphoreError {
@@ -245,6 +472,9 @@ impl fmt::Display for SemaphoreError {
             SemaphoreError::MissingExtension(s) => {
                 write!(fmt, "Missing the following extension: {}", s)
             }
+            SemaphoreError::MissingFeature(s) => {
+                write!(fmt, "Missing the following feature: {}", s)
+            }
         }
     }
 }
@@ -296,16 +526,16 @@ mod tests {
     fn semaphore_pool() {
         let (device, _) = gfx_dev_and_queue!();
 
-        assert_eq!(device.semaphore_pool().lock().unwrap().len(), 0);
+        assert_eq!(device.semaphore_pool().len(), 0);
         let sem1_internal_obj = {
             let sem = Semaphore::from_pool(device.clone()).unwrap();
-            assert_eq!(device.semaphore_pool().lock().unwrap().len(), 0);
+            assert_eq!(device.semaphore_pool().len(), 0);
             sem.internal_object()
         };
 
-        assert_eq!(device.semaphore_pool().lock().unwrap().len(), 1);
+        assert_eq!(device.semaphore_pool().len(), 1);
         let sem2 = Semaphore::from_pool(device.clone()).unwrap();
-        assert_eq!(device.semaphore_pool().lock().unwrap().len(), 0);
+        assert_eq!(device.semaphore_pool().len(), 0);
         assert_eq!(sem2.internal_object(), sem1_internal_obj);
     }
 
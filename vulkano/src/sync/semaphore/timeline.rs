@@ -0,0 +1,512 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::check_errors;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::sync::semaphore::semaphore;
+use crate::sync::semaphore::ExternalSemaphoreHandleType;
+use crate::sync::semaphore::SemaphoreError;
+use crate::Error;
+use crate::OomError;
+use crate::Success;
+use crate::Version;
+use crate::VulkanObject;
+use std::error;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::panic::Location;
+use std::ptr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A semaphore whose state is a 64-bit value that only ever increases.
+///
+/// Contrary to a binary `Semaphore`, waiting on a timeline semaphore doesn't reset it, so any
+/// number of submissions and CPU threads can wait for the same value. Submissions can wait for a
+/// value before the operation that signals it has been submitted. The CPU can also query the
+/// current value, wait for a value, or signal a value itself.
+///
+/// Using timeline semaphores requires the `timeline_semaphore` feature to be enabled on the
+/// device.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use vulkano::command_buffer::PrimaryAutoCommandBuffer;
+/// # use vulkano::device::Device;
+/// # use vulkano::device::Queue;
+/// # let device: Arc<Device> = return;
+/// # let queue: Arc<Queue> = return;
+/// # let command_buffer: PrimaryAutoCommandBuffer = return;
+/// use std::time::Duration;
+/// use vulkano::sync;
+/// use vulkano::sync::GpuFuture;
+/// use vulkano::sync::TimelineSemaphore;
+///
+/// let semaphore = Arc::new(TimelineSemaphore::new(device.clone(), 0).unwrap());
+///
+/// // Make the GPU signal the value 1 once the command buffer has been executed.
+/// let future = sync::now(device.clone())
+///     .then_execute(queue.clone(), command_buffer)
+///     .unwrap()
+///     .then_signal_timeline_semaphore(semaphore.clone(), 1);
+/// future.flush().unwrap();
+///
+/// // Wait on the CPU until the value is reached.
+/// semaphore.wait(1, Some(Duration::from_secs(1))).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct TimelineSemaphore {
+    semaphore: ash::vk::Semaphore,
+    device: Arc<Device>,
+}
+
+impl TimelineSemaphore {
+    /// Builds a new timeline semaphore, with the given initial value.
+    #[track_caller]
+    pub fn new(
+        device: Arc<Device>,
+        initial_value: u64,
+    ) -> Result<TimelineSemaphore, SemaphoreError> {
+        if !device.enabled_features().timeline_semaphore {
+            return Err(SemaphoreError::MissingFeature("timeline_semaphore"));
+        }
+
+        let semaphore = unsafe {
+            let type_create_info = ash::vk::SemaphoreTypeCreateInfo {
+                semaphore_type: ash::vk::SemaphoreType::TIMELINE,
+                initial_value,
+                ..Default::default()
+            };
+
+            let create_info = ash::vk::SemaphoreCreateInfo {
+                p_next: &type_create_info as *const _ as *const _,
+                ..Default::default()
+            };
+
+            let fns = device.fns();
+            let mut output = MaybeUninit::uninit();
+            check_errors(fns.v1_0.create_semaphore(
+                device.internal_object(),
+                &create_info,
+                ptr::null(),
+                output.as_mut_ptr(),
+            ))?;
+            output.assume_init()
+        };
+
+        let semaphore = TimelineSemaphore { semaphore, device };
+        semaphore
+            .device
+            .auto_debug_name(&semaphore, Location::caller());
+        Ok(semaphore)
+    }
+
+    /// Imports the payload of a Windows handle into the semaphore, for example a Direct3D 12
+    /// fence shared with `ID3D12Device::CreateSharedHandle` if `handle_type` is `d3d12_fence`.
+    ///
+    /// The value of the semaphore is then the value of the fence, and the submissions of both
+    /// APIs can wait for and signal the values of each other.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid handle of type `handle_type`.
+    /// - The semaphore must not be in use by a pending queue operation.
+    /// - If `handle_type` is an NT handle type, the caller keeps the ownership of the handle.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `handle_type` doesn't contain exactly one Windows handle type.
+    pub unsafe fn import_win32_handle(
+        &self,
+        handle_type: ExternalSemaphoreHandleType,
+        handle: ash::vk::HANDLE,
+    ) -> Result<(), SemaphoreError> {
+        semaphore::import_win32_handle(&self.device, self.semaphore, handle_type, handle)
+    }
+
+    /// Returns the current value of the semaphore.
+    pub fn value(&self) -> Result<u64, OomError> {
+        unsafe {
+            let fns = self.device.fns();
+            let mut output = MaybeUninit::uninit();
+
+            if self.device.api_version() >= Version::V1_2 {
+                check_errors(fns.v1_2.get_semaphore_counter_value(
+                    self.device.internal_object(),
+                    self.semaphore,
+                    output.as_mut_ptr(),
+                ))?;
+            } else {
+                check_errors(fns.khr_timeline_semaphore.get_semaphore_counter_value_khr(
+                    self.device.internal_object(),
+                    self.semaphore,
+                    output.as_mut_ptr(),
+                ))?;
+            }
+
+            Ok(output.assume_init())
+        }
+    }
+
+    /// Sets the value of the semaphore from the CPU.
+    ///
+    /// The value must also be lower than the values of the signal operations that are pending
+    /// on the GPU for this semaphore.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `value` isn't greater than the current value of the semaphore.
+    ///
+    pub fn signal(&self, value: u64) -> Result<(), OomError> {
+        // VUID-VkSemaphoreSignalInfo-value-03258
+        assert!(
+            value > self.value()?,
+            "the value must be greater than the current value of the semaphore"
+        );
+
+        unsafe {
+            let fns = self.device.fns();
+            let signal_info = ash::vk::SemaphoreSignalInfo {
+                semaphore: self.semaphore,
+                value,
+                ..Default::default()
+            };
+
+            if self.device.api_version() >= Version::V1_2 {
+                check_errors(
+                    fns.v1_2
+                        .signal_semaphore(self.device.internal_object(), &signal_info),
+                )?;
+            } else {
+                check_errors(
+                    fns.khr_timeline_semaphore
+                        .signal_semaphore_khr(self.device.internal_object(), &signal_info),
+                )?;
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Blocks the current thread until the value of the semaphore is greater than or equal to
+    /// `value`.
+    ///
+    /// If `timeout` is `None`, then the wait is infinite. Otherwise the thread will unblock after
+    /// the specified timeout has elapsed and an error will be returned.
+    #[inline]
+    pub fn wait(&self, value: u64, timeout: Option<Duration>) -> Result<(), SemaphoreWaitError> {
+        TimelineSemaphore::multi_wait(Some((self, value)), false, timeout)
+    }
+
+    /// Waits for multiple timeline semaphores at once.
+    ///
+    /// If `wait_any` is true, the function returns as soon as one of the semaphores reaches its
+    /// value. Otherwise it returns once they all have.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if not all semaphores belong to the same device.
+    ///
+    pub fn multi_wait<'a, I>(
+        iter: I,
+        wait_any: bool,
+        timeout: Option<Duration>,
+    ) -> Result<(), SemaphoreWaitError>
+    where
+        I: IntoIterator<Item = (&'a TimelineSemaphore, u64)>,
+    {
+        let mut device: Option<&Device> = None;
+        let mut semaphores = Vec::new();
+        let mut values = Vec::new();
+
+        for (semaphore, value) in iter {
+            match device {
+                None => device = Some(&semaphore.device),
+                Some(dev) if ptr::eq(dev, &*semaphore.device) => {}
+                _ => panic!(
+                    "Tried to wait for multiple semaphores that didn't belong to the same device"
+                ),
+            }
+
+            semaphores.push(semaphore.semaphore);
+            values.push(value);
+        }
+
+        let device = match device {
+            Some(device) => device,
+            None => return Ok(()),
+        };
+
+        let timeout_ns = if let Some(timeout) = timeout {
+            timeout
+                .as_secs()
+                .saturating_mul(1_000_000_000)
+                .saturating_add(timeout.subsec_nanos() as u64)
+        } else {
+            u64::MAX
+        };
+
+        let wait_info = ash::vk::SemaphoreWaitInfo {
+            flags: if wait_any {
+                ash::vk::SemaphoreWaitFlags::ANY
+            } else {
+                ash::vk::SemaphoreWaitFlags::empty()
+            },
+            semaphore_count: semaphores.len() as u32,
+            p_semaphores: semaphores.as_ptr(),
+            p_values: values.as_ptr(),
+            ..Default::default()
+        };
+
+        let r = unsafe {
+            let fns = device.fns();
+
+            if device.api_version() >= Version::V1_2 {
+                check_errors(fns.v1_2.wait_semaphores(
+                    device.internal_object(),
+                    &wait_info,
+                    timeout_ns,
+                ))?
+            } else {
+                check_errors(fns.khr_timeline_semaphore.wait_semaphores_khr(
+                    device.internal_object(),
+                    &wait_info,
+                    timeout_ns,
+                ))?
+            }
+        };
+
+        match r {
+            Success::Success => Ok(()),
+            Success::Timeout => Err(SemaphoreWaitError::Timeout),
+            _ => unreachable!(),
+        }
+    }
+}
+
+unsafe impl DeviceOwned for TimelineSemaphore {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+unsafe impl VulkanObject for TimelineSemaphore {
+    type Object = ash::vk::Semaphore;
+
+    #[inline]
+    fn internal_object(&self) -> ash::vk::Semaphore {
+        self.semaphore
+    }
+}
+
+impl Drop for TimelineSemaphore {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            let fns = self.device.fns();
+            fns.v1_0
+                .destroy_semaphore(self.device.internal_object(), self.semaphore, ptr::null());
+        }
+    }
+}
+
+/// Error that can be returned when waiting on a timeline semaphore.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SemaphoreWaitError {
+    /// Not enough memory to complete the wait.
+    OomError(OomError),
+
+    /// The specified timeout wasn't long enough.
+    Timeout,
+
+    /// The device has been lost.
+    DeviceLost,
+}
+
+impl error::Error for SemaphoreWaitError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            SemaphoreWaitError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SemaphoreWaitError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                SemaphoreWaitError::OomError(_) => "no memory available",
+                SemaphoreWaitError::Timeout => "the timeout has been reached",
+                SemaphoreWaitError::DeviceLost => "the device was lost",
+            }
+        )
+    }
+}
+
+impl From<Error> for SemaphoreWaitError {
+    #[inline]
+    fn from(err: Error) -> SemaphoreWaitError {
+        match err {
+            Error::OutOfHostMemory => SemaphoreWaitError::OomError(From::from(err)),
+            Error::OutOfDeviceMemory => SemaphoreWaitError::OomError(From::from(err)),
+            Error::DeviceLost => SemaphoreWaitError::DeviceLost,
+            _ => panic!("Unexpected error value: {}", err as i32),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_buffer::AutoCommandBufferBuilder;
+    use crate::command_buffer::CommandBufferUsage;
+    use crate::sync::wait_timeline_semaphore;
+    use crate::sync::GpuFuture;
+    use crate::sync::SemaphoreError;
+    use crate::sync::TimelineSemaphore;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn missing_feature() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match TimelineSemaphore::new(device, 0) {
+            Err(SemaphoreError::MissingFeature("timeline_semaphore")) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn host_signal() {
+        let (device, _) = gfx_dev_and_queue!(timeline_semaphore);
+
+        let semaphore = TimelineSemaphore::new(device, 3).unwrap();
+        assert_eq!(semaphore.value().unwrap(), 3);
+
+        semaphore.signal(5).unwrap();
+        assert_eq!(semaphore.value().unwrap(), 5);
+        semaphore.wait(4, Some(Duration::from_secs(0))).unwrap();
+    }
+
+    #[test]
+    fn device_signal_and_wait() {
+        let (device, queue) = gfx_dev_and_queue!(timeline_semaphore);
+
+        let semaphore = Arc::new(TimelineSemaphore::new(device.clone(), 0).unwrap());
+        let cb = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+
+        // Wait for a value that is only signalled from the host afterwards.
+        let future = wait_timeline_semaphore(semaphore.clone(), 1)
+            .then_execute(queue.clone(), cb)
+            .unwrap()
+            .then_signal_timeline_semaphore(semaphore.clone(), 2);
+        future.flush().unwrap();
+
+        semaphore.signal(1).unwrap();
+        semaphore.wait(2, Some(Duration::from_secs(5))).unwrap();
+        drop(future);
+    }
+
+    #[test]
+    fn signal_after_sparse_binding() {
+        use crate::buffer::BufferAccess;
+        use crate::command_buffer::submit::SubmitAnyBuilder;
+        use crate::command_buffer::submit::SubmitBindSparseBuilder;
+        use crate::device::Device;
+        use crate::device::DeviceOwned;
+        use crate::device::Queue;
+        use crate::image::ImageAccess;
+        use crate::image::ImageLayout;
+        use crate::sync::AccessCheckError;
+        use crate::sync::AccessFlags;
+        use crate::sync::FlushError;
+        use crate::sync::PipelineStages;
+
+        // A future whose submission is an empty sparse binding.
+        struct SparseBindingFuture(Arc<Queue>);
+
+        unsafe impl GpuFuture for SparseBindingFuture {
+            fn cleanup_finished(&mut self) {}
+
+            unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
+                Ok(SubmitAnyBuilder::BindSparse(SubmitBindSparseBuilder::new()))
+            }
+
+            fn flush(&self) -> Result<(), FlushError> {
+                Ok(())
+            }
+
+            unsafe fn signal_finished(&self) {}
+
+            fn queue_change_allowed(&self) -> bool {
+                false
+            }
+
+            fn queue(&self) -> Option<Arc<Queue>> {
+                Some(self.0.clone())
+            }
+
+            fn check_buffer_access(
+                &self,
+                _: &dyn BufferAccess,
+                _: bool,
+                _: &Queue,
+            ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+                Err(AccessCheckError::Unknown)
+            }
+
+            fn check_image_access(
+                &self,
+                _: &dyn ImageAccess,
+                _: ImageLayout,
+                _: bool,
+                _: &Queue,
+            ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+                Err(AccessCheckError::Unknown)
+            }
+        }
+
+        unsafe impl DeviceOwned for SparseBindingFuture {
+            fn device(&self) -> &Arc<Device> {
+                self.0.device()
+            }
+        }
+
+        let (device, queue) = gfx_dev_and_queue!(timeline_semaphore);
+        if !queue.family().supports_sparse_binding() {
+            return;
+        }
+
+        let semaphore = Arc::new(TimelineSemaphore::new(device, 0).unwrap());
+        let future =
+            SparseBindingFuture(queue).then_signal_timeline_semaphore(semaphore.clone(), 1);
+        future.flush().unwrap();
+        // Flushing again doesn't submit anything.
+        future.flush().unwrap();
+
+        semaphore.wait(1, Some(Duration::from_secs(5))).unwrap();
+        drop(future);
+    }
+}
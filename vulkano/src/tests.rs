@@ -9,6 +9,23 @@
 
 #![cfg(test)]
 
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::DynamicState;
+use crate::command_buffer::PrimaryAutoCommandBuffer;
+use crate::command_buffer::SubpassContents;
+use crate::debug_draw::DebugDraw;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::format::Format;
+use crate::image::view::ImageView;
+use crate::image::AttachmentImage;
+use crate::pipeline::viewport::Viewport;
+use crate::pipeline::GraphicsPipeline;
+use crate::render_pass::Framebuffer;
+use crate::render_pass::RenderPass;
+use crate::render_pass::Subpass;
+use std::sync::Arc;
+
 /// Creates an instance or returns if initialization fails.
 macro_rules! instance {
     () => {{
@@ -99,3 +116,79 @@ macro_rules! assert_should_panic {
         }
     }};
 }
+
+/// SPIR-V code of an empty compute shader whose entry point is named `name`, of 4 bytes at most.
+/// If `with_source` is true, the code also contains an `OpSource` instruction, which only changes
+/// its length.
+pub fn empty_compute_spirv(name: &[u8; 4], with_source: bool) -> Vec<u32> {
+    let mut words = vec![0x07230203, 0x00010000, 0, 5, 0];
+    words.extend_from_slice(&[(2 << 16) | 17, 1]);
+    words.extend_from_slice(&[(3 << 16) | 14, 0, 1]);
+    words.extend_from_slice(&[(5 << 16) | 15, 5, 1, u32::from_le_bytes(*name), 0]);
+    words.extend_from_slice(&[(6 << 16) | 16, 1, 17, 1, 1, 1]);
+    if with_source {
+        words.extend_from_slice(&[(3 << 16) | 3, 2, 450]);
+    }
+    words.extend_from_slice(&[(2 << 16) | 19, 2]);
+    words.extend_from_slice(&[(3 << 16) | 33, 3, 2]);
+    words.extend_from_slice(&[(5 << 16) | 54, 2, 1, 0, 3]);
+    words.extend_from_slice(&[(2 << 16) | 248, 4]);
+    words.extend_from_slice(&[(1 << 16) | 253, (1 << 16) | 56]);
+    words
+}
+
+/// Returns a render pass with one color attachment, and the pipeline of a `DebugDraw` that draws
+/// in it.
+pub fn debug_draw_pipeline(device: Arc<Device>) -> (Arc<RenderPass>, Arc<GraphicsPipeline>) {
+    let render_pass = Arc::new(
+        crate::single_pass_renderpass!(device,
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: Format::R8G8B8A8Unorm,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )
+        .unwrap(),
+    );
+    let debug_draw = DebugDraw::new(Subpass::from(render_pass.clone(), 0).unwrap()).unwrap();
+    (render_pass, debug_draw.pipeline().clone())
+}
+
+/// Begins the render pass of `debug_draw_pipeline` on `builder`, with a target of 4x4 pixels.
+/// Returns the pipeline, and the dynamic state that draws in the whole target.
+pub fn begin_debug_draw_pass(
+    builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+) -> (Arc<GraphicsPipeline>, DynamicState) {
+    let device = builder.device().clone();
+    let (render_pass, pipeline) = debug_draw_pipeline(device.clone());
+    let view = ImageView::new(AttachmentImage::new(device, [4, 4], Format::R8G8B8A8Unorm).unwrap())
+        .unwrap();
+    let framebuffer = Arc::new(
+        Framebuffer::start(render_pass)
+            .add(view)
+            .unwrap()
+            .build()
+            .unwrap(),
+    );
+
+    builder
+        .begin_render_pass(framebuffer, SubpassContents::Inline, vec![[0.0; 4].into()])
+        .unwrap();
+
+    let dynamic = DynamicState {
+        viewports: Some(vec![Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [4.0, 4.0],
+            depth_range: 0.0..1.0,
+        }]),
+        ..DynamicState::none()
+    };
+    (pipeline, dynamic)
+}
@@ -0,0 +1,468 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Sending CPU and GPU zones to the [Tracy](https://github.com/wolfpld/tracy) profiler.
+//!
+//! This module requires the `tracy` feature. The Tracy client is used through its C API and
+//! looked up at runtime, first in the executable, in case the application links the client
+//! statically, then in the `TracyClient` shared library. The client must be built with
+//! `TRACY_ENABLE`. If it isn't found, all the functions of this module do nothing.
+//!
+//! When the client is found, vulkano emits CPU zones around each queue submission and each
+//! pipeline creation. GPU zones are emitted from the scopes of a `GpuTrace` with a
+//! `TracyGpuContext`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use vulkano::device::Device;
+//! use vulkano::query::GpuTrace;
+//! use vulkano::tracy::TracyGpuContext;
+//! use vulkano::tracy;
+//!
+//! # let device: Arc<Device> = return;
+//! # let trace: GpuTrace = return;
+//! let mut context = TracyGpuContext::new(&device, "Graphics queue").unwrap();
+//!
+//! // Every frame:
+//! {
+//!     let _zone = tracy::zone("Update");
+//!     // ...
+//! }
+//!
+//! // Once the timestamps of the frame are read back and added to the trace:
+//! # let track = return;
+//! context.emit(&trace, track);
+//! ```
+
+use crate::device::Device;
+use crate::query::CalibrationError;
+use crate::query::GpuTrace;
+use crate::query::TimestampCalibration;
+use crate::query::TrackId;
+use lazy_static::lazy_static;
+use shared_library::dynamic_library::DynamicLibrary;
+use smallvec::SmallVec;
+use std::error;
+use std::fmt;
+use std::mem;
+use std::os::raw::c_char;
+use std::os::raw::c_int;
+use std::os::raw::c_void;
+use std::panic::Location;
+use std::path::Path;
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering;
+
+#[cfg(target_os = "windows")]
+const LIBRARY_NAME: &str = "TracyClient.dll";
+#[cfg(target_os = "macos")]
+const LIBRARY_NAME: &str = "libTracyClient.dylib";
+#[cfg(all(not(target_os = "windows"), not(target_os = "macos")))]
+const LIBRARY_NAME: &str = "libTracyClient.so";
+
+// Value of `tracy::GpuContextType::Vulkan`.
+const GPU_CONTEXT_TYPE_VULKAN: u8 = 2;
+
+lazy_static! {
+    static ref CLIENT: Option<Tracy> = Tracy::load().ok();
+}
+
+// Structures of `TracyC.h`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ZoneContext {
+    id: u32,
+    active: c_int,
+}
+
+#[repr(C)]
+struct GpuZoneBeginData {
+    srcloc: u64,
+    query_id: u16,
+    context: u8,
+}
+
+#[repr(C)]
+struct GpuZoneEndData {
+    query_id: u16,
+    context: u8,
+}
+
+#[repr(C)]
+struct GpuTimeData {
+    gpu_time: i64,
+    query_id: u16,
+    context: u8,
+}
+
+#[repr(C)]
+struct GpuNewContextData {
+    gpu_time: i64,
+    period: f32,
+    context: u8,
+    flags: u8,
+    ty: u8,
+}
+
+#[repr(C)]
+struct GpuContextNameData {
+    context: u8,
+    name: *const c_char,
+    len: u16,
+}
+
+// The last parameter is the color, which older versions of the client don't have and ignore.
+type AllocSrclocNameFn = extern "C" fn(
+    line: u32,
+    source: *const c_char,
+    source_len: usize,
+    function: *const c_char,
+    function_len: usize,
+    name: *const c_char,
+    name_len: usize,
+    color: u32,
+) -> u64;
+
+struct Fns {
+    alloc_srcloc_name: AllocSrclocNameFn,
+    emit_zone_begin_alloc: extern "C" fn(srcloc: u64, active: c_int) -> ZoneContext,
+    emit_zone_end: extern "C" fn(context: ZoneContext),
+    emit_gpu_zone_begin_alloc: extern "C" fn(data: GpuZoneBeginData),
+    emit_gpu_zone_end: extern "C" fn(data: GpuZoneEndData),
+    emit_gpu_time: extern "C" fn(data: GpuTimeData),
+    emit_gpu_new_context: extern "C" fn(data: GpuNewContextData),
+    emit_gpu_context_name: extern "C" fn(data: GpuContextNameData),
+}
+
+/// Handle to the Tracy client.
+pub struct Tracy {
+    // Kept loaded for as long as the functions are used.
+    _library: DynamicLibrary,
+    fns: Fns,
+    // Identifier of the next GPU context.
+    next_context: AtomicU8,
+}
+
+impl Tracy {
+    /// Returns the client that vulkano uses, or `None` if it wasn't found.
+    #[inline]
+    pub fn get() -> Option<&'static Tracy> {
+        CLIENT.as_ref()
+    }
+
+    /// Looks up the client in the executable, then in the `TracyClient` shared library of the
+    /// platform.
+    ///
+    /// Most applications should use `get` instead, as vulkano only emits its own zones with
+    /// the client returned by `get`.
+    pub fn load() -> Result<Tracy, TracyError> {
+        if let Ok(library) = DynamicLibrary::open(None) {
+            if let Ok(tracy) = Tracy::from_library(library) {
+                return Ok(tracy);
+            }
+        }
+
+        Tracy::load_from_path(LIBRARY_NAME)
+    }
+
+    /// Same as `load`, but only looks up the client in the library at the given path.
+    pub fn load_from_path<P>(path: P) -> Result<Tracy, TracyError>
+    where
+        P: AsRef<Path>,
+    {
+        let library =
+            DynamicLibrary::open(Some(path.as_ref())).map_err(TracyError::LibraryLoadFailure)?;
+        Tracy::from_library(library)
+    }
+
+    fn from_library(library: DynamicLibrary) -> Result<Tracy, TracyError> {
+        // The function pointers have the same size as the pointer returned by `symbol`.
+        unsafe fn load<F>(library: &DynamicLibrary, name: &'static str) -> Result<F, TracyError> {
+            let ptr: *mut c_void = library
+                .symbol(name)
+                .map_err(|_| TracyError::MissingEntryPoint(name))?;
+            Ok(mem::transmute_copy(&ptr))
+        }
+
+        let fns = unsafe {
+            Fns {
+                alloc_srcloc_name: load(&library, "___tracy_alloc_srcloc_name")?,
+                emit_zone_begin_alloc: load(&library, "___tracy_emit_zone_begin_alloc")?,
+                emit_zone_end: load(&library, "___tracy_emit_zone_end")?,
+                emit_gpu_zone_begin_alloc: load(&library, "___tracy_emit_gpu_zone_begin_alloc")?,
+                emit_gpu_zone_end: load(&library, "___tracy_emit_gpu_zone_end")?,
+                emit_gpu_time: load(&library, "___tracy_emit_gpu_time")?,
+                emit_gpu_new_context: load(&library, "___tracy_emit_gpu_new_context")?,
+                emit_gpu_context_name: load(&library, "___tracy_emit_gpu_context_name")?,
+            }
+        };
+
+        Ok(Tracy {
+            _library: library,
+            fns,
+            next_context: AtomicU8::new(0),
+        })
+    }
+
+    // Allocates a source location, which the client frees once the zone is sent.
+    fn alloc_srcloc(&self, location: &Location, name: &str) -> u64 {
+        let file = location.file();
+        (self.fns.alloc_srcloc_name)(
+            location.line(),
+            file.as_ptr() as *const c_char,
+            file.len(),
+            name.as_ptr() as *const c_char,
+            name.len(),
+            name.as_ptr() as *const c_char,
+            name.len(),
+            0,
+        )
+    }
+
+    /// Starts a CPU zone, which ends when the returned object is dropped.
+    #[inline]
+    #[track_caller]
+    pub fn zone(&self, name: &str) -> TracyZone<'_> {
+        let srcloc = self.alloc_srcloc(Location::caller(), name);
+        TracyZone {
+            tracy: self,
+            context: (self.fns.emit_zone_begin_alloc)(srcloc, 1),
+        }
+    }
+}
+
+impl fmt::Debug for Tracy {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<Tracy>")
+    }
+}
+
+/// Starts a CPU zone with the client returned by `Tracy::get`, if there is one.
+#[inline]
+#[track_caller]
+pub fn zone(name: &str) -> Option<TracyZone<'static>> {
+    Tracy::get().map(|tracy| tracy.zone(name))
+}
+
+/// CPU zone of Tracy. Ends the zone when destroyed.
+#[must_use = "the zone ends when it is dropped"]
+pub struct TracyZone<'a> {
+    tracy: &'a Tracy,
+    context: ZoneContext,
+}
+
+impl<'a> Drop for TracyZone<'a> {
+    #[inline]
+    fn drop(&mut self) {
+        (self.tracy.fns.emit_zone_end)(self.context)
+    }
+}
+
+impl<'a> fmt::Debug for TracyZone<'a> {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<TracyZone {}>", self.context.id)
+    }
+}
+
+/// GPU timeline of Tracy, usually one per queue, whose zones are emitted from a `GpuTrace`.
+///
+/// Tracy lines up the GPU timeline with the CPU zones through the time at which the context is
+/// created. The context takes care of the correlation: the timestamps of the traces are
+/// converted to the host clock with the calibration of each trace, so the device clock drifting
+/// from the host clock doesn't accumulate over the lifetime of the context.
+///
+/// The contexts of vulkano are numbered independently from the GPU contexts created with the
+/// C++ API of Tracy, so both shouldn't be used by the same application.
+pub struct TracyGpuContext {
+    tracy: &'static Tracy,
+    context: u8,
+    next_query: u16,
+}
+
+impl TracyGpuContext {
+    /// Creates a GPU timeline named `name` with the client returned by `Tracy::get`.
+    ///
+    /// Requires the `ext_calibrated_timestamps` extension to be enabled on the device, and
+    /// therefore isn't available on Windows, as for `TimestampCalibration::query`.
+    pub fn new(device: &Device, name: &str) -> Result<TracyGpuContext, TracyError> {
+        let tracy = Tracy::get().ok_or(TracyError::ClientNotFound)?;
+        let context = tracy
+            .next_context
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |id| id.checked_add(1))
+            .map_err(|_| TracyError::TooManyContexts)?;
+
+        // The time of the host at which the context is created, in the same clock as the
+        // timestamps of the traces.
+        let now = TimestampCalibration::query(device)?.host_timestamp();
+
+        (tracy.fns.emit_gpu_new_context)(GpuNewContextData {
+            gpu_time: now as i64,
+            period: 1.0,
+            context,
+            flags: 0,
+            ty: GPU_CONTEXT_TYPE_VULKAN,
+        });
+
+        let name = &name[..name.len().min(u16::MAX as usize)];
+        // The client copies the name.
+        (tracy.fns.emit_gpu_context_name)(GpuContextNameData {
+            context,
+            name: name.as_ptr() as *const c_char,
+            len: name.len() as u16,
+        });
+
+        Ok(TracyGpuContext {
+            tracy,
+            context,
+            next_query: 0,
+        })
+    }
+
+    /// Emits the scopes of `track` as GPU zones. The labels of the trace are ignored.
+    ///
+    /// Scopes that are contained in another one are displayed below it. The traces must be
+    /// emitted in chronological order.
+    #[track_caller]
+    pub fn emit(&mut self, trace: &GpuTrace, track: TrackId) {
+        let mut scopes: Vec<_> = trace.host_scopes(track).collect();
+        scopes.sort_by(|a, b| a.1.cmp(&b.1).then(b.2.cmp(&a.2)));
+
+        let location = Location::caller();
+        // End timestamps of the zones that contain the current one.
+        let mut open: SmallVec<[u64; 8]> = SmallVec::new();
+
+        for (name, start, end) in scopes {
+            while let Some(&parent_end) = open.last() {
+                if parent_end > start {
+                    break;
+                }
+                self.end_zone(parent_end);
+                open.pop();
+            }
+
+            let query_id = self.next_query_id();
+            (self.tracy.fns.emit_gpu_zone_begin_alloc)(GpuZoneBeginData {
+                srcloc: self.tracy.alloc_srcloc(location, name),
+                query_id,
+                context: self.context,
+            });
+            self.emit_time(query_id, start);
+            open.push(end);
+        }
+
+        while let Some(end) = open.pop() {
+            self.end_zone(end);
+        }
+    }
+
+    fn end_zone(&mut self, end: u64) {
+        let query_id = self.next_query_id();
+        (self.tracy.fns.emit_gpu_zone_end)(GpuZoneEndData {
+            query_id,
+            context: self.context,
+        });
+        self.emit_time(query_id, end);
+    }
+
+    #[inline]
+    fn emit_time(&self, query_id: u16, time: u64) {
+        (self.tracy.fns.emit_gpu_time)(GpuTimeData {
+            gpu_time: time as i64,
+            query_id,
+            context: self.context,
+        });
+    }
+
+    // Query identifiers only match the times with the zones, and can be reused once the time
+    // has been emitted.
+    #[inline]
+    fn next_query_id(&mut self) -> u16 {
+        let id = self.next_query;
+        self.next_query = self.next_query.wrapping_add(1);
+        id
+    }
+}
+
+impl fmt::Debug for TracyGpuContext {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<TracyGpuContext {}>", self.context)
+    }
+}
+
+/// Error that can happen when loading the Tracy client or creating a GPU context.
+#[derive(Clone, Debug)]
+pub enum TracyError {
+    /// Failed to load the library of the client.
+    LibraryLoadFailure(String),
+    /// The library doesn't contain one of the functions of the C API.
+    MissingEntryPoint(&'static str),
+    /// The client wasn't found when vulkano looked it up.
+    ClientNotFound,
+    /// All the 255 GPU contexts have been created.
+    TooManyContexts,
+    /// Failed to sample the clocks of the device and of the host.
+    CalibrationError(CalibrationError),
+}
+
+impl error::Error for TracyError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            TracyError::CalibrationError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TracyError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                TracyError::LibraryLoadFailure(_) => "failed to load the library of the client",
+                TracyError::MissingEntryPoint(_) => {
+                    "the library doesn't contain one of the functions of the C API"
+                }
+                TracyError::ClientNotFound => "the client wasn't found",
+                TracyError::TooManyContexts => "all the 255 GPU contexts have been created",
+                TracyError::CalibrationError(_) => {
+                    "failed to sample the clocks of the device and of the host"
+                }
+            }
+        )
+    }
+}
+
+impl From<CalibrationError> for TracyError {
+    #[inline]
+    fn from(err: CalibrationError) -> TracyError {
+        TracyError::CalibrationError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracy::Tracy;
+    use crate::tracy::TracyError;
+
+    #[test]
+    fn missing_library() {
+        match Tracy::load_from_path("/nonexistent/libTracyClient.so") {
+            Err(TracyError::LibraryLoadFailure(_)) => (),
+            _ => panic!(),
+        }
+    }
+}
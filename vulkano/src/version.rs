@@ -14,6 +14,7 @@ use std::fmt;
 
 /// Represents an API version of Vulkan.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Version {
     /// Major version number.
     pub major: u32,
@@ -27,6 +28,7 @@ impl Version {
     pub const V1_0: Version = Version::major_minor(1, 0);
     pub const V1_1: Version = Version::major_minor(1, 1);
     pub const V1_2: Version = Version::major_minor(1, 2);
+    pub const V1_3: Version = Version::major_minor(1, 3);
 
     /// Constructs a `Version` from the given major and minor version numbers.
     #[inline]